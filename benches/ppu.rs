@@ -0,0 +1,64 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use silknes_web::bus::BusLike;
+use silknes_web::cartridge::Cartridge;
+use silknes_web::nes::Nes;
+
+/// PPU cycles in an NTSC frame
+const FRAME_CYCLES: u32 = 341 * 262;
+
+/// NROM with a program that just spins, and CHR striped so every tile has something to draw
+fn rom() -> Vec<u8> {
+  let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+  let mut prg = vec![0xEA; 0x4000];
+  prg[..3].copy_from_slice(&[0x4C, 0x00, 0xC0]); // JMP $C000
+  prg[0x3FFA..].copy_from_slice(&[0x00, 0xC0, 0x00, 0xC0, 0x00, 0xC0]);
+  rom.extend(prg);
+  rom.extend((0..0x2000).map(|i| if i % 2 == 0 { 0x55 } else { 0x0F }));
+  rom
+}
+
+/// A console past the PPU's warm up with a screen full of tiles and sprites, drawing them if `rendering`
+fn console(rendering: bool) -> Nes {
+  let mut nes = Nes::new();
+  nes.insert_cartridge(Cartridge::from_bytes(rom()).unwrap());
+  nes.run_frames(2);
+  {
+    let bus = &mut nes.bus;
+    bus.cpu_write(0x2006, 0x20);
+    bus.cpu_write(0x2006, 0x00);
+    for i in 0..0x400 {
+      bus.cpu_write(0x2007, i as u8);
+    }
+    bus.cpu_write(0x2001, if rendering { 0x1E } else { 0x00 });
+  }
+  // Sprites spread down the screen, some lines with more than eight
+  for sprite in 0..64u8 {
+    let ppu = &mut nes.bus.ppu;
+    for (byte, value) in [sprite * 3, sprite, 0, sprite.wrapping_mul(37)].into_iter().enumerate() {
+      ppu.write_oam(sprite * 4 + byte as u8, value);
+    }
+  }
+  nes
+}
+
+fn ppu_step(c: &mut Criterion) {
+  let mut group = c.benchmark_group("ppu_frame");
+  for (name, rendering) in [("rendering", true), ("blank", false)] {
+    let mut nes = console(rendering);
+    group.bench_function(name, |b| b.iter(|| {
+      for _ in 0..FRAME_CYCLES {
+        nes.bus.clock_ppu();
+      }
+    }));
+  }
+  group.finish();
+}
+
+fn run_frame(c: &mut Criterion) {
+  let mut nes = console(true);
+  c.bench_function("run_frame", |b| b.iter(|| nes.run_frame()));
+}
+
+criterion_group!(benches, ppu_step, run_frame);
+criterion_main!(benches);
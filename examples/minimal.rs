@@ -0,0 +1,126 @@
+//! A frontend without egui: a winit window the frame's drawn straight into with softbuffer, and audio
+//! through the usual backend. Everything it needs from the emulator is [`Nes`], the audio output and
+//! the frame clock that times emulation by it.
+//!
+//! `cargo run --release --example minimal -- game.nes`
+//!
+//! Arrows move, Z is B, X is A, Space is Select, Enter is Start and Escape quits.
+
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use silknes_web::apu_output::{AudioBackend, AudioControls, AudioDriver};
+use silknes_web::cartridge::Cartridge;
+use silknes_web::emulation::FrameClock;
+use silknes_web::input::Button;
+use silknes_web::nes::Nes;
+use silknes_web::video::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, Event, KeyEvent, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::WindowBuilder;
+
+/// How often the loop wakes up to check whether a frame's due
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The controller button a key presses, going by the emulator's default bindings
+fn button(key: KeyCode) -> Option<Button> {
+  Some(match key {
+    KeyCode::ArrowUp => Button::Up,
+    KeyCode::ArrowDown => Button::Down,
+    KeyCode::ArrowLeft => Button::Left,
+    KeyCode::ArrowRight => Button::Right,
+    KeyCode::Space => Button::Select,
+    KeyCode::Enter => Button::Start,
+    KeyCode::KeyZ => Button::B,
+    KeyCode::KeyX => Button::A,
+    _ => return None,
+  })
+}
+
+/// Scale `screen` up by the largest whole number that fits in `width` by `height`, centred on black
+fn draw(screen: &[u8], buffer: &mut [u32], width: usize, height: usize) {
+  let scale = (width / SCREEN_WIDTH).min(height / SCREEN_HEIGHT).max(1);
+  let left = width.saturating_sub(SCREEN_WIDTH * scale) / 2;
+  let top = height.saturating_sub(SCREEN_HEIGHT * scale) / 2;
+  for (y, row) in buffer.chunks_exact_mut(width).enumerate() {
+    for (x, pixel) in row.iter_mut().enumerate() {
+      let (screen_x, screen_y) = (x.wrapping_sub(left) / scale, y.wrapping_sub(top) / scale);
+      *pixel = if screen_x < SCREEN_WIDTH && screen_y < SCREEN_HEIGHT {
+        let rgb = &screen[(screen_y * SCREEN_WIDTH + screen_x) * 3..][..3];
+        u32::from_be_bytes([0, rgb[0], rgb[1], rgb[2]])
+      } else {
+        0
+      };
+    }
+  }
+}
+
+fn main() -> Result<(), String> {
+  let path = std::env::args().nth(1).ok_or("Usage: minimal <rom>")?;
+  let bytes = std::fs::read(&path).map_err(|error| format!("Couldn't read {}: {}", path, error))?;
+  let cartridge = Cartridge::from_bytes(bytes).map_err(|error| format!("Couldn't load {}: {}", path, error))?;
+  let mut nes = Nes::new();
+  nes.insert_cartridge(cartridge);
+
+  // Frames are run as the audio plays, like the full frontend does
+  let (tx, rx) = mpsc::channel();
+  let controls = AudioControls::default();
+  let _audio = AudioBackend::start(rx, AudioDriver::default(), controls.clone());
+  let mut clock = FrameClock::new(controls);
+
+  let event_loop = EventLoop::new().map_err(|error| error.to_string())?;
+  let window = WindowBuilder::new()
+    .with_title("SilkNES")
+    .with_inner_size(LogicalSize::new(SCREEN_WIDTH as u32 * 3, SCREEN_HEIGHT as u32 * 3))
+    .build(&event_loop)
+    .map_err(|error| error.to_string())?;
+  let window = Rc::new(window);
+  let context = softbuffer::Context::new(Rc::clone(&window)).map_err(|error| error.to_string())?;
+  let mut surface = softbuffer::Surface::new(&context, Rc::clone(&window)).map_err(|error| error.to_string())?;
+
+  let mut buttons = 0;
+  event_loop.run(move |event, target| match event {
+    Event::WindowEvent { event, .. } => match event {
+      WindowEvent::CloseRequested => target.exit(),
+      WindowEvent::KeyboardInput { event: KeyEvent { physical_key: PhysicalKey::Code(key), state, .. }, .. } => {
+        if key == KeyCode::Escape {
+          target.exit();
+        } else if let Some(button) = button(key) {
+          match state {
+            ElementState::Pressed => buttons |= button.mask(),
+            ElementState::Released => buttons &= !button.mask(),
+          }
+        }
+      },
+      WindowEvent::RedrawRequested => {
+        let size = window.inner_size();
+        let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) else {
+          return;
+        };
+        surface.resize(width, height).unwrap();
+        let mut buffer = surface.buffer_mut().unwrap();
+        draw(nes.screen(), &mut buffer, size.width as usize, size.height as usize);
+        buffer.present().unwrap();
+      },
+      _ => {},
+    },
+    Event::AboutToWait => {
+      let mut ran = false;
+      while clock.frame_due() {
+        nes.update_controller(0, buttons);
+        nes.run_frame();
+        let _ = tx.send(nes.take_audio());
+        ran = true;
+      }
+      if ran {
+        window.request_redraw();
+      }
+      target.set_control_flow(ControlFlow::WaitUntil(Instant::now() + POLL_INTERVAL));
+    },
+    _ => {},
+  }).map_err(|error| error.to_string())
+}
@@ -0,0 +1,32 @@
+//! Runs the CPU over memory filled with whatever it's given, starting from the reset vector in it.
+//! Nothing should panic, and every instruction should finish in a handful of cycles.
+//!
+//! `cargo +nightly fuzz run cpu`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use silknes_web::bus::MockBus;
+use silknes_web::cpu::NES6502;
+
+/// The most cycles an instruction or interrupt takes: 7, plus one for crossing a page
+const MAX_INSTRUCTION_CYCLES: u32 = 8;
+
+const INSTRUCTIONS: usize = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+  let mut bus = MockBus::new();
+  let len = data.len().min(bus.cpu_ram.len());
+  bus.cpu_ram[..len].copy_from_slice(&data[..len]);
+  let mut cpu = NES6502::new();
+  cpu.pc = u16::from_le_bytes([bus.cpu_ram[0xFFFC], bus.cpu_ram[0xFFFD]]);
+  for _ in 0..INSTRUCTIONS {
+    cpu.step(&mut bus);
+    let mut cycles = 1;
+    while cpu.cycles > 0 {
+      cpu.step(&mut bus);
+      cycles += 1;
+      assert!(cycles <= MAX_INSTRUCTION_CYCLES, "an instruction at ${:04X} took over {} cycles", cpu.pc, MAX_INSTRUCTION_CYCLES);
+    }
+  }
+});
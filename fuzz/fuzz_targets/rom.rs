@@ -0,0 +1,20 @@
+//! Loads whatever it's given as a ROM and, if it loads, runs a couple of frames of it. Nothing should
+//! panic, however broken the header or the game.
+//!
+//! `cargo +nightly fuzz run rom`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use silknes_web::cartridge::Cartridge;
+use silknes_web::nes::Nes;
+
+fuzz_target!(|data: &[u8]| {
+  let Ok(cartridge) = Cartridge::from_bytes(data.to_vec()) else {
+    return;
+  };
+  let mut nes = Nes::new();
+  nes.insert_cartridge(cartridge);
+  nes.run_frames(2);
+  assert_eq!(nes.frame_count(), 2);
+});
@@ -1,42 +1,90 @@
-use std::rc::Rc;
-use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use crate::nes::CYCLES_PER_SECOND;
 
-use crate::bus::BusLike;
 
 const LC_LOOKUP: [u8; 32] = [
   10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
   12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30
 ];
 
+/// The four duty cycles in the order NESdev lists them, 12.5%, 25%, 50% and 25% negated. The sequencer
+/// counts down through them from step 0, so after a restart a 12.5% pulse is low for 7 steps, then high.
 const PULSE_SEQUENCE: [[f32; 8]; 4] = [
-  [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0],
-  [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0],
-  [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0],
-  [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0],
+  [0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+  [0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+  [0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0],
+  [1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0],
 ];
 
+/// The volume control shared by the pulse and noise channels: either a constant volume, or a level
+/// decaying from 15 to 0 that can loop back round
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Envelope {
+  start_flag: bool,
+  divider: u8,
+  decay_level: u8,
+  /// The constant volume, or the divider's period when decaying
+  volume: u8,
+  constant_flag: bool,
+}
+
+impl Envelope {
+  /// Take the volume settings from the channel's first register
+  fn write(&mut self, value: u8) {
+    self.constant_flag = value & 0b0001_0000 != 0;
+    self.volume = value & 0b0000_1111;
+  }
+
+  /// Start the decay again from 15 on the next clock, as writing the channel's length does
+  fn restart(&mut self) {
+    self.start_flag = true;
+  }
+
+  /// Clocked every quarter frame. `looping` is the channel's length counter halt flag, which doubles as
+  /// the envelope's loop flag.
+  pub fn tick(&mut self, looping: bool) {
+    if self.start_flag {
+      self.start_flag = false;
+      self.decay_level = 15;
+      self.divider = self.volume;
+    } else if self.divider == 0 {
+      self.divider = self.volume;
+      if self.decay_level > 0 {
+        self.decay_level -= 1;
+      } else if looping {
+        self.decay_level = 15;
+      }
+    } else {
+      self.divider -= 1;
+    }
+  }
+
+  pub fn output(&self) -> u8 {
+    if self.constant_flag { self.volume } else { self.decay_level }
+  }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pulse {
   duty_cycle: u8,
   length_counter_halt: bool,
   length_counter: u8,
-  constant_flag: bool,
+  envelope: Envelope,
   sweep_enabled: bool,
+  /// The sweep divider's period, in half frames less one
   sweep_period: u8,
   sweep_negate: bool,
   sweep_shift_count: u8,
   sweep_reload_flag: bool,
-  sweep_counter: u8,
-  timer_period: u16,
+  sweep_divider: u8,
   sequencer_cycle: usize,
   sequencer_counter: u16,
-  envelope_volume: u8,
-  envelope_decay_level: u8,
-  envelope_start_flag: bool,
-  envelope_counter: u8,
-  target_period: u16,
+  /// The 11 bit timer period, as written to the channel's registers
   raw_period: u16,
-  muted: bool,
+  /// Pulse 1 negates its sweep with one's complement, so it sweeps down one further than pulse 2
   channel1: bool,
 }
 
@@ -55,77 +103,73 @@ impl Pulse {
   }
 
   pub fn tick_envelope(&mut self) {
-    if !self.envelope_start_flag {
-      if self.envelope_counter == 0 {
-        self.envelope_counter = self.envelope_volume;
-        if self.envelope_decay_level > 0 {
-          self.envelope_decay_level -= 1;
-        }
-        if self.envelope_decay_level == 0 && self.length_counter_halt {
-          self.envelope_decay_level = 15;
-        }
-      } else {
-        self.envelope_counter -= 1;
-      }
+    self.envelope.tick(self.length_counter_halt);
+  }
+
+  /// The period the sweep unit would move to, worked out continuously from the current period
+  pub fn target_period(&self) -> u16 {
+    let change_amount = self.raw_period >> self.sweep_shift_count;
+    if self.sweep_negate {
+      let change_amount = if self.channel1 { change_amount + 1 } else { change_amount };
+      self.raw_period.saturating_sub(change_amount)
     } else {
-      self.envelope_start_flag = false;
-      self.envelope_decay_level = 15;
-      self.envelope_counter = self.envelope_volume;
+      self.raw_period + change_amount
     }
   }
 
-  pub fn tick_sweep(&mut self) {
-    self.sweep_counter = self.sweep_counter.saturating_sub(1);
-    if self.sweep_counter == 0 {
-      if self.sweep_shift_count > 0 && self.sweep_enabled && !self.muted {
-        self.raw_period = self.target_period;
-        self.timer_period = self.raw_period + 1;
-        self.update_target_period();
-      }
+  /// The channel's silenced when its period's too short to hear, or the sweep would take it past
+  /// $7FF. This holds whether or not the sweep's enabled.
+  pub fn muted(&self) -> bool {
+    self.raw_period < 8 || self.target_period() > 0x07FF
+  }
 
-      self.sweep_counter = self.sweep_period;
+  /// Clocked every half frame. The period only changes when the divider runs out, and is only
+  /// written back when the sweep's enabled with a shift and the channel isn't muted.
+  pub fn tick_sweep(&mut self) {
+    if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift_count > 0 && !self.muted() {
+      self.raw_period = self.target_period();
     }
 
-    if self.sweep_reload_flag {
+    if self.sweep_divider == 0 || self.sweep_reload_flag {
+      self.sweep_divider = self.sweep_period;
       self.sweep_reload_flag = false;
-      self.sweep_counter = self.sweep_period;
+    } else {
+      self.sweep_divider -= 1;
     }
-
-    // Set mute
-    self.muted = self.timer_period < 8 || (!self.sweep_negate && self.target_period > 0x07FF);
   }
 
+  /// Clocked every other CPU cycle, stepping down through the duty cycle every `raw_period + 1` clocks
   pub fn tick_sequencer(&mut self) {
-    if self.length_counter > 0 {
+    if self.sequencer_counter == 0 {
+      self.sequencer_counter = self.raw_period;
+      self.sequencer_cycle = (self.sequencer_cycle + 7) % 8;
+    } else {
       self.sequencer_counter -= 1;
-      if self.sequencer_counter == 0 {
-        self.sequencer_counter = self.timer_period;
-        self.sequencer_cycle = (self.sequencer_cycle + 1) % 8;
-      }
     }
   }
 
-  pub fn update_target_period(&mut self) {
-    // Calculate target period
-    let change_amount = (self.raw_period >> self.sweep_shift_count) as u16;
-
-    if self.sweep_negate {
-      self.target_period = self.timer_period.saturating_sub(change_amount);
-      if self.channel1 {
-        self.target_period -= 1;
-      }
-    } else {
-      self.target_period = self.timer_period.wrapping_add(change_amount);
+  /// Take the length and the period's high bits from the channel's fourth register, restarting the
+  /// envelope and the duty cycle. Games doing vibrato rewrite it every frame, and the duty cycle jumping
+  /// back to its start each time clicks, so with `reduce_popping` it's only restarted if the channel
+  /// was silent, for a note starting rather than one carrying on.
+  fn write_length_and_period(&mut self, value: u8, enabled: bool, reduce_popping: bool) {
+    let playing = enabled && self.length_counter > 0 && !self.muted();
+    if enabled {
+      self.length_counter = LC_LOOKUP[((value & 0b1111_1000) >> 3) as usize];
+    }
+    self.raw_period = (self.raw_period & 0x00FF) | ((value as u16 & 0b0000_0111) << 8);
+    self.envelope.restart();
+    if !reduce_popping || !playing {
+      self.sequencer_cycle = 0;
     }
   }
 
   pub fn get_output(&mut self, enabled: bool) -> f32 {
-    if !enabled || self.length_counter == 0 || self.muted {
+    if !enabled || self.length_counter == 0 || self.muted() {
       0.0
     } else {
       let duty_cycle_value = PULSE_SEQUENCE[self.duty_cycle as usize][self.sequencer_cycle];
-      let envelope_value = if self.constant_flag { self.envelope_volume } else { self.envelope_decay_level };
-      duty_cycle_value * envelope_value as f32
+      duty_cycle_value * self.envelope.output() as f32
     }
   }
 }
@@ -136,6 +180,7 @@ const TRIANGLE_SEQUENCE: [f32; 32] = [
 ];
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Triangle {
   control_flag: bool,
   linear_counter_reload_value: u8,
@@ -166,13 +211,19 @@ impl Triangle {
     }
   }
 
-  pub fn tick_sequencer(&mut self) {
-    if self.length_counter > 0 && self.linear_counter > 0 {
-      self.counter -= 1;
-      if self.counter == 0 {
-        self.counter = self.timer_period;
+  /// Clocked every CPU cycle, stepping through the sequence every `timer_period + 1` clocks while both
+  /// counters are non-zero. Games silence the triangle by setting a period of 0 or 1, which on hardware
+  /// plays at an ultrasonic frequency that averages out to a flat level, but here steps hard between
+  /// the extremes and pops, so unless `ultrasonic` the sequencer holds still at those periods instead.
+  pub fn tick_sequencer(&mut self, ultrasonic: bool) {
+    if self.counter == 0 {
+      self.counter = self.timer_period;
+      let audible = ultrasonic || self.timer_period >= 2;
+      if self.length_counter > 0 && self.linear_counter > 0 && audible {
         self.sequence_cycle = (self.sequence_cycle + 1) % 32;
       }
+    } else {
+      self.counter -= 1;
     }
   }
 
@@ -190,34 +241,27 @@ const NOISE_PERIOD_SEQUENCE: [u16; 16] = [
 ];
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Noise {
   length_counter_halt: bool,
-  constant_flag: bool,
   mode: bool,
   noise_period: u16,
   length_counter: u8,
   shift_register: u16,
   shift_register_timer: u16,
-  envelope_volume: u8,
-  envelope_decay_level: u8,
-  envelope_start_flag: bool,
-  envelope_counter: u8,
+  envelope: Envelope,
 }
 
 impl Default for Noise {
   fn default() -> Self {
     Self {
       length_counter_halt: false,
-      constant_flag: false,
       mode: false,
       noise_period: 0,
       length_counter: 0,
       shift_register: 1,
       shift_register_timer: 0,
-      envelope_volume: 0,
-      envelope_decay_level: 0,
-      envelope_start_flag: false,
-      envelope_counter: 0,
+      envelope: Envelope::default(),
     }
   }
 }
@@ -240,30 +284,14 @@ impl Noise {
   }
 
   pub fn tick_envelope(&mut self) {
-    if !self.envelope_start_flag {
-      self.envelope_counter = self.envelope_counter.saturating_sub(1);
-      if self.envelope_counter == 0 {
-        self.envelope_counter = self.envelope_volume;
-        if self.envelope_decay_level > 0 {
-          self.envelope_decay_level -= 1;
-        }
-        if self.envelope_decay_level == 0 && self.length_counter_halt {
-          self.envelope_decay_level = 15;
-        }
-      }
-    } else {
-      self.envelope_start_flag = false;
-      self.envelope_decay_level = 15;
-      self.envelope_counter = self.envelope_volume;
-    }
+    self.envelope.tick(self.length_counter_halt);
   }
 
   pub fn get_output(&mut self, enabled: bool) -> f32 {
     if !enabled || self.length_counter == 0 || self.shift_register & 0x1 != 0 {
       0.0
     } else {
-      let envelope_value = if self.constant_flag { self.envelope_volume } else { self.envelope_decay_level };
-      envelope_value as f32
+      self.envelope.output() as f32
     }
   }
 }
@@ -273,6 +301,7 @@ const DMC_RATES: [u16; 16] = [
 ];
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DMC {
   irq_enable: bool,
   loop_sample: bool,
@@ -283,7 +312,9 @@ pub struct DMC {
   // Memory reader
   memory_reader_address: u16,
   bytes_remaining: u16,
-  sample_buffer: u8,
+  /// The byte the output unit plays next, `None` from when it's taken until the memory reader fetches
+  /// another. A zero byte is a sample like any other, not an empty buffer.
+  sample_buffer: Option<u8>,
   // Ouput unit
   output_unit_timer: u16,
   shift_register: u8,
@@ -296,13 +327,13 @@ impl Default for DMC {
     Self {
       irq_enable: false,
       loop_sample: false,
-      rate: 0,
+      rate: DMC_RATES[0],
       output: 0,
       sample_address: 0xC000,
       sample_length: 1,
       memory_reader_address: 0,
       bytes_remaining: 0,
-      sample_buffer: 0,
+      sample_buffer: None,
       output_unit_timer: 0,
       shift_register: 0,
       bits_remaining: 0,
@@ -312,11 +343,32 @@ impl Default for DMC {
 }
 
 impl DMC {
+  /// Start the sample again from its first byte
   pub fn reset(&mut self) {
     self.memory_reader_address = self.sample_address;
     self.bytes_remaining = self.sample_length;
   }
 
+  /// Fill the sample buffer with the byte the memory reader fetched and move on to the next, wrapping
+  /// from $FFFF round to $8000. Returns whether that was the sample's last byte and it should raise an IRQ.
+  fn receive(&mut self, sample: u8) -> bool {
+    self.sample_buffer = Some(sample);
+    self.memory_reader_address = match self.memory_reader_address.overflowing_add(1) {
+      (_, true) => 0x8000,
+      (address, false) => address,
+    };
+    self.bytes_remaining -= 1;
+    if self.bytes_remaining > 0 {
+      return false;
+    }
+    if self.loop_sample {
+      self.reset();
+      false
+    } else {
+      self.irq_enable
+    }
+  }
+
   pub fn tick_output_unit(&mut self) {
     self.output_unit_timer = self.output_unit_timer.saturating_sub(1);
     if self.output_unit_timer == 0 {
@@ -329,13 +381,15 @@ impl DMC {
       }
       self.shift_register >>= 1;
       self.bits_remaining = self.bits_remaining.saturating_sub(1);
+      // Each output cycle empties the buffer into the shift register, or goes silent if it's empty
       if self.bits_remaining == 0 {
         self.bits_remaining = 8;
-        if self.sample_buffer == 0 {
-          self.silence_flag = true;
-        } else {
-          self.silence_flag = false;
-          self.shift_register = self.sample_buffer;
+        match self.sample_buffer.take() {
+          Some(sample) => {
+            self.silence_flag = false;
+            self.shift_register = sample;
+          },
+          None => self.silence_flag = true,
         }
       }
 
@@ -345,6 +399,7 @@ impl DMC {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct APUStatus {
   pub dmc_interrupt: bool,
   pub frame_interrupt: bool,
@@ -378,12 +433,14 @@ impl APUStatus {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct APUFrameCounter {
   mode: bool,
   irq_inhibit: bool,
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct APURegisters {
   pulse_1: Pulse,
   pulse_2: Pulse,
@@ -408,45 +465,460 @@ impl Default for APURegisters {
   }
 }
 
+/// The channels with a timer, envelope, sweep or length counter worth watching
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ApuChannel {
+  #[default]
+  Pulse1,
+  Pulse2,
+  Triangle,
+  Noise,
+}
+
+impl ApuChannel {
+  pub const ALL: [ApuChannel; 4] = [ApuChannel::Pulse1, ApuChannel::Pulse2, ApuChannel::Triangle, ApuChannel::Noise];
+}
+
+/// Everything mixed into the APU's output, including whatever sound chip the cartridge has
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioChannel {
+  Pulse1,
+  Pulse2,
+  Triangle,
+  Noise,
+  Dmc,
+  Expansion,
+}
+
+impl AudioChannel {
+  pub const ALL: [AudioChannel; 6] = [
+    AudioChannel::Pulse1,
+    AudioChannel::Pulse2,
+    AudioChannel::Triangle,
+    AudioChannel::Noise,
+    AudioChannel::Dmc,
+    AudioChannel::Expansion,
+  ];
+
+  /// Short name, used in console commands and UI string keys
+  pub fn name(&self) -> &'static str {
+    match self {
+      AudioChannel::Pulse1 => "pulse1",
+      AudioChannel::Pulse2 => "pulse2",
+      AudioChannel::Triangle => "triangle",
+      AudioChannel::Noise => "noise",
+      AudioChannel::Dmc => "dmc",
+      AudioChannel::Expansion => "expansion",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|channel| channel.name() == name)
+  }
+
+  fn bit(self) -> u8 {
+    1 << self as u8
+  }
+}
+
+/// Which channels are muted or soloed, for listening to the music or picking out one part of it.
+/// Only the mix is affected, so the channels carry on running and the game can't tell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChannelMix {
+  muted: u8,
+  soloed: u8,
+}
+
+impl ChannelMix {
+  pub fn muted(&self, channel: AudioChannel) -> bool {
+    self.muted & channel.bit() != 0
+  }
+
+  pub fn set_muted(&mut self, channel: AudioChannel, muted: bool) {
+    if muted { self.muted |= channel.bit() } else { self.muted &= !channel.bit() }
+  }
+
+  pub fn soloed(&self, channel: AudioChannel) -> bool {
+    self.soloed & channel.bit() != 0
+  }
+
+  pub fn set_soloed(&mut self, channel: AudioChannel, soloed: bool) {
+    if soloed { self.soloed |= channel.bit() } else { self.soloed &= !channel.bit() }
+  }
+
+  /// Whether `channel` is heard: it isn't muted, and either it's soloed or nothing is
+  pub fn audible(&self, channel: AudioChannel) -> bool {
+    !self.muted(channel) && (self.soloed == 0 || self.soloed(channel))
+  }
+
+  fn gain(&self, channel: AudioChannel) -> f32 {
+    if self.audible(channel) { 1.0 } else { 0.0 }
+  }
+}
+
+/// How the channels are mixed into the output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MixerMode {
+  /// A linear approximation of the mixer, cheap and close enough for most games
+  #[default]
+  Fast,
+  /// The console's nonlinear mixer, through the high and low pass filters its output stage has
+  Accurate,
+}
+
+impl MixerMode {
+  pub const ALL: [MixerMode; 2] = [MixerMode::Fast, MixerMode::Accurate];
+
+  /// Key used both in the config file and for the UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      MixerMode::Fast => "mixer.fast",
+      MixerMode::Accurate => "mixer.accurate",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|mode| mode.key() == key)
+  }
+}
+
+/// Where each channel sits between the speakers, from -1 all the way left to 1 all the way right.
+/// Like `ChannelMix` it only affects the mix. A centred channel plays at full volume on both sides,
+/// so with everything centred each side is exactly the console's mono output.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChannelPan {
+  pans: [f32; 6],
+}
+
+impl ChannelPan {
+  pub fn pan(&self, channel: AudioChannel) -> f32 {
+    self.pans[channel as usize]
+  }
+
+  pub fn set_pan(&mut self, channel: AudioChannel, pan: f32) {
+    self.pans[channel as usize] = pan.clamp(-1.0, 1.0);
+  }
+
+  pub fn centred(&self) -> bool {
+    self.pans.iter().all(|pan| *pan == 0.0)
+  }
+
+  /// How much of a channel goes to the left and right speakers
+  fn gains(&self, channel: AudioChannel) -> (f32, f32) {
+    let pan = self.pan(channel);
+    ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+  }
+}
+
+/// How the channels are spread between the speakers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StereoMode {
+  /// Everything in the middle, as the console sounds
+  #[default]
+  Mono,
+  /// The pulses to the left and the triangle and noise to the right, with the DMC and any expansion
+  /// audio left in the middle
+  Split,
+  /// Each channel wherever it's been put
+  Custom,
+}
+
+impl StereoMode {
+  pub const ALL: [StereoMode; 3] = [StereoMode::Mono, StereoMode::Split, StereoMode::Custom];
+
+  /// Key used both in the config file and for the UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      StereoMode::Mono => "stereo.mono",
+      StereoMode::Split => "stereo.split",
+      StereoMode::Custom => "stereo.custom",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|mode| mode.key() == key)
+  }
+
+  /// Where the channels go in this mode, given the ones the user's placed for `Custom`
+  pub fn pan(&self, custom: ChannelPan) -> ChannelPan {
+    let mut pan = ChannelPan::default();
+    match self {
+      StereoMode::Mono => {},
+      StereoMode::Split => {
+        pan.set_pan(AudioChannel::Pulse1, -0.75);
+        pan.set_pan(AudioChannel::Pulse2, -0.75);
+        pan.set_pan(AudioChannel::Triangle, 0.75);
+        pan.set_pan(AudioChannel::Noise, 0.75);
+      },
+      StereoMode::Custom => pan = custom,
+    }
+    pan
+  }
+}
+
+/// A first order high pass filter, run once per raw sample
+#[derive(Clone, Copy, Debug)]
+struct HighPass {
+  alpha: f32,
+  last_input: f32,
+  last_output: f32,
+}
+
+impl HighPass {
+  fn new(cutoff: f32) -> Self {
+    let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+    let dt = 1.0 / CYCLES_PER_SECOND as f32;
+    Self { alpha: rc / (rc + dt), last_input: 0.0, last_output: 0.0 }
+  }
+
+  fn filter(&mut self, input: f32) -> f32 {
+    self.last_output = self.alpha * (self.last_output + input - self.last_input);
+    self.last_input = input;
+    self.last_output
+  }
+}
+
+/// A first order low pass filter, run once per raw sample
+#[derive(Clone, Copy, Debug)]
+struct LowPass {
+  alpha: f32,
+  last_output: f32,
+}
+
+impl LowPass {
+  fn new(cutoff: f32) -> Self {
+    let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+    let dt = 1.0 / CYCLES_PER_SECOND as f32;
+    Self { alpha: dt / (rc + dt), last_output: 0.0 }
+  }
+
+  fn filter(&mut self, input: f32) -> f32 {
+    self.last_output += self.alpha * (input - self.last_output);
+    self.last_output
+  }
+}
+
+/// The filters between the console's mixer and its audio out: two high passes at 90 Hz and 440 Hz,
+/// which take out the DC offset, then a low pass at 14 kHz
+#[derive(Clone, Copy, Debug)]
+struct OutputFilters {
+  high_pass_90: HighPass,
+  high_pass_440: HighPass,
+  low_pass_14k: LowPass,
+}
+
+impl Default for OutputFilters {
+  fn default() -> Self {
+    Self {
+      high_pass_90: HighPass::new(90.0),
+      high_pass_440: HighPass::new(440.0),
+      low_pass_14k: LowPass::new(14_000.0),
+    }
+  }
+}
+
+impl OutputFilters {
+  fn filter(&mut self, input: f32) -> f32 {
+    let output = self.high_pass_90.filter(input);
+    let output = self.high_pass_440.filter(output);
+    self.low_pass_14k.filter(output)
+  }
+}
+
+/// A channel's timer, envelope, sweep and length counter at one moment, for debugging views
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChannelState {
+  /// Timer period, as written to the channel's registers. The noise channel's is looked up from its period index.
+  pub period: u16,
+  /// Volume from the envelope, or the constant volume if that's in use. The triangle has neither.
+  pub envelope: Option<u8>,
+  /// Period the sweep unit will move to next, for pulse channels with their sweep enabled
+  pub sweep_target: Option<u16>,
+  pub length_counter: u8,
+  /// Which of the four duty cycles a pulse channel's playing, from 12.5% to 75%
+  pub duty: Option<u8>,
+  /// The triangle's linear counter, which silences it when it runs out like a finer length counter
+  pub linear_counter: Option<u8>,
+}
+
+/// The DMC's rate, output level and progress through its sample, for debugging views
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DmcState {
+  /// CPU cycles between each bit of the sample
+  pub rate: u16,
+  pub output: u8,
+  pub bytes_remaining: u16,
+  pub looping: bool,
+}
+
+/// How many recent levels the scope keeps for each channel, about 20ms at the output rate
+pub const SCOPE_LENGTH: usize = 1024;
+
+/// Each channel's level going into the mixer over the last few milliseconds, for drawing oscilloscopes
+#[derive(Clone, Debug)]
+pub struct ChannelScope {
+  levels: [VecDeque<f32>; 6],
+}
+
+impl Default for ChannelScope {
+  fn default() -> Self {
+    Self {
+      levels: std::array::from_fn(|_| VecDeque::with_capacity(SCOPE_LENGTH)),
+    }
+  }
+}
+
+impl ChannelScope {
+  /// Add a level for each channel, in the order of [`AudioChannel::ALL`], dropping the oldest once full
+  pub fn record(&mut self, levels: [f32; 6]) {
+    for (history, level) in self.levels.iter_mut().zip(levels) {
+      if history.len() == SCOPE_LENGTH {
+        history.pop_front();
+      }
+      history.push_back(level);
+    }
+  }
+
+  /// The channel's levels from 0 to 1, oldest first
+  pub fn levels(&self, channel: AudioChannel) -> &VecDeque<f32> {
+    &self.levels[channel as usize]
+  }
+}
+
+impl Pulse {
+  fn state(&self) -> ChannelState {
+    ChannelState {
+      period: self.raw_period,
+      envelope: Some(self.envelope.output()),
+      sweep_target: (self.sweep_enabled && self.sweep_shift_count > 0).then_some(self.target_period()),
+      length_counter: self.length_counter,
+      duty: Some(self.duty_cycle),
+      linear_counter: None,
+    }
+  }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct APU {
-  bus: Option<Rc<RefCell<Box<dyn BusLike>>>>,
   pub registers: APURegisters,
   pub total_cycles: u32,
   pub irq_pending: bool,
+  /// A left and a right sample for each cycle, one after the other
   pub output_buffer: Vec<f32>,
+  /// Chosen by the user rather than part of the machine, so it's left out of save states
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub mix: ChannelMix,
+  /// What each channel last fed the mixer, kept for the scope
+  #[cfg_attr(feature = "serde", serde(skip))]
+  levels: [f32; 6],
+  /// Run the triangle at ultrasonic periods like hardware does, rather than holding it still to avoid
+  /// pops. A preference rather than part of the machine, like `mix`.
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub ultrasonic_triangle: bool,
+  /// Leave the pulses' duty cycles running when their period's high bits are rewritten mid note, rather
+  /// than restarting them as hardware does. A preference too.
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub reduce_pulse_popping: bool,
+  /// Another preference, see `MixerMode`
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub mixer: MixerMode,
+  /// Another preference, see `ChannelPan`
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub pan: ChannelPan,
+  /// Only used by the accurate mixer, with each side filtered separately
+  #[cfg_attr(feature = "serde", serde(skip))]
+  filters: [OutputFilters; 2],
+}
+
+impl Default for APU {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl APU {
   pub fn new() -> Self {
     Self {
-      bus: None,
       registers: APURegisters::default(),
       total_cycles: 0,
       irq_pending: false,
       output_buffer: Vec::new(),
+      mix: ChannelMix::default(),
+      levels: [0.0; 6],
+      ultrasonic_triangle: false,
+      reduce_pulse_popping: false,
+      mixer: MixerMode::default(),
+      pan: ChannelPan::default(),
+      filters: [OutputFilters::default(); 2],
     }
   }
 
-  pub fn connect_to_bus(&mut self, bus: Rc<RefCell<Box<dyn BusLike>>>) {
-    self.bus = Some(bus.clone());
+  /// Every register cleared, as the APU is at power on
+  pub fn power_on(&mut self) {
+    self.registers = APURegisters::default();
+    self.total_cycles = 0;
+    self.irq_pending = false;
+    self.output_buffer.clear();
   }
 
-  pub fn read(&self, address: u16) -> u8 {
-    if let Some(bus) = &self.bus {
-      bus.borrow().cpu_read(address)
-    } else {
-      panic!("Tried to read from bus before it was connected!");
+  /// What pressing the reset button does: every channel is silenced as if $4015 were cleared, and the
+  /// frame counter starts its sequence again in whichever mode it was in
+  pub fn reset(&mut self) {
+    self.cpu_write(0x4015, 0);
+    self.registers.status.frame_interrupt = false;
+    self.total_cycles = 0;
+    self.irq_pending = false;
+  }
+
+  /// Where the DMC's memory reader wants its next sample byte from, if its buffer's empty and there's
+  /// more of the sample to play. The bus reads it and hands it to `step`.
+  pub fn dmc_fetch_address(&self) -> Option<u16> {
+    let dmc = &self.registers.dmc;
+    (dmc.sample_buffer.is_none() && dmc.bytes_remaining > 0).then_some(dmc.memory_reader_address)
+  }
+
+  pub fn channel_state(&self, channel: ApuChannel) -> ChannelState {
+    match channel {
+      ApuChannel::Pulse1 => self.registers.pulse_1.state(),
+      ApuChannel::Pulse2 => self.registers.pulse_2.state(),
+      ApuChannel::Triangle => ChannelState {
+        period: self.registers.triangle.timer_period,
+        envelope: None,
+        sweep_target: None,
+        length_counter: self.registers.triangle.length_counter,
+        duty: None,
+        linear_counter: Some(self.registers.triangle.linear_counter),
+      },
+      ApuChannel::Noise => {
+        let noise = &self.registers.noise;
+        ChannelState {
+          period: noise.noise_period,
+          envelope: Some(noise.envelope.output()),
+          sweep_target: None,
+          length_counter: noise.length_counter,
+          duty: None,
+          linear_counter: None,
+        }
+      },
     }
   }
 
-  pub fn write(&mut self, address: u16, value: u8) {
-    if let Some(bus) = &self.bus {
-      bus.borrow_mut().cpu_write(address, value);
-    } else {
-      panic!("Tried to write to bus before it was connected!");
+  pub fn dmc_state(&self) -> DmcState {
+    let dmc = &self.registers.dmc;
+    DmcState {
+      rate: dmc.rate,
+      output: dmc.output,
+      bytes_remaining: dmc.bytes_remaining,
+      looping: dmc.loop_sample,
     }
   }
 
+  /// Each channel's latest level from 0 to 1, before muting, in the order of [`AudioChannel::ALL`]
+  pub fn channel_levels(&self) -> [f32; 6] {
+    self.levels
+  }
+
   pub fn tick_quarter_frame(&mut self) {
     self.registers.pulse_1.tick_envelope();
     self.registers.pulse_2.tick_envelope();
@@ -454,11 +926,9 @@ impl APU {
     self.registers.triangle.tick_linear_counter();
   }
 
+  /// Every half frame step is a quarter frame step too
   pub fn tick_half_frame(&mut self) {
-    self.registers.pulse_1.tick_envelope();
-    self.registers.pulse_2.tick_envelope();
-    self.registers.noise.tick_envelope();
-    self.registers.triangle.tick_linear_counter();
+    self.tick_quarter_frame();
     self.registers.pulse_1.tick_sweep();
     self.registers.pulse_2.tick_sweep();
     self.registers.pulse_1.tick_length_counter();
@@ -467,33 +937,19 @@ impl APU {
     self.registers.noise.tick_length_counter();
   }
 
-  pub fn step(&mut self, cpu_cycles: u32) {
+  /// Run a CPU cycle, with the byte read from `dmc_fetch_address` if there was one
+  pub fn step(&mut self, cpu_cycles: u32, dmc_sample: Option<u8>) {
     let mut reset = false;
 
-    self.registers.pulse_1.update_target_period();
-    self.registers.pulse_2.update_target_period();
-    self.registers.triangle.tick_sequencer();
+    self.registers.triangle.tick_sequencer(self.ultrasonic_triangle);
     self.registers.noise.tick_shift_register();
-    // Don't love doing this here but will fix it later
     // DMC MEMORY READER
-    if self.registers.dmc.sample_buffer == 0 && self.registers.dmc.bytes_remaining > 0 {
-      self.registers.dmc.sample_buffer = self.read(self.registers.dmc.sample_address);
-      self.registers.dmc.memory_reader_address = match self.registers.dmc.memory_reader_address.overflowing_add(1) {
-        (_, true) => 0x8000,
-        (address, false) => address,
-      };
-      self.registers.dmc.bytes_remaining -= 1;
-      if self.registers.dmc.bytes_remaining == 0 {
-        if self.registers.dmc.loop_sample {
-          self.registers.dmc.reset();
-        } else if self.registers.dmc.irq_enable {
-          self.registers.status.dmc_interrupt = true;
-        }
-      }
+    if dmc_sample.is_some_and(|sample| self.registers.dmc.receive(sample)) {
+      self.registers.status.dmc_interrupt = true;
     }
     self.registers.dmc.tick_output_unit();
 
-    if cpu_cycles % 2 == 0 {
+    if cpu_cycles.is_multiple_of(2) {
       self.registers.pulse_1.tick_sequencer();
       self.registers.pulse_2.tick_sequencer();
 
@@ -507,20 +963,16 @@ impl APU {
         11186 => {
           self.tick_quarter_frame();
         }
-        14915 => {
-          if !self.registers.frame_counter.mode {
-            self.tick_half_frame();
-            reset = true;
-            if !self.registers.frame_counter.irq_inhibit {
-              self.registers.status.frame_interrupt = true;
-            }
+        14915 if !self.registers.frame_counter.mode => {
+          self.tick_half_frame();
+          reset = true;
+          if !self.registers.frame_counter.irq_inhibit {
+            self.registers.status.frame_interrupt = true;
           }
         },
-        18641 => {
-          if self.registers.frame_counter.mode {
-            self.tick_half_frame();
-            reset = true;
-          }
+        18641 if self.registers.frame_counter.mode => {
+          self.tick_half_frame();
+          reset = true;
         }
         _ => {}
       }
@@ -561,6 +1013,8 @@ impl APU {
           value |= 0b1000_0000;
         }
 
+        // Only the frame counter's interrupt is acknowledged by reading. The DMC's stays until $4015 is
+        // written or $4010 turns its IRQs off.
         self.registers.status.frame_interrupt = false;
         value
       },
@@ -575,61 +1029,41 @@ impl APU {
       0x4000 => {
         self.registers.pulse_1.duty_cycle = (value & 0b1100_0000) >> 6;
         self.registers.pulse_1.length_counter_halt = value & 0b0010_0000 != 0;
-        self.registers.pulse_1.constant_flag = value & 0b0001_0000 != 0;
-        self.registers.pulse_1.envelope_volume = value & 0b0000_1111;
+        self.registers.pulse_1.envelope.write(value);
       },
       0x4001 => {
         self.registers.pulse_1.sweep_enabled = value & 0b1000_0000 != 0;
-        self.registers.pulse_1.sweep_period = ((value & 0b0111_0000) >> 4) + 1;
+        self.registers.pulse_1.sweep_period = (value & 0b0111_0000) >> 4;
         self.registers.pulse_1.sweep_negate = value & 0b0000_1000 != 0;
         self.registers.pulse_1.sweep_shift_count = value & 0b0000_0111;
         self.registers.pulse_1.sweep_reload_flag = true;
-        self.registers.pulse_1.update_target_period();
       },
       0x4002 => {
         self.registers.pulse_1.raw_period = (self.registers.pulse_1.raw_period & 0x700) | (value as u16);
-        self.registers.pulse_1.timer_period = self.registers.pulse_1.raw_period + 1;
-        self.registers.pulse_1.update_target_period();
       },
       0x4003 => {
-        if self.registers.status.pulse_1_active {
-          self.registers.pulse_1.length_counter = LC_LOOKUP[((value & 0b1111_1000) >> 3) as usize];
-        }
-        self.registers.pulse_1.raw_period = ((self.registers.pulse_1.raw_period & 0x00FF) | ((value as u16 & 0b0000_0111) << 8)) as u16;
-        self.registers.pulse_1.timer_period = self.registers.pulse_1.raw_period + 1;
-        self.registers.pulse_1.envelope_start_flag = true;
-        self.registers.pulse_1.sequencer_cycle = 0;
-        self.registers.pulse_1.update_target_period();
+        let enabled = self.registers.status.pulse_1_active;
+        self.registers.pulse_1.write_length_and_period(value, enabled, self.reduce_pulse_popping);
       },
       // Pulse 2
       0x4004 => {
         self.registers.pulse_2.duty_cycle = (value & 0b1100_0000) >> 6;
         self.registers.pulse_2.length_counter_halt = value & 0b0010_0000 != 0;
-        self.registers.pulse_2.constant_flag = value & 0b0001_0000 != 0;
-        self.registers.pulse_2.envelope_volume = value & 0b0000_1111;
+        self.registers.pulse_2.envelope.write(value);
       },
       0x4005 => {
         self.registers.pulse_2.sweep_enabled = value & 0b1000_0000 != 0;
-        self.registers.pulse_2.sweep_period = ((value & 0b0111_0000) >> 4) + 1;
+        self.registers.pulse_2.sweep_period = (value & 0b0111_0000) >> 4;
         self.registers.pulse_2.sweep_negate = value & 0b0000_1000 != 0;
         self.registers.pulse_2.sweep_shift_count = value & 0b0000_0111;
         self.registers.pulse_2.sweep_reload_flag = true;
-        self.registers.pulse_2.update_target_period();
       },
       0x4006 => {
         self.registers.pulse_2.raw_period = (self.registers.pulse_2.raw_period & 0x700) | (value as u16);
-        self.registers.pulse_2.timer_period = self.registers.pulse_2.raw_period + 1;
-        self.registers.pulse_2.update_target_period();
       },
       0x4007 => {
-        if self.registers.status.pulse_2_active {
-          self.registers.pulse_2.length_counter = LC_LOOKUP[((value & 0b1111_1000) >> 3) as usize];
-        }
-        self.registers.pulse_2.raw_period = ((self.registers.pulse_2.raw_period & 0x00FF) | ((value as u16 & 0b0000_0111) << 8)) as u16;
-        self.registers.pulse_2.timer_period = self.registers.pulse_2.raw_period + 1;
-        self.registers.pulse_2.envelope_start_flag = true;
-        self.registers.pulse_2.sequencer_cycle = 0;
-        self.registers.pulse_2.update_target_period();
+        let enabled = self.registers.status.pulse_2_active;
+        self.registers.pulse_2.write_length_and_period(value, enabled, self.reduce_pulse_popping);
       }
       // Triangle
       0x4008 => {
@@ -643,14 +1077,13 @@ impl APU {
         if self.registers.status.triangle_active {
           self.registers.triangle.length_counter = LC_LOOKUP[((value & 0b1111_1000) >> 3) as usize];
         }
-        self.registers.triangle.timer_period = (self.registers.triangle.timer_period & 0x00FF) | ((value as u16 & 0b0000_0111) << 8) as u16;
+        self.registers.triangle.timer_period = (self.registers.triangle.timer_period & 0x00FF) | ((value as u16 & 0b0000_0111) << 8);
         self.registers.triangle.linear_counter_reload_flag = true;
       },
       // Noise
       0x400C => {
         self.registers.noise.length_counter_halt = value & 0b0010_0000 != 0;
-        self.registers.noise.constant_flag = value & 0b0001_0000 != 0;
-        self.registers.noise.envelope_volume = value & 0b0000_1111;
+        self.registers.noise.envelope.write(value);
       },
       0x400E => {
         self.registers.noise.mode = value & 0b1000_0000 != 0;
@@ -660,30 +1093,35 @@ impl APU {
         if self.registers.status.noise_active {
           self.registers.noise.length_counter = LC_LOOKUP[((value & 0b1111_1000) >> 3) as usize];
         }
-        self.registers.noise.envelope_start_flag = true;
+        self.registers.noise.envelope.restart();
       },
       // DMC
       0x4010 => {
         self.registers.dmc.irq_enable = value & 0b1000_0000 != 0;
         self.registers.dmc.loop_sample = value & 0b0100_0000 != 0;
         self.registers.dmc.rate = DMC_RATES[(value & 0b0000_1111) as usize];
+        if !self.registers.dmc.irq_enable {
+          self.registers.status.dmc_interrupt = false;
+        }
       },
       0x4011 => {
         self.registers.dmc.output = value & 0b0111_1111;
       },
       0x4012 => {
-        self.registers.dmc.sample_address = 0xC000 + (value * 64) as u16;
+        self.registers.dmc.sample_address = 0xC000 + value as u16 * 64;
       },
       0x4013 => {
-        self.registers.dmc.sample_length = (value * 16) as u16 + 1;
+        self.registers.dmc.sample_length = value as u16 * 16 + 1;
       },
       // Status
       0x4015 => {
+        // Turning the DMC on only starts the sample over if it's finished, and turning it off lets the
+        // byte already in the buffer play out
         self.registers.status.dmc_active = value & 0b0001_0000 != 0;
-        if self.registers.status.dmc_active && self.registers.dmc.bits_remaining == 0 {
-          self.registers.dmc.reset();
-        } else {
+        if !self.registers.status.dmc_active {
           self.registers.dmc.bytes_remaining = 0;
+        } else if self.registers.dmc.bytes_remaining == 0 {
+          self.registers.dmc.reset();
         }
         self.registers.status.noise_active = value & 0b0000_1000 != 0;
         if !self.registers.status.noise_active {
@@ -721,24 +1159,55 @@ impl APU {
     }
   }
 
-  pub fn update_output(&mut self) {
+  /// Mix the next output sample, along with `expansion_out` from any sound hardware on the cartridge
+  pub fn update_output(&mut self, expansion_out: f32) {
     // Update output
     let pulse1_out = self.registers.pulse_1.get_output(self.registers.status.pulse_1_active);
     let pulse2_out = self.registers.pulse_2.get_output(self.registers.status.pulse_2_active);
     let triangle_out = self.registers.triangle.get_output(self.registers.status.triangle_active);
     let noise_out = self.registers.noise.get_output(self.registers.status.noise_active);
     let dmc_out = self.registers.dmc.output as f32;
+    // The expansion chip's output is already scaled for the mix, where a full scale chip reaches about half
+    self.levels = [pulse1_out / 15.0, pulse2_out / 15.0, triangle_out / 15.0, noise_out / 15.0, dmc_out / 127.0, (expansion_out * 2.0).clamp(0.0, 1.0)];
+
+    let mix = self.mix;
+    let outputs = [pulse1_out, pulse2_out, triangle_out, noise_out, dmc_out, expansion_out];
+    let outputs: [f32; 6] = std::array::from_fn(|i| outputs[i] * mix.gain(AudioChannel::ALL[i]));
+
+    // With nothing panned both sides are the same, so there's no need to mix twice. The right side's
+    // filters follow along, so they don't pop when something's panned again.
+    let pan = self.pan;
+    let (left, right) = if pan.centred() {
+      let output = self.mix_side(outputs, 0);
+      self.filters[1] = self.filters[0];
+      (output, output)
+    } else {
+      let left = std::array::from_fn(|i| outputs[i] * pan.gains(AudioChannel::ALL[i]).0);
+      let right = std::array::from_fn(|i| outputs[i] * pan.gains(AudioChannel::ALL[i]).1);
+      (self.mix_side(left, 0), self.mix_side(right, 1))
+    };
+    self.output_buffer.push(left);
+    self.output_buffer.push(right);
+  }
 
-    // // Accurate
-    // let pulse_out = 95.88 / ((8218.0 / (pulse1_out + pulse2_out)) + 100.0);
-    // let tnd_out = 159.79 / ((1.0 / (triangle_out / 8227.0 + noise_out / 12241.0 + dmc_out / 22638.0)) + 100.0);
-    // let output = 2.0 * (pulse_out + tnd_out) - 1.0;
-
-    // Linear Approximate
-    let pulse_out = 0.00752 * (pulse1_out + pulse2_out);
-    let tnd_out = 0.00851 * triangle_out + 0.00494 * noise_out + 0.00335 * dmc_out;
-    let output = 2.0 * (pulse_out + tnd_out) - 1.0;
-
-    self.output_buffer.push(output);
+  /// Mix one side of the output from each channel's level, in the order of [`AudioChannel::ALL`]
+  fn mix_side(&mut self, outputs: [f32; 6], side: usize) -> f32 {
+    let [pulse1_out, pulse2_out, triangle_out, noise_out, dmc_out, expansion_out] = outputs;
+    match self.mixer {
+      MixerMode::Accurate => {
+        // Both halves of the mixer are silent with nothing going in, rather than dividing by zero
+        let pulse_sum = pulse1_out + pulse2_out;
+        let pulse_out = if pulse_sum > 0.0 { 95.88 / ((8218.0 / pulse_sum) + 100.0) } else { 0.0 };
+        let tnd_sum = triangle_out / 8227.0 + noise_out / 12241.0 + dmc_out / 22638.0;
+        let tnd_out = if tnd_sum > 0.0 { 159.79 / ((1.0 / tnd_sum) + 100.0) } else { 0.0 };
+        // The high passes take out the DC offset, so there's no need to center it
+        self.filters[side].filter(2.0 * (pulse_out + tnd_out + expansion_out))
+      },
+      MixerMode::Fast => {
+        let pulse_out = 0.00752 * (pulse1_out + pulse2_out);
+        let tnd_out = 0.00851 * triangle_out + 0.00494 * noise_out + 0.00335 * dmc_out;
+        2.0 * (pulse_out + tnd_out + expansion_out) - 1.0
+      },
+    }
   }
 }
\ No newline at end of file
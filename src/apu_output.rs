@@ -1,28 +1,440 @@
 use std::collections::VecDeque;
-use std::sync::mpsc::Receiver;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::Arc;
 use std::time::Duration;
 
 use rodio::source::Source;
+use rodio::{OutputStream, Sink};
+use web_time::Instant;
+
+use crate::nes::AUDIO_CHANNELS;
+
+/// Rate the APU output is played back at
+pub const SAMPLE_RATE: u32 = 48000;
+
+/// How much the held sample decays each sample while starved of new audio
+const UNDERRUN_FADE: f32 = 0.999;
+
+/// Volume the APU output is played at with the volume turned all the way up, leaving some headroom
+const VOLUME: f32 = 0.25;
+
+/// How far ahead of the speakers the audio is buffered, in milliseconds
+pub const DEFAULT_LATENCY_MS: u32 = 80;
+pub const LATENCY_RANGE_MS: std::ops::RangeInclusive<u32> = 30..=250;
+
+/// How long the audio output can go without playing anything before emulation goes by the wall clock
+const AUDIO_STALL: Duration = Duration::from_millis(250);
+
+/// Which library the audio is played through
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AudioDriver {
+  /// rodio, on top of cpal, which works everywhere including the web
+  #[default]
+  Rodio,
+  /// cpal directly, asking the device for a buffer sized to the latency setting
+  #[cfg(all(feature = "cpal", not(target_arch = "wasm32")))]
+  Cpal,
+}
+
+impl AudioDriver {
+  pub const ALL: &'static [AudioDriver] = &[
+    AudioDriver::Rodio,
+    #[cfg(all(feature = "cpal", not(target_arch = "wasm32")))]
+    AudioDriver::Cpal,
+  ];
+
+  /// Key used both in the config file and for the UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      AudioDriver::Rodio => "audio.rodio",
+      #[cfg(all(feature = "cpal", not(target_arch = "wasm32")))]
+      AudioDriver::Cpal => "audio.cpal",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    Self::ALL.iter().copied().find(|driver| driver.key() == key)
+  }
+}
+
+/// What decides how fast the console runs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncMode {
+  /// Run exactly as much as the output device plays, so the audio never drifts from the emulation.
+  /// Goes by the wall clock whenever the output isn't playing.
+  #[default]
+  Audio,
+  /// Run by the wall clock, letting the audio buffer skip or wait when the device's rate doesn't match it
+  WallClock,
+}
+
+impl SyncMode {
+  pub const ALL: [SyncMode; 2] = [SyncMode::Audio, SyncMode::WallClock];
+
+  /// Key used both in the config file and for the UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      SyncMode::Audio => "sync.audio",
+      SyncMode::WallClock => "sync.wall_clock",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|mode| mode.key() == key)
+  }
+}
+
+/// Volume, mute and latency, shared with whichever thread is playing the audio so they take effect
+/// straight away without restarting the output
+#[derive(Clone)]
+pub struct AudioControls {
+  /// Volume from 0 to 1, stored as its bits
+  volume: Arc<AtomicU32>,
+  muted: Arc<AtomicBool>,
+  latency_ms: Arc<AtomicU32>,
+  /// Whether emulation is timed by the audio, see `SyncMode`
+  sync_to_audio: Arc<AtomicBool>,
+  /// Samples the output has pulled on each side since it first started, for the emulation to keep
+  /// pace with
+  played: Arc<AtomicU64>,
+}
+
+impl Default for AudioControls {
+  fn default() -> Self {
+    Self {
+      volume: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+      muted: Arc::new(AtomicBool::new(false)),
+      latency_ms: Arc::new(AtomicU32::new(DEFAULT_LATENCY_MS)),
+      sync_to_audio: Arc::new(AtomicBool::new(true)),
+      played: Arc::new(AtomicU64::new(0)),
+    }
+  }
+}
+
+impl AudioControls {
+  pub fn volume(&self) -> f32 {
+    f32::from_bits(self.volume.load(Ordering::Relaxed))
+  }
+
+  pub fn set_volume(&self, volume: f32) {
+    self.volume.store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+  }
+
+  pub fn muted(&self) -> bool {
+    self.muted.load(Ordering::Relaxed)
+  }
+
+  /// Silence the output without stopping it, so it picks up again straight away
+  pub fn set_muted(&self, muted: bool) {
+    self.muted.store(muted, Ordering::Relaxed);
+  }
+
+  pub fn latency_ms(&self) -> u32 {
+    self.latency_ms.load(Ordering::Relaxed)
+  }
+
+  pub fn set_latency_ms(&self, latency_ms: u32) {
+    let latency_ms = latency_ms.clamp(*LATENCY_RANGE_MS.start(), *LATENCY_RANGE_MS.end());
+    self.latency_ms.store(latency_ms, Ordering::Relaxed);
+  }
+
+  /// The latency as a number of samples
+  pub fn latency_samples(&self) -> usize {
+    (self.latency_ms() * SAMPLE_RATE / 1000) as usize
+  }
+
+  pub fn sync_mode(&self) -> SyncMode {
+    if self.sync_to_audio.load(Ordering::Relaxed) { SyncMode::Audio } else { SyncMode::WallClock }
+  }
+
+  pub fn set_sync_mode(&self, mode: SyncMode) {
+    self.sync_to_audio.store(mode == SyncMode::Audio, Ordering::Relaxed);
+  }
+
+  /// Samples played so far on each side, counting from when the first output started and carrying on across restarts.
+  /// The device plays at exactly `SAMPLE_RATE`, so this is a clock the emulation can run by.
+  pub fn samples_played(&self) -> u64 {
+    self.played.load(Ordering::Relaxed)
+  }
+
+  /// What each sample's multiplied by on its way out
+  fn gain(&self) -> f32 {
+    if self.muted() { 0.0 } else { self.volume() * VOLUME }
+  }
+}
+
+/// Keeps track of how far the audio handed to the output reaches past what it's played, for timing
+/// emulation by the audio
+pub struct AudioClock {
+  controls: AudioControls,
+  /// How many samples will have been played by the time the audio queued so far is
+  scheduled: u64,
+  last_played: u64,
+  /// When `last_played` last changed
+  played_changed: Instant,
+}
+
+impl AudioClock {
+  pub fn new(controls: AudioControls) -> Self {
+    Self {
+      last_played: controls.samples_played(),
+      controls,
+      scheduled: 0,
+      played_changed: Instant::now(),
+    }
+  }
+
+  /// Samples queued that the output hasn't played yet, or `None` if emulation should go by the wall
+  /// clock instead, because it's set to or the output hasn't played anything for a while
+  pub fn buffered(&mut self) -> Option<u64> {
+    let played = self.controls.samples_played();
+    if played != self.last_played {
+      self.last_played = played;
+      self.played_changed = Instant::now();
+    }
+    let playing = self.played_changed.elapsed() < AUDIO_STALL;
+    (playing && self.controls.sync_mode() == SyncMode::Audio).then(|| self.scheduled.saturating_sub(played))
+  }
+
+  /// Samples the output wants queued to stay the latency setting ahead, or `None` as for `buffered`
+  pub fn samples_wanted(&mut self) -> Option<u64> {
+    let latency = self.controls.latency_samples() as u64;
+    self.buffered().map(|buffered| latency.saturating_sub(buffered))
+  }
+
+  /// Count `samples` more as queued. Time the audio spent stalled isn't made up for.
+  pub fn queue(&mut self, samples: u64) {
+    self.scheduled = self.scheduled.max(self.last_played) + samples;
+  }
+}
+
+/// Where the APU output ends up
+pub enum AudioBackend {
+  /// Played through the default output device with rodio
+  Device {
+    _stream: OutputStream,
+    _sink: Sink,
+  },
+  /// Played through the default output device with cpal directly
+  #[cfg(all(feature = "cpal", not(target_arch = "wasm32")))]
+  Cpal {
+    _stream: cpal::Stream,
+  },
+  /// There's no output device, e.g. on a headless machine or in CI, so samples are pulled
+  /// at the same rate a device would and thrown away, keeping the buffering behaving the same
+  Null,
+  /// Not started yet, e.g. in a browser before the page has been interacted with
+  Waiting,
+}
+
+impl AudioBackend {
+  /// Start playing whatever arrives on `apu_messenger` through `driver`, falling back to the null
+  /// backend if there's no device to play it on
+  pub fn start(apu_messenger: Receiver<Vec<f32>>, driver: AudioDriver, controls: AudioControls) -> Self {
+    let source = APUOutput::new(apu_messenger, controls);
+    let device = match driver {
+      AudioDriver::Rodio => start_rodio(source),
+      #[cfg(all(feature = "cpal", not(target_arch = "wasm32")))]
+      AudioDriver::Cpal => start_cpal(source),
+    };
+
+    match device {
+      Ok(backend) => backend,
+      Err((error, source)) => {
+        log::warn!("No audio output available, continuing without sound: {}", error);
+        start_null_output(*source);
+        AudioBackend::Null
+      },
+    }
+  }
+
+  /// Key for the UI string describing the backend
+  pub fn key(&self) -> &'static str {
+    match self {
+      AudioBackend::Device { .. } => "audio.device",
+      #[cfg(all(feature = "cpal", not(target_arch = "wasm32")))]
+      AudioBackend::Cpal { .. } => "audio.device",
+      AudioBackend::Null => "audio.null",
+      AudioBackend::Waiting => "audio.waiting",
+    }
+  }
+}
+
+/// Play `source` through rodio, or hand it back if there's no device. It's boxed on the way back, being
+/// on the large side to return.
+fn start_rodio(source: APUOutput) -> Result<AudioBackend, (String, Box<APUOutput>)> {
+  let device = OutputStream::try_default()
+    .map_err(|error| error.to_string())
+    .and_then(|(stream, handle)| Ok((stream, Sink::try_new(&handle).map_err(|error| error.to_string())?)));
+  match device {
+    Ok((stream, sink)) => {
+      sink.append(source);
+      Ok(AudioBackend::Device { _stream: stream, _sink: sink })
+    },
+    Err(error) => Err((error, Box::new(source))),
+  }
+}
+
+/// Play `source` through cpal, or hand it back if there's no device. The left and right samples go to
+/// the first two channels, with both mixed together on any others or on a mono device, and the buffer's sized to about half the latency setting so the
+/// device pulls often enough to keep the backlog where it should be.
+#[cfg(all(feature = "cpal", not(target_arch = "wasm32")))]
+fn start_cpal(source: APUOutput) -> Result<AudioBackend, (String, Box<APUOutput>)> {
+  use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+  let device = match cpal::default_host().default_output_device() {
+    Some(device) => device,
+    None => return Err(("no output device".to_string(), Box::new(source))),
+  };
+  let supported = device.supported_output_configs()
+    .map_err(|error| error.to_string())
+    .and_then(|mut configs| configs
+      .find(|config| config.sample_format() == cpal::SampleFormat::F32
+        && config.min_sample_rate().0 <= SAMPLE_RATE
+        && config.max_sample_rate().0 >= SAMPLE_RATE)
+      .map(|config| config.with_sample_rate(cpal::SampleRate(SAMPLE_RATE)))
+      .ok_or(format!("the device can't play {}Hz", SAMPLE_RATE)));
+  let supported = match supported {
+    Ok(supported) => supported,
+    Err(error) => return Err((error, Box::new(source))),
+  };
+
+  let channels = supported.channels() as usize;
+  let frames = (source.controls.latency_samples() / 2) as u32;
+  let buffer_size = match supported.buffer_size() {
+    cpal::SupportedBufferSize::Range { min, max } => cpal::BufferSize::Fixed(frames.clamp(*min, *max)),
+    cpal::SupportedBufferSize::Unknown => cpal::BufferSize::Default,
+  };
+  let config = cpal::StreamConfig { buffer_size, ..supported.config() };
+
+  // The source is moved into the callback, so it's shared to get it back if the stream won't start
+  let shared = Arc::new(std::sync::Mutex::new(Some(source)));
+  let callback_source = Arc::clone(&shared);
+  let stream = device.build_output_stream(
+    &config,
+    move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+      let mut source = callback_source.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+      let Some(source) = source.as_mut() else {
+        data.fill(0.0);
+        return;
+      };
+      for frame in data.chunks_mut(channels) {
+        let left = source.next().unwrap_or(0.0);
+        let right = source.next().unwrap_or(0.0);
+        frame.fill((left + right) / 2.0);
+        if let [first, second, ..] = frame {
+          (*first, *second) = (left, right);
+        }
+      }
+    },
+    |error| log::warn!("Audio stream error: {}", error),
+    None,
+  ).map_err(|error| error.to_string())
+    .and_then(|stream| stream.play().map(|()| stream).map_err(|error| error.to_string()));
+
+  match stream {
+    Ok(stream) => Ok(AudioBackend::Cpal { _stream: stream }),
+    Err(error) => {
+      let source = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+      Err((error, Box::new(source.expect("the stream never started, so the source is still here"))))
+    },
+  }
+}
+
+/// Pull samples from `source` in real time on a background thread, until the emulator hangs up
+#[cfg(not(target_arch = "wasm32"))]
+fn start_null_output(mut source: APUOutput) {
+  std::thread::spawn(move || {
+    let started = std::time::Instant::now();
+    let mut consumed: u64 = 0;
+    while !source.disconnected {
+      std::thread::sleep(Duration::from_millis(10));
+      let due = (started.elapsed().as_secs_f64() * SAMPLE_RATE as f64) as u64;
+      while consumed < due {
+        for _ in 0..AUDIO_CHANNELS {
+          source.next();
+        }
+        consumed += 1;
+      }
+    }
+  });
+}
+
+/// Browsers can't block a thread, and the emulation timer doesn't mind its audio going nowhere,
+/// so just hang up
+#[cfg(target_arch = "wasm32")]
+fn start_null_output(_source: APUOutput) {}
 
 /// An infinite source representing the NES APU output.
 ///
-/// Always has a rate of 48kHz and one channel.
+/// Always has a rate of 48kHz and two channels, playing the left and right samples it's sent one after
+/// the other.
 pub struct APUOutput {
   apu_messenger: Receiver<Vec<f32>>,
+  controls: AudioControls,
   buffer: VecDeque<f32>,
-  last_value: f32,
+  /// The left and right samples playing now
+  frame: [f32; 2],
+  /// Whether the right side of `frame` comes out next
+  right_next: bool,
+  /// Set when the buffer runs dry, until it's built back up to half the latency setting, so an
+  /// emulator running just behind gets one gap rather than a crackle of tiny ones
+  starved: bool,
+  /// Set once the sending side has gone away
+  disconnected: bool,
 }
 
 impl APUOutput {
-  /// The frequency of the square wave.
   #[inline]
-  pub fn new(apu_messenger: Receiver<Vec<f32>>) -> APUOutput {
+  pub fn new(apu_messenger: Receiver<Vec<f32>>, controls: AudioControls) -> APUOutput {
     APUOutput {
       apu_messenger,
+      controls,
       buffer: vec![].into(),
-      last_value: 0.0,
+      frame: [0.0; 2],
+      right_next: false,
+      starved: true,
+      disconnected: false,
     }
   }
+
+  /// Take the next left and right samples from the buffer, counting them as played
+  fn next_frame(&mut self) -> [f32; 2] {
+    match self.apu_messenger.try_recv() {
+      Ok(buffer) => {
+        self.buffer.extend(buffer)
+      },
+      Err(TryRecvError::Disconnected) => self.disconnected = true,
+      Err(TryRecvError::Empty) => {},
+    }
+
+    // If the emulator gets ahead, skip the oldest audio rather than let the delay keep growing
+    let latency = self.controls.latency_samples() * AUDIO_CHANNELS;
+    if self.buffer.len() > latency * 2 {
+      let excess = self.buffer.len() - latency;
+      self.buffer.drain(..excess);
+    }
+    if self.starved && self.buffer.len() >= latency / 2 {
+      self.starved = false;
+    }
+
+    // If it falls behind, fade out whatever was last playing rather than holding it
+    let next = if self.starved || self.buffer.len() < AUDIO_CHANNELS {
+      None
+    } else {
+      self.buffer.pop_front().zip(self.buffer.pop_front())
+    };
+    let frame = match next {
+      Some((left, right)) => [left, right],
+      None => {
+        self.starved = true;
+        self.frame.map(|value| value * UNDERRUN_FADE)
+      },
+    };
+    self.controls.played.fetch_add(1, Ordering::Relaxed);
+    frame
+  }
 }
 
 impl Iterator for APUOutput {
@@ -30,16 +442,12 @@ impl Iterator for APUOutput {
 
   #[inline]
   fn next(&mut self) -> Option<f32> {
-    match self.apu_messenger.try_recv() {
-      Ok(buffer) => {
-        self.buffer.extend(buffer)
-      },
-      Err(_) => {},
+    if !self.right_next {
+      self.frame = self.next_frame();
     }
-
-    let value = self.buffer.pop_front().unwrap_or(self.last_value);
-    self.last_value = value;
-    Some(value)
+    let value = self.frame[self.right_next as usize];
+    self.right_next = !self.right_next;
+    Some(value * self.controls.gain())
   }
 }
 
@@ -51,12 +459,12 @@ impl Source for APUOutput {
 
   #[inline]
   fn channels(&self) -> u16 {
-    1
+    AUDIO_CHANNELS as u16
   }
 
   #[inline]
   fn sample_rate(&self) -> u32 {
-    48000
+    SAMPLE_RATE
   }
 
   #[inline]
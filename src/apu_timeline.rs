@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use eframe::egui;
+
+use crate::apu::{ApuChannel, ChannelState};
+use crate::i18n::tr;
+use crate::nes::Nes;
+
+const DEFAULT_FRAMES: usize = 300;
+
+/// Range of history lengths offered, up to 20 seconds
+const MIN_FRAMES: usize = 30;
+const MAX_FRAMES: usize = 1200;
+
+const GRAPH_SIZE: egui::Vec2 = egui::vec2(400.0, 60.0);
+
+const PERIOD_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 170, 255);
+const SWEEP_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 170, 60);
+const ENVELOPE_COLOR: egui::Color32 = egui::Color32::from_rgb(110, 210, 110);
+const LENGTH_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 110, 200);
+
+fn channel_key(channel: ApuChannel) -> &'static str {
+    match channel {
+        ApuChannel::Pulse1 => "apu_timeline.pulse1",
+        ApuChannel::Pulse2 => "apu_timeline.pulse2",
+        ApuChannel::Triangle => "apu_timeline.triangle",
+        ApuChannel::Noise => "apu_timeline.noise",
+    }
+}
+
+/// Window graphing one APU channel's period, envelope, sweep target and length counter over the
+/// last few hundred frames, for working out why a note cuts off early or bends the wrong way
+pub struct ApuTimeline {
+    pub open: bool,
+    channel: ApuChannel,
+    /// How many frames of history to keep and graph
+    frames: usize,
+    /// The channel's state at the end of each frame, oldest first
+    history: VecDeque<ChannelState>,
+    /// Frame the newest entry was taken on, so a frame isn't recorded twice while paused
+    last_frame: Option<u64>,
+}
+
+impl Default for ApuTimeline {
+    fn default() -> Self {
+        Self {
+            open: false,
+            channel: ApuChannel::default(),
+            frames: DEFAULT_FRAMES,
+            history: VecDeque::new(),
+            last_frame: None,
+        }
+    }
+}
+
+impl ApuTimeline {
+    /// Take the selected channel's state, if a new frame has finished since last time
+    pub fn record(&mut self, nes: &Nes) {
+        let frame = nes.frame_count();
+        if self.last_frame == Some(frame) {
+            return;
+        }
+        // Anything from before a reset or a state load would only confuse the graph
+        if self.last_frame.is_some_and(|last| frame < last) {
+            self.history.clear();
+        }
+        self.last_frame = Some(frame);
+        self.history.push_back(nes.bus.apu.channel_state(self.channel));
+        while self.history.len() > self.frames {
+            self.history.pop_front();
+        }
+    }
+
+    /// Recorded states, oldest first
+    pub fn history(&self) -> impl Iterator<Item = &ChannelState> {
+        self.history.iter()
+    }
+
+    pub fn set_channel(&mut self, channel: ApuChannel) {
+        if channel != self.channel {
+            self.channel = channel;
+            self.history.clear();
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, nes: &Nes) {
+        self.record(nes);
+
+        let mut open = self.open;
+        egui::Window::new(tr("apu_timeline.title"))
+            .id(egui::Id::new("apu_timeline_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let mut channel = self.channel;
+                    egui::ComboBox::from_label(tr("apu_timeline.channel"))
+                        .selected_text(tr(channel_key(channel)))
+                        .show_ui(ui, |ui| {
+                            for option in ApuChannel::ALL {
+                                ui.selectable_value(&mut channel, option, tr(channel_key(option)));
+                            }
+                        });
+                    self.set_channel(channel);
+                    ui.add(egui::Slider::new(&mut self.frames, MIN_FRAMES..=MAX_FRAMES).text(tr("apu_timeline.frames")));
+                });
+
+                let periods: Vec<Option<f32>> = self.history.iter().map(|state| Some(state.period as f32)).collect();
+                let targets: Vec<Option<f32>> = self.history.iter().map(|state| state.sweep_target.map(f32::from)).collect();
+                // Share a scale between the period and its target, so they can be compared
+                let period_max = periods.iter().chain(&targets).flatten().fold(1.0f32, |max, value| max.max(*value));
+                let latest = self.history.back().copied().unwrap_or_default();
+
+                let target_text = latest.sweep_target.map_or("-".to_string(), |target| format!("${:03X}", target));
+                ui.label(format!("{}: ${:03X}   {}: {}", tr("apu_timeline.period"), latest.period, tr("apu_timeline.sweep_target"), target_text));
+                self.graph(ui, &[(&periods, PERIOD_COLOR), (&targets, SWEEP_COLOR)], period_max);
+
+                // The triangle has no envelope to graph
+                if self.channel != ApuChannel::Triangle {
+                    let envelope: Vec<Option<f32>> = self.history.iter().map(|state| state.envelope.map(f32::from)).collect();
+                    ui.label(format!("{}: {}", tr("apu_timeline.envelope"), latest.envelope.unwrap_or_default()));
+                    self.graph(ui, &[(&envelope, ENVELOPE_COLOR)], 15.0);
+                }
+
+                let lengths: Vec<Option<f32>> = self.history.iter().map(|state| Some(state.length_counter as f32)).collect();
+                ui.label(format!("{}: {}", tr("apu_timeline.length"), latest.length_counter));
+                let length_max = lengths.iter().flatten().fold(1.0f32, |max, value| max.max(*value));
+                self.graph(ui, &[(&lengths, LENGTH_COLOR)], length_max);
+            });
+        self.open = open;
+    }
+
+    /// Plot each series left to right over the history, from 0 at the bottom to `max` at the top.
+    /// Gaps in a series, where it has no value, break the line.
+    fn graph(&self, ui: &mut egui::Ui, series: &[(&[Option<f32>], egui::Color32)], max: f32) {
+        let (response, painter) = ui.allocate_painter(GRAPH_SIZE, egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+        let x_step = rect.width() / (self.frames.max(2) - 1) as f32;
+        // Line the newest frame up with the right edge, so a short history grows in from the right
+        let x_start = rect.right() - x_step * self.history.len().saturating_sub(1) as f32;
+        for (values, color) in series {
+            let mut line = Vec::new();
+            for (i, value) in values.iter().enumerate() {
+                match value {
+                    Some(value) => {
+                        let y = rect.bottom() - (value / max).clamp(0.0, 1.0) * rect.height();
+                        line.push(egui::pos2(x_start + i as f32 * x_step, y));
+                    },
+                    None => {
+                        painter.add(egui::Shape::line(std::mem::take(&mut line), egui::Stroke::new(1.5, *color)));
+                    },
+                }
+            }
+            painter.add(egui::Shape::line(line, egui::Stroke::new(1.5, *color)));
+        }
+    }
+}
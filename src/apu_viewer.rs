@@ -0,0 +1,145 @@
+use eframe::egui;
+
+use crate::apu::{ApuChannel, AudioChannel, ChannelState};
+use crate::i18n::tr;
+use crate::nes::{Nes, CYCLES_PER_SECOND};
+
+/// NTSC CPU clock, which the channels' timers count down from. The CPU runs at a third of the PPU's rate.
+const CPU_CLOCK: f32 = (CYCLES_PER_SECOND / 3.0) as f32;
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// The duty cycles the pulse channels' duty setting picks between
+const DUTY_NAMES: [&str; 4] = ["12.5%", "25%", "50%", "75%"];
+
+const SCOPE_SIZE: egui::Vec2 = egui::vec2(180.0, 40.0);
+const SCOPE_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 170, 255);
+
+fn channel_key(channel: AudioChannel) -> &'static str {
+    match channel {
+        AudioChannel::Pulse1 => "channel.pulse1",
+        AudioChannel::Pulse2 => "channel.pulse2",
+        AudioChannel::Triangle => "channel.triangle",
+        AudioChannel::Noise => "channel.noise",
+        AudioChannel::Dmc => "channel.dmc",
+        AudioChannel::Expansion => "channel.expansion",
+    }
+}
+
+/// The frequency a channel plays at with its timer set to `period`, or `None` for the noise channel or
+/// a period too short to be heard. The pulse channels are silenced below 8, and the triangle's
+/// ultrasonic below 2.
+pub fn frequency(channel: ApuChannel, period: u16) -> Option<f32> {
+    let steps = match channel {
+        ApuChannel::Pulse1 | ApuChannel::Pulse2 if period >= 8 => 16.0,
+        ApuChannel::Triangle if period >= 2 => 32.0,
+        _ => return None,
+    };
+    Some(CPU_CLOCK / (steps * (period as f32 + 1.0)))
+}
+
+/// The nearest note to `frequency` in twelve tone equal temperament, like "A4", and how many cents
+/// sharp or flat of it the frequency is
+pub fn nearest_note(frequency: f32) -> (String, i32) {
+    let semitones = 12.0 * (frequency / 440.0).log2() + 69.0;
+    let note = semitones.round();
+    let cents = ((semitones - note) * 100.0).round() as i32;
+    let note = note as i32;
+    (format!("{}{}", NOTE_NAMES[note.rem_euclid(12) as usize], note.div_euclid(12) - 1), cents)
+}
+
+/// Window showing what each APU channel's doing right now, with an oscilloscope of each one's level
+#[derive(Default)]
+pub struct ApuViewer {
+    pub open: bool,
+}
+
+impl ApuViewer {
+    pub fn show(&mut self, ctx: &egui::Context, nes: &Nes) {
+        let mut open = self.open;
+        egui::Window::new(tr("apu_viewer.title"))
+            .id(egui::Id::new("apu_viewer_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let apu = &nes.bus.apu;
+                egui::Grid::new("apu_viewer_registers").striped(true).show(ui, |ui| {
+                    for heading in ["apu_viewer.channel", "apu_timeline.period", "apu_viewer.frequency", "apu_viewer.note", "apu_viewer.duty", "apu_viewer.volume", "apu_timeline.length"] {
+                        ui.strong(tr(heading));
+                    }
+                    ui.end_row();
+
+                    for (channel, name) in [
+                        (ApuChannel::Pulse1, "channel.pulse1"),
+                        (ApuChannel::Pulse2, "channel.pulse2"),
+                        (ApuChannel::Triangle, "channel.triangle"),
+                        (ApuChannel::Noise, "channel.noise"),
+                    ] {
+                        let state = apu.channel_state(channel);
+                        ui.label(tr(name));
+                        registers_row(ui, channel, &state);
+                        ui.end_row();
+                    }
+
+                    let dmc = apu.dmc_state();
+                    ui.label(tr("channel.dmc"));
+                    ui.label(format!("${:03X}", dmc.rate));
+                    ui.label(if dmc.rate > 0 { format!("{:.0} Hz", CPU_CLOCK / dmc.rate as f32) } else { "-".to_string() });
+                    ui.label("-");
+                    ui.label("-");
+                    ui.label(dmc.output.to_string());
+                    let looping = if dmc.looping { format!(" ({})", tr("apu_viewer.looping")) } else { String::new() };
+                    ui.label(format!("{} {}{}", dmc.bytes_remaining, tr("apu_viewer.bytes"), looping));
+                    ui.end_row();
+                });
+
+                ui.separator();
+                let Some(scope) = nes.scope() else {
+                    return;
+                };
+                egui::Grid::new("apu_viewer_scopes").show(ui, |ui| {
+                    for (i, channel) in AudioChannel::ALL.into_iter().enumerate() {
+                        ui.vertical(|ui| {
+                            ui.label(tr(channel_key(channel)));
+                            draw_scope(ui, scope.levels(channel).iter().copied());
+                        });
+                        if i % 2 == 1 {
+                            ui.end_row();
+                        }
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
+
+fn registers_row(ui: &mut egui::Ui, channel: ApuChannel, state: &ChannelState) {
+    ui.label(format!("${:03X}", state.period));
+    match frequency(channel, state.period) {
+        Some(frequency) => {
+            let (note, cents) = nearest_note(frequency);
+            ui.label(format!("{:.1} Hz", frequency));
+            ui.label(format!("{} {:+}¢", note, cents));
+        },
+        None => {
+            ui.label("-");
+            ui.label("-");
+        },
+    }
+    ui.label(state.duty.map_or("-", |duty| DUTY_NAMES[duty as usize]));
+    ui.label(state.envelope.map_or("-".to_string(), |volume| volume.to_string()));
+    ui.label(state.length_counter.to_string());
+}
+
+/// Plot levels from 0 to 1 left to right, oldest first
+fn draw_scope(ui: &mut egui::Ui, levels: impl ExactSizeIterator<Item = f32>) {
+    let (response, painter) = ui.allocate_painter(SCOPE_SIZE, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let x_step = rect.width() / levels.len().max(2).saturating_sub(1) as f32;
+    let line: Vec<egui::Pos2> = levels
+        .enumerate()
+        .map(|(i, level)| egui::pos2(rect.left() + i as f32 * x_step, rect.bottom() - level.clamp(0.0, 1.0) * rect.height()))
+        .collect();
+    painter.add(egui::Shape::line(line, egui::Stroke::new(1.0, SCOPE_COLOR)));
+}
@@ -0,0 +1,176 @@
+//! Downsampling the APU output on its own thread.
+//!
+//! The emulation thread hands over raw APU samples, a left and a right one per PPU cycle, through a
+//! lock-free ring buffer, and a worker averages them down to the output rate and passes them on to the
+//! audio backend. Nothing flows back, so the console runs exactly the same with or without the worker,
+//! and save states don't need to know about it. If the ring fills up the emulation thread waits for
+//! room rather than dropping samples, so the output is always exactly what the console produced.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::nes::{downsample_stereo, fade_out_stereo, AUDIO_CHANNELS, CYCLES_PER_FRAME, SAMPLES_PER_OUTPUT_SAMPLE};
+
+/// Raw samples the ring holds, about 3 frames' worth
+const RING_CAPACITY: usize = 1 << 19;
+
+/// How long the worker sleeps when there's nothing to do, if nobody wakes it sooner
+const IDLE_WAIT: Duration = Duration::from_millis(2);
+
+struct Ring {
+  /// Samples stored as their bits, so they can be atomics and the ring doesn't need any unsafe
+  buffer: Box<[AtomicU32]>,
+  /// Total samples ever read and written. Only the consumer moves `read` and only the producer moves `write`.
+  read: AtomicUsize,
+  write: AtomicUsize,
+}
+
+/// Create a single producer, single consumer ring buffer of `capacity` samples, which must be a power of two
+pub fn ring(capacity: usize) -> (Producer, Consumer) {
+  assert!(capacity.is_power_of_two(), "ring capacity must be a power of two");
+  let ring = Arc::new(Ring {
+    buffer: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+    read: AtomicUsize::new(0),
+    write: AtomicUsize::new(0),
+  });
+  (Producer { ring: Arc::clone(&ring) }, Consumer { ring })
+}
+
+/// The writing end of a ring buffer
+pub struct Producer {
+  ring: Arc<Ring>,
+}
+
+impl Producer {
+  /// Write as many of `samples` as there's room for, returning how many that was
+  pub fn push(&self, samples: &[f32]) -> usize {
+    let ring = &self.ring;
+    let write = ring.write.load(Ordering::Relaxed);
+    let free = ring.buffer.len() - (write - ring.read.load(Ordering::Acquire));
+    let count = samples.len().min(free);
+    let mask = ring.buffer.len() - 1;
+    for (i, sample) in samples[..count].iter().enumerate() {
+      ring.buffer[(write + i) & mask].store(sample.to_bits(), Ordering::Relaxed);
+    }
+    ring.write.store(write + count, Ordering::Release);
+    count
+  }
+}
+
+/// The reading end of a ring buffer
+pub struct Consumer {
+  ring: Arc<Ring>,
+}
+
+impl Consumer {
+  /// Move everything written so far onto the end of `samples`, returning how many that was
+  pub fn pop_into(&self, samples: &mut Vec<f32>) -> usize {
+    let ring = &self.ring;
+    let read = ring.read.load(Ordering::Relaxed);
+    let count = ring.write.load(Ordering::Acquire) - read;
+    let mask = ring.buffer.len() - 1;
+    samples.extend((0..count).map(|i| f32::from_bits(ring.buffer[(read + i) & mask].load(Ordering::Relaxed))));
+    ring.read.store(read + count, Ordering::Release);
+    count
+  }
+}
+
+/// Marks a frame that wasn't run in the stream of raw samples. The APU never outputs NaN.
+const PAUSED_FRAME: f32 = f32::NAN;
+
+/// Owns the worker thread, which keeps running until this is dropped
+pub struct AudioPipeline {
+  producer: Producer,
+  closed: Arc<AtomicBool>,
+  worker: Option<JoinHandle<()>>,
+}
+
+impl AudioPipeline {
+  /// Start a worker sending downsampled stereo audio on to `output`
+  pub fn start(output: Sender<Vec<f32>>) -> Self {
+    let (producer, consumer) = ring(RING_CAPACITY);
+    let closed = Arc::new(AtomicBool::new(false));
+    let worker = {
+      let closed = Arc::clone(&closed);
+      thread::Builder::new()
+        .name("audio".to_string())
+        .spawn(move || run_worker(consumer, &closed, output))
+        .expect("couldn't start the audio thread")
+    };
+    Self { producer, closed, worker: Some(worker) }
+  }
+
+  /// Hand over raw APU samples, e.g. from `Nes::take_raw_audio`, waiting for room if the worker is behind
+  pub fn push(&self, mut samples: &[f32]) {
+    while !samples.is_empty() {
+      let pushed = self.producer.push(samples);
+      samples = &samples[pushed..];
+      self.wake_worker();
+      if !samples.is_empty() {
+        thread::yield_now();
+      }
+    }
+  }
+
+  /// Send a frame fading out to silence after everything pushed so far, for a frame that wasn't run
+  pub fn push_paused_frame(&self) {
+    self.push(&[PAUSED_FRAME]);
+  }
+
+  fn wake_worker(&self) {
+    if let Some(worker) = &self.worker {
+      worker.thread().unpark();
+    }
+  }
+}
+
+impl Drop for AudioPipeline {
+  fn drop(&mut self) {
+    self.closed.store(true, Ordering::Release);
+    if let Some(worker) = self.worker.take() {
+      worker.thread().unpark();
+      let _ = worker.join();
+    }
+  }
+}
+
+fn run_worker(consumer: Consumer, closed: &AtomicBool, output: Sender<Vec<f32>>) {
+  let mut raw = Vec::with_capacity(CYCLES_PER_FRAME as usize * AUDIO_CHANNELS);
+  let mut last_sample = [0.0; 2];
+  loop {
+    // Check before draining, so everything pushed before closing still goes out
+    let closed = closed.load(Ordering::Acquire);
+    consumer.pop_into(&mut raw);
+
+    let mut samples = Vec::new();
+    while let Some(marker) = raw.iter().position(|sample| sample.is_nan()) {
+      // The console stopped partway through an output sample, so that one's a little short
+      let downsampled = downsample_stereo(&raw[..marker]);
+      let from = match downsampled[..] {
+        [.., left, right] => [left, right],
+        _ => last_sample,
+      };
+      samples.extend(downsampled);
+      samples.extend(fade_out_stereo(from));
+      last_sample = [0.0; 2];
+      raw.drain(..=marker);
+    }
+    // Otherwise keep any partial output sample for when the rest of it arrives
+    let whole = raw.len() - raw.len() % (SAMPLES_PER_OUTPUT_SAMPLE * AUDIO_CHANNELS);
+    let downsampled = downsample_stereo(&raw[..whole]);
+    if let [.., left, right] = downsampled[..] {
+      last_sample = [left, right];
+    }
+    samples.extend(downsampled);
+    raw.drain(..whole);
+
+    // Once the backend hangs up there's nobody left to play anything
+    if (!samples.is_empty() && output.send(samples).is_err()) || closed {
+      return;
+    }
+    thread::park_timeout(IDLE_WAIT);
+  }
+}
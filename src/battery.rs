@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use crate::config::{decode_hex, encode_hex};
+
+/// Every game's battery backed PRG RAM, keyed by the SHA-256 of its ROM, for frontends without a save
+/// file next to the ROM to keep them in
+#[derive(Default)]
+pub struct BatterySaves {
+    games: HashMap<String, Vec<u8>>,
+}
+
+impl BatterySaves {
+    /// Load the saves from storage, skipping any that no longer decode
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut saves = BatterySaves::default();
+        let Some(saved) = storage.and_then(|storage| storage.get_string("battery_saves")) else {
+            return saves;
+        };
+
+        // One game per line: rom hash, then its RAM in hex
+        for line in saved.lines() {
+            let Some((hash, hex)) = line.split_once('\t') else {
+                continue;
+            };
+            if let Some(ram) = decode_hex(hex) {
+                saves.games.insert(hash.to_string(), ram);
+            }
+        }
+
+        saves
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        let mut saved = String::new();
+        for (hash, ram) in &self.games {
+            saved += &format!("{}\t{}\n", hash, encode_hex(ram));
+        }
+        storage.set_string("battery_saves", saved);
+    }
+
+    pub fn get(&self, rom_hash: &str) -> Option<&[u8]> {
+        self.games.get(rom_hash).map(Vec::as_slice)
+    }
+
+    /// Keep `ram` as the game's save. Storage is tight in a browser and most games only touch the start
+    /// of the RAM, so the zeros on the end are left off.
+    pub fn set(&mut self, rom_hash: &str, ram: &[u8]) {
+        let used = ram.iter().rposition(|byte| *byte != 0).map_or(0, |last| last + 1);
+        self.games.insert(rom_hash.to_string(), ram[..used].to_vec());
+    }
+}
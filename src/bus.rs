@@ -1,45 +1,185 @@
-use std::borrow::Borrow;
-use std::cell::RefCell;
-use std::rc::Rc;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use crate::cartridge::Cartridge;
+use crate::cheat::Cheat;
+use crate::ppu::PPU;
+use crate::apu::APU;
+use crate::watchpoint::{Access, AddressSpace, WatchedAccess, Watchpoint, Watchpoints};
+
+/// Devices that can pull the CPU's IRQ line low. The line is shared, so it stays asserted for as long as
+/// any of them holds it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IrqSource {
+  FrameCounter,
+  Dmc,
+  Mapper,
+}
+
+impl IrqSource {
+  fn mask(self) -> u8 {
+    1 << self as u8
+  }
+}
+
+/// What RAM holds when the console's switched on. Real RAM settles on whatever it likes, and a few
+/// games behave differently depending on it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RamFill {
+  /// $00 and $FF in blocks of four bytes, the pattern many consoles show
+  #[default]
+  Pattern,
+  Zeros,
+  Ones,
+  /// Noise from a seed, the same every time for the same seed
+  Random(u64),
+}
+
+impl RamFill {
+  pub fn fill(self, ram: &mut [u8]) {
+    match self {
+      RamFill::Pattern => {
+        for (address, byte) in ram.iter_mut().enumerate() {
+          *byte = if address & 0x04 == 0 { 0x00 } else { 0xFF };
+        }
+      },
+      RamFill::Zeros => ram.fill(0x00),
+      RamFill::Ones => ram.fill(0xFF),
+      RamFill::Random(seed) => StdRng::seed_from_u64(seed).fill(ram),
+    }
+  }
+
+  /// Stable name for the config file, without the seed
+  pub fn key(self) -> &'static str {
+    match self {
+      RamFill::Pattern => "ram_fill.pattern",
+      RamFill::Zeros => "ram_fill.zeros",
+      RamFill::Ones => "ram_fill.ones",
+      RamFill::Random(_) => "ram_fill.random",
+    }
+  }
+
+  pub fn from_key(key: &str, seed: u64) -> Option<Self> {
+    match key {
+      "ram_fill.pattern" => Some(RamFill::Pattern),
+      "ram_fill.zeros" => Some(RamFill::Zeros),
+      "ram_fill.ones" => Some(RamFill::Ones),
+      "ram_fill.random" => Some(RamFill::Random(seed)),
+      _ => None,
+    }
+  }
+}
+
+/// How the console starts when it's switched on, for the things hardware leaves to chance. Fixing
+/// them makes runs repeatable, e.g. for TASes and regression tests.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PowerOnState {
+  pub ram: RamFill,
+  /// What's on the data bus before anything's driven it
+  pub open_bus: u8,
+  /// Written to $4017 as the console starts, e.g. $40 to start with frame IRQs inhibited
+  pub frame_counter: u8,
+}
+
+/// What an entry in the event log is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+  /// A write to $2000-$2007 or $4014
+  PpuWrite,
+  /// A write to $4000-$4013, $4015 or $4017
+  ApuWrite,
+  /// A write to the cartridge, other than to PRG RAM
+  MapperWrite,
+  /// A device starting to hold the IRQ line
+  Irq,
+}
+
+impl EventKind {
+  pub const ALL: [EventKind; 4] = [EventKind::PpuWrite, EventKind::ApuWrite, EventKind::MapperWrite, EventKind::Irq];
+}
 
-use crate::Cartridge;
-use crate::NES6502;
-use crate::PPU;
-use crate::APU;
+/// Something that happened at a point in the frame, for the event viewer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+  pub kind: EventKind,
+  /// Where the PPU was, with -1 the pre-render scanline
+  pub scanline: i16,
+  pub dot: u16,
+  /// The register written, or for an IRQ, 0
+  pub address: u16,
+  /// The value written, or for an IRQ, the `IrqSource` that raised it
+  pub value: u8,
+}
 
+/// The CPU's view of the system, handed to it for every cycle it runs
 pub trait BusLike {
-  fn connect_cpu(&mut self, cpu: Rc<RefCell<NES6502>>);
-  fn connect_ppu(&mut self, ppu: Rc<RefCell<PPU>>);
-  fn connect_apu(&mut self, apu: Rc<RefCell<APU>>);
-  fn insert_cartridge(&mut self, cartridge: Rc<RefCell<Cartridge>>);
-  fn cpu_read(&self, address: u16) -> u8;
+  fn cpu_read(&mut self, address: u16) -> u8;
+  /// Read without side effects, for debugging tools. Registers that can't be read safely return open bus.
+  fn peek(&self, address: u16) -> u8;
+  /// Write without side effects, for debugging tools. Only RAM can be poked, anything else is ignored.
+  fn poke(&mut self, address: u16, value: u8);
   fn cpu_write(&mut self, address: u16, data: u8);
-  fn reset(&mut self);
+  /// Put RAM, the controller ports and DMA back the way they are when the console's switched on
+  fn power_on(&mut self);
   fn dump_ram(&self) -> Vec<u8>;
   fn get_global_cycles(&self) -> u32;
   fn set_global_cycles(&mut self, cycles: u32);
   fn update_controller(&mut self, controller_index: usize, value: u8);
   fn dma_queued(&self) -> bool;
-  fn set_dma_queued(&mut self, queued: bool);
   fn dma_running(&self) -> bool;
-  fn set_dma_running(&mut self, running: bool);
+  /// Whether an OAM DMA is waiting to start or copying, either way holding the CPU up
+  fn dma_active(&self) -> bool {
+    self.dma_queued() || self.dma_running()
+  }
   fn dma_page(&self) -> u8;
   fn dma_address(&self) -> u8;
-  fn set_dma_address(&mut self, address: u8);
   fn dma_data(&self) -> u8;
-  fn set_dma_data(&mut self, data: u8);
-  fn scanline(&mut self);
+  /// Run a CPU cycle's worth of OAM DMA, returning true if the DMA took the cycle from the CPU.
+  /// A DMA waits for a get cycle to start, then alternates reading a byte and writing it to OAM.
+  fn clock_dma(&mut self) -> bool;
+  /// Abandon any DMA in progress, e.g. on reset
+  fn stop_dma(&mut self);
+  /// Assert or release `source`'s hold on the IRQ line
+  fn set_irq(&mut self, source: IrqSource, asserted: bool);
+  /// Whether anything is holding the IRQ line, as the CPU sees it when it polls for interrupts
+  fn irq_line(&self) -> bool;
+  /// Whether the NMI line is asserted. It's edge triggered, so the CPU samples it every cycle.
+  fn nmi_line(&self) -> bool;
+  fn cheats(&self) -> &[Cheat];
+  /// Replace the cheats patching CPU reads
+  fn set_cheats(&mut self, cheats: Vec<Cheat>);
+  /// Addresses held at a fixed value, and the value each is held at
+  fn frozen(&self) -> &[(u16, u8)];
+  fn set_frozen(&mut self, frozen: Vec<(u16, u8)>);
+  /// Write every frozen value back, undoing whatever the game did to it since last time
+  fn apply_frozen(&mut self);
+  /// Start or stop recording register writes and IRQs into the event log
+  fn set_event_logging(&mut self, enabled: bool);
+  fn event_logging(&self) -> bool;
+  /// Everything logged since last time, oldest first
+  fn take_events(&mut self) -> Vec<Event>;
+  /// Replace the watchpoints CPU reads and writes are checked against
+  fn set_watchpoints(&mut self, watchpoints: Vec<Watchpoint>);
+  /// The first CPU access to set a watchpoint off since last time
+  fn take_watch_hit(&mut self) -> Option<WatchedAccess>;
 }
 
+/// Everything the CPU's connected to, which the bus owns outright. The CPU is handed the bus for each
+/// cycle it runs rather than holding on to it, so nothing here points back at anything else.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bus {
   // Devices
-  cpu: Option<Rc<RefCell<NES6502>>>,
+  pub ppu: PPU,
+  pub apu: APU,
+  /// Not part of a serialized bus, as a state's only ever loaded into a console running the same game
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub cartridge: Option<Cartridge>,
   cpu_ram: Vec<u8>,
-  ppu: Option<Rc<RefCell<PPU>>>,
-  pub cartridge: Option<Rc<RefCell<Cartridge>>>,
   controllers: [u8; 2],
-  controllers_state: Rc<RefCell<[u8; 2]>>,
-  apu: Option<Rc<RefCell<APU>>>,
+  controllers_state: [u8; 2],
+  /// While the strobe bit of $4016 is set the controllers keep reloading, so reads only ever see A
+  controller_strobe: bool,
   // Global cycle count
   global_cycles: u32,
   // DMA vars
@@ -48,109 +188,229 @@ pub struct Bus {
   dma_data: u8,
   dma_queued: bool,
   dma_running: bool,
+  // Devices holding the IRQ line, one bit per IrqSource
+  irq_sources: u8,
+  // Last value driven on the CPU data bus, returned for reads nothing responds to
+  open_bus: u8,
+  // Cheats and frozen addresses belong to the user rather than the machine
+  #[cfg_attr(feature = "serde", serde(skip))]
+  cheats: Vec<Cheat>,
+  /// The user's choice too, and only used at power on
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub power_on_state: PowerOnState,
+  #[cfg_attr(feature = "serde", serde(skip))]
+  frozen: Vec<(u16, u8)>,
+  /// Register writes and IRQs, only kept while the event viewer wants them
+  #[cfg_attr(feature = "serde", serde(skip))]
+  events: Option<Vec<Event>>,
+  #[cfg_attr(feature = "serde", serde(skip))]
+  watchpoints: Watchpoints,
+}
+
+impl Default for Bus {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl Bus {
   pub fn new() -> Self {
     Self {
-      cpu: None,
-      cpu_ram: vec![0; 2048],
-      ppu: None,
-      apu: None,
+      ppu: PPU::new(),
+      apu: APU::new(),
       cartridge: None,
+      cpu_ram: vec![0; 2048],
       controllers: [0, 0],
-      controllers_state: Rc::new(RefCell::new([0, 0])),
+      controllers_state: [0, 0],
+      controller_strobe: false,
       global_cycles: 0,
       dma_page: 0,
       dma_address: 0,
       dma_data: 0,
       dma_queued: false,
       dma_running: false,
+      irq_sources: 0,
+      open_bus: 0,
+      cheats: vec![],
+      power_on_state: PowerOnState::default(),
+      frozen: vec![],
+      events: None,
+      watchpoints: Watchpoints::default(),
     }
   }
-}
 
-impl BusLike for Bus {
-  fn connect_cpu(&mut self, cpu: Rc<RefCell<NES6502>>) {
-    self.cpu = Some(cpu);
+  pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+    self.ppu.insert_cartridge(&cartridge);
+    self.cartridge = Some(cartridge);
+  }
+
+  /// Step the PPU a cycle, showing the mapper its address bus. Nothing's drawn without a cartridge.
+  pub fn clock_ppu(&mut self) {
+    let Some(cartridge) = &mut self.cartridge else {
+      return;
+    };
+    self.ppu.step(cartridge);
+    cartridge.mapper.ppu_bus(self.ppu.bus_signal());
+  }
+
+  /// Step the APU a CPU cycle, fetching the DMC's next sample byte if it wants one
+  pub fn clock_apu(&mut self, cpu_cycles: u32) {
+    let sample = self.apu.dmc_fetch_address().map(|address| self.cpu_read(address));
+    self.apu.step(cpu_cycles, sample);
   }
 
-  fn connect_ppu(&mut self, ppu: Rc<RefCell<PPU>>) {
-    self.ppu = Some(ppu);
+  /// Mix the APU's channels with the cartridge's own sound hardware into the next output sample
+  pub fn update_audio_output(&mut self) {
+    let expansion = self.cartridge.as_ref().map_or(0.0, |cartridge| cartridge.mapper.audio_output());
+    self.apu.update_output(expansion);
   }
 
-  fn connect_apu(&mut self, apu: Rc<RefCell<APU>>) {
-    self.apu = Some(apu);
+  /// The console's 2KB of work RAM, for frontends that read and write it directly
+  pub fn ram_mut(&mut self) -> &mut [u8] {
+    &mut self.cpu_ram
   }
 
-  fn insert_cartridge(&mut self, cartridge: Rc<RefCell<Cartridge>>) {
-    self.cartridge = Some(Rc::clone(&cartridge));
-    if let Some(ppu) = &self.ppu {
-      ppu.as_ref().borrow_mut().connect_cartridge(Rc::clone(&cartridge));
+  /// Become `other`, copying its work RAM into this bus's rather than taking its buffer, since
+  /// frontends can hold on to where the RAM is
+  pub fn replace(&mut self, mut other: Bus) {
+    self.cpu_ram.copy_from_slice(&other.cpu_ram);
+    std::mem::swap(&mut self.cpu_ram, &mut other.cpu_ram);
+    *self = other;
+  }
+
+  /// Read the PPU bus without setting off watchpoints, e.g. to look at the nametables
+  pub fn ppu_peek(&mut self, address: u16) -> u8 {
+    match &self.cartridge {
+      Some(cartridge) => self.ppu.peek(cartridge, address),
+      None => 0,
     }
   }
 
-  fn cpu_read(&self, address: u16) -> u8 {
-    match address {
+  /// Add to the event log, if it's being kept
+  fn log_event(&mut self, kind: EventKind, address: u16, value: u8) {
+    let Some(events) = &mut self.events else {
+      return;
+    };
+    let (scanline, dot) = self.ppu.position();
+    events.push(Event { kind, scanline, dot, address, value });
+  }
+
+  /// Patch a value read from `address` with the first cheat that changes it
+  fn apply_cheats(&self, address: u16, value: u8) -> u8 {
+    self.cheats.iter()
+      .map(|cheat| cheat.apply(address, value))
+      .find(|&patched| patched != value)
+      .unwrap_or(value)
+  }
+}
+
+impl BusLike for Bus {
+  fn cpu_read(&mut self, address: u16) -> u8 {
+    let open_bus = self.open_bus;
+    let value = match address {
       0x0000..=0x1FFF => {
         self.cpu_ram[(address & 0x07FF) as usize]
       },
       0x2000..=0x3FFF => {
-        if let Some(ppu) = &self.ppu {
-          ppu.as_ref().borrow_mut().cpu_read(address & 0x0007)
+        if let Some(cartridge) = &self.cartridge {
+          self.ppu.cpu_read(cartridge, address & 0x0007)
         } else {
-          panic!("PPU is not connected!");
+          panic!("Cartridge is not connected!");
         }
       },
       0x4015 => {
-        if let Some(apu) = &self.apu {
-          apu.as_ref().borrow_mut().cpu_read(address)
-        } else {
-          panic!("APU is not connected!");
-        }
+        // $4015 is read inside the CPU, so it doesn't change the external data bus,
+        // and bit 5 isn't driven at all
+        let status = self.apu.cpu_read(address);
+        let value = (status & 0xDF) | (open_bus & 0x20);
+        self.watchpoints.check(AddressSpace::Cpu, Access::Read, address, value);
+        return value;
       },
       0x4016 | 0x4017 => {
+        // Only the low bits are driven by the controller port, the rest is whatever was last on the bus
         let index = (address & 0x1) as usize;
-        let value = (self.controllers_state.as_ref().borrow()[index] & 0x80) > 0;
-        self.controllers_state.borrow_mut()[index] <<= 1;
-        value as u8
+        if self.controller_strobe {
+          self.controllers_state[index] = self.controllers[index];
+        }
+        let value = (self.controllers_state[index] & 0x80) > 0;
+        // Once all 8 buttons have been read, a standard controller reports 1s
+        if !self.controller_strobe {
+          self.controllers_state[index] = (self.controllers_state[index] << 1) | 1;
+        }
+        (value as u8) | (open_bus & 0xE0)
       },
-      0x6000..=0x7FFF => {
+      0x6000..=0xFFFF => {
         if let Some(cartridge) = &self.cartridge {
-          if cartridge.as_ref().borrow().has_ram {
-            cartridge.as_ref().borrow().cpu_read(address)
-          } else {
-            0
-          }
+          // PRG RAM that isn't there or is switched off leaves the bus as it was
+          cartridge.cpu_read(address).unwrap_or(open_bus)
         } else {
           panic!("Cartridge is not connected!");
         }
-      }
-      0x8000..=0xFFFF => {
-        if let Some(cartridge) = &self.cartridge {
-          cartridge.as_ref().borrow().cpu_read(address)
-        } else {
-          panic!("Cartridge is not connected!");
+      },
+      // $4000-$4014 and $4018-$401F are write-only or unused, and nothing sits at $4020-$5FFF yet
+      _ => open_bus
+    };
+    let value = self.apply_cheats(address, value);
+    self.open_bus = value;
+    if !self.watchpoints.is_empty() {
+      self.watchpoints.check(AddressSpace::Cpu, Access::Read, address, value);
+    }
+    value
+  }
+
+  fn peek(&self, address: u16) -> u8 {
+    let value = match address {
+      0x0000..=0x1FFF => self.cpu_ram[(address & 0x07FF) as usize],
+      0x6000..=0xFFFF => {
+        self.cartridge.as_ref().and_then(|cartridge| cartridge.cpu_read(address)).unwrap_or(self.open_bus)
+      },
+      _ => self.open_bus,
+    };
+    self.apply_cheats(address, value)
+  }
+
+  fn poke(&mut self, address: u16, value: u8) {
+    match address {
+      0x0000..=0x1FFF => self.cpu_ram[(address & 0x07FF) as usize] = value,
+      0x6000..=0x7FFF => {
+        if let Some(cartridge) = &mut self.cartridge {
+          if cartridge.has_ram {
+            cartridge.cpu_write(address, value);
+          }
         }
       },
-      _ => 0
+      // Writing anywhere else would poke a register, or a mapper thinking it's a bank switch
+      _ => {},
     }
   }
 
   fn cpu_write(&mut self, address: u16, value: u8) {
+    self.open_bus = value;
+    if !self.watchpoints.is_empty() {
+      self.watchpoints.check(AddressSpace::Cpu, Access::Write, address, value);
+    }
+    if self.events.is_some() {
+      match address {
+        0x2000..=0x3FFF => self.log_event(EventKind::PpuWrite, 0x2000 | (address & 0x0007), value),
+        0x4014 => self.log_event(EventKind::PpuWrite, address, value),
+        0x4000..=0x4013 | 0x4015 | 0x4017 => self.log_event(EventKind::ApuWrite, address, value),
+        // PRG RAM's just memory, but anything else on the cartridge is a mapper register
+        0x6000..=0x7FFF if self.cartridge.as_ref().is_some_and(|cartridge| cartridge.has_ram) => {},
+        0x4020..=0xFFFF => self.log_event(EventKind::MapperWrite, address, value),
+        _ => {},
+      }
+    }
     match address {
       0x0000..=0x1FFF => {
         self.cpu_ram[(address & 0x07FF) as usize] = value;
       },
       0x2000..=0x3FFF => {
-        if let Some(ppu) = &self.ppu {
-          ppu.as_ref().borrow_mut().cpu_write(address & 0x0007, value);
+        if let Some(cartridge) = &self.cartridge {
+          self.ppu.cpu_write(cartridge, address & 0x0007, value);
         }
       },
       0x4000..=0x4013 => {
-        if let Some(apu) = &self.apu {
-          apu.as_ref().borrow_mut().cpu_write(address, value);
-        }
+        self.apu.cpu_write(address, value);
       }
       0x4014 => {
         self.dma_page = value;
@@ -158,45 +418,47 @@ impl BusLike for Bus {
         self.dma_queued = true;
       },
       0x4015 => {
-        if let Some(apu) = &self.apu {
-          apu.as_ref().borrow_mut().cpu_write(address, value);
-        }
+        self.apu.cpu_write(address, value);
       }
       0x4016 => {
-        // NOTE: This seems to be inaccurate from the OLC video, fix later
         // https://www.nesdev.org/wiki/Standard_controller#Input_.28.244016_write.29
-        let index = (address & 0x1) as usize;
-        self.controllers_state.borrow_mut()[index] = self.controllers[index];
+        // Both controller ports share the strobe, and latch whatever the host last reported for each
+        // for as long as it's held, so the buttons read after it's released are the ones from then
+        let strobe = value & 0x01 != 0;
+        if strobe || self.controller_strobe {
+          self.controllers_state = self.controllers;
+        }
+        self.controller_strobe = strobe;
       },
       0x4017 => {
-        if let Some(apu) = &self.apu {
-          apu.as_ref().borrow_mut().cpu_write(address, value);
-        }
+        self.apu.cpu_write(address, value);
       },
-      0x6000..=0x7FFF => {
-        if let Some(cartridge) = &self.cartridge {
-          if cartridge.as_ref().borrow().has_ram {
-            cartridge.as_ref().borrow_mut().cpu_write(address, value);
-          }
-        } else {
-          panic!("Cartridge is not connected!");
-        }
-      }
-      0x8000..=0xFFFF => {
-        if let Some(cartridge) = &self.cartridge {
-          cartridge.as_ref().borrow_mut().cpu_write(address, value);
+      // $4018-$401F is the APU's test mode, which is disabled on retail consoles
+      0x4018..=0x401F => {},
+      0x4020..=0xFFFF => {
+        // Everything from here up is the cartridge's, whether or not it has RAM there,
+        // since mapper registers can sit anywhere in it
+        if let Some(cartridge) = &mut self.cartridge {
+          cartridge.cpu_write(address, value);
         } else {
           panic!("Cartridge is not connected!");
         }
       },
-      _ => {}
     }
   }
 
-  fn reset(&mut self) {
-    if let Some(cpu) = self.cpu.borrow() {
-      cpu.as_ref().borrow_mut().reset();
-    }
+  fn power_on(&mut self) {
+    self.power_on_state.ram.fill(&mut self.cpu_ram);
+    self.controllers_state = [0, 0];
+    self.controller_strobe = false;
+    self.global_cycles = 0;
+    self.dma_page = 0;
+    self.dma_address = 0;
+    self.dma_data = 0;
+    self.dma_queued = false;
+    self.dma_running = false;
+    self.irq_sources = 0;
+    self.open_bus = self.power_on_state.open_bus;
   }
 
   fn dump_ram(&self) -> Vec<u8> {
@@ -220,18 +482,10 @@ impl BusLike for Bus {
     self.dma_queued
   }
 
-  fn set_dma_queued(&mut self, queued: bool) {
-    self.dma_queued = queued;
-  }
-
   fn dma_running(&self) -> bool {
     self.dma_running
   }
 
-  fn set_dma_running(&mut self, running: bool) {
-    self.dma_running = running;
-  }
-
   fn dma_page(&self) -> u8 {
     self.dma_page
   }
@@ -240,61 +494,146 @@ impl BusLike for Bus {
     self.dma_address
   }
 
-  fn set_dma_address(&mut self, address: u8) {
-    self.dma_address = address;
-  }
-
   fn dma_data(&self) -> u8 {
     self.dma_data
   }
 
-  fn set_dma_data(&mut self, data: u8) {
-    self.dma_data = data;
+  fn clock_dma(&mut self) -> bool {
+    let get_cycle = self.global_cycles.is_multiple_of(2);
+    if self.dma_running {
+      if get_cycle {
+        self.dma_data = self.cpu_read((self.dma_page as u16) << 8 | self.dma_address as u16);
+      } else {
+        self.ppu.write_oam(self.dma_address, self.dma_data);
+        self.dma_address = self.dma_address.wrapping_add(1);
+        if self.dma_address == 0 {
+          self.dma_running = false;
+          self.dma_queued = false;
+        }
+      }
+      true
+    } else if self.dma_queued {
+      // Copying starts on the next get cycle, so this one's spent waiting
+      if !get_cycle {
+        self.dma_running = true;
+      }
+      true
+    } else {
+      false
+    }
   }
 
-  fn scanline(&mut self) {
-    if let Some(cartridge) = &self.cartridge {
-      cartridge.as_ref().borrow_mut().mapper.scanline();
+  fn stop_dma(&mut self) {
+    self.dma_queued = false;
+    self.dma_running = false;
+  }
+
+  fn set_irq(&mut self, source: IrqSource, asserted: bool) {
+    if asserted && self.irq_sources & source.mask() == 0 {
+      self.log_event(EventKind::Irq, 0, source as u8);
+    }
+    if asserted {
+      self.irq_sources |= source.mask();
     } else {
-      panic!("Cartridge is not connected!");
+      self.irq_sources &= !source.mask();
+    }
+  }
+
+  fn irq_line(&self) -> bool {
+    self.irq_sources != 0
+  }
+
+  fn nmi_line(&self) -> bool {
+    self.ppu.nmi_output()
+  }
+
+  fn cheats(&self) -> &[Cheat] {
+    &self.cheats
+  }
+
+  fn set_cheats(&mut self, cheats: Vec<Cheat>) {
+    self.cheats = cheats;
+  }
+
+  fn frozen(&self) -> &[(u16, u8)] {
+    &self.frozen
+  }
+
+  fn set_frozen(&mut self, frozen: Vec<(u16, u8)>) {
+    self.frozen = frozen;
+  }
+
+  fn apply_frozen(&mut self) {
+    for (address, value) in self.frozen.clone() {
+      self.poke(address, value);
     }
   }
+
+  fn set_event_logging(&mut self, enabled: bool) {
+    match (enabled, &self.events) {
+      (true, None) => self.events = Some(vec![]),
+      (false, Some(_)) => self.events = None,
+      _ => {},
+    }
+  }
+
+  fn event_logging(&self) -> bool {
+    self.events.is_some()
+  }
+
+  fn take_events(&mut self) -> Vec<Event> {
+    self.events.as_mut().map(std::mem::take).unwrap_or_default()
+  }
+
+  fn set_watchpoints(&mut self, watchpoints: Vec<Watchpoint>) {
+    self.watchpoints.set(watchpoints);
+  }
+
+  fn take_watch_hit(&mut self) -> Option<WatchedAccess> {
+    self.watchpoints.take_hit()
+  }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MockBus {
-  pub cpu: Option<Rc<RefCell<NES6502>>>,
   pub cpu_ram: Vec<u8>,
+  irq_sources: u8,
+}
+
+impl Default for MockBus {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl MockBus {
   pub fn new() -> Self {
     Self {
-      cpu: None,
       cpu_ram: vec![0; 0x10000],
+      irq_sources: 0,
     }
   }
 }
 
 impl BusLike for MockBus {
-  fn connect_cpu(&mut self, cpu: Rc<RefCell<NES6502>>) {
-    self.cpu = Some(cpu);
+  fn cpu_read(&mut self, address: u16) -> u8 {
+    self.cpu_ram[address as usize]
   }
 
-  fn connect_ppu(&mut self, _ppu: Rc<RefCell<PPU>>) {}
-
-  fn connect_apu(&mut self, _apu: Rc<RefCell<APU>>) {}
-
-  fn insert_cartridge(&mut self, _cartridge: Rc<RefCell<Cartridge>>) {}
-
-  fn cpu_read(&self, address: u16) -> u8 {
+  fn peek(&self, address: u16) -> u8 {
     self.cpu_ram[address as usize]
   }
 
+  fn poke(&mut self, address: u16, value: u8) {
+    self.cpu_ram[address as usize] = value;
+  }
+
   fn cpu_write(&mut self, address: u16, value: u8) {
     self.cpu_ram[address as usize] = value;
   }
 
-  fn reset(&mut self) {}
+  fn power_on(&mut self) {}
 
   fn dump_ram(&self) -> Vec<u8> {
     self.cpu_ram.clone()
@@ -312,14 +651,10 @@ impl BusLike for MockBus {
     false
   }
 
-  fn set_dma_queued(&mut self, _queued: bool) {}
-
   fn dma_running(&self) -> bool {
     false
   }
 
-  fn set_dma_running(&mut self, _running: bool) {}
-
   fn dma_page(&self) -> u8 {
     0
   }
@@ -328,13 +663,59 @@ impl BusLike for MockBus {
     0
   }
 
-  fn set_dma_address(&mut self, _address: u8) {}
-
   fn dma_data(&self) -> u8 {
     0
   }
 
-  fn set_dma_data(&mut self, _data: u8) {}
+  fn clock_dma(&mut self) -> bool {
+    false
+  }
+
+  fn stop_dma(&mut self) {}
 
-  fn scanline(&mut self) {}
+  fn set_irq(&mut self, source: IrqSource, asserted: bool) {
+    if asserted {
+      self.irq_sources |= source.mask();
+    } else {
+      self.irq_sources &= !source.mask();
+    }
+  }
+
+  fn irq_line(&self) -> bool {
+    self.irq_sources != 0
+  }
+
+  fn nmi_line(&self) -> bool {
+    false
+  }
+
+  fn cheats(&self) -> &[Cheat] {
+    &[]
+  }
+
+  fn set_cheats(&mut self, _cheats: Vec<Cheat>) {}
+
+  fn frozen(&self) -> &[(u16, u8)] {
+    &[]
+  }
+
+  fn set_frozen(&mut self, _frozen: Vec<(u16, u8)>) {}
+
+  fn apply_frozen(&mut self) {}
+
+  fn set_event_logging(&mut self, _enabled: bool) {}
+
+  fn event_logging(&self) -> bool {
+    false
+  }
+
+  fn take_events(&mut self) -> Vec<Event> {
+    vec![]
+  }
+
+  fn set_watchpoints(&mut self, _watchpoints: Vec<Watchpoint>) {}
+
+  fn take_watch_hit(&mut self) -> Option<WatchedAccess> {
+    None
+  }
 }
\ No newline at end of file
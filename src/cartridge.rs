@@ -1,100 +1,239 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::fs;
+use std::io;
 use std::path::Path;
+use std::sync::Arc;
 
-use crate::mapper::Mapper;
-use crate::mappers::{
-  mapper0::Mapper0,
-  mapper1::Mapper1,
-  mapper2::Mapper2,
-  mapper3::Mapper3,
-  mapper4::Mapper4,
-  mapper7::Mapper7,
-  mapper9::Mapper9,
-  mapper11::Mapper11,
-  mapper76::Mapper76,
-  mapper89::Mapper89,
-  mapper140::Mapper140,
-  mapper152::Mapper152,
+use crate::bus::RamFill;
+use crate::mapper::{Mapper, MapperState};
+use crate::mappers;
+
+/// What a pattern table read gives with CHR disabled, by the low byte of the address
+static ADDRESS_LOW_BYTES: [u8; 256] = {
+  let mut bytes = [0; 256];
+  let mut i = 0;
+  while i < 256 {
+    bytes[i] = i as u8;
+    i += 1;
+  }
+  bytes
 };
 
+/// The parts of a cartridge that change while it runs, without the ROM itself
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CartridgeState {
+  pub ram: Vec<u8>,
+  pub mapper: MapperState,
+}
+
+/// Why a ROM couldn't be loaded
+#[derive(Debug)]
+pub enum CartridgeError {
+  Io(io::Error),
+  /// Not an iNES or NES 2.0 image
+  InvalidHeader,
+  /// Shorter than the PRG and CHR sizes in its header say it should be
+  Truncated { expected: usize, actual: usize },
+  /// The header says there's no PRG ROM, so there'd be nothing to run
+  NoPrgRom,
+  UnsupportedMapper(u8),
+  /// Made for arcade hardware built around the NES, rather than the console itself
+  UnsupportedConsole(ConsoleType),
+}
+
+impl fmt::Display for CartridgeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CartridgeError::Io(error) => write!(f, "Couldn't read the ROM: {}", error),
+      CartridgeError::InvalidHeader => write!(f, "Not an iNES ROM"),
+      CartridgeError::Truncated { expected, actual } => write!(f, "ROM is truncated, the header says it's {} bytes but it's {}", expected, actual),
+      CartridgeError::NoPrgRom => write!(f, "ROM has no program in it"),
+      CartridgeError::UnsupportedMapper(mapper_id) => write!(f, "Mapper {} isn't supported", mapper_id),
+      CartridgeError::UnsupportedConsole(console_type) => write!(f, "This ROM is for the {}, which isn't supported", console_type),
+    }
+  }
+}
+
+impl std::error::Error for CartridgeError {}
+
+/// A game, ROM and all. The ROM is shared between clones rather than copied, so snapshotting the
+/// console doesn't copy it every time.
+#[derive(Clone)]
 pub struct Cartridge {
   pub header_info: HeaderInfo,
   pub mapper_id: u8,
-  pub prg_rom: Vec<u8>,
-  pub chr_rom: Vec<u8>,
+  pub prg_rom: Arc<Vec<u8>>,
+  /// 8 KB of zeros for boards with CHR RAM instead, which lives in the PPU's pattern tables
+  pub chr_rom: Arc<Vec<u8>>,
   pub mapper: Box<dyn Mapper>,
+  /// Whether there's PRG RAM at $6000-$7FFF, either battery backed or there for a trainer. Only
+  /// battery backed RAM is kept between sessions, see [`Cartridge::has_battery`].
   pub has_ram: bool,
   pub ram: Vec<u8>,
+  /// The 512 byte trainer, empty if there isn't one, kept to load into PRG RAM again at power on
+  pub trainer: Arc<Vec<u8>>,
+  /// NES 2.0 miscellaneous ROM area after CHR, for the few boards that carry extra ROM chips
+  pub misc_rom: Arc<Vec<u8>>,
+  /// Mapper features the game has tried to use that we ignore, in the order it first tried them
+  pub unimplemented: Vec<&'static str>,
 }
 
 impl Cartridge {
-  pub fn from_rom(rom_path: &str) -> Self {
-    let bytes = fs::read(Path::new(rom_path)).expect(&format!("Failed to load ROM from supplied path: {}", rom_path));
+  pub fn from_rom(rom_path: &str) -> Result<Self, CartridgeError> {
+    let bytes = fs::read(Path::new(rom_path)).map_err(CartridgeError::Io)?;
     Cartridge::from_bytes(bytes)
   }
 
-  pub fn from_bytes(rom_bytes: Vec<u8>) -> Self {
-    match parse_header(&rom_bytes) {
-      Ok(header_info) => {
-        let mapper_id = (header_info.flags6 & 0b1111_0000) >> 4 | (header_info.flags7 & 0b1111_0000);
-        let mapper = match mapper_id {
-          0 => Box::new(Mapper0::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          1 => Box::new(Mapper1::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          2 => Box::new(Mapper2::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          3 => Box::new(Mapper3::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          4 => Box::new(Mapper4::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          7 => Box::new(Mapper7::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          9 => Box::new(Mapper9::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          11 => Box::new(Mapper11::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          76 => Box::new(Mapper76::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          89 => Box::new(Mapper89::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          140 => Box::new(Mapper140::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          152 => Box::new(Mapper152::new(header_info.prg_rom_size, header_info.chr_rom_size)) as Box<dyn Mapper>,
-          _ => panic!("Mapper {} not implemented.", mapper_id),
-        };
-        let prg_start: u32 = 0x0010;
-        let prg_end: u32 = prg_start + (0x4000 * header_info.prg_rom_size as u32);
-        let chr_start: u32 = prg_end;
-        let chr_end: u32 = chr_start + (0x2000 * header_info.chr_rom_size as u32);
-        println!("PRG: {:#06X} - {:#06X}, CHR: {:#06X} - {:#06X}, Mapper: {}", prg_start, prg_end, chr_start, chr_end, mapper_id);
-        let chr_rom = if header_info.chr_rom_size == 0 {
-          vec![0; 0x2000]
-        } else {
-          rom_bytes[chr_start as usize..chr_end as usize].to_vec()
-        };
-        let has_ram = (header_info.flags6 & 0b0000_0010) != 0;
-        Self {
-          header_info,
-          mapper_id,
-          prg_rom: rom_bytes[prg_start as usize..prg_end as usize].to_vec(),
-          chr_rom,
-          mapper,
-          has_ram,
-          ram: vec![0; 0x8000],
-        }
-      },
-      Err(_) => panic!("Failed to parse ROM from supplied bytes."),
-    }
-  }
-
-  pub fn cpu_read(&self, address: u16) -> u8 {
-    if self.has_ram && address >= 0x6000 && address <= 0x7FFF {
-      self.ram[self.mapper.get_mapped_address_cpu(address) as usize]
+  /// Load an iNES or NES 2.0 image, as long as it's whole and we have its mapper
+  pub fn from_bytes(rom_bytes: Vec<u8>) -> Result<Self, CartridgeError> {
+    let header_info = parse_header(&rom_bytes)?;
+    // Vs. System and PlayChoice-10 games expect coin slots, DIP switches and their own palettes, and
+    // run wrong on a plain NES rather than not at all, so they're turned away up front
+    if !header_info.console_type.runs_on_nes() {
+      return Err(CartridgeError::UnsupportedConsole(header_info.console_type));
+    }
+    if header_info.prg_rom_size == 0 {
+      return Err(CartridgeError::NoPrgRom);
+    }
+    let mapper_id = (header_info.flags6 & 0b1111_0000) >> 4 | (header_info.flags7 & 0b1111_0000);
+    let mapper = create_mapper(mapper_id, &header_info).ok_or(CartridgeError::UnsupportedMapper(mapper_id))?;
+    // A 512 byte trainer sits between the header and PRG ROM when flag 6 says so
+    let has_trainer = header_info.flags6 & 0b0000_0100 != 0;
+    let prg_start: u32 = if has_trainer { 0x0210 } else { 0x0010 };
+    let prg_end: u32 = prg_start + (0x4000 * header_info.prg_rom_size as u32);
+    let chr_start: u32 = prg_end;
+    let chr_end: u32 = chr_start + (0x2000 * header_info.chr_rom_size as u32);
+    if rom_bytes.len() < chr_end as usize {
+      return Err(CartridgeError::Truncated { expected: chr_end as usize, actual: rom_bytes.len() });
+    }
+    log::debug!("PRG: {:#06X} - {:#06X}, CHR: {:#06X} - {:#06X}, Mapper: {}", prg_start, prg_end, chr_start, chr_end, mapper_id);
+    // Anything after CHR is only meaningful if an NES 2.0 header says there's misc ROM there
+    let trailing = rom_bytes.get(chr_end as usize..).unwrap_or_default();
+    let misc_rom = if header_info.misc_roms > 0 {
+      log::debug!("Misc ROM: {:#06X} - {:#06X}", chr_end, rom_bytes.len());
+      trailing.to_vec()
+    } else {
+      if !trailing.is_empty() {
+        log::debug!("Ignoring {} bytes after CHR ROM", trailing.len());
+      }
+      vec![]
+    };
+    let chr_rom = if header_info.chr_rom_size == 0 {
+      vec![0; 0x2000]
     } else {
-      self.prg_rom[self.mapper.get_mapped_address_cpu(address) as usize]
+      rom_bytes[chr_start as usize..chr_end as usize].to_vec()
+    };
+    // Trainers were patches copiers loaded into PRG RAM at $7000-$71FF, so there has to be RAM for them
+    let has_ram = (header_info.flags6 & 0b0000_0010) != 0 || has_trainer;
+    let trainer = if has_trainer { rom_bytes[0x0010..0x0210].to_vec() } else { vec![] };
+    let mut cartridge = Self {
+      header_info,
+      mapper_id,
+      prg_rom: Arc::new(rom_bytes[prg_start as usize..prg_end as usize].to_vec()),
+      chr_rom: Arc::new(chr_rom),
+      mapper,
+      has_ram,
+      ram: vec![0; 0x8000],
+      trainer: Arc::new(trainer),
+      misc_rom: Arc::new(misc_rom),
+      unimplemented: vec![],
+    };
+    cartridge.load_trainer();
+    Ok(cartridge)
+  }
+
+  fn load_trainer(&mut self) {
+    let ram_size = self.ram.len();
+    for (address, byte) in (0x7000..0x7200).zip(self.trainer.iter()) {
+      if let Some(mapped_address) = self.mapper.get_mapped_address_prg_ram(address) {
+        self.ram[mapped_address as usize % ram_size] = *byte;
+      }
     }
   }
 
+  /// Whether the header says PRG RAM is kept powered by a battery when the console's off
+  pub fn has_battery(&self) -> bool {
+    self.header_info.flags6 & 0b0000_0010 != 0
+  }
+
+  /// The mapper the game's on, by number and chip, e.g. "mapper 4, MMC3"
+  pub fn mapper_description(&self) -> String {
+    match mappers::info(self.mapper_id) {
+      Some(info) => format!("mapper {}, {}", self.mapper_id, info.name),
+      None => format!("mapper {}", self.mapper_id),
+    }
+  }
+
+  /// Put the mapper back the way it is when the console's switched on. Battery backed PRG RAM keeps
+  /// its contents, that's what the battery's for, while RAM without one comes up like the console's
+  /// own and gets the trainer loaded into it again.
+  pub fn power_on(&mut self, ram_fill: RamFill) {
+    self.mapper = create_mapper(self.mapper_id, &self.header_info).expect("the mapper was created when the cartridge was loaded");
+    if !self.has_battery() {
+      ram_fill.fill(&mut self.ram);
+      self.load_trainer();
+    }
+  }
+
+  /// What the cartridge puts on the data bus for a CPU read, or `None` if nothing does and the bus
+  /// keeps whatever was last on it, e.g. for $6000-$7FFF without PRG RAM or with it switched off
+  pub fn cpu_read(&self, address: u16) -> Option<u8> {
+    if let Some(offset) = self.mapper.get_mapped_address_misc(address).filter(|_| !self.misc_rom.is_empty()) {
+      return Some(self.misc_rom[offset as usize % self.misc_rom.len()]);
+    }
+    // Banks past the end of the chips wrap around, as boards leave the address lines they don't need unconnected
+    match address {
+      0x6000..=0x7FFF if self.prg_ram_enabled() => self.mapper.get_mapped_address_prg_ram(address).map(|mapped_address| self.ram[mapped_address as usize % self.ram.len()]),
+      0x8000..=0xFFFF => Some(self.prg_rom[self.mapper.get_mapped_address_cpu(address) as usize % self.prg_rom.len()]),
+      _ => None,
+    }
+  }
+
+  /// Whether there's PRG RAM at $6000-$7FFF for the CPU to see right now
+  pub fn prg_ram_enabled(&self) -> bool {
+    self.has_ram && self.mapper.prg_ram_enabled()
+  }
+
   pub fn cpu_write(&mut self, address: u16, value: u8) {
-    if self.has_ram && address >= 0x6000 && address <= 0x7FFF {
-      self.ram[self.mapper.get_mapped_address_cpu(address) as usize] = value
+    if let Some(feature) = self.mapper.unimplemented_write(address, value) {
+      if !self.unimplemented.contains(&feature) {
+        log::warn!("Mapper {}: {} isn't implemented, the game may not run correctly", self.mapper_id, feature);
+        self.unimplemented.push(feature);
+      }
+    }
+    if (0x6000..=0x7FFF).contains(&address) && self.prg_ram_enabled() && self.mapper.prg_ram_writable() {
+      if let Some(mapped_address) = self.mapper.get_mapped_address_prg_ram(address) {
+        let ram_size = self.ram.len();
+        self.ram[mapped_address as usize % ram_size] = value;
+      }
+    }
+    // Registers can sit under PRG RAM too, e.g. NINA-001's, and see the write as well
+    self.mapper.mapped_cpu_write(address, value);
+  }
+
+  pub fn state(&self) -> CartridgeState {
+    CartridgeState {
+      ram: self.ram.clone(),
+      mapper: self.mapper.state(),
+    }
+  }
+
+  pub fn load_state(&mut self, state: &CartridgeState) {
+    // Copied into place rather than swapped, as frontends can hold on to where the RAM is
+    if self.ram.len() == state.ram.len() {
+      self.ram.copy_from_slice(&state.ram);
     } else {
-      self.mapper.mapped_cpu_write(address, value);
+      self.ram = state.ram.clone();
     }
+    self.mapper = state.mapper.clone().into_mapper();
   }
 
   pub fn ppu_read(&self, address: u16) -> &u8 {
+    if !self.mapper.chr_enabled() {
+      // Nothing drives the PPU's data bus, so it reads back the low byte of the address it put there
+      return &ADDRESS_LOW_BYTES[address as usize & 0xFF];
+    }
     let mapped_address = self.mapper.get_mapped_address_ppu(address) as usize;
     if (mapped_address) < self.chr_rom.len() {
       &self.chr_rom[mapped_address]
@@ -103,10 +242,6 @@ impl Cartridge {
     }
   }
 
-  pub fn ppu_write(&mut self, address: u16, value: u8) {
-    self.chr_rom[self.mapper.get_mapped_address_ppu(address) as usize] = value
-  }
-
   pub fn get_nametable_layout(&self) -> MirroringMode {
     let mapper_mirroring_mode = self.mapper.mirroring_mode();
     if mapper_mirroring_mode == MirroringMode::_Hardwired {
@@ -121,11 +256,11 @@ impl Cartridge {
   }
 
   pub fn get_prg_rom(&self) -> Vec<u8> {
-    self.prg_rom.clone()
+    self.prg_rom.to_vec()
   }
 
   pub fn get_chr_rom(&self) -> Vec<u8> {
-    self.chr_rom.clone()
+    self.chr_rom.to_vec()
   }
 
   pub fn dump_prg_rom(&self) {
@@ -145,6 +280,43 @@ pub enum MirroringMode {
   Vertical,
   SingleScreenLow,
   SingleScreenHigh,
+  /// Which of the two pages of nametable RAM each of the four nametables uses, for boards that pick
+  /// them one at a time, e.g. TxSROM
+  Pages([u8; 4]),
+}
+
+/// The hardware a ROM was made for, from the header
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ConsoleType {
+  /// The NES or Famicom
+  #[default]
+  Nes,
+  /// Nintendo's Vs. System arcade boards
+  VsSystem,
+  /// The PlayChoice-10 arcade cabinet
+  PlayChoice10,
+  /// One of NES 2.0's extended console types, e.g. a Famiclone or VT01 system, by number
+  Extended(u8),
+}
+
+impl ConsoleType {
+  /// Whether a game for this console runs on the NES we emulate. Famiclones only add decimal mode
+  /// to the CPU, which NES games never rely on, and the EPSM is extra sound hardware that games still
+  /// run without, while the VTxx and other extended types have CPUs and PPUs of their own.
+  pub fn runs_on_nes(&self) -> bool {
+    matches!(self, ConsoleType::Nes | ConsoleType::Extended(3) | ConsoleType::Extended(4))
+  }
+}
+
+impl fmt::Display for ConsoleType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ConsoleType::Nes => write!(f, "NES"),
+      ConsoleType::VsSystem => write!(f, "Vs. System"),
+      ConsoleType::PlayChoice10 => write!(f, "PlayChoice-10"),
+      ConsoleType::Extended(console_type) => write!(f, "extended console type {}", console_type),
+    }
+  }
 }
 
 #[allow(non_camel_case_types)]
@@ -166,6 +338,20 @@ pub struct HeaderInfo {
   pub flags8: u8,
   pub flags9: u8,
   pub flags10: u8,
+  /// Number of misc ROMs after CHR, always 0 for anything but NES 2.0
+  pub misc_roms: u8,
+  pub console_type: ConsoleType,
+}
+
+impl HeaderInfo {
+  /// NES 2.0 submapper, which tells apart boards that share a mapper number. Always 0 for iNES.
+  pub fn submapper(&self) -> u8 {
+    if self.format == Format::NES2_0 {
+      self.flags8 >> 4
+    } else {
+      0
+    }
+  }
 }
 
 impl Debug for HeaderInfo {
@@ -179,18 +365,33 @@ impl Debug for HeaderInfo {
       .field("flags8", &format!("{:08b}", &self.flags8))
       .field("flags9", &format!("{:08b}", &self.flags9))
       .field("flags10", &format!("{:08b}", &self.flags10))
+      .field("misc_roms", &self.misc_roms)
+      .field("console_type", &self.console_type)
       .finish()
   }
 }
 
-fn parse_header(bytes: &[u8]) -> Result<HeaderInfo, &str> {
+/// A fresh mapper for the cartridge, set up for its submapper if the header has one
+fn create_mapper(mapper_id: u8, header_info: &HeaderInfo) -> Option<Box<dyn Mapper>> {
+  let mut mapper = mappers::create(mapper_id, header_info.prg_rom_size, header_info.chr_rom_size)?;
+  if header_info.format == Format::NES2_0 {
+    mapper.set_submapper(header_info.submapper());
+  }
+  Some(mapper)
+}
+
+fn parse_header(bytes: &[u8]) -> Result<HeaderInfo, CartridgeError> {
   let mut header_info = HeaderInfo::default();
 
+  if bytes.len() < 16 {
+    return Err(CartridgeError::InvalidHeader);
+  }
+
   // Check for NES<EOF> constant, otherwise this is invalid
   if bytes[0] == 0x4E && bytes[1] == 0x45 && bytes[2] == 0x53 && bytes[3] == 0x1A {
     header_info.format = Format::iNES;
   } else {
-    return Err("Invalid iNES header");
+    return Err(CartridgeError::InvalidHeader);
   }
 
   // If we've verified that it's iNES-compatible, check for NES2.0 bits
@@ -205,8 +406,28 @@ fn parse_header(bytes: &[u8]) -> Result<HeaderInfo, &str> {
   header_info.flags8 = bytes[8];
   header_info.flags9 = bytes[9];
   header_info.flags10 = bytes[10];
+  if header_info.format == Format::NES2_0 {
+    header_info.misc_roms = bytes[14] & 0b0000_0011;
+  }
+  // https://www.nesdev.org/wiki/NES_2.0#Console_Type
+  // iNES only has bit 0 for Vs. System and bit 1 for PlayChoice-10, NES 2.0 makes the two bits a
+  // number, with 3 meaning the real type is in byte 13. Old dumps with junk like "DiskDude!" over the
+  // end of an iNES header can't be trusted with either bit, so they're taken to be for the NES.
+  let clean_tail = bytes[12..16].iter().all(|&byte| byte == 0);
+  header_info.console_type = match (header_info.format, header_info.flags7 & 0b0000_0011) {
+    (_, 0) => ConsoleType::Nes,
+    (Format::iNES, _) if !clean_tail => ConsoleType::Nes,
+    (_, 1) => ConsoleType::VsSystem,
+    (Format::NES2_0, 3) => match bytes[13] & 0x0F {
+      0 => ConsoleType::Nes,
+      1 => ConsoleType::VsSystem,
+      2 => ConsoleType::PlayChoice10,
+      console_type => ConsoleType::Extended(console_type),
+    },
+    _ => ConsoleType::PlayChoice10,
+  };
 
-  println!("{:?}", header_info);
+  log::debug!("{:?}", header_info);
 
   Ok(header_info)
 }
@@ -0,0 +1,104 @@
+/// Letters used by Game Genie codes, in order of the 4-bit value each one stands for
+const GAME_GENIE_LETTERS: &[u8; 16] = b"APZLGITYEOXUKSVN";
+
+/// A single patch to what the CPU reads: whenever it reads `address` it sees `value` instead,
+/// but only if the real value matches `compare` when there is one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cheat {
+  /// The code as the user entered it
+  pub code: String,
+  pub description: String,
+  pub enabled: bool,
+  pub address: u16,
+  pub value: u8,
+  pub compare: Option<u8>,
+}
+
+impl Cheat {
+  /// Decode a cheat code, which can be a 6 or 8 letter Game Genie code,
+  /// a Pro Action Replay code (`AAAAVV` in hex), or a raw `AAAA:VV` / `AAAA?CC:VV` patch.
+  pub fn parse(code: &str) -> Result<Self, String> {
+    let code = code.trim().to_uppercase();
+    let (address, value, compare) = if code.contains(':') {
+      decode_raw(&code)?
+    } else if code.bytes().all(|c| GAME_GENIE_LETTERS.contains(&c)) {
+      decode_game_genie(&code)?
+    } else {
+      decode_pro_action_replay(&code)?
+    };
+
+    Ok(Self {
+      code,
+      description: String::new(),
+      enabled: true,
+      address,
+      value,
+      compare,
+    })
+  }
+
+  /// The value the CPU sees when reading `address`, given what's really there
+  pub fn apply(&self, address: u16, value: u8) -> u8 {
+    if self.enabled && self.address == address && self.compare.is_none_or(|compare| compare == value) {
+      self.value
+    } else {
+      value
+    }
+  }
+}
+
+/// Game Genie codes scramble the address, value and compare bits across 6 or 8 letters,
+/// each standing for 4 bits. The address always lands in $8000-$FFFF.
+fn decode_game_genie(code: &str) -> Result<(u16, u8, Option<u8>), String> {
+  if code.len() != 6 && code.len() != 8 {
+    return Err(format!("Game Genie codes are 6 or 8 letters long, {} is {}", code, code.len()));
+  }
+  let n: Vec<u16> = code.bytes()
+    .map(|c| GAME_GENIE_LETTERS.iter().position(|&letter| letter == c).unwrap() as u16)
+    .collect();
+
+  let address = 0x8000
+    | ((n[3] & 7) << 12)
+    | ((n[5] & 7) << 8) | ((n[4] & 8) << 8)
+    | ((n[2] & 7) << 4) | ((n[1] & 8) << 4)
+    | (n[4] & 7) | (n[3] & 8);
+
+  // The 8th letter takes over the value's low high-bit, which 6 letter codes keep in the 6th
+  let value_high_bit = if code.len() == 8 { n[7] & 8 } else { n[5] & 8 };
+  let value = ((n[1] & 7) << 4) | ((n[0] & 8) << 4) | (n[0] & 7) | value_high_bit;
+
+  let compare = (code.len() == 8).then(|| {
+    (((n[7] & 7) << 4) | ((n[6] & 8) << 4) | (n[6] & 7) | (n[5] & 8)) as u8
+  });
+
+  Ok((address, value as u8, compare))
+}
+
+/// Pro Action Replay codes are just the address and value in hex, `AAAAVV`
+fn decode_pro_action_replay(code: &str) -> Result<(u16, u8, Option<u8>), String> {
+  let hex: String = code.chars().filter(|c| *c != '-' && *c != ' ').collect();
+  if hex.len() != 6 || !hex.is_ascii() {
+    return Err(format!("Not a Game Genie or Pro Action Replay code: {}", code));
+  }
+  let address = parse_hex(&hex[0..4])?;
+  let value = parse_byte(&hex[4..6])?;
+  Ok((address, value, None))
+}
+
+/// Raw patches in the `AAAA:VV` or `AAAA?CC:VV` form most emulators accept
+fn decode_raw(code: &str) -> Result<(u16, u8, Option<u8>), String> {
+  let (target, value) = code.split_once(':').unwrap();
+  let (address, compare) = match target.split_once('?') {
+    Some((address, compare)) => (address, Some(parse_byte(compare)?)),
+    None => (target, None),
+  };
+  Ok((parse_hex(address)?, parse_byte(value)?, compare))
+}
+
+fn parse_hex(hex: &str) -> Result<u16, String> {
+  u16::from_str_radix(hex, 16).map_err(|_| format!("Invalid hex: {}", hex))
+}
+
+fn parse_byte(hex: &str) -> Result<u8, String> {
+  u8::from_str_radix(hex, 16).map_err(|_| format!("Invalid byte: {}", hex))
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use eframe::egui;
+
+use crate::cheat::Cheat;
+use crate::i18n::tr;
+
+/// Every game's cheats, keyed by the SHA-256 of its ROM so they come back when it's loaded again
+#[derive(Default)]
+pub struct CheatLibrary {
+    games: HashMap<String, Vec<Cheat>>,
+}
+
+impl CheatLibrary {
+    /// Load the library from storage, skipping any cheats that no longer decode
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut library = CheatLibrary::default();
+        let Some(saved) = storage.and_then(|storage| storage.get_string("cheats")) else {
+            return library;
+        };
+
+        // One cheat per line: rom hash, enabled, code, description
+        for line in saved.lines() {
+            let mut fields = line.splitn(4, '\t');
+            let (Some(hash), Some(enabled), Some(code)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            if let Ok(mut cheat) = Cheat::parse(code) {
+                cheat.enabled = enabled == "1";
+                cheat.description = fields.next().unwrap_or_default().to_string();
+                library.games.entry(hash.to_string()).or_default().push(cheat);
+            }
+        }
+
+        library
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        let mut saved = String::new();
+        for (hash, cheats) in &self.games {
+            for cheat in cheats {
+                let description = cheat.description.replace(['\t', '\n'], " ");
+                saved += &format!("{}\t{}\t{}\t{}\n", hash, cheat.enabled as u8, cheat.code, description);
+            }
+        }
+        storage.set_string("cheats", saved);
+    }
+
+    pub fn cheats(&self, rom_hash: &str) -> Vec<Cheat> {
+        self.games.get(rom_hash).cloned().unwrap_or_default()
+    }
+
+    pub fn cheats_mut(&mut self, rom_hash: &str) -> &mut Vec<Cheat> {
+        self.games.entry(rom_hash.to_string()).or_default()
+    }
+}
+
+/// Window for adding, toggling and removing the current game's cheats
+#[derive(Default)]
+pub struct CheatWindow {
+    pub open: bool,
+    code: String,
+    description: String,
+    error: Option<String>,
+}
+
+impl CheatWindow {
+    /// Draw the window, if open. `cheats` is `None` when no game is loaded.
+    /// Returns what changed, if the cheats need handing to the console again.
+    pub fn show(&mut self, ctx: &egui::Context, cheats: Option<&mut Vec<Cheat>>) -> Option<String> {
+        let mut changed = None;
+        let mut open = self.open;
+
+        egui::Window::new(tr("cheats.title"))
+            .id(egui::Id::new("cheat_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let Some(cheats) = cheats else {
+                    ui.label(tr("cheats.no_rom"));
+                    return;
+                };
+
+                let mut removed = None;
+                egui::Grid::new("cheat_list").striped(true).show(ui, |ui| {
+                    for (i, cheat) in cheats.iter_mut().enumerate() {
+                        if ui.checkbox(&mut cheat.enabled, "").changed() {
+                            let verb = if cheat.enabled { "Enabled" } else { "Disabled" };
+                            changed = Some(format!("{} cheat {}", verb, cheat.code));
+                        }
+                        ui.monospace(&cheat.code);
+                        ui.label(&cheat.description);
+                        if ui.button(tr("cheats.remove")).clicked() {
+                            removed = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = removed {
+                    let cheat = cheats.remove(i);
+                    changed = Some(format!("Removed cheat {}", cheat.code));
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.code)
+                        .hint_text(tr("cheats.code"))
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(100.0));
+                    ui.add(egui::TextEdit::singleline(&mut self.description)
+                        .hint_text(tr("cheats.description")));
+                    if ui.button(tr("cheats.add")).clicked() {
+                        match Cheat::parse(&self.code) {
+                            Ok(mut cheat) => {
+                                cheat.description = std::mem::take(&mut self.description);
+                                changed = Some(format!("Added cheat {}", cheat.code));
+                                cheats.push(cheat);
+                                self.code.clear();
+                                self.error = None;
+                            },
+                            Err(error) => self.error = Some(error),
+                        }
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+
+        self.open = open;
+        changed
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+
+/// The longest the APU frame sequencer runs before wrapping (5-step mode)
+const MAX_APU_SEQUENCE_CYCLES: u32 = 18641;
+
+/// Where each clock domain was at a single point in time
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClockSnapshot {
+  pub global_cycles: u32,
+  pub scanline: i16,
+  pub dot: u16,
+  pub cpu_cycles: u32,
+  pub apu_cycles: u32,
+  pub dma_active: bool,
+  pub pc: u16,
+}
+
+impl fmt::Display for ClockSnapshot {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "global cycle {}, scanline {} dot {}, CPU cycle {}, APU cycle {}, DMA {}, PC {:#06X}",
+      self.global_cycles,
+      self.scanline,
+      self.dot,
+      self.cpu_cycles,
+      self.apu_cycles,
+      if self.dma_active { "active" } else { "idle" },
+      self.pc,
+    )
+  }
+}
+
+/// A broken timing invariant, along with the state on either side of the clock that broke it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClockViolation {
+  pub message: String,
+  pub before: ClockSnapshot,
+  pub after: ClockSnapshot,
+}
+
+impl fmt::Display for ClockViolation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}\n  before: {}\n  after:  {}", self.message, self.before, self.after)
+  }
+}
+
+/// Checks that the PPU, CPU and APU stay in step with each other every time the system is clocked.
+///
+/// Only the first violation is kept and reported, since one slip usually throws every
+/// check after it off as well.
+#[derive(Clone, Debug, Default)]
+pub struct ClockAudit {
+  violation: Option<ClockViolation>,
+}
+
+impl ClockAudit {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn first_violation(&self) -> Option<&ClockViolation> {
+    self.violation.as_ref()
+  }
+
+  /// Compare the state before and after a single PPU cycle
+  pub fn check(&mut self, before: &ClockSnapshot, after: &ClockSnapshot) {
+    if self.violation.is_some() {
+      return;
+    }
+
+    if let Some(message) = find_violation(before, after) {
+      let violation = ClockViolation { message, before: *before, after: *after };
+      log::error!("Clock audit failed: {}", violation);
+      self.violation = Some(violation);
+    }
+  }
+}
+
+fn find_violation(before: &ClockSnapshot, after: &ClockSnapshot) -> Option<String> {
+  // The global counter is the PPU dot clock
+  if after.global_cycles != before.global_cycles.wrapping_add(1) {
+    return Some(format!("global cycles advanced by {} instead of 1", after.global_cycles.wrapping_sub(before.global_cycles)));
+  }
+
+  // The PPU moves forward exactly one dot, except for the idle dot skipped at the start of scanline 0
+  let expected_position = next_dot(before.scanline, before.dot);
+  let skipped_idle_dot = before.scanline == 0 && before.dot == 0 && (after.scanline, after.dot) == (0, 2);
+  if (after.scanline, after.dot) != expected_position && !skipped_idle_dot {
+    return Some(format!(
+      "PPU moved from scanline {} dot {} to scanline {} dot {}, expected scanline {} dot {}",
+      before.scanline, before.dot, after.scanline, after.dot, expected_position.0, expected_position.1,
+    ));
+  }
+
+  // The CPU gets one cycle every third dot, unless OAM DMA has it halted
+  let cpu_delta = after.cpu_cycles.wrapping_sub(before.cpu_cycles);
+  let cpu_slot = before.global_cycles.is_multiple_of(3);
+  let expected_cpu_delta = if cpu_slot && !before.dma_active { 1 } else { 0 };
+  if cpu_delta != expected_cpu_delta {
+    return Some(format!("CPU advanced {} cycles on a dot where it should advance {} (3 PPU dots per CPU cycle)", cpu_delta, expected_cpu_delta));
+  }
+
+  // The APU frame sequencer only moves on every other CPU cycle, and either counts up or wraps.
+  // A write to $4017 can also restart it on any CPU cycle, so a fresh sequence is always allowed.
+  let apu_restarted = cpu_delta == 1 && after.apu_cycles <= 1;
+  let apu_should_tick = cpu_delta == 1 && after.cpu_cycles.is_multiple_of(2);
+  if apu_should_tick && after.apu_cycles != before.apu_cycles.wrapping_add(1) && !apu_restarted {
+    return Some(format!("APU sequencer went from {} to {} instead of counting up or wrapping", before.apu_cycles, after.apu_cycles));
+  }
+  if !apu_should_tick && after.apu_cycles != before.apu_cycles && !apu_restarted {
+    return Some(format!("APU sequencer moved from {} to {} on a cycle it should have been idle", before.apu_cycles, after.apu_cycles));
+  }
+  if after.apu_cycles > MAX_APU_SEQUENCE_CYCLES {
+    return Some(format!("APU sequencer reached {}, past the end of the longest frame sequence", after.apu_cycles));
+  }
+
+  None
+}
+
+/// The dot after the given one, wrapping to the next scanline and then the pre-render line
+fn next_dot(scanline: i16, dot: u16) -> (i16, u16) {
+  if dot + 1 < 341 {
+    (scanline, dot + 1)
+  } else if scanline + 1 < 261 {
+    (scanline + 1, 0)
+  } else {
+    (-1, 0)
+  }
+}
@@ -0,0 +1,104 @@
+//! Counting the CPU cycles spent on each instruction, to show ROM hackers where a game's frame time goes.
+
+use std::collections::HashMap;
+
+use crate::cpu::ExecutionHook;
+
+/// How the code profile's lumped together for the heat list
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Grouping {
+  /// Everything run from each PRG ROM bank, with code in RAM lumped together by 8 KB window
+  #[default]
+  Bank,
+  /// Each 256 bytes of each bank
+  Page,
+  /// Each instruction on its own
+  Instruction,
+}
+
+impl Grouping {
+  pub const ALL: [Grouping; 3] = [Grouping::Bank, Grouping::Page, Grouping::Instruction];
+
+  pub fn key(&self) -> &'static str {
+    match self {
+      Grouping::Bank => "code_profile.bank",
+      Grouping::Page => "code_profile.page",
+      Grouping::Instruction => "code_profile.instruction",
+    }
+  }
+
+  /// Which group an instruction at `pc` in `bank` goes in
+  fn group(&self, bank: Option<u32>, pc: u16) -> (Option<u32>, u16) {
+    match (self, bank) {
+      (Grouping::Bank, Some(_)) => (bank, 0),
+      (Grouping::Bank, None) => (bank, pc & 0xE000),
+      (Grouping::Page, _) => (bank, pc & 0xFF00),
+      (Grouping::Instruction, _) => (bank, pc),
+    }
+  }
+}
+
+/// A stretch of code and the cycles spent running it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HotSpot {
+  /// The PRG ROM bank it's in, or `None` for code run from RAM
+  pub bank: Option<u32>,
+  /// The lowest and highest addresses an instruction in it ran from
+  pub first: u16,
+  pub last: u16,
+  pub cycles: u64,
+}
+
+/// Cycles spent on each instruction while profiling, told apart by the PRG bank they ran from as well as
+/// their address so bank switched code doesn't get mixed up
+#[derive(Clone, Debug, Default)]
+pub struct CodeProfile {
+  /// The PRG bank the next instruction's fetched from, set before each one runs since the instruction
+  /// itself might switch banks
+  pub bank: Option<u32>,
+  cycles: HashMap<(Option<u32>, u16), u64>,
+  total: u64,
+  frames: u32,
+}
+
+impl ExecutionHook for CodeProfile {
+  fn executed(&mut self, pc: u16, _opcode: u8, cycles: usize) {
+    *self.cycles.entry((self.bank, pc)).or_default() += cycles as u64;
+    self.total += cycles as u64;
+  }
+}
+
+impl CodeProfile {
+  /// Count another frame finished, for per frame figures
+  pub fn end_frame(&mut self) {
+    self.frames += 1;
+  }
+
+  pub fn frames(&self) -> u32 {
+    self.frames
+  }
+
+  /// Cycles spent running instructions since profiling started
+  pub fn total_cycles(&self) -> u64 {
+    self.total
+  }
+
+  /// Start counting again from nothing
+  pub fn clear(&mut self) {
+    *self = Self { bank: self.bank, ..Self::default() };
+  }
+
+  /// Where the cycles went, lumped together by `grouping`, the most cycles first
+  pub fn hot_spots(&self, grouping: Grouping) -> Vec<HotSpot> {
+    let mut groups: HashMap<(Option<u32>, u16), HotSpot> = HashMap::new();
+    for (&(bank, pc), &cycles) in &self.cycles {
+      let spot = groups.entry(grouping.group(bank, pc)).or_insert(HotSpot { bank, first: pc, last: pc, cycles: 0 });
+      spot.first = spot.first.min(pc);
+      spot.last = spot.last.max(pc);
+      spot.cycles += cycles;
+    }
+    let mut spots: Vec<HotSpot> = groups.into_values().collect();
+    spots.sort_by(|a, b| b.cycles.cmp(&a.cycles).then(a.bank.cmp(&b.bank)).then(a.first.cmp(&b.first)));
+    spots
+  }
+}
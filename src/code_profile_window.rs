@@ -0,0 +1,85 @@
+use eframe::egui;
+
+use crate::code_profile::{Grouping, HotSpot};
+use crate::i18n::tr;
+use crate::nes::Nes;
+
+/// Most rows in the heat list, which is plenty to find where the time goes
+const MAX_ROWS: usize = 100;
+
+const BAR_WIDTH: f32 = 120.0;
+
+/// From cool to hot as a row's share of the time goes from nothing to everything
+fn heat_color(share: f32) -> egui::Color32 {
+    let share = share.clamp(0.0, 1.0);
+    egui::Color32::from_rgb(
+        (80.0 + share * 175.0) as u8,
+        (140.0 - share * 90.0) as u8,
+        (220.0 - share * 180.0) as u8,
+    )
+}
+
+/// Where a hot spot is, e.g. "PRG bank 3 $8000-$9FF0", or "RAM $0300-$03A0" for code that isn't in ROM
+pub fn describe(spot: &HotSpot) -> String {
+    let place = match spot.bank {
+        Some(bank) => format!("{} {}", tr("code_profile.prg_bank"), bank),
+        None => tr("code_profile.ram").to_string(),
+    };
+    if spot.first == spot.last {
+        format!("{} ${:04X}", place, spot.first)
+    } else {
+        format!("{} ${:04X}-${:04X}", place, spot.first, spot.last)
+    }
+}
+
+/// Window listing where the CPU's time goes while the game runs, hottest first, for ROM hackers
+/// looking for what to speed up. Profiling only runs while the window's open.
+#[derive(Default)]
+pub struct CodeProfileWindow {
+    pub open: bool,
+    grouping: Grouping,
+}
+
+impl CodeProfileWindow {
+    pub fn show(&mut self, ctx: &egui::Context, nes: &mut Nes) {
+        let mut open = self.open;
+        egui::Window::new(tr("code_profile.title"))
+            .id(egui::Id::new("code_profile_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for grouping in Grouping::ALL {
+                        ui.radio_value(&mut self.grouping, grouping, tr(grouping.key()));
+                    }
+                    if ui.button(tr("code_profile.clear")).clicked() {
+                        if let Some(profile) = nes.code_profile_mut() {
+                            profile.clear();
+                        }
+                    }
+                });
+                let Some(profile) = nes.code_profile() else {
+                    return;
+                };
+                let total = profile.total_cycles().max(1);
+                let frames = profile.frames().max(1) as u64;
+                ui.label(format!("{}: {}, {}: {}", tr("code_profile.frames"), profile.frames(), tr("code_profile.cycles_per_frame"), total / frames));
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("code_profile_grid").striped(true).show(ui, |ui| {
+                        for spot in profile.hot_spots(self.grouping).iter().take(MAX_ROWS) {
+                            let share = spot.cycles as f32 / total as f32;
+                            let (rect, _) = ui.allocate_exact_size(egui::vec2(BAR_WIDTH, 12.0), egui::Sense::hover());
+                            let bar = egui::Rect::from_min_size(rect.min, egui::vec2((BAR_WIDTH * share).max(1.0), rect.height()));
+                            ui.painter().rect_filled(bar, 2.0, heat_color(share));
+                            ui.monospace(format!("{:5.1}%", share * 100.0));
+                            ui.monospace(describe(spot));
+                            ui.label(format!("{} {}", spot.cycles / frames, tr("code_profile.cycles")));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.open = open;
+    }
+}
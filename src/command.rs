@@ -0,0 +1,348 @@
+use crate::apu::{AudioChannel, MixerMode, StereoMode};
+use crate::apu_output::{AudioDriver, SyncMode, LATENCY_RANGE_MS};
+use crate::bus::PowerOnState;
+use crate::config::Config;
+use crate::demos;
+use crate::frame_advance::BackgroundMode;
+use crate::frame_skip::FrameSkip;
+use crate::i18n::Language;
+use crate::nes::RunTarget;
+use crate::palette::BuiltinPalette;
+use crate::video::ColorVision;
+use crate::watchpoint::{AddressSpace, Watchpoint};
+
+/// Number of save state slots available from the console, slot 0 doubles as the quick save
+pub const SAVE_SLOTS: usize = 10;
+
+/// Everything the user can ask the emulator to do, whether it comes from the menubar, a shortcut or the console
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    LoadRom,
+    /// Run the built in test pattern in place of the game
+    LoadTestPattern,
+    /// Play one of the built in demos, by its index in `DEMOS`
+    PlayDemo(usize),
+    /// Open the list of ROMs in the library folder
+    ShowLibrary,
+    SaveState(usize),
+    LoadState(usize),
+    TogglePause,
+    FrameAdvance,
+    /// Press the console's reset button
+    Reset,
+    /// Switch the console off and on again
+    PowerCycle,
+    Quit,
+    SetLanguage(Language),
+    SetColorVision(ColorVision),
+    SetUiScale(f32),
+    SetPalette(BuiltinPalette),
+    SetHideOverscan(bool),
+    /// Show each frame blended with the one before it
+    SetFlickerBlend(bool),
+    /// Choose which frames are left undrawn, to keep full speed on slow machines
+    SetFrameSkip(FrameSkip),
+    LoadPaletteFile,
+    ShowCheats,
+    /// Open the loaded game's own settings
+    ShowGameProperties,
+    ShowRamSearch,
+    /// Open the window for setting up conditions on memory
+    ShowConditions,
+    /// Open the practice slot browser
+    ShowPractice,
+    ShowApuTimeline,
+    ShowApuViewer,
+    ShowEventViewer,
+    ShowNametableViewer,
+    ShowCodeProfiler,
+    ShowNetplay,
+    ShowHotkeys,
+    ShowInput,
+    /// Save the current frame to the screenshots folder
+    SaveScreenshot,
+    ToggleFullscreen,
+    ToggleMute,
+    /// Set the volume, from 0 to 1
+    SetVolume(f32),
+    /// Set how far ahead the audio is buffered, in milliseconds
+    SetAudioLatency(u32),
+    SetAudioDriver(AudioDriver),
+    /// Choose whether the audio or the wall clock decides how fast the console runs
+    SetSyncMode(SyncMode),
+    SetUltrasonicTriangle(bool),
+    SetReducePulsePopping(bool),
+    /// Mix the channels with the console's nonlinear mixer and filters, or a cheaper linear approximation
+    SetMixer(MixerMode),
+    /// Choose how the channels are spread between the speakers
+    SetStereoMode(StereoMode),
+    /// Place a channel between the speakers, from -1 on the left to 1 on the right, switching to the
+    /// custom stereo mode
+    SetChannelPan(AudioChannel, f32),
+    /// Choose how the console starts, from the next power cycle on
+    SetPowerOnState(PowerOnState),
+    /// Choose what emulation does while the window doesn't have focus
+    SetBackgroundMode(BackgroundMode),
+    ToggleChannelMute(AudioChannel),
+    /// Solo a channel, or stop soloing it. Several can be soloed at once.
+    ToggleChannelSolo(AudioChannel),
+    About,
+    ToggleConsole,
+    Break(u16),
+    ClearBreakpoints,
+    Continue,
+    /// Pause and run until the console gets somewhere, for stepping through in the debugger
+    RunTo(RunTarget),
+    Watch(u16),
+    Unwatch(u16),
+    AddWatchpoint(Watchpoint),
+    ClearWatchpoints,
+    SetSpeed(f32),
+    DumpNametable(usize, String),
+    Screenshot(u64, String),
+    Help,
+}
+
+/// Usage for every console command, shown by `help`
+pub const CONSOLE_HELP: &[&str] = &[
+    "break $C123          stop when the CPU reaches an address",
+    "clear                remove all breakpoints",
+    "continue             resume after a breakpoint",
+    "step                 pause and run a single instruction",
+    "next scanline        pause and run to the next scanline, frame or vblank",
+    "runto 241 1          pause and run to a scanline and dot",
+    "watch $00FE          show a memory address in the console",
+    "unwatch $00FE        stop showing a memory address",
+    "wp rw $0300-$03FF    stop on a read (r), write (w) or execute (x) in a range",
+    "wp w $2000 ppu       the same on the PPU bus, where there's no execute",
+    "clearwp              remove all watchpoints",
+    "speed 2.0            set the emulation speed multiplier",
+    "frameskip auto       skip drawing frames when behind, always skip 1-3, or never (off)",
+    "background pause     pause in the background, run slowly (throttle), or keep going (run)",
+    "savestate 3          save to a slot (0-9)",
+    "loadstate 3          load from a slot (0-9)",
+    "palette fceux        switch to a built in palette (nesdev, fceux, sony)",
+    "scale 1.5            scale the interface, from 0.75 to 3.0",
+    "volume 50            set the volume, as a percentage",
+    "latency 80           set how far ahead audio is buffered, in milliseconds",
+    "sync audio           time emulation by the audio played, or by the wall clock (wall_clock)",
+    "mixer accurate       mix audio like the console does, or approximately (fast)",
+    "stereo split         pulses left and triangle and noise right, custom panning, or mono",
+    "pan noise -50        place a channel from -100 (left) to 100 (right)",
+    "mute triangle        mute or unmute an APU channel",
+    "solo pulse1          solo an APU channel, or stop soloing it",
+    "                     (pulse1, pulse2, triangle, noise, dmc, expansion)",
+    "dump nametable 0 file.bin",
+    "                     write a nametable to a file",
+    "screenshot 600 a.png run until a frame is drawn and save it as a PNG",
+    "load                 open a ROM",
+    "testpattern          run the built in test pattern",
+    "demo starfield       play a built in demo (bounce, starfield)",
+    "reset                press the reset button",
+    "power                switch the console off and on again",
+    "quit                 exit the emulator",
+];
+
+/// Parse a line typed into the console
+pub fn parse(line: &str) -> Result<Command, String> {
+    let mut args = line.split_whitespace();
+    let Some(name) = args.next() else {
+        return Err("Empty command".to_string());
+    };
+
+    let command = match name.to_lowercase().as_str() {
+        "break" | "b" => Command::Break(parse_address(args.next())?),
+        "clear" => Command::ClearBreakpoints,
+        "continue" | "c" => Command::Continue,
+        "step" | "s" => Command::RunTo(RunTarget::Instruction),
+        "next" | "n" => {
+            let target = match args.next().map(|target| target.to_lowercase()).as_deref() {
+                Some("scanline") => RunTarget::Scanline,
+                Some("frame") => RunTarget::Frame,
+                Some("vblank") => RunTarget::VBlank,
+                _ => return Err("Usage: next <scanline|frame|vblank>".to_string()),
+            };
+            Command::RunTo(target)
+        },
+        "runto" => {
+            let scanline = args.next()
+                .and_then(|scanline| scanline.parse::<i16>().ok())
+                .filter(|scanline| (-1..=311).contains(scanline))
+                .ok_or("Usage: runto <scanline -1-311> <dot 0-340>")?;
+            let dot = args.next()
+                .and_then(|dot| dot.parse::<u16>().ok())
+                .filter(|dot| *dot <= 340)
+                .ok_or("Usage: runto <scanline -1-311> <dot 0-340>")?;
+            Command::RunTo(RunTarget::Position(scanline, dot))
+        },
+        "watch" | "w" => Command::Watch(parse_address(args.next())?),
+        "unwatch" => Command::Unwatch(parse_address(args.next())?),
+        "watchpoint" | "wp" => Command::AddWatchpoint(parse_watchpoint(args.next(), args.next(), args.next())?),
+        "clearwp" => Command::ClearWatchpoints,
+        "speed" => {
+            let speed = args.next()
+                .and_then(|speed| speed.parse::<f32>().ok())
+                .filter(|speed| *speed > 0.0 && *speed <= 10.0)
+                .ok_or("Usage: speed <multiplier between 0 and 10>")?;
+            Command::SetSpeed(speed)
+        },
+        "frameskip" => {
+            let frame_skip = args.next()
+                .and_then(|name| FrameSkip::ALL.into_iter().find(|skip| skip.key().strip_prefix("frame_skip.") == Some(name)))
+                .ok_or("Usage: frameskip <off|auto|1|2|3>")?;
+            Command::SetFrameSkip(frame_skip)
+        },
+        "background" => {
+            let mode = args.next()
+                .and_then(|name| BackgroundMode::ALL.into_iter().find(|mode| mode.key().strip_prefix("background.") == Some(name)))
+                .ok_or("Usage: background <run|pause|throttle>")?;
+            Command::SetBackgroundMode(mode)
+        },
+        "savestate" => Command::SaveState(parse_slot(args.next())?),
+        "loadstate" => Command::LoadState(parse_slot(args.next())?),
+        "dump" => {
+            if args.next() != Some("nametable") {
+                return Err("Usage: dump nametable <0-1> <file>".to_string());
+            }
+            let index = args.next()
+                .and_then(|index| index.parse::<usize>().ok())
+                .filter(|index| *index < 2)
+                .ok_or("Usage: dump nametable <0-1> <file>")?;
+            let path = args.next().ok_or("Usage: dump nametable <0-1> <file>")?;
+            Command::DumpNametable(index, path.to_string())
+        },
+        "screenshot" => {
+            let frame = args.next()
+                .and_then(|frame| frame.parse::<u64>().ok())
+                .filter(|frame| *frame > 0)
+                .ok_or("Usage: screenshot <frame> <file>")?;
+            let path = args.next().ok_or("Usage: screenshot <frame> <file>")?;
+            Command::Screenshot(frame, path.to_string())
+        },
+        "palette" => {
+            let palette = match args.next().map(|name| name.to_lowercase()).as_deref() {
+                Some("nesdev") => BuiltinPalette::Nesdev,
+                Some("fceux") => BuiltinPalette::Fceux,
+                Some("sony") => BuiltinPalette::SonyCxa,
+                _ => return Err("Usage: palette <nesdev|fceux|sony>".to_string()),
+            };
+            Command::SetPalette(palette)
+        },
+        "scale" => {
+            let scale = args.next()
+                .and_then(|scale| scale.parse::<f32>().ok())
+                .filter(|scale| Config::UI_SCALE_RANGE.contains(scale))
+                .ok_or("Usage: scale <0.75-3.0>")?;
+            Command::SetUiScale(scale)
+        },
+        "volume" => {
+            let volume = args.next()
+                .and_then(|volume| volume.parse::<f32>().ok())
+                .filter(|volume| (0.0..=100.0).contains(volume))
+                .ok_or("Usage: volume <0-100>")?;
+            Command::SetVolume(volume / 100.0)
+        },
+        "latency" => {
+            let latency = args.next()
+                .and_then(|latency| latency.parse::<u32>().ok())
+                .filter(|latency| LATENCY_RANGE_MS.contains(latency))
+                .ok_or(format!("Usage: latency <{}-{}>", LATENCY_RANGE_MS.start(), LATENCY_RANGE_MS.end()))?;
+            Command::SetAudioLatency(latency)
+        },
+        "sync" => {
+            let mode = args.next()
+                .and_then(|name| SyncMode::ALL.into_iter().find(|mode| mode.key().strip_prefix("sync.") == Some(name)))
+                .ok_or("Usage: sync <audio|wall_clock>")?;
+            Command::SetSyncMode(mode)
+        },
+        "mixer" => {
+            let mixer = args.next()
+                .and_then(|name| MixerMode::ALL.into_iter().find(|mode| mode.key().strip_prefix("mixer.") == Some(name)))
+                .ok_or("Usage: mixer <fast|accurate>")?;
+            Command::SetMixer(mixer)
+        },
+        "stereo" => {
+            let mode = args.next()
+                .and_then(|name| StereoMode::ALL.into_iter().find(|mode| mode.key().strip_prefix("stereo.") == Some(name)))
+                .ok_or("Usage: stereo <mono|split|custom>")?;
+            Command::SetStereoMode(mode)
+        },
+        "pan" => {
+            let channel = parse_channel(args.next(), "pan")?;
+            let pan = args.next()
+                .and_then(|pan| pan.parse::<i32>().ok())
+                .filter(|pan| (-100..=100).contains(pan))
+                .ok_or("Usage: pan <channel> <-100-100>")?;
+            Command::SetChannelPan(channel, pan as f32 / 100.0)
+        },
+        "mute" => Command::ToggleChannelMute(parse_channel(args.next(), "mute")?),
+        "solo" => Command::ToggleChannelSolo(parse_channel(args.next(), "solo")?),
+        "load" => Command::LoadRom,
+        "testpattern" => Command::LoadTestPattern,
+        "demo" => {
+            let demo = args.next().and_then(demos::find).ok_or("Usage: demo <bounce|starfield>")?;
+            Command::PlayDemo(demo)
+        },
+        "reset" => Command::Reset,
+        "power" => Command::PowerCycle,
+        "quit" => Command::Quit,
+        "help" | "?" => Command::Help,
+        _ => return Err(format!("Unknown command: {}", name)),
+    };
+
+    if args.next().is_some() {
+        return Err(format!("Too many arguments for {}", name));
+    }
+
+    Ok(command)
+}
+
+/// Addresses can be written as `$C123`, `0xC123` or plain hex
+pub(crate) fn parse_address(arg: Option<&str>) -> Result<u16, String> {
+    let arg = arg.ok_or("Expected an address, like $C123")?;
+    let hex = arg.trim_start_matches('$').trim_start_matches("0x");
+    u16::from_str_radix(hex, 16).map_err(|_| format!("Invalid address: {}", arg))
+}
+
+/// A watchpoint as `<r|w|x...> <address>[-<address>] [cpu|ppu]`
+fn parse_watchpoint(kinds: Option<&str>, range: Option<&str>, space: Option<&str>) -> Result<Watchpoint, String> {
+    const USAGE: &str = "Usage: wp <r|w|x> <address>[-<address>] [cpu|ppu]";
+    let kinds = kinds.filter(|kinds| !kinds.is_empty() && kinds.chars().all(|kind| "rwx".contains(kind))).ok_or(USAGE)?;
+    let range = range.ok_or(USAGE)?;
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (parse_address(Some(start))?, parse_address(Some(end))?),
+        None => (parse_address(Some(range))?, parse_address(Some(range))?),
+    };
+    if end < start {
+        return Err(format!("Invalid range: {}", range));
+    }
+    let space = match space.map(|space| space.to_lowercase()).as_deref() {
+        None | Some("cpu") => AddressSpace::Cpu,
+        Some("ppu") => AddressSpace::Ppu,
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let watchpoint = Watchpoint {
+        space,
+        start,
+        end,
+        read: kinds.contains('r'),
+        write: kinds.contains('w'),
+        execute: kinds.contains('x'),
+    };
+    if space == AddressSpace::Ppu && (watchpoint.execute || end > 0x3FFF) {
+        return Err("PPU watchpoints can only read or write $0000-$3FFF".to_string());
+    }
+    Ok(watchpoint)
+}
+
+fn parse_slot(arg: Option<&str>) -> Result<usize, String> {
+    arg.and_then(|slot| slot.parse::<usize>().ok())
+        .filter(|slot| *slot < SAVE_SLOTS)
+        .ok_or(format!("Expected a slot between 0 and {}", SAVE_SLOTS - 1))
+}
+
+fn parse_channel(arg: Option<&str>, command: &str) -> Result<AudioChannel, String> {
+    arg.and_then(|name| AudioChannel::from_name(&name.to_lowercase()))
+        .ok_or(format!("Usage: {} <pulse1|pulse2|triangle|noise|dmc|expansion>", command))
+}
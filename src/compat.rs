@@ -0,0 +1,163 @@
+//! Compatibility reports: every ROM in a folder run headless for a while, noting which ones load, which
+//! panic and which never draw anything, to see how compatibility changes from one release to the next.
+
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::cartridge::Cartridge;
+use crate::nes::Nes;
+
+/// Frames each ROM's run for if not told otherwise, 10 seconds of play
+pub const COMPAT_FRAMES: u64 = 600;
+
+/// How often the picture's checked for something other than a single colour
+const BLANK_CHECK_INTERVAL: u64 = 30;
+
+/// How one ROM got on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompatResult {
+  pub file: String,
+  /// e.g. "mapper 4, MMC3", or why it couldn't be loaded
+  pub mapper: String,
+  /// Whether it loaded and ran for every frame without panicking
+  pub ran_ok: bool,
+  /// Frames run before it finished or panicked
+  pub frames: u64,
+  pub panic: Option<String>,
+  /// Nothing but a single colour on screen whenever it was checked, which usually means the game's
+  /// stuck before it's turned rendering on
+  pub blank_screen: bool,
+}
+
+/// Load `rom` and run it for `frames` frames, catching any panic along the way
+pub fn check_rom(file: &str, rom: Vec<u8>, frames: u64) -> CompatResult {
+  let mut result = CompatResult {
+    file: file.to_string(),
+    mapper: String::new(),
+    ran_ok: false,
+    frames: 0,
+    panic: None,
+    blank_screen: false,
+  };
+  let cartridge = match catch_unwind(|| Cartridge::from_bytes(rom)) {
+    Ok(Ok(cartridge)) => cartridge,
+    Ok(Err(error)) => {
+      result.mapper = error.to_string();
+      return result;
+    },
+    Err(payload) => {
+      result.panic = Some(panic_message(payload.as_ref()));
+      return result;
+    },
+  };
+  result.mapper = cartridge.mapper_description();
+
+  let mut nes = Nes::new();
+  let mut drawn = false;
+  let ran = catch_unwind(AssertUnwindSafe(|| {
+    nes.insert_cartridge(cartridge);
+    for frame in 1..=frames {
+      nes.run_frame();
+      result.frames = frame;
+      if frame % BLANK_CHECK_INTERVAL == 0 || frame == frames {
+        drawn |= !single_colour(nes.screen());
+      }
+    }
+  }));
+  match ran {
+    Ok(()) => result.ran_ok = true,
+    Err(payload) => result.panic = Some(panic_message(payload.as_ref())),
+  }
+  result.blank_screen = !drawn;
+  result
+}
+
+/// Check every ROM in `folder`, as many at once as there are cores, in order of their filenames
+pub fn scan(folder: &Path, frames: u64) -> std::io::Result<Vec<CompatResult>> {
+  let mut roms = vec![];
+  for file in std::fs::read_dir(folder)? {
+    let path = file?.path();
+    let is_rom = path.extension()
+      .and_then(|extension| extension.to_str())
+      .is_some_and(|extension| ["nes", "fds"].contains(&extension.to_ascii_lowercase().as_str()));
+    if is_rom {
+      roms.push(path);
+    }
+  }
+  roms.sort();
+
+  Ok(roms.par_iter().map(|path| {
+    let file = path.file_name().unwrap_or_default().to_string_lossy();
+    match std::fs::read(path) {
+      Ok(rom) => check_rom(&file, rom, frames),
+      Err(error) => CompatResult {
+        file: file.into_owned(),
+        mapper: format!("Couldn't read it: {}", error),
+        ran_ok: false,
+        frames: 0,
+        panic: None,
+        blank_screen: false,
+      },
+    }
+  }).collect())
+}
+
+/// The results as CSV with a header row
+pub fn to_csv(results: &[CompatResult]) -> String {
+  let mut csv = "file,mapper,ran_ok,frames,panic,blank_screen\n".to_string();
+  for result in results {
+    csv += &format!(
+      "{},{},{},{},{},{}\n",
+      csv_field(&result.file),
+      csv_field(&result.mapper),
+      result.ran_ok,
+      result.frames,
+      csv_field(result.panic.as_deref().unwrap_or_default()),
+      result.blank_screen,
+    );
+  }
+  csv
+}
+
+/// The results as a JSON array, one object per ROM with the same fields as the CSV
+pub fn to_json(results: &[CompatResult]) -> String {
+  let results: Vec<_> = results.iter().map(|result| serde_json::json!({
+    "file": result.file,
+    "mapper": result.mapper,
+    "ran_ok": result.ran_ok,
+    "frames": result.frames,
+    "panic": result.panic,
+    "blank_screen": result.blank_screen,
+  })).collect();
+  serde_json::to_string_pretty(&results).unwrap_or_default()
+}
+
+/// Quoted if it has anything in it that'd otherwise split it up, with quotes inside doubled
+fn csv_field(field: &str) -> String {
+  if field.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
+
+/// Whether every pixel of a packed RGB frame is the same colour
+fn single_colour(frame: &[u8]) -> bool {
+  let mut pixels = frame.chunks_exact(3);
+  let first = pixels.next();
+  pixels.all(|pixel| Some(pixel) == first)
+}
+
+/// What a panic said, which is almost always a string of some sort
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.clone()
+  } else {
+    "panicked without a message".to_string()
+  }
+}
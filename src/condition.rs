@@ -0,0 +1,148 @@
+use std::fmt;
+
+/// Tells conditions apart, handed out by `Conditions::watch`
+pub type ConditionId = u32;
+
+/// The test a condition made from the UI puts a byte through each frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Comparison {
+  /// Equal to the value
+  #[default]
+  Equal,
+  NotEqual,
+  Less,
+  Greater,
+  /// Different from last frame, ignoring the value
+  Changed,
+  /// Higher than last frame
+  Increased,
+  /// Lower than last frame
+  Decreased,
+}
+
+impl Comparison {
+  pub const ALL: [Comparison; 7] = [
+    Comparison::Equal,
+    Comparison::NotEqual,
+    Comparison::Less,
+    Comparison::Greater,
+    Comparison::Changed,
+    Comparison::Increased,
+    Comparison::Decreased,
+  ];
+
+  /// Key for the UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      Comparison::Equal => "condition.equal",
+      Comparison::NotEqual => "condition.not_equal",
+      Comparison::Less => "condition.less",
+      Comparison::Greater => "condition.greater",
+      Comparison::Changed => "condition.changed",
+      Comparison::Increased => "condition.increased",
+      Comparison::Decreased => "condition.decreased",
+    }
+  }
+
+  /// Whether the comparison looks at `value`, rather than just last frame's byte
+  pub fn uses_value(&self) -> bool {
+    matches!(self, Comparison::Equal | Comparison::NotEqual | Comparison::Less | Comparison::Greater)
+  }
+
+  pub fn test(&self, value: u8, previous: u8, current: u8) -> bool {
+    match self {
+      Comparison::Equal => current == value,
+      Comparison::NotEqual => current != value,
+      Comparison::Less => current < value,
+      Comparison::Greater => current > value,
+      Comparison::Changed => current != previous,
+      Comparison::Increased => current > previous,
+      Comparison::Decreased => current < previous,
+    }
+  }
+}
+
+/// A condition fired: the byte at `address` went from `previous` to `current` over the frame, and the
+/// predicate holds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConditionEvent {
+  pub id: ConditionId,
+  pub address: u16,
+  pub previous: u8,
+  pub current: u8,
+  /// The frame it fired at the end of, counted as `Nes::frame_count` does
+  pub frame: u64,
+}
+
+impl fmt::Display for ConditionEvent {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "${:04X}: ${:02X} -> ${:02X} at frame {}", self.address, self.previous, self.current, self.frame)
+  }
+}
+
+/// Given the byte's value last frame and now, whether the condition holds
+pub type Predicate = Box<dyn Fn(u8, u8) -> bool + Send>;
+
+struct Condition {
+  id: ConditionId,
+  address: u16,
+  predicate: Predicate,
+  /// The byte as it was at the end of the last frame
+  previous: u8,
+  /// Whether the predicate held last frame, so it doesn't fire again while nothing changes
+  held: bool,
+}
+
+/// Conditions on CPU memory checked at the end of every frame, as achievement systems do. Each fires an
+/// event on the frame its predicate becomes true, and again on any frame the byte changes while it
+/// still holds. Nothing's written, so the game can't tell.
+#[derive(Default)]
+pub struct Conditions {
+  conditions: Vec<Condition>,
+  next_id: ConditionId,
+  events: Vec<ConditionEvent>,
+}
+
+impl Conditions {
+  /// Start checking `predicate` against the byte at `address`, which is `current` now. It can't fire
+  /// until the end of the next frame.
+  pub fn watch(&mut self, address: u16, current: u8, predicate: Predicate) -> ConditionId {
+    let id = self.next_id;
+    self.next_id += 1;
+    let held = predicate(current, current);
+    self.conditions.push(Condition { id, address, predicate, previous: current, held });
+    id
+  }
+
+  pub fn unwatch(&mut self, id: ConditionId) {
+    self.conditions.retain(|condition| condition.id != id);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.conditions.is_empty()
+  }
+
+  /// Check every condition against memory as it is at the end of `frame`, read through `peek`
+  pub fn check(&mut self, frame: u64, peek: impl Fn(u16) -> u8) {
+    for condition in &mut self.conditions {
+      let current = peek(condition.address);
+      let holds = (condition.predicate)(condition.previous, current);
+      if holds && (!condition.held || current != condition.previous) {
+        self.events.push(ConditionEvent {
+          id: condition.id,
+          address: condition.address,
+          previous: condition.previous,
+          current,
+          frame,
+        });
+      }
+      condition.previous = current;
+      condition.held = holds;
+    }
+  }
+
+  /// The events fired since the last call, oldest first
+  pub fn take_events(&mut self) -> Vec<ConditionEvent> {
+    std::mem::take(&mut self.events)
+  }
+}
@@ -0,0 +1,137 @@
+use eframe::egui;
+
+use crate::command::parse_address;
+use crate::condition::{Comparison, ConditionEvent, ConditionId};
+use crate::i18n::tr;
+use crate::nes::Nes;
+
+/// A condition added from the window, kept to list it and to name it when it fires
+struct Entry {
+    id: ConditionId,
+    label: String,
+    address: u16,
+    comparison: Comparison,
+    value: u8,
+    hits: u32,
+}
+
+impl Entry {
+    /// The label, or the test itself if it hasn't got one
+    fn name(&self) -> String {
+        if !self.label.is_empty() {
+            return self.label.clone();
+        }
+        if self.comparison.uses_value() {
+            format!("${:04X} {} ${:02X}", self.address, tr(self.comparison.key()), self.value)
+        } else {
+            format!("${:04X} {}", self.address, tr(self.comparison.key()))
+        }
+    }
+}
+
+/// Window for setting up conditions on memory that pop up a toast when they're met, e.g. for practising
+/// a trick until a flag's set, or trying out achievement logic
+#[derive(Default)]
+pub struct ConditionWindow {
+    pub open: bool,
+    entries: Vec<Entry>,
+    label: String,
+    address: String,
+    comparison: Comparison,
+    value: String,
+    error: Option<String>,
+}
+
+impl ConditionWindow {
+    /// Draw the window, if open. Adding and removing conditions changes what the console checks.
+    pub fn show(&mut self, ctx: &egui::Context, nes: &mut Nes) {
+        let mut open = self.open;
+
+        egui::Window::new(tr("conditions.title"))
+            .id(egui::Id::new("condition_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let mut removed = None;
+                egui::Grid::new("condition_list").striped(true).show(ui, |ui| {
+                    for (i, entry) in self.entries.iter().enumerate() {
+                        ui.label(entry.name());
+                        ui.monospace(format!("${:02X}", nes.peek(entry.address)));
+                        ui.label(format!("{}: {}", tr("conditions.hits"), entry.hits));
+                        if ui.button(tr("conditions.remove")).clicked() {
+                            removed = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = removed {
+                    let entry = self.entries.remove(i);
+                    nes.unwatch(entry.id);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.address)
+                        .hint_text("$0000")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(50.0));
+                    egui::ComboBox::from_id_source("condition_comparison")
+                        .selected_text(tr(self.comparison.key()))
+                        .show_ui(ui, |ui| {
+                            for comparison in Comparison::ALL {
+                                ui.selectable_value(&mut self.comparison, comparison, tr(comparison.key()));
+                            }
+                        });
+                    ui.add_enabled(self.comparison.uses_value(), egui::TextEdit::singleline(&mut self.value)
+                        .hint_text("$00")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(30.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.label)
+                        .hint_text(tr("conditions.label")));
+                    if ui.button(tr("conditions.add")).clicked() {
+                        match self.add(nes) {
+                            Ok(()) => self.error = None,
+                            Err(error) => self.error = Some(error),
+                        }
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+
+        self.open = open;
+    }
+
+    /// Start the console checking the condition typed in
+    fn add(&mut self, nes: &mut Nes) -> Result<(), String> {
+        let address = parse_address(Some(self.address.trim()))?;
+        let comparison = self.comparison;
+        let value = if comparison.uses_value() { parse_value(self.value.trim())? } else { 0 };
+        let id = nes.watch(address, move |previous, current| comparison.test(value, previous, current));
+        self.entries.push(Entry { id, label: std::mem::take(&mut self.label), address, comparison, value, hits: 0 });
+        Ok(())
+    }
+
+    /// Messages to show for the window's conditions that fired in `events`, counting each hit.
+    /// Conditions set up elsewhere are left alone.
+    pub fn fired(&mut self, events: &[ConditionEvent]) -> Vec<String> {
+        events.iter()
+            .filter_map(|event| {
+                let entry = self.entries.iter_mut().find(|entry| entry.id == event.id)?;
+                entry.hits += 1;
+                Some(format!("{}: {}", tr("conditions.met"), entry.name()))
+            })
+            .collect()
+    }
+}
+
+/// Values can be written in decimal, or hex as `$1F` or `0x1F`
+pub(crate) fn parse_value(text: &str) -> Result<u8, String> {
+    let parsed = match text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("Invalid value: {}", text))
+}
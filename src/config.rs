@@ -0,0 +1,318 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::apu::{AudioChannel, ChannelPan, MixerMode, StereoMode};
+use crate::apu_output::{AudioDriver, SyncMode, DEFAULT_LATENCY_MS, LATENCY_RANGE_MS};
+use crate::bus::{PowerOnState, RamFill};
+use crate::frame_advance::BackgroundMode;
+use crate::frame_skip::FrameSkip;
+use crate::hotkeys::Hotkeys;
+use crate::i18n::Language;
+use crate::input::InputBindings;
+use crate::palette::{BuiltinPalette, Palette};
+use crate::video::ColorVision;
+
+/// User preferences that persist between sessions.
+///
+/// Stored in a [`SettingsFile`] in the platform's config directory on desktop, and through eframe's
+/// storage, which is localStorage, on the web. Both frontends save it as soon as it changes, rather
+/// than waiting for eframe's own autosave.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub language: Language,
+    pub ui_scale: f32,
+    pub color_vision: ColorVision,
+    pub palette: BuiltinPalette,
+    /// A palette loaded from a .pal file, used instead of the built in one when set
+    pub custom_palette: Option<Palette>,
+    /// Crop the rows TVs cut off at the top and bottom of the picture
+    pub hide_overscan: bool,
+    /// Average each frame with the one before it, for games that flicker sprites at 30 Hz
+    pub flicker_blend: bool,
+    /// Which frames are left undrawn to keep up on slow machines
+    pub frame_skip: FrameSkip,
+    pub hotkeys: Hotkeys,
+    pub input: InputBindings,
+    /// From 0 to 1
+    pub volume: f32,
+    pub audio_latency_ms: u32,
+    pub audio_driver: AudioDriver,
+    pub sync_mode: SyncMode,
+    /// Play the triangle at ultrasonic periods as hardware does, pops and all
+    pub ultrasonic_triangle: bool,
+    /// Don't restart the pulses' duty cycles on every period write, as hardware does, to save the clicks
+    pub reduce_pulse_popping: bool,
+    pub mixer: MixerMode,
+    pub stereo: StereoMode,
+    /// Where each channel sits in `StereoMode::Custom`
+    pub channel_pan: ChannelPan,
+    pub power_on: PowerOnState,
+    /// What emulation does while the window doesn't have focus
+    pub background: BackgroundMode,
+    /// Where the ROM dialog opens, the folder the last ROM was loaded from. The web build never has one.
+    pub rom_directory: Option<PathBuf>,
+    /// Whether the debug console's open
+    pub console_open: bool,
+    /// Memory addresses the debug console shows
+    pub watches: Vec<u16>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            language: Language::English,
+            ui_scale: 1.0,
+            color_vision: ColorVision::Normal,
+            palette: BuiltinPalette::default(),
+            custom_palette: None,
+            hide_overscan: false,
+            flicker_blend: false,
+            frame_skip: FrameSkip::default(),
+            hotkeys: Hotkeys::default(),
+            input: InputBindings::default(),
+            volume: 1.0,
+            audio_latency_ms: DEFAULT_LATENCY_MS,
+            audio_driver: AudioDriver::default(),
+            sync_mode: SyncMode::default(),
+            ultrasonic_triangle: false,
+            reduce_pulse_popping: false,
+            mixer: MixerMode::default(),
+            stereo: StereoMode::default(),
+            channel_pan: ChannelPan::default(),
+            power_on: PowerOnState::default(),
+            background: BackgroundMode::default(),
+            rom_directory: None,
+            console_open: false,
+            watches: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.75..=3.0;
+
+    /// Load the config from storage, falling back to defaults for anything missing or unreadable
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut config = Config::default();
+        let Some(storage) = storage else {
+            return config;
+        };
+
+        if let Some(language) = storage.get_string("language").and_then(|code| Language::from_code(&code)) {
+            config.language = language;
+        }
+        if let Some(ui_scale) = storage.get_string("ui_scale").and_then(|scale| scale.parse::<f32>().ok()) {
+            config.ui_scale = ui_scale.clamp(*Self::UI_SCALE_RANGE.start(), *Self::UI_SCALE_RANGE.end());
+        }
+        if let Some(color_vision) = storage.get_string("color_vision").and_then(|key| ColorVision::from_key(&key)) {
+            config.color_vision = color_vision;
+        }
+        if let Some(palette) = storage.get_string("palette").and_then(|key| BuiltinPalette::from_key(&key)) {
+            config.palette = palette;
+        }
+        // Custom palettes are stored as hex so they survive without the original file, which the web build never has a path to
+        if let Some(custom_palette) = storage.get_string("custom_palette").and_then(|hex| decode_hex(&hex)) {
+            config.custom_palette = Palette::from_pal_bytes(&custom_palette).ok();
+        }
+        if let Some(hide_overscan) = storage.get_string("hide_overscan").and_then(|value| value.parse::<bool>().ok()) {
+            config.hide_overscan = hide_overscan;
+        }
+        if let Some(flicker_blend) = storage.get_string("flicker_blend").and_then(|value| value.parse::<bool>().ok()) {
+            config.flicker_blend = flicker_blend;
+        }
+        if let Some(frame_skip) = storage.get_string("frame_skip").and_then(|key| FrameSkip::from_key(&key)) {
+            config.frame_skip = frame_skip;
+        }
+        config.hotkeys = Hotkeys::load(storage);
+        config.input = InputBindings::load(storage);
+        if let Some(volume) = storage.get_string("volume").and_then(|volume| volume.parse::<f32>().ok()) {
+            config.volume = volume.clamp(0.0, 1.0);
+        }
+        if let Some(latency) = storage.get_string("audio_latency").and_then(|latency| latency.parse::<u32>().ok()) {
+            config.audio_latency_ms = latency.clamp(*LATENCY_RANGE_MS.start(), *LATENCY_RANGE_MS.end());
+        }
+        if let Some(driver) = storage.get_string("audio_driver").and_then(|key| AudioDriver::from_key(&key)) {
+            config.audio_driver = driver;
+        }
+        if let Some(sync_mode) = storage.get_string("sync_mode").and_then(|key| SyncMode::from_key(&key)) {
+            config.sync_mode = sync_mode;
+        }
+        if let Some(ultrasonic) = storage.get_string("ultrasonic_triangle").and_then(|value| value.parse::<bool>().ok()) {
+            config.ultrasonic_triangle = ultrasonic;
+        }
+        if let Some(reduce) = storage.get_string("reduce_pulse_popping").and_then(|value| value.parse::<bool>().ok()) {
+            config.reduce_pulse_popping = reduce;
+        }
+        if let Some(mixer) = storage.get_string("mixer").and_then(|key| MixerMode::from_key(&key)) {
+            config.mixer = mixer;
+        }
+        if let Some(stereo) = storage.get_string("stereo").and_then(|key| StereoMode::from_key(&key)) {
+            config.stereo = stereo;
+        }
+        for channel in AudioChannel::ALL {
+            if let Some(pan) = storage.get_string(&format!("pan_{}", channel.name())).and_then(|pan| pan.parse::<f32>().ok()) {
+                config.channel_pan.set_pan(channel, pan);
+            }
+        }
+        let seed = storage.get_string("ram_seed").and_then(|seed| seed.parse::<u64>().ok()).unwrap_or(0);
+        if let Some(ram) = storage.get_string("ram_fill").and_then(|key| RamFill::from_key(&key, seed)) {
+            config.power_on.ram = ram;
+        }
+        if let Some(open_bus) = storage.get_string("power_on_open_bus").and_then(|value| value.parse::<u8>().ok()) {
+            config.power_on.open_bus = open_bus;
+        }
+        if let Some(frame_counter) = storage.get_string("power_on_frame_counter").and_then(|value| value.parse::<u8>().ok()) {
+            config.power_on.frame_counter = frame_counter & 0xC0;
+        }
+        if let Some(background) = storage.get_string("background").and_then(|key| BackgroundMode::from_key(&key)) {
+            config.background = background;
+        }
+        config.rom_directory = storage.get_string("rom_directory").filter(|path| !path.is_empty()).map(PathBuf::from);
+        if let Some(console_open) = storage.get_string("console_open").and_then(|value| value.parse::<bool>().ok()) {
+            config.console_open = console_open;
+        }
+        if let Some(watches) = storage.get_string("watches") {
+            config.watches = watches.split(',').filter_map(|address| u16::from_str_radix(address, 16).ok()).collect();
+        }
+
+        config
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        storage.set_string("language", self.language.code().to_string());
+        storage.set_string("ui_scale", self.ui_scale.to_string());
+        storage.set_string("color_vision", self.color_vision.key().to_string());
+        storage.set_string("palette", self.palette.key().to_string());
+        let custom_palette = self.custom_palette.map(|palette| encode_hex(&palette.to_pal_bytes()));
+        storage.set_string("custom_palette", custom_palette.unwrap_or_default());
+        storage.set_string("hide_overscan", self.hide_overscan.to_string());
+        storage.set_string("flicker_blend", self.flicker_blend.to_string());
+        storage.set_string("frame_skip", self.frame_skip.key().to_string());
+        self.hotkeys.save(storage);
+        self.input.save(storage);
+        storage.set_string("volume", self.volume.to_string());
+        storage.set_string("audio_latency", self.audio_latency_ms.to_string());
+        storage.set_string("audio_driver", self.audio_driver.key().to_string());
+        storage.set_string("sync_mode", self.sync_mode.key().to_string());
+        storage.set_string("ultrasonic_triangle", self.ultrasonic_triangle.to_string());
+        storage.set_string("reduce_pulse_popping", self.reduce_pulse_popping.to_string());
+        storage.set_string("mixer", self.mixer.key().to_string());
+        storage.set_string("stereo", self.stereo.key().to_string());
+        for channel in AudioChannel::ALL {
+            storage.set_string(&format!("pan_{}", channel.name()), self.channel_pan.pan(channel).to_string());
+        }
+        storage.set_string("ram_fill", self.power_on.ram.key().to_string());
+        if let RamFill::Random(seed) = self.power_on.ram {
+            storage.set_string("ram_seed", seed.to_string());
+        }
+        storage.set_string("power_on_open_bus", self.power_on.open_bus.to_string());
+        storage.set_string("power_on_frame_counter", self.power_on.frame_counter.to_string());
+        storage.set_string("background", self.background.key().to_string());
+        let rom_directory = self.rom_directory.as_ref().map(|path| path.to_string_lossy().into_owned());
+        storage.set_string("rom_directory", rom_directory.unwrap_or_default());
+        storage.set_string("console_open", self.console_open.to_string());
+        let watches: Vec<String> = self.watches.iter().map(|address| format!("{:04X}", address)).collect();
+        storage.set_string("watches", watches.join(","));
+    }
+
+    /// Save to `storage` straight away if anything's changed since `saved`, which is then brought up to
+    /// date, so nothing's lost if the emulator doesn't get to exit cleanly
+    pub fn save_if_changed(&self, saved: &mut Config, storage: &mut dyn eframe::Storage) {
+        if self == saved {
+            return;
+        }
+        self.save(storage);
+        storage.flush();
+        *saved = self.clone();
+    }
+
+    /// Where each channel actually goes, given the stereo mode
+    pub fn active_pan(&self) -> ChannelPan {
+        self.stereo.pan(self.channel_pan)
+    }
+
+    /// The palette the picture should be drawn with
+    pub fn active_palette(&self) -> Palette {
+        self.custom_palette.unwrap_or_else(|| self.palette.palette())
+    }
+}
+
+/// The desktop build's settings, kept as JSON in `settings.json` in the platform's config directory,
+/// e.g. `~/.config/silknes` on Linux, where people expect to find them rather than among eframe's
+/// window state
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+pub struct SettingsFile {
+    /// `None` when the platform has no config directory, in which case nothing's ever written
+    path: Option<PathBuf>,
+    values: BTreeMap<String, String>,
+    /// Whether there was a file to read, as opposed to this being the first run
+    existed: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SettingsFile {
+    /// The settings file in the platform's config directory
+    pub fn open() -> Self {
+        match directories_next::ProjectDirs::from("", "", "SilkNES") {
+            Some(dirs) => Self::at(dirs.config_dir().join("settings.json")),
+            None => Self::default(),
+        }
+    }
+
+    /// The settings file at `path`, empty if there isn't one there or it can't be read
+    pub fn at(path: PathBuf) -> Self {
+        let (values, existed) = match std::fs::read_to_string(&path) {
+            Ok(json) => (serde_json::from_str(&json).unwrap_or_else(|error| {
+                log::warn!("Ignoring unreadable settings in {}: {}", path.display(), error);
+                BTreeMap::new()
+            }), true),
+            Err(_) => (BTreeMap::new(), false),
+        };
+        Self { path: Some(path), values, existed }
+    }
+
+    /// Whether the file was there when opened. Until it is, the settings are still in eframe's storage
+    /// where older versions kept them.
+    pub fn existed(&self) -> bool {
+        self.existed
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl eframe::Storage for SettingsFile {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        self.values.insert(key.to_string(), value);
+    }
+
+    fn flush(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let json = serde_json::to_string_pretty(&self.values).expect("a map of strings always serializes");
+        let written = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(path, json));
+        if let Err(error) = written {
+            log::warn!("Couldn't save settings to {}: {}", path.display(), error);
+        }
+    }
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Bytes from lowercase or uppercase hex, or `None` if it isn't hex
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect()
+}
@@ -0,0 +1,100 @@
+use eframe::egui;
+use egui::Key;
+
+use crate::command::{self, Command};
+use crate::nes::Nes;
+
+/// How many lines of output the console keeps around
+const MAX_LOG_LINES: usize = 200;
+
+/// A quake-style drop-down console for typing commands instead of digging through menus
+pub struct Console {
+    pub open: bool,
+    input: String,
+    log: Vec<String>,
+    /// Memory addresses shown at the top of the console, refreshed every frame
+    pub watches: Vec<u16>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            log: vec!["Type `help` for a list of commands".to_string()],
+            watches: vec![],
+        }
+    }
+
+    pub fn log(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+        if self.log.len() > MAX_LOG_LINES {
+            self.log.remove(0);
+        }
+    }
+
+    /// Handle the toggle key, and if the console is open draw it and return the command the user entered this frame.
+    /// `nes` is only read from, to show the current value of each watched address.
+    pub fn show(&mut self, ctx: &egui::Context, nes: &Nes) -> Option<Command> {
+        // Eat the toggle key so it doesn't end up typed into the input
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, Key::Backtick) || i.consume_key(egui::Modifiers::SHIFT, Key::Backtick)) {
+            return Some(Command::ToggleConsole);
+        }
+
+        if !self.open {
+            return None;
+        }
+
+        let mut command = None;
+        egui::TopBottomPanel::top("console")
+            .resizable(true)
+            .default_height(200.0)
+            .show(ctx, |ui| {
+                if !self.watches.is_empty() {
+                    ui.horizontal_wrapped(|ui| {
+                        for address in &self.watches {
+                            ui.monospace(format!("{} = ${:02X}", nes.describe_address(*address), nes.peek(*address)));
+                        }
+                    });
+                    ui.separator();
+                }
+
+                let input_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y;
+                egui::ScrollArea::vertical()
+                    .max_height(ui.available_height() - input_height)
+                    .stick_to_bottom(true)
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for line in &self.log {
+                            ui.monospace(line);
+                        }
+                    });
+
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.input)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY),
+                );
+                if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                    let line = std::mem::take(&mut self.input);
+                    if !line.trim().is_empty() {
+                        self.log(format!("> {}", line));
+                        match command::parse(&line) {
+                            Ok(parsed) => command = Some(parsed),
+                            Err(error) => self.log(error),
+                        }
+                    }
+                }
+                // Keep typing without having to click back into the input after each command
+                response.request_focus();
+            });
+
+        command
+    }
+}
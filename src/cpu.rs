@@ -1,6 +1,4 @@
 use crate::bus::BusLike;
-use std::cell::RefCell;
-use std::rc::Rc;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum AddressingMode {
@@ -18,7 +16,15 @@ pub enum AddressingMode {
   IndirectIndexed,
 }
 
-#[derive(Default)]
+/// Told about every instruction the CPU runs, for debugging tools that follow execution like the code
+/// profiler. Interrupts aren't instructions, so they don't count.
+pub trait ExecutionHook {
+  /// The instruction at `pc` has just run, taking `cycles` counting any page crossing or taken branch
+  fn executed(&mut self, pc: u16, opcode: u8, cycles: usize);
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Flags {
   /// The carry flag is set if the last operation caused an overflow
   /// from bit 7 of the result or an underflow from bit 0.
@@ -28,12 +34,10 @@ pub struct Flags {
   /// The interrupt disable flag is set if the program has executed
   /// a 'Set Interrupt Disable' (SEI) instruction.
   pub interrupt_disable: bool,
-  /// While the decimal mode flag is set the processor will obey the rules of
-  /// Binary Coded Decimal (BCD) arithmetic during addition and subtraction.
+  /// On a stock 6502, while the decimal mode flag is set the processor will obey the rules of
+  /// Binary Coded Decimal (BCD) arithmetic during addition and subtraction. The 2A03 has the
+  /// flag, but its BCD circuitry was cut, so ADC and SBC ignore it.
   pub decimal_mode: bool,
-  /// The break command bit is set when a BRK instruction has been executed
-  /// and an interrupt has been generated to process it.
-  pub break_command: bool,
   /// The overflow flag is set during arithmetic operations if the result has yielded an invalid 2's complement result
   /// (e.g. adding to positive numbers and ending up with a negative result: 64 + 64 => -128).
   pub overflow: bool,
@@ -42,30 +46,39 @@ pub struct Flags {
 }
 
 impl Flags {
+  /// The flags as a byte. Bits 4 and 5 aren't flags at all, so they read as they do when an interrupt
+  /// pushes them, B clear and bit 5 set.
   pub fn to_u8(&self) -> u8 {
-    (self.carry as u8) << 0 |
+    (self.carry as u8) |
     (self.zero as u8) << 1 |
     (self.interrupt_disable as u8) << 2 |
     (self.decimal_mode as u8) << 3 |
-    (self.break_command as u8) << 4 |
     1 << 5 |
     (self.overflow as u8) << 6 |
     (self.negative as u8) << 7
   }
 
+  /// The flags as pushed to the stack. The B bit only exists here, set when BRK or PHP pushed them
+  /// and clear for an IRQ or NMI, so a handler can tell which it's running for.
+  pub fn to_stack_byte(&self, from_instruction: bool) -> u8 {
+    self.to_u8() | (from_instruction as u8) << 4
+  }
+
+  /// Flags pulled by PLP or RTI, or set up by a test. Bits 4 and 5 are ignored.
   pub fn from_u8(byte: u8) -> Self {
     Self {
       carry: (byte & (1 << 0)) != 0,
       zero: (byte & (1 << 1)) != 0,
       interrupt_disable: (byte & (1 << 2)) != 0,
       decimal_mode: (byte & (1 << 3)) != 0,
-      break_command: (byte & (1 << 4)) != 0,
       overflow: (byte & (1 << 6)) != 0,
       negative: (byte & (1 << 7)) != 0,
     }
   }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NES6502 {
   pub a: u8,
   pub x: u8,
@@ -74,11 +87,30 @@ pub struct NES6502 {
   pub pc: u16,
   pub flags: Flags,
   pub cycles: usize,
-  pub bus: Option<Rc<RefCell<Box<dyn BusLike>>>>,
   pub fetched_data: u8,
   pub current_address_abs: u16,
   pub current_address_rel: u16,
   pub total_cycles: u32,
+  /// The NMI line as of the end of the last cycle, to spot it being asserted
+  nmi_line: bool,
+  /// An NMI edge has arrived but not been polled yet
+  nmi_detected: bool,
+  /// An NMI edge caused by the current instruction's own access, which happens in its last cycle, so
+  /// after this instruction's poll
+  nmi_late: bool,
+  /// Interrupts found by the last poll, taken instead of the next instruction
+  nmi_pending: bool,
+  irq_pending: bool,
+  /// CLI, SEI and PLP change the I flag after the interrupt poll, so the poll for them sees the old value
+  poll_interrupt_disable: Option<bool>,
+  /// The vector a BRK or interrupt will read in its last two cycles. An NMI arriving before then takes it over.
+  vector: Option<u16>,
+}
+
+impl Default for NES6502 {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl NES6502 {
@@ -91,261 +123,313 @@ impl NES6502 {
       pc: 0,
       flags: Default::default(),
       cycles: 0,
-      bus: None,
       fetched_data: 0,
       current_address_abs: 0,
       current_address_rel: 0,
       total_cycles: 0,
+      nmi_line: false,
+      nmi_detected: false,
+      nmi_late: false,
+      nmi_pending: false,
+      irq_pending: false,
+      poll_interrupt_disable: None,
+      vector: None,
     }
   }
 
-  pub fn connect_to_bus(&mut self, bus: Rc<RefCell<Box<dyn BusLike>>>) {
-    self.bus = Some(bus);
+  /// Run a cycle, reaching memory and the interrupt lines through `bus`
+  pub fn step(&mut self, bus: &mut dyn BusLike) {
+    self.step_hooked(bus, None);
   }
 
-  pub fn step(&mut self) {
+  /// Run a cycle like `step`, telling `hook` about the instruction if one starts on it
+  pub fn step_hooked(&mut self, bus: &mut dyn BusLike, hook: Option<&mut dyn ExecutionHook>) {
     self.total_cycles += 1;
+    let nmi_line_before = self.sample_nmi_line(bus);
+    let mut executed = false;
     if self.cycles == 0 {
-      let opcode = self.read(self.pc);
-      //println!("PC: {:#04X}, opcode: {:02X}", self.pc, opcode);
-      self.pc = self.pc.wrapping_add(1);
-
-      match opcode {
-        // ADC
-        0x69 => self.adc(AddressingMode::Immediate, 2),
-        0x65 => self.adc(AddressingMode::ZeroPage, 3),
-        0x75 => self.adc(AddressingMode::ZeroPageX, 4),
-        0x6D => self.adc(AddressingMode::Absolute, 4),
-        0x7D => self.adc(AddressingMode::AbsoluteX, 4),
-        0x79 => self.adc(AddressingMode::AbsoluteY, 4),
-        0x61 => self.adc(AddressingMode::IndexedIndirect, 6),
-        0x71 => self.adc(AddressingMode::IndirectIndexed, 5),
-        // AND
-        0x29 => self.and(AddressingMode::Immediate, 2),
-        0x25 => self.and(AddressingMode::ZeroPage, 3),
-        0x35 => self.and(AddressingMode::ZeroPageX, 4),
-        0x2D => self.and(AddressingMode::Absolute, 4),
-        0x3D => self.and(AddressingMode::AbsoluteX, 4),
-        0x39 => self.and(AddressingMode::AbsoluteY, 4),
-        0x21 => self.and(AddressingMode::IndexedIndirect, 6),
-        0x31 => self.and(AddressingMode::IndirectIndexed, 5),
-        // ASL
-        0x0A => self.asl(AddressingMode::Implied, 2),
-        0x06 => self.asl(AddressingMode::ZeroPage, 5),
-        0x16 => self.asl(AddressingMode::ZeroPageX, 6),
-        0x0E => self.asl(AddressingMode::Absolute, 6),
-        0x1E => self.asl(AddressingMode::AbsoluteX, 7),
-        // BCC
-        0x90 => self.bcc(AddressingMode::Relative, 2),
-        // BCS
-        0xB0 => self.bcs(AddressingMode::Relative, 2),
-        // BEQ
-        0xF0 => self.beq(AddressingMode::Relative, 2),
-        // BIT
-        0x24 => self.bit(AddressingMode::ZeroPage, 3),
-        0x2C => self.bit(AddressingMode::Absolute, 4),
-        // BMI
-        0x30 => self.bmi(AddressingMode::Relative, 2),
-        // BNE
-        0xD0 => self.bne(AddressingMode::Relative, 2),
-        // BPL
-        0x10 => self.bpl(AddressingMode::Relative, 2),
-        // BRK
-        0x00 => self.brk(AddressingMode::Implied, 7),
-        // BVC
-        0x50 => self.bvc(AddressingMode::Relative, 2),
-        // BVS
-        0x70 => self.bvs(AddressingMode::Relative, 2),
-        // CLC
-        0x18 => self.clc(AddressingMode::Implied, 2),
-        // CLD
-        0xD8 => self.cld(AddressingMode::Implied, 2),
-        // CLI
-        0x58 => self.cli(AddressingMode::Implied, 2),
-        // CLV
-        0xB8 => self.clv(AddressingMode::Implied, 2),
-        // CMP
-        0xC9 => self.cmp(AddressingMode::Immediate, 2),
-        0xC5 => self.cmp(AddressingMode::ZeroPage, 3),
-        0xD5 => self.cmp(AddressingMode::ZeroPageX, 4),
-        0xCD => self.cmp(AddressingMode::Absolute, 4),
-        0xDD => self.cmp(AddressingMode::AbsoluteX, 4),
-        0xD9 => self.cmp(AddressingMode::AbsoluteY, 4),
-        0xC1 => self.cmp(AddressingMode::IndexedIndirect, 6),
-        0xD1 => self.cmp(AddressingMode::IndirectIndexed, 5),
-        // CPX
-        0xE0 => self.cpx(AddressingMode::Immediate, 2),
-        0xE4 => self.cpx(AddressingMode::ZeroPage, 3),
-        0xEC => self.cpx(AddressingMode::Absolute, 4),
-        // CPY
-        0xC0 => self.cpy(AddressingMode::Immediate, 2),
-        0xC4 => self.cpy(AddressingMode::ZeroPage, 3),
-        0xCC => self.cpy(AddressingMode::Absolute, 4),
-        // DEC
-        0xC6 => self.dec(AddressingMode::ZeroPage, 5),
-        0xD6 => self.dec(AddressingMode::ZeroPageX, 6),
-        0xCE => self.dec(AddressingMode::Absolute, 6),
-        0xDE => self.dec(AddressingMode::AbsoluteX, 7),
-        // DEX
-        0xCA => self.dex(AddressingMode::Implied, 2),
-        // DEY
-        0x88 => self.dey(AddressingMode::Implied, 2),
-        // EOR
-        0x49 => self.eor(AddressingMode::Immediate, 2),
-        0x45 => self.eor(AddressingMode::ZeroPage, 3),
-        0x55 => self.eor(AddressingMode::ZeroPageX, 4),
-        0x4D => self.eor(AddressingMode::Absolute, 4),
-        0x5D => self.eor(AddressingMode::AbsoluteX, 4),
-        0x59 => self.eor(AddressingMode::AbsoluteY, 4),
-        0x41 => self.eor(AddressingMode::IndexedIndirect, 6),
-        0x51 => self.eor(AddressingMode::IndirectIndexed, 5),
-        // INC
-        0xE6 => self.inc(AddressingMode::ZeroPage, 5),
-        0xF6 => self.inc(AddressingMode::ZeroPageX, 6),
-        0xEE => self.inc(AddressingMode::Absolute, 6),
-        0xFE => self.inc(AddressingMode::AbsoluteX, 7),
-        // INX
-        0xE8 => self.inx(AddressingMode::Implied, 2),
-        // INY
-        0xC8 => self.iny(AddressingMode::Implied, 2),
-        // JMP
-        0x4C => self.jmp(AddressingMode::Absolute, 3),
-        0x6C => self.jmp(AddressingMode::Indirect, 5),
-        // JSR
-        0x20 => self.jsr(AddressingMode::Absolute, 6),
-        // LDA
-        0xA9 => self.lda(AddressingMode::Immediate, 2),
-        0xA5 => self.lda(AddressingMode::ZeroPage, 3),
-        0xB5 => self.lda(AddressingMode::ZeroPageX, 4),
-        0xAD => self.lda(AddressingMode::Absolute, 4),
-        0xBD => self.lda(AddressingMode::AbsoluteX, 4),
-        0xB9 => self.lda(AddressingMode::AbsoluteY, 4),
-        0xA1 => self.lda(AddressingMode::IndexedIndirect, 6),
-        0xB1 => self.lda(AddressingMode::IndirectIndexed, 5),
-        // LDX
-        0xA2 => self.ldx(AddressingMode::Immediate, 2),
-        0xA6 => self.ldx(AddressingMode::ZeroPage, 3),
-        0xB6 => self.ldx(AddressingMode::ZeroPageY, 4),
-        0xAE => self.ldx(AddressingMode::Absolute, 4),
-        0xBE => self.ldx(AddressingMode::AbsoluteY, 4),
-        // LDY
-        0xA0 => self.ldy(AddressingMode::Immediate, 2),
-        0xA4 => self.ldy(AddressingMode::ZeroPage, 3),
-        0xB4 => self.ldy(AddressingMode::ZeroPageX, 4),
-        0xAC => self.ldy(AddressingMode::Absolute, 4),
-        0xBC => self.ldy(AddressingMode::AbsoluteX, 4),
-        // LSR
-        0x4A => self.lsr(AddressingMode::Implied, 2),
-        0x46 => self.lsr(AddressingMode::ZeroPage, 5),
-        0x56 => self.lsr(AddressingMode::ZeroPageX, 6),
-        0x4E => self.lsr(AddressingMode::Absolute, 6),
-        0x5E => self.lsr(AddressingMode::AbsoluteX, 7),
-        // NOP
-        0xEA => self.nop(AddressingMode::Implied, 2),
-        // ORA
-        0x09 => self.ora(AddressingMode::Immediate, 2),
-        0x05 => self.ora(AddressingMode::ZeroPage, 3),
-        0x15 => self.ora(AddressingMode::ZeroPageX, 4),
-        0x0D => self.ora(AddressingMode::Absolute, 4),
-        0x1D => self.ora(AddressingMode::AbsoluteX, 4),
-        0x19 => self.ora(AddressingMode::AbsoluteY, 4),
-        0x01 => self.ora(AddressingMode::IndexedIndirect, 6),
-        0x11 => self.ora(AddressingMode::IndirectIndexed, 5),
-        // PHA
-        0x48 => self.pha(AddressingMode::Implied, 3),
-        // PHP
-        0x08 => self.php(AddressingMode::Implied, 3),
-        // PLA
-        0x68 => self.pla(AddressingMode::Implied, 4),
-        // PLP
-        0x28 => self.plp(AddressingMode::Implied, 4),
-        // ROL
-        0x2A => self.rol(AddressingMode::Implied, 2),
-        0x26 => self.rol(AddressingMode::ZeroPage, 5),
-        0x36 => self.rol(AddressingMode::ZeroPageX, 6),
-        0x2E => self.rol(AddressingMode::Absolute, 6),
-        0x3E => self.rol(AddressingMode::AbsoluteX, 7),
-        // ROR
-        0x6A => self.ror(AddressingMode::Implied, 2),
-        0x66 => self.ror(AddressingMode::ZeroPage, 5),
-        0x76 => self.ror(AddressingMode::ZeroPageX, 6),
-        0x6E => self.ror(AddressingMode::Absolute, 6),
-        0x7E => self.ror(AddressingMode::AbsoluteX, 7),
-        // RTI
-        0x40 => self.rti(AddressingMode::Implied, 6),
-        // RTS
-        0x60 => self.rts(AddressingMode::Implied, 6),
-        // SBC
-        0xE9 => self.sbc(AddressingMode::Immediate, 2),
-        0xE5 => self.sbc(AddressingMode::ZeroPage, 3),
-        0xF5 => self.sbc(AddressingMode::ZeroPageX, 4),
-        0xED => self.sbc(AddressingMode::Absolute, 4),
-        0xFD => self.sbc(AddressingMode::AbsoluteX, 4),
-        0xF9 => self.sbc(AddressingMode::AbsoluteY, 4),
-        0xE1 => self.sbc(AddressingMode::IndexedIndirect, 6),
-        0xF1 => self.sbc(AddressingMode::IndirectIndexed, 5),
-        // SEC
-        0x38 => self.sec(AddressingMode::Implied, 2),
-        // SED
-        0xF8 => self.sed(AddressingMode::Implied, 2),
-        // SEI
-        0x78 => self.sei(AddressingMode::Implied, 2),
-        // STA
-        0x85 => self.sta(AddressingMode::ZeroPage, 3),
-        0x95 => self.sta(AddressingMode::ZeroPageX, 4),
-        0x8D => self.sta(AddressingMode::Absolute, 4),
-        0x9D => self.sta(AddressingMode::AbsoluteX, 5),
-        0x99 => self.sta(AddressingMode::AbsoluteY, 5),
-        0x81 => self.sta(AddressingMode::IndexedIndirect, 6),
-        0x91 => self.sta(AddressingMode::IndirectIndexed, 6),
-        // STX
-        0x86 => self.stx(AddressingMode::ZeroPage, 3),
-        0x96 => self.stx(AddressingMode::ZeroPageY, 4),
-        0x8E => self.stx(AddressingMode::Absolute, 4),
-        // STY
-        0x84 => self.sty(AddressingMode::ZeroPage, 3),
-        0x94 => self.sty(AddressingMode::ZeroPageX, 4),
-        0x8C => self.sty(AddressingMode::Absolute, 4),
-        // TAX
-        0xAA => self.tax(AddressingMode::Implied, 2),
-        // TAY
-        0xA8 => self.tay(AddressingMode::Implied, 2),
-        // TSX
-        0xBA => self.tsx(AddressingMode::Implied, 2),
-        // TXA
-        0x8A => self.txa(AddressingMode::Implied, 2),
-        // TXS
-        0x9A => self.txs(AddressingMode::Implied, 2),
-        // TYA
-        0x98 => self.tya(AddressingMode::Implied, 2),
-        // Any other opcode gets caught here
-        _ => {
-          println!("Invalid opcode: {:02X} at PC: {:04X}", opcode, self.pc);
-          self.cycles = 1;
-        },
+      if self.nmi_pending {
+        self.nmi_pending = false;
+        self.interrupt(bus, 0xFFFA);
+      } else if self.irq_pending {
+        self.irq_pending = false;
+        self.interrupt(bus, 0xFFFE);
+      } else {
+        let pc = self.pc;
+        let opcode = self.execute(bus);
+        if let Some(hook) = hook {
+          hook.executed(pc, opcode, self.cycles);
+        }
+        executed = true;
+      }
+    }
+
+    // Instructions do all their accesses at once, so one that set the NMI line off itself, e.g. by
+    // enabling NMIs in vblank, did it in its last cycle. Anything else came from the PPU before this cycle.
+    // An edge that's gone again by now, because the instruction read $2002, is missed altogether.
+    let nmi_line = self.sample_nmi_line(bus);
+    if nmi_line && !self.nmi_line {
+      if executed && !nmi_line_before {
+        self.nmi_late = true;
+      } else {
+        self.nmi_detected = true;
       }
     }
+    self.nmi_line = nmi_line;
 
     self.cycles -= 1;
+    match self.cycles {
+      2 => self.fetch_vector(bus),
+      // Interrupts are polled at the end of an instruction's second to last cycle
+      1 => self.poll_interrupts(bus),
+      _ => {},
+    }
   }
 
-  pub fn read(&self, address: u16) -> u8 {
-    if let Some(bus) = &self.bus {
-      bus.borrow().cpu_read(address)
-    } else {
-      panic!("Tried to read from bus before it was connected!");
+  /// Fetch and run the instruction at PC, returning its opcode
+  fn execute(&mut self, bus: &mut dyn BusLike) -> u8 {
+    let interrupt_disable = self.flags.interrupt_disable;
+    let opcode = bus.cpu_read(self.pc);
+    //println!("PC: {:#04X}, opcode: {:02X}", self.pc, opcode);
+    self.pc = self.pc.wrapping_add(1);
+
+    match opcode {
+      // ADC
+      0x69 => self.adc(bus, AddressingMode::Immediate, 2),
+      0x65 => self.adc(bus, AddressingMode::ZeroPage, 3),
+      0x75 => self.adc(bus, AddressingMode::ZeroPageX, 4),
+      0x6D => self.adc(bus, AddressingMode::Absolute, 4),
+      0x7D => self.adc(bus, AddressingMode::AbsoluteX, 4),
+      0x79 => self.adc(bus, AddressingMode::AbsoluteY, 4),
+      0x61 => self.adc(bus, AddressingMode::IndexedIndirect, 6),
+      0x71 => self.adc(bus, AddressingMode::IndirectIndexed, 5),
+      // AND
+      0x29 => self.and(bus, AddressingMode::Immediate, 2),
+      0x25 => self.and(bus, AddressingMode::ZeroPage, 3),
+      0x35 => self.and(bus, AddressingMode::ZeroPageX, 4),
+      0x2D => self.and(bus, AddressingMode::Absolute, 4),
+      0x3D => self.and(bus, AddressingMode::AbsoluteX, 4),
+      0x39 => self.and(bus, AddressingMode::AbsoluteY, 4),
+      0x21 => self.and(bus, AddressingMode::IndexedIndirect, 6),
+      0x31 => self.and(bus, AddressingMode::IndirectIndexed, 5),
+      // ASL
+      0x0A => self.asl(bus, AddressingMode::Implied, 2),
+      0x06 => self.asl(bus, AddressingMode::ZeroPage, 5),
+      0x16 => self.asl(bus, AddressingMode::ZeroPageX, 6),
+      0x0E => self.asl(bus, AddressingMode::Absolute, 6),
+      0x1E => self.asl(bus, AddressingMode::AbsoluteX, 7),
+      // BCC
+      0x90 => self.bcc(bus, AddressingMode::Relative, 2),
+      // BCS
+      0xB0 => self.bcs(bus, AddressingMode::Relative, 2),
+      // BEQ
+      0xF0 => self.beq(bus, AddressingMode::Relative, 2),
+      // BIT
+      0x24 => self.bit(bus, AddressingMode::ZeroPage, 3),
+      0x2C => self.bit(bus, AddressingMode::Absolute, 4),
+      // BMI
+      0x30 => self.bmi(bus, AddressingMode::Relative, 2),
+      // BNE
+      0xD0 => self.bne(bus, AddressingMode::Relative, 2),
+      // BPL
+      0x10 => self.bpl(bus, AddressingMode::Relative, 2),
+      // BRK
+      0x00 => self.brk(bus, AddressingMode::Implied, 7),
+      // BVC
+      0x50 => self.bvc(bus, AddressingMode::Relative, 2),
+      // BVS
+      0x70 => self.bvs(bus, AddressingMode::Relative, 2),
+      // CLC
+      0x18 => self.clc(bus, AddressingMode::Implied, 2),
+      // CLD
+      0xD8 => self.cld(bus, AddressingMode::Implied, 2),
+      // CLI
+      0x58 => self.cli(bus, AddressingMode::Implied, 2),
+      // CLV
+      0xB8 => self.clv(bus, AddressingMode::Implied, 2),
+      // CMP
+      0xC9 => self.cmp(bus, AddressingMode::Immediate, 2),
+      0xC5 => self.cmp(bus, AddressingMode::ZeroPage, 3),
+      0xD5 => self.cmp(bus, AddressingMode::ZeroPageX, 4),
+      0xCD => self.cmp(bus, AddressingMode::Absolute, 4),
+      0xDD => self.cmp(bus, AddressingMode::AbsoluteX, 4),
+      0xD9 => self.cmp(bus, AddressingMode::AbsoluteY, 4),
+      0xC1 => self.cmp(bus, AddressingMode::IndexedIndirect, 6),
+      0xD1 => self.cmp(bus, AddressingMode::IndirectIndexed, 5),
+      // CPX
+      0xE0 => self.cpx(bus, AddressingMode::Immediate, 2),
+      0xE4 => self.cpx(bus, AddressingMode::ZeroPage, 3),
+      0xEC => self.cpx(bus, AddressingMode::Absolute, 4),
+      // CPY
+      0xC0 => self.cpy(bus, AddressingMode::Immediate, 2),
+      0xC4 => self.cpy(bus, AddressingMode::ZeroPage, 3),
+      0xCC => self.cpy(bus, AddressingMode::Absolute, 4),
+      // DEC
+      0xC6 => self.dec(bus, AddressingMode::ZeroPage, 5),
+      0xD6 => self.dec(bus, AddressingMode::ZeroPageX, 6),
+      0xCE => self.dec(bus, AddressingMode::Absolute, 6),
+      0xDE => self.dec(bus, AddressingMode::AbsoluteX, 7),
+      // DEX
+      0xCA => self.dex(bus, AddressingMode::Implied, 2),
+      // DEY
+      0x88 => self.dey(bus, AddressingMode::Implied, 2),
+      // EOR
+      0x49 => self.eor(bus, AddressingMode::Immediate, 2),
+      0x45 => self.eor(bus, AddressingMode::ZeroPage, 3),
+      0x55 => self.eor(bus, AddressingMode::ZeroPageX, 4),
+      0x4D => self.eor(bus, AddressingMode::Absolute, 4),
+      0x5D => self.eor(bus, AddressingMode::AbsoluteX, 4),
+      0x59 => self.eor(bus, AddressingMode::AbsoluteY, 4),
+      0x41 => self.eor(bus, AddressingMode::IndexedIndirect, 6),
+      0x51 => self.eor(bus, AddressingMode::IndirectIndexed, 5),
+      // INC
+      0xE6 => self.inc(bus, AddressingMode::ZeroPage, 5),
+      0xF6 => self.inc(bus, AddressingMode::ZeroPageX, 6),
+      0xEE => self.inc(bus, AddressingMode::Absolute, 6),
+      0xFE => self.inc(bus, AddressingMode::AbsoluteX, 7),
+      // INX
+      0xE8 => self.inx(bus, AddressingMode::Implied, 2),
+      // INY
+      0xC8 => self.iny(bus, AddressingMode::Implied, 2),
+      // JMP
+      0x4C => self.jmp(bus, AddressingMode::Absolute, 3),
+      0x6C => self.jmp(bus, AddressingMode::Indirect, 5),
+      // JSR
+      0x20 => self.jsr(bus, AddressingMode::Absolute, 6),
+      // LDA
+      0xA9 => self.lda(bus, AddressingMode::Immediate, 2),
+      0xA5 => self.lda(bus, AddressingMode::ZeroPage, 3),
+      0xB5 => self.lda(bus, AddressingMode::ZeroPageX, 4),
+      0xAD => self.lda(bus, AddressingMode::Absolute, 4),
+      0xBD => self.lda(bus, AddressingMode::AbsoluteX, 4),
+      0xB9 => self.lda(bus, AddressingMode::AbsoluteY, 4),
+      0xA1 => self.lda(bus, AddressingMode::IndexedIndirect, 6),
+      0xB1 => self.lda(bus, AddressingMode::IndirectIndexed, 5),
+      // LDX
+      0xA2 => self.ldx(bus, AddressingMode::Immediate, 2),
+      0xA6 => self.ldx(bus, AddressingMode::ZeroPage, 3),
+      0xB6 => self.ldx(bus, AddressingMode::ZeroPageY, 4),
+      0xAE => self.ldx(bus, AddressingMode::Absolute, 4),
+      0xBE => self.ldx(bus, AddressingMode::AbsoluteY, 4),
+      // LDY
+      0xA0 => self.ldy(bus, AddressingMode::Immediate, 2),
+      0xA4 => self.ldy(bus, AddressingMode::ZeroPage, 3),
+      0xB4 => self.ldy(bus, AddressingMode::ZeroPageX, 4),
+      0xAC => self.ldy(bus, AddressingMode::Absolute, 4),
+      0xBC => self.ldy(bus, AddressingMode::AbsoluteX, 4),
+      // LSR
+      0x4A => self.lsr(bus, AddressingMode::Implied, 2),
+      0x46 => self.lsr(bus, AddressingMode::ZeroPage, 5),
+      0x56 => self.lsr(bus, AddressingMode::ZeroPageX, 6),
+      0x4E => self.lsr(bus, AddressingMode::Absolute, 6),
+      0x5E => self.lsr(bus, AddressingMode::AbsoluteX, 7),
+      // NOP
+      0xEA => self.nop(bus, AddressingMode::Implied, 2),
+      // ORA
+      0x09 => self.ora(bus, AddressingMode::Immediate, 2),
+      0x05 => self.ora(bus, AddressingMode::ZeroPage, 3),
+      0x15 => self.ora(bus, AddressingMode::ZeroPageX, 4),
+      0x0D => self.ora(bus, AddressingMode::Absolute, 4),
+      0x1D => self.ora(bus, AddressingMode::AbsoluteX, 4),
+      0x19 => self.ora(bus, AddressingMode::AbsoluteY, 4),
+      0x01 => self.ora(bus, AddressingMode::IndexedIndirect, 6),
+      0x11 => self.ora(bus, AddressingMode::IndirectIndexed, 5),
+      // PHA
+      0x48 => self.pha(bus, AddressingMode::Implied, 3),
+      // PHP
+      0x08 => self.php(bus, AddressingMode::Implied, 3),
+      // PLA
+      0x68 => self.pla(bus, AddressingMode::Implied, 4),
+      // PLP
+      0x28 => self.plp(bus, AddressingMode::Implied, 4),
+      // ROL
+      0x2A => self.rol(bus, AddressingMode::Implied, 2),
+      0x26 => self.rol(bus, AddressingMode::ZeroPage, 5),
+      0x36 => self.rol(bus, AddressingMode::ZeroPageX, 6),
+      0x2E => self.rol(bus, AddressingMode::Absolute, 6),
+      0x3E => self.rol(bus, AddressingMode::AbsoluteX, 7),
+      // ROR
+      0x6A => self.ror(bus, AddressingMode::Implied, 2),
+      0x66 => self.ror(bus, AddressingMode::ZeroPage, 5),
+      0x76 => self.ror(bus, AddressingMode::ZeroPageX, 6),
+      0x6E => self.ror(bus, AddressingMode::Absolute, 6),
+      0x7E => self.ror(bus, AddressingMode::AbsoluteX, 7),
+      // RTI
+      0x40 => self.rti(bus, AddressingMode::Implied, 6),
+      // RTS
+      0x60 => self.rts(bus, AddressingMode::Implied, 6),
+      // SBC
+      0xE9 => self.sbc(bus, AddressingMode::Immediate, 2),
+      0xE5 => self.sbc(bus, AddressingMode::ZeroPage, 3),
+      0xF5 => self.sbc(bus, AddressingMode::ZeroPageX, 4),
+      0xED => self.sbc(bus, AddressingMode::Absolute, 4),
+      0xFD => self.sbc(bus, AddressingMode::AbsoluteX, 4),
+      0xF9 => self.sbc(bus, AddressingMode::AbsoluteY, 4),
+      0xE1 => self.sbc(bus, AddressingMode::IndexedIndirect, 6),
+      0xF1 => self.sbc(bus, AddressingMode::IndirectIndexed, 5),
+      // SEC
+      0x38 => self.sec(bus, AddressingMode::Implied, 2),
+      // SED
+      0xF8 => self.sed(bus, AddressingMode::Implied, 2),
+      // SEI
+      0x78 => self.sei(bus, AddressingMode::Implied, 2),
+      // STA
+      0x85 => self.sta(bus, AddressingMode::ZeroPage, 3),
+      0x95 => self.sta(bus, AddressingMode::ZeroPageX, 4),
+      0x8D => self.sta(bus, AddressingMode::Absolute, 4),
+      0x9D => self.sta(bus, AddressingMode::AbsoluteX, 5),
+      0x99 => self.sta(bus, AddressingMode::AbsoluteY, 5),
+      0x81 => self.sta(bus, AddressingMode::IndexedIndirect, 6),
+      0x91 => self.sta(bus, AddressingMode::IndirectIndexed, 6),
+      // STX
+      0x86 => self.stx(bus, AddressingMode::ZeroPage, 3),
+      0x96 => self.stx(bus, AddressingMode::ZeroPageY, 4),
+      0x8E => self.stx(bus, AddressingMode::Absolute, 4),
+      // STY
+      0x84 => self.sty(bus, AddressingMode::ZeroPage, 3),
+      0x94 => self.sty(bus, AddressingMode::ZeroPageX, 4),
+      0x8C => self.sty(bus, AddressingMode::Absolute, 4),
+      // TAX
+      0xAA => self.tax(bus, AddressingMode::Implied, 2),
+      // TAY
+      0xA8 => self.tay(bus, AddressingMode::Implied, 2),
+      // TSX
+      0xBA => self.tsx(bus, AddressingMode::Implied, 2),
+      // TXA
+      0x8A => self.txa(bus, AddressingMode::Implied, 2),
+      // TXS
+      0x9A => self.txs(bus, AddressingMode::Implied, 2),
+      // TYA
+      0x98 => self.tya(bus, AddressingMode::Implied, 2),
+      // Any other opcode gets caught here
+      _ => {
+        println!("Invalid opcode: {:02X} at PC: {:04X}", opcode, self.pc);
+        self.cycles = 1;
+      },
     }
+
+    if matches!(opcode, 0x58 | 0x78 | 0x28) {
+      self.poll_interrupt_disable = Some(interrupt_disable);
+    }
+    opcode
   }
 
-  pub fn write(&mut self, address: u16, value: u8) {
-    if let Some(bus) = &self.bus {
-      bus.borrow_mut().cpu_write(address, value);
-    } else {
-      panic!("Tried to write to bus before it was connected!");
+  fn poll_interrupts(&mut self, bus: &mut dyn BusLike) {
+    let interrupt_disable = self.poll_interrupt_disable.take().unwrap_or(self.flags.interrupt_disable);
+    if self.nmi_detected {
+      self.nmi_pending = true;
     }
+    // A late edge is polled by the next instruction instead
+    self.nmi_detected = std::mem::take(&mut self.nmi_late);
+    let irq_line = bus.irq_line();
+    self.irq_pending = irq_line && !interrupt_disable;
   }
 
-  fn fetch(&mut self, mode: AddressingMode, requires_data: bool, add_cycle_for_page_cross: bool) {
+  fn sample_nmi_line(&self, bus: &mut dyn BusLike) -> bool {
+    bus.nmi_line()
+  }
+
+  fn fetch(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, requires_data: bool, add_cycle_for_page_cross: bool) {
     match mode {
       // Data has an implicit source, potentially the accumulator
       AddressingMode::Implied => {
@@ -358,24 +442,24 @@ impl NES6502 {
       },
       // Addressing 0x0000 to 0x00FF only
       AddressingMode::ZeroPage => {
-        self.current_address_abs = self.read(self.pc) as u16;
+        self.current_address_abs = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
         self.current_address_abs &= 0x00FF;
       },
       // Index into the zero page with X offset
       AddressingMode::ZeroPageX => {
-        self.current_address_abs = (self.read(self.pc).wrapping_add(self.x)) as u16 % 0xFFFF;
+        self.current_address_abs = (bus.cpu_read(self.pc).wrapping_add(self.x)) as u16 % 0xFFFF;
         self.pc = self.pc.wrapping_add(1);
         self.current_address_abs &= 0x00FF;
       },
       // Index into the zero page with Y offset
       AddressingMode::ZeroPageY => {
-        self.current_address_abs = (self.read(self.pc).wrapping_add(self.y)) as u16;
+        self.current_address_abs = (bus.cpu_read(self.pc).wrapping_add(self.y)) as u16;
         self.pc = self.pc.wrapping_add(1);
         self.current_address_abs &= 0x00FF;
       },
       AddressingMode::Relative => {
-        self.current_address_rel = self.read(self.pc) as u16;
+        self.current_address_rel = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
 
         // Check if relative address is negative
@@ -385,18 +469,18 @@ impl NES6502 {
       },
       // Read the next two bytes as a 16-bit address
       AddressingMode::Absolute => {
-        let low = self.read(self.pc) as u16;
+        let low = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
-        let high = self.read(self.pc) as u16;
+        let high = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
 
         self.current_address_abs = (high << 8) | low;
       },
       // Read the next two bytes as a 16-bit address, and add X offset
       AddressingMode::AbsoluteX => {
-        let low = self.read(self.pc) as u16;
+        let low = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
-        let high = self.read(self.pc) as u16;
+        let high = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
 
         self.current_address_abs = (high << 8) | low;
@@ -409,9 +493,9 @@ impl NES6502 {
       },
       // Read the next two bytes as a 16-bit address, and add Y offset
       AddressingMode::AbsoluteY => {
-        let low = self.read(self.pc) as u16;
+        let low = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
-        let high = self.read(self.pc) as u16;
+        let high = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
 
         self.current_address_abs = (high << 8) | low;
@@ -423,39 +507,39 @@ impl NES6502 {
         }
       },
       AddressingMode::Indirect => {
-        let ptr_low = self.read(self.pc) as u16;
+        let ptr_low = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
-        let ptr_high = self.read(self.pc) as u16;
+        let ptr_high = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
 
         let ptr = (ptr_high << 8) | ptr_low;
 
         if ptr_low == 0x00FF {
           // Simulates hardware page boundary bug
-          self.current_address_abs = (self.read(ptr & 0xFF00) as u16) << 8 | self.read(ptr) as u16;
+          self.current_address_abs = (bus.cpu_read(ptr & 0xFF00) as u16) << 8 | bus.cpu_read(ptr) as u16;
         } else {
-          self.current_address_abs = (((self.read(ptr + 1) as u16) << 8) | self.read(ptr) as u16) as u16;
+          self.current_address_abs = ((bus.cpu_read(ptr + 1) as u16) << 8) | bus.cpu_read(ptr) as u16;
         }
       },
       // Index into address table on the zero page and offset by X
       // val = PEEK(PEEK((arg + X) % 256) + PEEK((arg + X + 1) % 256) * 256)
       AddressingMode::IndexedIndirect => {
-        let operand = self.read(self.pc) as u16;
+        let operand = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
 
-        let low = self.read((operand.wrapping_add(self.x as u16)) & 0xFF) as u16;
-        let high = self.read((operand.wrapping_add(self.x as u16 + 1)) & 0xFF) as u16;
+        let low = bus.cpu_read((operand.wrapping_add(self.x as u16)) & 0xFF) as u16;
+        let high = bus.cpu_read((operand.wrapping_add(self.x as u16 + 1)) & 0xFF) as u16;
 
         self.current_address_abs = (high << 8) | low;
       },
       // Index into the zero page, read 16-bit address, and add Y offset to it
       // val = PEEK(PEEK(arg) + PEEK((arg + 1) % 256) * 256 + Y)
       AddressingMode::IndirectIndexed => {
-        let table = self.read(self.pc) as u16;
+        let table = bus.cpu_read(self.pc) as u16;
         self.pc = self.pc.wrapping_add(1);
 
-        let low = self.read((table as u16) & 0x00FF) as u16;
-        let high = self.read((table.wrapping_add(1)) as u16 & 0x00FF) as u16;
+        let low = bus.cpu_read(table & 0x00FF) as u16;
+        let high = bus.cpu_read(table.wrapping_add(1) & 0x00FF) as u16;
 
         self.current_address_abs = (high << 8) | low;
         self.current_address_abs = self.current_address_abs.wrapping_add(self.y as u16);
@@ -468,16 +552,16 @@ impl NES6502 {
     }
 
     if mode != AddressingMode::Implied && requires_data {
-      self.fetched_data = self.read(self.current_address_abs);
+      self.fetched_data = bus.cpu_read(self.current_address_abs);
     }
   }
 
   // region: Instructions
 
-  /// Add with carry
-  fn adc(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  /// Add with carry, always in binary since the 2A03 has no decimal mode
+  fn adc(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     let temp = self.a as u16 + self.fetched_data as u16 + self.flags.carry as u16;
     self.flags.carry = temp > 255;
@@ -489,9 +573,9 @@ impl NES6502 {
   }
 
   /// Logical AND accumulator with given data
-  fn and(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn and(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     self.a &= self.fetched_data;
 
@@ -500,9 +584,9 @@ impl NES6502 {
   }
 
   /// Arithmetic shift left
-  fn asl(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn asl(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, false);
+    self.fetch(bus, mode, true, false);
 
     let value = (self.fetched_data as u16) << 1;
 
@@ -513,14 +597,14 @@ impl NES6502 {
     if mode == AddressingMode::Implied {
       self.a = (value & 0x00FF) as u8;
     } else {
-      self.write(self.current_address_abs, (value & 0x00FF) as u8);
+      bus.cpu_write(self.current_address_abs, (value & 0x00FF) as u8);
     }
   }
 
   /// Branch if carry flag is clear
-  fn bcc(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn bcc(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     if !self.flags.carry {
       self.cycles += 1;
@@ -536,9 +620,9 @@ impl NES6502 {
   }
 
   /// Branch if carry flag is set
-  fn bcs(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn bcs(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     if self.flags.carry {
       self.cycles += 1;
@@ -554,9 +638,9 @@ impl NES6502 {
   }
 
   /// Branch if zero flag is set
-  fn beq(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn beq(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     if self.flags.zero {
       self.cycles += 1;
@@ -572,21 +656,21 @@ impl NES6502 {
   }
 
   /// AND the contents of A with the value in memory and check if bits are set
-  fn bit(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn bit(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     let temp = self.a & self.fetched_data;
 
-    self.flags.zero = (temp & 0x00FF) == 0;
+    self.flags.zero = temp == 0;
     self.flags.overflow = self.fetched_data & (1 << 6) != 0;
     self.flags.negative = self.fetched_data & (1 << 7) != 0;
   }
 
   /// Branch if negative flag is set
-  fn bmi(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn bmi(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     if self.flags.negative {
       self.cycles += 1;
@@ -602,9 +686,9 @@ impl NES6502 {
   }
 
   /// Branch if zero flag is clear
-  fn bne(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn bne(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     if !self.flags.zero {
       self.cycles += 1;
@@ -620,9 +704,9 @@ impl NES6502 {
   }
 
   /// Branch if negative flag is clear
-  fn bpl(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn bpl(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     if !self.flags.negative {
       self.cycles += 1;
@@ -638,33 +722,31 @@ impl NES6502 {
   }
 
   /// Forces the generation of an interrupt request
-  fn brk(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn brk(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
-    self.pc += 1;
+    self.pc = self.pc.wrapping_add(1);
 
     // Push the program counter onto the stack
-    self.write(0x0100 + self.sp as u16, (self.pc >> 8) as u8 & 0x00FF);
+    bus.cpu_write(0x0100 + self.sp as u16, (self.pc >> 8) as u8);
     self.sp = self.sp.wrapping_sub(1);
-    self.write(0x0100 + self.sp as u16, (self.pc & 0x00FF) as u8);
+    bus.cpu_write(0x0100 + self.sp as u16, (self.pc & 0x00FF) as u8);
     self.sp = self.sp.wrapping_sub(1);
 
-    // Write the status flags onto the stack
-    self.flags.break_command = true;
-    self.write(0x0100 + self.sp as u16, self.flags.to_u8());
+    // Write the status flags onto the stack, with B set to tell the handler it wasn't an IRQ
+    bus.cpu_write(0x0100 + self.sp as u16, self.flags.to_stack_byte(true));
     self.sp = self.sp.wrapping_sub(1);
-    self.flags.break_command = false;
 
     self.flags.interrupt_disable = true;
 
-    self.pc = self.read(0xFFFE) as u16 | ((self.read(0xFFFF) as u16) << 8) as u16;
+    self.vector = Some(0xFFFE);
   }
 
   /// Branch if overflow flag is clear
-  fn bvc(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn bvc(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     if !self.flags.overflow {
       self.cycles += 1;
@@ -680,9 +762,9 @@ impl NES6502 {
   }
 
   /// Branch if overflow flag is set
-  fn bvs(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn bvs(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     if self.flags.overflow {
       self.cycles += 1;
@@ -698,91 +780,91 @@ impl NES6502 {
   }
 
   /// Clear carry flag
-  fn clc(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn clc(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.flags.carry = false;
   }
 
   /// Clear decimal mode
-  fn cld(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn cld(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.flags.decimal_mode = false;
   }
 
   /// Clear interrupt disable flag
-  fn cli(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn cli(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.flags.interrupt_disable = false;
   }
 
   /// Clear overflow flag
-  fn clv(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn clv(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.flags.overflow = false;
   }
 
   /// Compare the contents of the accumulator with another value in memory
-  fn cmp(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn cmp(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     let temp = self.a.wrapping_sub(self.fetched_data);
 
     self.flags.carry = self.a >= self.fetched_data;
-    self.flags.zero = (temp & 0x00FF) == 0;
+    self.flags.zero = temp == 0;
     self.flags.negative = temp & 0x80 != 0;
   }
 
   /// Compare the contents of the X register with another value in memory
-  fn cpx(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn cpx(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     let temp = self.x.wrapping_sub(self.fetched_data);
 
     self.flags.carry = self.x >= self.fetched_data;
-    self.flags.zero = (temp & 0x00FF) == 0;
+    self.flags.zero = temp == 0;
     self.flags.negative = temp & 0x80 != 0;
   }
 
   /// Compare the contents of the Y register with another value in memory
-  fn cpy(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn cpy(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     let temp = self.y.wrapping_sub(self.fetched_data);
 
     self.flags.carry = self.y >= self.fetched_data;
-    self.flags.zero = (temp & 0x00FF) == 0;
+    self.flags.zero = temp == 0;
     self.flags.negative = temp & 0x80 != 0;
   }
 
   /// Decrement value stored at memory address by 1
-  fn dec(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn dec(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     // Make this better later
-    let mut value = self.read(self.current_address_abs);
-    self.write(self.current_address_abs, value.wrapping_sub(1) & 0x00FF);
-    value = self.read(self.current_address_abs);
+    let mut value = bus.cpu_read(self.current_address_abs);
+    bus.cpu_write(self.current_address_abs, value.wrapping_sub(1));
+    value = bus.cpu_read(self.current_address_abs);
 
-    self.flags.zero = (value & 0x00FF) == 0;
+    self.flags.zero = value == 0;
     self.flags.negative = (value & 0x80) != 0;
   }
 
   /// Decrement X register by 1
-  fn dex(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn dex(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.x = self.x.wrapping_sub(1);
 
@@ -791,9 +873,9 @@ impl NES6502 {
   }
 
   /// Decrement Y register by 1
-  fn dey(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn dey(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.y = self.y.wrapping_sub(1);
 
@@ -802,9 +884,9 @@ impl NES6502 {
   }
 
   /// Logical XOR accummulator with given value
-  fn eor(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn eor(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     self.a ^= self.fetched_data;
 
@@ -813,23 +895,23 @@ impl NES6502 {
   }
 
   /// Increment value stored at memory address by 1
-  fn inc(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn inc(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     // Make this better later
-    let mut value = self.read(self.current_address_abs);
-    self.write(self.current_address_abs, value.wrapping_add(1));
-    value = self.read(self.current_address_abs);
+    let mut value = bus.cpu_read(self.current_address_abs);
+    bus.cpu_write(self.current_address_abs, value.wrapping_add(1));
+    value = bus.cpu_read(self.current_address_abs);
 
     self.flags.zero = value == 0;
     self.flags.negative = (value & 0x80) != 0;
   }
 
   /// Increment X register by 1
-  fn inx(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn inx(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.x = self.x.wrapping_add(1);
 
@@ -838,9 +920,9 @@ impl NES6502 {
   }
 
   /// Increment Y register by 1
-  fn iny(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn iny(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.y = self.y.wrapping_add(1);
 
@@ -849,32 +931,32 @@ impl NES6502 {
   }
 
   /// Set the program counter to the given address
-  fn jmp(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn jmp(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.pc = self.current_address_abs;
   }
 
   // Push the current program counter to the stack, then jump to the given address
-  fn jsr(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn jsr(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.pc = self.pc.wrapping_sub(1);
 
-    self.write(0x0100 + self.sp as u16, (self.pc >> 8) as u8 & 0x00FF);
+    bus.cpu_write(0x0100 + self.sp as u16, (self.pc >> 8) as u8);
     self.sp = self.sp.wrapping_sub(1);
-    self.write(0x0100 + self.sp as u16, self.pc as u8 & 0x00FF);
+    bus.cpu_write(0x0100 + self.sp as u16, self.pc as u8);
     self.sp = self.sp.wrapping_sub(1);
 
     self.pc = self.current_address_abs;
   }
 
   /// Load a byte of memory into the accumulator
-  fn lda(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn lda(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     self.a = self.fetched_data;
 
@@ -883,9 +965,9 @@ impl NES6502 {
   }
 
   /// Load a byte of memory into the X register
-  fn ldx(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn ldx(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     self.x = self.fetched_data;
 
@@ -894,9 +976,9 @@ impl NES6502 {
   }
 
   /// Load a byte of memory into the Y register
-  fn ldy(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn ldy(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     self.y = self.fetched_data;
 
@@ -905,34 +987,34 @@ impl NES6502 {
   }
 
   /// Logical shift right
-  fn lsr(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn lsr(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, false);
+    self.fetch(bus, mode, true, false);
 
     let original_value = self.fetched_data as u16;
     let value = (original_value >> 1) as u8;
 
     self.flags.carry = (original_value & 0x01) != 0;
-    self.flags.zero = (value & 0x00FF) == 0;
+    self.flags.zero = value == 0;
     self.flags.negative = (value & 0x80) != 0;
 
     if mode == AddressingMode::Implied {
-      self.a = (value & 0x00FF) as u8;
+      self.a = value;
     } else {
-      self.write(self.current_address_abs, (value & 0x00FF) as u8);
+      bus.cpu_write(self.current_address_abs, value);
     }
   }
 
   /// No op
-  fn nop(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn nop(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
   }
 
   /// Logical OR the accumulator with a byte of memory
-  fn ora(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn ora(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     self.a |= self.fetched_data;
 
@@ -941,53 +1023,50 @@ impl NES6502 {
   }
 
   /// Pushes a copy of the accumulator on to the stack.
-  fn pha(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn pha(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
-    self.write(0x0100 + self.sp as u16, self.a);
+    bus.cpu_write(0x0100 + self.sp as u16, self.a);
     self.sp = self.sp.wrapping_sub(1);
   }
 
   /// Pushes a copy of the status flags on to the stack.
-  fn php(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn php(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
-    self.flags.break_command = true;
-    self.write(0x0100 + self.sp as u16, self.flags.to_u8());
-    self.flags.break_command = false;
+    bus.cpu_write(0x0100 + self.sp as u16, self.flags.to_stack_byte(true));
     self.sp = self.sp.wrapping_sub(1);
   }
 
   /// Pulls an 8 bit value from the stack and into the accumulator.
-  fn pla(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn pla(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.sp = self.sp.wrapping_add(1);
-    self.a = self.read(0x0100 + self.sp as u16);
+    self.a = bus.cpu_read(0x0100 + self.sp as u16);
 
     self.flags.zero = self.a == 0;
     self.flags.negative = self.a & 0x80 != 0;
   }
 
   /// Pulls an 8 bit value from the stack and into the processor flags.
-  fn plp(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn plp(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.sp = self.sp.wrapping_add(1);
-    self.flags = Flags::from_u8(self.read(0x0100 + self.sp as u16));
-    self.flags.break_command = false;
+    self.flags = Flags::from_u8(bus.cpu_read(0x0100 + self.sp as u16));
   }
 
   /// Move each of the bits in either A or M one place to the left.
-  fn rol(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn rol(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, false);
+    self.fetch(bus, mode, true, false);
 
-    let value = (((self.fetched_data as u16) << 1) | self.flags.carry as u16) as u16;
+    let value = ((self.fetched_data as u16) << 1) | self.flags.carry as u16;
 
     self.flags.carry = (value & 0xFF00) != 0;
     self.flags.zero = (value & 0x00FF) == 0;
@@ -996,16 +1075,16 @@ impl NES6502 {
     if mode == AddressingMode::Implied {
       self.a = (value & 0x00FF) as u8;
     } else {
-      self.write(self.current_address_abs, (value & 0x00FF) as u8);
+      bus.cpu_write(self.current_address_abs, (value & 0x00FF) as u8);
     }
   }
 
   /// Move each of the bits in either A or M one place to the right.
-  fn ror(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn ror(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, false);
+    self.fetch(bus, mode, true, false);
 
-    let value = ((self.flags.carry as u16) << 7) as u16 | (self.fetched_data >> 1) as u16;
+    let value = ((self.flags.carry as u16) << 7) | (self.fetched_data >> 1) as u16;
 
     self.flags.carry = (self.fetched_data & 0x01) != 0;
     self.flags.zero = (value & 0x00FF) == 0;
@@ -1014,45 +1093,43 @@ impl NES6502 {
     if mode == AddressingMode::Implied {
       self.a = (value & 0x00FF) as u8;
     } else {
-      self.write(self.current_address_abs, (value & 0x00FF) as u8);
+      bus.cpu_write(self.current_address_abs, (value & 0x00FF) as u8);
     }
   }
 
   /// Return from interrupt
-  fn rti(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn rti(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     // Pull status flags
     self.sp = self.sp.wrapping_add(1);
-    let break_prev = self.flags.break_command;
-    self.flags = Flags::from_u8(self.read(0x0100 + self.sp as u16));
-    self.flags.break_command = break_prev;
+    self.flags = Flags::from_u8(bus.cpu_read(0x0100 + self.sp as u16));
 
     // Pull program counter
     self.sp = self.sp.wrapping_add(1);
-    self.pc = self.read(0x0100 + self.sp as u16) as u16;
+    self.pc = bus.cpu_read(0x0100 + self.sp as u16) as u16;
     self.sp = self.sp.wrapping_add(1);
-    self.pc |= (self.read(0x0100 + self.sp as u16) as u16) << 8;
+    self.pc |= (bus.cpu_read(0x0100 + self.sp as u16) as u16) << 8;
   }
 
   /// Pull the program counter from the stack (minus one) and jump to it
-  fn rts(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn rts(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.sp = self.sp.wrapping_add(1);
-    self.pc = self.read(0x0100 + self.sp as u16) as u16;
+    self.pc = bus.cpu_read(0x0100 + self.sp as u16) as u16;
     self.sp = self.sp.wrapping_add(1);
-    self.pc |= (self.read(0x0100 + self.sp as u16) as u16) << 8;
+    self.pc |= (bus.cpu_read(0x0100 + self.sp as u16) as u16) << 8;
 
     self.pc = self.pc.wrapping_add(1);
   }
 
-  /// Subtraction with carry
-  fn sbc(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  /// Subtraction with carry, always in binary like ADC
+  fn sbc(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, true, true);
+    self.fetch(bus, mode, true, true);
 
     let value = self.fetched_data as u16 ^ 0x00FF;
     let temp = self.a as u16 + value + self.flags.carry as u16;
@@ -1065,57 +1142,57 @@ impl NES6502 {
   }
 
   /// Set carry
-  fn sec(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn sec(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.flags.carry = true;
   }
 
   /// Set decimal mode
-  fn sed(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn sed(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.flags.decimal_mode = true;
   }
 
   /// Set the interrupt disable flag
-  fn sei(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn sei(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.flags.interrupt_disable = true;
   }
 
   /// Store the contents of A in memory
-  fn sta(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn sta(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
-    self.write(self.current_address_abs, self.a);
+    bus.cpu_write(self.current_address_abs, self.a);
   }
 
   /// Store the contents of register X in memory
-  fn stx(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn stx(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
-    self.write(self.current_address_abs, self.x);
+    bus.cpu_write(self.current_address_abs, self.x);
   }
 
   /// Store the contents of register Y in memory
-  fn sty(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn sty(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
-    self.write(self.current_address_abs, self.y);
+    bus.cpu_write(self.current_address_abs, self.y);
   }
 
   /// Transfer the contents of A to register X
-  fn tax(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn tax(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.x = self.a;
 
@@ -1124,9 +1201,9 @@ impl NES6502 {
   }
 
   /// Transfer the contents of A to register Y
-  fn tay(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn tay(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.y = self.a;
 
@@ -1135,9 +1212,9 @@ impl NES6502 {
   }
 
   /// Transfer the contents of the stack register to register X
-  fn tsx(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn tsx(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.x = self.sp;
 
@@ -1146,9 +1223,9 @@ impl NES6502 {
   }
 
   /// Transfer the contents of register X to A
-  fn txa(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn txa(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.a = self.x;
 
@@ -1157,17 +1234,17 @@ impl NES6502 {
   }
 
   /// Transfer the contents of register X to the stack register
-  fn txs(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn txs(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.sp = self.x;
   }
 
   /// Transfer the contents of register Y to A
-  fn tya(&mut self, mode: AddressingMode, initial_cycle_count: usize) {
+  fn tya(&mut self, bus: &mut dyn BusLike, mode: AddressingMode, initial_cycle_count: usize) {
     self.cycles += initial_cycle_count;
-    self.fetch(mode, false, false);
+    self.fetch(bus, mode, false, false);
 
     self.a = self.y;
 
@@ -1177,10 +1254,10 @@ impl NES6502 {
 
   // endregion: Instructions
 
-  pub fn reset(&mut self) {
+  pub fn reset(&mut self, bus: &mut dyn BusLike) {
     self.current_address_abs = 0xFFFC;
-    let low = self.read(self.current_address_abs) as u16;
-    let high = self.read(self.current_address_abs + 1) as u16;
+    let low = bus.cpu_read(self.current_address_abs) as u16;
+    let high = bus.cpu_read(self.current_address_abs + 1) as u16;
     self.pc = (high << 8) | low;
 
     self.a = 0;
@@ -1192,51 +1269,75 @@ impl NES6502 {
     self.current_address_abs = 0x0000;
     self.current_address_rel = 0x0000;
     self.fetched_data = 0x00;
+    self.clear_interrupts();
 
     self.cycles = 8;
   }
 
-  pub fn irq(&mut self) {
-    if !self.flags.interrupt_disable {
-      self.write(0x0100 + self.sp as u16, (self.pc >> 8) as u8);
-      self.sp -= 1;
-      self.write(0x0100 + self.sp as u16, (self.pc & 0x00FF) as u8);
-      self.sp -= 1;
-
-      self.flags.break_command = false;
+  /// What pressing the reset button does, as opposed to `reset` at power on: the registers keep their
+  /// values, apart from the stack pointer moving down three as if an interrupt had been taken, and
+  /// interrupts being disabled
+  pub fn soft_reset(&mut self, bus: &mut dyn BusLike) {
+    self.current_address_abs = 0xFFFC;
+    let low = bus.cpu_read(self.current_address_abs) as u16;
+    let high = bus.cpu_read(self.current_address_abs + 1) as u16;
+    self.pc = (high << 8) | low;
 
-      self.write(0x0100 + self.sp as u16, self.flags.to_u8());
-      self.sp -= 1;
+    self.sp = self.sp.wrapping_sub(3);
+    self.flags.interrupt_disable = true;
 
-      self.flags.interrupt_disable = true;
+    self.current_address_abs = 0x0000;
+    self.current_address_rel = 0x0000;
+    self.fetched_data = 0x00;
+    self.clear_interrupts();
 
-      self.current_address_abs = 0xFFFE;
-      let low = self.read(self.current_address_abs) as u16;
-      let high = self.read(self.current_address_abs + 1) as u16;
-      self.pc = (high << 8) | low;
+    self.cycles = 8;
+  }
 
-      self.cycles = 7;
-    }
+  fn clear_interrupts(&mut self) {
+    self.nmi_detected = false;
+    self.nmi_late = false;
+    self.nmi_pending = false;
+    self.irq_pending = false;
+    self.poll_interrupt_disable = None;
+    self.vector = None;
   }
 
+  /// The NMI line has gone low. The CPU notices at its next poll and takes the NMI after that instruction.
   pub fn nmi(&mut self) {
-    self.write(0x0100 + self.sp as u16, (self.pc >> 8) as u8);
+    self.nmi_detected = true;
+  }
+
+  /// Push PC and the flags and disable interrupts, as an NMI or IRQ does in place of an instruction
+  fn interrupt(&mut self, bus: &mut dyn BusLike, vector: u16) {
+    bus.cpu_write(0x0100 + self.sp as u16, (self.pc >> 8) as u8);
     self.sp = self.sp.wrapping_sub(1);
-    self.write(0x0100 + self.sp as u16, (self.pc & 0x00FF) as u8);
+    bus.cpu_write(0x0100 + self.sp as u16, (self.pc & 0x00FF) as u8);
     self.sp = self.sp.wrapping_sub(1);
 
-    self.flags.break_command = false;
-
-    self.write(0x0100 + self.sp as u16, self.flags.to_u8());
+    bus.cpu_write(0x0100 + self.sp as u16, self.flags.to_stack_byte(false));
     self.sp = self.sp.wrapping_sub(1);
 
     self.flags.interrupt_disable = true;
 
-    self.current_address_abs = 0xFFFA;
-    let low = self.read(self.current_address_abs) as u16;
-    let high = self.read(self.current_address_abs + 1) as u16;
-    self.pc = (high << 8) | low;
+    self.vector = Some(vector);
+    self.cycles = 7;
+  }
 
-    self.cycles = 8;
+  /// Read the vector of a BRK or interrupt into PC. If an NMI has arrived in the meantime it hijacks
+  /// a BRK or IRQ, which then ends up in the NMI handler with its pushes already done.
+  fn fetch_vector(&mut self, bus: &mut dyn BusLike) {
+    let Some(mut vector) = self.vector.take() else {
+      return;
+    };
+    if vector == 0xFFFE && self.nmi_detected {
+      self.nmi_detected = false;
+      vector = 0xFFFA;
+    }
+
+    self.current_address_abs = vector;
+    let low = bus.cpu_read(self.current_address_abs) as u16;
+    let high = bus.cpu_read(self.current_address_abs + 1) as u16;
+    self.pc = (high << 8) | low;
   }
 }
\ No newline at end of file
@@ -0,0 +1,149 @@
+//! Eight balls bouncing round the screen, each playing its own note on pulse 1 when it hits an edge.
+
+use super::{nrom, set_tile, PALETTE, PRG_SIZE, SPRITES};
+
+/// Where the balls' X velocities go in PRG ROM, followed by their Y velocities, copied to $10-$1F
+const VELOCITIES: usize = 0x120;
+/// Where each ball's note, as the low byte of pulse 1's period, goes in PRG ROM
+const NOTES: usize = 0x130;
+
+/// Each ball's starting X and Y, and how far it moves each frame. A ball moving 2 pixels a frame has to
+/// start on an even position, as it only turns round when it lands exactly on an edge.
+const BALLS: [(u8, u8, i8, i8); 8] = [
+  (16, 40, 1, 1),
+  (200, 64, -1, 2),
+  (64, 180, 2, -1),
+  (120, 100, -2, -2),
+  (32, 150, 1, -2),
+  (180, 20, 2, 1),
+  (100, 200, -1, -1),
+  (230, 120, -2, 2),
+];
+
+/// A pentatonic scale up from A 440 Hz, as pulse periods
+const SCALE: [u8; 8] = [0xFD, 0xE1, 0xC9, 0xA9, 0x96, 0x7E, 0x70, 0x64];
+
+/// Sprite palettes, one colour of ball each with a white shine, on dark blue
+const PALETTES: [u8; 32] = [
+  0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+  0x01, 0x16, 0x00, 0x30, 0x01, 0x2A, 0x00, 0x30, 0x01, 0x12, 0x00, 0x30, 0x01, 0x28, 0x00, 0x30,
+];
+
+/// Tile 1, a ball in colour 1 with a colour 3 shine at the top left
+const BALL: [[u8; 8]; 2] = [
+  [0x3C, 0x7E, 0xFF, 0xFF, 0xFF, 0xFF, 0x7E, 0x3C],
+  [0x00, 0x30, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00],
+];
+
+/// Zero page: $00 frames drawn, counted by the NMI handler, $10-$17 the balls' X velocities and $18-$1F
+/// their Y velocities. Ball N is sprite N, its position kept in the sprites at $0200.
+const PROGRAM: [u8; 0xB4] = [
+  // Start up with rendering off, then wait out the PPU warming up
+  0x78,             // SEI
+  0xD8,             // CLD
+  0xA2, 0xFF,       // LDX #$FF
+  0x9A,             // TXS
+  0xA9, 0x40,       // LDA #$40
+  0x8D, 0x17, 0x40, // STA $4017
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x00, 0x20, // STA $2000
+  0x8D, 0x01, 0x20, // STA $2001
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL $C012
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL $C017
+  // Load the palettes, the balls and their velocities
+  0xA9, 0x3F,       // LDA #$3F
+  0x8D, 0x06, 0x20, // STA $2006
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x06, 0x20, // STA $2006
+  0xAA,             // TAX
+  0xBD, 0x00, 0xC1, // LDA $C100,X
+  0x8D, 0x07, 0x20, // STA $2007
+  0xE8,             // INX
+  0xE0, 0x20,       // CPX #$20
+  0xD0, 0xF5,       // BNE $C027
+  0xA2, 0x00,       // LDX #$00
+  0xBD, 0x00, 0xC2, // LDA $C200,X
+  0x9D, 0x00, 0x02, // STA $0200,X
+  0xE8,             // INX
+  0xD0, 0xF7,       // BNE $C034
+  0xA2, 0x0F,       // LDX #$0F
+  0xBD, 0x20, 0xC1, // LDA $C120,X
+  0x95, 0x10,       // STA $10,X
+  0xCA,             // DEX
+  0x10, 0xF8,       // BPL $C03F
+  // Pulse 1 on with its sweep off, then NMIs and sprites on
+  0xA9, 0x01,       // LDA #$01
+  0x8D, 0x15, 0x40, // STA $4015
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x01, 0x40, // STA $4001
+  0xA9, 0x80,       // LDA #$80
+  0x8D, 0x00, 0x20, // STA $2000
+  0xA9, 0x14,       // LDA #$14
+  0x8D, 0x01, 0x20, // STA $2001
+  // Each frame, wait for the NMI then move each ball
+  0xA5, 0x00,       // LDA $00
+  0xC5, 0x00,       // CMP $00
+  0xF0, 0xFC,       // BEQ $C05D
+  0xA2, 0x00,       // LDX #$00
+  0x8A,             // TXA
+  0x0A,             // ASL A
+  0x0A,             // ASL A
+  0xA8,             // TAY
+  0xB9, 0x03, 0x02, // LDA $0203,Y
+  0x18,             // CLC
+  0x75, 0x10,       // ADC $10,X
+  0x99, 0x03, 0x02, // STA $0203,Y
+  0xF0, 0x04,       // BEQ $C076
+  0xC9, 0xF8,       // CMP #$F8
+  0xD0, 0x0A,       // BNE $C080
+  0xA9, 0x00,       // LDA #$00
+  0x38,             // SEC
+  0xF5, 0x10,       // SBC $10,X
+  0x95, 0x10,       // STA $10,X
+  0x20, 0xA3, 0xC0, // JSR $C0A3
+  0xB9, 0x00, 0x02, // LDA $0200,Y
+  0x18,             // CLC
+  0x75, 0x18,       // ADC $18,X
+  0x99, 0x00, 0x02, // STA $0200,Y
+  0xC9, 0x08,       // CMP #$08
+  0xF0, 0x04,       // BEQ $C091
+  0xC9, 0xDE,       // CMP #$DE
+  0xD0, 0x0A,       // BNE $C09B
+  0xA9, 0x00,       // LDA #$00
+  0x38,             // SEC
+  0xF5, 0x18,       // SBC $18,X
+  0x95, 0x18,       // STA $18,X
+  0x20, 0xA3, 0xC0, // JSR $C0A3
+  0xE8,             // INX
+  0xE0, 0x08,       // CPX #$08
+  0xD0, 0xC3,       // BNE $C063
+  0x4C, 0x5B, 0xC0, // JMP $C05B
+  // Play the ball's note on pulse 1, restarting its envelope
+  0xA9, 0x84,       // LDA #$84
+  0x8D, 0x00, 0x40, // STA $4000
+  0xBD, 0x30, 0xC1, // LDA $C130,X
+  0x8D, 0x02, 0x40, // STA $4002
+  0xA9, 0x08,       // LDA #$08
+  0x8D, 0x03, 0x40, // STA $4003
+  0x60,             // RTS
+];
+
+pub fn rom() -> Vec<u8> {
+  let mut prg = [0; PRG_SIZE];
+  prg[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+  prg[PALETTE..PALETTE + PALETTES.len()].copy_from_slice(&PALETTES);
+  prg[NOTES..NOTES + SCALE.len()].copy_from_slice(&SCALE);
+  // Sprites past the balls are hidden below the screen
+  prg[SPRITES..SPRITES + 0x100].fill(0xFF);
+  for (ball, &(x, y, dx, dy)) in BALLS.iter().enumerate() {
+    prg[SPRITES + ball * 4..SPRITES + ball * 4 + 4].copy_from_slice(&[y, 1, ball as u8 & 3, x]);
+    prg[VELOCITIES + ball] = dx as u8;
+    prg[VELOCITIES + 8 + ball] = dy as u8;
+  }
+
+  let mut chr = [0; 0x2000];
+  set_tile(&mut chr, 1, BALL);
+  nrom(prg, chr)
+}
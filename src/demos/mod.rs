@@ -0,0 +1,92 @@
+//! Homebrew programs built into the emulator, so there's something to play before finding a ROM.
+//!
+//! They're little NROM programs written for SilkNES, built into iNES images in code and loaded through
+//! `Cartridge::from_bytes` like any other ROM. Each one draws with sprites only, keeping them at $0200
+//! for the shared NMI handler to copy to OAM.
+
+pub mod bounce;
+pub mod starfield;
+
+use crate::cartridge::{Cartridge, CartridgeError};
+
+/// A built in demo
+pub struct Demo {
+  /// What the console calls it
+  pub id: &'static str,
+  pub name: &'static str,
+  /// What the buttons do, for telling the user when it's loaded
+  pub controls: &'static str,
+  rom: fn() -> Vec<u8>,
+}
+
+impl Demo {
+  /// The demo as an iNES image
+  pub fn rom(&self) -> Vec<u8> {
+    (self.rom)()
+  }
+
+  pub fn cartridge(&self) -> Result<Cartridge, CartridgeError> {
+    Cartridge::from_bytes(self.rom())
+  }
+}
+
+pub const DEMOS: &[Demo] = &[
+  Demo {
+    id: "bounce",
+    name: "Bounce",
+    controls: "Just watch, and listen",
+    rom: bounce::rom,
+  },
+  Demo {
+    id: "starfield",
+    name: "Starfield",
+    controls: "D-pad: fly the ship",
+    rom: starfield::rom,
+  },
+];
+
+/// The demo the console calls `id`
+pub fn find(id: &str) -> Option<usize> {
+  DEMOS.iter().position(|demo| demo.id == id)
+}
+
+/// Size of an NROM-128 board's PRG ROM, which appears at $C000 and again at $8000
+const PRG_SIZE: usize = 0x4000;
+/// Where in PRG ROM the demos' palettes go, at $C100
+const PALETTE: usize = 0x100;
+/// Where in PRG ROM the sprites copied to $0200 at startup go, at $C200
+const SPRITES: usize = 0x200;
+/// Where the NMI handler goes, near the end of PRG ROM
+const NMI: usize = 0x3F00;
+
+/// Copies the sprites to OAM and counts the frame in $00, for the main loop to wait on
+const NMI_HANDLER: [u8; 15] = [
+  0x48,             // PHA
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x03, 0x20, // STA $2003
+  0xA9, 0x02,       // LDA #$02
+  0x8D, 0x14, 0x40, // STA $4014
+  0xE6, 0x00,       // INC $00
+  0x68,             // PLA
+  0x40,             // RTI
+];
+
+/// An iNES image of an NROM-128 board with `prg` and `chr`, running `prg` from $C000. The NMI handler and
+/// vectors are filled in here, with IRQs going straight to the handler's RTI.
+fn nrom(mut prg: [u8; PRG_SIZE], chr: [u8; 0x2000]) -> Vec<u8> {
+  prg[NMI..NMI + NMI_HANDLER.len()].copy_from_slice(&NMI_HANDLER);
+  let [nmi_low, nmi_high] = (0xC000 + NMI as u16).to_le_bytes();
+  let [return_low, return_high] = (0xC000 + (NMI + NMI_HANDLER.len() - 1) as u16).to_le_bytes();
+  prg[PRG_SIZE - 6..].copy_from_slice(&[nmi_low, nmi_high, 0x00, 0xC0, return_low, return_high]);
+
+  let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+  rom.extend(prg);
+  rom.extend(chr);
+  rom
+}
+
+/// Write an 8x8 tile into CHR from its two bit planes
+fn set_tile(chr: &mut [u8; 0x2000], tile: usize, planes: [[u8; 8]; 2]) {
+  chr[tile * 16..tile * 16 + 8].copy_from_slice(&planes[0]);
+  chr[tile * 16 + 8..tile * 16 + 16].copy_from_slice(&planes[1]);
+}
@@ -0,0 +1,130 @@
+//! Stars streaming past in four layers, with a ship to fly about in front of them.
+
+use super::{nrom, set_tile, PALETTE, PRG_SIZE, SPRITES};
+
+/// How many stars there are, as sprites 1 onwards
+pub const STARS: usize = 48;
+/// Where the ship starts, as its X and Y
+const SHIP: (u8, u8) = (48, 108);
+
+/// Sprite palettes, dark grey, grey and two whites for the stars from far to near, with the ship's white,
+/// red and orange in the last
+const PALETTES: [u8; 32] = [
+  0x0F, 0x00, 0x00, 0x00, 0x0F, 0x00, 0x00, 0x00, 0x0F, 0x00, 0x00, 0x00, 0x0F, 0x00, 0x00, 0x00,
+  0x0F, 0x00, 0x00, 0x00, 0x0F, 0x10, 0x00, 0x00, 0x0F, 0x20, 0x00, 0x00, 0x0F, 0x30, 0x16, 0x27,
+];
+
+/// Tile 1, a star a single pixel across
+const STAR: [[u8; 8]; 2] = [[0, 0, 0, 0x10, 0, 0, 0, 0], [0; 8]];
+/// Tile 2, the ship pointing right, a red body with a white nose and orange flames
+const SHIP_TILE: [[u8; 8]; 2] = [
+  [0x00, 0xC0, 0x44, 0xC3, 0x44, 0xC0, 0x00, 0x00],
+  [0x00, 0xF0, 0x78, 0xFC, 0x78, 0xF0, 0x00, 0x00],
+];
+
+/// Zero page: $00 frames drawn, counted by the NMI handler, and $01 the buttons held. The ship's sprite 0,
+/// and each star's palette is also how many pixels it moves left each frame, less one.
+const PROGRAM: [u8; 0xA9] = [
+  // Start up with rendering off, then wait out the PPU warming up
+  0x78,             // SEI
+  0xD8,             // CLD
+  0xA2, 0xFF,       // LDX #$FF
+  0x9A,             // TXS
+  0xA9, 0x40,       // LDA #$40
+  0x8D, 0x17, 0x40, // STA $4017
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x00, 0x20, // STA $2000
+  0x8D, 0x01, 0x20, // STA $2001
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL $C012
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL $C017
+  // Load the palettes, then the ship and stars
+  0xA9, 0x3F,       // LDA #$3F
+  0x8D, 0x06, 0x20, // STA $2006
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x06, 0x20, // STA $2006
+  0xAA,             // TAX
+  0xBD, 0x00, 0xC1, // LDA $C100,X
+  0x8D, 0x07, 0x20, // STA $2007
+  0xE8,             // INX
+  0xE0, 0x20,       // CPX #$20
+  0xD0, 0xF5,       // BNE $C027
+  0xA2, 0x00,       // LDX #$00
+  0xBD, 0x00, 0xC2, // LDA $C200,X
+  0x9D, 0x00, 0x02, // STA $0200,X
+  0xE8,             // INX
+  0xD0, 0xF7,       // BNE $C034
+  0xA9, 0x80,       // LDA #$80
+  0x8D, 0x00, 0x20, // STA $2000
+  0xA9, 0x14,       // LDA #$14
+  0x8D, 0x01, 0x20, // STA $2001
+  // Each frame, wait for the NMI then read the controller
+  0xA5, 0x00,       // LDA $00
+  0xC5, 0x00,       // CMP $00
+  0xF0, 0xFC,       // BEQ $C049
+  0xA9, 0x01,       // LDA #$01
+  0x8D, 0x16, 0x40, // STA $4016
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x16, 0x40, // STA $4016
+  0xA2, 0x08,       // LDX #$08
+  0xAD, 0x16, 0x40, // LDA $4016
+  0x4A,             // LSR A
+  0x26, 0x01,       // ROL $01
+  0xCA,             // DEX
+  0xD0, 0xF7,       // BNE $C059
+  // The D-pad flies the ship, which is sprite 0, 2 pixels a frame
+  0xA5, 0x01,       // LDA $01
+  0x29, 0x08,       // AND #$08
+  0xF0, 0x06,       // BEQ $C06E
+  0xCE, 0x00, 0x02, // DEC $0200
+  0xCE, 0x00, 0x02, // DEC $0200
+  0xA5, 0x01,       // LDA $01
+  0x29, 0x04,       // AND #$04
+  0xF0, 0x06,       // BEQ $C07A
+  0xEE, 0x00, 0x02, // INC $0200
+  0xEE, 0x00, 0x02, // INC $0200
+  0xA5, 0x01,       // LDA $01
+  0x29, 0x02,       // AND #$02
+  0xF0, 0x06,       // BEQ $C086
+  0xCE, 0x03, 0x02, // DEC $0203
+  0xCE, 0x03, 0x02, // DEC $0203
+  0xA5, 0x01,       // LDA $01
+  0x29, 0x01,       // AND #$01
+  0xF0, 0x06,       // BEQ $C092
+  0xEE, 0x03, 0x02, // INC $0203
+  0xEE, 0x03, 0x02, // INC $0203
+  // Each star moves left by one more than its palette, so the brighter ones are nearer and faster
+  0xA0, 0x04,       // LDY #$04
+  0xB9, 0x03, 0x02, // LDA $0203,Y
+  0x18,             // CLC
+  0xF9, 0x02, 0x02, // SBC $0202,Y
+  0x99, 0x03, 0x02, // STA $0203,Y
+  0xC8,             // INY
+  0xC8,             // INY
+  0xC8,             // INY
+  0xC8,             // INY
+  0xC0, 0xC4,       // CPY #$C4
+  0xD0, 0xEE,       // BNE $C094
+  0x4C, 0x47, 0xC0, // JMP $C047
+];
+
+pub fn rom() -> Vec<u8> {
+  let mut prg = [0; PRG_SIZE];
+  prg[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+  prg[PALETTE..PALETTE + PALETTES.len()].copy_from_slice(&PALETTES);
+  // Sprites past the stars are hidden below the screen
+  prg[SPRITES..SPRITES + 0x100].fill(0xFF);
+  prg[SPRITES..SPRITES + 4].copy_from_slice(&[SHIP.1, 2, 3, SHIP.0]);
+  for star in 0..STARS {
+    // Spread out down the screen, no more than two to a line, and scattered across it
+    let (x, y) = ((star * 97 + 31) as u8, (20 + star * 4) as u8);
+    let sprite = SPRITES + (star + 1) * 4;
+    prg[sprite..sprite + 4].copy_from_slice(&[y, 1, star as u8 & 3, x]);
+  }
+
+  let mut chr = [0; 0x2000];
+  set_tile(&mut chr, 1, STAR);
+  set_tile(&mut chr, 2, SHIP_TILE);
+  nrom(prg, chr)
+}
@@ -0,0 +1,384 @@
+//! Runs the console on its own thread, so the UI stalling (a file dialog open, the window being dragged)
+//! neither pauses emulation nor makes it race to catch up afterwards.
+//!
+//! The UI sends the controller and held hotkeys over a channel, and reads finished frames out of a
+//! triple buffer. Everything else, the debugging tools and commands like loading a state, locks the
+//! [`Core`] for as long as it needs it, which the emulation thread only ever holds for a frame at a time.
+//! Frames are timed by how much audio the output device has played, falling back to the wall clock
+//! if it stops playing or the sync mode is set to the wall clock. Each frame's run a quarter at a time
+//! as those come due, with the latest input handed over before each, so the game sees whatever was
+//! held most recently when it strobes the controllers, as in the web build.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::apu_output::{AudioClock, AudioControls};
+use crate::audio_pipeline::AudioPipeline;
+use crate::frame_advance::{BackgroundMode, FrameAdvance, FAST_FORWARD_SPEED};
+use crate::input::PadState;
+use crate::nes::{Nes, SaveState, CYCLES_PER_FRAME, NTSC_FRAME_RATE, OUTPUT_SAMPLES_PER_FRAME};
+use crate::rewind::Rewind;
+
+/// How long the emulation thread sleeps between checking whether a frame's due
+const IDLE_WAIT: Duration = Duration::from_millis(1);
+
+/// How many slices each frame's run in, with fresh input picked up between them
+pub const SLICES_PER_FRAME: u32 = 4;
+
+/// What the player's holding, sent every UI frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Input {
+    /// Turned into buttons as each slice runs, so turbo goes by the console's frame count
+    pub pad: PadState,
+    pub fast_forward: bool,
+    pub rewinding: bool,
+    pub frame_advance: bool,
+    /// The UI clock in seconds, for how long frame advance has been held
+    pub time: f64,
+    /// What to do while the window's lost focus, `None` while it has it
+    pub background: Option<BackgroundMode>,
+}
+
+/// Things the emulation thread tells the UI about
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// A breakpoint or watchpoint stopped emulation, described for the console
+    Stopped(String),
+}
+
+/// The console and everything that runs alongside it, locked by whichever thread is using it
+pub struct Core {
+    pub nes: Nes,
+    pub save_slots: Vec<Option<SaveState>>,
+    pub rewind: Rewind,
+    pub frame_advance: FrameAdvance,
+    /// Emulation speed multiplier, and how far through the next frame that's left us
+    pub speed: f32,
+    pub pending_frames: f32,
+    /// Set while a netplay session is connected, which runs frames from the UI thread in lockstep with
+    /// the other player instead
+    pub netplay: bool,
+    /// Downsamples the raw APU output on its own thread, and hands it on to the audio backend
+    pub audio_pipeline: AudioPipeline,
+}
+
+impl Core {
+    pub fn new(save_slots: usize, audio_pipeline: AudioPipeline) -> Self {
+        Self {
+            nes: Nes::new(),
+            save_slots: vec![None; save_slots],
+            rewind: Rewind::default(),
+            frame_advance: FrameAdvance::default(),
+            speed: 1.0,
+            pending_frames: 0.0,
+            netplay: false,
+            audio_pipeline,
+        }
+    }
+
+    /// Run the next slice of the frame that's coming due with `input` held, short of its last, which
+    /// `run_due_frame` finishes the frame from. Only plain play at normal speed goes a slice at a time,
+    /// anything else waits to run whole frames. Returns why emulation stopped if it did.
+    pub fn run_due_slice(&mut self, input: &Input) -> Option<String> {
+        let plain = !self.netplay && !input.rewinding && !input.fast_forward && input.background.is_none()
+            && !self.frame_advance.paused && self.speed == 1.0;
+        if !plain || !self.nes.rom_loaded() || self.nes.breakpoint_hit().is_some() {
+            return None;
+        }
+        self.nes.update_controller(0, input.pad.buttons(self.nes.frame_count()));
+        self.nes.run_cycles(CYCLES_PER_FRAME / SLICES_PER_FRAME);
+        self.nes.describe_stop()
+    }
+
+    /// Run whatever the frame that's come due calls for, returning why emulation stopped if it did
+    pub fn run_due_frame(&mut self, input: &Input) -> Option<String> {
+        self.frame_advance.update_hold(input.frame_advance, input.time);
+        if self.netplay || !self.nes.rom_loaded() || self.nes.breakpoint_hit().is_some() {
+            return None;
+        }
+        self.nes.update_controller(0, input.pad.buttons(self.nes.frame_count()));
+
+        if input.rewinding {
+            // Step back a snapshot each frame, with the audio fading out in place of the frames that don't run
+            self.rewind.step_back(&mut self.nes);
+            self.nes.take_raw_audio();
+            self.audio_pipeline.push_paused_frame();
+            self.pending_frames = 0.0;
+            return None;
+        }
+        if input.background == Some(BackgroundMode::Pause) {
+            // Likewise fading out while paused in the background, without touching the user's own pause
+            self.nes.take_raw_audio();
+            self.audio_pipeline.push_paused_frame();
+            self.pending_frames = 0.0;
+            return None;
+        }
+
+        // Run as many frames as the speed calls for
+        let speed = input.background.map_or(1.0, BackgroundMode::speed) * self.speed;
+        self.pending_frames += if input.fast_forward { speed * FAST_FORWARD_SPEED } else { speed };
+        while self.pending_frames >= 1.0 {
+            if self.frame_advance.take_frame() {
+                self.nes.run_frame();
+                self.rewind.record(&self.nes);
+            } else {
+                // Keep the audio flowing at the usual rate, so it neither starves nor has a backlog once unpaused
+                self.audio_pipeline.push_paused_frame();
+            }
+            self.pending_frames -= 1.0;
+            // Only the last frame's audio is played while fast forwarding, so it doesn't pile up
+            if input.fast_forward && self.pending_frames >= 1.0 {
+                self.nes.take_raw_audio();
+            }
+        }
+
+        let stopped = self.nes.describe_stop();
+        if stopped.is_some() {
+            self.pending_frames = 0.0;
+        }
+        if input.background == Some(BackgroundMode::Throttle) {
+            // Most frames that come due don't run while throttled, so the sound would only stutter
+            self.nes.take_raw_audio();
+            self.audio_pipeline.push_paused_frame();
+        } else {
+            self.audio_pipeline.push(&self.nes.take_raw_audio());
+        }
+        stopped
+    }
+}
+
+/// The frame the emulation thread has most recently finished and the one drawn before it, with
+/// whether the UI's seen them yet
+struct Finished {
+    frame: Vec<u8>,
+    previous: Vec<u8>,
+    fresh: bool,
+}
+
+/// Create a triple buffer for handing frames from the emulation thread to the UI. One buffer's being
+/// written, one's being read and the newest finished frame is in between, and they're swapped around
+/// rather than copied, so neither side waits on the other for longer than a swap.
+pub fn frame_buffer() -> (FrameWriter, FrameReader) {
+    let finished = Arc::new(Mutex::new(Finished { frame: Vec::new(), previous: Vec::new(), fresh: false }));
+    let writer = FrameWriter { back: Vec::new(), back_previous: Vec::new(), finished: Arc::clone(&finished) };
+    (writer, FrameReader { front: Vec::new(), front_previous: Vec::new(), finished })
+}
+
+/// The emulation thread's end of a frame buffer
+pub struct FrameWriter {
+    back: Vec<u8>,
+    back_previous: Vec<u8>,
+    finished: Arc<Mutex<Finished>>,
+}
+
+impl FrameWriter {
+    pub fn publish(&mut self, frame: &[u8]) {
+        self.publish_with_previous(frame, &[]);
+    }
+
+    /// Publish a frame along with the one drawn before it, swapped in together so the UI never sees
+    /// one without the other
+    pub fn publish_with_previous(&mut self, frame: &[u8], previous: &[u8]) {
+        self.back.clear();
+        self.back.extend_from_slice(frame);
+        self.back_previous.clear();
+        self.back_previous.extend_from_slice(previous);
+        let mut finished = self.finished.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::swap(&mut finished.frame, &mut self.back);
+        std::mem::swap(&mut finished.previous, &mut self.back_previous);
+        finished.fresh = true;
+    }
+}
+
+/// The UI's end of a frame buffer
+pub struct FrameReader {
+    front: Vec<u8>,
+    front_previous: Vec<u8>,
+    finished: Arc<Mutex<Finished>>,
+}
+
+impl FrameReader {
+    /// The newest finished frame, which stays the same until another's published
+    pub fn latest(&mut self) -> &[u8] {
+        self.latest_with_previous().0
+    }
+
+    /// The newest finished frame and the one published with it, which is empty if there wasn't one
+    pub fn latest_with_previous(&mut self) -> (&[u8], &[u8]) {
+        let mut finished = self.finished.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if finished.fresh {
+            std::mem::swap(&mut finished.frame, &mut self.front);
+            std::mem::swap(&mut finished.previous, &mut self.front_previous);
+            finished.fresh = false;
+        }
+        (&self.front, &self.front_previous)
+    }
+}
+
+/// Decides when each frame's due. The output device plays at exactly its sample rate, so frames run
+/// whenever the audio queued for it drops to the latency setting, keeping the console in step with
+/// its sound. If the device stops pulling samples, or the sync mode says so, frames go by the wall clock.
+pub struct FrameClock {
+    audio: AudioClock,
+    controls: AudioControls,
+    /// When the next frame or slice is due by the wall clock
+    deadline: Instant,
+    /// How many slices of the current frame have come due
+    slice: u32,
+}
+
+impl FrameClock {
+    pub fn new(controls: AudioControls) -> Self {
+        Self {
+            audio: AudioClock::new(controls.clone()),
+            controls,
+            deadline: Instant::now(),
+            slice: 0,
+        }
+    }
+
+    /// Whether another frame is due, counting it as run if so
+    pub fn frame_due(&mut self) -> bool {
+        let now = Instant::now();
+        let frame_duration = Duration::from_secs_f64(1.0 / NTSC_FRAME_RATE);
+
+        let due = self.due(now);
+        if due {
+            self.audio.queue(OUTPUT_SAMPLES_PER_FRAME as u64);
+            self.deadline = self.deadline.max(now - frame_duration) + frame_duration;
+        }
+        due
+    }
+
+    /// Which slice of the frame is due next, if one is, counting it as run. The last slice,
+    /// `SLICES_PER_FRAME - 1`, finishes the frame.
+    pub fn slice_due(&mut self) -> Option<u32> {
+        let now = Instant::now();
+        let slice_duration = Duration::from_secs_f64(1.0 / NTSC_FRAME_RATE / SLICES_PER_FRAME as f64);
+
+        if !self.due(now) {
+            return None;
+        }
+        let slice = self.slice;
+        self.slice = (slice + 1) % SLICES_PER_FRAME;
+        // Split so the slices add up to exactly a frame's audio
+        let samples = |slices: u32| OUTPUT_SAMPLES_PER_FRAME as u64 * slices as u64 / SLICES_PER_FRAME as u64;
+        self.audio.queue(samples(slice + 1) - samples(slice));
+        self.deadline = self.deadline.max(now - slice_duration) + slice_duration;
+        Some(slice)
+    }
+
+    /// Whether the frame after the one that's just come due is already due too, so emulation's
+    /// falling behind
+    pub fn behind(&mut self) -> bool {
+        self.due(Instant::now())
+    }
+
+    fn due(&mut self, now: Instant) -> bool {
+        match self.audio.buffered() {
+            Some(buffered) => buffered <= self.controls.latency_samples() as u64,
+            None => now >= self.deadline,
+        }
+    }
+}
+
+/// The UI's handle on the emulation thread, which keeps running until this is dropped
+pub struct Emulation {
+    core: Arc<Mutex<Core>>,
+    inputs: Sender<Input>,
+    events: Receiver<Event>,
+    frames: FrameReader,
+    closed: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Emulation {
+    /// Start running `core` on its own thread, timed by the audio `controls` belong to
+    pub fn start(core: Core, controls: AudioControls) -> Self {
+        let (mut writer, frames) = frame_buffer();
+        writer.publish_with_previous(core.nes.screen(), core.nes.previous_screen());
+        let core = Arc::new(Mutex::new(core));
+        let (inputs, input_receiver) = mpsc::channel();
+        let (event_sender, events) = mpsc::channel();
+        let closed = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let core = Arc::clone(&core);
+            let closed = Arc::clone(&closed);
+            let clock = FrameClock::new(controls);
+            thread::Builder::new()
+                .name("emulation".to_string())
+                .spawn(move || run(&core, &closed, clock, input_receiver, event_sender, writer))
+                .expect("couldn't start the emulation thread")
+        };
+        Self { core, inputs, events, frames, closed, thread: Some(thread) }
+    }
+
+    /// Lock the console away from the emulation thread. Don't hold on to it across anything that might
+    /// take a while, like a file dialog, as emulation waits until it's dropped.
+    pub fn lock(&self) -> MutexGuard<'_, Core> {
+        self.core.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn send_input(&self, input: Input) {
+        // The thread only hangs up when this is dropped
+        let _ = self.inputs.send(input);
+    }
+
+    /// Everything the emulation thread has said since last asked
+    pub fn take_events(&self) -> Vec<Event> {
+        self.events.try_iter().collect()
+    }
+
+    /// The newest frame the console's drawn
+    pub fn screen(&mut self) -> &[u8] {
+        self.frames.latest()
+    }
+
+    /// The newest frame the console's drawn and the one before it
+    pub fn screens(&mut self) -> (&[u8], &[u8]) {
+        self.frames.latest_with_previous()
+    }
+}
+
+impl Drop for Emulation {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(core: &Mutex<Core>, closed: &AtomicBool, mut clock: FrameClock, inputs: Receiver<Input>, events: Sender<Event>, mut frames: FrameWriter) {
+    let mut input = Input::default();
+    while !closed.load(Ordering::Acquire) {
+        // Only the latest input matters
+        loop {
+            match inputs.try_recv() {
+                Ok(latest) => input = latest,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+        let Some(slice) = clock.slice_due() else {
+            thread::sleep(IDLE_WAIT);
+            continue;
+        };
+
+        let mut core = core.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let stopped = if slice + 1 < SLICES_PER_FRAME {
+            core.run_due_slice(&input)
+        } else {
+            core.nes.set_falling_behind(clock.behind());
+            core.run_due_frame(&input)
+        };
+        if let Some(message) = stopped {
+            let _ = events.send(Event::Stopped(message));
+        }
+        // Published even when nothing ran, as the UI or netplay might have changed what's on screen
+        frames.publish_with_previous(core.nes.screen(), core.nes.previous_screen());
+    }
+}
@@ -0,0 +1,127 @@
+use eframe::egui;
+
+use crate::bus::{Event, EventKind};
+use crate::i18n::tr;
+use crate::nes::Nes;
+
+/// Dots and scanlines in an NTSC frame, pre-render line included
+const DOTS: u16 = 341;
+const SCANLINES: u16 = 262;
+
+/// Screen pixels per dot and scanline
+const SCALE: f32 = 2.0;
+
+/// How close the pointer has to be to an event, in screen pixels, to show what it is
+const HOVER_DISTANCE: f32 = 4.0;
+
+const VISIBLE_COLOR: egui::Color32 = egui::Color32::from_gray(40);
+
+fn kind_key(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::PpuWrite => "event_viewer.ppu",
+        EventKind::ApuWrite => "event_viewer.apu",
+        EventKind::MapperWrite => "event_viewer.mapper",
+        EventKind::Irq => "event_viewer.irq",
+    }
+}
+
+fn kind_color(kind: EventKind) -> egui::Color32 {
+    match kind {
+        EventKind::PpuWrite => egui::Color32::from_rgb(90, 170, 255),
+        EventKind::ApuWrite => egui::Color32::from_rgb(110, 210, 110),
+        EventKind::MapperWrite => egui::Color32::from_rgb(255, 170, 60),
+        EventKind::Irq => egui::Color32::from_rgb(240, 80, 80),
+    }
+}
+
+/// What an event did, like "$2005 = $3F", or which device raised an IRQ
+pub fn describe(event: &Event) -> String {
+    match event.kind {
+        EventKind::Irq => {
+            let source = match event.value {
+                0 => "event_viewer.irq_frame_counter",
+                1 => "event_viewer.irq_dmc",
+                _ => "event_viewer.irq_mapper",
+            };
+            format!("{} ({})", tr("event_viewer.irq"), tr(source))
+        },
+        _ => format!("${:04X} = ${:02X}", event.address, event.value),
+    }
+}
+
+/// Where an event's drawn in the plot, with the pre-render scanline along the top
+fn plot_position(rect: egui::Rect, event: &Event) -> egui::Pos2 {
+    let line = (event.scanline + 1) as f32;
+    rect.left_top() + egui::vec2((event.dot as f32 + 0.5) * SCALE, (line + 0.5) * SCALE)
+}
+
+/// Window plotting the last frame's register writes and IRQs by the dot and scanline they happened on,
+/// for working out raster effects and IRQ timing
+pub struct EventViewer {
+    pub open: bool,
+    /// Which kinds of event are drawn, in `EventKind::ALL` order
+    shown: [bool; EventKind::ALL.len()],
+}
+
+impl Default for EventViewer {
+    fn default() -> Self {
+        Self {
+            open: false,
+            shown: [true; EventKind::ALL.len()],
+        }
+    }
+}
+
+impl EventViewer {
+    pub fn show(&mut self, ctx: &egui::Context, nes: &Nes) {
+        let mut open = self.open;
+        egui::Window::new(tr("event_viewer.title"))
+            .id(egui::Id::new("event_viewer_window"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for (kind, shown) in EventKind::ALL.into_iter().zip(&mut self.shown) {
+                        let (rect, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                        ui.painter().rect_filled(rect, 2.0, kind_color(kind));
+                        ui.checkbox(shown, tr(kind_key(kind)));
+                    }
+                });
+
+                let events: Vec<&Event> = nes.frame_events()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|event| self.shown[EventKind::ALL.iter().position(|kind| *kind == event.kind).unwrap()])
+                    .collect();
+                ui.label(format!("{}: {}", tr("event_viewer.count"), events.len()));
+
+                let size = egui::vec2(DOTS as f32 * SCALE, SCANLINES as f32 * SCALE);
+                let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+                let rect = response.rect;
+                painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+                // The part of the frame that's drawn to the screen, dots 1-256 of scanlines 0-239
+                let visible = egui::Rect::from_min_size(rect.left_top() + egui::vec2(SCALE, SCALE), egui::vec2(256.0 * SCALE, 240.0 * SCALE));
+                painter.rect_filled(visible, 0.0, VISIBLE_COLOR);
+
+                for event in &events {
+                    let position = plot_position(rect, event);
+                    painter.rect_filled(egui::Rect::from_center_size(position, egui::vec2(SCALE + 1.0, SCALE + 1.0)), 0.0, kind_color(event.kind));
+                }
+
+                let Some(pointer) = response.hover_pos() else {
+                    return;
+                };
+                let hovered = events.iter()
+                    .map(|event| (event, plot_position(rect, event).distance(pointer)))
+                    .filter(|(_, distance)| *distance <= HOVER_DISTANCE)
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b));
+                if let Some((event, _)) = hovered {
+                    response.on_hover_ui_at_pointer(|ui| {
+                        ui.label(describe(event));
+                        ui.label(format!("{} {}, {} {}", tr("event_viewer.scanline"), event.scanline, tr("event_viewer.dot"), event.dot));
+                    });
+                }
+            });
+        self.open = open;
+    }
+}
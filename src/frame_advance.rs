@@ -0,0 +1,96 @@
+/// Speed multiplier while fast forward is held, on top of the usual speed
+pub const FAST_FORWARD_SPEED: f32 = 4.0;
+
+/// Speed multiplier while the window's in the background with `BackgroundMode::Throttle`
+pub const BACKGROUND_SPEED: f32 = 0.25;
+
+/// What emulation does while the window doesn't have focus
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackgroundMode {
+  /// Keep running as if nothing happened
+  #[default]
+  Run,
+  /// Pause until the window's focused again, with the audio faded out
+  Pause,
+  /// Keep running at `BACKGROUND_SPEED`, muted
+  Throttle,
+}
+
+impl BackgroundMode {
+  pub const ALL: [BackgroundMode; 3] = [BackgroundMode::Run, BackgroundMode::Pause, BackgroundMode::Throttle];
+
+  /// Key used both in the config file and for the UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      BackgroundMode::Run => "background.run",
+      BackgroundMode::Pause => "background.pause",
+      BackgroundMode::Throttle => "background.throttle",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|mode| mode.key() == key)
+  }
+
+  /// Multiplier on the emulation speed while in the background. Pausing doesn't slow anything down,
+  /// it stops frames running altogether.
+  pub fn speed(self) -> f32 {
+    match self {
+      BackgroundMode::Throttle => BACKGROUND_SPEED,
+      BackgroundMode::Run | BackgroundMode::Pause => 1.0,
+    }
+  }
+}
+
+/// How long frame advance has to be held before it starts advancing every frame
+const HOLD_DELAY_SECONDS: f64 = 0.4;
+
+/// Pause and frame advance state, deciding whether each frame that comes due actually runs
+#[derive(Default)]
+pub struct FrameAdvance {
+  pub paused: bool,
+  /// Frames asked for one press at a time, still to be run
+  queued: u32,
+  /// When the frame advance key went down, if it's still held
+  held_since: Option<f64>,
+  /// Whether it's been held long enough to keep advancing
+  holding: bool,
+}
+
+impl FrameAdvance {
+  pub fn toggle_pause(&mut self) {
+    self.paused = !self.paused;
+    self.queued = 0;
+  }
+
+  /// Step forward a single frame, pausing first if the console is running
+  pub fn advance(&mut self) {
+    if !self.paused {
+      self.paused = true;
+    } else if !self.holding {
+      self.queued += 1;
+    }
+  }
+
+  /// Call every UI frame with whether the frame advance key is down, and the UI clock in seconds
+  pub fn update_hold(&mut self, down: bool, now: f64) {
+    self.held_since = match (down, self.held_since) {
+      (true, None) => Some(now),
+      (true, since) => since,
+      (false, _) => None,
+    };
+    self.holding = self.paused && self.held_since.is_some_and(|since| now - since >= HOLD_DELAY_SECONDS);
+  }
+
+  /// Whether the frame that's come due should run
+  pub fn take_frame(&mut self) -> bool {
+    if !self.paused || self.holding {
+      return true;
+    }
+    if self.queued > 0 {
+      self.queued -= 1;
+      return true;
+    }
+    false
+  }
+}
@@ -0,0 +1,62 @@
+//! Frame skipping, for machines (usually slow browsers) that can't draw every frame at full speed.
+//! Skipped frames still run in full, so the game, its timing and its audio carry on exactly as they
+//! would otherwise. All that's left out is the PPU working out each pixel's colour and writing it to
+//! the screen, which is the most expensive part of a frame that nothing else depends on.
+
+/// The most frames skipped in a row, however far behind emulation is
+pub const MAX_FRAME_SKIP: u8 = 3;
+
+/// Which frames are drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameSkip {
+  /// Draw every frame
+  #[default]
+  Off,
+  /// Skip frames only while emulation's falling behind real time, up to `MAX_FRAME_SKIP` in a row
+  Auto,
+  /// Draw one frame in every this many plus one
+  Fixed(u8),
+}
+
+impl FrameSkip {
+  pub const ALL: [FrameSkip; 5] = [FrameSkip::Off, FrameSkip::Auto, FrameSkip::Fixed(1), FrameSkip::Fixed(2), FrameSkip::Fixed(3)];
+
+  /// Key used both in the config file and for the UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      FrameSkip::Off => "frame_skip.off",
+      FrameSkip::Auto => "frame_skip.auto",
+      FrameSkip::Fixed(0) => "frame_skip.off",
+      FrameSkip::Fixed(1) => "frame_skip.1",
+      FrameSkip::Fixed(2) => "frame_skip.2",
+      FrameSkip::Fixed(_) => "frame_skip.3",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|skip| skip.key() == key)
+  }
+}
+
+/// Decides frame by frame whether the next one's drawn
+#[derive(Clone, Debug, Default)]
+pub struct FrameSkipper {
+  pub mode: FrameSkip,
+  /// Whether emulation's running slower than real time, which `FrameSkip::Auto` skips frames for
+  pub behind: bool,
+  /// Frames skipped since the last one drawn
+  skipped: u8,
+}
+
+impl FrameSkipper {
+  /// Whether the next frame should be drawn, counting it as skipped if not
+  pub fn draw_next(&mut self) -> bool {
+    let skip = match self.mode {
+      FrameSkip::Off => false,
+      FrameSkip::Auto => self.behind && self.skipped < MAX_FRAME_SKIP,
+      FrameSkip::Fixed(frames) => self.skipped < frames.min(MAX_FRAME_SKIP),
+    };
+    self.skipped = if skip { self.skipped + 1 } else { 0 };
+    !skip
+  }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use eframe::egui;
+
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::input::InputBindings;
+use crate::palette::BuiltinPalette;
+use crate::ppu::Region;
+
+/// Settings for one game that take the place of the global ones whenever it's loaded. Anything left
+/// as `None` follows the global config.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameProfile {
+    /// Controls used instead of the global ones
+    pub input: Option<InputBindings>,
+    pub palette: Option<BuiltinPalette>,
+    pub hide_overscan: Option<bool>,
+    /// Whose PPU's wiring of the emphasis bits to use, instead of the one for the region the header
+    /// says the game was made for. Timing stays NTSC either way.
+    pub region: Option<Region>,
+    /// Whether the game's cheats are applied, so they can be switched off without losing them
+    pub cheats_enabled: bool,
+}
+
+impl Default for GameProfile {
+    fn default() -> Self {
+        Self {
+            input: None,
+            palette: None,
+            hide_overscan: None,
+            region: None,
+            cheats_enabled: true,
+        }
+    }
+}
+
+impl GameProfile {
+    /// The global config with this game's settings in place of the ones it overrides
+    pub fn apply(&self, config: &Config) -> Config {
+        let mut config = config.clone();
+        if let Some(input) = &self.input {
+            config.input = input.clone();
+        }
+        if let Some(palette) = self.palette {
+            config.palette = palette;
+            config.custom_palette = None;
+        }
+        if let Some(hide_overscan) = self.hide_overscan {
+            config.hide_overscan = hide_overscan;
+        }
+        config
+    }
+
+    fn load(settings: &Settings) -> Self {
+        let get = |key: &str| eframe::Storage::get_string(settings, key);
+        let has_input = settings.0.keys().any(|key| key.starts_with("input."));
+        Self {
+            input: has_input.then(|| InputBindings::load(settings)),
+            palette: get("palette").and_then(|key| BuiltinPalette::from_key(&key)),
+            hide_overscan: get("hide_overscan").and_then(|value| value.parse::<bool>().ok()),
+            region: get("region").and_then(|key| Region::from_key(&key)),
+            cheats_enabled: get("cheats_enabled").and_then(|value| value.parse::<bool>().ok()).unwrap_or(true),
+        }
+    }
+
+    fn save(&self, settings: &mut Settings) {
+        if let Some(input) = &self.input {
+            input.save(settings);
+        }
+        if let Some(palette) = self.palette {
+            settings.0.insert("palette".to_string(), palette.key().to_string());
+        }
+        if let Some(hide_overscan) = self.hide_overscan {
+            settings.0.insert("hide_overscan".to_string(), hide_overscan.to_string());
+        }
+        if let Some(region) = self.region {
+            settings.0.insert("region".to_string(), region.key().to_string());
+        }
+        if !self.cheats_enabled {
+            settings.0.insert("cheats_enabled".to_string(), false.to_string());
+        }
+    }
+}
+
+/// One game's settings by key, standing in for eframe's storage so the controls can be loaded and
+/// saved the same way the global ones are
+#[derive(Default)]
+struct Settings(HashMap<String, String>);
+
+impl eframe::Storage for Settings {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+
+    fn flush(&mut self) {}
+}
+
+/// Every game's profile, keyed by the SHA-256 of its ROM like its cheats
+#[derive(Default)]
+pub struct GameProfiles {
+    games: HashMap<String, GameProfile>,
+}
+
+impl GameProfiles {
+    /// Load the profiles from storage, skipping any settings that no longer make sense
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut profiles = GameProfiles::default();
+        let Some(saved) = storage.and_then(|storage| storage.get_string("game_profiles")) else {
+            return profiles;
+        };
+
+        // One setting per line: rom hash, setting, value
+        let mut games: HashMap<&str, Settings> = HashMap::new();
+        for line in saved.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(hash), Some(key), Some(value)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            games.entry(hash).or_default().0.insert(key.to_string(), value.to_string());
+        }
+        for (hash, settings) in games {
+            profiles.games.insert(hash.to_string(), GameProfile::load(&settings));
+        }
+
+        profiles
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        let mut saved = String::new();
+        for (hash, profile) in &self.games {
+            let mut settings = Settings::default();
+            profile.save(&mut settings);
+            for (key, value) in settings.0 {
+                saved += &format!("{}\t{}\t{}\n", hash, key, value);
+            }
+        }
+        storage.set_string("game_profiles", saved);
+    }
+
+    /// The game's profile, which follows the global config entirely if it's never been changed
+    pub fn get(&self, rom_hash: &str) -> GameProfile {
+        self.games.get(rom_hash).cloned().unwrap_or_default()
+    }
+
+    pub fn get_mut(&mut self, rom_hash: &str) -> &mut GameProfile {
+        self.games.entry(rom_hash.to_string()).or_default()
+    }
+}
+
+/// The "Game Properties" window, for changing the loaded game's profile
+#[derive(Default)]
+pub struct GameProfileWindow {
+    pub open: bool,
+}
+
+impl GameProfileWindow {
+    /// Draw the window, if open. `profile` is `None` when no game is loaded, and `config` is the
+    /// global config the profile starts from. Returns whether the profile changed.
+    pub fn show(&mut self, ctx: &egui::Context, profile: Option<&mut GameProfile>, config: &Config) -> bool {
+        let mut changed = false;
+        let mut open = self.open;
+
+        egui::Window::new(tr("game.title"))
+            .id(egui::Id::new("game_profile_window"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(profile) = profile else {
+                    ui.label(tr("game.no_rom"));
+                    return;
+                };
+                let before = profile.clone();
+
+                egui::Grid::new("game_profile_grid").num_columns(2).show(ui, |ui| {
+                    ui.label(tr("menu.palette"));
+                    let palettes = BuiltinPalette::ALL.map(|palette| (palette, tr(palette.key())));
+                    override_combo(ui, "game_palette", &mut profile.palette, &palettes);
+                    ui.end_row();
+
+                    ui.label(tr("game.overscan"));
+                    override_combo(ui, "game_overscan", &mut profile.hide_overscan, &[(false, tr("game.show")), (true, tr("game.hide"))]);
+                    ui.end_row();
+
+                    ui.label(tr("game.emphasis")).on_hover_text(tr("game.emphasis_hint"));
+                    let regions = Region::ALL.map(|region| (region, tr(emphasis_key(region))));
+                    override_combo(ui, "game_emphasis", &mut profile.region, &regions);
+                    ui.end_row();
+                });
+                ui.checkbox(&mut profile.cheats_enabled, tr("game.cheats"));
+
+                let mut own_controls = profile.input.is_some();
+                if ui.checkbox(&mut own_controls, tr("game.controls")).on_hover_text(tr("game.controls_hint")).changed() {
+                    // Starting from the global controls, so only what's different needs rebinding
+                    profile.input = own_controls.then(|| config.input.clone());
+                }
+
+                changed = *profile != before;
+            });
+
+        self.open = open;
+        changed
+    }
+}
+
+/// A combo box choosing between following the global setting and each of `choices`
+fn override_combo<T: Copy + PartialEq>(ui: &mut egui::Ui, id: &str, value: &mut Option<T>, choices: &[(T, &str)]) {
+    let selected = value
+        .and_then(|value| choices.iter().find(|(choice, _)| *choice == value))
+        .map_or(tr("game.default"), |(_, label)| label);
+    egui::ComboBox::from_id_source(id)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(value, None, tr("game.default"));
+            for (choice, label) in choices {
+                ui.selectable_value(value, Some(*choice), *label);
+            }
+        });
+}
+
+fn emphasis_key(region: Region) -> &'static str {
+    match region {
+        Region::Ntsc => "emphasis.ntsc",
+        Region::Pal => "emphasis.pal",
+    }
+}
@@ -0,0 +1,362 @@
+use eframe::egui;
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+use crate::command::Command;
+use crate::i18n::tr;
+
+/// Everything that can be bound to a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HotkeyAction {
+    LoadRom,
+    SaveState,
+    LoadState,
+    Screenshot,
+    Pause,
+    FrameAdvance,
+    FastForward,
+    Rewind,
+    Fullscreen,
+    Mute,
+}
+
+impl HotkeyAction {
+    pub const ALL: [HotkeyAction; 10] = [
+        HotkeyAction::LoadRom,
+        HotkeyAction::SaveState,
+        HotkeyAction::LoadState,
+        HotkeyAction::Screenshot,
+        HotkeyAction::Pause,
+        HotkeyAction::FrameAdvance,
+        HotkeyAction::FastForward,
+        HotkeyAction::Rewind,
+        HotkeyAction::Fullscreen,
+        HotkeyAction::Mute,
+    ];
+
+    /// Stable name for storing the binding
+    pub fn key(self) -> &'static str {
+        match self {
+            HotkeyAction::LoadRom => "load_rom",
+            HotkeyAction::SaveState => "save_state",
+            HotkeyAction::LoadState => "load_state",
+            HotkeyAction::Screenshot => "screenshot",
+            HotkeyAction::Pause => "pause",
+            HotkeyAction::FrameAdvance => "frame_advance",
+            HotkeyAction::FastForward => "fast_forward",
+            HotkeyAction::Rewind => "rewind",
+            HotkeyAction::Fullscreen => "fullscreen",
+            HotkeyAction::Mute => "mute",
+        }
+    }
+
+    /// Key for the UI string naming the action
+    pub fn label_key(self) -> &'static str {
+        match self {
+            HotkeyAction::LoadRom => "hotkeys.load_rom",
+            HotkeyAction::SaveState => "hotkeys.save_state",
+            HotkeyAction::LoadState => "hotkeys.load_state",
+            HotkeyAction::Screenshot => "hotkeys.screenshot",
+            HotkeyAction::Pause => "hotkeys.pause",
+            HotkeyAction::FrameAdvance => "hotkeys.frame_advance",
+            HotkeyAction::FastForward => "hotkeys.fast_forward",
+            HotkeyAction::Rewind => "hotkeys.rewind",
+            HotkeyAction::Fullscreen => "hotkeys.fullscreen",
+            HotkeyAction::Mute => "hotkeys.mute",
+        }
+    }
+
+    /// What pressing the key does. Fast forward and rewind only last while the key is held, so they
+    /// don't have one and the frontends check for them with [`Hotkeys::held`] instead.
+    pub fn command(self) -> Option<Command> {
+        match self {
+            HotkeyAction::LoadRom => Some(Command::LoadRom),
+            HotkeyAction::SaveState => Some(Command::SaveState(0)),
+            HotkeyAction::LoadState => Some(Command::LoadState(0)),
+            HotkeyAction::Screenshot => Some(Command::SaveScreenshot),
+            HotkeyAction::Pause => Some(Command::TogglePause),
+            HotkeyAction::FrameAdvance => Some(Command::FrameAdvance),
+            HotkeyAction::FastForward | HotkeyAction::Rewind => None,
+            HotkeyAction::Fullscreen => Some(Command::ToggleFullscreen),
+            HotkeyAction::Mute => Some(Command::ToggleMute),
+        }
+    }
+
+    fn default_shortcut(self) -> KeyboardShortcut {
+        let key = match self {
+            HotkeyAction::LoadRom => return KeyboardShortcut::new(Modifiers::COMMAND, Key::O),
+            HotkeyAction::SaveState => Key::F5,
+            HotkeyAction::LoadState => Key::F9,
+            HotkeyAction::Screenshot => Key::F12,
+            HotkeyAction::Pause => Key::P,
+            HotkeyAction::FrameAdvance => Key::Backslash,
+            HotkeyAction::FastForward => Key::Tab,
+            HotkeyAction::Rewind => Key::Backspace,
+            HotkeyAction::Fullscreen => Key::F11,
+            HotkeyAction::Mute => Key::M,
+        };
+        KeyboardShortcut::new(Modifiers::NONE, key)
+    }
+
+    fn index(self) -> usize {
+        HotkeyAction::ALL.iter().position(|action| *action == self).unwrap()
+    }
+}
+
+/// What one action is bound to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hotkey {
+    pub shortcut: Option<KeyboardShortcut>,
+    pub enabled: bool,
+}
+
+/// The key bound to every action
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hotkeys {
+    bindings: [Hotkey; HotkeyAction::ALL.len()],
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Self {
+            bindings: HotkeyAction::ALL.map(|action| Hotkey { shortcut: Some(action.default_shortcut()), enabled: true }),
+        }
+    }
+}
+
+impl Hotkeys {
+    pub fn get(&self, action: HotkeyAction) -> Hotkey {
+        self.bindings[action.index()]
+    }
+
+    pub fn set_shortcut(&mut self, action: HotkeyAction, shortcut: Option<KeyboardShortcut>) {
+        self.bindings[action.index()].shortcut = shortcut;
+    }
+
+    pub fn set_enabled(&mut self, action: HotkeyAction, enabled: bool) {
+        self.bindings[action.index()].enabled = enabled;
+    }
+
+    /// The other enabled actions bound to the same shortcut as `action`, if it's enabled itself
+    pub fn conflicts(&self, action: HotkeyAction) -> Vec<HotkeyAction> {
+        let hotkey = self.get(action);
+        let Some(shortcut) = hotkey.shortcut.filter(|_| hotkey.enabled) else {
+            return vec![];
+        };
+        HotkeyAction::ALL
+            .into_iter()
+            .filter(|other| *other != action)
+            .filter(|other| {
+                let other = self.get(*other);
+                other.enabled && other.shortcut == Some(shortcut)
+            })
+            .collect()
+    }
+
+    /// The shortcut that triggers `action`, if it's bound and enabled. Conflicting bindings don't
+    /// trigger anything, rather than one of them quietly winning, until they're sorted out.
+    pub fn active(&self, action: HotkeyAction) -> Option<KeyboardShortcut> {
+        let hotkey = self.get(action);
+        hotkey.shortcut.filter(|_| hotkey.enabled && self.conflicts(action).is_empty())
+    }
+
+    /// Commands for the hotkeys pressed this frame, consuming the key presses
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Command> {
+        let typing = ctx.wants_keyboard_input();
+        let mut bound: Vec<(KeyboardShortcut, Command)> = HotkeyAction::ALL
+            .into_iter()
+            .filter_map(|action| Some((self.active(action)?, action.command()?)))
+            .filter(|(shortcut, _)| !(typing && types_text(shortcut)))
+            .collect();
+        // Extra Shift and Alt are ignored when matching, so check Shift+F5 before F5
+        bound.sort_by_key(|(shortcut, _)| std::cmp::Reverse(modifier_count(shortcut.modifiers)));
+        bound
+            .into_iter()
+            .filter(|(shortcut, _)| ctx.input_mut(|i| i.consume_shortcut(shortcut)))
+            .map(|(_, command)| command)
+            .collect()
+    }
+
+    /// Whether the key for `action` is being held down
+    pub fn held(&self, ctx: &egui::Context, action: HotkeyAction) -> bool {
+        self.active(action)
+            .filter(|shortcut| !(ctx.wants_keyboard_input() && types_text(shortcut)))
+            .is_some_and(|shortcut| ctx.input(|i| i.key_down(shortcut.logical_key) && i.modifiers.matches_logically(shortcut.modifiers)))
+    }
+
+    /// Load the bindings from storage, keeping the default for any action that isn't there
+    pub fn load(storage: &dyn eframe::Storage) -> Self {
+        let mut hotkeys = Hotkeys::default();
+        for action in HotkeyAction::ALL {
+            if let Some(shortcut) = storage.get_string(&format!("hotkey.{}", action.key())) {
+                // An empty string is an action that's been deliberately unbound
+                let shortcut = if shortcut.is_empty() { None } else { decode_shortcut(&shortcut) };
+                hotkeys.set_shortcut(action, shortcut);
+            }
+            if let Some(enabled) = storage.get_string(&format!("hotkey.{}.enabled", action.key())) {
+                hotkeys.set_enabled(action, enabled != "false");
+            }
+        }
+        hotkeys
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        for action in HotkeyAction::ALL {
+            let hotkey = self.get(action);
+            storage.set_string(&format!("hotkey.{}", action.key()), hotkey.shortcut.map(|shortcut| encode_shortcut(&shortcut)).unwrap_or_default());
+            storage.set_string(&format!("hotkey.{}.enabled", action.key()), hotkey.enabled.to_string());
+        }
+    }
+}
+
+/// Whether a shortcut is something that could just as well be typing, like a plain letter,
+/// so it should be left alone while a text field has focus
+fn types_text(shortcut: &KeyboardShortcut) -> bool {
+    let modifiers = shortcut.modifiers;
+    let function_key = shortcut.logical_key.name().strip_prefix('F').is_some_and(|number| number.parse::<u8>().is_ok());
+    !(modifiers.command || modifiers.ctrl || modifiers.mac_cmd || modifiers.alt || function_key || shortcut.logical_key == Key::Escape)
+}
+
+fn modifier_count(modifiers: Modifiers) -> usize {
+    [modifiers.command || modifiers.ctrl || modifiers.mac_cmd, modifiers.alt, modifiers.shift].into_iter().filter(|held| *held).count()
+}
+
+/// A shortcut as stored, e.g. `Cmd+Shift+S`, where `Cmd` is Ctrl everywhere but a Mac
+pub fn encode_shortcut(shortcut: &KeyboardShortcut) -> String {
+    let modifiers = shortcut.modifiers;
+    let mut encoded = String::new();
+    if modifiers.command || modifiers.mac_cmd {
+        encoded += "Cmd+";
+    } else if modifiers.ctrl {
+        encoded += "Ctrl+";
+    }
+    if modifiers.alt {
+        encoded += "Alt+";
+    }
+    if modifiers.shift {
+        encoded += "Shift+";
+    }
+    encoded + shortcut.logical_key.name()
+}
+
+pub fn decode_shortcut(encoded: &str) -> Option<KeyboardShortcut> {
+    // Split from the right, since the key itself might be `+`
+    let (modifier_names, key) = match encoded.rsplit_once('+') {
+        Some((modifier_names, "")) => (modifier_names.strip_suffix('+').unwrap_or(""), "+"),
+        Some(split) => split,
+        None => ("", encoded),
+    };
+    let mut modifiers = Modifiers::NONE;
+    for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+        modifiers = modifiers | match name {
+            "Cmd" => Modifiers::COMMAND,
+            "Ctrl" => Modifiers::CTRL,
+            "Alt" => Modifiers::ALT,
+            "Shift" => Modifiers::SHIFT,
+            _ => return None,
+        };
+    }
+    Some(KeyboardShortcut::new(modifiers, Key::from_name(key)?))
+}
+
+/// A key pressed while rebinding, as the shortcut it should become. Ctrl on Windows and Linux and
+/// Cmd on a Mac both become the platform's command key, so bindings carry over between them.
+fn captured_shortcut(key: Key, pressed: Modifiers) -> KeyboardShortcut {
+    let mut modifiers = Modifiers::NONE;
+    if pressed.command {
+        modifiers = modifiers | Modifiers::COMMAND;
+    } else if pressed.ctrl {
+        modifiers = modifiers | Modifiers::CTRL;
+    }
+    if pressed.alt {
+        modifiers = modifiers | Modifiers::ALT;
+    }
+    if pressed.shift {
+        modifiers = modifiers | Modifiers::SHIFT;
+    }
+    KeyboardShortcut::new(modifiers, key)
+}
+
+/// Settings window for binding actions to keys
+#[derive(Default)]
+pub struct HotkeyWindow {
+    pub open: bool,
+    /// The action waiting for a key to be pressed
+    capturing: Option<HotkeyAction>,
+}
+
+impl HotkeyWindow {
+    /// Whether the window is waiting for a key, so hotkeys shouldn't fire
+    pub fn capturing(&self) -> bool {
+        self.open && self.capturing.is_some()
+    }
+
+    /// Draw the window, if open, returning the bindings if they've been changed
+    pub fn show(&mut self, ctx: &egui::Context, hotkeys: &Hotkeys) -> Option<Hotkeys> {
+        if !self.open {
+            self.capturing = None;
+            return None;
+        }
+
+        let mut changed = hotkeys.clone();
+        if let Some(action) = self.capturing {
+            let pressed = ctx.input(|i| i.events.iter().find_map(|event| match event {
+                egui::Event::Key { key, pressed: true, modifiers, .. } => Some((*key, *modifiers)),
+                _ => None,
+            }));
+            match pressed {
+                Some((Key::Escape, _)) => self.capturing = None,
+                Some((key, modifiers)) => {
+                    changed.set_shortcut(action, Some(captured_shortcut(key, modifiers)));
+                    self.capturing = None;
+                },
+                None => {},
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new(tr("hotkeys.title"))
+            .id(egui::Id::new("hotkey_window"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("hotkey_grid").num_columns(4).show(ui, |ui| {
+                    for action in HotkeyAction::ALL {
+                        let hotkey = changed.get(action);
+                        let mut enabled = hotkey.enabled;
+                        if ui.checkbox(&mut enabled, tr(action.label_key())).changed() {
+                            changed.set_enabled(action, enabled);
+                        }
+
+                        let text = if self.capturing == Some(action) {
+                            tr("hotkeys.press_key").to_string()
+                        } else {
+                            hotkey.shortcut.map(|shortcut| ctx.format_shortcut(&shortcut)).unwrap_or_else(|| tr("hotkeys.unbound").to_string())
+                        };
+                        if ui.add_enabled(enabled, egui::Button::new(text).min_size(egui::vec2(120.0, 0.0))).clicked() {
+                            self.capturing = Some(action);
+                        }
+                        if ui.add_enabled(hotkey.shortcut.is_some(), egui::Button::new(tr("hotkeys.clear"))).clicked() {
+                            changed.set_shortcut(action, None);
+                        }
+
+                        let conflicts = changed.conflicts(action);
+                        if conflicts.is_empty() {
+                            ui.label("");
+                        } else {
+                            let names: Vec<&str> = conflicts.iter().map(|other| tr(other.label_key())).collect();
+                            ui.colored_label(ui.visuals().error_fg_color, format!("{} {}", tr("hotkeys.conflict"), names.join(", ")));
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                if ui.button(tr("hotkeys.reset")).clicked() {
+                    changed = Hotkeys::default();
+                    self.capturing = None;
+                }
+            });
+        self.open = open;
+
+        (changed != *hotkeys).then_some(changed)
+    }
+}
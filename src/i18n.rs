@@ -0,0 +1,617 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Languages the UI can be displayed in
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Spanish];
+
+    /// The name of the language in that language, so users can always find their own
+    pub fn native_name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Spanish => "Español",
+        }
+    }
+
+    /// Short code used when persisting the choice
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Language::ALL.into_iter().find(|language| language.code() == code)
+    }
+
+    fn strings(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => ENGLISH,
+            Language::Spanish => SPANISH,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Language::Spanish,
+            _ => Language::English,
+        }
+    }
+}
+
+static CURRENT_LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_language(language: Language) {
+    CURRENT_LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    Language::from_u8(CURRENT_LANGUAGE.load(Ordering::Relaxed))
+}
+
+/// Look up the UI string for `key` in the current language.
+///
+/// Falls back to English if the current language is missing the key, and to the key itself
+/// if English is missing it too, so a missing translation is visible rather than blank.
+pub fn tr(key: &'static str) -> &'static str {
+    lookup(language(), key)
+        .or_else(|| lookup(Language::English, key))
+        .unwrap_or(key)
+}
+
+fn lookup(language: Language, key: &str) -> Option<&'static str> {
+    language.strings().iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("menu.file", "File"),
+    ("menu.load_rom", "Load ROM"),
+    ("menu.library", "Library…"),
+    ("library.title", "Library"),
+    ("library.open_folder", "Open Folder…"),
+    ("library.rescan", "Rescan"),
+    ("library.filter", "Filter:"),
+    ("library.no_folder", "Open a folder of ROMs to list them here. Double-click a game to play it."),
+    ("library.game", "Game"),
+    ("library.mapper", "Mapper"),
+    ("library.last_played", "Last Played"),
+    ("library.play_time", "Play Time"),
+    ("library.never", "Never"),
+    ("menu.test_pattern", "Load Test Pattern"),
+    ("menu.demos", "Play Built-in Demo"),
+    ("demos.welcome", "No ROM loaded. Load one from the File menu, or play a built-in demo:"),
+    ("menu.save_state", "Save State"),
+    ("menu.load_state", "Load State"),
+    ("menu.pause", "Pause"),
+    ("menu.frame_advance", "Frame Advance"),
+    ("menu.reset", "Reset"),
+    ("menu.power_cycle", "Power Cycle"),
+    ("menu.cheats", "Cheats..."),
+    ("menu.game_properties", "Game Properties..."),
+    ("menu.ram_search", "RAM Search..."),
+    ("menu.conditions", "Conditions..."),
+    ("menu.practice", "Practice..."),
+    ("menu.apu_timeline", "APU Timeline..."),
+    ("menu.apu_viewer", "APU Viewer..."),
+    ("menu.event_viewer", "Event Viewer..."),
+    ("menu.nametable_viewer", "Nametable Viewer..."),
+    ("menu.code_profiler", "Code Profiler..."),
+    ("menu.netplay", "Netplay..."),
+    ("menu.quit", "Quit"),
+    ("menu.settings", "Settings"),
+    ("menu.language", "Language"),
+    ("menu.color_vision", "Color Vision"),
+    ("menu.ui_scale", "UI Scale"),
+    ("menu.video", "Video"),
+    ("menu.palette", "Palette"),
+    ("menu.load_palette", "Load .pal File..."),
+    ("menu.hide_overscan", "Hide Overscan"),
+    ("menu.flicker_blend", "Flicker Blend"),
+    ("flicker_blend.hint", "Blend each frame with the one before it, so sprites that flicker every other frame stay visible"),
+    ("menu.frame_skip", "Frame Skip"),
+    ("frame_skip.hint", "Leave some frames undrawn, so slow machines keep the game and its sound at full speed"),
+    ("frame_skip.off", "Off"),
+    ("frame_skip.auto", "Auto (when falling behind)"),
+    ("frame_skip.1", "Skip 1 in 2"),
+    ("frame_skip.2", "Skip 2 in 3"),
+    ("frame_skip.3", "Skip 3 in 4"),
+    ("menu.audio", "Audio"),
+    ("menu.power_on", "Power On"),
+    ("power_on.hint", "How the console starts, from the next time it's switched on"),
+    ("menu.background", "In Background"),
+    ("background.hint", "What the emulator does while its window doesn't have focus"),
+    ("background.run", "Keep running"),
+    ("background.pause", "Pause"),
+    ("background.throttle", "Run slowly, muted"),
+    ("power_on.ram", "RAM"),
+    ("ram_fill.pattern", "$00/$FF pattern"),
+    ("ram_fill.zeros", "All $00"),
+    ("ram_fill.ones", "All $FF"),
+    ("ram_fill.random", "Random"),
+    ("power_on.seed", "Seed"),
+    ("power_on.open_bus", "Open bus"),
+    ("power_on.five_step", "5-step frame counter"),
+    ("power_on.irq_inhibit", "Frame IRQs inhibited"),
+    ("menu.input", "Input..."),
+    ("menu.hotkeys", "Hotkeys..."),
+    ("menu.help", "Help"),
+    ("menu.console", "Developer Console"),
+    ("menu.about", "About"),
+    ("dialog.roms", "ROMs"),
+    ("dialog.palettes", "Palettes"),
+    ("cheats.title", "Cheats"),
+    ("cheats.no_rom", "Load a ROM to add cheats for it"),
+    ("cheats.code", "Code"),
+    ("cheats.description", "Description"),
+    ("cheats.add", "Add"),
+    ("cheats.remove", "Remove"),
+    ("game.title", "Game Properties"),
+    ("game.no_rom", "Load a ROM to change its settings"),
+    ("game.default", "Default"),
+    ("game.overscan", "Overscan"),
+    ("game.show", "Show"),
+    ("game.hide", "Hide"),
+    ("game.emphasis", "Color emphasis"),
+    ("game.emphasis_hint", "Which way round the PPU wires the red and green emphasis bits. Games run at NTSC speed either way."),
+    ("game.cheats", "Apply cheats"),
+    ("game.controls", "Use different controls for this game"),
+    ("game.controls_hint", "While this is on, Settings > Input changes this game's controls"),
+    ("emphasis.ntsc", "NTSC PPU"),
+    ("emphasis.pal", "PAL PPU (red and green swapped)"),
+    ("ram_search.title", "RAM Search"),
+    ("ram_search.new_search", "New Search"),
+    ("ram_search.changed", "Changed"),
+    ("ram_search.unchanged", "Unchanged"),
+    ("ram_search.greater", "Greater"),
+    ("ram_search.less", "Less"),
+    ("ram_search.results", "Results"),
+    ("ram_search.pin", "Pin"),
+    ("ram_search.unpin", "Unpin"),
+    ("ram_search.watch_list", "Watch List"),
+    ("ram_search.freeze", "Freeze"),
+    ("conditions.title", "Conditions"),
+    ("conditions.label", "Label"),
+    ("conditions.add", "Add"),
+    ("conditions.remove", "Remove"),
+    ("conditions.hits", "Hits"),
+    ("conditions.met", "Condition met"),
+    ("condition.equal", "="),
+    ("condition.not_equal", "!="),
+    ("condition.less", "<"),
+    ("condition.greater", ">"),
+    ("condition.changed", "changed"),
+    ("condition.increased", "went up"),
+    ("condition.decreased", "went down"),
+    ("practice.title", "Practice"),
+    ("practice.enabled", "Practice mode"),
+    ("practice.hint", "Number keys load their slot, Shift + a number key saves to it"),
+    ("practice.empty", "Empty"),
+    ("practice.frame", "Frame"),
+    ("practice.save", "Save"),
+    ("practice.load", "Load"),
+    ("practice.death", "Death detection"),
+    ("practice.death_hint", "Save to a slot whenever this condition on memory is met, e.g. the lives counter going down"),
+    ("practice.death_slot", "Save to slot"),
+    ("practice.set", "Set"),
+    ("practice.clear", "Clear"),
+    ("apu_timeline.title", "APU Timeline"),
+    ("apu_timeline.channel", "Channel"),
+    ("apu_timeline.frames", "Frames"),
+    ("apu_timeline.pulse1", "Pulse 1"),
+    ("apu_timeline.pulse2", "Pulse 2"),
+    ("apu_timeline.triangle", "Triangle"),
+    ("apu_timeline.noise", "Noise"),
+    ("apu_timeline.period", "Period"),
+    ("apu_timeline.sweep_target", "Sweep target"),
+    ("apu_timeline.envelope", "Envelope"),
+    ("apu_timeline.length", "Length counter"),
+    ("apu_viewer.title", "APU Viewer"),
+    ("apu_viewer.channel", "Channel"),
+    ("apu_viewer.frequency", "Frequency"),
+    ("apu_viewer.note", "Note"),
+    ("apu_viewer.duty", "Duty"),
+    ("apu_viewer.volume", "Volume"),
+    ("apu_viewer.bytes", "bytes left"),
+    ("apu_viewer.looping", "looping"),
+    ("event_viewer.title", "Event Viewer"),
+    ("event_viewer.ppu", "PPU writes"),
+    ("event_viewer.apu", "APU writes"),
+    ("event_viewer.mapper", "Mapper writes"),
+    ("event_viewer.irq", "IRQs"),
+    ("event_viewer.irq_frame_counter", "frame counter"),
+    ("event_viewer.irq_dmc", "DMC"),
+    ("event_viewer.irq_mapper", "mapper"),
+    ("event_viewer.count", "Events last frame"),
+    ("event_viewer.scanline", "Scanline"),
+    ("event_viewer.dot", "dot"),
+    ("nametable_viewer.title", "Nametable Viewer"),
+    ("nametable_viewer.scroll", "Show what each line of the last frame scrolled to"),
+    ("nametable_viewer.tile", "Tile"),
+    ("code_profile.title", "Code Profiler"),
+    ("code_profile.bank", "By bank"),
+    ("code_profile.page", "By page"),
+    ("code_profile.instruction", "By instruction"),
+    ("code_profile.clear", "Clear"),
+    ("code_profile.frames", "Frames"),
+    ("code_profile.cycles_per_frame", "cycles per frame"),
+    ("code_profile.cycles", "cycles/frame"),
+    ("code_profile.prg_bank", "PRG bank"),
+    ("code_profile.ram", "RAM"),
+    ("hotkeys.title", "Hotkeys"),
+    ("hotkeys.load_rom", "Load ROM"),
+    ("hotkeys.save_state", "Save state"),
+    ("hotkeys.load_state", "Load state"),
+    ("hotkeys.screenshot", "Screenshot"),
+    ("hotkeys.pause", "Pause"),
+    ("hotkeys.frame_advance", "Frame advance"),
+    ("hotkeys.fast_forward", "Fast forward (hold)"),
+    ("hotkeys.rewind", "Rewind (hold)"),
+    ("hotkeys.fullscreen", "Fullscreen"),
+    ("hotkeys.mute", "Mute"),
+    ("hotkeys.press_key", "Press a key..."),
+    ("hotkeys.unbound", "Unbound"),
+    ("hotkeys.clear", "Clear"),
+    ("hotkeys.conflict", "Same key as:"),
+    ("hotkeys.reset", "Reset to Defaults"),
+    ("input.title", "Input"),
+    ("input.up", "Up"),
+    ("input.down", "Down"),
+    ("input.left", "Left"),
+    ("input.right", "Right"),
+    ("input.select", "Select"),
+    ("input.start", "Start"),
+    ("input.b", "B"),
+    ("input.a", "A"),
+    ("input.turbo_b", "Turbo B"),
+    ("input.turbo_a", "Turbo A"),
+    ("input.turbo_rate", "Frames per turbo press"),
+    ("netplay.title", "Netplay"),
+    ("netplay.no_rom", "Load a ROM first, both players need the same one"),
+    ("netplay.port", "Port"),
+    ("netplay.host", "Host"),
+    ("netplay.address", "Host address"),
+    ("netplay.join", "Join"),
+    ("netplay.waiting", "Waiting for another player to join..."),
+    ("netplay.connecting", "Connecting..."),
+    ("netplay.connected", "Connected"),
+    ("netplay.disconnect", "Disconnect"),
+    ("about.title", "About"),
+    ("about.created_by", "Created by Daniel Adams"),
+    ("color_vision.normal", "Normal"),
+    ("color_vision.protanopia", "Protanopia (red-blind)"),
+    ("color_vision.deuteranopia", "Deuteranopia (green-blind)"),
+    ("color_vision.tritanopia", "Tritanopia (blue-blind)"),
+    ("palette.nesdev", "NESDev Consensus"),
+    ("palette.fceux", "FCEUX"),
+    ("palette.sony_cxa", "Sony CXA2025AS"),
+    ("palette.custom", "Custom"),
+    ("audio.output", "Output"),
+    ("audio.device", "Default device"),
+    ("audio.null", "None (no audio device found)"),
+    ("audio.waiting", "Click or press a key to start"),
+    ("audio.no_device", "No audio device found, continuing without sound"),
+    ("audio.driver", "Driver"),
+    ("audio.rodio", "rodio"),
+    ("audio.cpal", "cpal (direct)"),
+    ("audio.mute", "Mute"),
+    ("audio.volume", "Volume"),
+    ("audio.latency", "Latency"),
+    ("audio.sync", "Timing"),
+    ("audio.sync_hint", "What sets the emulation speed. Going by the audio keeps the sound from drifting, skipping or running dry."),
+    ("sync.audio", "Audio"),
+    ("sync.wall_clock", "Wall clock"),
+    ("audio.ultrasonic_triangle", "Ultrasonic triangle"),
+    ("audio.ultrasonic_triangle_hint", "Play the triangle at the very short periods games use to silence it, as the console does. Accurate, but it pops."),
+    ("audio.reduce_pulse_popping", "Reduce pulse popping"),
+    ("audio.reduce_pulse_popping_hint", "Keep the pulse channels' waves running when a game changes their pitch mid note, rather than restarting them as the console does. Less accurate, but games with vibrato click less."),
+    ("audio.mixer", "Mixer"),
+    ("audio.mixer_hint", "How the channels are mixed. Accurate uses the console's own mixer and filters, at some cost in speed."),
+    ("mixer.fast", "Fast"),
+    ("mixer.accurate", "Accurate"),
+    ("audio.stereo", "Stereo"),
+    ("audio.stereo_hint", "Spread the channels between the left and right speakers. The console itself only has mono out."),
+    ("stereo.mono", "Mono"),
+    ("stereo.split", "Pulses left, triangle and noise right"),
+    ("stereo.custom", "Custom"),
+    ("audio.pan", "Pan"),
+    ("audio.pan_hint", "Where the channel sits, from -100 all the way left to 100 all the way right"),
+    ("audio.channels", "Channels"),
+    ("audio.mute_channel", "M"),
+    ("audio.solo_channel", "S"),
+    ("channel.pulse1", "Pulse 1"),
+    ("channel.pulse2", "Pulse 2"),
+    ("channel.triangle", "Triangle"),
+    ("channel.noise", "Noise"),
+    ("channel.dmc", "DMC"),
+    ("channel.expansion", "Expansion"),
+    ("rom_error.title", "Couldn't Load ROM"),
+    ("rom_error.supported", "Supported mappers"),
+    ("rom_error.mapper", "Mapper"),
+    ("rom_error.name", "Name"),
+    ("rom_error.board", "Boards"),
+    ("rom_error.prg_ram", "PRG RAM"),
+    ("rom_error.irq", "IRQ"),
+    ("rom_error.audio", "Audio"),
+    ("rom_error.ok", "OK"),
+    ("menu.unimplemented", "This game uses mapper features that aren't implemented yet, so it may not run correctly:"),
+];
+
+const SPANISH: &[(&str, &str)] = &[
+    ("menu.file", "Archivo"),
+    ("menu.load_rom", "Cargar ROM"),
+    ("menu.library", "Biblioteca…"),
+    ("library.title", "Biblioteca"),
+    ("library.open_folder", "Abrir carpeta…"),
+    ("library.rescan", "Volver a buscar"),
+    ("library.filter", "Filtro:"),
+    ("library.no_folder", "Abre una carpeta de ROMs para verlas aquí. Haz doble clic en un juego para jugarlo."),
+    ("library.game", "Juego"),
+    ("library.mapper", "Mapper"),
+    ("library.last_played", "Última partida"),
+    ("library.play_time", "Tiempo de juego"),
+    ("library.never", "Nunca"),
+    ("menu.test_pattern", "Cargar patrón de prueba"),
+    ("menu.demos", "Jugar demo incluida"),
+    ("demos.welcome", "No hay ninguna ROM cargada. Carga una desde el menú Archivo o juega una demo incluida:"),
+    ("menu.save_state", "Guardar estado"),
+    ("menu.load_state", "Cargar estado"),
+    ("menu.pause", "Pausa"),
+    ("menu.frame_advance", "Avanzar un fotograma"),
+    ("menu.reset", "Reiniciar"),
+    ("menu.power_cycle", "Apagar y encender"),
+    ("menu.cheats", "Trucos..."),
+    ("menu.game_properties", "Propiedades del juego..."),
+    ("menu.ram_search", "Buscar en RAM..."),
+    ("menu.conditions", "Condiciones..."),
+    ("menu.practice", "Práctica..."),
+    ("menu.apu_timeline", "Línea de tiempo del APU..."),
+    ("menu.apu_viewer", "Visor del APU..."),
+    ("menu.event_viewer", "Visor de eventos..."),
+    ("menu.nametable_viewer", "Visor de nametables..."),
+    ("menu.code_profiler", "Perfilador de código..."),
+    ("menu.netplay", "Juego en red..."),
+    ("menu.quit", "Salir"),
+    ("menu.settings", "Configuración"),
+    ("menu.language", "Idioma"),
+    ("menu.color_vision", "Visión del color"),
+    ("menu.ui_scale", "Escala de la interfaz"),
+    ("menu.video", "Vídeo"),
+    ("menu.palette", "Paleta"),
+    ("menu.load_palette", "Cargar archivo .pal..."),
+    ("menu.hide_overscan", "Ocultar overscan"),
+    ("menu.flicker_blend", "Mezclar parpadeo"),
+    ("flicker_blend.hint", "Mezcla cada fotograma con el anterior, para que los sprites que parpadean cada dos fotogramas sigan visibles"),
+    ("menu.frame_skip", "Salto de fotogramas"),
+    ("frame_skip.hint", "Deja algunos fotogramas sin dibujar, para que los equipos lentos mantengan el juego y su sonido a velocidad completa"),
+    ("frame_skip.off", "Desactivado"),
+    ("frame_skip.auto", "Automático (al quedarse atrás)"),
+    ("frame_skip.1", "Saltar 1 de cada 2"),
+    ("frame_skip.2", "Saltar 2 de cada 3"),
+    ("frame_skip.3", "Saltar 3 de cada 4"),
+    ("menu.audio", "Audio"),
+    ("menu.power_on", "Encendido"),
+    ("power_on.hint", "Cómo arranca la consola, a partir de la próxima vez que se encienda"),
+    ("menu.background", "En segundo plano"),
+    ("background.hint", "Qué hace el emulador mientras su ventana no tiene el foco"),
+    ("background.run", "Seguir"),
+    ("background.pause", "Pausar"),
+    ("background.throttle", "Ir despacio, sin sonido"),
+    ("power_on.ram", "RAM"),
+    ("ram_fill.pattern", "Patrón $00/$FF"),
+    ("ram_fill.zeros", "Todo $00"),
+    ("ram_fill.ones", "Todo $FF"),
+    ("ram_fill.random", "Aleatoria"),
+    ("power_on.seed", "Semilla"),
+    ("power_on.open_bus", "Bus abierto"),
+    ("power_on.five_step", "Contador de fotogramas de 5 pasos"),
+    ("power_on.irq_inhibit", "IRQ de fotograma inhibidas"),
+    ("menu.input", "Controles..."),
+    ("menu.hotkeys", "Atajos de teclado..."),
+    ("menu.help", "Ayuda"),
+    ("menu.console", "Consola de desarrollo"),
+    ("menu.about", "Acerca de"),
+    ("dialog.roms", "ROMs"),
+    ("dialog.palettes", "Paletas"),
+    ("cheats.title", "Trucos"),
+    ("cheats.no_rom", "Carga una ROM para añadirle trucos"),
+    ("cheats.code", "Código"),
+    ("cheats.description", "Descripción"),
+    ("cheats.add", "Añadir"),
+    ("cheats.remove", "Quitar"),
+    ("game.title", "Propiedades del juego"),
+    ("game.no_rom", "Carga una ROM para cambiar sus ajustes"),
+    ("game.default", "Predeterminado"),
+    ("game.overscan", "Overscan"),
+    ("game.show", "Mostrar"),
+    ("game.hide", "Ocultar"),
+    ("game.emphasis", "Énfasis de color"),
+    ("game.emphasis_hint", "Cómo conecta la PPU los bits de énfasis rojo y verde. Los juegos van a velocidad NTSC en ambos casos."),
+    ("game.cheats", "Aplicar trucos"),
+    ("game.controls", "Usar otros controles para este juego"),
+    ("game.controls_hint", "Mientras esté activado, Configuración > Controles cambia los controles de este juego"),
+    ("emphasis.ntsc", "PPU NTSC"),
+    ("emphasis.pal", "PPU PAL (rojo y verde intercambiados)"),
+    ("ram_search.title", "Buscar en RAM"),
+    ("ram_search.new_search", "Nueva búsqueda"),
+    ("ram_search.changed", "Cambió"),
+    ("ram_search.unchanged", "Sin cambios"),
+    ("ram_search.greater", "Mayor"),
+    ("ram_search.less", "Menor"),
+    ("ram_search.results", "Resultados"),
+    ("ram_search.pin", "Fijar"),
+    ("ram_search.unpin", "Soltar"),
+    ("ram_search.watch_list", "Lista de vigilancia"),
+    ("ram_search.freeze", "Congelar"),
+    ("conditions.title", "Condiciones"),
+    ("conditions.label", "Etiqueta"),
+    ("conditions.add", "Añadir"),
+    ("conditions.remove", "Quitar"),
+    ("conditions.hits", "Veces"),
+    ("conditions.met", "Condición cumplida"),
+    ("condition.equal", "="),
+    ("condition.not_equal", "!="),
+    ("condition.less", "<"),
+    ("condition.greater", ">"),
+    ("condition.changed", "cambió"),
+    ("condition.increased", "subió"),
+    ("condition.decreased", "bajó"),
+    ("practice.title", "Práctica"),
+    ("practice.enabled", "Modo práctica"),
+    ("practice.hint", "Las teclas numéricas cargan su ranura, Mayús + una tecla numérica guarda en ella"),
+    ("practice.empty", "Vacía"),
+    ("practice.frame", "Fotograma"),
+    ("practice.save", "Guardar"),
+    ("practice.load", "Cargar"),
+    ("practice.death", "Detección de muerte"),
+    ("practice.death_hint", "Guardar en una ranura cada vez que se cumpla esta condición en memoria, p. ej. cuando bajan las vidas"),
+    ("practice.death_slot", "Guardar en la ranura"),
+    ("practice.set", "Fijar"),
+    ("practice.clear", "Quitar"),
+    ("apu_timeline.title", "Línea de tiempo del APU"),
+    ("apu_timeline.channel", "Canal"),
+    ("apu_timeline.frames", "Fotogramas"),
+    ("apu_timeline.pulse1", "Pulso 1"),
+    ("apu_timeline.pulse2", "Pulso 2"),
+    ("apu_timeline.triangle", "Triángulo"),
+    ("apu_timeline.noise", "Ruido"),
+    ("apu_timeline.period", "Periodo"),
+    ("apu_timeline.sweep_target", "Objetivo del barrido"),
+    ("apu_timeline.envelope", "Envolvente"),
+    ("apu_timeline.length", "Contador de longitud"),
+    ("apu_viewer.title", "Visor del APU"),
+    ("apu_viewer.channel", "Canal"),
+    ("apu_viewer.frequency", "Frecuencia"),
+    ("apu_viewer.note", "Nota"),
+    ("apu_viewer.duty", "Ciclo de trabajo"),
+    ("apu_viewer.volume", "Volumen"),
+    ("apu_viewer.bytes", "bytes restantes"),
+    ("apu_viewer.looping", "en bucle"),
+    ("event_viewer.title", "Visor de eventos"),
+    ("event_viewer.ppu", "Escrituras al PPU"),
+    ("event_viewer.apu", "Escrituras al APU"),
+    ("event_viewer.mapper", "Escrituras al mapper"),
+    ("event_viewer.irq", "IRQs"),
+    ("event_viewer.irq_frame_counter", "contador de cuadros"),
+    ("event_viewer.irq_dmc", "DMC"),
+    ("event_viewer.irq_mapper", "mapper"),
+    ("event_viewer.count", "Eventos del último cuadro"),
+    ("event_viewer.scanline", "Línea"),
+    ("event_viewer.dot", "punto"),
+    ("nametable_viewer.title", "Visor de nametables"),
+    ("nametable_viewer.scroll", "Mostrar el scroll de cada línea del último cuadro"),
+    ("nametable_viewer.tile", "Tile"),
+    ("code_profile.title", "Perfilador de código"),
+    ("code_profile.bank", "Por banco"),
+    ("code_profile.page", "Por página"),
+    ("code_profile.instruction", "Por instrucción"),
+    ("code_profile.clear", "Borrar"),
+    ("code_profile.frames", "Cuadros"),
+    ("code_profile.cycles_per_frame", "ciclos por cuadro"),
+    ("code_profile.cycles", "ciclos/cuadro"),
+    ("code_profile.prg_bank", "Banco PRG"),
+    ("code_profile.ram", "RAM"),
+    ("hotkeys.title", "Atajos de teclado"),
+    ("hotkeys.load_rom", "Cargar ROM"),
+    ("hotkeys.save_state", "Guardar estado"),
+    ("hotkeys.load_state", "Cargar estado"),
+    ("hotkeys.screenshot", "Captura de pantalla"),
+    ("hotkeys.pause", "Pausa"),
+    ("hotkeys.frame_advance", "Avanzar un fotograma"),
+    ("hotkeys.fast_forward", "Avance rápido (mantener)"),
+    ("hotkeys.rewind", "Rebobinar (mantener)"),
+    ("hotkeys.fullscreen", "Pantalla completa"),
+    ("hotkeys.mute", "Silenciar"),
+    ("hotkeys.press_key", "Pulsa una tecla..."),
+    ("hotkeys.unbound", "Sin asignar"),
+    ("hotkeys.clear", "Quitar"),
+    ("hotkeys.conflict", "Misma tecla que:"),
+    ("hotkeys.reset", "Restablecer"),
+    ("input.title", "Controles"),
+    ("input.up", "Arriba"),
+    ("input.down", "Abajo"),
+    ("input.left", "Izquierda"),
+    ("input.right", "Derecha"),
+    ("input.select", "Select"),
+    ("input.start", "Start"),
+    ("input.b", "B"),
+    ("input.a", "A"),
+    ("input.turbo_b", "Turbo B"),
+    ("input.turbo_a", "Turbo A"),
+    ("input.turbo_rate", "Fotogramas por pulsación turbo"),
+    ("netplay.title", "Juego en red"),
+    ("netplay.no_rom", "Carga una ROM primero, los dos jugadores necesitan la misma"),
+    ("netplay.port", "Puerto"),
+    ("netplay.host", "Crear partida"),
+    ("netplay.address", "Dirección del anfitrión"),
+    ("netplay.join", "Unirse"),
+    ("netplay.waiting", "Esperando a que se una otro jugador..."),
+    ("netplay.connecting", "Conectando..."),
+    ("netplay.connected", "Conectado"),
+    ("netplay.disconnect", "Desconectar"),
+    ("about.title", "Acerca de"),
+    ("about.created_by", "Creado por Daniel Adams"),
+    ("color_vision.normal", "Normal"),
+    ("color_vision.protanopia", "Protanopía (rojo)"),
+    ("color_vision.deuteranopia", "Deuteranopía (verde)"),
+    ("color_vision.tritanopia", "Tritanopía (azul)"),
+    ("palette.nesdev", "Consenso de NESDev"),
+    ("palette.fceux", "FCEUX"),
+    ("palette.sony_cxa", "Sony CXA2025AS"),
+    ("palette.custom", "Personalizada"),
+    ("audio.output", "Salida"),
+    ("audio.device", "Dispositivo predeterminado"),
+    ("audio.null", "Ninguna (no se encontró dispositivo de audio)"),
+    ("audio.waiting", "Haz clic o pulsa una tecla para empezar"),
+    ("audio.no_device", "No se encontró dispositivo de audio, se continúa sin sonido"),
+    ("audio.driver", "Controlador"),
+    ("audio.rodio", "rodio"),
+    ("audio.cpal", "cpal (directo)"),
+    ("audio.mute", "Silenciar"),
+    ("audio.volume", "Volumen"),
+    ("audio.latency", "Latencia"),
+    ("audio.sync", "Sincronización"),
+    ("audio.sync_hint", "Lo que marca la velocidad de emulación. Seguir al audio evita que el sonido se desfase, salte o se quede sin datos."),
+    ("sync.audio", "Audio"),
+    ("sync.wall_clock", "Reloj del sistema"),
+    ("audio.ultrasonic_triangle", "Triángulo ultrasónico"),
+    ("audio.ultrasonic_triangle_hint", "Reproduce el triángulo en los periodos muy cortos que usan los juegos para silenciarlo, como hace la consola. Es preciso, pero produce chasquidos."),
+    ("audio.reduce_pulse_popping", "Reducir chasquidos de pulso"),
+    ("audio.reduce_pulse_popping_hint", "Mantiene las ondas de los canales de pulso cuando un juego cambia su tono a mitad de nota, en lugar de reiniciarlas como hace la consola. Menos preciso, pero los juegos con vibrato producen menos chasquidos."),
+    ("audio.mixer", "Mezclador"),
+    ("audio.mixer_hint", "Cómo se mezclan los canales. Preciso usa el mezclador y los filtros de la propia consola, a costa de algo de velocidad."),
+    ("mixer.fast", "Rápido"),
+    ("mixer.accurate", "Preciso"),
+    ("audio.stereo", "Estéreo"),
+    ("audio.stereo_hint", "Reparte los canales entre los altavoces izquierdo y derecho. La consola en sí solo tiene salida mono."),
+    ("stereo.mono", "Mono"),
+    ("stereo.split", "Pulsos a la izquierda, triángulo y ruido a la derecha"),
+    ("stereo.custom", "Personalizado"),
+    ("audio.pan", "Panorama"),
+    ("audio.pan_hint", "Dónde se sitúa el canal, de -100 del todo a la izquierda a 100 del todo a la derecha"),
+    ("audio.channels", "Canales"),
+    ("audio.mute_channel", "M"),
+    ("audio.solo_channel", "S"),
+    ("channel.pulse1", "Pulso 1"),
+    ("channel.pulse2", "Pulso 2"),
+    ("channel.triangle", "Triángulo"),
+    ("channel.noise", "Ruido"),
+    ("channel.dmc", "DMC"),
+    ("channel.expansion", "Expansión"),
+    ("rom_error.title", "No se pudo cargar la ROM"),
+    ("rom_error.supported", "Mappers compatibles"),
+    ("rom_error.mapper", "Mapper"),
+    ("rom_error.name", "Nombre"),
+    ("rom_error.board", "Placas"),
+    ("rom_error.prg_ram", "PRG RAM"),
+    ("rom_error.irq", "IRQ"),
+    ("rom_error.audio", "Audio"),
+    ("rom_error.ok", "Aceptar"),
+    ("menu.unimplemented", "Este juego usa funciones del mapper que aún no están implementadas, así que puede no funcionar bien:"),
+];
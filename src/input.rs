@@ -0,0 +1,272 @@
+use eframe::egui;
+use egui::Key;
+
+use crate::i18n::tr;
+
+/// Controller buttons that can be bound to a key, including the turbo versions of A and B
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Start,
+    B,
+    A,
+    TurboB,
+    TurboA,
+}
+
+impl Button {
+    pub const ALL: [Button; 10] = [
+        Button::Up,
+        Button::Down,
+        Button::Left,
+        Button::Right,
+        Button::Select,
+        Button::Start,
+        Button::B,
+        Button::A,
+        Button::TurboB,
+        Button::TurboA,
+    ];
+
+    /// Stable name for storing the binding
+    pub fn key(self) -> &'static str {
+        match self {
+            Button::Up => "up",
+            Button::Down => "down",
+            Button::Left => "left",
+            Button::Right => "right",
+            Button::Select => "select",
+            Button::Start => "start",
+            Button::B => "b",
+            Button::A => "a",
+            Button::TurboB => "turbo_b",
+            Button::TurboA => "turbo_a",
+        }
+    }
+
+    /// Key for the UI string naming the button
+    pub fn label_key(self) -> &'static str {
+        match self {
+            Button::Up => "input.up",
+            Button::Down => "input.down",
+            Button::Left => "input.left",
+            Button::Right => "input.right",
+            Button::Select => "input.select",
+            Button::Start => "input.start",
+            Button::B => "input.b",
+            Button::A => "input.a",
+            Button::TurboB => "input.turbo_b",
+            Button::TurboA => "input.turbo_a",
+        }
+    }
+
+    /// The button's bit in the controller's shift register
+    pub fn mask(self) -> u8 {
+        match self {
+            Button::Right => 0x01,
+            Button::Left => 0x02,
+            Button::Down => 0x04,
+            Button::Up => 0x08,
+            Button::Start => 0x10,
+            Button::Select => 0x20,
+            Button::B | Button::TurboB => 0x40,
+            Button::A | Button::TurboA => 0x80,
+        }
+    }
+
+    pub fn is_turbo(self) -> bool {
+        matches!(self, Button::TurboA | Button::TurboB)
+    }
+
+    fn default_key(self) -> Key {
+        match self {
+            Button::Up => Key::ArrowUp,
+            Button::Down => Key::ArrowDown,
+            Button::Left => Key::ArrowLeft,
+            Button::Right => Key::ArrowRight,
+            Button::Select => Key::Space,
+            Button::Start => Key::Enter,
+            Button::B => Key::Z,
+            Button::A => Key::X,
+            Button::TurboB => Key::A,
+            Button::TurboA => Key::S,
+        }
+    }
+
+    fn index(self) -> usize {
+        Button::ALL.iter().position(|button| *button == self).unwrap()
+    }
+}
+
+/// What's held on a controller, with the buttons held through turbo kept apart so they can be
+/// pressed and released as frames go by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PadState {
+    pub held: u8,
+    pub turbo: u8,
+    /// Frames each turbo press and release lasts
+    pub turbo_rate: u32,
+}
+
+impl PadState {
+    /// The buttons the console sees on `frame`. Turbo buttons start pressed, so a quick tap still
+    /// registers.
+    pub fn buttons(&self, frame: u64) -> u8 {
+        let pressed = (frame / self.turbo_rate.max(1) as u64).is_multiple_of(2);
+        self.held | if pressed { self.turbo } else { 0 }
+    }
+}
+
+/// The key bound to each controller button, and how fast turbo fires
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputBindings {
+    keys: [Option<Key>; Button::ALL.len()],
+    pub turbo_rate: u32,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self {
+            keys: Button::ALL.map(|button| Some(button.default_key())),
+            turbo_rate: InputBindings::DEFAULT_TURBO_RATE,
+        }
+    }
+}
+
+impl InputBindings {
+    /// Two frames pressed and two released, fifteen presses a second
+    pub const DEFAULT_TURBO_RATE: u32 = 2;
+    pub const TURBO_RATE_RANGE: std::ops::RangeInclusive<u32> = 1..=10;
+
+    pub fn get(&self, button: Button) -> Option<Key> {
+        self.keys[button.index()]
+    }
+
+    pub fn set(&mut self, button: Button, key: Option<Key>) {
+        self.keys[button.index()] = key;
+    }
+
+    /// The controller state for whichever bound keys `held` says are down
+    pub fn pad_state(&self, held: impl Fn(Key) -> bool) -> PadState {
+        let mut state = PadState { turbo_rate: self.turbo_rate, ..PadState::default() };
+        for button in Button::ALL {
+            if self.get(button).is_some_and(&held) {
+                if button.is_turbo() {
+                    state.turbo |= button.mask();
+                } else {
+                    state.held |= button.mask();
+                }
+            }
+        }
+        state
+    }
+
+    /// The controller state from the keyboard. Nothing's pressed while typing into a text field, so
+    /// using the console doesn't press buttons.
+    pub fn read(&self, ctx: &egui::Context) -> PadState {
+        if ctx.wants_keyboard_input() {
+            return PadState { turbo_rate: self.turbo_rate, ..PadState::default() };
+        }
+        self.pad_state(|key| ctx.input(|i| i.key_down(key)))
+    }
+
+    /// Load the bindings from storage, keeping the default for any button that isn't there
+    pub fn load(storage: &dyn eframe::Storage) -> Self {
+        let mut bindings = InputBindings::default();
+        for button in Button::ALL {
+            if let Some(key) = storage.get_string(&format!("input.{}", button.key())) {
+                // An empty string is a button that's been deliberately unbound
+                bindings.set(button, Key::from_name(&key));
+            }
+        }
+        if let Some(rate) = storage.get_string("input.turbo_rate").and_then(|rate| rate.parse::<u32>().ok()) {
+            bindings.turbo_rate = rate.clamp(*Self::TURBO_RATE_RANGE.start(), *Self::TURBO_RATE_RANGE.end());
+        }
+        bindings
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        for button in Button::ALL {
+            storage.set_string(&format!("input.{}", button.key()), self.get(button).map(|key| key.name().to_string()).unwrap_or_default());
+        }
+        storage.set_string("input.turbo_rate", self.turbo_rate.to_string());
+    }
+}
+
+/// Settings window for binding controller buttons to keys
+#[derive(Default)]
+pub struct InputWindow {
+    pub open: bool,
+    /// The button waiting for a key to be pressed
+    capturing: Option<Button>,
+}
+
+impl InputWindow {
+    /// Whether the window is waiting for a key, so hotkeys shouldn't fire
+    pub fn capturing(&self) -> bool {
+        self.open && self.capturing.is_some()
+    }
+
+    /// Draw the window, if open, returning the bindings if they've been changed
+    pub fn show(&mut self, ctx: &egui::Context, bindings: &InputBindings) -> Option<InputBindings> {
+        if !self.open {
+            self.capturing = None;
+            return None;
+        }
+
+        let mut changed = bindings.clone();
+        if let Some(button) = self.capturing {
+            let pressed = ctx.input(|i| i.events.iter().find_map(|event| match event {
+                egui::Event::Key { key, pressed: true, .. } => Some(*key),
+                _ => None,
+            }));
+            match pressed {
+                Some(Key::Escape) => self.capturing = None,
+                Some(key) => {
+                    changed.set(button, Some(key));
+                    self.capturing = None;
+                },
+                None => {},
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window::new(tr("input.title"))
+            .id(egui::Id::new("input_window"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("input_grid").num_columns(3).show(ui, |ui| {
+                    for button in Button::ALL {
+                        ui.label(tr(button.label_key()));
+                        let text = if self.capturing == Some(button) {
+                            tr("hotkeys.press_key").to_string()
+                        } else {
+                            changed.get(button).map(|key| key.name().to_string()).unwrap_or_else(|| tr("hotkeys.unbound").to_string())
+                        };
+                        if ui.add(egui::Button::new(text).min_size(egui::vec2(120.0, 0.0))).clicked() {
+                            self.capturing = Some(button);
+                        }
+                        if ui.add_enabled(changed.get(button).is_some(), egui::Button::new(tr("hotkeys.clear"))).clicked() {
+                            changed.set(button, None);
+                        }
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                ui.add(egui::Slider::new(&mut changed.turbo_rate, InputBindings::TURBO_RATE_RANGE).text(tr("input.turbo_rate")));
+                ui.separator();
+                if ui.button(tr("hotkeys.reset")).clicked() {
+                    changed = InputBindings::default();
+                    self.capturing = None;
+                }
+            });
+        self.open = open;
+
+        (changed != *bindings).then_some(changed)
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use sha256::digest;
+use web_time::Instant;
+
+use crate::cartridge::Cartridge;
+use crate::i18n::tr;
+use crate::rom_database::RomDatabase;
+
+/// A ROM in the library's folder
+#[derive(Clone, Debug, PartialEq)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    /// The ROM database's name for it, or failing that its filename
+    pub title: String,
+    /// e.g. "mapper 4, MMC3", or why it can't be loaded
+    pub mapper: String,
+}
+
+/// When a game was last played and for how long altogether
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlayHistory {
+    /// Seconds since the Unix epoch
+    pub last_played: u64,
+    /// Seconds spent with it loaded
+    pub play_time: u64,
+}
+
+/// The ROMs in a folder, with how much each has been played. The history's kept by path for every game
+/// ever loaded from a library, so it's still there after a trip to another folder.
+#[derive(Default)]
+pub struct Library {
+    pub folder: Option<PathBuf>,
+    pub entries: Vec<LibraryEntry>,
+    history: HashMap<PathBuf, PlayHistory>,
+    /// The game that's loaded and since when, for adding to its play time when it's swapped out
+    playing: Option<(PathBuf, Instant)>,
+}
+
+impl Library {
+    /// Load the library from storage as it was last listed, without reading the folder again
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut library = Library::default();
+        let Some(storage) = storage else {
+            return library;
+        };
+        library.folder = storage.get_string("library_folder").filter(|path| !path.is_empty()).map(PathBuf::from);
+
+        // One game per line: path, title, mapper
+        for line in storage.get_string("library").unwrap_or_default().lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(path), Some(title), Some(mapper)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            library.entries.push(LibraryEntry { path: path.into(), title: title.to_string(), mapper: mapper.to_string() });
+        }
+
+        // One game per line: path, last played, play time
+        for line in storage.get_string("library_history").unwrap_or_default().lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(path), Some(Ok(last_played)), Some(Ok(play_time))) = (
+                fields.next(),
+                fields.next().map(str::parse::<u64>),
+                fields.next().map(str::parse::<u64>),
+            ) else {
+                continue;
+            };
+            library.history.insert(path.into(), PlayHistory { last_played, play_time });
+        }
+
+        library
+    }
+
+    /// Save the library, counting the time the loaded game's been played so far
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        let folder = self.folder.as_ref().map(|folder| folder.to_string_lossy().into_owned()).unwrap_or_default();
+        storage.set_string("library_folder", folder);
+
+        let mut saved = String::new();
+        for entry in &self.entries {
+            saved += &format!("{}\t{}\t{}\n", entry.path.display(), entry.title.replace(['\t', '\n'], " "), entry.mapper);
+        }
+        storage.set_string("library", saved);
+
+        let mut saved = String::new();
+        for path in self.history.keys() {
+            let history = self.history(path).unwrap_or_default();
+            saved += &format!("{}\t{}\t{}\n", path.display(), history.last_played, history.play_time);
+        }
+        storage.set_string("library_history", saved);
+    }
+
+    /// List the ROMs in `folder` in place of whatever was listed before, named by the ROM database where
+    /// it knows them. ROMs that can't be loaded are listed anyway, with the reason in place of their mapper.
+    pub fn scan(&mut self, folder: &Path, rom_database: &RomDatabase) -> std::io::Result<()> {
+        let mut entries = vec![];
+        for file in std::fs::read_dir(folder)? {
+            let path = file?.path();
+            let is_rom = path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| ["nes", "fds"].contains(&extension.to_ascii_lowercase().as_str()));
+            if !is_rom {
+                continue;
+            }
+            let Ok(mut rom_bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            rom_database.fix_header(&mut rom_bytes);
+            let name = rom_database.find_sha256(&digest(rom_bytes.as_slice())).map(|entry| entry.name.clone());
+            let title = name.unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+            let mapper = match Cartridge::from_bytes(rom_bytes) {
+                Ok(cartridge) => cartridge.mapper_description(),
+                Err(error) => error.to_string(),
+            };
+            entries.push(LibraryEntry { path, title, mapper });
+        }
+        entries.sort_by_key(|entry| entry.title.to_lowercase());
+        self.folder = Some(folder.to_path_buf());
+        self.entries = entries;
+        Ok(())
+    }
+
+    /// How long the game at `path` has been played, counting the time it's been loaded so far
+    pub fn history(&self, path: &Path) -> Option<PlayHistory> {
+        let mut history = *self.history.get(path)?;
+        if let Some((playing, since)) = &self.playing {
+            if playing == path {
+                history.play_time += since.elapsed().as_secs();
+            }
+        }
+        Some(history)
+    }
+
+    /// Note that the game at `path` was loaded at `now`, in seconds since the Unix epoch, and start
+    /// counting its play time
+    pub fn played(&mut self, path: &Path, now: u64) {
+        self.stop();
+        self.history.entry(path.to_path_buf()).or_default().last_played = now;
+        self.playing = Some((path.to_path_buf(), Instant::now()));
+    }
+
+    /// Stop counting play time, as the game's been swapped for one from elsewhere
+    pub fn stop(&mut self) {
+        if let Some((path, since)) = self.playing.take() {
+            self.history.entry(path).or_default().play_time += since.elapsed().as_secs();
+        }
+    }
+}
+
+/// e.g. "2024-06-06", for a time in seconds since the Unix epoch
+pub fn format_date(seconds: u64) -> String {
+    // Howard Hinnant's days to civil date, for the proleptic Gregorian calendar
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// e.g. "1h 05m", or "12m" for less than an hour
+pub fn format_play_time(seconds: u64) -> String {
+    let minutes = seconds / 60;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+/// What the user asked for from the library window
+#[derive(Clone, Debug, PartialEq)]
+pub enum LibraryAction {
+    OpenFolder,
+    Rescan,
+    Load(PathBuf),
+}
+
+/// Window listing the ROMs in a folder, for picking a game without going through a file dialog each time
+#[derive(Default)]
+pub struct LibraryWindow {
+    pub open: bool,
+    filter: String,
+    selected: Option<PathBuf>,
+}
+
+impl LibraryWindow {
+    pub fn show(&mut self, ctx: &egui::Context, library: &Library) -> Option<LibraryAction> {
+        let mut action = None;
+        let mut open = self.open;
+
+        egui::Window::new(tr("library.title"))
+            .id(egui::Id::new("library_window"))
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button(tr("library.open_folder")).clicked() {
+                        action = Some(LibraryAction::OpenFolder);
+                    }
+                    if library.folder.is_some() && ui.button(tr("library.rescan")).clicked() {
+                        action = Some(LibraryAction::Rescan);
+                    }
+                    ui.label(tr("library.filter"));
+                    ui.text_edit_singleline(&mut self.filter);
+                });
+                let Some(folder) = &library.folder else {
+                    ui.label(tr("library.no_folder"));
+                    return;
+                };
+                ui.label(folder.display().to_string());
+                ui.separator();
+
+                let filter = self.filter.to_lowercase();
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("library_grid").striped(true).show(ui, |ui| {
+                        ui.strong(tr("library.game"));
+                        ui.strong(tr("library.mapper"));
+                        ui.strong(tr("library.last_played"));
+                        ui.strong(tr("library.play_time"));
+                        ui.end_row();
+
+                        for entry in library.entries.iter().filter(|entry| entry.title.to_lowercase().contains(&filter)) {
+                            let selected = self.selected.as_ref() == Some(&entry.path);
+                            let response = ui.selectable_label(selected, &entry.title);
+                            if response.clicked() {
+                                self.selected = Some(entry.path.clone());
+                            }
+                            if response.double_clicked() {
+                                action = Some(LibraryAction::Load(entry.path.clone()));
+                            }
+                            ui.label(&entry.mapper);
+                            let history = library.history(&entry.path);
+                            ui.label(history.map_or_else(|| tr("library.never").to_string(), |history| format_date(history.last_played)));
+                            ui.label(history.map(|history| format_play_time(history.play_time)).unwrap_or_default());
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+
+        self.open = open;
+        action
+    }
+}
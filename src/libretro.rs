@@ -0,0 +1,436 @@
+//! The console as a libretro core, so it can be loaded into RetroArch and other libretro frontends.
+//!
+//! Frontends load the library and call the `retro_*` functions below, handing over callbacks for
+//! video, audio and input. There's only ever one game loaded, so the console lives in a global
+//! for the frontend to drive one frame at a time with `retro_run`.
+
+use std::ffi::{c_char, c_uint, c_void, CStr};
+use std::sync::Mutex;
+
+use crate::cartridge::Cartridge;
+use crate::cheat::Cheat;
+use crate::input::Button;
+use crate::nes::{Nes, SaveState, CYCLES_PER_SECOND, NTSC_FRAME_RATE, SAMPLES_PER_OUTPUT_SAMPLE};
+use crate::video::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub const RETRO_API_VERSION: c_uint = 1;
+
+pub const RETRO_DEVICE_JOYPAD: c_uint = 1;
+pub const RETRO_DEVICE_ID_JOYPAD_B: c_uint = 0;
+pub const RETRO_DEVICE_ID_JOYPAD_SELECT: c_uint = 2;
+pub const RETRO_DEVICE_ID_JOYPAD_START: c_uint = 3;
+pub const RETRO_DEVICE_ID_JOYPAD_UP: c_uint = 4;
+pub const RETRO_DEVICE_ID_JOYPAD_DOWN: c_uint = 5;
+pub const RETRO_DEVICE_ID_JOYPAD_LEFT: c_uint = 6;
+pub const RETRO_DEVICE_ID_JOYPAD_RIGHT: c_uint = 7;
+pub const RETRO_DEVICE_ID_JOYPAD_A: c_uint = 8;
+
+pub const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+pub const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+pub const RETRO_REGION_NTSC: c_uint = 0;
+
+pub const RETRO_MEMORY_SAVE_RAM: c_uint = 0;
+pub const RETRO_MEMORY_SYSTEM_RAM: c_uint = 2;
+
+pub type RetroEnvironment = extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+pub type RetroVideoRefresh = extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+pub type RetroAudioSample = extern "C" fn(left: i16, right: i16);
+pub type RetroAudioSampleBatch = extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type RetroInputPoll = extern "C" fn();
+pub type RetroInputState = extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+#[repr(C)]
+pub struct RetroSystemInfo {
+  pub library_name: *const c_char,
+  pub library_version: *const c_char,
+  pub valid_extensions: *const c_char,
+  pub need_fullpath: bool,
+  pub block_extract: bool,
+}
+
+#[repr(C)]
+pub struct RetroGameGeometry {
+  pub base_width: c_uint,
+  pub base_height: c_uint,
+  pub max_width: c_uint,
+  pub max_height: c_uint,
+  pub aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct RetroSystemTiming {
+  pub fps: f64,
+  pub sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+  pub geometry: RetroGameGeometry,
+  pub timing: RetroSystemTiming,
+}
+
+#[repr(C)]
+pub struct RetroGameInfo {
+  pub path: *const c_char,
+  pub data: *const c_void,
+  pub size: usize,
+  pub meta: *const c_char,
+}
+
+/// Which libretro joypad button presses which of the controller's
+const JOYPAD_BUTTONS: [(c_uint, Button); 8] = [
+  (RETRO_DEVICE_ID_JOYPAD_A, Button::A),
+  (RETRO_DEVICE_ID_JOYPAD_B, Button::B),
+  (RETRO_DEVICE_ID_JOYPAD_SELECT, Button::Select),
+  (RETRO_DEVICE_ID_JOYPAD_START, Button::Start),
+  (RETRO_DEVICE_ID_JOYPAD_UP, Button::Up),
+  (RETRO_DEVICE_ID_JOYPAD_DOWN, Button::Down),
+  (RETRO_DEVICE_ID_JOYPAD_LEFT, Button::Left),
+  (RETRO_DEVICE_ID_JOYPAD_RIGHT, Button::Right),
+];
+
+/// Bytes in front of a serialized state giving its length, since the frontend's buffer is usually bigger
+const STATE_LENGTH_BYTES: usize = 4;
+
+/// States are JSON, so their size wobbles from frame to frame, but frontends want the same size every
+/// time without paying for a serialize to find it out. Two screens of bytes written out in full, PRG and
+/// CHR RAM and a frame's audio come to a bit over 2MB, so this leaves room to spare.
+const STATE_SIZE: usize = 0x400000;
+
+/// What the frontend handed over to call back into
+#[derive(Clone, Copy)]
+struct Callbacks {
+  environment: Option<RetroEnvironment>,
+  video_refresh: Option<RetroVideoRefresh>,
+  audio_sample_batch: Option<RetroAudioSampleBatch>,
+  input_poll: Option<RetroInputPoll>,
+  input_state: Option<RetroInputState>,
+}
+
+/// The loaded game and everything kept alongside it between frames
+struct Core {
+  nes: Nes,
+  /// The frame converted to the frontend's pixel format
+  video: Vec<u32>,
+  /// Cheats by the frontend's index for them, each of which can be several codes
+  cheats: Vec<(c_uint, Cheat)>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+  environment: None,
+  video_refresh: None,
+  audio_sample_batch: None,
+  input_poll: None,
+  input_state: None,
+});
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn callbacks() -> Callbacks {
+  *CALLBACKS.lock().unwrap()
+}
+
+/// Run `f` on the loaded game, or return `None` if there isn't one
+fn with_core<T>(f: impl FnOnce(&mut Core) -> T) -> Option<T> {
+  CORE.lock().unwrap().as_mut().map(f)
+}
+
+impl Core {
+  fn new(nes: Nes) -> Self {
+    Self {
+      nes,
+      video: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+      cheats: vec![],
+    }
+  }
+
+  /// Run a frame with the frontend's input, then hand it the picture and sound
+  fn run(&mut self, callbacks: &Callbacks) {
+    if let Some(input_state) = callbacks.input_state {
+      for port in 0..2 {
+        let buttons = JOYPAD_BUTTONS.iter()
+          .filter(|(id, _)| input_state(port, RETRO_DEVICE_JOYPAD, 0, *id) != 0)
+          .fold(0, |buttons, (_, button)| buttons | button.mask());
+        self.nes.update_controller(port as usize, buttons);
+      }
+    }
+
+    self.nes.run_frame();
+
+    for (pixel, rgb) in self.video.iter_mut().zip(self.nes.screen().chunks_exact(3)) {
+      *pixel = u32::from_be_bytes([0, rgb[0], rgb[1], rgb[2]]);
+    }
+    if let Some(video_refresh) = callbacks.video_refresh {
+      video_refresh(self.video.as_ptr() as *const c_void, SCREEN_WIDTH as c_uint, SCREEN_HEIGHT as c_uint, SCREEN_WIDTH * 4);
+    }
+
+    // Interleaved left and right, as the frontend wants them
+    let samples: Vec<i16> = self.nes.take_stereo_audio().iter()
+      .map(|sample| (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+      .collect();
+    if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+      audio_sample_batch(samples.as_ptr(), samples.len() / 2);
+    }
+  }
+
+  /// The state with its length in front, padded out to [`STATE_SIZE`]
+  fn serialize(&self) -> Option<Vec<u8>> {
+    let state = self.nes.save_state().to_bytes().ok()?;
+    let mut bytes = (state.len() as u32).to_le_bytes().to_vec();
+    bytes.extend(state);
+    if bytes.len() > STATE_SIZE {
+      log::error!("The state came to {} bytes, more than the {} frontends make room for", bytes.len(), STATE_SIZE);
+      return None;
+    }
+    bytes.resize(STATE_SIZE, 0);
+    Some(bytes)
+  }
+
+  fn unserialize(&mut self, bytes: &[u8]) -> bool {
+    let Some((length, state)) = bytes.split_first_chunk::<STATE_LENGTH_BYTES>() else {
+      return false;
+    };
+    let Some(state) = state.get(..u32::from_le_bytes(*length) as usize) else {
+      return false;
+    };
+    match SaveState::from_bytes(state) {
+      Ok(state) => {
+        self.nes.load_state(&state);
+        true
+      },
+      Err(error) => {
+        log::error!("Couldn't load state: {}", error);
+        false
+      },
+    }
+  }
+
+  /// Replace the cheats at `index` with `code`, which can be several codes joined with `+`
+  fn set_cheat(&mut self, index: c_uint, enabled: bool, code: &str) {
+    self.cheats.retain(|(cheat_index, _)| *cheat_index != index);
+    for code in code.split('+').filter(|code| !code.trim().is_empty()) {
+      match Cheat::parse(code) {
+        Ok(cheat) => self.cheats.push((index, Cheat { enabled, ..cheat })),
+        Err(error) => log::warn!("Couldn't add cheat {}: {}", code, error),
+      }
+    }
+    self.nes.set_cheats(self.cheats.iter().map(|(_, cheat)| cheat.clone()).collect());
+  }
+
+  /// Memory the frontend can look at directly, e.g. for saving battery RAM or achievements
+  fn memory(&mut self, id: c_uint) -> Option<&mut [u8]> {
+    match id {
+      RETRO_MEMORY_SAVE_RAM => self.nes.bus.cartridge.as_mut()
+        .filter(|cartridge| cartridge.has_battery())
+        .map(|cartridge| cartridge.ram.as_mut_slice()),
+      RETRO_MEMORY_SYSTEM_RAM => Some(self.nes.bus.ram_mut()),
+      _ => None,
+    }
+  }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+  RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(environment: RetroEnvironment) {
+  CALLBACKS.lock().unwrap().environment = Some(environment);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: RetroVideoRefresh) {
+  CALLBACKS.lock().unwrap().video_refresh = Some(video_refresh);
+}
+
+/// Audio always goes through the batch callback, so the single sample one is never used
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: RetroAudioSample) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: RetroAudioSampleBatch) {
+  CALLBACKS.lock().unwrap().audio_sample_batch = Some(audio_sample_batch);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: RetroInputPoll) {
+  CALLBACKS.lock().unwrap().input_poll = Some(input_poll);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: RetroInputState) {
+  CALLBACKS.lock().unwrap().input_state = Some(input_state);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+  *CORE.lock().unwrap() = None;
+}
+
+/// # Safety
+///
+/// `info` must point to a `RetroSystemInfo` to fill in.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+  *info = RetroSystemInfo {
+    library_name: c"SilkNES".as_ptr(),
+    library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+    valid_extensions: c"nes".as_ptr(),
+    need_fullpath: false,
+    block_extract: false,
+  };
+}
+
+/// # Safety
+///
+/// `info` must point to a `RetroSystemAvInfo` to fill in.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+  *info = RetroSystemAvInfo {
+    geometry: RetroGameGeometry {
+      base_width: SCREEN_WIDTH as c_uint,
+      base_height: SCREEN_HEIGHT as c_uint,
+      max_width: SCREEN_WIDTH as c_uint,
+      max_height: SCREEN_HEIGHT as c_uint,
+      aspect_ratio: 4.0 / 3.0,
+    },
+    timing: RetroSystemTiming {
+      // The region only changes how the PPU's emphasis bits are wired, the console always runs at NTSC speed
+      fps: NTSC_FRAME_RATE,
+      sample_rate: CYCLES_PER_SECOND / SAMPLES_PER_OUTPUT_SAMPLE as f64,
+    },
+  };
+}
+
+/// Only standard controllers are supported, so whatever's plugged in is treated as one
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+  with_core(|core| core.nes.reset());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+  let callbacks = callbacks();
+  if let Some(input_poll) = callbacks.input_poll {
+    input_poll();
+  }
+  with_core(|core| core.run(&callbacks));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+  STATE_SIZE
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+  let Some(bytes) = with_core(|core| core.serialize()).flatten() else {
+    return false;
+  };
+  if bytes.len() > size {
+    return false;
+  }
+  std::slice::from_raw_parts_mut(data as *mut u8, size).fill(0);
+  std::ptr::copy_nonoverlapping(bytes.as_ptr(), data as *mut u8, bytes.len());
+  true
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+  let bytes = std::slice::from_raw_parts(data as *const u8, size);
+  with_core(|core| core.unserialize(bytes)).unwrap_or(false)
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {
+  with_core(|core| {
+    core.cheats.clear();
+    core.nes.set_cheats(vec![]);
+  });
+}
+
+/// # Safety
+///
+/// `code` must be a nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn retro_cheat_set(index: c_uint, enabled: bool, code: *const c_char) {
+  if code.is_null() {
+    return;
+  }
+  let code = CStr::from_ptr(code).to_string_lossy();
+  with_core(|core| core.set_cheat(index, enabled, &code));
+}
+
+/// # Safety
+///
+/// `game` must be null or point to a `RetroGameInfo` whose `data` holds `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+  let Some(game) = game.as_ref().filter(|game| !game.data.is_null()) else {
+    return false;
+  };
+  let bytes = std::slice::from_raw_parts(game.data as *const u8, game.size).to_vec();
+  let cartridge = match Cartridge::from_bytes(bytes) {
+    Ok(cartridge) => cartridge,
+    Err(error) => {
+      log::error!("Couldn't load the ROM: {}", error);
+      return false;
+    },
+  };
+
+  let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+  let environment = callbacks().environment;
+  if let Some(environment) = environment {
+    if !environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut format as *mut c_uint as *mut c_void) {
+      log::error!("The frontend can't show XRGB8888 frames");
+      return false;
+    }
+  }
+
+  let mut nes = Nes::new();
+  nes.insert_cartridge(cartridge);
+  *CORE.lock().unwrap() = Some(Core::new(nes));
+  true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(_game_type: c_uint, _info: *const RetroGameInfo, _num_info: usize) -> bool {
+  false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+  *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+  // Even for PAL games, as there's no PAL timing to go with them
+  RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+  with_core(|core| core.memory(id).map(|memory| memory.as_mut_ptr() as *mut c_void))
+    .flatten()
+    .unwrap_or(std::ptr::null_mut())
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+  with_core(|core| core.memory(id).map_or(0, |memory| memory.len())).unwrap_or(0)
+}
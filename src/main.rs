@@ -1,138 +1,260 @@
 pub mod apu;
 pub mod apu_output;
+pub mod apu_timeline;
+pub mod apu_viewer;
+pub mod audio_pipeline;
 pub mod bus;
 pub mod cartridge;
+pub mod cheat;
+pub mod cheat_window;
+pub mod clock_audit;
+pub mod code_profile;
+pub mod code_profile_window;
+pub mod command;
+pub mod compat;
+pub mod condition;
+pub mod condition_window;
+pub mod config;
+pub mod console;
 pub mod cpu;
+pub mod demos;
+pub mod emulation;
+pub mod event_viewer;
+pub mod frame_advance;
+pub mod frame_skip;
+pub mod game_profile;
+pub mod hotkeys;
+pub mod i18n;
+pub mod input;
+pub mod library;
+pub mod nametable_viewer;
+pub mod netplay;
+pub mod netplay_window;
 pub mod ppu;
+pub mod practice;
+pub mod profile;
+pub mod ram_search;
+pub mod regression;
+pub mod rewind;
+pub mod rom_database;
+pub mod rom_error_window;
+pub mod test_pattern;
+pub mod test_rom;
+pub mod toast;
+pub mod watchpoint;
+#[cfg(feature = "serde")]
+pub mod serde_arrays;
+pub mod video;
 pub mod mapper;
 pub mod mappers;
+pub mod menubar;
+pub mod movie;
+pub mod nes;
+pub mod palette;
 
-use apu::APU;
-use apu_output::APUOutput;
-use bus::{Bus, BusLike};
-use cartridge::Cartridge;
-use cpu::NES6502;
-use ppu::PPU;
+use apu::StereoMode;
+use apu_output::{AudioBackend, AudioControls, AudioDriver};
+use audio_pipeline::AudioPipeline;
+use cartridge::{Cartridge, CartridgeError};
+use cheat::Cheat;
+use cheat_window::{CheatLibrary, CheatWindow};
+use condition_window::ConditionWindow;
+use command::{Command, CONSOLE_HELP, SAVE_SLOTS};
+use config::{Config, SettingsFile};
+use console::Console;
+use demos::DEMOS;
+use emulation::{Core, Emulation, Event, Input};
+use game_profile::{GameProfile, GameProfileWindow, GameProfiles};
+use hotkeys::{HotkeyAction, HotkeyWindow};
+use input::InputWindow;
+use library::{Library, LibraryAction, LibraryWindow};
+use menubar::MENUBAR_HEIGHT;
+use nes::{Nes, NTSC_FRAME_RATE};
+use netplay::{Session, Status};
+use netplay_window::{NetplayAction, NetplayWindow};
+use palette::Palette;
+use practice::Practice;
+use ram_search::RamSearch;
+use rom_database::RomDatabase;
+use rom_error_window::RomErrorWindow;
+use apu_timeline::ApuTimeline;
+use apu_viewer::ApuViewer;
+use code_profile_window::CodeProfileWindow;
+use event_viewer::EventViewer;
+use nametable_viewer::NametableViewer;
+use toast::Toasts;
+use video::{save_png, visible_area, Daltonize, ColorVision, Display, VideoFilterChain};
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::path::Path;
 use std::sync::mpsc;
 
-use std::collections::HashMap;
-
 use eframe::egui;
-use egui::Key;
-use muda::{accelerator::{Accelerator, Code, Modifiers}, Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem, Submenu};
 use rfd::FileDialog;
-use rodio::{source::Source, OutputStream, Sink};
-use roxmltree::Document;
 use sha256::digest;
-use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
-
-fn main() -> Result<(), eframe::Error> {
-    // Set window options, main important one here is min_inner_size so our window accounts for menubar insertion
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([512.0, 480.0]).with_min_inner_size([512.0, 480.0]),
-        ..Default::default()
-    };
-
-    // Create bus
-    let bus = Rc::new(RefCell::new(Box::new(Bus::new()) as Box<dyn BusLike>));
-
-    // Create CPU
-    let cpu = Rc::new(RefCell::new(NES6502::new()));
-
-    let ppu = Rc::new(RefCell::new(PPU::new()));
-
-    let apu = Rc::new(RefCell::new(APU::new()));
 
-    // Connect bus to CPU
-    {
-        let mut bus_ref = bus.borrow_mut();
-        let cpu_ref = Rc::clone(&cpu);
-        bus_ref.connect_cpu(Rc::clone(&cpu_ref));
-    }
+/// Where screenshots taken with the hotkey go, relative to the working directory
+const SCREENSHOT_DIR: &str = "screenshots";
 
-    // Connect CPU to bus
-    {
-        let mut cpu_ref = cpu.borrow_mut();
-        let bus_ref = Rc::clone(&bus);
-        cpu_ref.connect_to_bus(Rc::clone(&bus_ref));
-    }
+/// No-Intro's dat of known good dumps, with the header each one should have
+const ROM_DATABASE_PATH: &str = "res/Nintendo - Nintendo Entertainment System (Headered) (20240606-224704).dat";
 
-    // Connect bus to PPU
-    {
-        let mut bus_ref = bus.borrow_mut();
-        let ppu_ref = Rc::clone(&ppu);
-        bus_ref.connect_ppu(Rc::clone(&ppu_ref));
-    }
-
-    // Connect PPU to bus
-    {
-        let mut ppu_ref = ppu.borrow_mut();
-        let bus_ref = Rc::clone(&bus);
-        ppu_ref.connect_to_bus(Rc::clone(&bus_ref));
-    }
+/// A command line mode that runs without a window, given the arguments after its flag, and failing
+/// with what to print
+type HeadlessMode = fn(&[String]) -> Result<(), String>;
 
-    // Connect bus to APU
-    {
-        let mut bus_ref = bus.borrow_mut();
-        let apu_ref = Rc::clone(&apu);
-        bus_ref.connect_apu(Rc::clone(&apu_ref));
+fn main() -> Result<(), eframe::Error> {
+    // Grabbing a single frame, benchmarking and the regression and compatibility runs don't need a window,
+    // so they're handled before one is opened
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let headless: Option<HeadlessMode> = match args.first().map(String::as_str) {
+        Some("--screenshot") => Some(screenshot),
+        Some("--bench") => Some(bench),
+        Some("--regress") => Some(regress),
+        Some("--compat-scan") => Some(compat_scan),
+        _ => None,
+    };
+    if let Some(run) = headless {
+        std::process::exit(match run(&args[1..]) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("{}", error);
+                1
+            },
+        });
     }
 
-    // Connect APU to bus
-    {
-        let mut apu_ref = apu.borrow_mut();
-        let bus_ref = Rc::clone(&bus);
-        apu_ref.connect_to_bus(Rc::clone(&bus_ref));
-    }
+    // Set window options, main important one here is min_inner_size so our window accounts for the menubar
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([512.0, 480.0 + MENUBAR_HEIGHT])
+            .with_min_inner_size([512.0, 480.0 + MENUBAR_HEIGHT]),
+        ..Default::default()
+    };
 
     // Setup audio
     let (tx, rx) = mpsc::channel();
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let sink = Sink::try_new(&stream_handle).unwrap();
-    let source = APUOutput::new(rx).amplify(0.25);
-    sink.append(source);
+    let audio_controls = AudioControls::default();
+    let audio = AudioBackend::start(rx, AudioDriver::default(), audio_controls.clone());
+    let emulation = Emulation::start(Core::new(SAVE_SLOTS, AudioPipeline::start(tx)), audio_controls.clone());
+
+    let rom_database = RomDatabase::load(ROM_DATABASE_PATH).unwrap_or_else(|error| {
+        log::warn!("Couldn't load the ROM database from {}, ROMs won't be named or have their headers fixed: {}", ROM_DATABASE_PATH, error);
+        RomDatabase::default()
+    });
 
     let silknes = SilkNES {
         show_about_window: false,
-        menubar: None,
-        menubar_items: HashMap::new(),
-        menubar_interaction: "".to_string(),
-        bus,
-        cpu,
-        ppu,
-        apu,
-        cartridge: None,
-        rom_loaded: false,
-        tx,
+        cheat_window: CheatWindow::default(),
+        game_profile_window: GameProfileWindow::default(),
+        ram_search: RamSearch::default(),
+        condition_window: ConditionWindow::default(),
+        practice: Practice::default(),
+        apu_timeline: ApuTimeline::default(),
+        apu_viewer: ApuViewer::default(),
+        event_viewer: EventViewer::default(),
+        nametable_viewer: NametableViewer::default(),
+        code_profile_window: CodeProfileWindow::default(),
+        netplay_window: NetplayWindow::default(),
+        library_window: LibraryWindow::default(),
+        hotkey_window: HotkeyWindow::default(),
+        input_window: InputWindow::default(),
+        rom_error_window: RomErrorWindow::default(),
+        toasts: Toasts::default(),
+        config: Config::default(),
+        saved_config: Config::default(),
+        settings: SettingsFile::open(),
+        video_filters: VideoFilterChain::new(),
+        display: Display::default(),
+        console: Console::new(),
+        emulation,
+        rom_hash: None,
+        rom_bytes: None,
+        cheat_library: CheatLibrary::default(),
+        game_profiles: GameProfiles::default(),
+        library: Library::default(),
+        rom_database,
+        netplay: None,
+        audio_controls,
+        audio,
     };
     eframe::run_native(
         "SilkNES",
         options,
-        Box::new(|_cc| Box::<SilkNES>::new(silknes)),
+        Box::new(|cc| {
+            let mut silknes = silknes;
+            // Settings move out of eframe's storage into the settings file the first time round
+            let config = if silknes.settings.existed() {
+                Config::load(Some(&silknes.settings))
+            } else {
+                let config = Config::load(cc.storage);
+                config.save(&mut silknes.settings);
+                eframe::Storage::flush(&mut silknes.settings);
+                config
+            };
+            silknes.console.open = config.console_open;
+            silknes.console.watches.clone_from(&config.watches);
+            silknes.saved_config = config.clone();
+            silknes.apply_config(&cc.egui_ctx, config);
+            silknes.cheat_library = CheatLibrary::load(cc.storage);
+            silknes.game_profiles = GameProfiles::load(cc.storage);
+            silknes.library = Library::load(cc.storage);
+            if matches!(silknes.audio, AudioBackend::Null) {
+                silknes.toasts.error(i18n::tr("audio.no_device"));
+            }
+            Box::<SilkNES>::new(silknes)
+        }),
     )
 }
 
+/// Seconds since the Unix epoch, for naming screenshots and noting when games were played
+fn unix_seconds() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
 struct SilkNES {
-    /// Immediate viewports are show immediately, so passing state to/from them is easy.
-    /// The downside is that their painting is linked with the parent viewport:
-    /// if either needs repainting, they are both repainted.
     show_about_window: bool,
+    cheat_window: CheatWindow,
+    game_profile_window: GameProfileWindow,
+    ram_search: RamSearch,
+    condition_window: ConditionWindow,
+    practice: Practice,
+    apu_timeline: ApuTimeline,
+    apu_viewer: ApuViewer,
+    event_viewer: EventViewer,
+    nametable_viewer: NametableViewer,
+    code_profile_window: CodeProfileWindow,
+    netplay_window: NetplayWindow,
+    library_window: LibraryWindow,
+    hotkey_window: HotkeyWindow,
+    input_window: InputWindow,
+    rom_error_window: RomErrorWindow,
+    toasts: Toasts,
+    console: Console,
 
-    menubar: Option<Menu>,
-    menubar_items: HashMap<MenuId, String>,
-    menubar_interaction: String,
-
-    bus: Rc<RefCell<Box<dyn BusLike>>>,
-    cpu: Rc<RefCell<NES6502>>,
-    ppu: Rc<RefCell<PPU>>,
-    apu: Rc<RefCell<APU>>,
-    cartridge: Option<Rc<RefCell<Cartridge>>>,
-    rom_loaded: bool,
+    config: Config,
+    /// The config as it was last saved, to save it again as soon as it changes
+    saved_config: Config,
+    settings: SettingsFile,
+    video_filters: VideoFilterChain,
+    display: Display,
 
-    tx: mpsc::Sender<Vec<f32>>,
+    /// The console, running on its own thread
+    emulation: Emulation,
+    /// SHA-256 of the loaded ROM, used to look up its cheats and profile
+    rom_hash: Option<String>,
+    /// The loaded ROM, kept so netplay can power cycle both consoles into the same state
+    rom_bytes: Option<Vec<u8>>,
+    cheat_library: CheatLibrary,
+    game_profiles: GameProfiles,
+    library: Library,
+    /// Known good dumps, for naming ROMs and fixing bad headers
+    rom_database: RomDatabase,
+    netplay: Option<Session>,
+    /// Volume, mute and latency, shared with the thread playing the audio
+    audio_controls: AudioControls,
+    audio: AudioBackend,
 }
 
 impl eframe::App for SilkNES {
@@ -140,310 +262,834 @@ impl eframe::App for SilkNES {
         egui_extras::install_image_loaders(ctx);
         ctx.request_repaint();
 
-        // Check for interactions on the menubar
-        if let Ok(event) = MenuEvent::receiver().try_recv() {
-            let item_string = self.menubar_items.get(event.id()).unwrap();
-            match item_string.as_str() {
-                "Load ROM" => {
-                    let file = FileDialog::new()
-                        .add_filter("ROMs", &["nes", "fds"])
-                        .set_directory("./roms")
-                        .pick_file();
-                    if let Some(path) = file {
-                        let rom_bytes = std::fs::read(path.clone()).unwrap();
-                        let cartridge = Rc::new(RefCell::new(Cartridge::from_bytes(rom_bytes.clone())));
-                        {
-                            let mut bus_ref = self.bus.borrow_mut();
-                            let cartridge_ref = Rc::clone(&cartridge);
-                            bus_ref.insert_cartridge(Rc::clone(&cartridge_ref));
-                        }
-                        self.cartridge = Some(cartridge);
-                        self.rom_loaded = true;
-
-                        self.cpu.borrow_mut().reset();
-                        self.ppu.borrow_mut().reset();
-
-                        let mut title_string = "SilkNES | ".to_string();
-                        let sha256 = digest(rom_bytes);
-                        let rom_name = check_dat_file(&sha256);
-                        if let Some(name) = rom_name {
-                            title_string += &name;
-                        } else {
-                            let filename = path.file_name().unwrap().to_str().unwrap().to_string();
-                            title_string += &filename;
-                        }
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title_string));
-                    }
-                },
-                "Quit" => {
-                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        // Check for commands from hotkeys, the menubar and the console. Hotkeys come first so they
+        // work even while a menu is open, but not while a key's being bound.
+        if !self.hotkey_window.capturing() && !self.input_window.capturing() {
+            for command in self.config.hotkeys.pressed(ctx) {
+                self.run_command(ctx, command);
+            }
+            for command in self.practice.pressed(ctx) {
+                self.run_command(ctx, command);
+            }
+        }
+        let (mix, unimplemented) = {
+            let core = self.emulation.lock();
+            (core.nes.channel_mix(), core.nes.unimplemented_mapper_features())
+        };
+        if let Some(command) = menubar::show(ctx, &self.config, &self.audio, self.audio_controls.muted(), mix, &unimplemented) {
+            self.run_command(ctx, command);
+        }
+        let command = self.console.show(ctx, &self.emulation.lock().nes);
+        if let Some(command) = command {
+            self.run_command(ctx, command);
+        }
+        for event in self.emulation.take_events() {
+            match event {
+                Event::Stopped(message) => {
+                    self.console.log(message);
+                    self.console.open = true;
                 },
-                "About" => {
-                    self.show_about_window = true;
-                }
-                _ => {}
             }
-        } else if self.menubar_interaction != "" {
-            // I don't love this but it's conceptually easier than messing around
-            // with the Windows API I'd have to interact with for accelerators
-            match self.menubar_interaction.to_owned().as_str() {
-                "Load ROM" => {
-                    let file = FileDialog::new()
-                        .add_filter("ROMs", &["nes", "fds"])
-                        .set_directory("./roms")
-                        .pick_file();
-                    if let Some(path) = file {
-                        let rom_bytes = std::fs::read(path.clone()).unwrap();
-                        let cartridge = Rc::new(RefCell::new(Cartridge::from_bytes(rom_bytes.clone())));
-                        {
-                            let mut bus_ref = self.bus.borrow_mut();
-                            let cartridge_ref = Rc::clone(&cartridge);
-                            bus_ref.insert_cartridge(Rc::clone(&cartridge_ref));
-                        }
-                        self.cartridge = Some(cartridge);
-                        self.rom_loaded = true;
-
-                        self.cpu.borrow_mut().reset();
-                        self.ppu.borrow_mut().reset();
-
-                        let mut title_string = "SilkNES | ".to_string();
-                        let sha256 = digest(rom_bytes);
-                        let rom_name = check_dat_file(&sha256);
-                        if let Some(name) = rom_name {
-                            title_string += &name;
-                        } else {
-                            let filename = path.file_name().unwrap().to_str().unwrap().to_string();
-                            title_string += &filename;
-                        }
-                    }
+        }
+
+        // Hand over input as soon as it's read, so it's what the game sees on the next slice of a frame that runs
+        let game_config = self.game_profile().apply(&self.config);
+        let frame_count = self.emulation.lock().nes.frame_count();
+        let pad = game_config.input.read(ctx);
+        let controller_state = pad.buttons(frame_count);
+        let hotkeys = &self.config.hotkeys;
+        let capturing = self.hotkey_window.capturing() || self.input_window.capturing();
+        let held = |action| !capturing && hotkeys.held(ctx, action);
+        let rewinding = held(HotkeyAction::Rewind);
+        self.emulation.send_input(Input {
+            pad,
+            fast_forward: held(HotkeyAction::FastForward),
+            rewinding,
+            frame_advance: held(HotkeyAction::FrameAdvance),
+            time: ctx.input(|i| i.time),
+            background: (!ctx.input(|i| i.focused)).then_some(self.config.background),
+        });
+        if rewinding && self.netplay.is_none() && self.emulation.lock().nes.rom_loaded() {
+            self.toasts.hold("rewind", "Rewinding…");
+        }
+        // Netplay decides when frames run, and with whose input
+        self.run_netplay_frame(controller_state);
+
+        // Render the newest frame to a texture for egui
+        let (screen, previous) = self.emulation.screens();
+        let previous = game_config.flicker_blend.then_some(previous);
+        let texture = self.display.update(ctx, screen, previous, &self.video_filters);
+
+        // Draw main window, or the demos on offer until there's a game to draw
+        let rom_loaded = self.emulation.lock().nes.rom_loaded();
+        if rom_loaded {
+            egui::CentralPanel::default().frame(egui::Frame::none()).show(ctx, |ui| {
+                // Fit the display to whatever space is left, keeping its aspect ratio
+                let (uv, visible) = visible_area(game_config.hide_overscan);
+                let available = ui.available_size();
+                let scale = (available.x / visible.x).min(available.y / visible.y);
+                let sized_image = egui::load::SizedTexture::new(texture, visible * scale);
+                let image = egui::Image::from_texture(sized_image).uv(uv);
+                ui.vertical_centered(|ui| {
+                    let response = ui.add(image);
+                    self.toasts.place_over(response.rect);
+                });
+            });
+        } else if let Some(command) = menubar::show_welcome(ctx) {
+            self.run_command(ctx, command);
+        }
+
+        // Draw about window, if active
+        menubar::show_about_window(ctx, &mut self.show_about_window);
+
+        // Draw cheat window, if active, and hand any changes straight to the console
+        let cheats = self.rom_hash.as_ref().map(|hash| self.cheat_library.cheats_mut(hash));
+        if let Some(change) = self.cheat_window.show(ctx, cheats) {
+            let cheats = self.game_cheats();
+            self.emulation.lock().nes.set_cheats(cheats);
+            self.toasts.info(change);
+        }
+        let profile = self.rom_hash.as_ref().map(|hash| self.game_profiles.get_mut(hash));
+        if self.game_profile_window.show(ctx, profile, &self.config) {
+            self.apply_game_profile();
+        }
+        let (rom_loaded, practice_command, died) = {
+            let mut core = self.emulation.lock();
+            if self.ram_search.open {
+                self.ram_search.show(ctx, &mut core.nes);
+            }
+            if self.condition_window.open {
+                self.condition_window.show(ctx, &mut core.nes);
+            }
+            let practice_command = if self.practice.open { self.practice.show(ctx, &mut core.nes) } else { None };
+            let events = core.nes.take_condition_events();
+            for message in self.condition_window.fired(&events) {
+                self.toasts.info(message);
+            }
+            let died = self.practice.died(&events);
+            if self.apu_timeline.open {
+                self.apu_timeline.show(ctx, &core.nes);
+            }
+            // Only record the channels' levels while there's a scope to draw them
+            core.nes.set_scope_enabled(self.apu_viewer.open);
+            if self.apu_viewer.open {
+                self.apu_viewer.show(ctx, &core.nes);
+            }
+            // Likewise register writes, while there's somewhere to plot them
+            core.nes.set_event_logging(self.event_viewer.open);
+            if self.event_viewer.open {
+                self.event_viewer.show(ctx, &core.nes);
+            }
+            // And the scroll at the start of each line, while there are nametables to draw it over
+            core.nes.set_scroll_logging(self.nametable_viewer.open);
+            if self.nametable_viewer.open {
+                self.nametable_viewer.show(ctx, &mut core.nes);
+            }
+            // Counting cycles per instruction slows emulation down, so only while they're being looked at
+            core.nes.set_code_profiling(self.code_profile_window.open);
+            if self.code_profile_window.open {
+                self.code_profile_window.show(ctx, &mut core.nes);
+            }
+            (core.nes.rom_loaded(), practice_command, died)
+        };
+        if died {
+            self.notify("Death detected");
+            self.run_command(ctx, Command::SaveState(self.practice.death_slot));
+        }
+        if let Some(command) = practice_command {
+            self.run_command(ctx, command);
+        }
+        if let Some(hotkeys) = self.hotkey_window.show(ctx, &self.config.hotkeys) {
+            let config = Config { hotkeys, ..self.config.clone() };
+            self.apply_config(ctx, config);
+        }
+        // Games with their own controls have those changed instead of the global ones
+        if let Some(input) = self.input_window.show(ctx, &game_config.input) {
+            match &self.rom_hash {
+                Some(hash) if self.game_profiles.get(hash).input.is_some() => {
+                    self.game_profiles.get_mut(hash).input = Some(input);
+                },
+                _ => {
+                    let config = Config { input, ..self.config.clone() };
+                    self.apply_config(ctx, config);
                 },
-                _ => {}
             }
-            self.menubar_interaction = "".to_string();
-        }
-
-        if self.rom_loaded {
-            // Run the emulation
-            // It would be nice to just eventually step the bus itself,
-            // but the borrow checker is screwing me here so this is fine for now
-            for _ in 0..(341*262) {
-                // Grab some variables from the bus to use while stepping
-                let cycles = self.bus.borrow().get_global_cycles();
-                let dma_running = self.bus.borrow().dma_running();
-                let mut should_run_dma = false;
-
-                self.ppu.borrow_mut().step();
-                if cycles % 3 == 0 {
-                    if self.bus.borrow().dma_queued() && !dma_running {
-                        if cycles % 2 == 1 {
-                            should_run_dma = true;
-                        }
-                    } else if dma_running {
-                        if cycles % 2 == 0 {
-                            let dma_data = {
-                                let bus = self.bus.borrow();
-                                let dma_page = bus.dma_page() as u16;
-                                let dma_address = bus.dma_address() as u16;
-                                let dma_data = bus.cpu_read((dma_page << 8) | dma_address);
-                                dma_data
-                            };
-                            self.bus.borrow_mut().set_dma_data(dma_data);
-                        } else {
-                            let mut dma_address = self.bus.borrow().dma_address();
-                            let dma_data = self.bus.borrow().dma_data();
-                            let oam_index = (dma_address / 4) as usize;
-                            let mut ppu = self.ppu.borrow_mut();
-                            match dma_address % 4 {
-                                0 => ppu.oam[oam_index].y = dma_data,
-                                1 => ppu.oam[oam_index].id = dma_data,
-                                2 => ppu.oam[oam_index].attributes.set_from_u8(dma_data),
-                                3 => ppu.oam[oam_index].x = dma_data,
-                                _ => (),
-                            }
-                            dma_address = dma_address.wrapping_add(1);
-                            self.bus.borrow_mut().set_dma_address(dma_address);
-
-                            if dma_address == 0 {
-                                self.bus.borrow_mut().set_dma_running(false);
-                                self.bus.borrow_mut().set_dma_queued(false);
-                            }
-                        }
-                    } else {
-                        self.cpu.borrow_mut().step();
-                        self.apu.borrow_mut().step(self.cpu.borrow().total_cycles);
-                        if self.apu.borrow().registers.status.dmc_interrupt || self.apu.borrow().registers.status.frame_interrupt || self.cartridge.as_ref().unwrap().borrow().mapper.irq_state() {
-                            self.cpu.borrow_mut().irq();
-                        }
+        }
+        self.rom_error_window.show(ctx);
+        let status = self.netplay.as_ref().map(Session::status);
+        if let Some(action) = self.netplay_window.show(ctx, status, rom_loaded) {
+            self.run_netplay_action(action);
+        }
+        if let Some(action) = self.library_window.show(ctx, &self.library) {
+            self.run_library_action(ctx, action);
+        }
+        self.toasts.show(ctx);
+
+        // The debug console's kept with the settings. Nothing's saved mid drag, so a slider's only
+        // saved once it's let go.
+        self.config.console_open = self.console.open;
+        self.config.watches.clone_from(&self.console.watches);
+        if !ctx.input(|input| input.pointer.any_down()) {
+            self.config.save_if_changed(&mut self.saved_config, &mut self.settings);
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.config.save(&mut self.settings);
+        eframe::Storage::flush(&mut self.settings);
+        self.cheat_library.save(storage);
+        self.game_profiles.save(storage);
+        self.library.save(storage);
+    }
+}
+
+impl SilkNES {
+    fn run_command(&mut self, ctx: &egui::Context, command: Command) {
+        match command {
+            Command::LoadRom => self.load_rom(ctx),
+            Command::LoadTestPattern => {
+                self.insert_rom(ctx, test_pattern::rom(), "Test pattern".to_string());
+                self.notify(test_pattern::CONTROLS);
+            },
+            Command::PlayDemo(index) => {
+                let demo = &DEMOS[index];
+                self.insert_rom(ctx, demo.rom(), demo.name.to_string());
+                self.notify(demo.controls);
+            },
+            Command::SaveState(slot) => {
+                let rom_loaded = self.emulation.lock().nes.rom_loaded();
+                if rom_loaded {
+                    {
+                        let core = &mut *self.emulation.lock();
+                        core.save_slots[slot] = Some(core.nes.save_state());
+                        self.practice.saved(slot, core.nes.frame_count(), core.nes.screen());
                     }
+                    self.notify(format!("Saved state to slot {}", slot));
                 }
-                let nmi = self.ppu.borrow().nmi;
-                if nmi {
-                    self.ppu.borrow_mut().nmi = false;
-                    self.cpu.borrow_mut().nmi();
+            },
+            Command::TogglePause | Command::FrameAdvance | Command::RunTo(_) if self.netplay.is_some() => {
+                self.notify_error("Netplay can't be paused, the other player's game would be left waiting");
+            },
+            Command::TogglePause => self.emulation.lock().frame_advance.toggle_pause(),
+            Command::FrameAdvance => self.emulation.lock().frame_advance.advance(),
+            Command::Reset | Command::PowerCycle if self.netplay.is_some() => {
+                self.notify_error("The console can't be reset during netplay, the other player's game wouldn't follow");
+            },
+            Command::Reset => {
+                let rom_loaded = self.emulation.lock().nes.rom_loaded();
+                if rom_loaded {
+                    self.emulation.lock().nes.reset();
+                    self.notify("Reset");
                 }
-                self.bus.borrow_mut().set_global_cycles(cycles + 1);
-                if should_run_dma {
-                    self.bus.borrow_mut().set_dma_running(true);
+            },
+            Command::PowerCycle => {
+                let rom_loaded = self.emulation.lock().nes.rom_loaded();
+                if rom_loaded {
+                    self.emulation.lock().nes.power_on();
+                    self.notify("Power cycled");
                 }
-                self.apu.borrow_mut().update_output();
-            }
-
-            // Update audio
-            let buffer = std::mem::take(&mut self.apu.borrow_mut().output_buffer);
-            let averaged = buffer
-                .chunks(112)
-                .fold(Vec::new(), |mut acc, x| {
-                    let sum: f32 = x.iter().sum();
-                    acc.push(sum / x.len() as f32);
-                    acc
-                });
-            self.tx.send(averaged).unwrap();
-        }
-
-        // Render the display to a texture for egui
-        let display = self.ppu.borrow().get_screen();
-        let color_image = egui::ColorImage::from_rgb([256, 240], &display);
-        let handle = ctx.load_texture("Display", color_image, egui::TextureOptions::NEAREST);
-
-        // Draw main window
-        egui::CentralPanel::default().frame(egui::Frame::none()).show(ctx, |ui| {
-            if self.menubar.is_none() {
-                let (menubar, menubar_items) = create_menubar();
-                #[cfg(target_os = "windows")]
-                {
-                    let handle = _frame.window_handle().unwrap().as_raw();
-                    let hwnd = match handle {
-                        RawWindowHandle::Win32(handle) => handle.hwnd.get(),
-                        _ => panic!("Cannot handle other platform window handles yet!"),
-                    };
-                    menubar.init_for_hwnd(hwnd).unwrap();
+            },
+            Command::LoadState(_) if self.netplay.is_some() => {
+                self.notify_error("States can't be loaded during netplay, the other player's game wouldn't follow");
+            },
+            Command::LoadState(slot) => {
+                let loaded = {
+                    let core = &mut *self.emulation.lock();
+                    if let Some(state) = &core.save_slots[slot] {
+                        core.nes.load_state(state);
+                        core.rewind.clear();
+                    }
+                    core.save_slots[slot].is_some()
+                };
+                if loaded {
+                    self.notify(format!("Loaded state from slot {}", slot));
+                } else {
+                    self.notify_error(format!("Slot {} is empty", slot));
+                }
+            },
+            Command::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            Command::SetLanguage(language) => {
+                let config = Config { language, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetColorVision(color_vision) => {
+                let config = Config { color_vision, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetUiScale(ui_scale) => {
+                let config = Config { ui_scale, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetVolume(volume) => {
+                let config = Config { volume, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetAudioLatency(audio_latency_ms) => {
+                let config = Config { audio_latency_ms, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetAudioDriver(audio_driver) => {
+                let config = Config { audio_driver, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetSyncMode(sync_mode) => {
+                let config = Config { sync_mode, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetUltrasonicTriangle(ultrasonic_triangle) => {
+                let config = Config { ultrasonic_triangle, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetReducePulsePopping(reduce_pulse_popping) => {
+                let config = Config { reduce_pulse_popping, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetMixer(mixer) => {
+                let config = Config { mixer, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetStereoMode(stereo) => {
+                let config = Config { stereo, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetChannelPan(channel, pan) => {
+                let mut channel_pan = self.config.channel_pan;
+                channel_pan.set_pan(channel, pan);
+                let config = Config { stereo: StereoMode::Custom, channel_pan, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetPowerOnState(power_on) => {
+                let config = Config { power_on, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetBackgroundMode(background) => {
+                let config = Config { background, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::ToggleChannelMute(channel) => {
+                let nes = &mut self.emulation.lock().nes;
+                let mut mix = nes.channel_mix();
+                mix.set_muted(channel, !mix.muted(channel));
+                nes.set_channel_mix(mix);
+            },
+            Command::ToggleChannelSolo(channel) => {
+                let nes = &mut self.emulation.lock().nes;
+                let mut mix = nes.channel_mix();
+                mix.set_soloed(channel, !mix.soloed(channel));
+                nes.set_channel_mix(mix);
+            },
+            Command::SetPalette(palette) => {
+                let config = Config { palette, custom_palette: None, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetHideOverscan(hide_overscan) => {
+                let config = Config { hide_overscan, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetFlickerBlend(flicker_blend) => {
+                let config = Config { flicker_blend, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetFrameSkip(frame_skip) => {
+                let config = Config { frame_skip, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::LoadPaletteFile => self.load_palette_file(ctx),
+            Command::ShowCheats => self.cheat_window.open = true,
+            Command::ShowGameProperties => self.game_profile_window.open = true,
+            Command::ShowRamSearch => self.ram_search.open = true,
+            Command::ShowConditions => self.condition_window.open = true,
+            Command::ShowPractice => self.practice.open = true,
+            Command::ShowApuTimeline => self.apu_timeline.open = true,
+            Command::ShowApuViewer => self.apu_viewer.open = true,
+            Command::ShowEventViewer => self.event_viewer.open = true,
+            Command::ShowNametableViewer => self.nametable_viewer.open = true,
+            Command::ShowCodeProfiler => self.code_profile_window.open = true,
+            Command::ShowNetplay => self.netplay_window.open = true,
+            Command::ShowLibrary => self.library_window.open = true,
+            Command::ShowHotkeys => self.hotkey_window.open = true,
+            Command::ShowInput => self.input_window.open = true,
+            Command::SaveScreenshot => {
+                let rom_loaded = self.emulation.lock().nes.rom_loaded();
+                if rom_loaded {
+                    self.save_screenshot();
+                }
+            },
+            Command::ToggleFullscreen => {
+                let fullscreen = ctx.input(|i| i.viewport().fullscreen).unwrap_or(false);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+            },
+            Command::ToggleMute => {
+                let muted = !self.audio_controls.muted();
+                self.audio_controls.set_muted(muted);
+                self.notify(if muted { "Muted" } else { "Unmuted" });
+            },
+            Command::About => self.show_about_window = true,
+            Command::ToggleConsole => self.console.open = !self.console.open,
+            Command::Break(address) => {
+                let described = {
+                    let nes = &mut self.emulation.lock().nes;
+                    nes.add_breakpoint(address);
+                    nes.describe_address(address)
+                };
+                self.console.log(format!("Breakpoint set at {}", described));
+            },
+            Command::ClearBreakpoints => {
+                self.emulation.lock().nes.clear_breakpoints();
+                self.console.log("Cleared all breakpoints");
+            },
+            Command::Continue => self.emulation.lock().nes.resume(),
+            Command::RunTo(target) => {
+                let message = {
+                    let core = &mut *self.emulation.lock();
+                    core.nes.rom_loaded().then(|| {
+                        core.frame_advance.paused = true;
+                        core.nes.resume();
+                        let reached = core.nes.run_until(target);
+                        // A few cycles of sound would only come out as a click
+                        core.nes.take_raw_audio();
+                        match core.nes.describe_stop() {
+                            Some(message) => message,
+                            None if reached => format!("Stopped at {}", core.nes.describe_position()),
+                            None => format!("Didn't get there within two frames, stopped at {}", core.nes.describe_position()),
+                        }
+                    })
+                };
+                if let Some(message) = message {
+                    self.console.log(message);
                 }
-                #[cfg(target_os = "macos")]
-                {
-                    menubar.init_for_nsapp();
+            },
+            Command::AddWatchpoint(watchpoint) => {
+                self.emulation.lock().nes.add_watchpoint(watchpoint);
+                self.console.log(format!("Watchpoint set on {}", watchpoint));
+            },
+            Command::ClearWatchpoints => {
+                self.emulation.lock().nes.clear_watchpoints();
+                self.console.log("Cleared all watchpoints");
+            },
+            Command::Watch(address) => {
+                if !self.console.watches.contains(&address) {
+                    self.console.watches.push(address);
                 }
-                self.menubar = Some(menubar);
-                self.menubar_items = menubar_items;
+            },
+            Command::Unwatch(address) => self.console.watches.retain(|watch| *watch != address),
+            Command::SetSpeed(speed) => {
+                self.emulation.lock().speed = speed;
+                self.console.log(format!("Speed set to {}x", speed));
+            },
+            Command::DumpNametable(index, path) => {
+                let nametable = self.emulation.lock().nes.bus.ppu.nametables[index];
+                match std::fs::write(&path, nametable) {
+                    Ok(()) => self.notify(format!("Wrote nametable {} to {}", index, path)),
+                    Err(error) => self.notify_error(format!("Couldn't write {}: {}", path, error)),
+                }
+            },
+            Command::Screenshot(frame, path) => {
+                let (current, captured) = {
+                    let nes = &mut self.emulation.lock().nes;
+                    (nes.frame_count(), nes.capture_frame(frame))
+                };
+                match captured {
+                    Some(screen) => match save_png(&path, &screen) {
+                        Ok(()) => self.notify(format!("Wrote frame {} to {}", frame, path)),
+                        Err(error) => self.notify_error(format!("Couldn't write {}: {}", path, error)),
+                    },
+                    None if frame <= current => self.notify_error(format!("Frame {} has already been drawn, this is frame {}", frame, current)),
+                    None => self.notify_error(format!("Stopped before frame {} was drawn", frame)),
+                }
+            },
+            Command::Help => {
+                for line in CONSOLE_HELP {
+                    self.console.log(*line);
+                }
+            },
+        }
+    }
+
+    fn apply_config(&mut self, ctx: &egui::Context, config: Config) {
+        let previous_scale = ctx.zoom_factor();
+        i18n::set_language(config.language);
+        ctx.set_zoom_factor(config.ui_scale);
+
+        // Keep the display the same size on screen when the UI is scaled
+        if (previous_scale - config.ui_scale).abs() > f32::EPSILON {
+            let size = egui::vec2(512.0, 480.0 + MENUBAR_HEIGHT);
+            ctx.send_viewport_cmd(egui::ViewportCommand::MinInnerSize(size));
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(size));
+        }
+
+        self.video_filters.clear();
+        if config.color_vision != ColorVision::Normal {
+            self.video_filters.push(Box::new(Daltonize::new(config.color_vision)));
+        }
+        {
+            let palette = self.game_profile().apply(&config).active_palette();
+            let nes = &mut self.emulation.lock().nes;
+            nes.set_palette(palette);
+            nes.set_ultrasonic_triangle(config.ultrasonic_triangle);
+            nes.set_reduce_pulse_popping(config.reduce_pulse_popping);
+            nes.set_mixer(config.mixer);
+            nes.set_channel_pan(config.active_pan());
+            nes.set_power_on_state(config.power_on);
+            nes.set_frame_skip(config.frame_skip);
+        }
+
+        self.audio_controls.set_volume(config.volume);
+        self.audio_controls.set_latency_ms(config.audio_latency_ms);
+        self.audio_controls.set_sync_mode(config.sync_mode);
+        if config.audio_driver != self.config.audio_driver {
+            // Hang up on the old output before opening the device again
+            let (tx, rx) = mpsc::channel();
+            self.emulation.lock().audio_pipeline = AudioPipeline::start(tx);
+            self.audio = AudioBackend::start(rx, config.audio_driver, self.audio_controls.clone());
+            if matches!(self.audio, AudioBackend::Null) {
+                self.toasts.error(i18n::tr("audio.no_device"));
             }
+        }
 
-            let sized_image = egui::load::SizedTexture::new(handle.id(), egui::vec2(512.0, 480.0));
-            let image = egui::Image::from_texture(sized_image);
-            ui.add(image);
-        });
+        self.config = config;
+    }
 
-        // Draw about window, if activve
-        if self.show_about_window {
-            ctx.show_viewport_immediate(
-                egui::ViewportId::from_hash_of("about_window"),
-                egui::ViewportBuilder::default()
-                    .with_title("About")
-                    .with_inner_size([256.0, 128.0]),
-                |ctx, class| {
-                    assert!(
-                        class == egui::ViewportClass::Immediate,
-                        "This egui backend doesn't support multiple viewports"
-                    );
-
-                    egui::CentralPanel::default().show(ctx, |ui| {
-                        ui.vertical_centered(|ui| {
-                            ui.label("Created by Daniel Adams");
-                        })
-                    });
-
-                    if ctx.input(|i| i.viewport().close_requested()) {
-                        // Tell parent viewport that we should not show next frame:
-                        self.show_about_window = false;
-                    }
-                },
-            );
-        }
-
-        // Handle input
-        let mut controller_state = 0x00;
-
-        for (key, value) in [
-            (Key::ArrowRight, 0x01), // D-Pad Right
-            (Key::ArrowLeft, 0x02), // D-Pad Left
-            (Key::ArrowDown, 0x04), // D-Pad Down
-            (Key::ArrowUp, 0x08), // D-Pad Up
-            (Key::Enter, 0x10), // Start
-            (Key::Space, 0x20), // Select
-            (Key::Z, 0x40), // B
-            (Key::X, 0x80), // A
-        ] {
-            if ctx.input(|i| i.key_down(key)) {
-                controller_state |= value;
+    fn load_rom(&mut self, ctx: &egui::Context) {
+        let directory = self.config.rom_directory.clone().unwrap_or_else(|| "./roms".into());
+        let file = FileDialog::new()
+            .add_filter(i18n::tr("dialog.roms"), &["nes", "fds"])
+            .set_directory(directory)
+            .pick_file();
+        if let Some(path) = file {
+            self.load_rom_file(ctx, &path);
+        }
+    }
+
+    /// Load the ROM at `path`, counting it as played if it's in the library
+    fn load_rom_file(&mut self, ctx: &egui::Context, path: &Path) {
+        self.config.rom_directory = path.parent().map(Path::to_path_buf);
+        let filename = path.file_name().unwrap().to_str().unwrap().to_string();
+        let loaded = match std::fs::read(path) {
+            Ok(rom_bytes) => self.insert_rom(ctx, rom_bytes, filename),
+            Err(error) => {
+                self.rom_error_window.open(CartridgeError::Io(error).to_string());
+                false
+            },
+        };
+        if loaded && self.library.entries.iter().any(|entry| entry.path == path) {
+            self.library.played(path, unix_seconds());
+        }
+    }
+
+    fn run_library_action(&mut self, ctx: &egui::Context, action: LibraryAction) {
+        let folder = match action {
+            LibraryAction::Load(path) => return self.load_rom_file(ctx, &path),
+            LibraryAction::OpenFolder => {
+                let directory = self.library.folder.clone().or_else(|| self.config.rom_directory.clone());
+                let Some(folder) = FileDialog::new().set_directory(directory.unwrap_or_else(|| "./roms".into())).pick_folder() else {
+                    return;
+                };
+                folder
+            },
+            LibraryAction::Rescan => {
+                let Some(folder) = self.library.folder.clone() else {
+                    return;
+                };
+                folder
+            },
+        };
+        if let Err(error) = self.library.scan(&folder, &self.rom_database) {
+            self.notify_error(format!("Couldn't read {}: {}", folder.display(), error));
+        }
+    }
+
+    /// Swap the game for `rom_bytes`, fixing its header first if the ROM database knows better. It's
+    /// called `filename` unless the database knows its name. Returns whether it could be loaded.
+    fn insert_rom(&mut self, ctx: &egui::Context, mut rom_bytes: Vec<u8>, filename: String) -> bool {
+        let header_fix = self.rom_database.fix_header(&mut rom_bytes);
+        // Carry on with whatever was running before
+        let cartridge = match Cartridge::from_bytes(rom_bytes.clone()) {
+            Ok(cartridge) => cartridge,
+            Err(error) => {
+                self.rom_error_window.open(error.to_string());
+                return false;
             }
+        };
+        // The other player can't follow us onto another game
+        if self.netplay.is_some() {
+            self.notify("Netplay ended: loaded another ROM");
+            self.end_netplay();
+        }
+        let sha256 = digest(rom_bytes.as_slice());
+        let mapper = cartridge.mapper_description();
+        {
+            let mut core = self.emulation.lock();
+            core.nes.insert_cartridge(cartridge);
+            // States from the previous game can't be loaded into this one
+            core.save_slots.fill(None);
+            core.rewind.clear();
+        }
+        self.practice.clear();
+        self.rom_hash = Some(sha256.clone());
+        self.apply_game_profile();
+
+        let rom_name = self.rom_database.find_sha256(&sha256).map(|entry| entry.name.clone());
+        let name = rom_name.unwrap_or(filename);
+        self.notify(format!("Loaded {} ({})", name, mapper));
+        let mut title_string = format!("SilkNES | {}", name);
+        if let Some(fix) = header_fix {
+            title_string += " (header fixed)";
+            self.notify(format!("Fixed the header for {}: {}", fix.name, fix.changes.join(", ")));
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title_string));
+        self.rom_bytes = Some(rom_bytes);
+        // Whatever was loaded before has stopped being played
+        self.library.stop();
+        true
+    }
+
+    /// The loaded game's profile, which is the defaults if there isn't a game
+    fn game_profile(&self) -> GameProfile {
+        self.rom_hash.as_ref().map(|hash| self.game_profiles.get(hash)).unwrap_or_default()
+    }
+
+    /// The loaded game's cheats, unless its profile has them switched off
+    fn game_cheats(&self) -> Vec<Cheat> {
+        match &self.rom_hash {
+            Some(hash) if self.game_profile().cheats_enabled => self.cheat_library.cheats(hash),
+            _ => vec![],
+        }
+    }
+
+    /// Put the loaded game's settings in place of the global ones they override
+    fn apply_game_profile(&mut self) {
+        let profile = self.game_profile();
+        let palette = profile.apply(&self.config).active_palette();
+        let cheats = self.game_cheats();
+        let nes = &mut self.emulation.lock().nes;
+        nes.set_palette(palette);
+        nes.set_region(profile.region);
+        nes.set_cheats(cheats);
+    }
+
+    /// Tell the user something happened, in a toast as well as the console
+    fn notify(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        self.console.log(message.clone());
+        self.toasts.info(message);
+    }
+
+    fn notify_error(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        self.console.log(message.clone());
+        self.toasts.error(message);
+    }
+
+    /// Save the frame on screen to the screenshots folder, named so they never overwrite each other
+    fn save_screenshot(&mut self) {
+        let seconds = unix_seconds();
+        let (frame_count, screen) = {
+            let nes = &self.emulation.lock().nes;
+            (nes.frame_count(), nes.screen().to_vec())
+        };
+        let path = format!("{}/silknes-{}-{}.png", SCREENSHOT_DIR, seconds, frame_count);
+        let saved = std::fs::create_dir_all(SCREENSHOT_DIR)
+            .map_err(|error| error.to_string())
+            .and_then(|()| save_png(&path, &screen));
+        match saved {
+            Ok(()) => self.notify(format!("Saved screenshot to {}", path)),
+            Err(error) => self.notify_error(format!("Couldn't write {}: {}", path, error)),
+        }
+    }
 
-            self.bus.borrow_mut().update_controller(0, controller_state);
+    fn run_netplay_action(&mut self, action: NetplayAction) {
+        let Some(rom_hash) = self.rom_hash.clone() else {
+            return;
+        };
+        let session = match action {
+            NetplayAction::Host(port) => Session::host(port, &rom_hash),
+            NetplayAction::Join(address) => Session::join(&address, &rom_hash),
+            NetplayAction::Disconnect => {
+                self.end_netplay();
+                return;
+            },
+        };
+        match session {
+            Ok(session) => self.netplay = Some(session),
+            Err(error) => self.notify_error(format!("Couldn't start netplay: {}", error)),
+        }
+    }
 
-            if ctx.input(|i| i.modifiers.ctrl) && ctx.input(|i| i.key_pressed(Key::O)) {
-                self.menubar_interaction = "Load ROM".to_string();
+    /// Run the next frame of the netplay session, once both players' inputs for it have arrived. While
+    /// it's connected the emulation thread leaves running frames to this.
+    fn run_netplay_frame(&mut self, controller_state: u8) {
+        let Some(session) = &mut self.netplay else {
+            return;
+        };
+        let inputs = session.update(controller_state);
+        let started = session.take_started();
+        let status = session.status().clone();
+
+        if started {
+            // Both consoles start from power on, without anything only one player has set up
+            let mut core = self.emulation.lock();
+            if let Some(rom_bytes) = &self.rom_bytes {
+                core.nes.insert_cartridge(Cartridge::from_bytes(rom_bytes.clone()).expect("this ROM loaded before"));
             }
+            core.nes.set_cheats(vec![]);
+            core.nes.clear_breakpoints();
+            core.pending_frames = 0.0;
+            drop(core);
+            self.notify("Netplay started");
         }
 
-        if ctx.input(|i| i.modifiers.ctrl) && ctx.input(|i| i.key_pressed(Key::O)) {
-            self.menubar_interaction = "Load ROM".to_string();
+        match status {
+            Status::Connected => {
+                let core = &mut *self.emulation.lock();
+                core.netplay = true;
+                if let Some([player_1, player_2]) = inputs {
+                    core.nes.update_controller(0, player_1);
+                    core.nes.update_controller(1, player_2);
+                    core.nes.run_frame();
+                    core.audio_pipeline.push(&core.nes.take_raw_audio());
+                }
+            },
+            Status::Disconnected(reason) => {
+                self.notify_error(format!("Netplay ended: {}", reason));
+                self.end_netplay();
+            },
+            Status::Waiting | Status::Connecting => {},
         }
     }
+
+    fn end_netplay(&mut self) {
+        self.netplay = None;
+        let core = &mut *self.emulation.lock();
+        core.netplay = false;
+        core.nes.update_controller(1, 0);
+        core.nes.set_cheats(self.game_cheats());
+    }
+
+    fn load_palette_file(&mut self, ctx: &egui::Context) {
+        let file = FileDialog::new()
+            .add_filter(i18n::tr("dialog.palettes"), &["pal"])
+            .pick_file();
+        let Some(path) = file else {
+            return;
+        };
+
+        let palette = std::fs::read(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|bytes| Palette::from_pal_bytes(&bytes));
+        match palette {
+            Ok(palette) => {
+                let config = Config { custom_palette: Some(palette), ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Err(error) => self.notify_error(format!("Couldn't load {}: {}", path.display(), error)),
+        }
+    }
+}
+
+/// `silknes --screenshot <rom> <frame> <file>`: run a ROM from power on until `frame` is drawn and
+/// save it as a PNG, so the same image comes out every time
+fn screenshot(args: &[String]) -> Result<(), String> {
+    let [rom, frame, path] = args else {
+        return Err("Usage: silknes --screenshot <rom> <frame> <file>".to_string());
+    };
+    let frame = frame.parse::<u64>()
+        .ok()
+        .filter(|frame| *frame > 0)
+        .ok_or(format!("Invalid frame number: {}", frame))?;
+    let bytes = std::fs::read(rom).map_err(|error| format!("Couldn't read {}: {}", rom, error))?;
+
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cartridge::from_bytes(bytes).map_err(|error| error.to_string())?);
+    let screen = nes.capture_frame(frame).ok_or(format!("Frame {} was never drawn", frame))?;
+    save_png(path, &screen).map_err(|error| format!("Couldn't write {}: {}", path, error))
 }
 
-fn create_menubar() -> (Menu, HashMap<MenuId, String>) {
-    let menu = Menu::new();
-
-    // File Tab
-    let load_rom = MenuItem::new(
-        "Load ROM",
-        true,
-        Some(Accelerator::new(Some(Modifiers::CONTROL), Code::KeyO)),
-    );
-    let quit = MenuItem::new(
-        "Quit",
-        true,
-        None,
-    );
-    let file_tab = Submenu::with_items(
-        "File",
-        true,
-        &[
-            &load_rom,
-            &PredefinedMenuItem::separator(),
-            &quit,
-        ],
-    ).unwrap();
-    menu.append(&file_tab).unwrap();
-
-    // Help Tab
-    let about = MenuItem::new(
-        "About",
-        true,
-        None,
-    );
-    let help_tab = Submenu::with_items(
-        "Help",
-        true,
-        &[
-            &about,
-        ],
-    ).unwrap();
-    menu.append(&help_tab).unwrap();
-
-    let mut menu_ids = HashMap::new();
-    menu_ids.insert(load_rom.id().clone(), "Load ROM".to_string());
-    menu_ids.insert(quit.id().clone(), "Quit".to_string());
-    menu_ids.insert(about.id().clone(), "About".to_string());
-
-    (menu, menu_ids)
+/// Frames `--bench` runs if it isn't told, 10 seconds of play
+const BENCH_FRAMES: u64 = 600;
+
+/// `silknes --bench <rom> [frames]`: run a ROM headless as fast as it'll go and report the frame rate,
+/// then run as long again with profiling on to show where the time goes. Profiling slows things down
+/// itself, so the frame rate comes from the first run.
+fn bench(args: &[String]) -> Result<(), String> {
+    let (rom, frames) = match args {
+        [rom] => (rom, BENCH_FRAMES),
+        [rom, frames] => (rom, frames.parse::<u64>().ok().filter(|frames| *frames > 0).ok_or(format!("Invalid frame count: {}", frames))?),
+        _ => return Err("Usage: silknes --bench <rom> [frames]".to_string()),
+    };
+    let bytes = std::fs::read(rom).map_err(|error| format!("Couldn't read {}: {}", rom, error))?;
+    let mut nes = Nes::new();
+    nes.insert_cartridge(Cartridge::from_bytes(bytes).map_err(|error| error.to_string())?);
+
+    let started = std::time::Instant::now();
+    nes.run_frames(frames);
+    let elapsed = started.elapsed();
+    let fps = frames as f64 / elapsed.as_secs_f64();
+    println!("{} frames in {:.2}s: {:.1} frames/sec, {:.1}x full speed", frames, elapsed.as_secs_f64(), fps, fps / NTSC_FRAME_RATE);
+
+    nes.start_profiling();
+    let started = std::time::Instant::now();
+    nes.run_frames(frames);
+    let elapsed = started.elapsed();
+    let profile = nes.stop_profiling().unwrap_or_default();
+    let [cpu, ppu, apu, other] = profile.percentages(elapsed);
+    println!("CPU {:.1}%, PPU {:.1}%, APU {:.1}%, other {:.1}%", cpu, ppu, apu, other);
+    Ok(())
 }
 
-fn check_dat_file(hash: &str) -> Option<String> {
-    let dat_file = std::fs::read("res/Nintendo - Nintendo Entertainment System (Headered) (20240606-224704).dat").unwrap();
-    let dat_file_string = String::from_utf8(dat_file).unwrap();
-    let dat = Document::parse(&dat_file_string).unwrap();
-    let elem = dat.descendants().find(|n| n.attribute("sha256") == Some(hash));
-    if let Some(elem) = elem {
-        Some(elem.attribute("name").unwrap().to_string())
-    } else {
-        None
+/// `silknes --regress <manifest>`: play every case in a regression manifest, as many at once as there
+/// are cores, and report which games no longer end up where they should. Fails if any of them don't.
+fn regress(args: &[String]) -> Result<(), String> {
+    let [manifest] = args else {
+        return Err("Usage: silknes --regress <manifest>".to_string());
+    };
+    let cases = regression::load_manifest(Path::new(manifest))?;
+
+    let started = std::time::Instant::now();
+    let reports = regression::run_all(&cases);
+    for report in &reports {
+        println!("{}", report);
+    }
+    let failed = reports.iter().filter(|report| !report.passed()).count();
+    println!("{} passed, {} failed in {:.1}s", reports.len() - failed, failed, started.elapsed().as_secs_f64());
+    if failed > 0 {
+        return Err(format!("{} of {} cases failed", failed, reports.len()));
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// Where `--compat-scan` writes its report if it isn't told
+const COMPAT_REPORT_PATH: &str = "compat.csv";
+
+/// `silknes --compat-scan <folder> [report] [frames]`: run every ROM in a folder headless, catching
+/// panics, and write a report of which ones load, run and draw something. The report's JSON if its
+/// name ends in .json, otherwise CSV. It's written to a file rather than printed, as loading a ROM
+/// prints its layout.
+fn compat_scan(args: &[String]) -> Result<(), String> {
+    let usage = || "Usage: silknes --compat-scan <folder> [report.csv|report.json] [frames]".to_string();
+    let (folder, report, frames) = match args {
+        [folder] => (folder.as_str(), COMPAT_REPORT_PATH, compat::COMPAT_FRAMES),
+        [folder, report] => (folder.as_str(), report.as_str(), compat::COMPAT_FRAMES),
+        [folder, report, frames] => (
+            folder.as_str(),
+            report.as_str(),
+            frames.parse::<u64>().ok().filter(|frames| *frames > 0).ok_or(format!("Invalid frame count: {}", frames))?,
+        ),
+        _ => return Err(usage()),
+    };
+
+    // Panics are expected and end up in the report, so keep them from filling the terminal meanwhile
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let results = compat::scan(Path::new(folder), frames);
+    std::panic::set_hook(default_hook);
+    let results = results.map_err(|error| format!("Couldn't read {}: {}", folder, error))?;
+
+    let text = if report.to_ascii_lowercase().ends_with(".json") { compat::to_json(&results) } else { compat::to_csv(&results) };
+    std::fs::write(report, text).map_err(|error| format!("Couldn't write {}: {}", report, error))?;
+    let ran = results.iter().filter(|result| result.ran_ok).count();
+    let panicked = results.iter().filter(|result| result.panic.is_some()).count();
+    let blank = results.iter().filter(|result| result.ran_ok && result.blank_screen).count();
+    println!("{} of {} ROMs ran, {} panicked, {} ran but stayed blank; report written to {}", ran, results.len(), panicked, blank, report);
+    Ok(())
+}
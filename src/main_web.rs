@@ -1,30 +1,112 @@
 pub mod apu;
 pub mod apu_output;
+pub mod apu_timeline;
+pub mod apu_viewer;
+pub mod battery;
 pub mod bus;
 pub mod cartridge;
+pub mod cheat;
+pub mod cheat_window;
+pub mod clock_audit;
+pub mod code_profile;
+pub mod code_profile_window;
+pub mod command;
+pub mod condition;
+pub mod condition_window;
+pub mod config;
+pub mod console;
 pub mod cpu;
+pub mod demos;
+pub mod event_viewer;
+pub mod frame_advance;
+pub mod frame_skip;
+pub mod game_profile;
+pub mod hotkeys;
+pub mod i18n;
+pub mod input;
+pub mod movie;
+pub mod nametable_viewer;
 pub mod ppu;
+pub mod practice;
+pub mod profile;
+pub mod ram_search;
+pub mod rewind;
+pub mod rom_database;
+pub mod rom_error_window;
+pub mod test_pattern;
+pub mod test_rom;
+pub mod toast;
+pub mod watchpoint;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod netplay;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod netplay_window;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod library;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod regression;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod compat;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod audio_pipeline;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod emulation;
+#[cfg(feature = "serde")]
+pub mod serde_arrays;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod video;
 pub mod mapper;
 pub mod mappers;
+pub mod menubar;
+pub mod nes;
+pub mod palette;
 
-use apu::APU;
-use apu_output::APUOutput;
-use bus::{Bus, BusLike};
-use cartridge::Cartridge;
-use cpu::NES6502;
-use ppu::PPU;
+use nes::Nes;
 
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::rc::Weak;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    mpsc,
     Mutex
 };
 
-use eframe::egui;
-use egui::Key;
-use rodio::{source::Source, OutputStream, Sink};
+// The rest is only for the app itself, which desktop builds of the library leave out
+#[cfg(target_arch = "wasm32")]
+use {
+    apu::StereoMode,
+    apu_output::{AudioBackend, AudioClock, AudioControls},
+    battery::BatterySaves,
+    cartridge::Cartridge,
+    cheat::Cheat,
+    cheat_window::{CheatLibrary, CheatWindow},
+    condition_window::ConditionWindow,
+    command::{Command, CONSOLE_HELP, SAVE_SLOTS},
+    config::Config,
+    console::Console,
+    demos::DEMOS,
+    frame_advance::{BackgroundMode, FrameAdvance, FAST_FORWARD_SPEED},
+    game_profile::{GameProfile, GameProfileWindow, GameProfiles},
+    hotkeys::{HotkeyAction, HotkeyWindow},
+    input::{InputWindow, PadState},
+    nes::SaveState,
+    palette::Palette,
+    practice::Practice,
+    ram_search::RamSearch,
+    rewind::Rewind,
+    rom_error_window::RomErrorWindow,
+    apu_timeline::ApuTimeline,
+    apu_viewer::ApuViewer,
+    code_profile_window::CodeProfileWindow,
+    event_viewer::EventViewer,
+    nametable_viewer::NametableViewer,
+    toast::Toasts,
+    video::{visible_area, Daltonize, ColorVision, Display, VideoFilterChain},
+    std::cell::Cell,
+    std::rc::Rc,
+    std::sync::mpsc,
+    eframe::egui,
+};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -35,8 +117,24 @@ lazy_static! {
     static ref ROM_CHANGED: AtomicBool = AtomicBool::new(false);
     static ref ROM_BYTES: Mutex<Vec<u8>> = Mutex::new(vec![]);
     static ref CONTROLLER_STATE: Mutex<u8> = Mutex::new(0);
+    static ref PALETTE_BYTES: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+    static ref STATE_BYTES: Mutex<Option<Vec<u8>>> = Mutex::new(None);
 }
 
+thread_local! {
+    /// The console, for `export_state` to read from outside of egui's update
+    static NES: RefCell<Weak<RefCell<Nes>>> = const { RefCell::new(Weak::new()) };
+}
+
+/// How often the browser timer wakes up to run the cycles that are due and sample input,
+/// roughly every quarter of a frame
+#[cfg(target_arch = "wasm32")]
+const EMULATION_TICK_MS: i32 = 4;
+
+/// Most frames to catch up on in one tick, so a throttled tab doesn't try to run them all at once
+#[cfg(target_arch = "wasm32")]
+const MAX_CATCH_UP_FRAMES: u32 = 4;
+
 #[cfg(target_arch = "wasm32")]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 fn main() {
@@ -45,233 +143,809 @@ fn main() {
 
     let web_options = eframe::WebOptions::default();
 
-    // Create bus
-    let bus = Rc::new(RefCell::new(Box::new(Bus::new()) as Box<dyn BusLike>));
+    let nes = Rc::new(RefCell::new(Nes::new()));
+    NES.with(|weak| *weak.borrow_mut() = Rc::downgrade(&nes));
+    let keyboard_state = Rc::new(Cell::new(PadState::default()));
+    let speed = Rc::new(Cell::new(1.0));
+    let frame_advance = Rc::new(RefCell::new(FrameAdvance::default()));
+    let fast_forward = Rc::new(Cell::new(false));
+    let rewinding = Rc::new(Cell::new(false));
+    let background = Rc::new(Cell::new(None));
+
+    // Setup audio. The output itself waits for the page to be interacted with, see `start_audio`.
+    let (tx, rx) = mpsc::channel();
+    let audio_controls = AudioControls::default();
+
+    // Emulation and audio run off a timer rather than egui's repaints, so they keep going
+    // when the canvas loses focus or the page puts an overlay over it
+    let _emulation_timer = start_emulation_timer(
+        Rc::clone(&nes),
+        Rc::clone(&keyboard_state),
+        Rc::clone(&speed),
+        Rc::clone(&frame_advance),
+        Rc::clone(&fast_forward),
+        Rc::clone(&rewinding),
+        Rc::clone(&background),
+        audio_controls.clone(),
+        tx,
+    );
+
+    let silknes = SilkNES {
+        show_about_window: false,
+        cheat_window: CheatWindow::default(),
+        game_profile_window: GameProfileWindow::default(),
+        ram_search: RamSearch::default(),
+        condition_window: ConditionWindow::default(),
+        practice: Practice::default(),
+        apu_timeline: ApuTimeline::default(),
+        apu_viewer: ApuViewer::default(),
+        event_viewer: EventViewer::default(),
+        nametable_viewer: NametableViewer::default(),
+        code_profile_window: CodeProfileWindow::default(),
+        hotkey_window: HotkeyWindow::default(),
+        input_window: InputWindow::default(),
+        rom_error_window: RomErrorWindow::default(),
+        toasts: Toasts::default(),
+        console: Console::new(),
+        config: Config::default(),
+        saved_config: Config::default(),
+        video_filters: VideoFilterChain::new(),
+        display: Display::default(),
+        nes,
+        keyboard_state,
+        speed,
+        frame_advance,
+        fast_forward,
+        rewinding,
+        background,
+        rewind: Rewind::default(),
+        audio_controls,
+        rom_hash: None,
+        cheat_library: CheatLibrary::default(),
+        game_profiles: GameProfiles::default(),
+        battery_saves: BatterySaves::default(),
+        save_slots: vec![None; SAVE_SLOTS],
+        reported_breakpoint: None,
+        _emulation_timer,
+        audio: AudioBackend::Waiting,
+        pending_audio: Some(rx),
+    };
+    wasm_bindgen_futures::spawn_local(async {
+        eframe::WebRunner::new()
+            .start(
+                "nesCanvas", // hardcode it
+                web_options,
+                Box::new(|cc| {
+                    let mut silknes = silknes;
+                    let config = Config::load(cc.storage);
+                    silknes.console.open = config.console_open;
+                    silknes.console.watches.clone_from(&config.watches);
+                    silknes.saved_config = config.clone();
+                    silknes.apply_config(&cc.egui_ctx, config);
+                    silknes.cheat_library = CheatLibrary::load(cc.storage);
+                    silknes.game_profiles = GameProfiles::load(cc.storage);
+                    silknes.battery_saves = BatterySaves::load(cc.storage);
+                    Box::new(silknes)
+                }),
+            )
+            .await
+            .expect("failed to start eframe");
+    });
+}
+
+/// Start a browser interval that runs however many cycles are due since it last fired,
+/// and feeds the audio they produced to the output stream. Cycles come due as the output plays
+/// the audio queued for it, or by the wall clock when it isn't playing or the sync mode says so.
+///
+/// Input is sampled on every tick rather than once a frame, so the game latches the
+/// most recent state whenever it strobes the controllers.
+#[cfg(target_arch = "wasm32")]
+fn start_emulation_timer(
+    nes: Rc<RefCell<Nes>>,
+    keyboard_state: Rc<Cell<PadState>>,
+    speed: Rc<Cell<f32>>,
+    frame_advance: Rc<RefCell<FrameAdvance>>,
+    fast_forward: Rc<Cell<bool>>,
+    rewinding: Rc<Cell<bool>>,
+    background: Rc<Cell<Option<BackgroundMode>>>,
+    audio_controls: AudioControls,
+    tx: mpsc::Sender<Vec<f32>>,
+) -> Closure<dyn FnMut()> {
+    let mut last_tick = web_time::Instant::now();
+    let mut pending_cycles = 0.0;
+    let mut audio_clock = AudioClock::new(audio_controls);
+    let tick = Closure::<dyn FnMut()>::new(move || {
+        let now = web_time::Instant::now();
+        let background = background.get();
+        let speed = background.map_or(1.0, BackgroundMode::speed) * speed.get();
+        let speed = if fast_forward.get() { speed * FAST_FORWARD_SPEED } else { speed };
+        pending_cycles += match audio_clock.samples_wanted() {
+            Some(samples) => {
+                audio_clock.queue(samples);
+                (samples as usize * nes::SAMPLES_PER_OUTPUT_SAMPLE) as f64 * speed as f64
+            },
+            None => now.duration_since(last_tick).as_secs_f64() * nes::CYCLES_PER_SECOND * speed as f64,
+        };
+        last_tick = now;
+
+        let mut nes = nes.borrow_mut();
+        if !nes.rom_loaded() || nes.breakpoint_hit().is_some() {
+            pending_cycles = 0.0;
+            return;
+        }
+
+        // Drop whatever we can't reasonably catch up on instead of carrying it forward
+        let max_cycles = (nes::CYCLES_PER_FRAME * MAX_CATCH_UP_FRAMES) as f64 * speed.max(1.0) as f64;
+        pending_cycles = pending_cycles.min(max_cycles);
+        // More than a frame due at once means the ticks are coming too slowly to keep up
+        nes.set_falling_behind(pending_cycles > nes::CYCLES_PER_FRAME as f64 * speed.max(1.0) as f64);
+
+        let keyboard = keyboard_state.get().buttons(nes.frame_count());
+        nes.update_controller(0, keyboard | *CONTROLLER_STATE.lock().unwrap());
+
+        let mut frame_advance = frame_advance.borrow_mut();
+        let background_paused = background == Some(BackgroundMode::Pause);
+        if frame_advance.paused || rewinding.get() || background_paused {
+            // Only whole frames are stepped through while paused, with silence standing in for the ones
+            // that don't run so the audio neither starves nor has a backlog once unpaused. Rewinding
+            // steps back from the UI instead, and nothing runs at all while paused in the background.
+            while pending_cycles >= nes::CYCLES_PER_FRAME as f64 {
+                pending_cycles -= nes::CYCLES_PER_FRAME as f64;
+                let audio = if !rewinding.get() && !background_paused && frame_advance.take_frame() {
+                    nes.run_frame();
+                    nes.take_stereo_audio()
+                } else {
+                    nes.paused_stereo_audio()
+                };
+                let _ = tx.send(audio);
+            }
+            return;
+        }
+
+        let cycles = pending_cycles as u32;
+        nes.run_cycles(cycles);
+        pending_cycles -= cycles as f64;
+
+        let mut audio = nes.take_stereo_audio();
+        // Throttled in the background, there's too little to play without stuttering
+        if background == Some(BackgroundMode::Throttle) {
+            audio.clear();
+        }
+        // Only play the latest part while fast forwarding, so it doesn't pile up
+        if fast_forward.get() {
+            let frames = audio.len() / nes::AUDIO_CHANNELS;
+            let keep = (frames as f32 / FAST_FORWARD_SPEED) as usize * nes::AUDIO_CHANNELS;
+            audio = audio.split_off(audio.len() - keep);
+        }
+        if !audio.is_empty() {
+            let _ = tx.send(audio);
+        }
+    });
 
-    // Create CPU
-    let cpu = Rc::new(RefCell::new(NES6502::new()));
+    web_sys::window()
+        .unwrap()
+        .set_interval_with_callback_and_timeout_and_arguments_0(tick.as_ref().unchecked_ref(), EMULATION_TICK_MS)
+        .expect("failed to start emulation timer");
+    tick
+}
 
-    let ppu = Rc::new(RefCell::new(PPU::new()));
+#[cfg(target_arch = "wasm32")]
+struct SilkNES {
+    show_about_window: bool,
+    cheat_window: CheatWindow,
+    game_profile_window: GameProfileWindow,
+    ram_search: RamSearch,
+    condition_window: ConditionWindow,
+    practice: Practice,
+    apu_timeline: ApuTimeline,
+    apu_viewer: ApuViewer,
+    event_viewer: EventViewer,
+    nametable_viewer: NametableViewer,
+    code_profile_window: CodeProfileWindow,
+    hotkey_window: HotkeyWindow,
+    input_window: InputWindow,
+    rom_error_window: RomErrorWindow,
+    toasts: Toasts,
+    console: Console,
+
+    config: Config,
+    /// The config as it was last saved, to save it again as soon as it changes
+    saved_config: Config,
+    video_filters: VideoFilterChain,
+    display: Display,
+
+    nes: Rc<RefCell<Nes>>,
+    /// Keys held on the keyboard, picked up by the emulation timer between frames
+    keyboard_state: Rc<Cell<PadState>>,
+    /// Emulation speed multiplier, shared with the emulation timer
+    speed: Rc<Cell<f32>>,
+    frame_advance: Rc<RefCell<FrameAdvance>>,
+    /// Whether fast forward and rewind are held, shared with the emulation timer
+    fast_forward: Rc<Cell<bool>>,
+    rewinding: Rc<Cell<bool>>,
+    /// What to do while the page doesn't have focus, `None` while it has it, shared with the emulation timer
+    background: Rc<Cell<Option<BackgroundMode>>>,
+    rewind: Rewind,
+    /// Volume, mute and latency, shared with the thread playing the audio
+    audio_controls: AudioControls,
+    save_slots: Vec<Option<SaveState>>,
+    /// SHA-256 of the loaded ROM, used to look up its cheats and profile
+    rom_hash: Option<String>,
+    cheat_library: CheatLibrary,
+    game_profiles: GameProfiles,
+    /// Games' battery backed RAM, kept in the browser's storage since there's no save file
+    battery_saves: BatterySaves,
+    /// The breakpoint last announced in the console, so it's only logged once
+    reported_breakpoint: Option<u16>,
+    #[cfg(target_arch = "wasm32")]
+    _emulation_timer: Closure<dyn FnMut()>,
+
+    audio: AudioBackend,
+    /// The emulation timer's audio, until the output's been started
+    pending_audio: Option<mpsc::Receiver<Vec<f32>>>,
+}
 
-    let apu = Rc::new(RefCell::new(APU::new()));
+#[cfg(target_arch = "wasm32")]
+impl SilkNES {
+    /// Start the audio output once the page has been clicked, tapped or typed into. Browsers keep an
+    /// audio context suspended until then, so starting it any earlier plays nothing. Until it's
+    /// started, the timer's audio is thrown away rather than left to pile up.
+    fn start_audio(&mut self, ctx: &egui::Context) {
+        let Some(rx) = self.pending_audio.take() else {
+            return;
+        };
+        let interacted = ctx.input(|i| i.events.iter().any(|event| matches!(event,
+            egui::Event::PointerButton { pressed: true, .. } | egui::Event::Key { pressed: true, .. } | egui::Event::Touch { .. })));
+        if !interacted {
+            while rx.try_recv().is_ok() {}
+            self.pending_audio = Some(rx);
+            return;
+        }
 
-    // Connect bus to CPU
-    {
-        let mut bus_ref = bus.borrow_mut();
-        let cpu_ref = Rc::clone(&cpu);
-        bus_ref.connect_cpu(Rc::clone(&cpu_ref));
+        self.audio = AudioBackend::start(rx, self.config.audio_driver, self.audio_controls.clone());
+        if matches!(self.audio, AudioBackend::Null) {
+            self.toasts.error(i18n::tr("audio.no_device"));
+        }
     }
 
-    // Connect CPU to bus
-    {
-        let mut cpu_ref = cpu.borrow_mut();
-        let bus_ref = Rc::clone(&bus);
-        cpu_ref.connect_to_bus(Rc::clone(&bus_ref));
+    fn apply_config(&mut self, ctx: &egui::Context, config: Config) {
+        i18n::set_language(config.language);
+        ctx.set_zoom_factor(config.ui_scale);
+
+        self.video_filters.clear();
+        if config.color_vision != ColorVision::Normal {
+            self.video_filters.push(Box::new(Daltonize::new(config.color_vision)));
+        }
+        self.nes.borrow_mut().set_palette(self.game_profile().apply(&config).active_palette());
+        self.nes.borrow_mut().set_ultrasonic_triangle(config.ultrasonic_triangle);
+        self.nes.borrow_mut().set_reduce_pulse_popping(config.reduce_pulse_popping);
+        self.nes.borrow_mut().set_mixer(config.mixer);
+        self.nes.borrow_mut().set_channel_pan(config.active_pan());
+        self.nes.borrow_mut().set_power_on_state(config.power_on);
+        self.nes.borrow_mut().set_frame_skip(config.frame_skip);
+        // rodio's the only driver in the browser, so there's never an output to restart
+        self.audio_controls.set_volume(config.volume);
+        self.audio_controls.set_latency_ms(config.audio_latency_ms);
+        self.audio_controls.set_sync_mode(config.sync_mode);
+
+        self.config = config;
     }
 
-    // Connect bus to PPU
-    {
-        let mut bus_ref = bus.borrow_mut();
-        let ppu_ref = Rc::clone(&ppu);
-        bus_ref.connect_ppu(Rc::clone(&ppu_ref));
+    /// The loaded game's profile, which is the defaults if there isn't a game
+    fn game_profile(&self) -> GameProfile {
+        self.rom_hash.as_ref().map(|hash| self.game_profiles.get(hash)).unwrap_or_default()
     }
 
-    // Connect PPU to bus
-    {
-        let mut ppu_ref = ppu.borrow_mut();
-        let bus_ref = Rc::clone(&bus);
-        ppu_ref.connect_to_bus(Rc::clone(&bus_ref));
+    /// The loaded game's cheats, unless its profile has them switched off
+    fn game_cheats(&self) -> Vec<Cheat> {
+        match &self.rom_hash {
+            Some(hash) if self.game_profile().cheats_enabled => self.cheat_library.cheats(hash),
+            _ => vec![],
+        }
     }
 
-    // Connect bus to APU
-    {
-        let mut bus_ref = bus.borrow_mut();
-        let apu_ref = Rc::clone(&apu);
-        bus_ref.connect_apu(Rc::clone(&apu_ref));
+    /// Put the loaded game's settings in place of the global ones they override
+    fn apply_game_profile(&mut self) {
+        let profile = self.game_profile();
+        let mut nes = self.nes.borrow_mut();
+        nes.set_palette(profile.apply(&self.config).active_palette());
+        nes.set_region(profile.region);
+        nes.set_cheats(self.game_cheats());
     }
 
-    // Connect APU to bus
-    {
-        let mut apu_ref = apu.borrow_mut();
-        let bus_ref = Rc::clone(&bus);
-        apu_ref.connect_to_bus(Rc::clone(&bus_ref));
+    /// Hold on to the running game's battery backed RAM, ready for the next time the app's saved
+    fn keep_battery_ram(&mut self) {
+        let ram = self.nes.borrow().battery_ram();
+        if let (Some(hash), Some(ram)) = (&self.rom_hash, ram) {
+            self.battery_saves.set(hash, &ram);
+        }
     }
 
-    // Setup audio
-    let (tx, rx) = mpsc::channel();
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let _sink = Sink::try_new(&stream_handle).unwrap();
-    let source = APUOutput::new(rx).amplify(0.25);
-    _sink.append(source);
+    /// Load a state handed over by the page through `import_state`
+    #[cfg(feature = "serde")]
+    fn import_state(&mut self, bytes: &[u8]) {
+        match SaveState::from_bytes(bytes) {
+            Ok(state) => {
+                self.nes.borrow_mut().load_state(&state);
+                self.rewind.clear();
+                self.notify("Imported state");
+            },
+            Err(error) => self.notify_error(format!("Couldn't import state: {}", error)),
+        }
+    }
 
-    let silknes = SilkNES {
-        bus,
-        cpu,
-        ppu,
-        apu,
-        cartridge: None,
-        rom_loaded: false,
-        tx,
-        _sink,
-        _stream,
-    };
-    wasm_bindgen_futures::spawn_local(async {
-        eframe::WebRunner::new()
-            .start(
-                "nesCanvas", // hardcode it
-                web_options,
-                Box::new(|cc| Box::new(silknes)),
-            )
-            .await
-            .expect("failed to start eframe");
-    });
-}
+    #[cfg(not(feature = "serde"))]
+    fn import_state(&mut self, _bytes: &[u8]) {
+        self.notify_error("Couldn't import state: this build can't read save state files");
+    }
 
-struct SilkNES {
-    bus: Rc<RefCell<Box<dyn BusLike>>>,
-    cpu: Rc<RefCell<NES6502>>,
-    ppu: Rc<RefCell<PPU>>,
-    apu: Rc<RefCell<APU>>,
-    cartridge: Option<Rc<RefCell<Cartridge>>>,
-    rom_loaded: bool,
+    fn notify(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        self.console.log(message.clone());
+        self.toasts.info(message);
+    }
 
-    tx: mpsc::Sender<Vec<f32>>,
-    _sink: Sink,
-    _stream: OutputStream,
+    fn notify_error(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        self.console.log(message.clone());
+        self.toasts.error(message);
+    }
+
+    fn run_command(&mut self, ctx: &egui::Context, command: Command) {
+        match command {
+            Command::LoadRom => {
+                // The browser file picker is async, so the ROM comes back through load_rom like any other
+                #[cfg(target_arch = "wasm32")]
+                wasm_bindgen_futures::spawn_local(async {
+                    let file = rfd::AsyncFileDialog::new()
+                        .add_filter(i18n::tr("dialog.roms"), &["nes", "fds"])
+                        .pick_file()
+                        .await;
+                    if let Some(file) = file {
+                        load_rom(file.read().await);
+                    }
+                });
+            },
+            Command::LoadTestPattern => {
+                load_rom(test_pattern::rom());
+                self.toasts.info(test_pattern::CONTROLS);
+            },
+            Command::PlayDemo(index) => {
+                load_rom(DEMOS[index].rom());
+                self.toasts.info(DEMOS[index].controls);
+            },
+            Command::SaveState(slot) => {
+                if self.nes.borrow().rom_loaded() {
+                    let nes = self.nes.borrow();
+                    self.save_slots[slot] = Some(nes.save_state());
+                    self.practice.saved(slot, nes.frame_count(), nes.screen());
+                    drop(nes);
+                    self.notify(format!("Saved state to slot {}", slot));
+                }
+            },
+            Command::LoadState(slot) => {
+                if let Some(state) = &self.save_slots[slot] {
+                    self.nes.borrow_mut().load_state(state);
+                    self.rewind.clear();
+                    self.notify(format!("Loaded state from slot {}", slot));
+                } else {
+                    self.notify_error(format!("Slot {} is empty", slot));
+                }
+            },
+            Command::SetLanguage(language) => {
+                let config = Config { language, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetColorVision(color_vision) => {
+                let config = Config { color_vision, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetUiScale(ui_scale) => {
+                let config = Config { ui_scale, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetVolume(volume) => {
+                let config = Config { volume, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetAudioLatency(audio_latency_ms) => {
+                let config = Config { audio_latency_ms, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetAudioDriver(audio_driver) => {
+                let config = Config { audio_driver, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetSyncMode(sync_mode) => {
+                let config = Config { sync_mode, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetUltrasonicTriangle(ultrasonic_triangle) => {
+                let config = Config { ultrasonic_triangle, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetReducePulsePopping(reduce_pulse_popping) => {
+                let config = Config { reduce_pulse_popping, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetMixer(mixer) => {
+                let config = Config { mixer, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetStereoMode(stereo) => {
+                let config = Config { stereo, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetChannelPan(channel, pan) => {
+                let mut channel_pan = self.config.channel_pan;
+                channel_pan.set_pan(channel, pan);
+                let config = Config { stereo: StereoMode::Custom, channel_pan, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetPowerOnState(power_on) => {
+                let config = Config { power_on, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetBackgroundMode(background) => {
+                let config = Config { background, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::ToggleChannelMute(channel) => {
+                let mut mix = self.nes.borrow().channel_mix();
+                mix.set_muted(channel, !mix.muted(channel));
+                self.nes.borrow_mut().set_channel_mix(mix);
+            },
+            Command::ToggleChannelSolo(channel) => {
+                let mut mix = self.nes.borrow().channel_mix();
+                mix.set_soloed(channel, !mix.soloed(channel));
+                self.nes.borrow_mut().set_channel_mix(mix);
+            },
+            Command::SetPalette(palette) => {
+                let config = Config { palette, custom_palette: None, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetHideOverscan(hide_overscan) => {
+                let config = Config { hide_overscan, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetFlickerBlend(flicker_blend) => {
+                let config = Config { flicker_blend, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::SetFrameSkip(frame_skip) => {
+                let config = Config { frame_skip, ..self.config.clone() };
+                self.apply_config(ctx, config);
+            },
+            Command::LoadPaletteFile => {
+                // Like ROMs, the file comes back asynchronously and is picked up on the next update
+                #[cfg(target_arch = "wasm32")]
+                wasm_bindgen_futures::spawn_local(async {
+                    let file = rfd::AsyncFileDialog::new()
+                        .add_filter(i18n::tr("dialog.palettes"), &["pal"])
+                        .pick_file()
+                        .await;
+                    if let Some(file) = file {
+                        *PALETTE_BYTES.lock().unwrap() = Some(file.read().await);
+                    }
+                });
+            },
+            Command::TogglePause => self.frame_advance.borrow_mut().toggle_pause(),
+            Command::FrameAdvance => self.frame_advance.borrow_mut().advance(),
+            Command::Reset => {
+                if self.nes.borrow().rom_loaded() {
+                    self.nes.borrow_mut().reset();
+                    self.notify("Reset");
+                }
+            },
+            Command::PowerCycle => {
+                if self.nes.borrow().rom_loaded() {
+                    self.nes.borrow_mut().power_on();
+                    self.notify("Power cycled");
+                }
+            },
+            Command::ShowCheats => self.cheat_window.open = true,
+            Command::ShowGameProperties => self.game_profile_window.open = true,
+            Command::ShowNetplay => self.console.log("Netplay isn't available in the browser"),
+            Command::ShowLibrary => self.console.log("The library isn't available in the browser"),
+            Command::ShowRamSearch => self.ram_search.open = true,
+            Command::ShowConditions => self.condition_window.open = true,
+            Command::ShowPractice => self.practice.open = true,
+            Command::ShowApuTimeline => self.apu_timeline.open = true,
+            Command::ShowApuViewer => self.apu_viewer.open = true,
+            Command::ShowEventViewer => self.event_viewer.open = true,
+            Command::ShowNametableViewer => self.nametable_viewer.open = true,
+            Command::ShowCodeProfiler => self.code_profile_window.open = true,
+            Command::ShowHotkeys => self.hotkey_window.open = true,
+            Command::ShowInput => self.input_window.open = true,
+            Command::ToggleFullscreen => {
+                let fullscreen = ctx.input(|i| i.viewport().fullscreen).unwrap_or(false);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+            },
+            Command::ToggleMute => {
+                let muted = !self.audio_controls.muted();
+                self.audio_controls.set_muted(muted);
+                self.notify(if muted { "Muted" } else { "Unmuted" });
+            },
+            Command::About => self.show_about_window = true,
+            Command::ToggleConsole => self.console.open = !self.console.open,
+            Command::Break(address) => {
+                self.nes.borrow_mut().add_breakpoint(address);
+                let address = self.nes.borrow().describe_address(address);
+                self.console.log(format!("Breakpoint set at {}", address));
+            },
+            Command::ClearBreakpoints => {
+                self.nes.borrow_mut().clear_breakpoints();
+                self.console.log("Cleared all breakpoints");
+            },
+            Command::Continue => self.nes.borrow_mut().resume(),
+            Command::RunTo(target) => {
+                let mut nes = self.nes.borrow_mut();
+                if nes.rom_loaded() {
+                    self.frame_advance.borrow_mut().paused = true;
+                    nes.resume();
+                    self.reported_breakpoint = None;
+                    let reached = nes.run_until(target);
+                    // A few cycles of sound would only come out as a click
+                    nes.take_audio();
+                    // Stopping at a breakpoint is logged along with the ones the timer runs into
+                    let message = match nes.describe_stop() {
+                        Some(_) => None,
+                        None if reached => Some(format!("Stopped at {}", nes.describe_position())),
+                        None => Some(format!("Didn't get there within two frames, stopped at {}", nes.describe_position())),
+                    };
+                    drop(nes);
+                    if let Some(message) = message {
+                        self.console.log(message);
+                    }
+                }
+            },
+            Command::AddWatchpoint(watchpoint) => {
+                self.nes.borrow_mut().add_watchpoint(watchpoint);
+                self.console.log(format!("Watchpoint set on {}", watchpoint));
+            },
+            Command::ClearWatchpoints => {
+                self.nes.borrow_mut().clear_watchpoints();
+                self.console.log("Cleared all watchpoints");
+            },
+            Command::Watch(address) => {
+                if !self.console.watches.contains(&address) {
+                    self.console.watches.push(address);
+                }
+            },
+            Command::Unwatch(address) => self.console.watches.retain(|watch| *watch != address),
+            Command::SetSpeed(speed) => {
+                self.speed.set(speed);
+                self.console.log(format!("Speed set to {}x", speed));
+            },
+            // There's no filesystem to write to or browser tab to quit from
+            Command::DumpNametable(..) => self.console.log("Dumping nametables isn't available in the browser"),
+            Command::Screenshot(..) | Command::SaveScreenshot => self.console.log("Saving screenshots isn't available in the browser"),
+            Command::Quit => self.console.log("There's nothing to quit to in the browser"),
+            Command::Help => {
+                for line in CONSOLE_HELP {
+                    self.console.log(*line);
+                }
+            },
+        }
+    }
 }
 
+#[cfg(target_arch = "wasm32")]
 impl eframe::App for SilkNES {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         egui_extras::install_image_loaders(ctx);
         ctx.request_repaint();
+        self.start_audio(ctx);
 
-        if !HAS_ROM.load(Ordering::Relaxed) {
-            if ROM_CHANGED.load(Ordering::Relaxed) {
-                ROM_CHANGED.store(false, Ordering::Relaxed);
-                HAS_ROM.store(true, Ordering::Relaxed);
-                let cartridge = Rc::new(RefCell::new(Cartridge::from_bytes(ROM_BYTES.lock().unwrap().to_owned())));
-                {
-                    let mut bus_ref = self.bus.borrow_mut();
-                    let cartridge_ref = Rc::clone(&cartridge);
-                    bus_ref.insert_cartridge(Rc::clone(&cartridge_ref));
-                }
-                self.cartridge = Some(cartridge);
-                self.cpu.borrow_mut().reset();
-                self.ppu.borrow_mut().reset();
-                self.rom_loaded = true;
-            } else {
-              return;
+        // Check for commands from hotkeys, the menubar and the console. Hotkeys come first so they
+        // work even while a menu is open, but not while a key's being bound.
+        if !self.hotkey_window.capturing() && !self.input_window.capturing() {
+            for command in self.config.hotkeys.pressed(ctx) {
+                self.run_command(ctx, command);
+            }
+            for command in self.practice.pressed(ctx) {
+                self.run_command(ctx, command);
             }
         }
-        if self.rom_loaded {
-            // Run the emulation
-            // It would be nice to just eventually step the bus itself,
-            // but the borrow checker is screwing me here so this is fine for now
-            for _ in 0..(341*262) {
-                // Grab some variables from the bus to use while stepping
-                let cycles = self.bus.borrow().get_global_cycles();
-                let dma_running = self.bus.borrow().dma_running();
-                let mut should_run_dma = false;
-
-                self.ppu.borrow_mut().step();
-                if cycles % 3 == 0 {
-                    if self.bus.borrow().dma_queued() && !dma_running {
-                        if cycles % 2 == 1 {
-                            should_run_dma = true;
-                        }
-                    } else if dma_running {
-                        if cycles % 2 == 0 {
-                            let dma_data = {
-                                let bus = self.bus.borrow();
-                                let dma_page = bus.dma_page() as u16;
-                                let dma_address = bus.dma_address() as u16;
-                                let dma_data = bus.cpu_read((dma_page << 8) | dma_address);
-                                dma_data
-                            };
-                            self.bus.borrow_mut().set_dma_data(dma_data);
-                        } else {
-                            let mut dma_address = self.bus.borrow().dma_address();
-                            let dma_data = self.bus.borrow().dma_data();
-                            let oam_index = (dma_address / 4) as usize;
-                            let mut ppu = self.ppu.borrow_mut();
-                            match dma_address % 4 {
-                                0 => ppu.oam[oam_index].y = dma_data,
-                                1 => ppu.oam[oam_index].id = dma_data,
-                                2 => ppu.oam[oam_index].attributes.set_from_u8(dma_data),
-                                3 => ppu.oam[oam_index].x = dma_data,
-                                _ => (),
-                            }
-                            dma_address = dma_address.wrapping_add(1);
-                            self.bus.borrow_mut().set_dma_address(dma_address);
-
-                            if dma_address == 0 {
-                                self.bus.borrow_mut().set_dma_running(false);
-                                self.bus.borrow_mut().set_dma_queued(false);
-                            }
-                        }
-                    } else {
-                        self.cpu.borrow_mut().step();
-                        self.apu.borrow_mut().step(self.cpu.borrow().total_cycles);
-                        if self.apu.borrow().registers.status.dmc_interrupt || self.apu.borrow().registers.status.frame_interrupt || self.cartridge.as_ref().unwrap().borrow().mapper.irq_state() {
-                            self.cpu.borrow_mut().irq();
-                        }
-                    }
-                }
-                let nmi = self.ppu.borrow().nmi;
-                if nmi {
-                    self.ppu.borrow_mut().nmi = false;
-                    self.cpu.borrow_mut().nmi();
-                }
-                self.bus.borrow_mut().set_global_cycles(cycles + 1);
-                if should_run_dma {
-                    self.bus.borrow_mut().set_dma_running(true);
-                }
-                // self.apu.borrow_mut().update_output();
+        let unimplemented = self.nes.borrow().unimplemented_mapper_features();
+        let mix = self.nes.borrow().channel_mix();
+        if let Some(command) = menubar::show(ctx, &self.config, &self.audio, self.audio_controls.muted(), mix, &unimplemented) {
+            self.run_command(ctx, command);
+        }
+        // Bound first so the console's borrow of the NES ends before the command runs
+        let command = self.console.show(ctx, &self.nes.borrow());
+        if let Some(command) = command {
+            self.run_command(ctx, command);
+        }
+
+        let palette_bytes = PALETTE_BYTES.lock().unwrap().take();
+        if let Some(bytes) = palette_bytes {
+            match Palette::from_pal_bytes(&bytes) {
+                Ok(palette) => {
+                    let config = Config { custom_palette: Some(palette), ..self.config.clone() };
+                    self.apply_config(ctx, config);
+                },
+                Err(error) => self.notify_error(format!("Couldn't load palette: {}", error)),
+            }
+        }
+
+        // The timer stops at breakpoints on its own, let the console know when it does
+        let breakpoint = self.nes.borrow().breakpoint_hit();
+        if breakpoint.is_some() && breakpoint != self.reported_breakpoint {
+            let message = self.nes.borrow().describe_stop().unwrap_or_default();
+            self.console.log(message);
+            self.console.open = true;
+        }
+        self.reported_breakpoint = breakpoint;
+
+        menubar::show_about_window(ctx, &mut self.show_about_window);
+
+        // Draw cheat window, if active, and hand any changes straight to the console
+        let cheats = self.rom_hash.as_ref().map(|hash| self.cheat_library.cheats_mut(hash));
+        if let Some(change) = self.cheat_window.show(ctx, cheats) {
+            self.nes.borrow_mut().set_cheats(self.game_cheats());
+            self.toasts.info(change);
+        }
+        let profile = self.rom_hash.as_ref().map(|hash| self.game_profiles.get_mut(hash));
+        if self.game_profile_window.show(ctx, profile, &self.config) {
+            self.apply_game_profile();
+        }
+        if self.ram_search.open {
+            self.ram_search.show(ctx, &mut self.nes.borrow_mut());
+        }
+        if self.condition_window.open {
+            self.condition_window.show(ctx, &mut self.nes.borrow_mut());
+        }
+        let practice_command = if self.practice.open { self.practice.show(ctx, &mut self.nes.borrow_mut()) } else { None };
+        let events = self.nes.borrow_mut().take_condition_events();
+        for message in self.condition_window.fired(&events) {
+            self.toasts.info(message);
+        }
+        if self.practice.died(&events) {
+            self.notify("Death detected");
+            self.run_command(ctx, Command::SaveState(self.practice.death_slot));
+        }
+        if let Some(command) = practice_command {
+            self.run_command(ctx, command);
+        }
+        if self.apu_timeline.open {
+            self.apu_timeline.show(ctx, &self.nes.borrow());
+        }
+        // Only record the channels' levels while there's a scope to draw them
+        self.nes.borrow_mut().set_scope_enabled(self.apu_viewer.open);
+        if self.apu_viewer.open {
+            self.apu_viewer.show(ctx, &self.nes.borrow());
+        }
+        // Likewise register writes, while there's somewhere to plot them
+        self.nes.borrow_mut().set_event_logging(self.event_viewer.open);
+        if self.event_viewer.open {
+            self.event_viewer.show(ctx, &self.nes.borrow());
+        }
+        // And the scroll at the start of each line, while there are nametables to draw it over
+        self.nes.borrow_mut().set_scroll_logging(self.nametable_viewer.open);
+        if self.nametable_viewer.open {
+            self.nametable_viewer.show(ctx, &mut self.nes.borrow_mut());
+        }
+        // Counting cycles per instruction slows emulation down, so only while they're being looked at
+        self.nes.borrow_mut().set_code_profiling(self.code_profile_window.open);
+        if self.code_profile_window.open {
+            self.code_profile_window.show(ctx, &mut self.nes.borrow_mut());
+        }
+        if let Some(hotkeys) = self.hotkey_window.show(ctx, &self.config.hotkeys) {
+            let config = Config { hotkeys, ..self.config.clone() };
+            self.apply_config(ctx, config);
+        }
+        // Games with their own controls have those changed instead of the global ones
+        let game_config = self.game_profile().apply(&self.config);
+        if let Some(input) = self.input_window.show(ctx, &game_config.input) {
+            match &self.rom_hash {
+                Some(hash) if self.game_profiles.get(hash).input.is_some() => {
+                    self.game_profiles.get_mut(hash).input = Some(input);
+                },
+                _ => {
+                    let config = Config { input, ..self.config.clone() };
+                    self.apply_config(ctx, config);
+                },
             }
+        }
+        self.toasts.show(ctx);
+
+        // As on desktop, the debug console's kept with the settings and nothing's saved mid drag
+        self.config.console_open = self.console.open;
+        self.config.watches.clone_from(&self.console.watches);
+        if !ctx.input(|input| input.pointer.any_down()) {
+            if let Some(storage) = frame.storage_mut() {
+                self.config.save_if_changed(&mut self.saved_config, storage);
+            }
+        }
 
-            // // Update audio
-            // let buffer = std::mem::take(&mut self.apu.borrow_mut().output_buffer);
-            // let averaged = buffer
-            //     .chunks(112)
-            //     .fold(Vec::new(), |mut acc, x| {
-            //         let sum: f32 = x.iter().sum();
-            //         acc.push(sum / x.len() as f32);
-            //         acc
-            //     });
-            // self.tx.send(averaged).unwrap();
+        if ROM_CHANGED.load(Ordering::Relaxed) {
+            ROM_CHANGED.store(false, Ordering::Relaxed);
+            let rom_bytes = ROM_BYTES.lock().unwrap().to_owned();
+            let rom_hash = sha256::digest(rom_bytes.as_slice());
+            // Carry on with whatever was running before if it can't be loaded
+            match Cartridge::from_bytes(rom_bytes) {
+                Ok(cartridge) => {
+                    HAS_ROM.store(true, Ordering::Relaxed);
+                    self.toasts.info(format!("Loaded ROM ({})", cartridge.mapper_description()));
+                    self.keep_battery_ram();
+                    self.nes.borrow_mut().insert_cartridge(cartridge);
+                    if let Some(ram) = self.battery_saves.get(&rom_hash) {
+                        self.nes.borrow_mut().load_battery_ram(ram);
+                    }
+                    self.rom_hash = Some(rom_hash);
+                    self.apply_game_profile();
+                    // States from the previous game can't be loaded into this one
+                    self.save_slots.fill(None);
+                    self.practice.clear();
+                    self.rewind.clear();
+                },
+                Err(error) => self.rom_error_window.open(error.to_string()),
+            }
+        }
+        self.rom_error_window.show(ctx);
+        if !HAS_ROM.load(Ordering::Relaxed) {
+            // Give visitors something to play before they've found a ROM of their own
+            if let Some(command) = menubar::show_welcome(ctx) {
+                self.run_command(ctx, command);
+            }
+            return;
+        }
+        let state_bytes = STATE_BYTES.lock().unwrap().take();
+        if let Some(bytes) = state_bytes {
+            self.import_state(&bytes);
         }
 
         // Render the display to a texture for egui
-        let display = self.ppu.borrow().get_screen();
-        let color_image = egui::ColorImage::from_rgb([256, 240], &display);
-        let handle = ctx.load_texture("Display", color_image, egui::TextureOptions::NEAREST);
+        let texture = {
+            let nes = self.nes.borrow();
+            let previous = game_config.flicker_blend.then(|| nes.previous_screen());
+            self.display.update(ctx, nes.screen(), previous, &self.video_filters)
+        };
 
         // Draw main window
         egui::CentralPanel::default().frame(egui::Frame::none()).show(ctx, |ui| {
-            let sized_image = egui::load::SizedTexture::new(handle.id(), egui::vec2(512.0, 480.0));
-            let image = egui::Image::from_texture(sized_image);
-            ui.add(image);
+            // Fit the display to whatever space is left, keeping its aspect ratio
+            let (uv, visible) = visible_area(game_config.hide_overscan);
+            let available = ui.available_size();
+            let scale = (available.x / visible.x).min(available.y / visible.y);
+            let sized_image = egui::load::SizedTexture::new(texture, visible * scale);
+            let image = egui::Image::from_texture(sized_image).uv(uv);
+            ui.vertical_centered(|ui| {
+                let response = ui.add(image);
+                self.toasts.place_over(response.rect);
+            });
         });
 
-        // Handle input
-        let mut controller_state = 0x00;
-        let outside_state = *CONTROLLER_STATE.lock().unwrap();
-
-        for (key, value) in [
-            (Key::ArrowRight, 0x01), // D-Pad Right
-            (Key::ArrowLeft, 0x02), // D-Pad Left
-            (Key::ArrowDown, 0x04), // D-Pad Down
-            (Key::ArrowUp, 0x08), // D-Pad Up
-            (Key::Enter, 0x10), // Start
-            (Key::Space, 0x20), // Select
-            (Key::Z, 0x40), // B
-            (Key::X, 0x80), // A
-        ] {
-            if ctx.input(|i| i.key_down(key) || outside_state & value == value) {
-                controller_state |= value;
-            }
+        // Handle input. The emulation timer presses and releases turbo buttons as the frames go by.
+        self.keyboard_state.set(game_config.input.read(ctx));
+        let hotkeys = &self.config.hotkeys;
+        let capturing = self.hotkey_window.capturing() || self.input_window.capturing();
+        let held = |action| !capturing && hotkeys.held(ctx, action);
+        let (advance_down, fast_forward, rewinding) = (held(HotkeyAction::FrameAdvance), held(HotkeyAction::FastForward), held(HotkeyAction::Rewind));
+        self.frame_advance.borrow_mut().update_hold(advance_down, ctx.input(|i| i.time));
+        self.fast_forward.set(fast_forward);
+        self.rewinding.set(rewinding);
+        if rewinding {
+            self.toasts.hold("rewind", "Rewinding…");
         }
-        self.bus.borrow_mut().update_controller(0, controller_state);
+        self.background.set((!ctx.input(|i| i.focused)).then_some(self.config.background));
+
+        // The timer runs the frames, so snapshots are taken and stepped back through from here
+        let mut nes = self.nes.borrow_mut();
+        if rewinding && nes.breakpoint_hit().is_none() {
+            self.rewind.step_back(&mut nes);
+        } else {
+            self.rewind.record(&nes);
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.config.save(storage);
+        self.cheat_library.save(storage);
+        self.game_profiles.save(storage);
+        self.keep_battery_ram();
+        self.battery_saves.save(storage);
     }
 }
 
@@ -286,3 +960,25 @@ pub fn load_rom(bytes: Vec<u8>) {
 pub fn set_controller_state(value: u8) {
   *CONTROLLER_STATE.lock().unwrap() = value;
 }
+
+/// The running game's state as bytes, for the page to keep wherever it likes and hand back to
+/// `import_state` later. Empty if nothing's running, or if the build doesn't have the `serde` feature
+/// to write states with.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn export_state() -> Vec<u8> {
+  #[cfg(feature = "serde")]
+  if let Some(nes) = NES.with(|weak| weak.borrow().upgrade()) {
+    let nes = nes.borrow();
+    if nes.rom_loaded() {
+      return nes.save_state().to_bytes().unwrap_or_default();
+    }
+  }
+  vec![]
+}
+
+/// Load a state from `export_state` once the next frame's drawn. It has to be from the game that's
+/// running now.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn import_state(bytes: Vec<u8>) {
+  *STATE_BYTES.lock().unwrap() = Some(bytes);
+}
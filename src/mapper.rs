@@ -1,10 +1,258 @@
 use crate::cartridge::MirroringMode;
+use crate::mappers::{
+  mapper0::Mapper0,
+  mapper1::Mapper1,
+  mapper2::Mapper2,
+  mapper3::Mapper3,
+  mapper4::Mapper4,
+  mapper7::Mapper7,
+  mapper9::Mapper9,
+  mapper10::Mapper10,
+  mapper11::Mapper11,
+  mapper34::Mapper34,
+  mapper66::Mapper66,
+  mapper75::Mapper75,
+  mapper76::Mapper76,
+  mapper80::Mapper80,
+  mapper85::Mapper85,
+  mapper87::Mapper87,
+  mapper89::Mapper89,
+  mapper140::Mapper140,
+  mapper152::Mapper152,
+  mapper185::Mapper185,
+};
 
-pub trait Mapper {
+use std::fmt;
+
+/// Where in PRG ROM a CPU address currently points
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrgLocation {
+  pub bank: u32,
+  pub offset: u32,
+}
+
+impl fmt::Display for PrgLocation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "PRG bank {} + ${:04X}", self.bank, self.offset)
+  }
+}
+
+/// Which part of rendering a pattern table fetch was for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FetchKind {
+  Background,
+  Sprite,
+}
+
+/// One pattern table read the PPU made while rendering
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PatternFetch {
+  pub address: u16,
+  pub kind: FetchKind,
+  /// Scanline and dot the read happened on, with -1 being the pre-render line
+  pub scanline: i16,
+  pub dot: u16,
+}
+
+/// PPU cycles A12 has to stay low for before it going high counts as a rising edge. MMC3 filters the
+/// line against the CPU clock, which comes to about this many PPU cycles, so the short dips between
+/// sprite fetches don't count and there's one edge a scanline.
+pub const A12_LOW_CYCLES: u16 = 8;
+
+/// What a board watching the PPU's address bus sees on one PPU cycle
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PpuBusSignal {
+  pub address: u16,
+  /// Whether A12 went high this cycle after being low for at least `A12_LOW_CYCLES`
+  pub a12_rise: bool,
+}
+
+/// Follows A12 on the PPU's address bus from one cycle to the next, to pick out the rising edges
+/// that count
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct A12Filter {
+  high: bool,
+  /// Cycles A12's been low for, up to the point it stops mattering
+  low_cycles: u16,
+}
+
+impl A12Filter {
+  /// Take the address on the bus for another cycle
+  pub fn clock(&mut self, address: u16) -> PpuBusSignal {
+    let high = address & 0x1000 != 0;
+    let a12_rise = high && !self.high && self.low_cycles >= A12_LOW_CYCLES;
+    self.low_cycles = if high { 0 } else { (self.low_cycles + 1).min(A12_LOW_CYCLES) };
+    self.high = high;
+    PpuBusSignal { address, a12_rise }
+  }
+}
+
+pub trait Mapper: MapperClone + Send {
   fn get_mapped_address_cpu(&self, address: u16) -> u32;
+  /// Size of the PRG ROM banks the mapper currently switches between
+  fn prg_bank_size(&self) -> u32;
   fn get_mapped_address_ppu(&self, address: u16) -> u32;
   fn mapped_cpu_write(&mut self, address: u16, value: u8);
   fn mirroring_mode(&self) -> MirroringMode;
-  fn scanline(&mut self);
   fn irq_state(&self) -> bool;
+  /// A copy of the mapper's registers, for save states
+  fn state(&self) -> MapperState;
+
+  /// Where in the cartridge's misc ROM a CPU read lands, for boards that map it into CPU space.
+  /// Reads that return `Some` skip PRG ROM and RAM entirely.
+  fn get_mapped_address_misc(&self, _address: u16) -> Option<u32> {
+    None
+  }
+
+  /// Where in CHR RAM a pattern table read or write lands, for boards with CHR RAM alongside CHR ROM,
+  /// e.g. TQROM. Anything that returns `None` is CHR ROM as usual.
+  fn get_mapped_address_chr_ram(&self, _address: u16) -> Option<u32> {
+    None
+  }
+
+  /// Where in PRG RAM a CPU address in $6000-$7FFF lands, or `None` where there's no RAM and reads
+  /// are open bus. Most boards have a single 8 KB page there.
+  fn get_mapped_address_prg_ram(&self, address: u16) -> Option<u32> {
+    Some((address & 0x1FFF) as u32)
+  }
+
+  /// Called once the mapper's been created with the cartridge's NES 2.0 submapper, for mappers
+  /// whose boards behave differently. Not called for iNES headers, which can't tell the boards apart.
+  fn set_submapper(&mut self, _submapper: u8) {}
+
+  /// Whether PRG RAM at $6000-$7FFF is switched on, for boards that can switch it off, e.g. MMC1 and
+  /// MMC3. Reads from it while it's off are open bus, and writes go nowhere.
+  fn prg_ram_enabled(&self) -> bool {
+    true
+  }
+
+  /// Whether the pattern tables read from CHR at all, for boards that can cut it off, e.g. mapper 185's
+  /// copy protection
+  fn chr_enabled(&self) -> bool {
+    true
+  }
+
+  /// Whether PRG RAM takes writes, for boards that can protect it while still letting it be read
+  fn prg_ram_writable(&self) -> bool {
+    true
+  }
+
+  /// Called every PPU cycle with what's on its address bus, for boards that watch it, e.g. MMC3
+  /// clocking its IRQ counter on A12's rising edges. Outside of rendering the bus holds v, so
+  /// $2006 and $2007 accesses show up here too.
+  fn ppu_bus(&mut self, _signal: PpuBusSignal) {}
+
+  /// Called after every pattern table read the PPU makes while rendering, in the order it makes them,
+  /// for boards that watch the PPU's address bus, e.g. MMC2's CHR latches. Sprite slots with nothing
+  /// in them still read tile $FF, like the real PPU does.
+  fn pattern_fetch(&mut self, _fetch: PatternFetch) {}
+
+  /// Called once per CPU cycle, for boards that count cycles themselves, e.g. for IRQs
+  fn cpu_clock(&mut self) {}
+
+  /// Run the board's own sound hardware on by `cpu_cycles` CPU cycles, for boards that have any
+  fn audio_clock(&mut self, _cpu_cycles: u32) {}
+
+  /// The current sample from the board's own sound hardware, mixed in with the APU's channels.
+  /// On the same scale as the APU's pulse and triangle/noise/DMC mix, and silent by default.
+  fn audio_output(&self) -> f32 {
+    0.0
+  }
+
+  /// Called before each CPU write the mapper sees, to name the feature it's asking for if that's one
+  /// the mapper ignores, e.g. MMC1's 512 KB PRG banking. Lets odd behaviour be traced back to the mapper.
+  fn unimplemented_write(&self, _address: u16, _value: u8) -> Option<&'static str> {
+    None
+  }
+
+  /// Which PRG ROM bank a CPU address currently reads from, and how far into it.
+  /// `None` for anything outside of $8000-$FFFF.
+  fn prg_location(&self, address: u16) -> Option<PrgLocation> {
+    if address < 0x8000 {
+      return None;
+    }
+    let mapped = self.get_mapped_address_cpu(address);
+    let bank_size = self.prg_bank_size();
+    Some(PrgLocation { bank: mapped / bank_size, offset: mapped % bank_size })
+  }
+}
+
+/// A sound chip on a cartridge board, for mappers to hand `audio_clock` and `audio_output` on to
+pub trait ExpansionAudio {
+  /// Run on by a single CPU cycle
+  fn clock(&mut self);
+  fn output(&self) -> f32;
+}
+
+/// Lets a boxed mapper be cloned along with its cartridge, e.g. for save states.
+/// Implemented for every mapper that derives Clone.
+pub trait MapperClone {
+  fn clone_box(&self) -> Box<dyn Mapper>;
+}
+
+impl<T: 'static + Mapper + Clone> MapperClone for T {
+  fn clone_box(&self) -> Box<dyn Mapper> {
+    Box::new(self.clone())
+  }
+}
+
+impl Clone for Box<dyn Mapper> {
+  fn clone(&self) -> Self {
+    self.clone_box()
+  }
+}
+
+/// Every mapper's state, as a concrete type so it can be serialized
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MapperState {
+  Mapper0(Mapper0),
+  Mapper1(Mapper1),
+  Mapper2(Mapper2),
+  Mapper3(Mapper3),
+  Mapper4(Mapper4),
+  Mapper7(Mapper7),
+  Mapper9(Mapper9),
+  Mapper10(Mapper10),
+  Mapper11(Mapper11),
+  Mapper34(Mapper34),
+  Mapper66(Mapper66),
+  Mapper75(Mapper75),
+  Mapper76(Mapper76),
+  Mapper80(Mapper80),
+  Mapper85(Box<Mapper85>),
+  Mapper87(Mapper87),
+  Mapper89(Mapper89),
+  Mapper140(Mapper140),
+  Mapper152(Mapper152),
+  Mapper185(Mapper185),
+}
+
+impl MapperState {
+  pub fn into_mapper(self) -> Box<dyn Mapper> {
+    match self {
+      MapperState::Mapper0(mapper) => Box::new(mapper),
+      MapperState::Mapper1(mapper) => Box::new(mapper),
+      MapperState::Mapper2(mapper) => Box::new(mapper),
+      MapperState::Mapper3(mapper) => Box::new(mapper),
+      MapperState::Mapper4(mapper) => Box::new(mapper),
+      MapperState::Mapper7(mapper) => Box::new(mapper),
+      MapperState::Mapper9(mapper) => Box::new(mapper),
+      MapperState::Mapper10(mapper) => Box::new(mapper),
+      MapperState::Mapper11(mapper) => Box::new(mapper),
+      MapperState::Mapper34(mapper) => Box::new(mapper),
+      MapperState::Mapper66(mapper) => Box::new(mapper),
+      MapperState::Mapper75(mapper) => Box::new(mapper),
+      MapperState::Mapper76(mapper) => Box::new(mapper),
+      MapperState::Mapper80(mapper) => Box::new(mapper),
+      MapperState::Mapper85(mapper) => mapper,
+      MapperState::Mapper87(mapper) => Box::new(mapper),
+      MapperState::Mapper89(mapper) => Box::new(mapper),
+      MapperState::Mapper140(mapper) => Box::new(mapper),
+      MapperState::Mapper152(mapper) => Box::new(mapper),
+      MapperState::Mapper185(mapper) => Box::new(mapper),
+    }
+  }
 }
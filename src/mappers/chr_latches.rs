@@ -0,0 +1,42 @@
+use crate::mapper::PatternFetch;
+
+/// The two latches MMC2 and MMC4 pick 4 KB CHR banks with. Each half of the pattern table has a bank
+/// for when its latch is set to $FD and one for $FE, and the latch flips whenever the PPU fetches one of
+/// those tiles, so games can switch banks partway down the screen just by where they put the tiles.
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChrLatches {
+  /// Banks for $0000-$0FFF and $1000-$1FFF, when the latch is on $FD and when it's on $FE
+  pub banks: [[u8; 2]; 2],
+  /// Whether each latch is on $FE rather than $FD
+  on_fe: [bool; 2],
+  /// MMC2's lower latch only flips on the first row of the tile's second plane, not all 8
+  exact_lower_trigger: bool,
+}
+
+impl ChrLatches {
+  pub fn new(exact_lower_trigger: bool) -> Self {
+    Self { exact_lower_trigger, ..Self::default() }
+  }
+
+  /// Flip a latch if `fetch` was from the second plane of tile $FD or $FE
+  pub fn fetch(&mut self, fetch: PatternFetch) {
+    let half = ((fetch.address >> 12) & 1) as usize;
+    let on_fe = match fetch.address & 0x0FF8 {
+      0x0FD8 => false,
+      0x0FE8 => true,
+      _ => return,
+    };
+    if half == 0 && self.exact_lower_trigger && fetch.address & 0x07 != 0 {
+      return;
+    }
+    self.on_fe[half] = on_fe;
+  }
+
+  /// Where a PPU read from the pattern tables lands in CHR ROM
+  pub fn get_mapped_address(&self, address: u16) -> u32 {
+    let half = ((address >> 12) & 1) as usize;
+    let bank = self.banks[half][self.on_fe[half] as usize];
+    bank as u32 * 0x1000 + (address & 0x0FFF) as u32
+  }
+}
@@ -1,16 +1,16 @@
 use crate::cartridge::MirroringMode;
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper0 {
   prg_rom_banks: u8,
-  chr_rom_banks: u8,
 }
 
 impl Mapper0 {
-  pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
     Self {
       prg_rom_banks,
-      chr_rom_banks,
     }
   }
 }
@@ -19,12 +19,16 @@ impl Mapper for Mapper0 {
   fn get_mapped_address_cpu(&self, address: u16) -> u32 {
     if address >= 0x8000 {
       let mask = if self.prg_rom_banks > 1 { 0x7FFF } else { 0x3FFF };
-      return (address & mask) as u32;
+      (address & mask) as u32
     } else {
-      0
+      (address & 0x1FFF) as u32
     }
   }
 
+  fn prg_bank_size(&self) -> u32 {
+    0x4000
+  }
+
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
     if address <= 0x1FFF {
       address as u32
@@ -33,15 +37,17 @@ impl Mapper for Mapper0 {
     }
   }
 
-  fn mapped_cpu_write(&mut self, address: u16, value: u8) {}
+  fn mapped_cpu_write(&mut self, _address: u16, _value: u8) {}
 
   fn mirroring_mode(&self) -> MirroringMode {
     MirroringMode::_Hardwired
   }
 
-  fn scanline(&mut self) {}
-
   fn irq_state(&self) -> bool {
     false
   }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper0(self.clone())
+  }
 }
\ No newline at end of file
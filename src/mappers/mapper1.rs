@@ -1,6 +1,7 @@
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState};
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MMC1Registers {
   shift_register: u16,
   control_register: u8,
@@ -23,17 +24,17 @@ impl Default for MMC1Registers {
   }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper1 {
   prg_rom_banks: u8,
-  chr_rom_banks: u8,
   registers: MMC1Registers,
 }
 
 impl Mapper1 {
-  pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
     Self {
       prg_rom_banks,
-      chr_rom_banks,
       registers: MMC1Registers::default(),
     }
   }
@@ -42,13 +43,12 @@ impl Mapper1 {
 impl Mapper for Mapper1 {
   fn get_mapped_address_cpu(&self, address: u16) -> u32 {
     match address {
-      0x6000..=0x7FFF => address as u32,
       0x8000..=0xFFFF => {
         let bank_mode = (self.registers.control_register & 0b1100) >> 2;
         match (address, bank_mode) {
           (0x8000..=0xBFFF, 0 | 1) | (0xC000..=0xFFFF, 0 | 1) => {
-            // switch 32 KB at $8000, ignoring low bit of bank number
-            ((self.registers.prg_bank & 0xE) as u32 * 0x8000) + (address & 0x7FFF) as u32
+            // switch 32 KB at $8000, ignoring low bit of the 16 KB bank number
+            ((self.registers.prg_bank & 0xE) as u32 * 0x4000) + (address & 0x7FFF) as u32
           },
           (0x8000..=0xBFFF, 2) => {
             // fix first bank at $8000 and switch 16 KB bank at $C000
@@ -69,19 +69,30 @@ impl Mapper for Mapper1 {
     }
   }
 
+  fn prg_bank_size(&self) -> u32 {
+    // Modes 0 and 1 switch all 32 KB at once
+    if (self.registers.control_register & 0b1100) >> 2 < 2 { 0x8000 } else { 0x4000 }
+  }
+
+  // Kept at the top of the 32 KB, where battery saves have always had it
+  fn get_mapped_address_prg_ram(&self, address: u16) -> Option<u32> {
+    Some(address as u32)
+  }
+
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
     let is_8k_mode = self.registers.control_register & 0b10000 == 0;
     match address {
       0x0000..=0x0FFF => {
+        // The bank registers count in 4 KB, even when switching 8 KB at a time
         if is_8k_mode {
-          ((self.registers.chr_bank_0 & 0b11110) as u32 * 0x2000) + (address & 0x1FFF) as u32
+          ((self.registers.chr_bank_0 & 0b11110) as u32 * 0x1000) + (address & 0x1FFF) as u32
         } else {
           (self.registers.chr_bank_0 as u32 * 0x1000) + (address & 0x0FFF) as u32
         }
       },
       0x1000..=0x1FFF => {
         if is_8k_mode {
-          ((self.registers.chr_bank_0 & 0b11110) as u32 * 0x2000) + (address & 0x1FFF) as u32
+          ((self.registers.chr_bank_0 & 0b11110) as u32 * 0x1000) + (address & 0x1FFF) as u32
         } else {
           (self.registers.chr_bank_1 as u32 * 0x1000) + (address & 0x0FFF) as u32
         }
@@ -91,6 +102,9 @@ impl Mapper for Mapper1 {
   }
 
   fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    if address < 0x8000 {
+      return;
+    }
     let shift_bit = value as u16 & 0x1;
     if value & 0x80 != 0 {
       self.registers.shift_register = 0;
@@ -124,6 +138,21 @@ impl Mapper for Mapper1 {
     }
   }
 
+  fn unimplemented_write(&self, address: u16, value: u8) -> Option<&'static str> {
+    // Only the write that completes a register matters
+    if address < 0x8000 || value & 0x80 != 0 || self.registers.shift_register_writes != 4 {
+      return None;
+    }
+    // SUROM and SXROM reach past 256 KB of PRG with bit 4 of the CHR registers
+    let chr_register = matches!((address >> 13) & 0x03, 1 | 2);
+    (chr_register && self.prg_rom_banks > 16).then_some("MMC1 512 KB PRG banking (SUROM/SXROM)")
+  }
+
+  fn prg_ram_enabled(&self) -> bool {
+    // Bit 4 of the PRG bank register switches PRG RAM off on MMC1B and later
+    self.registers.prg_bank & 0x10 == 0
+  }
+
   fn mirroring_mode(&self) -> crate::cartridge::MirroringMode {
       match self.registers.control_register & 0b00011 {
         0 => crate::cartridge::MirroringMode::SingleScreenLow,
@@ -134,9 +163,11 @@ impl Mapper for Mapper1 {
       }
   }
 
-  fn scanline(&mut self) {}
-
   fn irq_state(&self) -> bool {
     false
   }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper1(self.clone())
+  }
 }
\ No newline at end of file
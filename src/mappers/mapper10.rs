@@ -0,0 +1,91 @@
+use crate::cartridge::MirroringMode;
+use crate::mapper::{Mapper, MapperState, PatternFetch};
+use crate::mappers::chr_latches::ChrLatches;
+
+/// MMC4, MMC2's CHR latches with 16 KB PRG banks and 8 KB of PRG RAM
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper10 {
+  prg_rom_banks: u8,
+  prg_rom_bank: u8,
+  chr_latches: ChrLatches,
+  mirroring: bool,
+}
+
+impl Mapper10 {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
+    Self {
+      prg_rom_banks,
+      prg_rom_bank: 0,
+      // Either latch flips on any row of the trigger tiles
+      chr_latches: ChrLatches::new(false),
+      mirroring: false,
+    }
+  }
+}
+
+impl Mapper for Mapper10 {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    match address {
+      0x8000..=0xBFFF => {
+        ((self.prg_rom_bank as u32) * 0x4000) + (address & 0x3FFF) as u32
+      },
+      0xC000..=0xFFFF => {
+        (self.prg_rom_banks as u32 - 1) * 0x4000 + (address & 0x3FFF) as u32
+      },
+      _ => 0,
+    }
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x4000
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    self.chr_latches.get_mapped_address(address)
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    match address {
+      0xA000..=0xAFFF => {
+        self.prg_rom_bank = value & 0xF;
+      },
+      0xB000..=0xBFFF => {
+        self.chr_latches.banks[0][0] = value & 0x1F;
+      },
+      0xC000..=0xCFFF => {
+        self.chr_latches.banks[0][1] = value & 0x1F;
+      },
+      0xD000..=0xDFFF => {
+        self.chr_latches.banks[1][0] = value & 0x1F;
+      },
+      0xE000..=0xEFFF => {
+        self.chr_latches.banks[1][1] = value & 0x1F;
+      },
+      0xF000..=0xFFFF => {
+        self.mirroring = value & 1 == 1;
+      },
+      _ => {},
+    }
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    if self.mirroring {
+      MirroringMode::Horizontal
+    } else {
+      MirroringMode::Vertical
+    }
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper10(self.clone())
+  }
+
+  fn pattern_fetch(&mut self, fetch: PatternFetch) {
+    self.chr_latches.fetch(fetch);
+  }
+}
@@ -1,17 +1,15 @@
 use crate::cartridge::MirroringMode;
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper11 {
-  prg_rom_banks: u8,
-  chr_rom_banks: u8,
   bank_select: u8,
 }
 
 impl Mapper11 {
-  pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+  pub fn new(_prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
     Self {
-      prg_rom_banks,
-      chr_rom_banks,
       bank_select: 0,
     }
   }
@@ -28,6 +26,10 @@ impl Mapper for Mapper11 {
     }
   }
 
+  fn prg_bank_size(&self) -> u32 {
+    0x8000
+  }
+
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
     if address <= 0x1FFF {
       (((self.bank_select as u32 >> 4) & 0xF) * 0x2000) + address as u32
@@ -47,9 +49,11 @@ impl Mapper for Mapper11 {
     MirroringMode::_Hardwired
   }
 
-  fn scanline(&mut self) {}
-
   fn irq_state(&self) -> bool {
     false
   }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper11(self.clone())
+  }
 }
\ No newline at end of file
@@ -1,17 +1,15 @@
 use crate::cartridge::MirroringMode;
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper140 {
-  prg_rom_banks: u8,
-  chr_rom_banks: u8,
   bank_select: u8,
 }
 
 impl Mapper140 {
-  pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+  pub fn new(_prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
     Self {
-      prg_rom_banks,
-      chr_rom_banks,
       bank_select: 0,
     }
   }
@@ -27,6 +25,10 @@ impl Mapper for Mapper140 {
     }
   }
 
+  fn prg_bank_size(&self) -> u32 {
+    0x8000
+  }
+
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
     if address <= 0x1FFF {
       ((self.bank_select as u32 >> 4) * 0x2000) + address as u32
@@ -36,8 +38,7 @@ impl Mapper for Mapper140 {
   }
 
   fn mapped_cpu_write(&mut self, address: u16, value: u8) {
-    if address >= 0x6000 && address <= 0x7FFF {
-      println!("Bank select: {:#08b}", value);
+    if (0x6000..=0x7FFF).contains(&address) {
       self.bank_select = value;
     }
   }
@@ -46,9 +47,11 @@ impl Mapper for Mapper140 {
     MirroringMode::_Hardwired
   }
 
-  fn scanline(&mut self) {}
-
   fn irq_state(&self) -> bool {
     false
   }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper140(self.clone())
+  }
 }
\ No newline at end of file
@@ -0,0 +1,74 @@
+use crate::cartridge::MirroringMode;
+use crate::mapper::{Mapper, MapperState};
+
+/// CNROM with copy protection instead of CHR banking. The one 8 KB CHR bank is only enabled for the
+/// right value written to $8000-$FFFF, and games check that the pattern tables read back as garbage
+/// for a wrong one.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper185 {
+  prg_rom_banks: u8,
+  /// NES 2.0 submappers 4-7 say which of the low two bits' values enables CHR. Without one, we guess
+  /// the way most emulators do.
+  submapper: u8,
+  chr_enabled: bool,
+}
+
+impl Mapper185 {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
+    Self {
+      prg_rom_banks,
+      submapper: 0,
+      chr_enabled: true,
+    }
+  }
+}
+
+impl Mapper for Mapper185 {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    if address >= 0x8000 {
+      let mask = if self.prg_rom_banks > 1 { 0x7FFF } else { 0x3FFF };
+      (address & mask) as u32
+    } else {
+      0
+    }
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x4000
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    (address & 0x1FFF) as u32
+  }
+
+  fn set_submapper(&mut self, submapper: u8) {
+    self.submapper = submapper;
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    if address >= 0x8000 {
+      self.chr_enabled = match self.submapper {
+        4..=7 => value & 0x03 == self.submapper - 4,
+        // The games disable CHR either with the low two bits clear or by writing $13
+        _ => value & 0x03 != 0 && value != 0x13,
+      };
+    }
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    MirroringMode::_Hardwired
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn chr_enabled(&self) -> bool {
+    self.chr_enabled
+  }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper185(self.clone())
+  }
+}
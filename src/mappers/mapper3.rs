@@ -1,17 +1,17 @@
 use crate::cartridge::MirroringMode;
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper3 {
   prg_rom_banks: u8,
-  chr_rom_banks: u8,
   bank_select: u8,
 }
 
 impl Mapper3 {
-  pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
     Self {
       prg_rom_banks,
-      chr_rom_banks,
       bank_select: 0,
     }
   }
@@ -21,12 +21,16 @@ impl Mapper for Mapper3 {
   fn get_mapped_address_cpu(&self, address: u16) -> u32 {
     if address >= 0x8000 {
       let mask = if self.prg_rom_banks > 1 { 0x7FFF } else { 0x3FFF };
-      return (address & mask) as u32;
+      (address & mask) as u32
     } else {
       0
     }
   }
 
+  fn prg_bank_size(&self) -> u32 {
+    0x4000
+  }
+
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
     if address <= 0x1FFF {
       (0x2000 * self.bank_select as u32) + address as u32
@@ -45,9 +49,11 @@ impl Mapper for Mapper3 {
     MirroringMode::_Hardwired
   }
 
-  fn scanline(&mut self) {}
-
   fn irq_state(&self) -> bool {
     false
   }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper3(self.clone())
+  }
 }
\ No newline at end of file
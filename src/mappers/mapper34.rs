@@ -0,0 +1,77 @@
+use crate::cartridge::MirroringMode;
+use crate::mapper::{Mapper, MapperState};
+
+/// Two unrelated boards share mapper 34, told apart by whether there's CHR ROM:
+/// BNROM switches a 32 KB PRG bank and uses CHR RAM, while NINA-001 adds two 4 KB CHR banks
+/// and keeps its registers at the top of PRG RAM
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper34 {
+  prg_rom_banks: u8,
+  chr_rom_banks: u8,
+  prg_rom_bank: u8,
+  /// NINA-001's CHR banks for $0000-$0FFF and $1000-$1FFF
+  chr_banks: [u8; 2],
+}
+
+impl Mapper34 {
+  pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+    Self {
+      prg_rom_banks,
+      chr_rom_banks,
+      prg_rom_bank: 0,
+      chr_banks: [0, 1],
+    }
+  }
+
+  fn is_nina(&self) -> bool {
+    self.chr_rom_banks > 0
+  }
+}
+
+impl Mapper for Mapper34 {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    match address {
+      0x8000..=0xFFFF => {
+        let banks = (self.prg_rom_banks as u32 / 2).max(1);
+        (self.prg_rom_bank as u32 % banks) * 0x8000 + (address & 0x7FFF) as u32
+      },
+      _ => 0,
+    }
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x8000
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    if self.is_nina() {
+      let bank = self.chr_banks[((address >> 12) & 1) as usize] as u32;
+      bank * 0x1000 + (address & 0x0FFF) as u32
+    } else {
+      (address & 0x1FFF) as u32
+    }
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    match (address, self.is_nina()) {
+      (0x7FFD, true) => self.prg_rom_bank = value & 0x01,
+      (0x7FFE, true) => self.chr_banks[0] = value & 0x0F,
+      (0x7FFF, true) => self.chr_banks[1] = value & 0x0F,
+      (0x8000..=0xFFFF, false) => self.prg_rom_bank = value,
+      _ => {},
+    }
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    MirroringMode::_Hardwired
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper34(self.clone())
+  }
+}
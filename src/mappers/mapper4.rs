@@ -1,7 +1,8 @@
 use crate::cartridge::MirroringMode;
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState, PpuBusSignal};
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MMC3Registers {
   /// 2 KB CHR bank at PPU $0000-$07FF (or $1000-$17FF)
   r0: u8,
@@ -25,31 +26,110 @@ pub struct MMC3Registers {
   irq_enabled: bool,
   irq_active: bool,
   irq_counter: u8,
+  /// Set by $C001, so the counter reloads from the latch the next time it's clocked
+  irq_reload: bool,
+  /// $A001 bit 7 clear, which disconnects PRG RAM
+  prg_ram_disabled: bool,
+  /// $A001 bit 6, which lets PRG RAM be read but not written
+  prg_ram_write_protected: bool,
 }
 
+/// The boards an MMC3 is wired up differently on, each with its own mapper number
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mmc3Board {
+  /// Mapper 4, the usual TxROM boards
+  #[default]
+  TxROM,
+  /// Mapper 118, TKSROM and TLSROM, where bit 7 of each CHR bank picks the page of nametable RAM
+  /// for one nametable, in place of the mirroring register
+  TxSROM,
+  /// Mapper 119, TQROM, with 8 KB of CHR RAM alongside CHR ROM. CHR banks with bit 6 set are RAM.
+  TQROM,
+}
+
+/// When the IRQ counter reaching 0 fires an IRQ, which depends on the MMC3's revision
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IrqBehaviour {
+  /// MMC3B and MMC3C, which fire whenever the counter's 0 after it's clocked, so a latch of 0 fires
+  /// on every scanline
+  #[default]
+  Normal,
+  /// MMC3A and Acclaim's MC-ACC, which only fire when the counter's counted down to 0 or was just
+  /// reloaded by a write to $C001, so a latch of 0 fires once
+  Alternate,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper4 {
   prg_rom_banks: u8,
-  chr_rom_banks: u8,
+  board: Mmc3Board,
+  irq_behaviour: IrqBehaviour,
+  /// Whether $A001 can switch PRG RAM off and write protect it. MMC6 boards, e.g. StarTropics, use
+  /// $A001 differently and share the mapper number, so it's only on when an NES 2.0 header says MMC3.
+  prg_ram_protect: bool,
   registers: MMC3Registers,
 }
 
 impl Mapper4 {
   pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+    Self::with_board(prg_rom_banks, chr_rom_banks, Mmc3Board::TxROM)
+  }
+
+  pub fn with_board(prg_rom_banks: u8, _chr_rom_banks: u8, board: Mmc3Board) -> Self {
     Self {
       prg_rom_banks,
-      chr_rom_banks,
+      board,
+      irq_behaviour: IrqBehaviour::default(),
+      prg_ram_protect: false,
       registers: MMC3Registers::default(),
     }
   }
+
+  /// The 1 KB CHR bank a pattern table address reads from. R0 and R1 switch 2 KB at a time, so their
+  /// low bit picks nothing.
+  fn chr_bank(&self, address: u16) -> u8 {
+    let chr_rom_bank_mode = (self.registers.bank_select & 0b1000_0000) >> 7;
+    match (address, chr_rom_bank_mode) {
+      (0x0000..=0x03FF, 0) | (0x1000..=0x13FF, 1) => self.registers.r0 & 0xFE,
+      (0x0400..=0x07FF, 0) | (0x1400..=0x17FF, 1) => self.registers.r0 | 0x01,
+      (0x0800..=0x0BFF, 0) | (0x1800..=0x1BFF, 1) => self.registers.r1 & 0xFE,
+      (0x0C00..=0x0FFF, 0) | (0x1C00..=0x1FFF, 1) => self.registers.r1 | 0x01,
+      (0x0000..=0x03FF, 1) | (0x1000..=0x13FF, 0) => self.registers.r2,
+      (0x0400..=0x07FF, 1) | (0x1400..=0x17FF, 0) => self.registers.r3,
+      (0x0800..=0x0BFF, 1) | (0x1800..=0x1BFF, 0) => self.registers.r4,
+      (0x0C00..=0x0FFF, 1) | (0x1C00..=0x1FFF, 0) => self.registers.r5,
+      _ => 0,
+    }
+  }
+
+  /// Clock the IRQ counter, as A12 rising does
+  fn clock_irq_counter(&mut self) {
+    let counted_down = self.registers.irq_counter != 0 && !self.registers.irq_reload;
+    let reloaded_by_write = self.registers.irq_reload;
+    if self.registers.irq_counter == 0 || self.registers.irq_reload {
+      self.registers.irq_counter = self.registers.irq_latch;
+      self.registers.irq_reload = false;
+    } else {
+      self.registers.irq_counter -= 1;
+    }
+
+    let fires = match self.irq_behaviour {
+      IrqBehaviour::Normal => true,
+      IrqBehaviour::Alternate => counted_down || reloaded_by_write,
+    };
+    if self.registers.irq_counter == 0 && self.registers.irq_enabled && fires {
+      self.registers.irq_active = true;
+    }
+  }
 }
 
 impl Mapper for Mapper4 {
   fn get_mapped_address_cpu(&self, address: u16) -> u32 {
     let prg_rom_bank_mode = (self.registers.bank_select & 0b0100_0000) >> 6;
     match (address, prg_rom_bank_mode) {
-      (0x6000..=0x7FFF, _) => {
-        address as u32
-      },
       (0x8000..=0x9FFF, 0) => {
         (self.registers.r6 as u32 * 0x2000) + (address & 0x1FFF) as u32
       },
@@ -72,39 +152,40 @@ impl Mapper for Mapper4 {
     }
   }
 
+  fn prg_bank_size(&self) -> u32 {
+    0x2000
+  }
+
+  // Kept at the top of the 32 KB, where battery saves have always had it
+  fn get_mapped_address_prg_ram(&self, address: u16) -> Option<u32> {
+    Some(address as u32)
+  }
+
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
-    let chr_rom_bank_mode = (self.registers.bank_select & 0b1000_0000) >> 7;
-    match (address, chr_rom_bank_mode) {
-      (0x0000..=0x03FF, 0) | (0x1000..=0x13FF, 1) => {
-        (self.registers.r0 as u32 * 0x400) + (address & 0x3FF) as u32
-      },
-      (0x0400..=0x07FF, 0) | (0x1400..=0x17FF, 1) => {
-        (self.registers.r0 as u32 * 0x400) + 0x400 + (address & 0x3FF) as u32
-      },
-      (0x0800..=0x0BFF, 0) | (0x1800..=0x1BFF, 1) => {
-        (self.registers.r1 as u32 * 0x400) + (address & 0x3FF) as u32
-      },
-      (0x0C00..=0x0FFF, 0) | (0x1C00..=0x1FFF, 1) => {
-        (self.registers.r1 as u32 * 0x400) + 0x400 + (address & 0x3FF) as u32
-      },
-      (0x0000..=0x03FF, 1) | (0x1000..=0x13FF, 0) => {
-        (self.registers.r2 as u32 * 0x400) + (address & 0x3FF) as u32
-      },
-      (0x0400..=0x07FF, 1) | (0x1400..=0x17FF, 0) => {
-        (self.registers.r3 as u32 * 0x400) + (address & 0x3FF) as u32
-      },
-      (0x0800..=0x0BFF, 1) | (0x1800..=0x1BFF, 0) => {
-        (self.registers.r4 as u32 * 0x400) + (address & 0x3FF) as u32
-      },
-      (0x0C00..=0x0FFF, 1) | (0x1C00..=0x1FFF, 0) => {
-        (self.registers.r5 as u32 * 0x400) + (address & 0x3FF) as u32
-      },
-      _ => 0,
+    let bank = match self.board {
+      // TQROM only has 64 KB of CHR ROM, with bit 6 choosing RAM instead
+      Mmc3Board::TQROM => self.chr_bank(address) & 0x3F,
+      _ => self.chr_bank(address),
+    };
+    (bank as u32 * 0x400) + (address & 0x3FF) as u32
+  }
+
+  fn get_mapped_address_chr_ram(&self, address: u16) -> Option<u32> {
+    let bank = self.chr_bank(address);
+    (self.board == Mmc3Board::TQROM && bank & 0x40 != 0).then(|| ((bank & 0x07) as u32 * 0x400) + (address & 0x3FF) as u32)
+  }
+
+  fn set_submapper(&mut self, submapper: u8) {
+    // 3 is MC-ACC and 4 is MMC3A
+    if matches!(submapper, 3 | 4) {
+      self.irq_behaviour = IrqBehaviour::Alternate;
     }
+    // 1 is MMC6
+    self.prg_ram_protect = submapper != 1;
   }
 
   fn mapped_cpu_write(&mut self, address: u16, value: u8) {
-    let even = address % 2 == 0;
+    let even = address.is_multiple_of(2);
     match (address, even) {
       (0x8000..=0x9FFF, true) => {
         self.registers.bank_select = value;
@@ -126,14 +207,16 @@ impl Mapper for Mapper4 {
       (0xA000..=0xBFFF, true) => {
         self.registers.mirroring_mode = value & 0b1 == 1;
       }
-      (0xA000..=0xBFFF, false) => {
-        // TODO: PRG RAM PROTECT
+      (0xA000..=0xBFFF, false) if self.prg_ram_protect => {
+        self.registers.prg_ram_disabled = value & 0x80 == 0;
+        self.registers.prg_ram_write_protected = value & 0x40 != 0;
       }
       (0xC000..=0xDFFF, true) => {
         self.registers.irq_latch = value;
       }
       (0xC000..=0xDFFF, false) => {
-        self.registers.irq_counter = self.registers.irq_latch;
+        self.registers.irq_counter = 0;
+        self.registers.irq_reload = true;
       }
       (0xE000..=0xFFFF, true) => {
         self.registers.irq_enabled = false;
@@ -146,7 +229,18 @@ impl Mapper for Mapper4 {
     }
   }
 
+  fn prg_ram_enabled(&self) -> bool {
+    !self.registers.prg_ram_disabled
+  }
+
+  fn prg_ram_writable(&self) -> bool {
+    !self.registers.prg_ram_write_protected
+  }
+
   fn mirroring_mode(&self) -> MirroringMode {
+    if self.board == Mmc3Board::TxSROM {
+      return MirroringMode::Pages([0x0000, 0x0400, 0x0800, 0x0C00].map(|address| self.chr_bank(address) >> 7));
+    }
     if self.registers.mirroring_mode {
       MirroringMode::Horizontal
     } else {
@@ -154,19 +248,19 @@ impl Mapper for Mapper4 {
     }
   }
 
-  fn scanline(&mut self) {
-    if self.registers.irq_counter == 0 {
-      self.registers.irq_counter = self.registers.irq_latch;
-    } else {
-      self.registers.irq_counter -= 1;
-    }
-
-    if self.registers.irq_counter == 0 && self.registers.irq_enabled {
-      self.registers.irq_active = true;
+  /// The IRQ counter's clocked by A12 rising, which happens once a scanline while rendering as long
+  /// as the background and sprites use different pattern tables
+  fn ppu_bus(&mut self, signal: PpuBusSignal) {
+    if signal.a12_rise {
+      self.clock_irq_counter();
     }
   }
 
   fn irq_state(&self) -> bool {
     self.registers.irq_active
   }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper4(self.clone())
+  }
 }
\ No newline at end of file
@@ -0,0 +1,59 @@
+use crate::cartridge::MirroringMode;
+use crate::mapper::{Mapper, MapperState};
+
+/// GxROM, a 32 KB PRG bank and an 8 KB CHR bank picked by one register
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper66 {
+  prg_rom_banks: u8,
+  /// PRG bank in bits 4-5, CHR bank in bits 0-1
+  bank_select: u8,
+}
+
+impl Mapper66 {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
+    Self {
+      prg_rom_banks,
+      bank_select: 0,
+    }
+  }
+}
+
+impl Mapper for Mapper66 {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    match address {
+      0x8000..=0xFFFF => {
+        // Carts with a single 16 KB bank mirror it into both halves
+        let prg_size = (self.prg_rom_banks as u32 * 0x4000).max(0x4000);
+        (((self.bank_select as u32 >> 4) & 0x03) * 0x8000 + (address & 0x7FFF) as u32) % prg_size
+      },
+      _ => 0,
+    }
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x8000
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    (self.bank_select as u32 & 0x03) * 0x2000 + (address & 0x1FFF) as u32
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    if address >= 0x8000 {
+      self.bank_select = value & 0x33;
+    }
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    MirroringMode::_Hardwired
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper66(self.clone())
+  }
+}
@@ -1,17 +1,15 @@
 use crate::cartridge::MirroringMode;
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper7 {
-  prg_rom_banks: u8,
-  chr_rom_banks: u8,
   bank_select: u8,
 }
 
 impl Mapper7 {
-  pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+  pub fn new(_prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
     Self {
-      prg_rom_banks,
-      chr_rom_banks,
       bank_select: 0,
     }
   }
@@ -27,6 +25,10 @@ impl Mapper for Mapper7 {
     }
   }
 
+  fn prg_bank_size(&self) -> u32 {
+    0x8000
+  }
+
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
     if address <= 0x1FFF {
       address as u32
@@ -37,7 +39,8 @@ impl Mapper for Mapper7 {
 
   fn mapped_cpu_write(&mut self, address: u16, value: u8) {
     if address >= 0x8000 {
-      self.bank_select = value & 0xF;
+      // Bit 4 picks the nametable page, so it's kept alongside the PRG bank
+      self.bank_select = value & 0x1F;
     }
   }
 
@@ -49,9 +52,11 @@ impl Mapper for Mapper7 {
     }
   }
 
-  fn scanline(&mut self) {}
-
   fn irq_state(&self) -> bool {
     false
   }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper7(self.clone())
+  }
 }
\ No newline at end of file
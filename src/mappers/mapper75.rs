@@ -0,0 +1,80 @@
+use crate::cartridge::MirroringMode;
+use crate::mapper::{Mapper, MapperState};
+
+/// Konami's VRC1, with three switchable 8 KB PRG banks and two 4 KB CHR banks
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper75 {
+  prg_rom_banks: u8,
+  /// 8 KB banks at $8000, $A000 and $C000, with the last bank fixed at $E000
+  prg_banks: [u8; 3],
+  /// 4 KB banks at PPU $0000 and $1000. The low 4 bits come from $E000 and $F000, and the top bit
+  /// from $9000.
+  chr_banks: [u8; 2],
+  horizontal_mirroring: bool,
+}
+
+impl Mapper75 {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
+    Self {
+      prg_rom_banks,
+      prg_banks: [0; 3],
+      chr_banks: [0; 2],
+      horizontal_mirroring: false,
+    }
+  }
+}
+
+impl Mapper for Mapper75 {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    let bank = match address {
+      0x8000..=0x9FFF => self.prg_banks[0] as u32,
+      0xA000..=0xBFFF => self.prg_banks[1] as u32,
+      0xC000..=0xDFFF => self.prg_banks[2] as u32,
+      0xE000..=0xFFFF => self.prg_rom_banks as u32 * 2 - 1,
+      _ => return 0,
+    };
+    bank * 0x2000 + (address & 0x1FFF) as u32
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x2000
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    let bank = self.chr_banks[(address as usize >> 12) & 1];
+    bank as u32 * 0x1000 + (address & 0x0FFF) as u32
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    match address & 0xF000 {
+      0x8000 => self.prg_banks[0] = value & 0x0F,
+      0x9000 => {
+        self.horizontal_mirroring = value & 0x01 != 0;
+        self.chr_banks[0] = (self.chr_banks[0] & 0x0F) | ((value & 0x02) << 3);
+        self.chr_banks[1] = (self.chr_banks[1] & 0x0F) | ((value & 0x04) << 2);
+      },
+      0xA000 => self.prg_banks[1] = value & 0x0F,
+      0xC000 => self.prg_banks[2] = value & 0x0F,
+      0xE000 => self.chr_banks[0] = (self.chr_banks[0] & 0x10) | (value & 0x0F),
+      0xF000 => self.chr_banks[1] = (self.chr_banks[1] & 0x10) | (value & 0x0F),
+      _ => {},
+    }
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    if self.horizontal_mirroring {
+      MirroringMode::Horizontal
+    } else {
+      MirroringMode::Vertical
+    }
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper75(self.clone())
+  }
+}
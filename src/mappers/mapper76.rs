@@ -1,101 +1,117 @@
 use crate::cartridge::MirroringMode;
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState};
 
-#[derive(Debug, Default, Clone, Copy)]
-pub struct NAMCOT3446Registers {
-  pub prg_bank_1: u8,
-  pub prg_bank_2: u8,
-  pub chr_bank_1: u8,
-  pub chr_bank_2: u8,
-  pub chr_bank_3: u8,
-  pub chr_bank_4: u8,
+/// The boards a Namco 108 is wired up differently on, each with its own mapper number. The chip banks
+/// like an MMC3 stuck in its first PRG and CHR modes, without the IRQ or a mirroring register.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Namco108Board {
+  /// Mapper 206, DxROM and Namco's own boards, with R0 and R1 switching 2 KB of CHR and R2-R5 1 KB
+  #[default]
+  DxROM,
+  /// Mapper 76, NAMCOT-3446, where R2-R5 switch 2 KB of CHR each and R0 and R1 do nothing
+  NAMCOT3446,
+  /// Mapper 88, NAMCOT-3433 and 3443, with CHR A16 wired to PPU A12, so the left pattern table always
+  /// comes from the first 64 KB of CHR and the right one from the second
+  NAMCOT3433,
+  /// Mapper 154, NAMCOT-3453, which is mapper 88 plus bit 6 of any write picking a single screen
+  NAMCOT3453,
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper76 {
   prg_rom_banks: u8,
-  chr_rom_banks: u8,
+  board: Namco108Board,
   bank_select: u8,
-  bank_data: u8,
-  registers: NAMCOT3446Registers,
+  /// R0-R5 are CHR banks and R6 and R7 are 8 KB PRG banks at $8000 and $A000, as on the MMC3
+  registers: [u8; 8],
+  /// NAMCOT-3453 only, whether the upper page of nametable RAM is the one showing
+  single_screen_high: bool,
 }
 
 impl Mapper76 {
   pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+    Self::with_board(prg_rom_banks, chr_rom_banks, Namco108Board::NAMCOT3446)
+  }
+
+  pub fn with_board(prg_rom_banks: u8, _chr_rom_banks: u8, board: Namco108Board) -> Self {
     Self {
       prg_rom_banks,
-      chr_rom_banks,
+      board,
       bank_select: 0,
-      bank_data: 0,
-      registers: NAMCOT3446Registers::default(),
+      registers: [0; 8],
+      single_screen_high: false,
+    }
+  }
+
+  /// The 1 KB CHR bank a pattern table address reads from
+  fn chr_bank(&self, address: u16) -> u32 {
+    let window = (address as usize >> 10) & 7;
+    if self.board == Namco108Board::NAMCOT3446 {
+      // Each of R2-R5 covers two windows, in 2 KB units
+      let bank = self.registers[2 + window / 2] & 0x3F;
+      return (bank as u32 * 2) | (window as u32 & 1);
+    }
+    let bank = match window {
+      0..=3 => (self.registers[window / 2] & 0x3E) | (window as u8 & 1),
+      _ => self.registers[window - 2] & 0x3F,
+    };
+    match self.board {
+      Namco108Board::NAMCOT3433 | Namco108Board::NAMCOT3453 if address >= 0x1000 => bank as u32 | 0x40,
+      _ => bank as u32,
     }
   }
 }
 
 impl Mapper for Mapper76 {
   fn get_mapped_address_cpu(&self, address: u16) -> u32 {
-    match address {
-      0x8000..=0x9FFF => {
-        ((self.registers.prg_bank_1 as u32) * 0x2000) + (address & 0x1FFF) as u32
-      },
-      0xA000..=0xBFFF => {
-        ((self.registers.prg_bank_2 as u32) * 0x2000) + (address & 0x1FFF) as u32
-      },
-      0xC000..=0xDFFF => {
-        ((self.prg_rom_banks as u32 * 2 - 2) * 0x2000) + (address & 0x1FFF) as u32
-      },
-      0xE000..=0xFFFF => {
-        ((self.prg_rom_banks as u32 * 2 - 1) * 0x2000) + (address & 0x1FFF) as u32
-      },
-      _ => 0,
-    }
+    let bank = match address {
+      0x8000..=0x9FFF => (self.registers[6] & 0x0F) as u32,
+      0xA000..=0xBFFF => (self.registers[7] & 0x0F) as u32,
+      0xC000..=0xDFFF => self.prg_rom_banks as u32 * 2 - 2,
+      0xE000..=0xFFFF => self.prg_rom_banks as u32 * 2 - 1,
+      _ => return 0,
+    };
+    bank * 0x2000 + (address & 0x1FFF) as u32
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x2000
   }
 
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
-    match address {
-      0x0000..=0x07FF => {
-        ((self.registers.chr_bank_1 as u32) * 0x0800) + (address & 0x07FF) as u32
-      },
-      0x0800..=0x0FFF => {
-        ((self.registers.chr_bank_2 as u32) * 0x0800) + (address & 0x07FF) as u32
-      },
-      0x1000..=0x1BFF => {
-        ((self.registers.chr_bank_3 as u32) * 0x0800) + (address & 0x07FF) as u32
-      },
-      0x1C00..=0x1FFF => {
-        ((self.registers.chr_bank_4 as u32) * 0x0800) + (address & 0x07FF) as u32
-      },
-      _ => 0,
-    }
+    self.chr_bank(address) * 0x400 + (address & 0x3FF) as u32
   }
 
   fn mapped_cpu_write(&mut self, address: u16, value: u8) {
-    match address {
-      0x8000 => {
-        self.bank_select = value & 0x7;
-      },
-      0x8001 => {
-        let data = value & 0x3F;
-        match self.bank_select {
-          0 => self.registers.chr_bank_1 = data,
-          1 => self.registers.chr_bank_2 = data,
-          2 => self.registers.chr_bank_3 = data,
-          3 => self.registers.chr_bank_4 = data,
-          4 => self.registers.prg_bank_1 = data,
-          5 => self.registers.prg_bank_2 = data,
-          _ => {},
-        }
-      },
+    if address < 0x8000 {
+      return;
+    }
+    if self.board == Namco108Board::NAMCOT3453 {
+      self.single_screen_high = value & 0x40 != 0;
+    }
+    // Only A0 is decoded within $8000-$9FFF, and nothing above it
+    match address & 0xE001 {
+      0x8000 => self.bank_select = value & 0x07,
+      0x8001 => self.registers[self.bank_select as usize] = value,
       _ => {},
     }
   }
 
   fn mirroring_mode(&self) -> MirroringMode {
-    MirroringMode::_Hardwired
+    match (self.board, self.single_screen_high) {
+      (Namco108Board::NAMCOT3453, false) => MirroringMode::SingleScreenLow,
+      (Namco108Board::NAMCOT3453, true) => MirroringMode::SingleScreenHigh,
+      _ => MirroringMode::_Hardwired,
+    }
   }
 
-  fn scanline(&mut self) {}
-
   fn irq_state(&self) -> bool {
     false
   }
-}
\ No newline at end of file
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper76(self.clone())
+  }
+}
@@ -0,0 +1,95 @@
+use crate::cartridge::MirroringMode;
+use crate::mapper::{Mapper, MapperState};
+
+/// Value written to $7EF8/$7EF9 that lets the chip's own RAM be used
+const RAM_UNLOCK: u8 = 0xA3;
+
+/// Taito's X1-005, with its registers at $7EF0-$7EFF and 128 bytes of RAM of its own at $7F00-$7F7F,
+/// mirrored up to $7FFF
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper80 {
+  prg_rom_banks: u8,
+  /// 8 KB banks at $8000, $A000 and $C000, with the last bank fixed at $E000
+  prg_banks: [u8; 3],
+  /// Two 2 KB banks at PPU $0000 and $0800 in 1 KB units ignoring the low bit, then four 1 KB banks
+  /// at $1000-$1FFF
+  chr_banks: [u8; 6],
+  vertical_mirroring: bool,
+  ram_enabled: bool,
+}
+
+impl Mapper80 {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
+    Self {
+      prg_rom_banks,
+      prg_banks: [0; 3],
+      chr_banks: [0; 6],
+      vertical_mirroring: false,
+      ram_enabled: false,
+    }
+  }
+}
+
+impl Mapper for Mapper80 {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    let bank = match address {
+      0x8000..=0x9FFF => self.prg_banks[0] as u32,
+      0xA000..=0xBFFF => self.prg_banks[1] as u32,
+      0xC000..=0xDFFF => self.prg_banks[2] as u32,
+      0xE000..=0xFFFF => self.prg_rom_banks as u32 * 2 - 1,
+      _ => return 0,
+    };
+    bank * 0x2000 + (address & 0x1FFF) as u32
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x2000
+  }
+
+  // The chip's 128 bytes are all there is, anything else below $8000 is open bus
+  fn get_mapped_address_prg_ram(&self, address: u16) -> Option<u32> {
+    (0x7F00..=0x7FFF).contains(&address).then_some((address & 0x7F) as u32)
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    let bank = match address {
+      0x0000..=0x07FF => (self.chr_banks[0] & 0xFE) | ((address >> 10) & 1) as u8,
+      0x0800..=0x0FFF => (self.chr_banks[1] & 0xFE) | ((address >> 10) & 1) as u8,
+      _ => self.chr_banks[2 + ((address as usize >> 10) & 3)],
+    };
+    bank as u32 * 0x400 + (address & 0x3FF) as u32
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    match address {
+      0x7EF0..=0x7EF5 => self.chr_banks[(address - 0x7EF0) as usize] = value,
+      0x7EF6 | 0x7EF7 => self.vertical_mirroring = value & 0x01 != 0,
+      0x7EF8 | 0x7EF9 => self.ram_enabled = value == RAM_UNLOCK,
+      0x7EFA | 0x7EFB => self.prg_banks[0] = value,
+      0x7EFC | 0x7EFD => self.prg_banks[1] = value,
+      0x7EFE | 0x7EFF => self.prg_banks[2] = value,
+      _ => {},
+    }
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    if self.vertical_mirroring {
+      MirroringMode::Vertical
+    } else {
+      MirroringMode::Horizontal
+    }
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn prg_ram_enabled(&self) -> bool {
+    self.ram_enabled
+  }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper80(self.clone())
+  }
+}
@@ -0,0 +1,176 @@
+use crate::cartridge::MirroringMode;
+use crate::mapper::{ExpansionAudio, Mapper, MapperState};
+use crate::mappers::vrc7_audio::Vrc7Audio;
+
+/// The IRQ counter Konami's VRC boards share, counting either CPU cycles or scanlines
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct VrcIrq {
+  latch: u8,
+  counter: u8,
+  /// Counts down 3 a CPU cycle from 341, so the counter goes up once per scanline's worth of PPU dots
+  prescaler: i16,
+  enabled: bool,
+  /// What `enabled` goes back to when the IRQ is acknowledged
+  enable_on_acknowledge: bool,
+  /// Counts every CPU cycle instead of every scanline
+  cycle_mode: bool,
+  active: bool,
+}
+
+impl VrcIrq {
+  fn write_control(&mut self, value: u8) {
+    self.enable_on_acknowledge = value & 0b001 != 0;
+    self.enabled = value & 0b010 != 0;
+    self.cycle_mode = value & 0b100 != 0;
+    self.active = false;
+    if self.enabled {
+      self.counter = self.latch;
+      self.prescaler = 341;
+    }
+  }
+
+  fn acknowledge(&mut self) {
+    self.active = false;
+    self.enabled = self.enable_on_acknowledge;
+  }
+
+  fn cpu_clock(&mut self) {
+    if !self.enabled {
+      return;
+    }
+    if self.cycle_mode {
+      self.clock_counter();
+    } else {
+      self.prescaler -= 3;
+      if self.prescaler <= 0 {
+        self.prescaler += 341;
+        self.clock_counter();
+      }
+    }
+  }
+
+  fn clock_counter(&mut self) {
+    if self.counter == 0xFF {
+      self.counter = self.latch;
+      self.active = true;
+    } else {
+      self.counter += 1;
+    }
+  }
+}
+
+/// Konami VRC7, with 8 KB PRG banks, 1 KB CHR banks, the VRC IRQ counter and an FM synth
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper85 {
+  prg_rom_banks: u8,
+  /// 8 KB PRG ROM banks at $8000, $A000 and $C000, $E000 is fixed to the last bank
+  prg_banks: [u8; 3],
+  /// 1 KB CHR banks, in PPU address order
+  chr_banks: [u8; 8],
+  /// Mirroring in bits 0-1, sound reset in bit 6 and PRG RAM enable in bit 7
+  control: u8,
+  irq: VrcIrq,
+  audio: Vrc7Audio,
+}
+
+impl Mapper85 {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
+    Self {
+      prg_rom_banks,
+      prg_banks: [0; 3],
+      chr_banks: [0; 8],
+      control: 0,
+      irq: VrcIrq::default(),
+      audio: Vrc7Audio::new(),
+    }
+  }
+}
+
+impl Mapper for Mapper85 {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    let bank = match address {
+      0x8000..=0x9FFF => self.prg_banks[0] as u32,
+      0xA000..=0xBFFF => self.prg_banks[1] as u32,
+      0xC000..=0xDFFF => self.prg_banks[2] as u32,
+      0xE000..=0xFFFF => (self.prg_rom_banks as u32 * 2).saturating_sub(1),
+      _ => return 0,
+    };
+    bank * 0x2000 + (address & 0x1FFF) as u32
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x2000
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    let bank = self.chr_banks[(address as usize >> 10) & 0x07] as u32;
+    bank * 0x400 + (address & 0x3FF) as u32
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    // Boards wire the second register of each pair to either A3 or A4, so either one selects it
+    let register = (address & 0xF000) | if address & 0x0018 != 0 { 0x0010 } else { 0 };
+    match register {
+      0x8000 => self.prg_banks[0] = value & 0x3F,
+      0x8010 => self.prg_banks[1] = value & 0x3F,
+      0x9000 => self.prg_banks[2] = value & 0x3F,
+      0x9010 => {
+        // $9010 picks a sound register and $9030 writes to it
+        if address & 0x0020 != 0 {
+          self.audio.write_data(value);
+        } else {
+          self.audio.write_address(value);
+        }
+      },
+      0xA000..=0xDFFF => {
+        let index = ((register - 0xA000) >> 11) as usize | (register & 0x0010 != 0) as usize;
+        self.chr_banks[index] = value;
+      },
+      0xE000 => {
+        self.control = value;
+        self.audio.set_silenced(value & 0x40 != 0);
+      },
+      0xE010 => self.irq.latch = value,
+      0xF000 => self.irq.write_control(value),
+      0xF010 => self.irq.acknowledge(),
+      _ => {},
+    }
+  }
+
+  fn prg_ram_enabled(&self) -> bool {
+    self.control & 0x80 != 0
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    match self.control & 0x03 {
+      0 => MirroringMode::Vertical,
+      1 => MirroringMode::Horizontal,
+      2 => MirroringMode::SingleScreenLow,
+      _ => MirroringMode::SingleScreenHigh,
+    }
+  }
+
+  fn irq_state(&self) -> bool {
+    self.irq.active
+  }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper85(Box::new(self.clone()))
+  }
+
+  fn cpu_clock(&mut self) {
+    self.irq.cpu_clock();
+  }
+
+  fn audio_clock(&mut self, cpu_cycles: u32) {
+    for _ in 0..cpu_cycles {
+      self.audio.clock();
+    }
+  }
+
+  fn audio_output(&self) -> f32 {
+    self.audio.output()
+  }
+}
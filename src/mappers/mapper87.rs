@@ -0,0 +1,57 @@
+use crate::cartridge::MirroringMode;
+use crate::mapper::{Mapper, MapperState};
+
+/// Jaleco, Konami and Taito's discrete CHR switching boards, with a latch at $6000-$7FFF whose two
+/// low bits are wired up the wrong way round
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper87 {
+  prg_rom_banks: u8,
+  chr_bank: u8,
+}
+
+impl Mapper87 {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
+    Self {
+      prg_rom_banks,
+      chr_bank: 0,
+    }
+  }
+}
+
+impl Mapper for Mapper87 {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    if address >= 0x8000 {
+      let mask = if self.prg_rom_banks > 1 { 0x7FFF } else { 0x3FFF };
+      (address & mask) as u32
+    } else {
+      0
+    }
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    0x4000
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    self.chr_bank as u32 * 0x2000 + (address & 0x1FFF) as u32
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    if (0x6000..=0x7FFF).contains(&address) {
+      self.chr_bank = ((value & 0x01) << 1) | ((value & 0x02) >> 1);
+    }
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    MirroringMode::_Hardwired
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper87(self.clone())
+  }
+}
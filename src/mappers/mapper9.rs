@@ -1,30 +1,32 @@
 use crate::cartridge::MirroringMode;
-use crate::mapper::Mapper;
+use crate::mapper::{Mapper, MapperState, PatternFetch};
+use crate::mappers::chr_latches::ChrLatches;
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper9 {
   prg_rom_banks: u8,
-  chr_rom_banks: u8,
   prg_rom_bank: u8,
-  chr_rom_bank_1: u8,
-  chr_rom_bank_2: u8,
-  chr_rom_bank_3: u8,
-  chr_rom_bank_4: u8,
+  chr_latches: ChrLatches,
   mirroring: bool,
 }
 
 impl Mapper9 {
-  pub fn new(prg_rom_banks: u8, chr_rom_banks: u8) -> Self {
+  pub fn new(prg_rom_banks: u8, _chr_rom_banks: u8) -> Self {
     Self {
       prg_rom_banks,
-      chr_rom_banks,
       prg_rom_bank: 0,
-      chr_rom_bank_1: 0,
-      chr_rom_bank_2: 0,
-      chr_rom_bank_3: 0,
-      chr_rom_bank_4: 0,
+      chr_latches: ChrLatches::new(true),
       mirroring: false,
     }
   }
+
+  /// The 8KB PRG bank `back` from the end, counting round from the start again on boards with fewer
+  /// banks. `prg_rom_banks` counts 16KB banks, as the header does.
+  fn bank_from_end(&self, back: u32) -> u32 {
+    let banks = self.prg_rom_banks as u32 * 2;
+    (banks - back % banks) % banks
+  }
 }
 
 impl Mapper for Mapper9 {
@@ -34,28 +36,24 @@ impl Mapper for Mapper9 {
         ((self.prg_rom_bank as u32) * 0x2000) + (address & 0x1FFF) as u32
       },
       0xA000..=0xBFFF => {
-        (self.prg_rom_banks as u32 - 3) * 0x2000 + (address & 0x1FFF) as u32
+        self.bank_from_end(3) * 0x2000 + (address & 0x1FFF) as u32
       },
       0xC000..=0xDFFF => {
-        (self.prg_rom_banks as u32 - 2) * 0x2000 + (address & 0x1FFF) as u32
+        self.bank_from_end(2) * 0x2000 + (address & 0x1FFF) as u32
       },
       0xE000..=0xFFFF => {
-        (self.prg_rom_banks as u32 - 1) * 0x2000 + (address & 0x1FFF) as u32
+        self.bank_from_end(1) * 0x2000 + (address & 0x1FFF) as u32
       },
       _ => 0,
     }
   }
 
+  fn prg_bank_size(&self) -> u32 {
+    0x2000
+  }
+
   fn get_mapped_address_ppu(&self, address: u16) -> u32 {
-    match address {
-      0x0000..=0x0FFF => {
-        (self.chr_rom_bank_1 as u32 * 0x1000) + (address & 0x0FFF) as u32
-      },
-      0x1000..=0x1FFF => {
-        (self.chr_rom_bank_3 as u32 * 0x1000) + (address & 0x0FFF) as u32
-      },
-      _ => 0,
-    }
+    self.chr_latches.get_mapped_address(address)
   }
 
   fn mapped_cpu_write(&mut self, address: u16, value: u8) {
@@ -64,16 +62,16 @@ impl Mapper for Mapper9 {
         self.prg_rom_bank = value & 0xF;
       },
       0xB000..=0xBFFF => {
-        self.chr_rom_bank_1 = value & 0x1F;
+        self.chr_latches.banks[0][0] = value & 0x1F;
       },
       0xC000..=0xCFFF => {
-        self.chr_rom_bank_2 = value & 0x1F;
+        self.chr_latches.banks[0][1] = value & 0x1F;
       },
       0xD000..=0xDFFF => {
-        self.chr_rom_bank_3 = value & 0x1F;
+        self.chr_latches.banks[1][0] = value & 0x1F;
       },
       0xE000..=0xEFFF => {
-        self.chr_rom_bank_4 = value & 0x1F;
+        self.chr_latches.banks[1][1] = value & 0x1F;
       },
       0xF000..=0xFFFF => {
         self.mirroring = value & 1 == 1;
@@ -90,9 +88,15 @@ impl Mapper for Mapper9 {
     }
   }
 
-  fn scanline(&mut self) {}
-
   fn irq_state(&self) -> bool {
     false
   }
+
+  fn state(&self) -> MapperState {
+    MapperState::Mapper9(self.clone())
+  }
+
+  fn pattern_fetch(&mut self, fetch: PatternFetch) {
+    self.chr_latches.fetch(fetch);
+  }
 }
\ No newline at end of file
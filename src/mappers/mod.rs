@@ -5,8 +5,78 @@ pub mod mapper3;
 pub mod mapper4;
 pub mod mapper7;
 pub mod mapper9;
+pub mod mapper10;
 pub mod mapper11;
+pub mod mapper34;
+pub mod mapper66;
+pub mod mapper75;
 pub mod mapper76;
+pub mod mapper80;
+pub mod mapper85;
+pub mod mapper87;
 pub mod mapper89;
 pub mod mapper140;
-pub mod mapper152;
\ No newline at end of file
+pub mod mapper152;
+pub mod mapper185;
+pub mod chr_latches;
+pub mod vrc7_audio;
+
+use crate::mapper::Mapper;
+
+/// Builds a mapper from the cartridge's PRG ROM size in 16 KB banks and CHR ROM size in 8 KB banks
+pub type MapperConstructor = fn(u8, u8) -> Box<dyn Mapper>;
+
+/// What we know about a mapper, for building one and for telling the user what it is
+pub struct MapperInfo {
+  /// iNES mapper number
+  pub id: u8,
+  pub name: &'static str,
+  /// The boards that use it
+  pub board: &'static str,
+  /// Whether it maps PRG RAM into $6000-$7FFF when the cartridge has some
+  pub prg_ram: bool,
+  pub irq: bool,
+  /// Whether the cartridge mixes its own sound channels in
+  pub audio: bool,
+  pub create: MapperConstructor,
+}
+
+/// Every mapper a cartridge can use, by iNES mapper number. A new mapper only needs an entry here,
+/// plus a `MapperState` variant so it can go in save states.
+pub const MAPPERS: &[MapperInfo] = &[
+  MapperInfo { id: 0, name: "NROM", board: "NROM", prg_ram: true, irq: false, audio: false, create: |prg, chr| Box::new(mapper0::Mapper0::new(prg, chr)) },
+  MapperInfo { id: 1, name: "MMC1", board: "SxROM", prg_ram: true, irq: false, audio: false, create: |prg, chr| Box::new(mapper1::Mapper1::new(prg, chr)) },
+  MapperInfo { id: 2, name: "UxROM", board: "UNROM, UOROM", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper2::Mapper2::new(prg, chr)) },
+  MapperInfo { id: 3, name: "CNROM", board: "CNROM", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper3::Mapper3::new(prg, chr)) },
+  MapperInfo { id: 4, name: "MMC3", board: "TxROM", prg_ram: true, irq: true, audio: false, create: |prg, chr| Box::new(mapper4::Mapper4::new(prg, chr)) },
+  MapperInfo { id: 7, name: "AxROM", board: "ANROM, AOROM", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper7::Mapper7::new(prg, chr)) },
+  MapperInfo { id: 9, name: "MMC2", board: "PxROM", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper9::Mapper9::new(prg, chr)) },
+  MapperInfo { id: 10, name: "MMC4", board: "FxROM", prg_ram: true, irq: false, audio: false, create: |prg, chr| Box::new(mapper10::Mapper10::new(prg, chr)) },
+  MapperInfo { id: 11, name: "Color Dreams", board: "Color Dreams", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper11::Mapper11::new(prg, chr)) },
+  MapperInfo { id: 34, name: "BNROM / NINA-001", board: "BNROM, NINA-001", prg_ram: true, irq: false, audio: false, create: |prg, chr| Box::new(mapper34::Mapper34::new(prg, chr)) },
+  MapperInfo { id: 66, name: "GxROM", board: "GNROM, MHROM", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper66::Mapper66::new(prg, chr)) },
+  MapperInfo { id: 75, name: "VRC1", board: "Konami VRC1, Jaleco JF-20, JF-22", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper75::Mapper75::new(prg, chr)) },
+  MapperInfo { id: 76, name: "Namco 108", board: "NAMCOT-3446", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper76::Mapper76::new(prg, chr)) },
+  MapperInfo { id: 80, name: "Taito X1-005", board: "Taito X1-005", prg_ram: true, irq: false, audio: false, create: |prg, chr| Box::new(mapper80::Mapper80::new(prg, chr)) },
+  MapperInfo { id: 85, name: "VRC7", board: "Konami VRC7", prg_ram: true, irq: true, audio: true, create: |prg, chr| Box::new(mapper85::Mapper85::new(prg, chr)) },
+  MapperInfo { id: 87, name: "J87", board: "Jaleco JF-05 to JF-10, Konami, Taito", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper87::Mapper87::new(prg, chr)) },
+  MapperInfo { id: 88, name: "Namco 108", board: "NAMCOT-3433, NAMCOT-3443", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper76::Mapper76::with_board(prg, chr, mapper76::Namco108Board::NAMCOT3433)) },
+  MapperInfo { id: 89, name: "Sunsoft-2", board: "Sunsoft-2 on Sunsoft-3 board", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper89::Mapper89::new(prg, chr)) },
+  MapperInfo { id: 118, name: "TxSROM", board: "TKSROM, TLSROM", prg_ram: true, irq: true, audio: false, create: |prg, chr| Box::new(mapper4::Mapper4::with_board(prg, chr, mapper4::Mmc3Board::TxSROM)) },
+  MapperInfo { id: 119, name: "TQROM", board: "TQROM", prg_ram: false, irq: true, audio: false, create: |prg, chr| Box::new(mapper4::Mapper4::with_board(prg, chr, mapper4::Mmc3Board::TQROM)) },
+  MapperInfo { id: 140, name: "Jaleco JF-11/14", board: "JF-11, JF-14", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper140::Mapper140::new(prg, chr)) },
+  MapperInfo { id: 152, name: "Bandai 74161", board: "Bandai single screen", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper152::Mapper152::new(prg, chr)) },
+  MapperInfo { id: 154, name: "Namco 108", board: "NAMCOT-3453", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper76::Mapper76::with_board(prg, chr, mapper76::Namco108Board::NAMCOT3453)) },
+  MapperInfo { id: 185, name: "CNROM with copy protection", board: "CNROM", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper185::Mapper185::new(prg, chr)) },
+  MapperInfo { id: 206, name: "Namco 108", board: "DxROM, Namco 108", prg_ram: false, irq: false, audio: false, create: |prg, chr| Box::new(mapper76::Mapper76::with_board(prg, chr, mapper76::Namco108Board::DxROM)) },
+];
+
+/// What we know about iNES mapper number `mapper_id`, if it's one we support
+pub fn info(mapper_id: u8) -> Option<&'static MapperInfo> {
+  MAPPERS.iter().find(|info| info.id == mapper_id)
+}
+
+/// A fresh mapper for iNES mapper number `mapper_id`, if it's one we support
+pub fn create(mapper_id: u8, prg_rom_banks: u8, chr_rom_banks: u8) -> Option<Box<dyn Mapper>> {
+  info(mapper_id).map(|info| (info.create)(prg_rom_banks, chr_rom_banks))
+}
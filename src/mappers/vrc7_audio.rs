@@ -0,0 +1,383 @@
+//! The VRC7's sound chip, a cut down YM2413 (OPLL) with six two-operator FM channels, no rhythm mode
+//! and its own set of built in instruments.
+
+use std::f32::consts::TAU;
+
+use crate::mapper::ExpansionAudio;
+
+/// The chip runs at 3.58 MHz and takes 72 of its clocks per sample, so one sample every 36 CPU cycles
+const CPU_CYCLES_PER_SAMPLE: u8 = 36;
+
+const SAMPLE_RATE: f32 = 1_789_773.0 / CPU_CYCLES_PER_SAMPLE as f32;
+
+const CHANNELS: usize = 6;
+
+/// Attenuation in dB at which the envelope bottoms out and the operator is silent
+const MAX_ATTENUATION: f32 = 48.0;
+
+/// Seconds for an envelope to decay all the way at rate 4, the slowest there is. Each 4 rates faster halves it.
+const DECAY_SECONDS: f32 = 19.6;
+
+/// Same again for attack, which climbs out of silence instead
+const ATTACK_SECONDS: f32 = 2.83;
+
+/// Tremolo rate in Hz and depth in dB
+const AM_RATE: f32 = 3.7;
+const AM_DEPTH: f32 = 4.8;
+
+/// Vibrato rate in Hz and depth, as a fraction of the frequency
+const VIBRATO_RATE: f32 = 6.4;
+const VIBRATO_DEPTH: f32 = 0.004;
+
+/// How far a modulator at full volume can push its carrier's phase, in cycles
+const MODULATION_DEPTH: f32 = 2.0;
+
+/// Peak output of one channel, relative to the APU's own mix
+const CHANNEL_VOLUME: f32 = 0.1;
+
+/// Instruments 1-15, in the same layout as the custom instrument in registers $00-$07
+const INSTRUMENTS: [[u8; 8]; 15] = [
+  [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27], // Buzzy bell
+  [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12], // Guitar
+  [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12], // Wurly
+  [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27], // Flute
+  [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28], // Clarinet
+  [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4], // Synth
+  [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07], // Trumpet
+  [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17], // Organ
+  [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01], // Bells
+  [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02], // Vibes
+  [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12], // Vibraphone
+  [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16], // Tutti
+  [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02], // Fretless
+  [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6], // Synth bass
+  [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06], // Sweep
+];
+
+/// Frequency multiplier for each MULT setting, which skips 11, 13 and 14
+const MULTIPLIERS: [f32; 16] = [0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0];
+
+/// Key scale level by the top 4 bits of the F-number, in 0.75 dB steps at 6 dB per octave
+const KSL_TABLE: [f32; 16] = [0.0, 32.0, 40.0, 45.0, 48.0, 51.0, 53.0, 55.0, 56.0, 58.0, 59.0, 60.0, 61.0, 62.0, 63.0, 64.0];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum EnvelopeStage {
+  Attack,
+  Decay,
+  Sustain,
+  Release,
+  #[default]
+  Off,
+}
+
+/// One operator's half of an instrument
+struct OperatorPatch {
+  am: bool,
+  vibrato: bool,
+  /// Holds at the sustain level while the key is down, rather than carrying on fading
+  sustained: bool,
+  key_scale_rate: bool,
+  multiplier: f32,
+  key_scale_level: u8,
+  rectified: bool,
+  attack: u8,
+  decay: u8,
+  sustain_level: f32,
+  release: u8,
+}
+
+impl OperatorPatch {
+  fn new(instrument: &[u8; 8], carrier: bool) -> Self {
+    let i = carrier as usize;
+    let flags = instrument[i];
+    Self {
+      am: flags & 0x80 != 0,
+      vibrato: flags & 0x40 != 0,
+      sustained: flags & 0x20 != 0,
+      key_scale_rate: flags & 0x10 != 0,
+      multiplier: MULTIPLIERS[(flags & 0x0F) as usize],
+      key_scale_level: instrument[2 + i] >> 6,
+      rectified: instrument[3] & if carrier { 0x10 } else { 0x08 } != 0,
+      attack: instrument[4 + i] >> 4,
+      decay: instrument[4 + i] & 0x0F,
+      sustain_level: (instrument[6 + i] >> 4) as f32 * 3.0,
+      release: instrument[6 + i] & 0x0F,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Operator {
+  /// Position through the waveform, in cycles
+  phase: f32,
+  /// Envelope attenuation in dB
+  attenuation: f32,
+  stage: EnvelopeStage,
+  /// Last two outputs, fed back into a modulator
+  previous: [f32; 2],
+}
+
+impl Operator {
+  fn key_on(&mut self) {
+    self.phase = 0.0;
+    self.stage = EnvelopeStage::Attack;
+  }
+
+  fn key_off(&mut self) {
+    if self.stage != EnvelopeStage::Off {
+      self.stage = EnvelopeStage::Release;
+    }
+  }
+
+  /// Move the envelope on a sample. `release` is the rate it fades at once the key is up.
+  fn step_envelope(&mut self, patch: &OperatorPatch, key_code: u8, release: u8) {
+    let rate = |rate: u8| effective_rate(rate, patch.key_scale_rate, key_code);
+    match self.stage {
+      EnvelopeStage::Attack => {
+        let rate = rate(patch.attack);
+        if rate >= 60 {
+          self.attenuation = 0.0;
+        } else if rate >= 4 {
+          // Attack is exponential, closing in on full volume quickly and then easing into it
+          let samples = ATTACK_SECONDS * 2f32.powf(-((rate - 4) as f32) / 4.0) * SAMPLE_RATE;
+          self.attenuation *= (0.1 / MAX_ATTENUATION).powf(1.0 / samples);
+        }
+        if self.attenuation < 0.1 {
+          self.attenuation = 0.0;
+          self.stage = EnvelopeStage::Decay;
+        }
+      },
+      EnvelopeStage::Decay => {
+        self.attenuation += decay_step(rate(patch.decay));
+        if self.attenuation >= patch.sustain_level {
+          self.attenuation = patch.sustain_level;
+          self.stage = EnvelopeStage::Sustain;
+        }
+      },
+      EnvelopeStage::Sustain => {
+        if !patch.sustained {
+          self.attenuation += decay_step(rate(patch.release));
+        }
+      },
+      EnvelopeStage::Release => {
+        self.attenuation += decay_step(rate(release));
+        if self.attenuation >= MAX_ATTENUATION {
+          self.stage = EnvelopeStage::Off;
+        }
+      },
+      EnvelopeStage::Off => self.attenuation = MAX_ATTENUATION,
+    }
+    self.attenuation = self.attenuation.min(MAX_ATTENUATION);
+  }
+
+  /// Output for the current phase, pushed along by `modulation` cycles, at `attenuation` dB on top of the envelope
+  fn output(&self, modulation: f32, attenuation: f32, rectified: bool) -> f32 {
+    let total = self.attenuation + attenuation;
+    if total >= MAX_ATTENUATION {
+      return 0.0;
+    }
+    let wave = (TAU * (self.phase + modulation)).sin();
+    let wave = if rectified { wave.max(0.0) } else { wave };
+    wave * 10f32.powf(-total / 20.0)
+  }
+}
+
+/// Envelope rate after key scaling, from 0 to 63
+fn effective_rate(rate: u8, key_scale_rate: bool, key_code: u8) -> u8 {
+  if rate == 0 {
+    return 0;
+  }
+  let offset = if key_scale_rate { key_code } else { key_code >> 2 };
+  (rate * 4 + offset).min(63)
+}
+
+/// dB a decay or release at `rate` fades by each sample
+fn decay_step(rate: u8) -> f32 {
+  if rate < 4 {
+    return 0.0;
+  }
+  let seconds = DECAY_SECONDS * 2f32.powf(-((rate - 4) as f32) / 4.0);
+  MAX_ATTENUATION / (seconds * SAMPLE_RATE)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Channel {
+  /// 9 bit F-number and 3 bit octave, which set the pitch together
+  f_number: u16,
+  block: u8,
+  key_on: bool,
+  /// Slows the release down, like a piano's sustain pedal
+  sustain: bool,
+  instrument: u8,
+  /// Attenuation of the carrier, in 3 dB steps
+  volume: u8,
+  modulator: Operator,
+  carrier: Operator,
+}
+
+impl Channel {
+  /// Octave and top bit of the F-number, which the envelopes scale by
+  fn key_code(&self) -> u8 {
+    (self.block << 1) | (self.f_number >> 8) as u8
+  }
+
+  /// Extra attenuation for higher notes
+  fn key_scale_level(&self, level: u8) -> f32 {
+    if level == 0 {
+      return 0.0;
+    }
+    let steps = (KSL_TABLE[(self.f_number >> 5) as usize] - 8.0 * (7 - self.block) as f32).max(0.0);
+    steps * 0.75 / (1 << (3 - level)) as f32
+  }
+
+  /// Phase advance per sample for an operator with `multiplier`, in cycles
+  fn phase_step(&self, multiplier: f32) -> f32 {
+    self.f_number as f32 * (1 << self.block) as f32 * multiplier / (1 << 19) as f32
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vrc7Audio {
+  /// Register the next data write goes to
+  address: u8,
+  /// Instrument 0, set by the game
+  custom_instrument: [u8; 8],
+  channels: [Channel; CHANNELS],
+  /// CPU cycles until the next sample
+  divider: u8,
+  /// Positions through the tremolo and vibrato cycles
+  am_phase: f32,
+  vibrato_phase: f32,
+  output: f32,
+  /// Held in reset by the mapper, silent and ignoring writes
+  silenced: bool,
+}
+
+impl Vrc7Audio {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn write_address(&mut self, value: u8) {
+    self.address = value;
+  }
+
+  pub fn write_data(&mut self, value: u8) {
+    if self.silenced {
+      return;
+    }
+    let index = (self.address & 0x0F) as usize;
+    match self.address {
+      0x00..=0x07 => self.custom_instrument[index] = value,
+      0x10..=0x15 => {
+        let channel = &mut self.channels[index];
+        channel.f_number = (channel.f_number & 0x100) | value as u16;
+      },
+      0x20..=0x25 => {
+        let channel = &mut self.channels[index];
+        channel.f_number = (channel.f_number & 0xFF) | ((value & 0x01) as u16) << 8;
+        channel.block = (value >> 1) & 0x07;
+        channel.sustain = value & 0x20 != 0;
+        let key_on = value & 0x10 != 0;
+        if key_on && !channel.key_on {
+          channel.modulator.key_on();
+          channel.carrier.key_on();
+        } else if !key_on && channel.key_on {
+          channel.modulator.key_off();
+          channel.carrier.key_off();
+        }
+        channel.key_on = key_on;
+      },
+      0x30..=0x35 => {
+        let channel = &mut self.channels[index];
+        channel.instrument = value >> 4;
+        channel.volume = value & 0x0F;
+      },
+      _ => {},
+    }
+  }
+
+  /// Hold the chip in reset, or let it go again
+  pub fn set_silenced(&mut self, silenced: bool) {
+    if silenced && !self.silenced {
+      *self = Self { silenced: true, ..Self::default() };
+    }
+    self.silenced = silenced;
+  }
+
+  fn instrument(&self, channel: &Channel) -> [u8; 8] {
+    match channel.instrument {
+      0 => self.custom_instrument,
+      instrument => INSTRUMENTS[instrument as usize - 1],
+    }
+  }
+
+  /// Work out the next sample from all six channels
+  fn sample(&mut self) {
+    self.am_phase = (self.am_phase + AM_RATE / SAMPLE_RATE).fract();
+    self.vibrato_phase = (self.vibrato_phase + VIBRATO_RATE / SAMPLE_RATE).fract();
+    let am = AM_DEPTH * 0.5 * (1.0 + (TAU * self.am_phase).sin());
+    let vibrato = 1.0 + VIBRATO_DEPTH * (TAU * self.vibrato_phase).sin();
+
+    let mut output = 0.0;
+    for i in 0..CHANNELS {
+      let instrument = self.instrument(&self.channels[i]);
+      let channel = &mut self.channels[i];
+      let key_code = channel.key_code();
+      let feedback = instrument[3] & 0x07;
+      let modulator_patch = OperatorPatch::new(&instrument, false);
+      let carrier_patch = OperatorPatch::new(&instrument, true);
+
+      // Once the key's up, the sustain flag and the instrument's type pick how quickly it fades
+      let release = |patch: &OperatorPatch| if channel.sustain { 5 } else if patch.sustained { patch.release } else { 7 };
+      let (modulator_release, carrier_release) = (release(&modulator_patch), release(&carrier_patch));
+      channel.modulator.step_envelope(&modulator_patch, key_code, modulator_release);
+      channel.carrier.step_envelope(&carrier_patch, key_code, carrier_release);
+
+      let modulator_attenuation = (instrument[2] & 0x3F) as f32 * 0.75
+        + channel.key_scale_level(modulator_patch.key_scale_level)
+        + if modulator_patch.am { am } else { 0.0 };
+      let feedback = if feedback == 0 {
+        0.0
+      } else {
+        (channel.modulator.previous[0] + channel.modulator.previous[1]) * 2f32.powi(feedback as i32 - 8)
+      };
+      let modulation = channel.modulator.output(feedback, modulator_attenuation, modulator_patch.rectified);
+      channel.modulator.previous = [channel.modulator.previous[1], modulation];
+
+      let carrier_attenuation = channel.volume as f32 * 3.0
+        + channel.key_scale_level(carrier_patch.key_scale_level)
+        + if carrier_patch.am { am } else { 0.0 };
+      output += channel.carrier.output(modulation * MODULATION_DEPTH, carrier_attenuation, carrier_patch.rectified);
+
+      let steps = [channel.phase_step(modulator_patch.multiplier), channel.phase_step(carrier_patch.multiplier)];
+      for ((operator, patch), step) in [(&mut channel.modulator, &modulator_patch), (&mut channel.carrier, &carrier_patch)].into_iter().zip(steps) {
+        let step = if patch.vibrato { step * vibrato } else { step };
+        operator.phase = (operator.phase + step).fract();
+      }
+    }
+
+    self.output = output * CHANNEL_VOLUME;
+  }
+}
+
+impl ExpansionAudio for Vrc7Audio {
+  fn clock(&mut self) {
+    if self.silenced {
+      return;
+    }
+    if self.divider == 0 {
+      self.divider = CPU_CYCLES_PER_SAMPLE;
+      self.sample();
+    }
+    self.divider -= 1;
+  }
+
+  fn output(&self) -> f32 {
+    self.output
+  }
+}
@@ -0,0 +1,426 @@
+use eframe::egui;
+
+use crate::apu::{AudioChannel, ChannelMix, MixerMode, StereoMode};
+use crate::apu_output::{AudioBackend, AudioDriver, SyncMode, LATENCY_RANGE_MS};
+use crate::bus::{PowerOnState, RamFill};
+use crate::command::Command;
+use crate::config::Config;
+use crate::demos::DEMOS;
+use crate::frame_advance::BackgroundMode;
+use crate::frame_skip::FrameSkip;
+use crate::hotkeys::HotkeyAction;
+use crate::i18n::{tr, Language};
+use crate::palette::BuiltinPalette;
+use crate::video::ColorVision;
+
+/// Height reserved for the menubar so the 512x480 display isn't squashed
+pub const MENUBAR_HEIGHT: f32 = 24.0;
+
+/// Draw the menubar at the top of the window and return the action the user picked this frame, if any.
+///
+/// This is drawn with egui itself rather than a native menu so it works the same on every platform,
+/// including the web build. Hotkeys are handled by the frontends, the menu only shows what they're bound to.
+/// Any mapper features the game needs that we don't have get a warning icon at the end.
+pub fn show(ctx: &egui::Context, config: &Config, audio: &AudioBackend, muted: bool, mix: ChannelMix, unimplemented: &[&str]) -> Option<Command> {
+    let mut action = None;
+    let shortcut_text = |hotkey: HotkeyAction| {
+        config.hotkeys.active(hotkey).map(|shortcut| ctx.format_shortcut(&shortcut)).unwrap_or_default()
+    };
+
+    egui::TopBottomPanel::top("menubar")
+        .exact_height(MENUBAR_HEIGHT)
+        .show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                // File Tab
+                ui.menu_button(tr("menu.file"), |ui| {
+                    let load_rom = egui::Button::new(tr("menu.load_rom"))
+                        .shortcut_text(shortcut_text(HotkeyAction::LoadRom));
+                    if ui.add(load_rom).clicked() {
+                        action = Some(Command::LoadRom);
+                        ui.close_menu();
+                    }
+                    // Browsers can't list a folder's files
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button(tr("menu.library")).clicked() {
+                        action = Some(Command::ShowLibrary);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.test_pattern")).clicked() {
+                        action = Some(Command::LoadTestPattern);
+                        ui.close_menu();
+                    }
+                    ui.menu_button(tr("menu.demos"), |ui| {
+                        for (index, demo) in DEMOS.iter().enumerate() {
+                            if ui.button(demo.name).clicked() {
+                                action = Some(Command::PlayDemo(index));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.separator();
+                    let save_state = egui::Button::new(tr("menu.save_state"))
+                        .shortcut_text(shortcut_text(HotkeyAction::SaveState));
+                    if ui.add(save_state).clicked() {
+                        action = Some(Command::SaveState(0));
+                        ui.close_menu();
+                    }
+                    let load_state = egui::Button::new(tr("menu.load_state"))
+                        .shortcut_text(shortcut_text(HotkeyAction::LoadState));
+                    if ui.add(load_state).clicked() {
+                        action = Some(Command::LoadState(0));
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let pause = egui::Button::new(tr("menu.pause"))
+                        .shortcut_text(shortcut_text(HotkeyAction::Pause));
+                    if ui.add(pause).clicked() {
+                        action = Some(Command::TogglePause);
+                        ui.close_menu();
+                    }
+                    let frame_advance = egui::Button::new(tr("menu.frame_advance"))
+                        .shortcut_text(shortcut_text(HotkeyAction::FrameAdvance));
+                    if ui.add(frame_advance).clicked() {
+                        action = Some(Command::FrameAdvance);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.reset")).clicked() {
+                        action = Some(Command::Reset);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.power_cycle")).clicked() {
+                        action = Some(Command::PowerCycle);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(tr("menu.cheats")).clicked() {
+                        action = Some(Command::ShowCheats);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.game_properties")).clicked() {
+                        action = Some(Command::ShowGameProperties);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.ram_search")).clicked() {
+                        action = Some(Command::ShowRamSearch);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.conditions")).clicked() {
+                        action = Some(Command::ShowConditions);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.practice")).clicked() {
+                        action = Some(Command::ShowPractice);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.apu_timeline")).clicked() {
+                        action = Some(Command::ShowApuTimeline);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.apu_viewer")).clicked() {
+                        action = Some(Command::ShowApuViewer);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.event_viewer")).clicked() {
+                        action = Some(Command::ShowEventViewer);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.nametable_viewer")).clicked() {
+                        action = Some(Command::ShowNametableViewer);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.code_profiler")).clicked() {
+                        action = Some(Command::ShowCodeProfiler);
+                        ui.close_menu();
+                    }
+                    // Browsers can't send UDP, and there's nothing to quit to in a browser tab
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        if ui.button(tr("menu.netplay")).clicked() {
+                            action = Some(Command::ShowNetplay);
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui.button(tr("menu.quit")).clicked() {
+                            action = Some(Command::Quit);
+                            ui.close_menu();
+                        }
+                    }
+                });
+
+                // Settings Tab
+                ui.menu_button(tr("menu.settings"), |ui| {
+                    ui.menu_button(tr("menu.language"), |ui| {
+                        for language in Language::ALL {
+                            if ui.radio(config.language == language, language.native_name()).clicked() {
+                                action = Some(Command::SetLanguage(language));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button(tr("menu.color_vision"), |ui| {
+                        for mode in ColorVision::ALL {
+                            if ui.radio(config.color_vision == mode, tr(mode.key())).clicked() {
+                                action = Some(Command::SetColorVision(mode));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button(tr("menu.video"), |ui| {
+                        ui.label(tr("menu.palette"));
+                        for palette in BuiltinPalette::ALL {
+                            let selected = config.custom_palette.is_none() && config.palette == palette;
+                            if ui.radio(selected, tr(palette.key())).clicked() {
+                                action = Some(Command::SetPalette(palette));
+                                ui.close_menu();
+                            }
+                        }
+                        if config.custom_palette.is_some() {
+                            let _ = ui.radio(true, tr("palette.custom"));
+                        }
+                        ui.separator();
+                        if ui.button(tr("menu.load_palette")).clicked() {
+                            action = Some(Command::LoadPaletteFile);
+                            ui.close_menu();
+                        }
+                        let mut hide_overscan = config.hide_overscan;
+                        if ui.checkbox(&mut hide_overscan, tr("menu.hide_overscan")).changed() {
+                            action = Some(Command::SetHideOverscan(hide_overscan));
+                        }
+                        let mut flicker_blend = config.flicker_blend;
+                        if ui.checkbox(&mut flicker_blend, tr("menu.flicker_blend")).on_hover_text(tr("flicker_blend.hint")).changed() {
+                            action = Some(Command::SetFlickerBlend(flicker_blend));
+                        }
+                        ui.separator();
+                        ui.label(tr("menu.frame_skip")).on_hover_text(tr("frame_skip.hint"));
+                        for frame_skip in FrameSkip::ALL {
+                            if ui.radio(config.frame_skip == frame_skip, tr(frame_skip.key())).clicked() {
+                                action = Some(Command::SetFrameSkip(frame_skip));
+                            }
+                        }
+                    });
+                    ui.menu_button(tr("menu.audio"), |ui| {
+                        ui.label(format!("{}: {}", tr("audio.output"), tr(audio.key())));
+                        if AudioDriver::ALL.len() > 1 {
+                            ui.separator();
+                            ui.label(tr("audio.driver"));
+                            for &driver in AudioDriver::ALL {
+                                if ui.radio(config.audio_driver == driver, tr(driver.key())).clicked() {
+                                    action = Some(Command::SetAudioDriver(driver));
+                                    ui.close_menu();
+                                }
+                            }
+                        }
+                        ui.separator();
+                        let mute = egui::Button::new(tr("audio.mute"))
+                            .selected(muted)
+                            .shortcut_text(shortcut_text(HotkeyAction::Mute));
+                        if ui.add(mute).clicked() {
+                            action = Some(Command::ToggleMute);
+                        }
+                        ui.label(tr("audio.volume"));
+                        let mut volume = config.volume * 100.0;
+                        if ui.add(egui::Slider::new(&mut volume, 0.0..=100.0).suffix("%").integer()).changed() {
+                            action = Some(Command::SetVolume(volume / 100.0));
+                        }
+                        ui.label(tr("audio.latency"));
+                        let mut latency = config.audio_latency_ms;
+                        if ui.add(egui::Slider::new(&mut latency, LATENCY_RANGE_MS).suffix(" ms")).changed() {
+                            action = Some(Command::SetAudioLatency(latency));
+                        }
+                        ui.label(tr("audio.sync")).on_hover_text(tr("audio.sync_hint"));
+                        for mode in SyncMode::ALL {
+                            if ui.radio(config.sync_mode == mode, tr(mode.key())).clicked() {
+                                action = Some(Command::SetSyncMode(mode));
+                            }
+                        }
+                        let mut ultrasonic = config.ultrasonic_triangle;
+                        if ui.checkbox(&mut ultrasonic, tr("audio.ultrasonic_triangle"))
+                            .on_hover_text(tr("audio.ultrasonic_triangle_hint"))
+                            .changed()
+                        {
+                            action = Some(Command::SetUltrasonicTriangle(ultrasonic));
+                        }
+                        let mut reduce_popping = config.reduce_pulse_popping;
+                        if ui.checkbox(&mut reduce_popping, tr("audio.reduce_pulse_popping"))
+                            .on_hover_text(tr("audio.reduce_pulse_popping_hint"))
+                            .changed()
+                        {
+                            action = Some(Command::SetReducePulsePopping(reduce_popping));
+                        }
+                        ui.label(tr("audio.mixer")).on_hover_text(tr("audio.mixer_hint"));
+                        for mixer in MixerMode::ALL {
+                            if ui.radio(config.mixer == mixer, tr(mixer.key())).clicked() {
+                                action = Some(Command::SetMixer(mixer));
+                            }
+                        }
+                        ui.label(tr("audio.stereo")).on_hover_text(tr("audio.stereo_hint"));
+                        for mode in StereoMode::ALL {
+                            if ui.radio(config.stereo == mode, tr(mode.key())).clicked() {
+                                action = Some(Command::SetStereoMode(mode));
+                            }
+                        }
+                        ui.separator();
+                        ui.label(tr("audio.channels"));
+                        egui::Grid::new("channel_mix").show(ui, |ui| {
+                            for channel in AudioChannel::ALL {
+                                ui.label(tr(channel_key(channel)));
+                                if ui.selectable_label(mix.muted(channel), tr("audio.mute_channel")).clicked() {
+                                    action = Some(Command::ToggleChannelMute(channel));
+                                }
+                                if ui.selectable_label(mix.soloed(channel), tr("audio.solo_channel")).clicked() {
+                                    action = Some(Command::ToggleChannelSolo(channel));
+                                }
+                                if config.stereo == StereoMode::Custom {
+                                    let mut pan = config.channel_pan.pan(channel) * 100.0;
+                                    let slider = egui::Slider::new(&mut pan, -100.0..=100.0).integer().text(tr("audio.pan"));
+                                    if ui.add(slider).on_hover_text(tr("audio.pan_hint")).changed() {
+                                        action = Some(Command::SetChannelPan(channel, pan / 100.0));
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.menu_button(tr("menu.power_on"), |ui| {
+                        if let Some(power_on) = power_on_menu(ui, config.power_on) {
+                            action = Some(Command::SetPowerOnState(power_on));
+                        }
+                    });
+                    ui.menu_button(tr("menu.background"), |ui| {
+                        ui.label(tr("background.hint"));
+                        for mode in BackgroundMode::ALL {
+                            if ui.radio(config.background == mode, tr(mode.key())).clicked() {
+                                action = Some(Command::SetBackgroundMode(mode));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    if ui.button(tr("menu.input")).clicked() {
+                        action = Some(Command::ShowInput);
+                        ui.close_menu();
+                    }
+                    if ui.button(tr("menu.hotkeys")).clicked() {
+                        action = Some(Command::ShowHotkeys);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    ui.label(tr("menu.ui_scale"));
+                    // Rescaling the UI while the slider is being dragged moves the slider out from under
+                    // the cursor, so keep the in-progress value aside and only apply it on release
+                    let scale_id = egui::Id::new("ui_scale_slider");
+                    let mut scale = ui.data(|d| d.get_temp(scale_id)).unwrap_or(config.ui_scale);
+                    let slider = egui::Slider::new(&mut scale, Config::UI_SCALE_RANGE).step_by(0.05);
+                    let response = ui.add(slider);
+                    if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                        ui.data_mut(|d| d.remove::<f32>(scale_id));
+                        action = Some(Command::SetUiScale(scale));
+                    } else if response.dragged() {
+                        ui.data_mut(|d| d.insert_temp(scale_id, scale));
+                    }
+                });
+
+                // Help Tab
+                ui.menu_button(tr("menu.help"), |ui| {
+                    let console = egui::Button::new(tr("menu.console")).shortcut_text("`");
+                    if ui.add(console).clicked() {
+                        action = Some(Command::ToggleConsole);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(tr("menu.about")).clicked() {
+                        action = Some(Command::About);
+                        ui.close_menu();
+                    }
+                });
+
+                if !unimplemented.is_empty() {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let details = std::iter::once(tr("menu.unimplemented").to_string())
+                            .chain(unimplemented.iter().map(|feature| format!("• {}", feature)))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        ui.label(egui::RichText::new("⚠").color(ui.visuals().warn_fg_color)).on_hover_text(details);
+                    });
+                }
+            });
+        });
+
+    action
+}
+
+/// The power on settings, returning them changed if they were
+fn power_on_menu(ui: &mut egui::Ui, mut power_on: PowerOnState) -> Option<PowerOnState> {
+    let before = power_on;
+    ui.label(tr("power_on.hint"));
+    ui.separator();
+    ui.label(tr("power_on.ram"));
+    let seed = match power_on.ram {
+        RamFill::Random(seed) => seed,
+        _ => 0,
+    };
+    for fill in [RamFill::Pattern, RamFill::Zeros, RamFill::Ones, RamFill::Random(seed)] {
+        if ui.radio(power_on.ram.key() == fill.key(), tr(fill.key())).clicked() {
+            power_on.ram = fill;
+        }
+    }
+    if let RamFill::Random(seed) = &mut power_on.ram {
+        ui.horizontal(|ui| {
+            ui.label(tr("power_on.seed"));
+            ui.add(egui::DragValue::new(seed));
+        });
+    }
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label(tr("power_on.open_bus"));
+        ui.add(egui::DragValue::new(&mut power_on.open_bus).hexadecimal(2, false, true).prefix("$"));
+    });
+    let mut five_step = power_on.frame_counter & 0x80 != 0;
+    let mut irq_inhibit = power_on.frame_counter & 0x40 != 0;
+    ui.checkbox(&mut five_step, tr("power_on.five_step"));
+    ui.checkbox(&mut irq_inhibit, tr("power_on.irq_inhibit"));
+    power_on.frame_counter = if five_step { 0x80 } else { 0 } | if irq_inhibit { 0x40 } else { 0 };
+    (power_on != before).then_some(power_on)
+}
+
+/// Draw the about window, if active
+pub fn show_about_window(ctx: &egui::Context, open: &mut bool) {
+    egui::Window::new(tr("about.title"))
+        .id(egui::Id::new("about_window"))
+        .open(open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(tr("about.created_by"));
+            });
+        });
+}
+
+/// Shown in place of the game until a ROM's loaded, offering the built in demos so there's something to
+/// play straight away
+pub fn show_welcome(ctx: &egui::Context) -> Option<Command> {
+    let mut action = None;
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 3.0);
+            ui.label(tr("demos.welcome"));
+            for (index, demo) in DEMOS.iter().enumerate() {
+                if ui.button(demo.name).clicked() {
+                    action = Some(Command::PlayDemo(index));
+                }
+            }
+        });
+    });
+    action
+}
+
+fn channel_key(channel: AudioChannel) -> &'static str {
+    match channel {
+        AudioChannel::Pulse1 => "channel.pulse1",
+        AudioChannel::Pulse2 => "channel.pulse2",
+        AudioChannel::Triangle => "channel.triangle",
+        AudioChannel::Noise => "channel.noise",
+        AudioChannel::Dmc => "channel.dmc",
+        AudioChannel::Expansion => "channel.expansion",
+    }
+}
@@ -0,0 +1,95 @@
+//! Input movies in FCEUX's FM2 format, for playing recorded input back into the console frame by frame.
+//!
+//! An FM2 file is a header of `key value` lines followed by one line per frame, e.g. `|0|R......A|........||`:
+//! commands first (1 to press reset, 2 to power cycle), then each controller's buttons in `RLDUTSBA`
+//! order, with `.` or a space for a button that isn't held. Only text movies are read, not binary ones.
+
+use crate::nes::Nes;
+
+/// Commands in the first field of a frame's line
+const SOFT_RESET: u32 = 1;
+const HARD_RESET: u32 = 2;
+
+/// What happens on one frame of a movie
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MovieFrame {
+  /// Buttons held on each controller, in the same bits as the controller's shift register
+  pub controllers: [u8; 2],
+  pub reset: bool,
+  pub power: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Movie {
+  pub frames: Vec<MovieFrame>,
+}
+
+impl Movie {
+  pub fn from_fm2(text: &str) -> Result<Self, String> {
+    let mut frames = vec![];
+    for (number, line) in text.lines().enumerate() {
+      let line = line.trim_end_matches('\r');
+      let Some(fields) = line.strip_prefix('|') else {
+        if line.trim() == "binary 1" {
+          return Err("Binary FM2 movies aren't supported, only text ones".to_string());
+        }
+        continue;
+      };
+      let mut fields = fields.split('|');
+      let commands = fields.next().unwrap_or_default().trim();
+      let commands = if commands.is_empty() {
+        0
+      } else {
+        commands.parse::<u32>().map_err(|_| format!("Line {}: invalid commands {:?}", number + 1, commands))?
+      };
+      let mut controllers = [0; 2];
+      for controller in &mut controllers {
+        *controller = buttons(fields.next().unwrap_or_default())
+          .ok_or(format!("Line {}: a controller needs 8 buttons, RLDUTSBA", number + 1))?;
+      }
+      frames.push(MovieFrame {
+        controllers,
+        reset: commands & SOFT_RESET != 0,
+        power: commands & HARD_RESET != 0,
+      });
+    }
+    Ok(Movie { frames })
+  }
+
+  pub fn len(&self) -> usize {
+    self.frames.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.frames.is_empty()
+  }
+
+  /// Run one frame with the movie's input for `frame`, or nothing held once the movie's over
+  pub fn play_frame(&self, nes: &mut Nes, frame: usize) {
+    let input = self.frames.get(frame).copied().unwrap_or_default();
+    if input.power {
+      nes.power_on();
+    } else if input.reset {
+      nes.reset();
+    }
+    for (index, buttons) in input.controllers.into_iter().enumerate() {
+      nes.update_controller(index, buttons);
+    }
+    nes.run_frame();
+  }
+}
+
+/// A controller's field, `RLDUTSBA` with `.` or a space for each button that isn't held, as the bits the
+/// controller shifts out. An empty field is a port with nothing plugged in.
+fn buttons(field: &str) -> Option<u8> {
+  if field.is_empty() {
+    return Some(0);
+  }
+  if field.chars().count() != 8 {
+    return None;
+  }
+  // Right is the lowest bit and A the highest, the same order the field's written in
+  Some(field.chars().enumerate().fold(0, |buttons, (bit, button)| {
+    if button == '.' || button == ' ' { buttons } else { buttons | 1 << bit }
+  }))
+}
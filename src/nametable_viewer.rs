@@ -0,0 +1,123 @@
+use eframe::egui;
+
+use crate::i18n::tr;
+use crate::nes::Nes;
+use crate::ppu::{ScrollSample, NAMETABLES_HEIGHT, NAMETABLES_WIDTH};
+
+/// Width of the screen in the nametables, which is how wide each line's scroll window is
+const SCREEN_WIDTH: usize = 256;
+
+const SCROLL_FILL: egui::Color32 = egui::Color32::from_rgba_premultiplied(60, 12, 12, 60);
+const SCROLL_EDGE: egui::Color32 = egui::Color32::from_rgb(255, 60, 60);
+const DIVIDER_COLOR: egui::Color32 = egui::Color32::from_gray(90);
+
+/// A run of `SCREEN_WIDTH` pixels `height` rows tall starting at `x`, `y` in the nametables, wrapping
+/// around the right edge back to the left as the PPU does
+fn paint_span(painter: &egui::Painter, rect: egui::Rect, (x, y): (usize, usize), height: f32, color: egui::Color32) {
+    let first = SCREEN_WIDTH.min(NAMETABLES_WIDTH - x);
+    let runs = [(x, first), (0, SCREEN_WIDTH - first)];
+    for (start, width) in runs.into_iter().filter(|(_, width)| *width > 0) {
+        let min = rect.left_top() + egui::vec2(start as f32, y as f32);
+        painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(width as f32, height)), 0.0, color);
+    }
+}
+
+/// Shade what each line of the last frame showed, outlining it so splits stand out as separate boxes
+fn paint_scroll(painter: &egui::Painter, rect: egui::Rect, samples: &[ScrollSample]) {
+    let mut previous: Option<(&ScrollSample, (usize, usize))> = None;
+    for sample in samples {
+        let origin = sample.origin();
+        paint_span(painter, rect, origin, 1.0, SCROLL_FILL);
+        for edge in [origin.0, (origin.0 + SCREEN_WIDTH - 1) % NAMETABLES_WIDTH] {
+            let min = rect.left_top() + egui::vec2(edge as f32, origin.1 as f32);
+            painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(1.0, 1.0)), 0.0, SCROLL_EDGE);
+        }
+
+        // A line that doesn't carry on from the one above it starts a new box, closing the last one
+        let continues = previous.is_some_and(|(before, (x, y))| {
+            before.scanline + 1 == sample.scanline && x == origin.0 && (y + 1) % NAMETABLES_HEIGHT == origin.1
+        });
+        if !continues {
+            paint_span(painter, rect, origin, 1.0, SCROLL_EDGE);
+            if let Some((_, end)) = previous {
+                paint_span(painter, rect, end, 1.0, SCROLL_EDGE);
+            }
+        }
+        previous = Some((sample, origin));
+    }
+    if let Some((_, end)) = previous {
+        paint_span(painter, rect, end, 1.0, SCROLL_EDGE);
+    }
+}
+
+/// Window showing all four nametables as the background would draw them, with what each line of the
+/// last frame scrolled to drawn over the top, for working out split screens and scrolling bugs
+pub struct NametableViewer {
+    pub open: bool,
+    show_scroll: bool,
+    texture: Option<egui::TextureHandle>,
+}
+
+impl Default for NametableViewer {
+    fn default() -> Self {
+        Self {
+            open: false,
+            show_scroll: true,
+            texture: None,
+        }
+    }
+}
+
+impl NametableViewer {
+    pub fn show(&mut self, ctx: &egui::Context, nes: &mut Nes) {
+        let mut open = self.open;
+        egui::Window::new(tr("nametable_viewer.title"))
+            .id(egui::Id::new("nametable_viewer_window"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut self.show_scroll, tr("nametable_viewer.scroll"));
+
+                let image = egui::ColorImage::from_rgb([NAMETABLES_WIDTH, NAMETABLES_HEIGHT], &nes.nametables_image());
+                let texture = match &mut self.texture {
+                    Some(texture) => {
+                        texture.set(image, egui::TextureOptions::NEAREST);
+                        texture.id()
+                    },
+                    None => {
+                        let texture = ctx.load_texture("Nametables", image, egui::TextureOptions::NEAREST);
+                        let id = texture.id();
+                        self.texture = Some(texture);
+                        id
+                    },
+                };
+
+                let size = egui::vec2(NAMETABLES_WIDTH as f32, NAMETABLES_HEIGHT as f32);
+                let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+                let rect = response.rect;
+                let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                painter.image(texture, rect, uv, egui::Color32::WHITE);
+                painter.hline(rect.x_range(), rect.center().y, (1.0, DIVIDER_COLOR));
+                painter.vline(rect.center().x, rect.y_range(), (1.0, DIVIDER_COLOR));
+
+                if self.show_scroll {
+                    paint_scroll(&painter, rect, nes.frame_scroll().unwrap_or_default());
+                }
+
+                let Some(pointer) = response.hover_pos() else {
+                    return;
+                };
+                let offset = pointer - rect.left_top();
+                let (x, y) = ((offset.x as usize).min(NAMETABLES_WIDTH - 1), (offset.y as usize).min(NAMETABLES_HEIGHT - 1));
+                let table = (x / 256) as u16 + (y / 240) as u16 * 2;
+                let (tile_x, tile_y) = ((x % 256 / 8) as u16, (y % 240 / 8) as u16);
+                let address = 0x2000 + table * 0x400 + tile_y * 32 + tile_x;
+                let tile = nes.bus.ppu_peek(address);
+                response.on_hover_ui_at_pointer(|ui| {
+                    ui.label(format!("{} ${:04X} = ${:02X}", tr("nametable_viewer.tile"), address, tile));
+                    ui.label(format!("X {}, Y {}", x, y));
+                });
+            });
+        self.open = open;
+    }
+}
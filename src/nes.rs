@@ -0,0 +1,938 @@
+use crate::apu::{ChannelMix, ChannelPan, ChannelScope, MixerMode};
+use crate::condition::{ConditionEvent, ConditionId, Conditions};
+use crate::bus::{Bus, BusLike, Event, IrqSource, PowerOnState};
+use crate::cartridge::{Cartridge, CartridgeState};
+use crate::cheat::Cheat;
+use crate::code_profile::CodeProfile;
+use crate::clock_audit::{ClockAudit, ClockSnapshot, ClockViolation};
+use crate::cpu::NES6502;
+use crate::frame_skip::{FrameSkip, FrameSkipper};
+use crate::mapper::PrgLocation;
+use crate::palette::Palette;
+use crate::ppu::{Region, ScrollSample, NAMETABLES_HEIGHT, NAMETABLES_WIDTH};
+use crate::profile::{Component, Laps, Profile};
+use crate::video::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::watchpoint::{Access, AddressSpace, WatchHit, WatchedAccess, Watchpoint};
+
+/// PPU cycles in one full frame (341 dots x 262 scanlines)
+pub const CYCLES_PER_FRAME: u32 = 341 * 262;
+
+/// NTSC PPU clock rate (21.477272 MHz master clock / 4)
+pub const CYCLES_PER_SECOND: f64 = 5_369_318.0;
+
+/// Frames per second an NTSC console draws
+pub const NTSC_FRAME_RATE: f64 = 60.0988;
+
+/// How many raw APU samples get averaged down into one output sample.
+/// The APU is sampled every PPU cycle, so this brings a frame's worth down to ~48kHz.
+pub const SAMPLES_PER_OUTPUT_SAMPLE: usize = 112;
+
+/// Output samples in one frame's worth of audio
+pub const OUTPUT_SAMPLES_PER_FRAME: usize = CYCLES_PER_FRAME as usize / SAMPLES_PER_OUTPUT_SAMPLE;
+
+/// Stereo audio comes as a left sample then a right one, raw and downsampled alike
+pub const AUDIO_CHANNELS: usize = 2;
+
+/// Where [`Nes::run_until`] runs the console to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunTarget {
+  /// The start of the next instruction
+  Instruction,
+  /// Dot 0 of the next scanline
+  Scanline,
+  /// The end of the frame being drawn
+  Frame,
+  /// Just after the PPU sets the vblank flag, on dot 1 of scanline 241
+  VBlank,
+  /// The PPU about to draw this dot of this scanline, with -1 the pre-render scanline
+  Position(i16, u16),
+}
+
+/// Average raw APU samples down to the output rate, `SAMPLES_PER_OUTPUT_SAMPLE` at a time.
+/// A partial chunk at the end is averaged on its own, so callers should only pass whole ones.
+pub fn downsample(raw: &[f32]) -> Vec<f32> {
+  raw
+    .chunks(SAMPLES_PER_OUTPUT_SAMPLE)
+    .map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32)
+    .collect()
+}
+
+/// `downsample` for stereo audio, averaging each side separately
+pub fn downsample_stereo(raw: &[f32]) -> Vec<f32> {
+  raw
+    .chunks(SAMPLES_PER_OUTPUT_SAMPLE * AUDIO_CHANNELS)
+    .flat_map(|chunk| {
+      let frames = (chunk.len() / AUDIO_CHANNELS) as f32;
+      let left = chunk.iter().step_by(AUDIO_CHANNELS).sum::<f32>() / frames;
+      let right = chunk.iter().skip(1).step_by(AUDIO_CHANNELS).sum::<f32>() / frames;
+      [left, right]
+    })
+    .collect()
+}
+
+/// A frame's worth of output samples fading from `from` to silence, for frames that aren't being run.
+/// Cutting straight to silence would click.
+pub fn fade_out(from: f32) -> Vec<f32> {
+  (0..OUTPUT_SAMPLES_PER_FRAME)
+    .map(|i| from * (1.0 - i as f32 / OUTPUT_SAMPLES_PER_FRAME as f32))
+    .collect()
+}
+
+/// `fade_out` for stereo audio, from a left and right sample
+pub fn fade_out_stereo([left, right]: [f32; 2]) -> Vec<f32> {
+  fade_out(left).into_iter().zip(fade_out(right)).flat_map(|(left, right)| [left, right]).collect()
+}
+
+/// A snapshot of everything in the console that changes while it runs.
+///
+/// The ROM itself isn't included, so a state can only be loaded into a console running the same game.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SaveState {
+  /// The bus and the devices on it, without the cartridge
+  bus: Bus,
+  cpu: NES6502,
+  cartridge: Option<CartridgeState>,
+  frame: Vec<u8>,
+  frame_count: u64,
+}
+
+#[cfg(feature = "serde")]
+impl SaveState {
+  /// The state as bytes, for keeping outside the emulator
+  pub fn to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+    serde_json::to_vec(self)
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+    serde_json::from_slice(bytes)
+  }
+}
+
+/// The whole console, ready to be stepped by a frontend. The CPU is the only device outside the bus,
+/// which owns everything else, so the console can be moved to another thread as a whole.
+pub struct Nes {
+  pub cpu: NES6502,
+  pub bus: Bus,
+  /// The last frame the PPU finished drawing
+  frame: Vec<u8>,
+  /// The frame drawn before it, for blending the two together
+  previous_frame: Vec<u8>,
+  frame_ready: bool,
+  /// Frames completed since the cartridge was inserted, so the first one drawn is frame 1
+  frame_count: u64,
+  /// The last audio sample handed out on each side, so pausing can fade out from it
+  last_sample: [f32; 2],
+  /// CPU addresses to stop at before executing, and the one we're currently stopped at
+  breakpoints: Vec<u16>,
+  breakpoint_hit: Option<u16>,
+  /// Memory accesses to stop at, and the one that stopped us. Watchpoints stop emulation the same way
+  /// breakpoints do, so `breakpoint_hit` is set as well.
+  watchpoints: Vec<Watchpoint>,
+  watch_hit: Option<WatchHit>,
+  /// Where the instruction the CPU's running started, to say which one set a watchpoint off
+  instruction_pc: u16,
+  /// Checked every cycle in debug builds to catch timing regressions
+  clock_audit: ClockAudit,
+  /// Time spent in each component, while profiling
+  profile: Option<Profile>,
+  /// Cycles spent on each instruction, while the code profiler's running
+  code_profile: Option<CodeProfile>,
+  /// Recent levels of each APU channel, while something's drawing them
+  scope: Option<ChannelScope>,
+  /// The register writes and IRQs of the last frame finished, while the event log's being kept
+  events: Option<Vec<Event>>,
+  /// The scroll at the start of each rendered line of the last frame finished, while it's being sampled
+  scroll: Option<Vec<ScrollSample>>,
+  /// Which frames the PPU draws
+  frame_skip: FrameSkipper,
+  /// Memory conditions checked at the end of each frame
+  conditions: Conditions,
+}
+
+impl Default for Nes {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Nes {
+  pub fn new() -> Self {
+    Self {
+      cpu: NES6502::new(),
+      bus: Bus::new(),
+      frame: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 3],
+      previous_frame: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 3],
+      frame_ready: false,
+      frame_count: 0,
+      last_sample: [0.0; 2],
+      breakpoints: vec![],
+      breakpoint_hit: None,
+      watchpoints: vec![],
+      watch_hit: None,
+      instruction_pc: 0,
+      clock_audit: ClockAudit::new(),
+      profile: None,
+      code_profile: None,
+      scope: None,
+      events: None,
+      scroll: None,
+      frame_skip: FrameSkipper::default(),
+      conditions: Conditions::default(),
+    }
+  }
+
+  pub fn rom_loaded(&self) -> bool {
+    self.bus.cartridge.is_some()
+  }
+
+  /// Mapper features the game has used that the emulator ignores, see `Mapper::unimplemented_write`
+  pub fn unimplemented_mapper_features(&self) -> Vec<&'static str> {
+    self.bus.cartridge.as_ref().map(|cartridge| cartridge.unimplemented.clone()).unwrap_or_default()
+  }
+
+  /// The cartridge's PRG RAM if it's battery backed, to keep the game's saves from one session to the next
+  pub fn battery_ram(&self) -> Option<Vec<u8>> {
+    let cartridge = self.bus.cartridge.as_ref()?;
+    cartridge.has_battery().then(|| cartridge.ram.clone())
+  }
+
+  /// Put back PRG RAM kept from [`Nes::battery_ram`]. Whatever's past the end of `ram` is left as it
+  /// is, and nothing happens if the cartridge doesn't have a battery.
+  pub fn load_battery_ram(&mut self, ram: &[u8]) {
+    let Some(cartridge) = &mut self.bus.cartridge else {
+      return;
+    };
+    if cartridge.has_battery() {
+      let len = ram.len().min(cartridge.ram.len());
+      cartridge.ram[..len].copy_from_slice(&ram[..len]);
+    }
+  }
+
+  /// Insert a cartridge and switch the console on so it starts running it
+  pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+    self.bus.insert_cartridge(cartridge);
+    // Whatever was frozen meant something to the last game, not this one
+    self.bus.set_frozen(vec![]);
+    // A freshly loaded cartridge's mapper is already as it is at power on
+    self.power_on_console();
+  }
+
+  /// Switch the console off and on again. RAM, every chip and the mapper start from scratch, and the
+  /// frame count goes back to 0. Does nothing without a cartridge, there'd be no reset vector to start from.
+  pub fn power_on(&mut self) {
+    let Some(cartridge) = &mut self.bus.cartridge else {
+      return;
+    };
+    cartridge.power_on(self.bus.power_on_state.ram);
+    self.power_on_console();
+  }
+
+  /// Power on everything but the cartridge
+  fn power_on_console(&mut self) {
+    self.bus.power_on();
+    self.bus.ppu.power_on();
+    self.bus.apu.power_on();
+    if self.bus.power_on_state.frame_counter != 0 {
+      self.bus.apu.cpu_write(0x4017, self.bus.power_on_state.frame_counter);
+    }
+    self.cpu.reset(&mut self.bus);
+    self.frame_ready = false;
+    self.frame_count = 0;
+    self.breakpoint_hit = None;
+    self.watch_hit = None;
+  }
+
+  /// Press the reset button. The CPU starts again from the reset vector and the APU goes quiet, while
+  /// RAM and the mapper keep everything the game left in them. Does nothing without a cartridge.
+  pub fn reset(&mut self) {
+    if !self.rom_loaded() {
+      return;
+    }
+    self.bus.stop_dma();
+    self.bus.ppu.reset();
+    self.bus.apu.reset();
+    self.cpu.soft_reset(&mut self.bus);
+    self.frame_ready = false;
+    self.breakpoint_hit = None;
+    self.watch_hit = None;
+  }
+
+  /// Swap in another cartridge without resetting the console, e.g. to change disks or step through a
+  /// multicart's games. With `keep_ram` the new cartridge takes over the old one's PRG RAM and CHR RAM,
+  /// as if they lived on an adapter both plug into, otherwise it starts with them blank.
+  pub fn swap_cartridge(&mut self, mut cartridge: Cartridge, keep_ram: bool) {
+    let Some(old) = self.bus.cartridge.take() else {
+      self.insert_cartridge(cartridge);
+      return;
+    };
+    if keep_ram {
+      cartridge.ram = old.ram;
+    } else {
+      self.bus.ppu.clear_chr_ram();
+    }
+
+    // Inserting picks the region from the new header, but the console itself hasn't changed
+    let region = self.bus.ppu.region();
+    self.bus.insert_cartridge(cartridge);
+    self.bus.ppu.set_region(region);
+  }
+
+  /// Capture the current state of the console.
+  ///
+  /// Safe to call on any PPU cycle, including partway through an instruction or an OAM DMA,
+  /// since the CPU's remaining cycles and the bus's DMA progress are part of the snapshot.
+  pub fn save_state(&self) -> SaveState {
+    // Cloning the cartridge shares its ROM rather than copying it, then what changes is kept on its own
+    let mut bus = self.bus.clone();
+    let cartridge = bus.cartridge.take().map(|cartridge| cartridge.state());
+    SaveState {
+      bus,
+      cpu: self.cpu.clone(),
+      cartridge,
+      frame: self.frame.clone(),
+      frame_count: self.frame_count,
+    }
+  }
+
+  /// Restore a state previously captured with [`Nes::save_state`]
+  pub fn load_state(&mut self, state: &SaveState) {
+    let mut bus = state.bus.clone();
+    bus.cartridge = self.bus.cartridge.take();
+    if let (Some(cartridge), Some(saved_cartridge)) = (&mut bus.cartridge, &state.cartridge) {
+      cartridge.load_state(saved_cartridge);
+    }
+    // Cheats and frozen addresses are chosen by the user rather than part of the machine, so keep the current ones
+    bus.set_cheats(self.bus.cheats().to_vec());
+    bus.set_frozen(self.bus.frozen().to_vec());
+    bus.power_on_state = self.bus.power_on_state;
+    // The state's bus may have been logging partway through its frame, which doesn't carry over
+    bus.set_event_logging(false);
+    bus.set_event_logging(self.bus.event_logging());
+    bus.ppu.set_scroll_logging(self.bus.ppu.scroll_logging());
+    // The palette is a display preference rather than part of the machine, so keep the current one
+    bus.ppu.set_colors(self.bus.ppu.colors());
+    // Likewise the channels muted or soloed, and how the triangle's played
+    bus.apu.mix = self.bus.apu.mix;
+    bus.apu.ultrasonic_triangle = self.bus.apu.ultrasonic_triangle;
+    bus.apu.reduce_pulse_popping = self.bus.apu.reduce_pulse_popping;
+    bus.apu.mixer = self.bus.apu.mixer;
+    // Watchpoints are the user's too, and a state never has any
+    bus.set_watchpoints(self.watchpoints.clone());
+    bus.ppu.set_watchpoints(self.watchpoints.clone());
+    self.bus.replace(bus);
+    self.cpu = state.cpu.clone();
+    self.frame = state.frame.clone();
+    // Nothing was drawn before it as far as blending goes, rather than a frame from some other time
+    self.previous_frame = state.frame.clone();
+    self.frame_count = state.frame_count;
+  }
+
+  /// The first timing invariant broken since the console was created, if any.
+  /// Only checked in debug builds.
+  pub fn clock_violation(&self) -> Option<&ClockViolation> {
+    self.clock_audit.first_violation()
+  }
+
+  fn clock_snapshot(&self) -> ClockSnapshot {
+    let (scanline, dot) = self.bus.ppu.position();
+    ClockSnapshot {
+      global_cycles: self.bus.get_global_cycles(),
+      scanline,
+      dot,
+      cpu_cycles: self.cpu.total_cycles,
+      apu_cycles: self.bus.apu.total_cycles,
+      dma_active: self.bus.dma_active(),
+      pc: self.cpu.pc,
+    }
+  }
+
+  /// Advance the system by a single PPU cycle. This is the one system clock: every device, DMA and
+  /// interrupt is stepped from here, with the CPU handed the bus for the cycles it runs.
+  pub fn clock(&mut self) {
+    let audit_before = cfg!(debug_assertions).then(|| self.clock_snapshot());
+
+    let cycles = self.bus.get_global_cycles();
+
+    let mut laps = self.profile.is_some().then(Laps::start);
+    self.bus.clock_ppu();
+    self.lap(&mut laps, Component::Ppu);
+    if !self.watchpoints.is_empty() {
+      // Anything the PPU touched on its own was for rendering, not for an instruction
+      self.check_watchpoints(None);
+    }
+    if cycles.is_multiple_of(3) {
+      self.clock_cpu(&mut laps);
+      if !self.watchpoints.is_empty() {
+        self.check_watchpoints(Some(self.instruction_pc));
+      }
+    }
+    self.bus.set_global_cycles(cycles + 1);
+    self.bus.update_audio_output();
+    if let Some(scope) = &mut self.scope {
+      if (cycles as usize).is_multiple_of(SAMPLES_PER_OUTPUT_SAMPLE) {
+        scope.record(self.bus.apu.channel_levels());
+      }
+    }
+    self.lap(&mut laps, Component::Apu);
+
+    if self.bus.ppu.take_frame_complete() {
+      // A skipped frame leaves the last one drawn on screen
+      if !self.bus.ppu.skipping_drawing() {
+        std::mem::swap(&mut self.frame, &mut self.previous_frame);
+        self.frame.copy_from_slice(self.bus.ppu.screen());
+      }
+      let draw = self.frame_skip.draw_next();
+      self.bus.ppu.set_skip_drawing(!draw);
+      self.frame_ready = true;
+      self.frame_count += 1;
+      self.bus.apply_frozen();
+      if !self.conditions.is_empty() {
+        let bus = &self.bus;
+        self.conditions.check(self.frame_count, |address| bus.peek(address));
+      }
+      if self.events.is_some() {
+        self.events = Some(self.bus.take_events());
+      }
+      if self.scroll.is_some() {
+        self.scroll = Some(self.bus.ppu.take_scroll_log());
+      }
+      if let Some(profile) = &mut self.code_profile {
+        profile.end_frame();
+      }
+    }
+
+    if let Some(before) = audit_before {
+      let after = self.clock_snapshot();
+      self.clock_audit.check(&before, &after);
+    }
+  }
+
+  /// Everything that happens once per CPU cycle, every third PPU cycle
+  fn clock_cpu(&mut self, laps: &mut Option<Laps>) {
+    if let Some(cartridge) = &mut self.bus.cartridge {
+      cartridge.mapper.cpu_clock();
+      cartridge.mapper.audio_clock(1);
+    }
+    // The CPU's held up while a DMA has the bus
+    if self.bus.clock_dma() {
+      self.lap(laps, Component::Cpu);
+      return;
+    }
+
+    if self.cpu.cycles == 0 {
+      self.instruction_pc = self.cpu.pc;
+      if let Some(profile) = &mut self.code_profile {
+        let location = self.bus.cartridge.as_ref().and_then(|cartridge| cartridge.mapper.prg_location(self.cpu.pc));
+        profile.bank = location.map(|location| location.bank);
+      }
+    }
+    match &mut self.code_profile {
+      Some(profile) => self.cpu.step_hooked(&mut self.bus, Some(profile)),
+      None => self.cpu.step(&mut self.bus),
+    }
+    // Between instructions, check whether the next one is somewhere we should stop
+    if !self.breakpoints.is_empty() && self.cpu.cycles == 0 && self.breakpoints.contains(&self.cpu.pc) {
+      self.breakpoint_hit = Some(self.cpu.pc);
+    }
+    if !self.watchpoints.is_empty() {
+      let (cycles, pc) = (self.cpu.cycles, self.cpu.pc);
+      if cycles == 0 && self.watchpoints.iter().any(|watchpoint| watchpoint.matches(AddressSpace::Cpu, Access::Execute, pc)) {
+        let opcode = self.peek(pc);
+        self.stop_at_watchpoint(WatchedAccess { space: AddressSpace::Cpu, access: Access::Execute, address: pc, value: opcode }, Some(pc));
+      }
+    }
+    self.lap(laps, Component::Cpu);
+    self.bus.clock_apu(self.cpu.total_cycles);
+    self.lap(laps, Component::Apu);
+    self.update_irq_line();
+    self.lap(laps, Component::Cpu);
+  }
+
+  /// Stop if the bus or PPU saw an access set a watchpoint off, blaming the instruction at `pc`
+  fn check_watchpoints(&mut self, pc: Option<u16>) {
+    let bus_hit = self.bus.take_watch_hit();
+    let ppu_hit = self.bus.ppu.take_watch_hit();
+    if let Some(access) = bus_hit.or(ppu_hit) {
+      self.stop_at_watchpoint(access, pc);
+    }
+  }
+
+  fn stop_at_watchpoint(&mut self, access: WatchedAccess, pc: Option<u16>) {
+    if self.watch_hit.is_some() {
+      return;
+    }
+    let (scanline, dot) = self.bus.ppu.position();
+    self.watch_hit = Some(WatchHit { access, pc, scanline, dot });
+    self.breakpoint_hit = Some(pc.unwrap_or(self.cpu.pc));
+  }
+
+  /// Credit the time since the last lap to `component`, if profiling
+  fn lap(&mut self, laps: &mut Option<Laps>, component: Component) {
+    if let (Some(laps), Some(profile)) = (laps, &mut self.profile) {
+      laps.lap(profile, component);
+    }
+  }
+
+  /// Start timing each component as the console runs, from zero
+  pub fn start_profiling(&mut self) {
+    self.profile = Some(Profile::default());
+  }
+
+  /// Stop profiling, returning the time spent in each component since it started
+  pub fn stop_profiling(&mut self) -> Option<Profile> {
+    self.profile.take()
+  }
+
+  /// Start or stop counting the cycles spent on each instruction for the code profiler. Like the event
+  /// log it's off by default, and stopping throws away what's been counted.
+  pub fn set_code_profiling(&mut self, enabled: bool) {
+    match (enabled, &self.code_profile) {
+      (true, None) => self.code_profile = Some(CodeProfile::default()),
+      (false, Some(_)) => self.code_profile = None,
+      _ => {},
+    }
+  }
+
+  pub fn code_profile(&self) -> Option<&CodeProfile> {
+    self.code_profile.as_ref()
+  }
+
+  pub fn code_profile_mut(&mut self) -> Option<&mut CodeProfile> {
+    self.code_profile.as_mut()
+  }
+
+  /// Put each device's IRQ output on the bus, where the CPU polls it
+  fn update_irq_line(&mut self) {
+    let status = &self.bus.apu.registers.status;
+    let (frame_irq, dmc_irq) = (status.frame_interrupt, status.dmc_interrupt);
+    let mapper_irq = self.bus.cartridge.as_ref().is_some_and(|cartridge| cartridge.mapper.irq_state());
+    self.bus.set_irq(IrqSource::FrameCounter, frame_irq);
+    self.bus.set_irq(IrqSource::Dmc, dmc_irq);
+    self.bus.set_irq(IrqSource::Mapper, mapper_irq);
+  }
+
+  /// Run until the PPU finishes the frame it's currently drawing
+  pub fn run_frame(&mut self) {
+    if !self.rom_loaded() {
+      return;
+    }
+
+    self.frame_ready = false;
+    while !self.frame_ready && self.breakpoint_hit.is_none() {
+      self.clock();
+    }
+  }
+
+  /// Run `frames` whole frames, stopping early at a breakpoint
+  pub fn run_frames(&mut self, frames: u64) {
+    for _ in 0..frames {
+      if self.breakpoint_hit.is_some() {
+        break;
+      }
+      self.run_frame();
+    }
+  }
+
+  /// Run until frame `frame` has been drawn, and return exactly that frame.
+  ///
+  /// Returns `None` if that frame has already been replaced by a later one, or a breakpoint stops the
+  /// console before it's finished.
+  pub fn capture_frame(&mut self, frame: u64) -> Option<Vec<u8>> {
+    if !self.rom_loaded() || frame < self.frame_count {
+      return None;
+    }
+
+    while self.frame_count < frame && self.breakpoint_hit.is_none() {
+      self.clock();
+    }
+    (self.frame_count == frame && frame > 0).then(|| self.frame.clone())
+  }
+
+  /// Run one PPU cycle at a time until the console reaches `target`. Stops early at a breakpoint or
+  /// watchpoint, or if two frames go by without getting there, e.g. for a dot the odd frame skip jumps
+  /// over. Returns whether it got there.
+  pub fn run_until(&mut self, target: RunTarget) -> bool {
+    if !self.rom_loaded() {
+      return false;
+    }
+
+    let start_instruction = self.cpu.total_cycles;
+    let (start_scanline, _) = self.bus.ppu.position();
+    let start_frame = self.frame_count;
+    self.frame_ready = false;
+    while self.breakpoint_hit.is_none() && self.frame_count < start_frame + 2 {
+      self.clock();
+      let position = self.bus.ppu.position();
+      let reached = match target {
+        RunTarget::Instruction => self.cpu.total_cycles > start_instruction && self.cpu.cycles == 0,
+        RunTarget::Scanline => position.0 != start_scanline,
+        RunTarget::Frame => self.frame_ready,
+        RunTarget::VBlank => position == (241, 2),
+        RunTarget::Position(scanline, dot) => position == (scanline, dot),
+      };
+      if reached {
+        return true;
+      }
+    }
+    false
+  }
+
+  /// Whether an OAM DMA is holding the CPU up
+  pub fn dma_active(&self) -> bool {
+    self.bus.dma_active()
+  }
+
+  /// Number of frames completed since the cartridge was inserted
+  pub fn frame_count(&self) -> u64 {
+    self.frame_count
+  }
+
+  /// Run for a set number of PPU cycles, regardless of where that leaves the frame.
+  ///
+  /// Running in smaller slices like this lets a frontend feed in fresh input between them,
+  /// so the game sees whatever the host reported most recently when it strobes the controllers.
+  pub fn run_cycles(&mut self, cycles: u32) {
+    if !self.rom_loaded() {
+      return;
+    }
+
+    for _ in 0..cycles {
+      if self.breakpoint_hit.is_some() {
+        break;
+      }
+      self.clock();
+    }
+  }
+
+  /// Drain the audio generated since the last call, downsampled for output and mixed down to mono.
+  /// Samples that don't fill a whole output sample yet are kept for next time.
+  pub fn take_audio(&mut self) -> Vec<f32> {
+    self.take_stereo_audio()
+      .chunks_exact(AUDIO_CHANNELS)
+      .map(|frame| (frame[0] + frame[1]) / 2.0)
+      .collect()
+  }
+
+  /// `take_audio` with the left and right sides kept apart, one after the other
+  pub fn take_stereo_audio(&mut self) -> Vec<f32> {
+    let apu = &mut self.bus.apu;
+    let chunk = SAMPLES_PER_OUTPUT_SAMPLE * AUDIO_CHANNELS;
+    let whole_chunks = apu.output_buffer.len() - apu.output_buffer.len() % chunk;
+    let samples = downsample_stereo(&apu.output_buffer[..whole_chunks]);
+    apu.output_buffer.drain(..whole_chunks);
+    if let [.., left, right] = samples[..] {
+      self.last_sample = [left, right];
+    }
+    samples
+  }
+
+  /// Drain the raw APU samples generated since the last call, a left and a right one per PPU cycle,
+  /// for downsampling somewhere else. Use either this or `take_audio`, not both.
+  pub fn take_raw_audio(&mut self) -> Vec<f32> {
+    std::mem::take(&mut self.bus.apu.output_buffer)
+  }
+
+  /// A frame's worth of audio for a frame that isn't being run, e.g. while paused, so the output keeps
+  /// being fed at the usual rate. Goes with `take_audio`.
+  pub fn paused_audio(&mut self) -> Vec<f32> {
+    let [left, right] = std::mem::take(&mut self.last_sample);
+    fade_out((left + right) / 2.0)
+  }
+
+  /// `paused_audio` in stereo, going with `take_stereo_audio`
+  pub fn paused_stereo_audio(&mut self) -> Vec<f32> {
+    fade_out_stereo(std::mem::take(&mut self.last_sample))
+  }
+
+  /// Stop before the CPU executes the instruction at `address`
+  pub fn add_breakpoint(&mut self, address: u16) {
+    if !self.breakpoints.contains(&address) {
+      self.breakpoints.push(address);
+    }
+  }
+
+  pub fn clear_breakpoints(&mut self) {
+    self.breakpoints.clear();
+    self.breakpoint_hit = None;
+  }
+
+  /// The address of the breakpoint emulation is stopped at, if any.
+  /// Nothing runs until [`Nes::resume`] is called.
+  pub fn breakpoint_hit(&self) -> Option<u16> {
+    self.breakpoint_hit
+  }
+
+  pub fn resume(&mut self) {
+    self.breakpoint_hit = None;
+    self.watch_hit = None;
+  }
+
+  /// Stop when `watchpoint` sees an access it's watching for
+  pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+    if !self.watchpoints.contains(&watchpoint) {
+      self.watchpoints.push(watchpoint);
+      self.bus.set_watchpoints(self.watchpoints.clone());
+      self.bus.ppu.set_watchpoints(self.watchpoints.clone());
+    }
+  }
+
+  pub fn clear_watchpoints(&mut self) {
+    self.watchpoints.clear();
+    self.bus.set_watchpoints(vec![]);
+    self.bus.ppu.set_watchpoints(vec![]);
+    if self.watch_hit.take().is_some() {
+      self.breakpoint_hit = None;
+    }
+  }
+
+  pub fn watchpoints(&self) -> &[Watchpoint] {
+    &self.watchpoints
+  }
+
+  /// The watchpoint access emulation is stopped at, if it was one rather than a breakpoint
+  pub fn watch_hit(&self) -> Option<WatchHit> {
+    self.watch_hit
+  }
+
+  /// Read CPU memory without any of the side effects a real read would have
+  pub fn peek(&self, address: u16) -> u8 {
+    self.bus.peek(address)
+  }
+
+  /// Write to RAM without any of the side effects a real write would have
+  pub fn poke(&mut self, address: u16, value: u8) {
+    self.bus.poke(address, value);
+  }
+
+  /// Hold `address` at `value`, writing it back at the end of every frame
+  pub fn freeze(&mut self, address: u16, value: u8) {
+    let mut frozen = self.bus.frozen().to_vec();
+    frozen.retain(|(frozen_address, _)| *frozen_address != address);
+    frozen.push((address, value));
+    self.bus.set_frozen(frozen);
+    self.poke(address, value);
+  }
+
+  pub fn unfreeze(&mut self, address: u16) {
+    let mut frozen = self.bus.frozen().to_vec();
+    frozen.retain(|(frozen_address, _)| *frozen_address != address);
+    self.bus.set_frozen(frozen);
+  }
+
+  pub fn is_frozen(&self, address: u16) -> bool {
+    self.bus.frozen().iter().any(|(frozen_address, _)| *frozen_address == address)
+  }
+
+  /// Fire an event at the end of any frame where `predicate`, given the byte at `address` last frame
+  /// and now, becomes true. Collect them with `take_condition_events`.
+  pub fn watch(&mut self, address: u16, predicate: impl Fn(u8, u8) -> bool + Send + 'static) -> ConditionId {
+    let current = self.peek(address);
+    self.conditions.watch(address, current, Box::new(predicate))
+  }
+
+  pub fn unwatch(&mut self, id: ConditionId) {
+    self.conditions.unwatch(id);
+  }
+
+  /// The conditions that fired since the last call, oldest first
+  pub fn take_condition_events(&mut self) -> Vec<ConditionEvent> {
+    self.conditions.take_events()
+  }
+
+  /// Which PRG ROM bank and offset a CPU address currently maps to, if it's in ROM
+  pub fn prg_location(&self, address: u16) -> Option<PrgLocation> {
+    self.bus.cartridge.as_ref()?.mapper.prg_location(address)
+  }
+
+  /// An address as shown in debugging tools, labelled with the ROM bank it's in so
+  /// bank switched code can be told apart, e.g. `$C123 (PRG bank 5 + $0123)`
+  pub fn describe_address(&self, address: u16) -> String {
+    match self.prg_location(address) {
+      Some(location) => format!("${:04X} ({})", address, location),
+      None => format!("${:04X}", address),
+    }
+  }
+
+  /// Why emulation's stopped as shown in the console, if it is
+  pub fn describe_stop(&self) -> Option<String> {
+    let address = self.breakpoint_hit?;
+    Some(match &self.watch_hit {
+      Some(hit) => self.describe_watch_hit(hit),
+      None => format!("Hit breakpoint at {}", self.describe_address(address)),
+    })
+  }
+
+  /// Where the console's got to as shown in the console, e.g. `scanline 12 dot 40, PC $C123`
+  pub fn describe_position(&self) -> String {
+    let (scanline, dot) = self.bus.ppu.position();
+    let pc = self.cpu.pc;
+    format!("scanline {} dot {}, PC {}", scanline, dot, self.describe_address(pc))
+  }
+
+  /// A watchpoint going off as shown in the console, e.g.
+  /// `Watchpoint: CPU write $0300 = $1F by $C123 (PRG bank 5 + $0123), scanline 12 dot 40`
+  pub fn describe_watch_hit(&self, hit: &WatchHit) -> String {
+    let space = match hit.access.space {
+      AddressSpace::Cpu => "CPU",
+      AddressSpace::Ppu => "PPU",
+    };
+    let by = match hit.pc {
+      Some(pc) => self.describe_address(pc),
+      None => "rendering".to_string(),
+    };
+    format!("Watchpoint: {} {} ${:04X} = ${:02X} by {}, scanline {} dot {}",
+      space, hit.access.access, hit.access.address, hit.access.value, by, hit.scanline, hit.dot)
+  }
+
+  /// The last complete frame, so a frontend never shows one that's half drawn
+  pub fn screen(&self) -> &[u8] {
+    &self.frame
+  }
+
+  /// The complete frame drawn before [`Nes::screen`], for frontends blending the two to smooth out
+  /// sprites that flicker every other frame
+  pub fn previous_screen(&self) -> &[u8] {
+    &self.previous_frame
+  }
+
+  /// A copy of the last complete frame, e.g. to keep hold of while the console runs on
+  pub fn get_screen(&self) -> Vec<u8> {
+    self.frame.clone()
+  }
+
+  /// XXH3 hash of the last complete frame, for checking what's drawn against a known good frame
+  /// without storing the whole thing
+  pub fn frame_hash(&self) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(&self.frame)
+  }
+
+  /// Wire the emphasis bits like `region`'s PPU, or the one for the region the cartridge was made for
+  /// if `None`. Only the colors change, the console keeps running at NTSC speed.
+  pub fn set_region(&mut self, region: Option<Region>) {
+    let from_header = self.bus.cartridge.as_ref().map(|cartridge| Region::from_header(&cartridge.header_info));
+    self.bus.ppu.set_region(region.or(from_header).unwrap_or_default());
+  }
+
+  /// Replace the cheats applied to CPU reads
+  pub fn set_cheats(&mut self, cheats: Vec<Cheat>) {
+    self.bus.set_cheats(cheats);
+  }
+
+  /// Switch the colors the picture is drawn with, from the next frame on
+  pub fn set_palette(&mut self, palette: Palette) {
+    self.bus.ppu.set_colors(palette);
+  }
+
+  /// Which APU channels are muted or soloed
+  pub fn channel_mix(&self) -> ChannelMix {
+    self.bus.apu.mix
+  }
+
+  /// Mute or solo APU channels, from the next sample on
+  pub fn set_channel_mix(&mut self, mix: ChannelMix) {
+    self.bus.apu.mix = mix;
+  }
+
+  /// Where each APU channel sits between the speakers
+  pub fn channel_pan(&self) -> ChannelPan {
+    self.bus.apu.pan
+  }
+
+  /// Spread the APU channels between the speakers, from the next sample on
+  pub fn set_channel_pan(&mut self, pan: ChannelPan) {
+    self.bus.apu.pan = pan;
+  }
+
+  /// How the console starts from the next time it's switched on
+  pub fn set_power_on_state(&mut self, state: PowerOnState) {
+    self.bus.power_on_state = state;
+  }
+
+  /// Let the triangle play at ultrasonic periods as on hardware, rather than holding it still to avoid pops
+  pub fn set_ultrasonic_triangle(&mut self, ultrasonic: bool) {
+    self.bus.apu.ultrasonic_triangle = ultrasonic;
+  }
+
+  /// Keep the pulses' duty cycles running through mid note period writes, rather than restarting them
+  /// with a click as on hardware
+  pub fn set_reduce_pulse_popping(&mut self, reduce: bool) {
+    self.bus.apu.reduce_pulse_popping = reduce;
+  }
+
+  /// Mix the channels accurately or cheaply, see [`MixerMode`]. Takes effect from the next sample.
+  pub fn set_mixer(&mut self, mixer: MixerMode) {
+    self.bus.apu.mixer = mixer;
+  }
+
+  /// Skip drawing some frames to save time, see [`FrameSkip`]. Takes effect from the next frame.
+  pub fn set_frame_skip(&mut self, frame_skip: FrameSkip) {
+    self.frame_skip.mode = frame_skip;
+  }
+
+  pub fn frame_skip(&self) -> FrameSkip {
+    self.frame_skip.mode
+  }
+
+  /// Tell the console whether emulation's falling behind real time, which `FrameSkip::Auto` skips
+  /// frames for. Frontends call this as they run, since only they know how late they are.
+  pub fn set_falling_behind(&mut self, behind: bool) {
+    self.frame_skip.behind = behind;
+  }
+
+  /// Start or stop recording each APU channel's level for an oscilloscope. It's off by default,
+  /// since it costs a little on every sample.
+  pub fn set_scope_enabled(&mut self, enabled: bool) {
+    match (enabled, &self.scope) {
+      (true, None) => self.scope = Some(ChannelScope::default()),
+      (false, Some(_)) => self.scope = None,
+      _ => {},
+    }
+  }
+
+  pub fn scope(&self) -> Option<&ChannelScope> {
+    self.scope.as_ref()
+  }
+
+  /// Start or stop logging register writes and IRQs for the event viewer. Like the scope, it's off by
+  /// default since it costs a little on every write.
+  pub fn set_event_logging(&mut self, enabled: bool) {
+    if enabled == self.events.is_some() {
+      return;
+    }
+    self.bus.set_event_logging(enabled);
+    self.events = enabled.then(Vec::new);
+  }
+
+  /// The register writes and IRQs of the last frame finished, oldest first, if they're being logged
+  pub fn frame_events(&self) -> Option<&[Event]> {
+    self.events.as_deref()
+  }
+
+  /// Start or stop sampling the scroll at the start of each line for the nametable viewer. Like the
+  /// event log, it's off by default.
+  pub fn set_scroll_logging(&mut self, enabled: bool) {
+    if enabled == self.scroll.is_some() {
+      return;
+    }
+    self.bus.ppu.set_scroll_logging(enabled);
+    self.scroll = enabled.then(Vec::new);
+  }
+
+  /// The scroll at the start of each rendered line of the last frame finished, if it's being sampled
+  pub fn frame_scroll(&self) -> Option<&[ScrollSample]> {
+    self.scroll.as_deref()
+  }
+
+  /// All four nametables as the PPU would draw them now, as packed RGB bytes
+  pub fn nametables_image(&mut self) -> Vec<u8> {
+    match &self.bus.cartridge {
+      Some(cartridge) => self.bus.ppu.render_nametables(cartridge),
+      None => vec![0; NAMETABLES_WIDTH * NAMETABLES_HEIGHT * 3],
+    }
+  }
+
+  pub fn update_controller(&mut self, controller_index: usize, value: u8) {
+    self.bus.update_controller(controller_index, value);
+  }
+}
@@ -0,0 +1,253 @@
+//! Two player netplay over UDP.
+//!
+//! Both players run the whole console and only swap controller inputs, so they stay in step as long
+//! as each frame is run with the same inputs on both sides. Inputs are delayed by a few frames to give
+//! them time to arrive, and if they're still late the frame waits for them. Every packet carries all
+//! the inputs the peer hasn't confirmed yet, so a lost packet is covered by the next one.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 7845;
+
+/// Frames between a button being pressed and the game seeing it, enough to cover a typical round trip
+pub const DEFAULT_INPUT_DELAY: u8 = 3;
+
+/// How long to go without hearing from the peer before giving up on them
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often to ask the host to let us in until they answer
+const HELLO_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Most inputs sent in one packet, if the peer is further behind than this they'll get the rest later
+const MAX_INPUTS_PER_PACKET: usize = 128;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Packet {
+    /// A player asking to join, with the SHA-256 of the ROM they have loaded
+    Hello { rom_hash: String },
+    /// The host letting them in, and the input delay both sides will use
+    Welcome { input_delay: u8 },
+    /// The host turning them away
+    Reject { reason: String },
+    /// Inputs for consecutive frames from `first_frame` on, and the first frame the sender is still missing
+    Inputs { ack: u64, first_frame: u64, inputs: Vec<u8> },
+}
+
+impl Packet {
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Packet::Hello { rom_hash } => [&[0], rom_hash.as_bytes()].concat(),
+            Packet::Welcome { input_delay } => vec![1, *input_delay],
+            Packet::Reject { reason } => [&[2], reason.as_bytes()].concat(),
+            Packet::Inputs { ack, first_frame, inputs } => {
+                [&[3], &ack.to_le_bytes()[..], &first_frame.to_le_bytes()[..], inputs].concat()
+            },
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Packet> {
+        let (&kind, body) = bytes.split_first()?;
+        match kind {
+            0 => Some(Packet::Hello { rom_hash: String::from_utf8(body.to_vec()).ok()? }),
+            1 => Some(Packet::Welcome { input_delay: *body.first()? }),
+            2 => Some(Packet::Reject { reason: String::from_utf8_lossy(body).to_string() }),
+            3 if body.len() >= 16 => Some(Packet::Inputs {
+                ack: u64::from_le_bytes(body[0..8].try_into().ok()?),
+                first_frame: u64::from_le_bytes(body[8..16].try_into().ok()?),
+                inputs: body[16..].to_vec(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    /// Hosting, and nobody has joined yet
+    Waiting,
+    /// Asked the host to join, and waiting to hear back
+    Connecting,
+    Connected,
+    /// The session is over, and why
+    Disconnected(String),
+}
+
+/// One end of a netplay session. The host is player 1 and whoever joins is player 2.
+pub struct Session {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+    is_host: bool,
+    rom_hash: String,
+    status: Status,
+    started: bool,
+    input_delay: u8,
+    /// The next frame to be run
+    frame: u64,
+    /// Our inputs the peer hasn't confirmed yet, and theirs we haven't run yet, by frame
+    local_inputs: BTreeMap<u64, u8>,
+    remote_inputs: BTreeMap<u64, u8>,
+    last_heard: Instant,
+    last_hello: Option<Instant>,
+}
+
+impl Session {
+    /// Start hosting a game of the ROM with hash `rom_hash`, for one other player to join
+    pub fn host(port: u16, rom_hash: &str) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        Self::new(socket, None, true, rom_hash, Status::Waiting)
+    }
+
+    /// Join the game hosted at `address`, which needs to be of the same ROM
+    pub fn join(address: &str, rom_hash: &str) -> io::Result<Self> {
+        let peer = address.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Couldn't find {}", address)))?;
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        Self::new(socket, Some(peer), false, rom_hash, Status::Connecting)
+    }
+
+    fn new(socket: UdpSocket, peer: Option<SocketAddr>, is_host: bool, rom_hash: &str, status: Status) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            peer,
+            is_host,
+            rom_hash: rom_hash.to_string(),
+            status,
+            started: false,
+            input_delay: DEFAULT_INPUT_DELAY,
+            frame: 0,
+            local_inputs: BTreeMap::new(),
+            remote_inputs: BTreeMap::new(),
+            last_heard: Instant::now(),
+            last_hello: None,
+        })
+    }
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// The port we're listening on, which is what the other player needs when hosting
+    pub fn local_port(&self) -> u16 {
+        self.socket.local_addr().map(|address| address.port()).unwrap_or_default()
+    }
+
+    /// Whether the session started since this was last called.
+    /// Both consoles need resetting at that point, so they start from the same state.
+    pub fn take_started(&mut self) -> bool {
+        std::mem::take(&mut self.started)
+    }
+
+    /// Call once per displayed frame with what our controller is pressing. Returns the player 1 and
+    /// player 2 controller states to run the next frame with, or `None` if it has to wait.
+    pub fn update(&mut self, local_input: u8) -> Option<[u8; 2]> {
+        self.receive();
+
+        if matches!(self.status, Status::Connecting | Status::Connected) && self.last_heard.elapsed() > TIMEOUT {
+            self.status = Status::Disconnected("The other player stopped responding".to_string());
+        }
+        match self.status {
+            Status::Connecting => {
+                if self.last_hello.is_none_or(|sent| sent.elapsed() > HELLO_INTERVAL) {
+                    self.send(&Packet::Hello { rom_hash: self.rom_hash.clone() });
+                    self.last_hello = Some(Instant::now());
+                }
+                return None;
+            },
+            Status::Connected => {},
+            _ => return None,
+        }
+
+        // Only one input per frame run, otherwise waiting on the peer would queue up a backlog of them
+        self.local_inputs.entry(self.frame + self.input_delay as u64).or_insert(local_input);
+        self.send_inputs();
+
+        let local = *self.local_inputs.get(&self.frame)?;
+        let remote = self.remote_inputs.remove(&self.frame)?;
+        self.frame += 1;
+        Some(if self.is_host { [local, remote] } else { [remote, local] })
+    }
+
+    fn receive(&mut self) {
+        let mut buffer = [0; 1024];
+        while let Ok((length, from)) = self.socket.recv_from(&mut buffer) {
+            let Some(packet) = Packet::decode(&buffer[..length]) else {
+                continue;
+            };
+            if self.peer.is_some_and(|peer| peer != from) {
+                continue;
+            }
+            self.last_heard = Instant::now();
+
+            match packet {
+                Packet::Hello { rom_hash } if self.is_host => {
+                    if rom_hash != self.rom_hash {
+                        let reason = "The host is playing a different ROM".to_string();
+                        let _ = self.socket.send_to(&Packet::Reject { reason }.encode(), from);
+                        continue;
+                    }
+                    // The welcome may have been lost, so answer every hello, but only start once
+                    self.peer = Some(from);
+                    self.send(&Packet::Welcome { input_delay: self.input_delay });
+                    if self.status == Status::Waiting {
+                        self.start();
+                    }
+                },
+                Packet::Welcome { input_delay } if self.status == Status::Connecting => {
+                    self.input_delay = input_delay;
+                    self.start();
+                },
+                Packet::Reject { reason } if self.status == Status::Connecting => {
+                    self.status = Status::Disconnected(reason);
+                },
+                Packet::Inputs { ack, first_frame, inputs } if self.status == Status::Connected => {
+                    // Everything before `ack` has made it, so there's no need to send it again
+                    self.local_inputs.retain(|frame, _| *frame >= ack.min(self.frame));
+                    for (frame, input) in (first_frame..).zip(inputs) {
+                        if frame >= self.frame {
+                            self.remote_inputs.insert(frame, input);
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn start(&mut self) {
+        self.status = Status::Connected;
+        self.started = true;
+        // Nobody could press anything in time for the first few frames
+        for frame in 0..self.input_delay as u64 {
+            self.local_inputs.insert(frame, 0);
+            self.remote_inputs.insert(frame, 0);
+        }
+    }
+
+    fn send_inputs(&self) {
+        let Some((&first_frame, _)) = self.local_inputs.first_key_value() else {
+            return;
+        };
+        let inputs = self.local_inputs.range(first_frame..)
+            .take_while(|(frame, _)| **frame < first_frame + MAX_INPUTS_PER_PACKET as u64)
+            .map(|(_, input)| *input)
+            .collect();
+        self.send(&Packet::Inputs { ack: self.first_missing_frame(), first_frame, inputs });
+    }
+
+    /// The earliest frame we still need the peer's input for
+    fn first_missing_frame(&self) -> u64 {
+        (self.frame..).find(|frame| !self.remote_inputs.contains_key(frame)).unwrap_or(self.frame)
+    }
+
+    fn send(&self, packet: &Packet) {
+        // Anything lost is sent again with the next packet, so there's nothing to do about errors here
+        if let Some(peer) = self.peer {
+            let _ = self.socket.send_to(&packet.encode(), peer);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use eframe::egui;
+
+use crate::i18n::tr;
+use crate::netplay::{Status, DEFAULT_PORT};
+
+/// What the user asked for from the netplay window
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetplayAction {
+    Host(u16),
+    Join(String),
+    Disconnect,
+}
+
+/// Window for hosting or joining a netplay session, and seeing how it's going
+pub struct NetplayWindow {
+    pub open: bool,
+    port: String,
+    address: String,
+}
+
+impl Default for NetplayWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            port: DEFAULT_PORT.to_string(),
+            address: format!("127.0.0.1:{}", DEFAULT_PORT),
+        }
+    }
+}
+
+impl NetplayWindow {
+    /// Draw the window, if open. `status` is `None` when there's no session.
+    pub fn show(&mut self, ctx: &egui::Context, status: Option<&Status>, rom_loaded: bool) -> Option<NetplayAction> {
+        let mut action = None;
+        let mut open = self.open;
+
+        egui::Window::new(tr("netplay.title"))
+            .id(egui::Id::new("netplay_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(status) = status {
+                    ui.label(match status {
+                        Status::Waiting => tr("netplay.waiting").to_string(),
+                        Status::Connecting => tr("netplay.connecting").to_string(),
+                        Status::Connected => tr("netplay.connected").to_string(),
+                        Status::Disconnected(reason) => reason.clone(),
+                    });
+                    if ui.button(tr("netplay.disconnect")).clicked() {
+                        action = Some(NetplayAction::Disconnect);
+                    }
+                    return;
+                }
+
+                if !rom_loaded {
+                    ui.label(tr("netplay.no_rom"));
+                    return;
+                }
+
+                egui::Grid::new("netplay_grid").show(ui, |ui| {
+                    ui.label(tr("netplay.port"));
+                    ui.add(egui::TextEdit::singleline(&mut self.port).desired_width(60.0));
+                    let port = self.port.trim().parse::<u16>();
+                    if ui.add_enabled(port.is_ok(), egui::Button::new(tr("netplay.host"))).clicked() {
+                        action = port.ok().map(NetplayAction::Host);
+                    }
+                    ui.end_row();
+
+                    ui.label(tr("netplay.address"));
+                    ui.add(egui::TextEdit::singleline(&mut self.address).desired_width(140.0));
+                    if ui.button(tr("netplay.join")).clicked() {
+                        action = Some(NetplayAction::Join(self.address.trim().to_string()));
+                    }
+                    ui.end_row();
+                });
+            });
+
+        self.open = open;
+        action
+    }
+}
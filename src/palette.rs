@@ -0,0 +1,107 @@
+/// Size of a .pal file with one RGB triple for each of the 64 colors
+pub const PAL_FILE_SIZE: usize = 64 * 3;
+
+/// Size of a .pal file that also has the 7 color emphasis variations of every color
+const PAL_FILE_SIZE_WITH_EMPHASIS: usize = PAL_FILE_SIZE * 8;
+
+/// The RGB colors the PPU's 64 color indices are displayed as.
+///
+/// There's no single right answer here, the console outputs a composite signal rather than RGB
+/// and every TV decodes it a little differently, so this is left up to the user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Palette {
+  #[cfg_attr(feature = "serde", serde(with = "crate::serde_arrays::big"))]
+  pub colors: [[u8; 3]; 64],
+}
+
+impl Default for Palette {
+  fn default() -> Self {
+    BuiltinPalette::default().palette()
+  }
+}
+
+impl Palette {
+  /// Read a .pal file, a flat list of RGB bytes.
+  ///
+  /// Files with the emphasis variations included are accepted too, but only the first 64 colors are used
+  /// since emphasis is applied when rendering.
+  pub fn from_pal_bytes(bytes: &[u8]) -> Result<Self, String> {
+    if bytes.len() != PAL_FILE_SIZE && bytes.len() != PAL_FILE_SIZE_WITH_EMPHASIS {
+      return Err(format!("Expected a {} byte palette file, got {} bytes", PAL_FILE_SIZE, bytes.len()));
+    }
+
+    let mut colors = [[0; 3]; 64];
+    for (color, rgb) in colors.iter_mut().zip(bytes.chunks_exact(3)) {
+      color.copy_from_slice(rgb);
+    }
+    Ok(Self { colors })
+  }
+
+  pub fn to_pal_bytes(&self) -> Vec<u8> {
+    self.colors.concat()
+  }
+}
+
+/// Palettes that ship with the emulator
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BuiltinPalette {
+  #[default]
+  Nesdev,
+  Fceux,
+  SonyCxa,
+}
+
+impl BuiltinPalette {
+  pub const ALL: [BuiltinPalette; 3] = [
+    BuiltinPalette::Nesdev,
+    BuiltinPalette::Fceux,
+    BuiltinPalette::SonyCxa,
+  ];
+
+  /// Key used both for persisting the setting and for looking up its UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      BuiltinPalette::Nesdev => "palette.nesdev",
+      BuiltinPalette::Fceux => "palette.fceux",
+      BuiltinPalette::SonyCxa => "palette.sony_cxa",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    BuiltinPalette::ALL.into_iter().find(|palette| palette.key() == key)
+  }
+
+  pub fn palette(&self) -> Palette {
+    let colors = match self {
+      BuiltinPalette::Nesdev => NESDEV,
+      BuiltinPalette::Fceux => FCEUX,
+      BuiltinPalette::SonyCxa => SONY_CXA,
+    };
+    Palette { colors }
+  }
+}
+
+/// The NESDev wiki's consensus 2C02 palette
+const NESDEV: [[u8; 3]; 64] = [
+  [98, 98, 98], [0, 31, 178], [36, 4, 200], [82, 0, 178], [115, 0, 118], [128, 0, 36], [115, 11, 0], [82, 40, 0], [36, 68, 0], [0, 87, 0], [0, 92, 0], [0, 83, 36], [0, 60, 118], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+  [171, 171, 171], [13, 87, 255], [75, 48, 255], [138, 19, 255], [188, 8, 214], [210, 18, 105], [199, 46, 0], [157, 84, 0], [96, 123, 0], [32, 152, 0], [0, 163, 0], [0, 153, 66], [0, 125, 180], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+  [255, 255, 255], [83, 174, 255], [144, 133, 255], [211, 101, 255], [255, 87, 255], [255, 93, 207], [255, 119, 87], [255, 158, 0], [189, 199, 0], [122, 231, 0], [67, 246, 17], [38, 239, 126], [44, 213, 246], [78, 78, 78], [0, 0, 0], [0, 0, 0],
+  [255, 255, 255], [182, 225, 255], [206, 209, 255], [233, 195, 255], [255, 188, 255], [255, 189, 244], [255, 198, 195], [255, 213, 154], [233, 230, 129], [206, 244, 129], [182, 251, 154], [169, 250, 195], [169, 240, 244], [184, 184, 184], [0, 0, 0], [0, 0, 0],
+];
+
+/// FCEUX's default palette
+const FCEUX: [[u8; 3]; 64] = [
+  [116, 116, 116], [36, 24, 140], [0, 0, 168], [68, 0, 156], [140, 0, 116], [168, 0, 16], [164, 0, 0], [124, 8, 0], [64, 44, 0], [0, 68, 0], [0, 80, 0], [0, 60, 20], [24, 60, 92], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+  [188, 188, 188], [0, 112, 236], [32, 56, 236], [128, 0, 240], [188, 0, 188], [228, 0, 88], [216, 40, 0], [200, 76, 12], [136, 112, 0], [0, 148, 0], [0, 168, 0], [0, 144, 56], [0, 128, 136], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+  [252, 252, 252], [60, 188, 252], [92, 148, 252], [204, 136, 252], [244, 120, 252], [252, 116, 180], [252, 116, 96], [252, 152, 56], [240, 188, 60], [128, 208, 16], [76, 220, 72], [88, 248, 152], [0, 232, 216], [120, 120, 120], [0, 0, 0], [0, 0, 0],
+  [252, 252, 252], [168, 228, 252], [196, 212, 252], [212, 200, 252], [252, 196, 252], [252, 196, 216], [252, 188, 176], [252, 216, 168], [252, 228, 160], [224, 252, 160], [168, 240, 188], [176, 252, 204], [156, 252, 240], [196, 196, 196], [0, 0, 0], [0, 0, 0],
+];
+
+/// What a TV decoding with Sony's CXA2025AS chip shows, as used in a lot of US sets
+const SONY_CXA: [[u8; 3]; 64] = [
+  [88, 88, 88], [0, 35, 140], [0, 19, 155], [45, 5, 133], [93, 0, 82], [122, 0, 23], [122, 8, 0], [95, 24, 0], [53, 42, 0], [9, 57, 0], [0, 63, 0], [0, 60, 34], [0, 50, 93], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+  [161, 161, 161], [0, 83, 238], [21, 60, 254], [96, 40, 228], [169, 29, 152], [212, 30, 65], [210, 44, 0], [170, 68, 0], [108, 94, 0], [45, 115, 0], [0, 125, 6], [0, 120, 82], [0, 105, 169], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+  [255, 255, 255], [31, 165, 254], [94, 137, 254], [181, 114, 254], [254, 101, 246], [254, 103, 144], [254, 119, 60], [254, 147, 8], [196, 178, 0], [121, 202, 16], [58, 213, 74], [17, 209, 164], [6, 191, 254], [66, 66, 66], [0, 0, 0], [0, 0, 0],
+  [255, 255, 255], [160, 217, 254], [189, 204, 254], [221, 194, 254], [254, 188, 251], [254, 189, 208], [254, 197, 169], [254, 209, 142], [233, 222, 134], [199, 233, 146], [168, 238, 176], [149, 236, 217], [145, 228, 254], [172, 172, 172], [0, 0, 0], [0, 0, 0],
+];
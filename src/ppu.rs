@@ -1,13 +1,13 @@
-use crate::bus::BusLike;
-use crate::cartridge::{Cartridge, MirroringMode};
+use crate::cartridge::{Cartridge, Format, HeaderInfo, MirroringMode};
+use crate::mapper::{A12Filter, FetchKind, PatternFetch, PpuBusSignal};
+use crate::palette::Palette;
+use crate::watchpoint::{Access, AddressSpace, WatchedAccess, Watchpoint, Watchpoints};
 
-use std::borrow::BorrowMut;
-use std::rc::Rc;
-use std::cell::RefCell;
 
 // region: PPU Registers
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PPUCTRL {
   pub nametable_x: bool,
   pub nametable_y: bool,
@@ -25,7 +25,7 @@ pub struct PPUCTRL {
 
 impl PPUCTRL {
   pub fn to_u8(&self) -> u8 {
-    (self.nametable_x as u8) << 0 |
+    (self.nametable_x as u8) |
     (self.nametable_y as u8) << 1 |
     (self.increment_mode as u8) << 2 |
     (self.sprite_tile_select as u8) << 3 |
@@ -48,6 +48,7 @@ impl PPUCTRL {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PPUMASK {
   pub greyscale: bool,
   pub background_left_column_enable: bool,
@@ -61,7 +62,7 @@ pub struct PPUMASK {
 
 impl PPUMASK {
   pub fn to_u8(&self) -> u8 {
-    (self.greyscale as u8) << 0 |
+    (self.greyscale as u8) |
     (self.background_left_column_enable as u8) << 1 |
     (self.sprite_left_column_enable as u8) << 2 |
     (self.background_enable as u8) << 3 |
@@ -84,6 +85,7 @@ impl PPUMASK {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PPUSTATUS {
   pub sprite_overflow: bool,
   pub sprite_zero_hit: bool,
@@ -105,6 +107,7 @@ impl PPUSTATUS {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Loopy {
   pub coarse_x: u8,
   pub coarse_y: u8,
@@ -146,6 +149,7 @@ impl Loopy {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PPUInternal {
   /// During rendering, used for the scroll position. Outside of rendering, used as the current VRAM address.
   pub v: Loopy,
@@ -161,6 +165,7 @@ pub struct PPUInternal {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PPURegisters {
   pub ctrl: PPUCTRL,
   pub mask: PPUMASK,
@@ -175,14 +180,84 @@ pub struct PPURegisters {
 
 // endregion: PPU Registers
 
-pub const COLORS: [[u8; 3]; 0x40] = [
-  [98, 98, 98], [0, 31, 178], [36, 4, 200], [82, 0, 178], [115, 0, 118], [128, 0, 36], [115, 11, 0], [82, 40, 0], [36, 68, 0], [0, 87, 0], [0, 92, 0], [0, 83, 36], [0, 60, 118], [0, 0, 0], [0, 0, 0], [0, 0, 0],
-  [171, 171, 171], [13, 87, 255], [75, 48, 255], [138, 19, 255], [188, 8, 214], [210, 18, 105], [199, 46, 0], [157, 84, 0], [96, 123, 0], [32, 152, 0], [0, 163, 0], [0, 153, 66], [0, 125, 180], [0, 0, 0], [0, 0, 0], [0, 0, 0],
-  [255, 255, 255], [83, 174, 255], [144, 133, 255], [211, 101, 255], [255, 87, 255], [255, 93, 207], [255, 119, 87], [255, 158, 0], [189, 199, 0], [122, 231, 0], [67, 246, 17], [38, 239, 126], [44, 213, 246], [78, 78, 78], [0, 0, 0], [0, 0, 0],
-  [255, 255, 255], [182, 225, 255], [206, 209, 255], [233, 195, 255], [255, 188, 255], [255, 189, 244], [255, 198, 195], [255, 213, 154], [233, 230, 129], [206, 244, 129], [182, 251, 154], [169, 250, 195], [169, 240, 244], [184, 184, 184], [0, 0, 0], [0, 0, 0],
-];
+/// Roughly how long (~600ms) an open bus bit holds its value once it stops being refreshed
+const OPEN_BUS_DECAY_FRAMES: u8 = 36;
+
+/// PPU cycles after power on or reset before the PPU listens to PPUCTRL, PPUMASK, PPUSCROLL and PPUADDR,
+/// about the end of the first vblank. Games that write them too early start up with them still cleared.
+pub const WARM_UP_CYCLES: u32 = 29658 * 3;
+
+/// How much each color emphasis bit dims the channels it isn't emphasizing
+const EMPHASIS_ATTENUATION: f32 = 0.816;
+
+/// Dots on a line where the background shifters can be loaded, every 8th from 1 to 337
+const BG_LOAD_SLOTS: usize = 43;
+
+/// Width and height of the picture of all four nametables, laid out as they are in the PPU's address space
+pub const NAMETABLES_WIDTH: usize = 512;
+pub const NAMETABLES_HEIGHT: usize = 480;
+
+/// The scroll registers at the start of a visible scanline, sampled while the nametable viewer's open
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollSample {
+  pub scanline: u8,
+  pub v: u16,
+  pub t: u16,
+  pub fine_x: u8,
+}
+
+impl ScrollSample {
+  /// Where in the picture of all four nametables the line's leftmost pixel comes from. The first two
+  /// tiles of a line are fetched at the end of the one before, so by dot 1 v's already two tiles on.
+  pub fn origin(&self) -> (usize, usize) {
+    let coarse_x = (self.v & 0x1F) as usize;
+    let coarse_y = ((self.v >> 5) & 0x1F) as usize;
+    let nametable_x = ((self.v >> 10) & 1) as usize;
+    let nametable_y = ((self.v >> 11) & 1) as usize;
+    let fine_y = ((self.v >> 12) & 7) as usize;
+    let x = nametable_x * 256 + coarse_x * 8 + self.fine_x as usize + NAMETABLES_WIDTH - 16;
+    let y = nametable_y * 240 + coarse_y * 8 + fine_y;
+    (x % NAMETABLES_WIDTH, y % NAMETABLES_HEIGHT)
+  }
+}
+
+/// Which console the PPU is emulating, since PAL PPUs wire up the emphasis bits differently
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Region {
+  #[default]
+  Ntsc,
+  Pal,
+}
+
+impl Region {
+  pub const ALL: [Region; 2] = [Region::Ntsc, Region::Pal];
+
+  /// The region a cartridge was made for, going by its header. iNES headers flag PAL games in bit 0
+  /// of flags 9.
+  pub fn from_header(header_info: &HeaderInfo) -> Self {
+    if header_info.format == Format::iNES && header_info.flags9 & 0x01 != 0 {
+      Region::Pal
+    } else {
+      Region::Ntsc
+    }
+  }
+
+  /// Short name used when persisting the choice
+  pub fn key(&self) -> &'static str {
+    match self {
+      Region::Ntsc => "ntsc",
+      Region::Pal => "pal",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    Region::ALL.into_iter().find(|region| region.key() == key)
+  }
+}
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OAMAttributes {
   pub palette: u8,
   pub priority: bool,
@@ -192,7 +267,7 @@ pub struct OAMAttributes {
 
 impl OAMAttributes {
   pub fn to_u8(&self) -> u8 {
-    ((self.flip_horizontally as u8) << 7) | ((self.flip_vertically as u8) << 6) | ((self.priority as u8) << 5) | self.palette
+    ((self.flip_vertically as u8) << 7) | ((self.flip_horizontally as u8) << 6) | ((self.priority as u8) << 5) | self.palette
   }
 
   pub fn set_from_u8(&mut self, value: u8) {
@@ -204,6 +279,7 @@ impl OAMAttributes {
 }
 
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OAMSprite {
   pub y: u8,
   pub id: u8,
@@ -211,19 +287,49 @@ pub struct OAMSprite {
   pub x: u8,
 }
 
+impl OAMSprite {
+  /// Read one of the sprite's 4 bytes, in the order they're laid out in OAM
+  pub fn byte(&self, index: u8) -> u8 {
+    match index % 4 {
+      0 => self.y,
+      1 => self.id,
+      2 => self.attributes.to_u8(),
+      _ => self.x,
+    }
+  }
+
+  /// Write one of the sprite's 4 bytes, in the order they're laid out in OAM
+  pub fn set_byte(&mut self, index: u8, value: u8) {
+    match index % 4 {
+      0 => self.y = value,
+      1 => self.id = value,
+      2 => self.attributes.set_from_u8(value),
+      _ => self.x = value,
+    }
+  }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PPU {
-  bus: Option<Rc<RefCell<Box<dyn BusLike>>>>,
-  cartridge: Option<Rc<RefCell<Cartridge>>>,
-  screen: [u8; 256 * 240 * 3],
+  /// Kept on the heap, as it's by far the biggest part of the PPU and the PPU gets moved around whole
+  screen: Vec<u8>,
+  #[cfg_attr(feature = "serde", serde(with = "crate::serde_arrays::nested"))]
   pub nametables: [[u8; 0x400]; 2],
-  palette: [u8; 32],
+  palette: PaletteRam,
+  #[cfg_attr(feature = "serde", serde(with = "crate::serde_arrays::nested"))]
   pattern: [[u8; 0x1000]; 2],
   cycle_count: u16,
   scanline_count: i16,
   frame_complete: bool,
   registers: PPURegisters,
   buffered_data: u8,
-  pub nmi: bool,
+  /// The I/O data latch between the CPU and PPU, returned for any bits a register read doesn't drive
+  open_bus: u8,
+  /// Frames since each bit of the open bus latch was last refreshed
+  open_bus_decay: [u8; 8],
+  /// $2002 was read the cycle before vblank starts, which stops the flag being set for that frame
+  skip_vblank: bool,
   // Background rendering
   bg_next_tile_id: u8,
   bg_next_tile_attrib: u8,
@@ -233,35 +339,78 @@ pub struct PPU {
   bg_pattern_shift_high: u16,
   bg_attrib_shift_low: u16,
   bg_attrib_shift_high: u16,
+  /// The tile loaded into the background shifters at each load dot of this line, as its pattern low and
+  /// high bytes and attribute bits. The fetches are made on time, but the shifters are only loaded once
+  /// the pixel pipeline catches up.
+  #[cfg_attr(feature = "serde", serde(with = "crate::serde_arrays::big"))]
+  bg_loads: [[u8; 3]; BG_LOAD_SLOTS],
+  /// The next dot of this line the pixel pipeline will draw, see `render_pixels`
+  pixel_dot: u16,
   // Foreground rendering
+  #[cfg_attr(feature = "serde", serde(with = "crate::serde_arrays::big"))]
   pub oam: [OAMSprite; 64],
   oam_address: u8,
-  active_sprites: Vec<OAMSprite>,
+  /// Sprites found for the next scanline during evaluation
+  secondary_oam: [OAMSprite; 8],
+  secondary_oam_count: u8,
+  secondary_oam_has_sprite_zero: bool,
+  /// Which primary OAM sprite and byte evaluation is looking at
+  eval_sprite: u8,
+  eval_byte: u8,
+  /// Evaluation steps left copying an in-range sprite into secondary OAM
+  eval_copy_steps: u8,
+  eval_done: bool,
+  /// Sprites being drawn on the current scanline
+  active_sprites: [OAMSprite; 8],
   sprite_count: u8,
   sprite_shift_low: [u8; 8],
   sprite_shift_high: [u8; 8],
   sprite_zero_hit_possible: bool,
   sprite_zero_being_rendered: bool,
   // Misc
+  region: Region,
+  /// RGB colors used to display the 64 color indices
+  colors: Palette,
   current_palette: u8,
   current_value: u8,
+  /// The last address the PPU put on its bus, and what a mapper watching it saw this cycle
+  bus_address: u16,
+  a12: A12Filter,
+  bus_signal: PpuBusSignal,
+  /// PPU cycles left until the PPU's warmed up, see `WARM_UP_CYCLES`
+  warm_up_cycles: u32,
+  /// Leave the screen as it is this frame, working out everything but the colour of each pixel. Set
+  /// for frames being skipped, see `FrameSkip`.
+  #[cfg_attr(feature = "serde", serde(skip))]
+  skip_drawing: bool,
+  #[cfg_attr(feature = "serde", serde(skip))]
+  watchpoints: Watchpoints,
+  /// The scroll at the start of each rendered line of the frame so far, while it's being sampled
+  #[cfg_attr(feature = "serde", serde(skip))]
+  scroll_log: Option<Vec<ScrollSample>>,
+}
+
+impl Default for PPU {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl PPU {
   pub fn new() -> Self {
     Self {
-      bus: None,
-      cartridge: None,
-      screen: [0; 256 * 240 * 3],
+      screen: vec![0; 256 * 240 * 3],
       nametables: [[0; 0x400]; 2],
-      palette: [0; 32],
+      palette: PaletteRam::default(),
       pattern: [[0; 0x1000]; 2],
       cycle_count: 0,
       scanline_count: -1,
       frame_complete: false,
       registers: PPURegisters::default(),
       buffered_data: 0,
-      nmi: false,
+      open_bus: 0,
+      open_bus_decay: [0; 8],
+      skip_vblank: false,
       bg_next_tile_id: 0,
       bg_next_tile_attrib: 0,
       bg_next_tile_lsb: 0,
@@ -270,73 +419,152 @@ impl PPU {
       bg_pattern_shift_high: 0,
       bg_attrib_shift_low: 0,
       bg_attrib_shift_high: 0,
+      bg_loads: [[0; 3]; BG_LOAD_SLOTS],
+      pixel_dot: 0,
       oam: [OAMSprite::default(); 64],
       oam_address: 0,
-      active_sprites: Vec::<OAMSprite>::with_capacity(8),
+      secondary_oam: [OAMSprite::default(); 8],
+      secondary_oam_count: 0,
+      secondary_oam_has_sprite_zero: false,
+      eval_sprite: 0,
+      eval_byte: 0,
+      eval_copy_steps: 0,
+      eval_done: false,
+      active_sprites: [OAMSprite::default(); 8],
       sprite_count: 0,
       sprite_shift_low: [0; 8],
       sprite_shift_high: [0; 8],
       sprite_zero_hit_possible: false,
       sprite_zero_being_rendered: false,
+      region: Region::Ntsc,
+      colors: Palette::default(),
       current_palette: 0,
       current_value: 0,
+      bus_address: 0,
+      a12: A12Filter::default(),
+      bus_signal: PpuBusSignal::default(),
+      warm_up_cycles: 0,
+      skip_drawing: false,
+      watchpoints: Watchpoints::default(),
+      scroll_log: None,
     }
   }
 
-  pub fn connect_to_bus(&mut self, bus: Rc<RefCell<Box<dyn BusLike>>>) {
-    self.bus = Some(bus);
+  /// Pick the region a newly inserted cartridge was made for
+  pub fn insert_cartridge(&mut self, cartridge: &Cartridge) {
+    self.region = Region::from_header(&cartridge.header_info);
+  }
+
+  pub fn region(&self) -> Region {
+    self.region
+  }
+
+  pub fn set_region(&mut self, region: Region) {
+    self.catch_up();
+    self.region = region;
+  }
+
+  pub fn colors(&self) -> Palette {
+    self.colors
   }
 
-  pub fn connect_cartridge(&mut self, cartridge: Rc<RefCell<Cartridge>>) {
-    self.cartridge = Some(cartridge);
+  /// Change the colors used to display the picture, takes effect from the next pixel drawn
+  pub fn set_colors(&mut self, colors: Palette) {
+    self.catch_up();
+    self.colors = colors;
   }
 
   // CPU is reading from PPU
-  pub fn cpu_read(&mut self, address: u16) -> u8 {
-    match address {
-      0x0000 => 0, // CTRL (not readable)
-      0x0001 => 0, // MASK (not readable)
+  pub fn cpu_read(&mut self, cartridge: &Cartridge, address: u16) -> u8 {
+    self.catch_up();
+    // The registers repeat every 8 bytes, so any address is one of them
+    match address & 0x0007 {
+      0x0000 => self.open_bus, // CTRL (not readable)
+      0x0001 => self.open_bus, // MASK (not readable)
       0x0002 => { // STATUS
-        // Technically only the top bits of the status register will be used,
-        // but we emulate the behavior of the bottom bits being old buffered data
-        let data = (self.registers.status.to_u8() & 0xE0) | (self.buffered_data & 0x1F);
+        // Only the top bits of the status register are driven, the rest come from the open bus
+        let data = (self.registers.status.to_u8() & 0xE0) | (self.open_bus & 0x1F);
+        self.refresh_open_bus(data, 0xE0);
+        if self.scanline_count == 241 && self.cycle_count == 1 {
+          self.skip_vblank = true;
+        }
         self.registers.status.vertical_blank = false;
         self.registers.internal.write_latch = false;
         data
       },
-      0x0003 => 0, // OAMADDR (not readable)
+      0x0003 => self.open_bus, // OAMADDR (not readable)
       0x0004 => { // OAMDATA
-        let entry = self.oam[(self.oam_address / 4) as usize];
-        match self.oam_address % 4 {
-          0 => entry.y,
-          1 => entry.id,
-          2 => entry.attributes.to_u8(),
-          3 => entry.x,
-          _ => panic!("Invalid OAM address: {:#04X}", self.oam_address),
-        }
+        // While secondary OAM is being cleared, the PPU forces reads of OAM to $FF
+        let clearing_secondary_oam = self.rendering_enabled()
+          && self.scanline_count >= 0 && self.scanline_count < 240
+          && self.cycle_count >= 1 && self.cycle_count <= 64;
+        let data = if clearing_secondary_oam {
+          0xFF
+        } else {
+          self.oam[(self.oam_address / 4) as usize].byte(self.oam_address)
+        };
+        self.refresh_open_bus(data, 0xFF);
+        data
       },
-      0x0005 => 0, // SCROLL (not readable)
-      0x0006 => 0, // ADDR (not readable)
-      0x0007 => { // DATA
-        let mut data = self.buffered_data;
-        self.buffered_data = *self.ppu_read(self.registers.internal.v.address);
-
-        // Reads from palette memory are not buffered
-        if self.registers.internal.v.address >= 0x3F00 {
-          data = self.buffered_data;
-        }
+      0x0005 => self.open_bus, // SCROLL (not readable)
+      0x0006 => self.open_bus, // ADDR (not readable)
+      _ => { // DATA
+        let address = self.registers.internal.v.address & 0x3FFF;
+        let data = if address >= 0x3F00 {
+          // Reads from palette memory are not buffered, and only drive the bottom 6 bits.
+          // The buffer still gets filled, but with the nametable byte "underneath" the palette.
+          let palette = *self.ppu_read(cartridge, address) & self.greyscale_mask();
+          self.buffered_data = *self.ppu_read(cartridge, address & 0x2FFF);
+          let data = (self.open_bus & 0xC0) | palette;
+          self.refresh_open_bus(data, 0x3F);
+          data
+        } else {
+          let data = self.buffered_data;
+          self.buffered_data = *self.ppu_read(cartridge, address);
+          self.refresh_open_bus(data, 0xFF);
+          data
+        };
 
         let increment = if self.registers.ctrl.increment_mode { 32 } else { 1 };
         self.registers.internal.v.set_address(self.registers.internal.v.address.wrapping_add(increment));
 
         data
       },
-      _ => panic!("Invalid address for PPU read: {:#04X}", address),
+    }
+  }
+
+  /// Drive the bits in `mask` onto the open bus latch, resetting their decay
+  fn refresh_open_bus(&mut self, value: u8, mask: u8) {
+    self.open_bus = (self.open_bus & !mask) | (value & mask);
+    for bit in 0..8 {
+      if mask & (1 << bit) != 0 {
+        self.open_bus_decay[bit] = 0;
+      }
+    }
+  }
+
+  /// Bits on the open bus that haven't been refreshed for a while decay back to 0
+  fn decay_open_bus(&mut self) {
+    for bit in 0..8 {
+      if self.open_bus_decay[bit] < OPEN_BUS_DECAY_FRAMES {
+        self.open_bus_decay[bit] += 1;
+      } else {
+        self.open_bus &= !(1 << bit);
+      }
     }
   }
 
   // CPU is writing to PPU
-  pub fn cpu_write(&mut self, address: u16, value: u8) {
+  pub fn cpu_write(&mut self, cartridge: &Cartridge, address: u16, value: u8) {
+    self.catch_up();
+    // Any write fills the whole latch, even to registers that ignore the value
+    self.refresh_open_bus(value, 0xFF);
+    // The registers repeat every 8 bytes, so any address is one of them
+    let address = address & 0x0007;
+    if self.warm_up_cycles > 0 && matches!(address, 0x0000 | 0x0001 | 0x0005 | 0x0006) {
+      return;
+    }
+
     match address {
       0x0000 => { // CTRL
         self.registers.ctrl.set_from_u8(value);
@@ -348,20 +576,13 @@ impl PPU {
       },
       0x0002 => { // STATUS
         // Writing to this register does nothing, but it's interesting that it's happening at all
-        println!("Caught a write to the PPU status register with value: {:02X}", value);
+        log::debug!("Caught a write to the PPU status register with value: {:02X}", value);
       },
       0x0003 => { // OAMADDR
         self.oam_address = value;
       },
       0x0004 => { // OAMDATA
-        let entry = &mut self.oam[(self.oam_address / 4) as usize];
-        match self.oam_address % 4 {
-          0 => entry.y = value,
-          1 => entry.id = value,
-          2 => entry.attributes.set_from_u8(value),
-          3 => entry.x = value,
-          _ => panic!("Invalid OAM address: {:#04X}", self.oam_address),
-        }
+        self.oam[(self.oam_address / 4) as usize].set_byte(self.oam_address, value);
       },
       0x0005 => { // SCROLL
         if !self.registers.internal.write_latch {
@@ -384,142 +605,110 @@ impl PPU {
           self.registers.internal.write_latch = false;
         }
       },
-      0x0007 => { // DATA
-        self.ppu_write(self.registers.internal.v.address, value);
+      _ => { // DATA
+        self.ppu_write(cartridge, self.registers.internal.v.address, value);
         let increment = if self.registers.ctrl.increment_mode { 32 } else { 1 };
         self.registers.internal.v.set_address(self.registers.internal.v.address.wrapping_add(increment));
       },
-      _ => panic!("Invalid address for PPU write: {:#04X}", address),
     }
   }
 
+  /// Replace the watchpoints PPU bus reads and writes are checked against
+  pub fn set_watchpoints(&mut self, watchpoints: Vec<Watchpoint>) {
+    self.watchpoints.set(watchpoints);
+  }
+
+  /// The first PPU bus access to set a watchpoint off since last time
+  pub fn take_watch_hit(&mut self) -> Option<WatchedAccess> {
+    self.watchpoints.take_hit()
+  }
+
   // PPU is reading from PPU bus
-  pub fn ppu_read(&mut self, address: u16) -> &u8 {
-    let mut masked = address & 0x3FFF;
+  pub fn ppu_read(&mut self, cartridge: &Cartridge, address: u16) -> &u8 {
+    self.bus_address = address & 0x3FFF;
+    if self.watchpoints.is_empty() {
+      return self.vram_read(cartridge, address);
+    }
+    let value = *self.vram_read(cartridge, address);
+    self.watchpoints.check(AddressSpace::Ppu, Access::Read, address & 0x3FFF, value);
+    self.current_value = value;
+    &self.current_value
+  }
+
+  /// Read the PPU bus without checking watchpoints, so debugging tools can look without setting them off
+  pub fn peek(&mut self, cartridge: &Cartridge, address: u16) -> u8 {
+    *self.vram_read(cartridge, address)
+  }
+
+  fn vram_read(&mut self, cartridge: &Cartridge, address: u16) -> &u8 {
+    let masked = address & 0x3FFF;
     if masked <= 0x1FFF {
-      let cartridge = if let Some(cartridge) = &self.cartridge {
-        cartridge.borrow()
-      } else {
-        panic!("Cartridge is not attached to PPU!");
-      };
       if cartridge.header_info.chr_rom_size > 0 {
+        if let Some(ram) = cartridge.mapper.get_mapped_address_chr_ram(address) {
+          return &self.pattern[((ram & 0x1000) >> 12) as usize][(ram & 0x0FFF) as usize];
+        }
         self.current_value = cartridge.ppu_read(address).to_owned();
         &self.current_value
       } else {
         &self.pattern[((address & 0x1000) >> 12) as usize][(address & 0x0FFF) as usize]
       }
-    } else if masked >= 0x2000 && masked <= 0x3EFF {
-      //println!("PPU READ from address {:#04X} at scanline {} cycle {}", masked, self.scanline_count, self.cycle_count);
+    } else if masked <= 0x3EFF {
       // Nametables
-      masked = address & 0x0FFF;
-      let cartridge = if let Some(cartridge) = &self.cartridge {
-        cartridge.borrow()
-      } else {
-        panic!("Cartridge is not attached to PPU!");
-      };
-      match cartridge.get_nametable_layout() {
-        MirroringMode::Vertical => {
-          match masked {
-            0x0000..=0x03FF => &self.nametables[0][(masked & 0x03FF) as usize],
-            0x0400..=0x07FF => &self.nametables[1][(masked & 0x03FF) as usize],
-            0x0800..=0x0BFF => &self.nametables[0][(masked & 0x03FF) as usize],
-            0x0C00..=0x0FFF => &self.nametables[1][(masked & 0x03FF) as usize],
-            _ => panic!("Invalid address for PPU read: {:#04X}", masked),
-          }
-        },
-        MirroringMode::Horizontal => {
-          match masked {
-            0x0000..=0x03FF => &self.nametables[0][(address & 0x03FF) as usize],
-            0x0400..=0x07FF => &self.nametables[0][(address & 0x03FF) as usize],
-            0x0800..=0x0BFF => &self.nametables[1][(address & 0x03FF) as usize],
-            0x0C00..=0x0FFF => &self.nametables[1][(address & 0x03FF) as usize],
-            _ => panic!("Invalid address for PPU read: {:#04X}", address),
-          }
-        },
-        MirroringMode::SingleScreenLow => {
-          &self.nametables[0][(address & 0x03FF) as usize]
-        },
-        MirroringMode::SingleScreenHigh => {
-          &self.nametables[1][(address & 0x03FF) as usize]
-        },
-        _ => panic!("Invalid mirroring mode for PPU read: {:?}", cartridge.get_nametable_layout()),
-      }
-    } else if masked >= 0x3F00 && masked <= 0x3FFF {
-      let pallete_address = match address & 0x001F {
-        0x0010 => self.palette[0x0000 as usize],
-        0x0014 => self.palette[0x0004 as usize],
-        0x0018 => self.palette[0x0008 as usize],
-        0x001C => self.palette[0x000C as usize],
-        _ => (address & 0x001F) as u8,
-      };
-      self.current_palette = self.palette[pallete_address as usize] & if self.registers.mask.greyscale { 0x30 } else { 0x3F };
-      &self.current_palette
+      let (page, offset) = nametable_location(cartridge, address);
+      &self.nametables[page][offset]
     } else {
-      panic!("Invalid address for PPU read: {:#04X}", address);
+      self.current_palette = self.palette.read(address);
+      &self.current_palette
     }
   }
 
   // PPU is writing to PPU bus
-  pub fn ppu_write(&mut self, address: u16, value: u8) {
-    let mut masked = (address & 0x3FFF) as usize;
-    let cartridge = if let Some(cartridge) = &self.cartridge {
-      cartridge.borrow()
-    } else {
-      panic!("Cartridge is not attached to PPU!");
-    };
+  pub fn ppu_write(&mut self, cartridge: &Cartridge, address: u16, value: u8) {
+    self.catch_up();
+    self.bus_address = address & 0x3FFF;
+    if !self.watchpoints.is_empty() {
+      self.watchpoints.check(AddressSpace::Ppu, Access::Write, address & 0x3FFF, value);
+    }
+    let masked = (address & 0x3FFF) as usize;
 
     if masked <= 0x1FFF {
-      self.pattern[(masked & 0x1000) >> 12][masked & 0x0FFF] = value;
-    } else if masked >= 0x2000 && masked <= 0x3EFF {
-      masked &= 0x0FFF;
-      match cartridge.get_nametable_layout() {
-        MirroringMode::Vertical => {
-          match masked {
-            0x0000..=0x03FF => self.nametables[0][masked & 0x03FF] = value,
-            0x0400..=0x07FF => self.nametables[1][masked & 0x03FF] = value,
-            0x0800..=0x0BFF => self.nametables[0][masked & 0x03FF] = value,
-            0x0C00..=0x0FFF => self.nametables[1][masked & 0x03FF] = value,
-            _ => panic!("Invalid address for PPU write: {:#04X}", masked),
-          }
-        },
-        MirroringMode::Horizontal => {
-          match masked {
-            0x0000..=0x03FF => self.nametables[0][masked & 0x03FF] = value,
-            0x0400..=0x07FF => self.nametables[0][masked & 0x03FF] = value,
-            0x0800..=0x0BFF => self.nametables[1][masked & 0x03FF] = value,
-            0x0C00..=0x0FFF => self.nametables[1][masked & 0x03FF] = value,
-            _ => panic!("Invalid address for PPU write: {:#04X}", masked),
-          }
-        },
-        MirroringMode::SingleScreenLow => {
-          self.nametables[0][masked & 0x03FF] = value
-        },
-        MirroringMode::SingleScreenHigh => {
-          self.nametables[1][masked & 0x03FF] = value
-        },
-        _ => panic!("Invalid mirroring mode for PPU write: {:?}", cartridge.get_nametable_layout()),
+      // With CHR ROM, only what the mapper's put CHR RAM in front of can be written
+      let ram = if cartridge.header_info.chr_rom_size > 0 {
+        cartridge.mapper.get_mapped_address_chr_ram(address).map(|ram| ram as usize)
+      } else {
+        Some(masked)
+      };
+      if let Some(ram) = ram {
+        self.pattern[(ram & 0x1000) >> 12][ram & 0x0FFF] = value;
       }
-    } else if masked >= 0x3F00 && masked <= 0x3FFF {
-      let masked = match address & 0x001F {
-        0x0010 => 0x0000,
-        0x0014 => 0x0004,
-        0x0018 => 0x0008,
-        0x001C => 0x000C,
-        _ => address & 0x001F,
-      } as usize;
-      self.palette[masked] = value;
+    } else if masked <= 0x3EFF {
+      let (page, offset) = nametable_location(cartridge, address);
+      self.nametables[page][offset] = value;
     } else {
-      panic!("Invalid address for PPU write: {:#04X}", address);
+      self.palette.write(address, value);
     }
   }
 
   /// Step the clock of the PPU
-  pub fn step(&mut self) {
+  pub fn step(&mut self, cartridge: &mut Cartridge) {
+    self.warm_up_cycles = self.warm_up_cycles.saturating_sub(1);
     if self.scanline_count >= -1 && self.scanline_count < 240 {
       if self.scanline_count == 0 && self.cycle_count == 0 {
         self.cycle_count = 1;
       }
 
+      if self.cycle_count == 1 && self.scanline_count >= 0 && self.rendering_enabled() {
+        if let Some(log) = &mut self.scroll_log {
+          let internal = &self.registers.internal;
+          log.push(ScrollSample {
+            scanline: self.scanline_count as u8,
+            v: internal.v.address,
+            t: internal.t.address,
+            fine_x: internal.fine_x,
+          });
+        }
+      }
+
       if self.scanline_count == -1 && self.cycle_count == 1 {
         // Reset status register values
         self.registers.status.vertical_blank = false;
@@ -530,46 +719,22 @@ impl PPU {
         self.sprite_shift_low.fill(0);
         self.sprite_shift_high.fill(0);
 
-        // Clear secondary OAM
-        self.active_sprites.clear();
+        // No sprites are drawn on the first visible scanline
+        self.sprite_count = 0;
       }
 
-      let active_sprites_len = self.active_sprites.len();
-
       if (self.cycle_count >= 2 && self.cycle_count < 258) || (self.cycle_count >= 321 && self.cycle_count < 338) {
-        // Update shifters
-        if self.registers.mask.background_enable {
-          self.bg_pattern_shift_low <<= 1;
-          self.bg_pattern_shift_high <<= 1;
-          self.bg_attrib_shift_low <<= 1;
-          self.bg_attrib_shift_high <<= 1;
-        }
-
-        if self.registers.mask.sprite_enable && self.cycle_count >= 1 && self.cycle_count < 258 {
-          for i in 0..active_sprites_len {
-            if self.active_sprites[i].x > 0 {
-              self.active_sprites[i].x -= 1;
-            } else {
-              self.sprite_shift_low[i] <<= 1;
-              self.sprite_shift_high[i] <<= 1;
-            }
-          }
-        }
-
         // Run background rendering tasks
         match (self.cycle_count - 1) % 8 {
           0 => {
-            // Load background shifters
-            self.bg_pattern_shift_low = (self.bg_pattern_shift_low & 0xFF00) | self.bg_next_tile_lsb as u16;
-            self.bg_pattern_shift_high = (self.bg_pattern_shift_high & 0xFF00) | self.bg_next_tile_msb as u16;
-
-            self.bg_attrib_shift_low = (self.bg_attrib_shift_low & 0xFF00) | if (self.bg_next_tile_attrib & 0b01) != 0 { 0xFF } else { 0 };
-            self.bg_attrib_shift_high = (self.bg_attrib_shift_high & 0xFF00) | if (self.bg_next_tile_attrib & 0b10) != 0 { 0xFF } else { 0 };
+            // The last tile fetched goes into the background shifters here
+            let slot = (self.cycle_count - 1) as usize / 8;
+            self.bg_loads[slot] = [self.bg_next_tile_lsb, self.bg_next_tile_msb, self.bg_next_tile_attrib];
 
-            self.bg_next_tile_id = *self.ppu_read(0x2000 | (self.registers.internal.v.address & 0x0FFF));
+            self.bg_next_tile_id = *self.ppu_read(cartridge, 0x2000 | (self.registers.internal.v.address & 0x0FFF));
           },
           2 => {
-            self.bg_next_tile_attrib = *self.ppu_read(0x23C0 | ((self.registers.internal.v.nametable_y as u16) << 11)
+            self.bg_next_tile_attrib = *self.ppu_read(cartridge, 0x23C0 | ((self.registers.internal.v.nametable_y as u16) << 11)
               | ((self.registers.internal.v.nametable_x as u16) << 10)
               | ((self.registers.internal.v.coarse_y as u16 >> 2) << 3)
               | (self.registers.internal.v.coarse_x as u16 >> 2));
@@ -584,24 +749,22 @@ impl PPU {
             self.bg_next_tile_attrib &= 0x03;
           },
           4 => {
-            self.bg_next_tile_lsb = *self.ppu_read(((self.registers.ctrl.background_tile_select as u16) << 12)
+            self.bg_next_tile_lsb = self.fetch_pattern(cartridge, ((self.registers.ctrl.background_tile_select as u16) << 12)
               + ((self.bg_next_tile_id as u16) << 4)
-              + self.registers.internal.v.fine_y as u16);
+              + self.registers.internal.v.fine_y as u16, FetchKind::Background);
           },
           6 => {
-            self.bg_next_tile_msb = *self.ppu_read(((self.registers.ctrl.background_tile_select as u16) << 12)
+            self.bg_next_tile_msb = self.fetch_pattern(cartridge, ((self.registers.ctrl.background_tile_select as u16) << 12)
               + ((self.bg_next_tile_id as u16) << 4)
-              + self.registers.internal.v.fine_y as u16 + 8);
+              + self.registers.internal.v.fine_y as u16 + 8, FetchKind::Background);
           },
-          7 => {
-            // Increment scroll X
-            if self.registers.mask.background_enable || self.registers.mask.sprite_enable {
-              if self.registers.internal.v.coarse_x == 31 {
-                self.registers.internal.v.set_coarse_x(0);
-                self.registers.internal.v.set_nametable_x(!self.registers.internal.v.nametable_x);
-              } else {
-                self.registers.internal.v.set_coarse_x(self.registers.internal.v.coarse_x.wrapping_add(1));
-              }
+          // Increment scroll X
+          7 if self.registers.mask.background_enable || self.registers.mask.sprite_enable => {
+            if self.registers.internal.v.coarse_x == 31 {
+              self.registers.internal.v.set_coarse_x(0);
+              self.registers.internal.v.set_nametable_x(!self.registers.internal.v.nametable_x);
+            } else {
+              self.registers.internal.v.set_coarse_x(self.registers.internal.v.coarse_x.wrapping_add(1));
             }
           },
           _ => {}
@@ -629,13 +792,6 @@ impl PPU {
       }
 
       if self.cycle_count == 257 {
-        // Load background shifters
-        self.bg_pattern_shift_low = (self.bg_pattern_shift_low & 0xFF00) | self.bg_next_tile_lsb as u16;
-        self.bg_pattern_shift_high = (self.bg_pattern_shift_high & 0xFF00) | self.bg_next_tile_msb as u16;
-
-        self.bg_attrib_shift_low = (self.bg_attrib_shift_low & 0xFF00) | if (self.bg_next_tile_attrib & 0b01) != 0 { 0xFF } else { 0 };
-        self.bg_attrib_shift_high = (self.bg_attrib_shift_high & 0xFF00) | if (self.bg_next_tile_attrib & 0b10) != 0 { 0xFF } else { 0 };
-
         // Transfer address X
         if self.registers.mask.background_enable || self.registers.mask.sprite_enable {
           self.registers.internal.v.set_nametable_x(self.registers.internal.t.nametable_x);
@@ -644,7 +800,7 @@ impl PPU {
       }
 
       if self.cycle_count == 338 || self.cycle_count == 340 {
-        self.bg_next_tile_id = *self.ppu_read(0x2000 | (self.registers.internal.v.address & 0x0FFF));
+        self.bg_next_tile_id = *self.ppu_read(cartridge, 0x2000 | (self.registers.internal.v.address & 0x0FFF));
       }
 
       if self.scanline_count == -1 && self.cycle_count >= 280 && self.cycle_count < 305 {
@@ -656,92 +812,60 @@ impl PPU {
         }
       }
 
-      if self.cycle_count == 257 && self.scanline_count >= 0 {
-        self.active_sprites.clear();
-        self.sprite_count = 0;
-        self.sprite_shift_low.fill(0);
-        self.sprite_shift_high.fill(0);
-        self.sprite_zero_hit_possible = false;
-
-        for i in 0..64 as usize {
-          // If diff is positive, scanline is overlapping sprite location
-          let diff = self.scanline_count - self.oam[i].y as i16;
-          let sprite_size = if self.registers.ctrl.sprite_size { 16 } else { 8 };
-
-          if diff >= 0 && diff < sprite_size {
-            if self.sprite_count < 8 {
-              if i == 0 {
-                self.sprite_zero_hit_possible = true;
+      if self.rendering_enabled() {
+        // Sprite evaluation for the next scanline. Odd cycles read primary OAM and even cycles write
+        // secondary OAM, so everything here happens on one or the other.
+        if self.scanline_count >= 0 {
+          match self.cycle_count {
+            // Clear secondary OAM, one byte every other cycle
+            1..=64 if self.cycle_count.is_multiple_of(2) => {
+              let index = (self.cycle_count / 2 - 1) as u8;
+              self.secondary_oam[(index / 4) as usize].set_byte(index, 0xFF);
+            },
+            65..=256 => {
+              if self.cycle_count == 65 {
+                self.secondary_oam_count = 0;
+                self.secondary_oam_has_sprite_zero = false;
+                self.eval_sprite = 0;
+                self.eval_byte = 0;
+                self.eval_copy_steps = 0;
+                self.eval_done = false;
               }
-              self.active_sprites.push(self.oam[i]);
-              self.sprite_count += 1;
-            }
-          }
-
-          if self.sprite_count == 9 {
-            self.registers.status.sprite_overflow = true;
-            break;
+              if self.cycle_count % 2 == 1 {
+                self.evaluate_sprite();
+              }
+            },
+            _ => {}
           }
         }
-      }
 
-      if self.cycle_count == 340 {
-        for i in 0..active_sprites_len {
-          let mut sprite_pattern_bits_low: u8;
-          let mut sprite_pattern_bits_high: u8;
-          let sprite_pattern_address_low: u16;
-          let sprite_pattern_address_high: u16;
-
-          if !self.registers.ctrl.sprite_size { // 8x8 sprites
-            if !self.active_sprites[i].attributes.flip_vertically {
-              sprite_pattern_address_low = ((self.registers.ctrl.sprite_tile_select as u16) << 12) |
-                ((self.active_sprites[i].id as u16) << 4) |
-                (self.scanline_count - self.active_sprites[i].y as i16) as u16;
-            } else {
-              sprite_pattern_address_low = ((self.registers.ctrl.sprite_tile_select as u16) << 12) |
-                ((self.active_sprites[i].id as u16) << 4) |
-                (7 - (self.scanline_count - self.active_sprites[i].y as i16)) as u16;
-            }
-          } else { // 8x16 sprites
-            if !self.active_sprites[i].attributes.flip_vertically {
-              if (self.scanline_count - self.active_sprites[i].y as i16) < 8 {
-                // Reading top half of tile
-                sprite_pattern_address_low = ((self.active_sprites[i].id as u16 & 0x01) << 12) |
-                  ((self.active_sprites[i].id as u16 & 0xFE) << 4) |
-                  ((self.scanline_count - self.active_sprites[i].y as i16) & 0x07) as u16;
-              } else {
-                // Reading bottom half of tile
-                sprite_pattern_address_low = ((self.active_sprites[i].id as u16 & 0x01) << 12) |
-                  (((self.active_sprites[i].id as u16 & 0xFE) + 1) << 4) |
-                  (((self.scanline_count - self.active_sprites[i].y as i16) & 0x07)) as u16;
-              }
+        // Sprite fetches for the next scanline, 8 cycles per sprite slot
+        if self.cycle_count >= 257 && self.cycle_count <= 320 {
+          self.oam_address = 0;
+
+          if self.cycle_count == 257 {
+            // The pixel pipeline's done with this line's sprites once it's past this dot
+            self.render_pixels(258);
+            if self.scanline_count >= 0 {
+              self.active_sprites = self.secondary_oam;
+              self.sprite_count = self.secondary_oam_count;
+              self.sprite_zero_hit_possible = self.secondary_oam_has_sprite_zero;
             } else {
-              if (self.scanline_count - self.active_sprites[i].y as i16) < 8 {
-                // Reading top half of tile
-                sprite_pattern_address_low = ((self.active_sprites[i].id as u16 & 0x01) << 12) |
-                  (((self.active_sprites[i].id as u16 & 0xFE) + 1) << 4) |
-                  (7 - (self.scanline_count - self.active_sprites[i].y as i16) & 0x07) as u16;
-              } else {
-                // Reading bottom half of tile
-                sprite_pattern_address_low = ((self.active_sprites[i].id as u16 & 0x01) << 12) |
-                  (((self.active_sprites[i].id as u16 & 0xFE)) << 4) |
-                  (7 - ((self.scanline_count - self.active_sprites[i].y as i16) & 0x07)) as u16;
-              }
+              self.sprite_count = 0;
+              self.sprite_zero_hit_possible = false;
             }
           }
 
-          sprite_pattern_address_high = sprite_pattern_address_low + 8;
-
-          sprite_pattern_bits_low = *self.ppu_read(sprite_pattern_address_low);
-          sprite_pattern_bits_high = *self.ppu_read(sprite_pattern_address_high);
-
-          if self.active_sprites[i].attributes.flip_horizontally {
-            sprite_pattern_bits_low = sprite_pattern_bits_low.reverse_bits();
-            sprite_pattern_bits_high = sprite_pattern_bits_high.reverse_bits();
+          let slot = ((self.cycle_count - 257) / 8) as usize;
+          match (self.cycle_count - 257) % 8 {
+            5 => {
+              self.sprite_shift_low[slot] = self.fetch_sprite_pattern(cartridge, slot, 0);
+            },
+            7 => {
+              self.sprite_shift_high[slot] = self.fetch_sprite_pattern(cartridge, slot, 8);
+            },
+            _ => {}
           }
-
-          self.sprite_shift_low[i] = sprite_pattern_bits_low;
-          self.sprite_shift_high[i] = sprite_pattern_bits_high;
         }
       }
     }
@@ -750,131 +874,403 @@ impl PPU {
       // Nothing apparently?
     }
 
-    if self.scanline_count >= 241 && self.scanline_count < 261 {
-      if self.scanline_count == 241 && self.cycle_count == 1 {
-        self.registers.status.vertical_blank = true;
-        if self.registers.ctrl.enable_nmi {
-          self.nmi = true;
-        }
+    if self.scanline_count == 241 && self.cycle_count == 1 {
+      self.registers.status.vertical_blank = !self.skip_vblank;
+      self.skip_vblank = false;
+    }
+
+    // Rendering's fetches put their own addresses on the bus, otherwise it's left holding v
+    if !(self.rendering_enabled() && (-1..240).contains(&self.scanline_count)) {
+      self.bus_address = self.registers.internal.v.address & 0x3FFF;
+    }
+    self.bus_signal = self.a12.clock(self.bus_address);
+
+    self.cycle_count += 1;
+    // Palette lookups can set watchpoints off, so while there are any each dot's drawn as it happens
+    if !self.watchpoints.is_empty() {
+      self.catch_up();
+    }
+    if self.cycle_count >= 341 {
+      self.catch_up();
+      self.cycle_count = 0;
+      self.pixel_dot = 0;
+      self.scanline_count += 1;
+      if self.scanline_count >= 261 {
+        self.scanline_count = -1;
+        self.frame_complete = true;
+        self.decay_open_bus();
       }
     }
+  }
 
-    // Background rendering
-    let mut bg_pixel = 0;
-    let mut bg_pal = 0;
-    if self.registers.mask.background_enable {
-      let bit_mux = 0x8000 >> self.registers.internal.fine_x;
+  /// What was on the PPU's address bus on the cycle just stepped, for the mapper
+  pub fn bus_signal(&self) -> PpuBusSignal {
+    self.bus_signal
+  }
 
-      let p0_pixel = ((self.bg_pattern_shift_low & bit_mux) > 0) as u8;
-      let p1_pixel = ((self.bg_pattern_shift_high & bit_mux) > 0) as u8;
-      bg_pixel = (p1_pixel << 1) | p0_pixel;
+  /// Draw everything up to the dot the PPU's reached. Called before anything that changes what the
+  /// pixel pipeline draws, like a register write, and before anything that looks at what it's drawn.
+  pub fn catch_up(&mut self) {
+    self.render_pixels(self.cycle_count);
+  }
 
-      let bg_pal0 = ((self.bg_attrib_shift_low & bit_mux) > 0) as u8;
-      let bg_pal1 = ((self.bg_attrib_shift_high & bit_mux) > 0) as u8;
-      bg_pal = (bg_pal1 << 1) | bg_pal0;
+  /// Run the pixel pipeline through this line up to dot `to`: shift the background and sprite
+  /// shifters along, work out each pixel and sprite 0 hits, and write the pixels to the screen.
+  ///
+  /// It runs behind the rest of the PPU, a whole run of dots at a time, as nothing else depends on it
+  /// until the CPU next touches a register (which might change what's drawn, or be reading $2002 for a
+  /// sprite 0 hit) or the line ends. Over a run the registers and palette stay the same, so they're
+  /// only looked at once instead of on every dot. Mid-line writes end a run early, so raster effects
+  /// still land on the exact dot.
+  fn render_pixels(&mut self, to: u16) {
+    let from = self.pixel_dot;
+    if from >= to {
+      return;
+    }
+    self.pixel_dot = to;
+    if !(-1..240).contains(&self.scanline_count) {
+      return;
     }
 
-    // Foreground rendering
-    let mut fg_pixel = 0;
-    let mut fg_pal = 0;
-    let mut fg_priority = 0;
-    if self.registers.mask.sprite_enable {
-      self.sprite_zero_being_rendered = false;
+    let mask = self.registers.mask;
+    let visible = self.scanline_count >= 0;
+    let drawing = visible && !self.skip_drawing;
+    let colors = if drawing { self.palette_colors() } else { [[0; 3]; 32] };
+    let watching = !self.watchpoints.is_empty();
+    let bit_mux = 0x8000 >> self.registers.internal.fine_x;
+    let drawing_both = mask.background_enable && mask.sprite_enable;
+    // https://www.nesdev.org/wiki/PPU_palettes#The_background_palette_hack
+    // With rendering off the backdrop colour's drawn, unless v points into palette RAM, in which case
+    // the entry it points at is drawn instead. Some games draw with this, writing to palette RAM
+    // mid-frame with rendering off.
+    let v = self.registers.internal.v.address & 0x3FFF;
+    let backdrop = (!self.rendering_enabled() && v >= 0x3F00).then_some((v & 0x1F) as usize);
+    let row = self.scanline_count.max(0) as usize * 256;
+
+    // The pipeline's state is kept in locals for the run, so writing pixels out doesn't make the
+    // compiler reload it all on every dot
+    let [mut pattern_low, mut pattern_high] = [self.bg_pattern_shift_low, self.bg_pattern_shift_high];
+    let [mut attrib_low, mut attrib_high] = [self.bg_attrib_shift_low, self.bg_attrib_shift_high];
+    let sprites = &mut self.active_sprites[..self.sprite_count as usize];
+    let mut sprite_low = self.sprite_shift_low;
+    let mut sprite_high = self.sprite_shift_high;
+    let mut sprite_zero_rendered = self.sprite_zero_being_rendered;
+    let mut sprite_zero_hit = false;
+    let screen = &mut self.screen[row * 3..(row + 256) * 3];
+
+    for dot in from..to {
+      if (2..=257).contains(&dot) || (321..=337).contains(&dot) {
+        if mask.background_enable {
+          pattern_low <<= 1;
+          pattern_high <<= 1;
+          attrib_low <<= 1;
+          attrib_high <<= 1;
+        }
 
-      for i in 0..self.active_sprites.len() as usize {
-        if self.active_sprites[i].x == 0 {
-          let fg_pixel_low = ((self.sprite_shift_low[i] & 0x80) > 0) as u8;
-          let fg_pixel_high = ((self.sprite_shift_high[i] & 0x80) > 0) as u8;
-          fg_pixel = (fg_pixel_high << 1) | fg_pixel_low;
+        if mask.sprite_enable && dot <= 257 {
+          for (i, sprite) in sprites.iter_mut().enumerate() {
+            if sprite.x > 0 {
+              sprite.x -= 1;
+            } else {
+              sprite_low[i] <<= 1;
+              sprite_high[i] <<= 1;
+            }
+          }
+        }
 
-          fg_pal = self.active_sprites[i].attributes.palette + 0x04;
-          fg_priority = !(self.active_sprites[i].attributes.priority) as u8;
+        if (dot - 1) % 8 == 0 {
+          let [lsb, msb, attrib] = self.bg_loads[(dot - 1) as usize / 8];
+          pattern_low = (pattern_low & 0xFF00) | lsb as u16;
+          pattern_high = (pattern_high & 0xFF00) | msb as u16;
+          attrib_low = (attrib_low & 0xFF00) | if (attrib & 0b01) != 0 { 0xFF } else { 0 };
+          attrib_high = (attrib_high & 0xFF00) | if (attrib & 0b10) != 0 { 0xFF } else { 0 };
+        }
+      }
 
-          if fg_pixel != 0 {
-            if i == 0 {
-              self.sprite_zero_being_rendered = true;
-            }
+      // Dots 1-256 of each visible line output pixels 0-255
+      if !visible || !(1..=256).contains(&dot) {
+        continue;
+      }
+
+      // Background rendering
+      let mut bg_pixel = 0;
+      let mut bg_pal = 0;
+      if mask.background_enable {
+        let p0_pixel = ((pattern_low & bit_mux) > 0) as u8;
+        let p1_pixel = ((pattern_high & bit_mux) > 0) as u8;
+        bg_pixel = (p1_pixel << 1) | p0_pixel;
+
+        let bg_pal0 = ((attrib_low & bit_mux) > 0) as u8;
+        let bg_pal1 = ((attrib_high & bit_mux) > 0) as u8;
+        bg_pal = (bg_pal1 << 1) | bg_pal0;
+      }
+
+      // Foreground rendering
+      let mut fg_pixel = 0;
+      let mut fg_pal = 0;
+      let mut fg_priority = 0;
+      if mask.sprite_enable {
+        sprite_zero_rendered = false;
+
+        for (i, sprite) in sprites.iter().enumerate() {
+          if sprite.x == 0 {
+            let fg_pixel_low = ((sprite_low[i] & 0x80) > 0) as u8;
+            let fg_pixel_high = ((sprite_high[i] & 0x80) > 0) as u8;
+            fg_pixel = (fg_pixel_high << 1) | fg_pixel_low;
+
+            fg_pal = sprite.attributes.palette + 0x04;
+            fg_priority = !(sprite.attributes.priority) as u8;
+
+            if fg_pixel != 0 {
+              if i == 0 {
+                sprite_zero_rendered = true;
+              }
 
-            break;
+              break;
+            }
           }
         }
       }
-    }
 
-    // BG+FG composite
-    let mut pixel: u8 = 0;
-    let mut pal: u8 = 0;
-
-    if bg_pixel == 0 && fg_pixel == 0 {
-      // BG and FG are both transparent, draw background color
-      pixel = 0;
-      pal = 0;
-    } else if bg_pixel == 0 && fg_pixel > 0 {
-      // BG is transparent, FG is visible
-      pixel = fg_pixel;
-      pal = fg_pal;
-    } else if bg_pixel > 0 && fg_pixel == 0 {
-      // BG is visible, FG is transparent
-      pixel = bg_pixel;
-      pal = bg_pal;
-    } else if bg_pixel > 0 && fg_pixel > 0 {
-      // BG and FG are visible, check priority
-      if fg_priority > 0 {
-        pixel = fg_pixel;
-        pal = fg_pal;
+      // The left column masks hide each layer's first 8 pixels independently of the other
+      if dot <= 8 && !mask.background_left_column_enable {
+        bg_pixel = 0;
+      }
+      if dot <= 8 && !mask.sprite_left_column_enable {
+        fg_pixel = 0;
+        sprite_zero_rendered = false;
+      }
+
+      // https://www.nesdev.org/wiki/PPU_OAM#Sprite_zero_hits
+      // Sprite 0 hits where an opaque pixel of it is drawn over an opaque background pixel, whatever the
+      // priority, on pixels 0-254 of a visible line. Never at x=255, and with both layers switched on.
+      if self.sprite_zero_hit_possible && sprite_zero_rendered && bg_pixel != 0 && drawing_both && dot != 256 {
+        sprite_zero_hit = true;
+      }
+
+      // Skipped frames leave the rest out, as none of it affects anything but the picture
+      if !drawing {
+        continue;
+      }
+
+      // BG+FG composite: the sprite wins if the background's transparent or it's in front
+      let (pal, pixel) = if fg_pixel > 0 && (bg_pixel == 0 || fg_priority > 0) {
+        (fg_pal, fg_pixel)
+      } else if bg_pixel > 0 {
+        (bg_pal, bg_pixel)
       } else {
-        pixel = bg_pixel;
-        pal = bg_pal;
+        (0, 0)
+      };
+      let entry = backdrop.unwrap_or((pal * 4 + pixel) as usize);
+      if watching {
+        let value = self.palette.read(entry as u16);
+        self.watchpoints.check(AddressSpace::Ppu, Access::Read, 0x3F00 + entry as u16, value);
       }
+      let x = dot as usize - 1;
+      screen[x * 3..x * 3 + 3].copy_from_slice(&colors[entry]);
     }
 
-    if self.sprite_zero_hit_possible && self.sprite_zero_being_rendered {
-      if self.registers.mask.background_enable && self.registers.mask.sprite_enable {
-        if !(self.registers.mask.background_left_column_enable || self.registers.mask.sprite_left_column_enable) {
-          if self.cycle_count >= 9 && self.cycle_count <= 258 {
-            self.registers.status.sprite_zero_hit = true;
-          }
-        } else {
-          if self.cycle_count >= 1 && self.cycle_count <= 258 {
-            self.registers.status.sprite_zero_hit = true;
-          }
+    self.bg_pattern_shift_low = pattern_low;
+    self.bg_pattern_shift_high = pattern_high;
+    self.bg_attrib_shift_low = attrib_low;
+    self.bg_attrib_shift_high = attrib_high;
+    self.sprite_shift_low = sprite_low;
+    self.sprite_shift_high = sprite_high;
+    self.sprite_zero_being_rendered = sprite_zero_rendered;
+    self.registers.status.sprite_zero_hit |= sprite_zero_hit;
+  }
+
+  /// Greyscale mode drops the hue bits from every palette entry, leaving only the brightness
+  fn greyscale_mask(&self) -> u8 {
+    if self.registers.mask.greyscale { 0x30 } else { 0x3F }
+  }
+
+  /// The RGB colour each of the 32 palette RAM entries draws in, going by PPUMASK
+  fn palette_colors(&self) -> [[u8; 3]; 32] {
+    std::array::from_fn(|entry| self.palette_color(entry as u16))
+  }
+
+  /// Look up the final RGB color for a palette RAM entry, with greyscale and color emphasis applied
+  fn palette_color(&self, entry: u16) -> [u8; 3] {
+    let palette_index = (self.palette.read(entry) & self.greyscale_mask()) as usize;
+    let mask = self.registers.mask;
+    if !(mask.color_emphasis_red || mask.color_emphasis_green || mask.color_emphasis_blue) {
+      return self.colors.colors[palette_index];
+    }
+
+    // PAL PPUs swap the red and green emphasis bits
+    let (emphasize_red, emphasize_green) = match self.region {
+      Region::Ntsc => (mask.color_emphasis_red, mask.color_emphasis_green),
+      Region::Pal => (mask.color_emphasis_green, mask.color_emphasis_red),
+    };
+    let mut color = self.colors.colors[palette_index].map(|channel| channel as f32);
+
+    // Emphasizing a color really darkens the other two, so with several bits set
+    // a channel can be dimmed more than once, and with all three everything is darker
+    for (emphasized, channel) in [(emphasize_red, 0), (emphasize_green, 1), (mask.color_emphasis_blue, 2)] {
+      if emphasized {
+        for other in (0..3).filter(|&other| other != channel) {
+          color[other] *= EMPHASIS_ATTENUATION;
         }
       }
     }
 
-    if self.scanline_count < 240 && self.cycle_count < 256 {
-      let index = (self.scanline_count as usize).wrapping_mul(256) + (self.cycle_count.saturating_sub(1) as usize);
-      if index < self.screen.len() {
-        let palette_index = (self.ppu_read(0x3F00 + (pal as u16 * 4) + pixel as u16) & 0x3F) as usize;
-        self.screen[index * 3] = COLORS[palette_index][0];
-        self.screen[index * 3 + 1] = COLORS[palette_index][1];
-        self.screen[index * 3 + 2] = COLORS[palette_index][2];
+    color.map(|channel| channel.round() as u8)
+  }
+
+  fn rendering_enabled(&self) -> bool {
+    self.registers.mask.background_enable || self.registers.mask.sprite_enable
+  }
+
+  /// One read/write pair of sprite evaluation: check the next sprite in primary OAM against
+  /// the current scanline and copy it to secondary OAM if it's in range
+  fn evaluate_sprite(&mut self) {
+    if self.eval_done {
+      return;
+    }
+
+    // Still busy copying the rest of an in-range sprite
+    if self.eval_copy_steps > 0 {
+      self.eval_copy_steps -= 1;
+      if self.eval_copy_steps == 0 {
+        self.next_eval_sprite();
       }
+      return;
     }
 
-    self.cycle_count += 1;
-    if self.cycle_count >= 341 {
-      self.cycle_count = 0;
-      self.scanline_count += 1;
-      if self.scanline_count >= 261 {
-        self.scanline_count = -1;
-        self.frame_complete = true;
+    let sprite_size = if self.registers.ctrl.sprite_size { 16 } else { 8 };
+    let sprite = self.oam[self.eval_sprite as usize];
+
+    if self.secondary_oam_count < 8 {
+      let diff = self.scanline_count - sprite.y as i16;
+      if diff >= 0 && diff < sprite_size {
+        if self.eval_sprite == 0 {
+          self.secondary_oam_has_sprite_zero = true;
+        }
+        self.secondary_oam[self.secondary_oam_count as usize] = sprite;
+        self.secondary_oam_count += 1;
+        // The other 3 bytes take a read/write pair each
+        self.eval_copy_steps = 3;
+      } else {
+        self.next_eval_sprite();
+      }
+    } else {
+      // Secondary OAM is full, so look for overflow. The hardware increments the byte index along
+      // with the sprite index here, so it ends up treating tile numbers, attributes and X as Y.
+      let diff = self.scanline_count - sprite.byte(self.eval_byte) as i16;
+      if diff >= 0 && diff < sprite_size {
+        self.registers.status.sprite_overflow = true;
+        self.eval_done = true;
+      } else {
+        self.eval_byte = (self.eval_byte + 1) % 4;
+        self.next_eval_sprite();
+      }
+    }
+  }
+
+  fn next_eval_sprite(&mut self) {
+    self.eval_sprite += 1;
+    if self.eval_sprite == 64 {
+      self.eval_sprite = 0;
+      self.eval_done = true;
+    }
+  }
+
+  /// Read from the pattern tables for rendering, and tell the mapper about it
+  fn fetch_pattern(&mut self, cartridge: &mut Cartridge, address: u16, kind: FetchKind) -> u8 {
+    let value = *self.ppu_read(cartridge, address);
+    // With rendering off the real PPU isn't fetching anything, so there's nothing for the mapper to see
+    if self.rendering_enabled() {
+      let fetch = PatternFetch { address, kind, scanline: self.scanline_count, dot: self.cycle_count };
+      cartridge.mapper.pattern_fetch(fetch);
+    }
+    value
+  }
+
+  /// Fetch one bitplane of the pattern for a sprite slot, flipped so it can be shifted out MSB first.
+  /// Empty slots come back transparent, after a fetch of tile $FF like the real PPU makes.
+  fn fetch_sprite_pattern(&mut self, cartridge: &mut Cartridge, slot: usize, plane_offset: u16) -> u8 {
+    if slot >= self.sprite_count as usize {
+      // 8x16 sprites take their pattern table from bit 0 of the tile, and their top tile from the rest
+      let address = if self.registers.ctrl.sprite_size { 0x1FE0 } else { ((self.registers.ctrl.sprite_tile_select as u16) << 12) | 0x0FF0 };
+      self.fetch_pattern(cartridge, address + plane_offset, FetchKind::Sprite);
+      return 0;
+    }
+
+    let sprite = self.active_sprites[slot];
+    // Only the row's low bits are used, which matters when the sprite size changes between evaluation
+    // and the fetch and leaves the row outside the sprite
+    let row = (self.scanline_count - sprite.y as i16) & if self.registers.ctrl.sprite_size { 0x0F } else { 0x07 };
+    let sprite_pattern_address_low: u16 = if !self.registers.ctrl.sprite_size { // 8x8 sprites
+      if !sprite.attributes.flip_vertically {
+        ((self.registers.ctrl.sprite_tile_select as u16) << 12) | ((sprite.id as u16) << 4) | row as u16
+      } else {
+        ((self.registers.ctrl.sprite_tile_select as u16) << 12) | ((sprite.id as u16) << 4) | (7 - row) as u16
       }
-      self.bus.as_ref().unwrap().as_ref().borrow_mut().scanline();
+    } else { // 8x16 sprites
+      if !sprite.attributes.flip_vertically {
+        if row < 8 {
+          // Reading top half of tile
+          ((sprite.id as u16 & 0x01) << 12) | ((sprite.id as u16 & 0xFE) << 4) | (row & 0x07) as u16
+        } else {
+          // Reading bottom half of tile
+          ((sprite.id as u16 & 0x01) << 12) | (((sprite.id as u16 & 0xFE) + 1) << 4) | (row & 0x07) as u16
+        }
+      } else {
+        if row < 8 {
+          // Reading top half of tile
+          ((sprite.id as u16 & 0x01) << 12) | (((sprite.id as u16 & 0xFE) + 1) << 4) | ((7 - row) & 0x07) as u16
+        } else {
+          // Reading bottom half of tile
+          ((sprite.id as u16 & 0x01) << 12) | ((sprite.id as u16 & 0xFE) << 4) | (7 - (row & 0x07)) as u16
+        }
+      }
+    };
+
+    let bits = self.fetch_pattern(cartridge, sprite_pattern_address_low + plane_offset, FetchKind::Sprite);
+    if sprite.attributes.flip_horizontally {
+      bits.reverse_bits()
+    } else {
+      bits
     }
   }
 
-  pub fn get_pattern_table(&mut self, index: u8) -> Vec<u8> {
-    let mut vec: Vec<u8> = Vec::new();
-    vec.resize(0x4000, 0);
+  /// The PPU's NMI output, held for as long as the vblank flag is set and NMIs are enabled. The CPU
+  /// reacts to it turning on, so enabling NMIs partway through vblank triggers another one, while
+  /// reading $2002 as vblank starts clears the flag before the CPU sees it and the NMI is missed.
+  pub fn nmi_output(&self) -> bool {
+    self.registers.status.vertical_blank && self.registers.ctrl.enable_nmi
+  }
+
+  /// Whether a frame has finished since this was last called
+  pub fn take_frame_complete(&mut self) -> bool {
+    std::mem::take(&mut self.frame_complete)
+  }
+
+  /// Whether the frame being drawn is left off the screen, see `skip_drawing`
+  pub fn skipping_drawing(&self) -> bool {
+    self.skip_drawing
+  }
+
+  pub fn set_skip_drawing(&mut self, skip: bool) {
+    self.skip_drawing = skip;
+  }
+
+  /// Current (scanline, dot) the PPU is about to render
+  pub fn position(&self) -> (i16, u16) {
+    (self.scanline_count, self.cycle_count)
+  }
+
+  pub fn get_pattern_table(&mut self, cartridge: &Cartridge, index: u8) -> Vec<u8> {
+    let mut vec: Vec<u8> = vec![0; 0x4000];
 
     for tile_y in 0..16 {
       for tile_x in 0..16 {
         let offset: u16 = tile_y * 256 + tile_x * 16;
 
         for row in 0..8 {
-          let mut tile_lsb = *self.ppu_read((index as u16 * 0x1000 + offset + row) as u16);
-          let mut tile_msb = *self.ppu_read((index as u16 * 0x1000 + offset + row + 8) as u16);
+          let mut tile_lsb = *self.vram_read(cartridge, index as u16 * 0x1000 + offset + row);
+          let mut tile_msb = *self.vram_read(cartridge, index as u16 * 0x1000 + offset + row + 8);
           for col in 0..8 {
             let pixel = (tile_lsb & 0x01) + (tile_msb & 0x01);
             tile_lsb >>= 1;
@@ -892,25 +1288,108 @@ impl PPU {
     vec
   }
 
+  /// Start or stop sampling the scroll at the start of each line, which is off unless something's
+  /// drawing it
+  pub fn set_scroll_logging(&mut self, enabled: bool) {
+    match (enabled, &self.scroll_log) {
+      (true, None) => self.scroll_log = Some(vec![]),
+      (false, Some(_)) => self.scroll_log = None,
+      _ => {},
+    }
+  }
+
+  pub fn scroll_logging(&self) -> bool {
+    self.scroll_log.is_some()
+  }
+
+  /// Every scroll sample since last time, top line first
+  pub fn take_scroll_log(&mut self) -> Vec<ScrollSample> {
+    self.scroll_log.as_mut().map(std::mem::take).unwrap_or_default()
+  }
+
+  /// All four nametables as packed RGB bytes, `NAMETABLES_WIDTH` by `NAMETABLES_HEIGHT`, drawn with the
+  /// background's pattern table and palettes as they are now
+  pub fn render_nametables(&mut self, cartridge: &Cartridge) -> Vec<u8> {
+    let colors = self.palette_colors();
+    let pattern_table = (self.registers.ctrl.background_tile_select as u16) << 12;
+    let mut image = vec![0; NAMETABLES_WIDTH * NAMETABLES_HEIGHT * 3];
+    for table in 0..4u16 {
+      let base = 0x2000 + table * 0x400;
+      let (left, top) = ((table as usize & 1) * 256, (table as usize >> 1) * 240);
+      for tile_y in 0..30u16 {
+        for tile_x in 0..32u16 {
+          let tile = self.peek(cartridge, base + tile_y * 32 + tile_x) as u16;
+          let attribute = self.peek(cartridge, base + 0x3C0 + (tile_y / 4) * 8 + tile_x / 4);
+          let shift = ((tile_y & 2) << 1) | (tile_x & 2);
+          let palette = ((attribute >> shift) & 3) as usize;
+          for row in 0..8u16 {
+            let low = self.peek(cartridge, pattern_table + tile * 16 + row);
+            let high = self.peek(cartridge, pattern_table + tile * 16 + row + 8);
+            for column in 0..8 {
+              let pixel = (((high >> (7 - column)) & 1) << 1 | ((low >> (7 - column)) & 1)) as usize;
+              let entry = if pixel == 0 { 0 } else { palette * 4 + pixel };
+              let x = left + tile_x as usize * 8 + column;
+              let y = top + tile_y as usize * 8 + row as usize;
+              let offset = (y * NAMETABLES_WIDTH + x) * 3;
+              image[offset..offset + 3].copy_from_slice(&colors[entry]);
+            }
+          }
+        }
+      }
+    }
+    image
+  }
+
+  /// The 32 entries of palette RAM as $3F00-$3F1F reads them, so $3F10/$3F14/$3F18/$3F1C show the
+  /// entries they mirror
   pub fn get_palettes(&self) -> Vec<u8> {
-    Vec::from(self.palette)
+    Vec::from(self.palette.entries())
   }
 
-  pub fn get_screen(&self) -> Vec<u8> {
-    Vec::from(self.screen)
+  /// The frame being drawn, as packed RGB bytes. Pixels are drawn a run at a time, so the line the PPU's
+  /// on can be behind where it's got to; `catch_up` first if that matters.
+  pub fn screen(&self) -> &[u8] {
+    &self.screen
   }
 
-  pub fn reset(&mut self) {
+  /// Write a byte of OAM without touching OAMADDR, as OAM DMA does
+  pub fn write_oam(&mut self, address: u8, value: u8) {
+    self.oam[(address / 4) as usize].set_byte(address, value);
+  }
+
+  /// Blank the pattern tables used by cartridges with CHR RAM instead of ROM
+  pub fn clear_chr_ram(&mut self) {
+    self.pattern.fill([0; 0x1000]);
+  }
+
+  /// Everything cleared, including VRAM, palette RAM and OAM, as at power on
+  pub fn power_on(&mut self) {
     self.screen.fill(0);
     self.nametables.fill([0; 0x400]);
-    self.palette.fill(0);
+    self.palette = PaletteRam::default();
     self.pattern.fill([0; 0x1000]);
+    self.registers = PPURegisters::default();
+    self.open_bus = 0;
+    self.open_bus_decay = [0; 8];
+    self.oam = [OAMSprite::default(); 64];
+    self.oam_address = 0;
+    self.reset();
+  }
+
+  /// What the reset button does on consoles that wire it to the PPU, like the front loading NES. The
+  /// registers, latches and shifters are cleared and the PPU warms up again, while VRAM, palette RAM
+  /// and OAM keep their contents.
+  pub fn reset(&mut self) {
     self.cycle_count = 0;
     self.scanline_count = -1;
     self.frame_complete = false;
-    self.registers = PPURegisters::default();
+    self.registers.ctrl = PPUCTRL::default();
+    self.registers.mask = PPUMASK::default();
+    self.registers.internal.t = Loopy::default();
+    self.registers.internal.fine_x = 0;
+    self.registers.internal.write_latch = false;
     self.buffered_data = 0;
-    self.nmi = false;
+    self.skip_vblank = false;
     self.bg_next_tile_id = 0;
     self.bg_next_tile_attrib = 0;
     self.bg_next_tile_lsb = 0;
@@ -919,13 +1398,71 @@ impl PPU {
     self.bg_pattern_shift_high = 0;
     self.bg_attrib_shift_low = 0;
     self.bg_attrib_shift_high = 0;
-    self.oam = [OAMSprite::default(); 64];
-    self.oam_address = 0;
-    self.active_sprites.clear();
+    self.bg_loads = [[0; 3]; BG_LOAD_SLOTS];
+    self.pixel_dot = 0;
+    self.secondary_oam = [OAMSprite::default(); 8];
+    self.secondary_oam_count = 0;
+    self.secondary_oam_has_sprite_zero = false;
+    self.eval_sprite = 0;
+    self.eval_byte = 0;
+    self.eval_copy_steps = 0;
+    self.eval_done = false;
+    self.active_sprites = [OAMSprite::default(); 8];
     self.sprite_count = 0;
     self.sprite_shift_low.fill(0);
     self.sprite_shift_high.fill(0);
     self.sprite_zero_hit_possible = false;
     self.sprite_zero_being_rendered = false;
+    self.warm_up_cycles = WARM_UP_CYCLES;
+  }
+
+}
+
+/// Palette RAM, $3F00-$3FFF on the PPU bus. Reads, writes and rendering all go through [`PaletteRam::index`],
+/// so a mirrored entry can never drift from the one it mirrors.
+/// https://www.nesdev.org/wiki/PPU_palettes#Memory_Map
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct PaletteRam([u8; 32]);
+
+impl PaletteRam {
+  /// Where in palette RAM a palette address lands. The 32 bytes repeat up to $3FFF, and
+  /// $3F10/$3F14/$3F18/$3F1C are mirrors of $3F00/$3F04/$3F08/$3F0C, as sprites' colour 0 is never
+  /// drawn. $3F04/$3F08/$3F0C are entries of their own, drawn only by the background palette hack,
+  /// but read and written like any other.
+  pub fn index(address: u16) -> usize {
+    let entry = (address & 0x001F) as usize;
+    if entry & 0x13 == 0x10 { entry & 0x0F } else { entry }
+  }
+
+  /// The entry at `address`. Palette RAM is only 6 bits wide, so the top 2 bits are always clear.
+  pub fn read(&self, address: u16) -> u8 {
+    self.0[Self::index(address)] & 0x3F
+  }
+
+  pub fn write(&mut self, address: u16, value: u8) {
+    self.0[Self::index(address)] = value;
+  }
+
+  /// All 32 entries as $3F00-$3F1F read them
+  pub fn entries(&self) -> [u8; 32] {
+    std::array::from_fn(|entry| self.read(entry as u16))
   }
-}
\ No newline at end of file
+}
+
+/// Which page of nametable RAM a nametable address lands in, and where in the page, going by how the
+/// cartridge mirrors them
+fn nametable_location(cartridge: &Cartridge, address: u16) -> (usize, usize) {
+  let table = ((address & 0x0FFF) >> 10) as usize;
+  let page = match cartridge.get_nametable_layout() {
+    MirroringMode::Vertical => table & 1,
+    MirroringMode::Horizontal => table >> 1,
+    MirroringMode::SingleScreenLow => 0,
+    MirroringMode::SingleScreenHigh => 1,
+    // There are only two pages of RAM, so a mapper asking for more gets one of those
+    MirroringMode::Pages(pages) => pages[table] as usize & 1,
+    // The cartridge hands out the header's mirroring in place of this, so it never gets here
+    MirroringMode::_Hardwired => 0,
+  };
+  (page, (address & 0x03FF) as usize)
+}
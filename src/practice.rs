@@ -0,0 +1,265 @@
+use eframe::egui;
+use egui::{Key, Modifiers};
+
+use crate::command::{parse_address, Command, SAVE_SLOTS};
+use crate::condition::{Comparison, ConditionEvent, ConditionId};
+use crate::condition_window::parse_value;
+use crate::i18n::tr;
+use crate::nes::Nes;
+use crate::video::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Thumbnails are the screen at half size each way
+pub const THUMBNAIL_WIDTH: usize = SCREEN_WIDTH / 2;
+pub const THUMBNAIL_HEIGHT: usize = SCREEN_HEIGHT / 2;
+
+/// The keys for each slot, in slot order
+const SLOT_KEYS: [Key; SAVE_SLOTS] = [
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+];
+
+/// Shrink a 256x240 frame of packed RGB bytes down to a thumbnail, averaging each 2x2 block of pixels
+pub fn thumbnail(screen: &[u8]) -> Vec<u8> {
+    let mut thumbnail = Vec::with_capacity(THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT * 3);
+    for y in 0..THUMBNAIL_HEIGHT {
+        for x in 0..THUMBNAIL_WIDTH {
+            for channel in 0..3 {
+                let pixel = |dx: usize, dy: usize| {
+                    let index = ((y * 2 + dy) * SCREEN_WIDTH + x * 2 + dx) * 3 + channel;
+                    screen.get(index).copied().unwrap_or(0) as u16
+                };
+                thumbnail.push(((pixel(0, 0) + pixel(1, 0) + pixel(0, 1) + pixel(1, 1)) / 4) as u8);
+            }
+        }
+    }
+    thumbnail
+}
+
+/// A slot that's been saved to, as shown in the slot browser
+struct Slot {
+    /// The frame the state was saved on, counted as `Nes::frame_count` does
+    frame: u64,
+    thumbnail: Vec<u8>,
+    /// Uploaded the first time the browser draws it
+    texture: Option<egui::TextureHandle>,
+}
+
+/// What counts as the player dying, e.g. the lives counter going down
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeathCondition {
+    pub address: u16,
+    pub comparison: Comparison,
+    pub value: u8,
+}
+
+/// Practice mode, for running a tricky section over and over. While it's on, a number key loads
+/// the slot it's bound to and Shift with it saves there, and the console can save to a slot by
+/// itself whenever a memory condition says the player died. The window browses the slots by the
+/// thumbnail taken when each was saved.
+pub struct Practice {
+    pub open: bool,
+    pub enabled: bool,
+    slots: Vec<Option<Slot>>,
+    /// The condition the console is watching for deaths, if one's been set up
+    death: Option<(DeathCondition, ConditionId)>,
+    /// The slot a death saves to
+    pub death_slot: usize,
+    address: String,
+    comparison: Comparison,
+    value: String,
+    error: Option<String>,
+}
+
+impl Default for Practice {
+    fn default() -> Self {
+        Self {
+            open: false,
+            enabled: false,
+            slots: (0..SAVE_SLOTS).map(|_| None).collect(),
+            death: None,
+            death_slot: SAVE_SLOTS - 1,
+            address: String::new(),
+            comparison: Comparison::Decreased,
+            value: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl Practice {
+    /// Commands for the number keys pressed this frame, consuming the key presses. Nothing while
+    /// practice mode's off or a text field has focus.
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Command> {
+        if !self.enabled || ctx.wants_keyboard_input() {
+            return vec![];
+        }
+        let mut commands = vec![];
+        for (slot, key) in SLOT_KEYS.into_iter().enumerate() {
+            // Extra Shift is ignored when matching, so check for saving first
+            if ctx.input_mut(|i| i.consume_key(Modifiers::SHIFT, key)) {
+                commands.push(Command::SaveState(slot));
+            } else if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, key)) {
+                commands.push(Command::LoadState(slot));
+            }
+        }
+        commands
+    }
+
+    /// Keep a thumbnail of `screen` for a state that's just been saved to `slot` on `frame`
+    pub fn saved(&mut self, slot: usize, frame: u64, screen: &[u8]) {
+        self.slots[slot] = Some(Slot { frame, thumbnail: thumbnail(screen), texture: None });
+    }
+
+    /// The frame and thumbnail of what's saved in `slot`, if anything
+    pub fn slot(&self, slot: usize) -> Option<(u64, &[u8])> {
+        self.slots[slot].as_ref().map(|saved| (saved.frame, saved.thumbnail.as_slice()))
+    }
+
+    /// Forget every slot, as the states in them have been thrown away
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+    }
+
+    pub fn death_condition(&self) -> Option<DeathCondition> {
+        self.death.map(|(condition, _)| condition)
+    }
+
+    /// Watch for deaths by `condition` instead of whatever was watched for before, or stop watching
+    pub fn set_death_condition(&mut self, nes: &mut Nes, condition: Option<DeathCondition>) {
+        if let Some((_, id)) = self.death.take() {
+            nes.unwatch(id);
+        }
+        self.death = condition.map(|condition| {
+            let DeathCondition { comparison, value, .. } = condition;
+            let id = nes.watch(condition.address, move |previous, current| comparison.test(value, previous, current));
+            (condition, id)
+        });
+    }
+
+    /// Whether the player died in `events`, and it should be saved to `death_slot`. Deaths don't count
+    /// while practice mode's off.
+    pub fn died(&self, events: &[ConditionEvent]) -> bool {
+        match self.death {
+            Some((_, id)) if self.enabled => events.iter().any(|event| event.id == id),
+            _ => false,
+        }
+    }
+
+    /// Draw the slot browser, if open, returning the save or load the user picked
+    pub fn show(&mut self, ctx: &egui::Context, nes: &mut Nes) -> Option<Command> {
+        let mut command = None;
+        let mut open = self.open;
+
+        egui::Window::new(tr("practice.title"))
+            .id(egui::Id::new("practice_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut self.enabled, tr("practice.enabled"))
+                    .on_hover_text(tr("practice.hint"));
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    egui::Grid::new("practice_slots").striped(true).show(ui, |ui| {
+                        for (i, slot) in self.slots.iter_mut().enumerate() {
+                            ui.label(format!("{}", i));
+                            match slot {
+                                Some(slot) => {
+                                    let texture = slot.texture.get_or_insert_with(|| {
+                                        let image = egui::ColorImage::from_rgb([THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT], &slot.thumbnail);
+                                        ctx.load_texture(format!("practice_slot_{}", i), image, egui::TextureOptions::LINEAR)
+                                    });
+                                    let size = egui::vec2(THUMBNAIL_WIDTH as f32, THUMBNAIL_HEIGHT as f32);
+                                    ui.add(egui::Image::from_texture(egui::load::SizedTexture::new(texture.id(), size)));
+                                    ui.label(format!("{} {}", tr("practice.frame"), slot.frame));
+                                },
+                                None => {
+                                    ui.label(tr("practice.empty"));
+                                    ui.label("");
+                                },
+                            }
+                            ui.vertical(|ui| {
+                                if ui.button(tr("practice.save")).clicked() {
+                                    command = Some(Command::SaveState(i));
+                                }
+                                if ui.add_enabled(slot.is_some(), egui::Button::new(tr("practice.load"))).clicked() {
+                                    command = Some(Command::LoadState(i));
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                ui.separator();
+                ui.label(tr("practice.death")).on_hover_text(tr("practice.death_hint"));
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.address)
+                        .hint_text("$0000")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(50.0));
+                    egui::ComboBox::from_id_source("practice_comparison")
+                        .selected_text(tr(self.comparison.key()))
+                        .show_ui(ui, |ui| {
+                            for comparison in Comparison::ALL {
+                                ui.selectable_value(&mut self.comparison, comparison, tr(comparison.key()));
+                            }
+                        });
+                    ui.add_enabled(self.comparison.uses_value(), egui::TextEdit::singleline(&mut self.value)
+                        .hint_text("$00")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(30.0));
+                });
+                ui.horizontal(|ui| {
+                    ui.label(tr("practice.death_slot"));
+                    ui.add(egui::DragValue::new(&mut self.death_slot).clamp_range(0..=SAVE_SLOTS - 1));
+                });
+                ui.horizontal(|ui| {
+                    if ui.button(tr("practice.set")).clicked() {
+                        match self.parse_death_condition() {
+                            Ok(condition) => {
+                                self.set_death_condition(nes, Some(condition));
+                                self.error = None;
+                            },
+                            Err(error) => self.error = Some(error),
+                        }
+                    }
+                    if ui.add_enabled(self.death.is_some(), egui::Button::new(tr("practice.clear"))).clicked() {
+                        self.set_death_condition(nes, None);
+                    }
+                });
+                if let Some(condition) = self.death_condition() {
+                    ui.monospace(describe(&condition));
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+
+        self.open = open;
+        command
+    }
+
+    /// The death condition typed into the window
+    fn parse_death_condition(&self) -> Result<DeathCondition, String> {
+        let address = parse_address(Some(self.address.trim()))?;
+        let value = if self.comparison.uses_value() { parse_value(self.value.trim())? } else { 0 };
+        Ok(DeathCondition { address, comparison: self.comparison, value })
+    }
+}
+
+/// A death condition as shown under the fields, e.g. `$0075 went down`
+fn describe(condition: &DeathCondition) -> String {
+    if condition.comparison.uses_value() {
+        format!("${:04X} {} ${:02X}", condition.address, tr(condition.comparison.key()), condition.value)
+    } else {
+        format!("${:04X} {}", condition.address, tr(condition.comparison.key()))
+    }
+}
@@ -0,0 +1,63 @@
+//! Timing how long each part of the console takes to emulate, for spotting performance regressions.
+
+use std::time::Duration;
+
+use web_time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Component {
+  /// The CPU, along with the cartridge clocks and DMA that run alongside it
+  Cpu,
+  Ppu,
+  Apu,
+}
+
+/// Time spent in each component while profiling was on
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+  pub cpu: Duration,
+  pub ppu: Duration,
+  pub apu: Duration,
+}
+
+impl Profile {
+  fn add(&mut self, component: Component, time: Duration) {
+    match component {
+      Component::Cpu => self.cpu += time,
+      Component::Ppu => self.ppu += time,
+      Component::Apu => self.apu += time,
+    }
+  }
+
+  /// Time spent in any component
+  pub fn total(&self) -> Duration {
+    self.cpu + self.ppu + self.apu
+  }
+
+  /// How much of `elapsed` went on each component, as percentages of CPU, PPU and APU time, and
+  /// whatever's left over for everything else
+  pub fn percentages(&self, elapsed: Duration) -> [f64; 4] {
+    let elapsed = elapsed.as_secs_f64().max(f64::EPSILON);
+    let percent = |time: Duration| time.as_secs_f64() / elapsed * 100.0;
+    let [cpu, ppu, apu] = [percent(self.cpu), percent(self.ppu), percent(self.apu)];
+    [cpu, ppu, apu, (100.0 - cpu - ppu - apu).max(0.0)]
+  }
+}
+
+/// Times consecutive stretches of a clock, crediting each one to a component
+pub struct Laps {
+  last: Instant,
+}
+
+impl Laps {
+  pub fn start() -> Self {
+    Self { last: Instant::now() }
+  }
+
+  /// Credit the time since the last lap to `component`
+  pub fn lap(&mut self, profile: &mut Profile, component: Component) {
+    let now = Instant::now();
+    profile.add(component, now - self.last);
+    self.last = now;
+  }
+}
@@ -0,0 +1,144 @@
+use eframe::egui;
+
+use crate::i18n::tr;
+use crate::nes::Nes;
+
+/// Size of the console's internal RAM, which is all the search looks through
+const RAM_SIZE: u16 = 0x0800;
+
+/// Most results listed at once, narrowing the search further is more useful than scrolling thousands
+const MAX_RESULTS_SHOWN: usize = 200;
+
+/// How a value has to have moved since the last snapshot to stay in the results
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Filter {
+    Changed,
+    Unchanged,
+    Greater,
+    Less,
+}
+
+impl Filter {
+    const ALL: [Filter; 4] = [Filter::Changed, Filter::Unchanged, Filter::Greater, Filter::Less];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Filter::Changed => "ram_search.changed",
+            Filter::Unchanged => "ram_search.unchanged",
+            Filter::Greater => "ram_search.greater",
+            Filter::Less => "ram_search.less",
+        }
+    }
+
+    fn matches(&self, previous: u8, current: u8) -> bool {
+        match self {
+            Filter::Changed => current != previous,
+            Filter::Unchanged => current == previous,
+            Filter::Greater => current > previous,
+            Filter::Less => current < previous,
+        }
+    }
+}
+
+/// Window for tracking down where a game keeps a value, e.g. lives or health, by snapshotting RAM
+/// and narrowing down the addresses that moved the way the value did
+#[derive(Default)]
+pub struct RamSearch {
+    pub open: bool,
+    /// RAM as it was when the search started or was last filtered
+    snapshot: Vec<u8>,
+    /// Addresses that have passed every filter so far
+    candidates: Vec<u16>,
+    /// Addresses pinned to the watch list
+    pinned: Vec<u16>,
+}
+
+impl RamSearch {
+    /// Draw the window, if open. Freezing an address writes to the console, so it needs `nes` mutably.
+    pub fn show(&mut self, ctx: &egui::Context, nes: &mut Nes) {
+        let mut open = self.open;
+
+        egui::Window::new(tr("ram_search.title"))
+            .id(egui::Id::new("ram_search_window"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button(tr("ram_search.new_search")).clicked() {
+                        self.snapshot = read_ram(nes);
+                        self.candidates = (0..RAM_SIZE).collect();
+                    }
+                    ui.add_enabled_ui(!self.snapshot.is_empty(), |ui| {
+                        for filter in Filter::ALL {
+                            if ui.button(tr(filter.key())).clicked() {
+                                self.apply_filter(nes, filter);
+                            }
+                        }
+                    });
+                });
+
+                if !self.snapshot.is_empty() {
+                    ui.label(format!("{}: {}", tr("ram_search.results"), self.candidates.len()));
+                    egui::ScrollArea::vertical()
+                        .id_source("ram_search_results")
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("ram_search_results_grid").striped(true).show(ui, |ui| {
+                                for &address in self.candidates.iter().take(MAX_RESULTS_SHOWN) {
+                                    let previous = self.snapshot[address as usize];
+                                    ui.monospace(format!("${:04X}", address));
+                                    ui.monospace(format!("${:02X} -> ${:02X}", previous, nes.peek(address)));
+                                    if ui.button(tr("ram_search.pin")).clicked() && !self.pinned.contains(&address) {
+                                        self.pinned.push(address);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                }
+
+                if !self.pinned.is_empty() {
+                    ui.separator();
+                    ui.label(tr("ram_search.watch_list"));
+                    let mut unpinned = None;
+                    egui::Grid::new("ram_search_watch_grid").striped(true).show(ui, |ui| {
+                        for &address in &self.pinned {
+                            let value = nes.peek(address);
+                            ui.monospace(format!("${:04X}", address));
+                            ui.monospace(format!("${:02X}", value));
+                            ui.monospace(format!("{:3}", value));
+
+                            let mut frozen = nes.is_frozen(address);
+                            if ui.checkbox(&mut frozen, tr("ram_search.freeze")).changed() {
+                                if frozen {
+                                    nes.freeze(address, value);
+                                } else {
+                                    nes.unfreeze(address);
+                                }
+                            }
+                            if ui.button(tr("ram_search.unpin")).clicked() {
+                                unpinned = Some(address);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(address) = unpinned {
+                        self.pinned.retain(|pinned| *pinned != address);
+                        nes.unfreeze(address);
+                    }
+                }
+            });
+
+        self.open = open;
+    }
+
+    /// Drop every candidate that doesn't match `filter`, and snapshot RAM again for the next comparison
+    fn apply_filter(&mut self, nes: &Nes, filter: Filter) {
+        let current = read_ram(nes);
+        self.candidates.retain(|&address| filter.matches(self.snapshot[address as usize], current[address as usize]));
+        self.snapshot = current;
+    }
+}
+
+fn read_ram(nes: &Nes) -> Vec<u8> {
+    (0..RAM_SIZE).map(|address| nes.peek(address)).collect()
+}
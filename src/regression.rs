@@ -0,0 +1,169 @@
+//! A regression pack of games played through recorded input, for checking before a release that they
+//! still end up where they should.
+//!
+//! The manifest lists one case per line, with paths relative to the manifest:
+//!
+//! ```text
+//! # ROM, movie (or - for none) and what to check once it's played
+//! smb.nes smb-warp.fm2 $075F=03 $0760=00
+//! zelda.nes zelda-intro.fm2 frames=900 hash=ee971d276a15f092
+//! ```
+//!
+//! `hash=` checks the XXH3 hash of the last frame drawn, `$XXXX=YY` the byte the CPU reads from an
+//! address, and `frames=` runs that many frames in all rather than stopping when the movie does. Failed
+//! cases report what they found, so a new case's expected values can be filled in from a first run.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::cartridge::Cartridge;
+use crate::movie::Movie;
+use crate::nes::Nes;
+
+/// What a case expects once its movie's played
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+  FrameHash(u64),
+  Ram(u16, u8),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegressionCase {
+  /// The ROM and movie as the manifest names them
+  pub name: String,
+  pub rom: PathBuf,
+  pub movie: Option<PathBuf>,
+  /// Frames to run in all, if not just the movie's length
+  pub frames: Option<u64>,
+  pub checks: Vec<Check>,
+}
+
+/// Read the cases from a manifest, resolving their paths against `base`
+pub fn parse_manifest(text: &str, base: &Path) -> Result<Vec<RegressionCase>, String> {
+  let mut cases = vec![];
+  for (number, line) in text.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let error = |message: String| format!("Line {}: {}", number + 1, message);
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [rom, movie, options @ ..] = fields.as_slice() else {
+      return Err(error("expected a ROM, a movie and what to check".to_string()));
+    };
+
+    let mut case = RegressionCase {
+      name: format!("{} {}", rom, movie),
+      rom: base.join(rom),
+      movie: (*movie != "-").then(|| base.join(movie)),
+      frames: None,
+      checks: vec![],
+    };
+    for option in options {
+      let parsed = if let Some(frames) = option.strip_prefix("frames=") {
+        frames.parse().ok().map(|frames| case.frames = Some(frames))
+      } else if let Some(hash) = option.strip_prefix("hash=") {
+        u64::from_str_radix(hash, 16).ok().map(|hash| case.checks.push(Check::FrameHash(hash)))
+      } else if let Some((address, value)) = option.strip_prefix('$').and_then(|ram| ram.split_once('=')) {
+        u16::from_str_radix(address, 16).ok()
+          .zip(u8::from_str_radix(value, 16).ok())
+          .map(|(address, value)| case.checks.push(Check::Ram(address, value)))
+      } else {
+        None
+      };
+      parsed.ok_or_else(|| error(format!("can't make sense of {:?}", option)))?;
+    }
+    if case.checks.is_empty() {
+      return Err(error(format!("{} doesn't check anything", case.name)));
+    }
+    if case.movie.is_none() && case.frames.is_none() {
+      return Err(error(format!("{} needs frames= to know how long to run without a movie", case.name)));
+    }
+    cases.push(case);
+  }
+  Ok(cases)
+}
+
+/// Read a manifest file, with its cases' paths relative to where it is
+pub fn load_manifest(path: &Path) -> Result<Vec<RegressionCase>, String> {
+  let text = std::fs::read_to_string(path).map_err(|error| format!("Couldn't read {}: {}", path.display(), error))?;
+  let base = path.parent().unwrap_or(Path::new(""));
+  parse_manifest(&text, base).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+  Passed,
+  /// What didn't match, e.g. "$075F is 02, expected 03"
+  Failed(Vec<String>),
+  /// The case couldn't be run, e.g. its ROM is missing
+  Error(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaseReport {
+  pub name: String,
+  pub verdict: Verdict,
+  pub frames: u64,
+}
+
+impl CaseReport {
+  pub fn passed(&self) -> bool {
+    self.verdict == Verdict::Passed
+  }
+}
+
+impl fmt::Display for CaseReport {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.verdict {
+      Verdict::Passed => write!(f, "PASS  {} ({} frames)", self.name, self.frames),
+      Verdict::Failed(mismatches) => write!(f, "FAIL  {} after {} frames: {}", self.name, self.frames, mismatches.join(", ")),
+      Verdict::Error(error) => write!(f, "ERROR {}: {}", self.name, error),
+    }
+  }
+}
+
+/// Play a case's movie against its ROM from power on and check where it ends up
+pub fn run_case(case: &RegressionCase) -> CaseReport {
+  let report = |verdict, frames| CaseReport { name: case.name.clone(), verdict, frames };
+  let read = |path: &Path| std::fs::read(path).map_err(|error| format!("Couldn't read {}: {}", path.display(), error));
+
+  let cartridge = match read(&case.rom).and_then(|bytes| Cartridge::from_bytes(bytes).map_err(|error| error.to_string())) {
+    Ok(cartridge) => cartridge,
+    Err(error) => return report(Verdict::Error(error), 0),
+  };
+  let movie = match &case.movie {
+    Some(path) => match read(path).and_then(|bytes| Movie::from_fm2(&String::from_utf8_lossy(&bytes))) {
+      Ok(movie) => movie,
+      Err(error) => return report(Verdict::Error(format!("{}: {}", path.display(), error)), 0),
+    },
+    None => Movie::default(),
+  };
+
+  let mut nes = Nes::new();
+  nes.insert_cartridge(cartridge);
+  let frames = case.frames.unwrap_or(movie.len() as u64);
+  for frame in 0..frames {
+    movie.play_frame(&mut nes, frame as usize);
+  }
+
+  let mismatches: Vec<String> = case.checks.iter().filter_map(|check| match *check {
+    Check::FrameHash(expected) => {
+      let hash = nes.frame_hash();
+      (hash != expected).then(|| format!("frame hash is {:016x}, expected {:016x}", hash, expected))
+    },
+    Check::Ram(address, expected) => {
+      let value = nes.peek(address);
+      (value != expected).then(|| format!("${:04X} is {:02X}, expected {:02X}", address, value, expected))
+    },
+  }).collect();
+  let verdict = if mismatches.is_empty() { Verdict::Passed } else { Verdict::Failed(mismatches) };
+  report(verdict, frames)
+}
+
+/// Run every case, as many at once as there are cores, reporting them in the manifest's order
+pub fn run_all(cases: &[RegressionCase]) -> Vec<CaseReport> {
+  cases.par_iter().map(run_case).collect()
+}
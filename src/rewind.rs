@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+
+use crate::nes::{Nes, SaveState};
+
+/// Frames between snapshots, so rewinding one snapshot per UI frame runs backwards at double speed
+const FRAMES_PER_SNAPSHOT: u64 = 2;
+
+/// Snapshots kept, about 10 seconds' worth
+const MAX_SNAPSHOTS: usize = 300;
+
+/// Recent save states taken as the game runs, for stepping back through while rewind is held
+#[derive(Default)]
+pub struct Rewind {
+    snapshots: VecDeque<SaveState>,
+    /// Frame the newest snapshot was taken on
+    last_frame: Option<u64>,
+}
+
+impl Rewind {
+    /// Take a snapshot, if enough frames have run since the last one
+    pub fn record(&mut self, nes: &Nes) {
+        let frame = nes.frame_count();
+        // Anything from before a reset or a state load is from another timeline
+        if self.last_frame.is_some_and(|last| frame < last) {
+            self.clear();
+        }
+        if self.last_frame.is_some_and(|last| frame < last + FRAMES_PER_SNAPSHOT) {
+            return;
+        }
+        self.snapshots.push_back(nes.save_state());
+        self.last_frame = Some(frame);
+        while self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Go back to the newest snapshot and drop it, returning false if there aren't any left.
+    /// The oldest one is kept, so holding rewind stops there rather than running out.
+    pub fn step_back(&mut self, nes: &mut Nes) -> bool {
+        let snapshot = if self.snapshots.len() > 1 { self.snapshots.pop_back() } else { self.snapshots.back().cloned() };
+        let Some(snapshot) = snapshot else {
+            return false;
+        };
+        nes.load_state(&snapshot);
+        self.last_frame = Some(nes.frame_count());
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.last_frame = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
@@ -0,0 +1,222 @@
+//! Known good dumps, for naming a ROM and putting its header right when it's wrong.
+//!
+//! The database is a No-Intro style XML dat giving each dump's size, hashes and the header it should
+//! have. The hashes cover the header as well, so a dump with a bad header doesn't match its own entry.
+//! Instead, each entry the same size with a different header has its header tried in place of the
+//! ROM's, and if the CRC32 then matches, that's the header the ROM should have had.
+
+/// iNES headers are always 16 bytes
+pub const HEADER_LENGTH: usize = 16;
+
+/// One dump in the database
+#[derive(Clone, Debug)]
+pub struct RomEntry {
+  pub name: String,
+  /// The whole file, header included
+  size: usize,
+  crc32: u32,
+  sha256: Option<String>,
+  header: Option<[u8; HEADER_LENGTH]>,
+}
+
+/// A header replaced with the one the database has for the dump
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderFix {
+  /// The game the ROM turned out to be
+  pub name: String,
+  /// What changed, e.g. "mapper 4 → 1", in the order they're read from the header
+  pub changes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RomDatabase {
+  entries: Vec<RomEntry>,
+}
+
+impl RomDatabase {
+  /// Read the database from a dat. Entries without a size or CRC32 are skipped, since they can't be
+  /// matched against anything.
+  pub fn parse(xml: &str) -> Result<Self, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|error| error.to_string())?;
+    let entries = document.descendants()
+      .filter(|node| node.has_tag_name("rom"))
+      .filter_map(|rom| {
+        let name = rom.parent_element()?.attribute("name")?.to_string();
+        Some(RomEntry {
+          name,
+          size: rom.attribute("size")?.parse().ok()?,
+          crc32: u32::from_str_radix(rom.attribute("crc")?, 16).ok()?,
+          sha256: rom.attribute("sha256").map(str::to_lowercase),
+          header: rom.attribute("header").and_then(parse_header),
+        })
+      })
+      .collect();
+    Ok(Self { entries })
+  }
+
+  pub fn load(path: &str) -> Result<Self, String> {
+    let xml = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    Self::parse(&xml)
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  /// The dump whose whole file, header included, has this SHA-256
+  pub fn find_sha256(&self, sha256: &str) -> Option<&RomEntry> {
+    self.entries.iter().find(|entry| entry.sha256.as_deref() == Some(sha256))
+  }
+
+  /// Put the right header on `rom` if it's a known dump with the wrong one, saying what changed.
+  /// Returns `None`, leaving `rom` alone, if it already matches or isn't in the database.
+  pub fn fix_header(&self, rom: &mut [u8]) -> Option<HeaderFix> {
+    if rom.len() < HEADER_LENGTH {
+      return None;
+    }
+    let crc = crc32(rom);
+    let candidates = self.entries.iter().filter(|entry| entry.size == rom.len());
+    if candidates.clone().any(|entry| entry.crc32 == crc) {
+      return None;
+    }
+
+    // CRC32 is linear, so for files the same length, swapping the header changes the CRC by the CRC of
+    // the difference between the headers followed by as many zeros as the rest of the file
+    let shift = zeros_operator(rom.len() - HEADER_LENGTH);
+    let current: [u8; HEADER_LENGTH] = rom[..HEADER_LENGTH].try_into().unwrap();
+    let entry = candidates.filter(|entry| entry.header.is_some_and(|header| header != current)).find(|entry| {
+      let header = entry.header.unwrap();
+      let difference: Vec<u8> = header.iter().zip(current).map(|(a, b)| a ^ b).collect();
+      crc ^ gf2_times(&shift, crc32_update(0, &difference)) == entry.crc32
+    })?;
+
+    let header = entry.header.unwrap();
+    rom[..HEADER_LENGTH].copy_from_slice(&header);
+    Some(HeaderFix { name: entry.name.clone(), changes: header_changes(&current, &header) })
+  }
+}
+
+/// A header written as space separated hex bytes, like the dat's "4E 45 53 1A ..."
+fn parse_header(text: &str) -> Option<[u8; HEADER_LENGTH]> {
+  let bytes: Vec<u8> = text.split_whitespace().map(|byte| u8::from_str_radix(byte, 16).ok()).collect::<Option<_>>()?;
+  bytes.try_into().ok()
+}
+
+fn mapper(header: &[u8; HEADER_LENGTH]) -> u16 {
+  let mapper = (header[6] >> 4) as u16 | (header[7] & 0xF0) as u16;
+  // NES 2.0 has another four bits of mapper number
+  if header[7] & 0x0C == 0x08 { mapper | ((header[8] & 0x0F) as u16) << 8 } else { mapper }
+}
+
+fn mirroring(header: &[u8; HEADER_LENGTH]) -> &'static str {
+  match header[6] & 0b0000_1001 {
+    0b0000_0000 => "horizontal",
+    0b0000_0001 => "vertical",
+    _ => "four screen",
+  }
+}
+
+/// PRG RAM size in bytes, which only NES 2.0 headers give. iNES ones just say whether there's a battery.
+fn prg_ram(header: &[u8; HEADER_LENGTH]) -> Option<u32> {
+  (header[7] & 0x0C == 0x08).then(|| {
+    let shifts = [header[10] & 0x0F, header[10] >> 4];
+    shifts.into_iter().filter(|shift| *shift > 0).map(|shift| 64 << shift).sum()
+  })
+}
+
+/// Describe the differences that matter between two headers
+fn header_changes(old: &[u8; HEADER_LENGTH], new: &[u8; HEADER_LENGTH]) -> Vec<String> {
+  let mut changes = vec![];
+  if mapper(old) != mapper(new) {
+    changes.push(format!("mapper {} → {}", mapper(old), mapper(new)));
+  }
+  if old[4] != new[4] || old[5] != new[5] {
+    changes.push(format!("PRG/CHR banks {}/{} → {}/{}", old[4], old[5], new[4], new[5]));
+  }
+  if mirroring(old) != mirroring(new) {
+    changes.push(format!("{} → {} mirroring", mirroring(old), mirroring(new)));
+  }
+  let battery = |header: &[u8; HEADER_LENGTH]| header[6] & 0b0000_0010 != 0;
+  if battery(old) != battery(new) {
+    changes.push(if battery(new) { "battery added".to_string() } else { "battery removed".to_string() });
+  }
+  if let Some(ram) = prg_ram(new).filter(|ram| prg_ram(old) != Some(*ram)) {
+    changes.push(format!("{} KB PRG RAM", ram / 1024));
+  }
+  if changes.is_empty() {
+    changes.push("NES 2.0 header".to_string());
+  }
+  changes
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+  let mut table = [0; 256];
+  let mut i = 0;
+  while i < 256 {
+    let mut crc = i as u32;
+    let mut bit = 0;
+    while bit < 8 {
+      crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+      bit += 1;
+    }
+    table[i] = crc;
+    i += 1;
+  }
+  table
+}
+
+/// Run `bytes` through the CRC register, without the inversions either side
+fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+  for byte in bytes {
+    crc = CRC_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+  }
+  crc
+}
+
+/// The CRC32 used by zip, PNG and the dats
+pub fn crc32(bytes: &[u8]) -> u32 {
+  !crc32_update(!0, bytes)
+}
+
+/// Multiply a vector by a matrix over GF(2), the matrix given as its columns
+fn gf2_times(matrix: &[u32; 32], mut vector: u32) -> u32 {
+  let mut sum = 0;
+  let mut column = 0;
+  while vector != 0 {
+    if vector & 1 != 0 {
+      sum ^= matrix[column];
+    }
+    vector >>= 1;
+    column += 1;
+  }
+  sum
+}
+
+fn gf2_square(matrix: &[u32; 32]) -> [u32; 32] {
+  std::array::from_fn(|column| gf2_times(matrix, matrix[column]))
+}
+
+/// The matrix that runs the CRC register through `length` zero bytes, built by repeated squaring
+/// the way zlib's crc32_combine does it
+fn zeros_operator(mut length: usize) -> [u32; 32] {
+  let mut result: [u32; 32] = std::array::from_fn(|column| 1 << column);
+  // One zero bit, then squared up to one zero byte
+  let mut operator: [u32; 32] = std::array::from_fn(|column| if column == 0 { 0xEDB8_8320 } else { 1 << (column - 1) });
+  for _ in 0..3 {
+    operator = gf2_square(&operator);
+  }
+  while length > 0 {
+    if length & 1 != 0 {
+      result = std::array::from_fn(|column| gf2_times(&operator, result[column]));
+    }
+    length >>= 1;
+    operator = gf2_square(&operator);
+  }
+  result
+}
@@ -0,0 +1,60 @@
+use eframe::egui;
+
+use crate::i18n::tr;
+use crate::mappers::MAPPERS;
+
+/// Dialog saying why a ROM couldn't be loaded, with the mappers we do support in case that's why
+#[derive(Default)]
+pub struct RomErrorWindow {
+    message: Option<String>,
+}
+
+impl RomErrorWindow {
+    pub fn open(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Draw the dialog, if there's an error to show
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let Some(message) = &self.message else {
+            return;
+        };
+        let mut close = false;
+
+        egui::Window::new(tr("rom_error.title"))
+            .id(egui::Id::new("rom_error_window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(message);
+                ui.collapsing(tr("rom_error.supported"), |ui| {
+                    egui::Grid::new("rom_error_mappers").striped(true).show(ui, |ui| {
+                        for key in ["rom_error.mapper", "rom_error.name", "rom_error.board", "rom_error.prg_ram", "rom_error.irq", "rom_error.audio"] {
+                            ui.strong(tr(key));
+                        }
+                        ui.end_row();
+                        let check = |supported: bool| if supported { "✔" } else { "" };
+                        for info in MAPPERS {
+                            ui.label(info.id.to_string());
+                            ui.label(info.name);
+                            ui.label(info.board);
+                            ui.label(check(info.prg_ram));
+                            ui.label(check(info.irq));
+                            ui.label(check(info.audio));
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.vertical_centered(|ui| {
+                    if ui.button(tr("rom_error.ok")).clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if close {
+            self.message = None;
+        }
+    }
+}
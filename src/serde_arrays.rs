@@ -0,0 +1,46 @@
+//! Serde only implements its traits for arrays of up to 32 elements, so the bigger ones
+//! in the core state go through these with `#[serde(with = "...")]`.
+
+use serde::de::{DeserializeOwned, Error};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// For `[T; N]` of any size
+pub mod big {
+  use super::*;
+
+  pub fn serialize<S: Serializer, T: Serialize, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(array.iter())
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>, T: DeserializeOwned, const N: usize>(deserializer: D) -> Result<[T; N], D::Error> {
+    let items = Vec::<T>::deserialize(deserializer)?;
+    let len = items.len();
+    items.try_into().map_err(|_| D::Error::invalid_length(len, &format!("an array of {} elements", N).as_str()))
+  }
+}
+
+/// For `[[T; N]; M]` where the inner arrays are too big
+pub mod nested {
+  use super::*;
+
+  pub fn serialize<S: Serializer, T: Serialize, const N: usize, const M: usize>(array: &[[T; N]; M], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut seq = serializer.serialize_seq(Some(M))?;
+    for inner in array {
+      seq.serialize_element(inner.as_slice())?;
+    }
+    seq.end()
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>, T: DeserializeOwned, const N: usize, const M: usize>(deserializer: D) -> Result<[[T; N]; M], D::Error> {
+    let rows = Vec::<Vec<T>>::deserialize(deserializer)?;
+    let row_count = rows.len();
+    let rows = rows.into_iter()
+      .map(|row| {
+        let len = row.len();
+        row.try_into().map_err(|_| D::Error::invalid_length(len, &format!("an array of {} elements", N).as_str()))
+      })
+      .collect::<Result<Vec<[T; N]>, D::Error>>()?;
+    rows.try_into().map_err(|_| D::Error::invalid_length(row_count, &format!("an array of {} arrays", M).as_str()))
+  }
+}
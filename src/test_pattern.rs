@@ -0,0 +1,189 @@
+//! A test pattern that runs without a ROM, for checking palettes, colour emphasis, greyscale and video
+//! filters without loading a game.
+//!
+//! It's a little NROM program that fills the screen with bars of 12 hues of one brightness row of the
+//! NES palette, on that row's grey. The controller changes what's shown:
+//!
+//! - A steps through the 8 combinations of PPUMASK's emphasis bits
+//! - B toggles greyscale
+//! - Left and Right, or Select, move between the 4 brightness rows
+//! - Start puts everything back
+
+/// What the buttons do, for telling the user when the pattern's loaded
+pub const CONTROLS: &str = "A: colour emphasis, B: greyscale, Left/Right: brightness, Start: reset";
+
+/// Where the palette entries for a row go in PRG ROM, as hues ORed with the row
+const TEMPLATE: usize = 0x1F0;
+/// Where the nametable and attribute table copied to $2000 go in PRG ROM
+const SCREEN: usize = 0x200;
+/// Where the RTI the unused interrupt vectors point to goes
+const RETURN: usize = PROGRAM.len();
+
+/// Hues of each background palette entry, with the row's grey as the backdrop in entry 0
+const HUES: [u8; 16] = [0x0, 0x1, 0x2, 0x3, 0x0, 0x4, 0x5, 0x6, 0x0, 0x7, 0x8, 0x9, 0x0, 0xA, 0xB, 0xC];
+
+/// The 12 bars, each 2 tiles wide, start this many tiles from the left
+const FIRST_BAR_COLUMN: usize = 4;
+/// Tile rows the bars run from and to
+const BAR_ROWS: std::ops::Range<usize> = 6..24;
+
+/// Zero page: $00 brightness row, $01 PPUMASK's emphasis and greyscale bits, $02 and $03 buttons held last
+/// frame and this one, $04 buttons just pressed, $05 row shifted into the high nibble, $10-$11 copy pointer
+const PROGRAM: [u8; 0xDE] = [
+  // Start up with rendering off, then wait out the PPU warming up
+  0x78,             // SEI
+  0xD8,             // CLD
+  0xA2, 0xFF,       // LDX #$FF
+  0x9A,             // TXS
+  0xA9, 0x40,       // LDA #$40
+  0x8D, 0x17, 0x40, // STA $4017
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x00, 0x20, // STA $2000
+  0x8D, 0x01, 0x20, // STA $2001
+  0x85, 0x00,       // STA $00
+  0x85, 0x01,       // STA $01
+  0x85, 0x02,       // STA $02
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL $C018
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL $C01D
+  // Copy the screen to nametable 0
+  0xA9, 0x20,       // LDA #$20
+  0x8D, 0x06, 0x20, // STA $2006
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x06, 0x20, // STA $2006
+  0xA9, 0x00,       // LDA #$00
+  0x85, 0x10,       // STA $10
+  0xA9, 0xC2,       // LDA #$C2
+  0x85, 0x11,       // STA $11
+  0xA2, 0x04,       // LDX #$04
+  0xA0, 0x00,       // LDY #$00
+  0xB1, 0x10,       // LDA ($10),Y
+  0x8D, 0x07, 0x20, // STA $2007
+  0xC8,             // INY
+  0xD0, 0xF8,       // BNE $C038
+  0xE6, 0x11,       // INC $11
+  0xCA,             // DEX
+  0xD0, 0xF3,       // BNE $C038
+  // Each frame, wait for vblank then read the controller
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL $C045
+  0xA9, 0x01,       // LDA #$01
+  0x8D, 0x16, 0x40, // STA $4016
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x16, 0x40, // STA $4016
+  0xA2, 0x08,       // LDX #$08
+  0xAD, 0x16, 0x40, // LDA $4016
+  0x4A,             // LSR A
+  0x26, 0x03,       // ROL $03
+  0xCA,             // DEX
+  0xD0, 0xF7,       // BNE $C056
+  0xA5, 0x02,       // LDA $02
+  0x49, 0xFF,       // EOR #$FF
+  0x25, 0x03,       // AND $03
+  0x85, 0x04,       // STA $04
+  0xA5, 0x03,       // LDA $03
+  0x85, 0x02,       // STA $02
+  // A steps through the emphasis bits, B flips greyscale
+  0x24, 0x04,       // BIT $04
+  0x10, 0x07,       // BPL $C076
+  0xA5, 0x01,       // LDA $01
+  0x18,             // CLC
+  0x69, 0x20,       // ADC #$20
+  0x85, 0x01,       // STA $01
+  0x24, 0x04,       // BIT $04
+  0x50, 0x06,       // BVC $C080
+  0xA5, 0x01,       // LDA $01
+  0x49, 0x01,       // EOR #$01
+  0x85, 0x01,       // STA $01
+  // Select or Right goes to the next row and Left the one before, Start resets
+  0xA5, 0x04,       // LDA $04
+  0x29, 0x21,       // AND #$21
+  0xF0, 0x02,       // BEQ $C088
+  0xE6, 0x00,       // INC $00
+  0xA5, 0x04,       // LDA $04
+  0x29, 0x02,       // AND #$02
+  0xF0, 0x02,       // BEQ $C090
+  0xC6, 0x00,       // DEC $00
+  0xA5, 0x00,       // LDA $00
+  0x29, 0x03,       // AND #$03
+  0x85, 0x00,       // STA $00
+  0xA5, 0x04,       // LDA $04
+  0x29, 0x10,       // AND #$10
+  0xF0, 0x06,       // BEQ $C0A2
+  0xA9, 0x00,       // LDA #$00
+  0x85, 0x00,       // STA $00
+  0x85, 0x01,       // STA $01
+  // Write the row's palette, then the scroll and PPUMASK
+  0xA9, 0x3F,       // LDA #$3F
+  0x8D, 0x06, 0x20, // STA $2006
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x06, 0x20, // STA $2006
+  0xA5, 0x00,       // LDA $00
+  0x0A,             // ASL A
+  0x0A,             // ASL A
+  0x0A,             // ASL A
+  0x0A,             // ASL A
+  0x85, 0x05,       // STA $05
+  0xA2, 0x00,       // LDX #$00
+  0xBD, 0xF0, 0xC1, // LDA $C1F0,X
+  0x05, 0x05,       // ORA $05
+  0x8D, 0x07, 0x20, // STA $2007
+  0xE8,             // INX
+  0xE0, 0x10,       // CPX #$10
+  0xD0, 0xF3,       // BNE $C0B6
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x06, 0x20, // STA $2006
+  0x8D, 0x06, 0x20, // STA $2006
+  0x8D, 0x00, 0x20, // STA $2000
+  0x8D, 0x05, 0x20, // STA $2005
+  0x8D, 0x05, 0x20, // STA $2005
+  0xA5, 0x01,       // LDA $01
+  0x09, 0x0A,       // ORA #$0A
+  0x8D, 0x01, 0x20, // STA $2001
+  0x4C, 0x45, 0xC0, // JMP $C045
+];
+
+/// Nametable 0 and its attributes, as copied to $2000. Each bar's a solid tile of its palette entry, and
+/// the bars are aligned so each one gets an attribute quadrant column to itself.
+fn screen() -> Vec<u8> {
+  let mut screen = vec![0; 0x400];
+  for row in BAR_ROWS {
+    for bar in 0..12 {
+      let column = FIRST_BAR_COLUMN + bar * 2;
+      screen[row * 32 + column..row * 32 + column + 2].fill(bar as u8 % 3 + 1);
+    }
+  }
+  let palette = |column: usize| match column.checked_sub(FIRST_BAR_COLUMN) {
+    Some(offset) if offset < 24 => (offset / 2 / 3) as u8,
+    _ => 0,
+  };
+  for attribute in 0..64 {
+    let (left, right) = (palette(attribute % 8 * 4), palette(attribute % 8 * 4 + 2));
+    screen[0x3C0 + attribute] = left | right << 2 | left << 4 | right << 6;
+  }
+  screen
+}
+
+/// The test pattern as an iNES image, 16 KB of PRG and 8 KB of CHR
+pub fn rom() -> Vec<u8> {
+  let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+  let mut prg = vec![0; 0x4000];
+  prg[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+  prg[RETURN] = 0x40; // RTI
+  prg[TEMPLATE..TEMPLATE + HUES.len()].copy_from_slice(&HUES);
+  prg[SCREEN..SCREEN + 0x400].copy_from_slice(&screen());
+  let [return_low, return_high] = (0xC000 + RETURN as u16).to_le_bytes();
+  prg[0x3FFA..].copy_from_slice(&[return_low, return_high, 0x00, 0xC0, return_low, return_high]);
+  rom.extend(prg);
+
+  // Tiles 1-3 are solid in palette entries 1-3, and everything else is blank
+  let mut chr = vec![0; 0x2000];
+  for tile in 1..4 {
+    let planes = &mut chr[tile * 16..tile * 16 + 16];
+    planes[..8].fill(if tile & 1 != 0 { 0xFF } else { 0 });
+    planes[8..].fill(if tile & 2 != 0 { 0xFF } else { 0 });
+  }
+  rom.extend(chr);
+  rom
+}
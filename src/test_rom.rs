@@ -0,0 +1,106 @@
+//! Running test ROMs headless and reading back their results.
+//!
+//! Blargg's tests, and most nesdev ones since, report through PRG RAM: $6000 holds $80 while the test
+//! runs, $81 if it wants the reset button pressed, and otherwise its result, 0 meaning it passed.
+//! $6001-$6003 hold DE B0 61 to show the rest is meaningful, and the text the test printed follows
+//! from $6004, null terminated. Older tests only print their result on screen.
+
+use crate::nes::Nes;
+
+const STATUS: u16 = 0x6000;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const TEXT: u16 = 0x6004;
+
+/// Frames to wait before pressing reset when a test asks for it, which should be at least 100ms
+const RESET_DELAY_FRAMES: u64 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestStatus {
+  Running,
+  /// The test wants the console reset to carry on, e.g. to check what survives it
+  ResetRequested,
+  /// The test finished, with 0 for a pass or the number of whatever failed
+  Finished(u8),
+}
+
+/// What a test ROM reported by the time it finished or ran out of time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestOutcome {
+  /// `None` if it never reported a result through $6000
+  pub result: Option<u8>,
+  /// The text it wrote to $6004 onwards, or failing that, what's on screen
+  pub text: String,
+  pub frames: u64,
+}
+
+impl TestOutcome {
+  pub fn passed(&self) -> bool {
+    self.result == Some(0)
+  }
+}
+
+/// The status the test's reporting at $6000, or `None` if it isn't reporting there (yet)
+pub fn status(nes: &Nes) -> Option<TestStatus> {
+  let signature = [nes.peek(STATUS + 1), nes.peek(STATUS + 2), nes.peek(STATUS + 3)];
+  if signature != SIGNATURE {
+    return None;
+  }
+  Some(match nes.peek(STATUS) {
+    0x80 => TestStatus::Running,
+    0x81 => TestStatus::ResetRequested,
+    result => TestStatus::Finished(result),
+  })
+}
+
+/// The text the test's written from $6004, empty if it isn't reporting there
+pub fn text(nes: &Nes) -> String {
+  if status(nes).is_none() {
+    return String::new();
+  }
+  let mut text = String::new();
+  for address in TEXT..0x8000 {
+    match nes.peek(address) {
+      0 => break,
+      byte => text.push(byte as char),
+    }
+  }
+  text
+}
+
+/// The first nametable read as text. Test ROMs load their font so each character's tile number is its
+/// ASCII code, so this is what they printed, one line per row with trailing spaces trimmed.
+pub fn screen_text(nes: &mut Nes) -> String {
+  let rows: Vec<String> = (0..30u16).map(|row| {
+    let line: String = (0..32u16).map(|column| {
+      let tile = nes.bus.ppu_peek(0x2000 + row * 32 + column);
+      if tile.is_ascii_graphic() { tile as char } else { ' ' }
+    }).collect();
+    line.trim_end().to_string()
+  }).collect();
+  rows.join("\n").trim().to_string()
+}
+
+/// Run the test ROM in `nes` until it finishes or `max_frames` have gone by, pressing reset whenever it asks
+pub fn run(nes: &mut Nes, max_frames: u64) -> TestOutcome {
+  let mut frames = 0;
+  let mut result = None;
+  while frames < max_frames {
+    nes.run_frames(1);
+    frames += 1;
+    match status(nes) {
+      Some(TestStatus::Finished(code)) => {
+        result = Some(code);
+        break;
+      },
+      Some(TestStatus::ResetRequested) => {
+        nes.run_frames(RESET_DELAY_FRAMES);
+        frames += RESET_DELAY_FRAMES;
+        nes.reset();
+      },
+      _ => {},
+    }
+  }
+
+  let text = if status(nes).is_some() { text(nes) } else { screen_text(nes) };
+  TestOutcome { result, text, frames }
+}
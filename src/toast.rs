@@ -0,0 +1,134 @@
+use eframe::egui;
+
+/// How long a toast stays up, in seconds
+const TOAST_DURATION: f64 = 3.0;
+
+/// How long a toast takes to fade out at the end of its time, in seconds
+const FADE_DURATION: f64 = 0.5;
+
+/// Most toasts shown at once, older ones are dropped to make room
+const MAX_TOASTS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+struct Toast {
+    text: String,
+    kind: ToastKind,
+    /// Set for toasts that stay up while something's going on, so there's only ever one of each
+    key: Option<&'static str>,
+    /// Seconds from first being drawn to being gone
+    duration: f64,
+    /// UI time the toast goes away, set the first time it's drawn
+    expires: Option<f64>,
+}
+
+/// Short messages shown over the corner of the display for a few seconds, for feedback on things
+/// that happen away from any window, e.g. a state being saved from a shortcut. Each one fades out
+/// rather than vanishing.
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+    /// Where the game's drawn, so toasts sit over its corner rather than the window's
+    game_rect: Option<egui::Rect>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(text.into(), ToastKind::Info);
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(text.into(), ToastKind::Error);
+    }
+
+    /// Keep a toast up for as long as this is called every frame, e.g. "Rewinding…" while the key's
+    /// held, fading out once it stops. Calling it again with the same `key` updates the text in place.
+    pub fn hold(&mut self, key: &'static str, text: impl Into<String>) {
+        let text = text.into();
+        match self.toasts.iter_mut().find(|toast| toast.key == Some(key)) {
+            Some(toast) => {
+                toast.text = text;
+                toast.expires = None;
+            },
+            None => {
+                self.push(text, ToastKind::Info);
+                if let Some(toast) = self.toasts.last_mut() {
+                    toast.key = Some(key);
+                    toast.duration = FADE_DURATION;
+                }
+            },
+        }
+    }
+
+    fn push(&mut self, text: String, kind: ToastKind) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push(Toast { text, kind, key: None, duration: TOAST_DURATION, expires: None });
+    }
+
+    /// Put the toasts over the game from now on, given where it was last drawn
+    pub fn place_over(&mut self, game_rect: egui::Rect) {
+        self.game_rect = Some(game_rect);
+    }
+
+    /// Text of the toasts currently up, oldest first
+    pub fn messages(&self) -> impl Iterator<Item = (&str, ToastKind)> {
+        self.toasts.iter().map(|toast| (toast.text.as_str(), toast.kind))
+    }
+
+    /// Draw any current toasts stacked in the bottom right corner of the game, or of the window until
+    /// the game's been placed, without taking any input
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        for toast in &mut self.toasts {
+            toast.expires.get_or_insert(now + toast.duration);
+        }
+        self.toasts.retain(|toast| toast.expires.is_some_and(|expires| expires > now));
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let corner = self.game_rect.unwrap_or_else(|| ctx.screen_rect()).right_bottom();
+        egui::Area::new(egui::Id::new("toasts"))
+            .pivot(egui::Align2::RIGHT_BOTTOM)
+            .fixed_pos(corner - egui::vec2(8.0, 8.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                for toast in &self.toasts {
+                    ui.scope(|ui| {
+                        ui.set_opacity(toast.opacity(now));
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            let color = match toast.kind {
+                                ToastKind::Info => ui.visuals().text_color(),
+                                ToastKind::Error => ui.visuals().error_fg_color,
+                            };
+                            ui.colored_label(color, &toast.text);
+                        });
+                    });
+                }
+            });
+
+        // Make sure there's another frame to fade them out and take them down, even if nothing else is
+        // happening
+        let first_expiry = self.toasts.iter().filter_map(|toast| toast.expires).fold(f64::INFINITY, f64::min);
+        let fade_start = first_expiry - FADE_DURATION;
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64((fade_start - now).max(0.0)));
+    }
+
+    /// How visible each toast currently up is, from 1 down to 0 as it fades out, oldest first
+    pub fn opacities(&self, now: f64) -> impl Iterator<Item = f32> + '_ {
+        self.toasts.iter().map(move |toast| toast.opacity(now))
+    }
+}
+
+impl Toast {
+    fn opacity(&self, now: f64) -> f32 {
+        self.expires.map_or(1.0, |expires| ((expires - now) / FADE_DURATION).clamp(0.0, 1.0) as f32)
+    }
+}
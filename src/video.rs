@@ -0,0 +1,253 @@
+use eframe::egui;
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+/// Rows at the top and bottom of the picture that TVs cut off, which games often leave garbage in
+pub const OVERSCAN_ROWS: usize = 8;
+
+/// The part of the picture to show, in texture coordinates, and its size in pixels
+pub fn visible_area(hide_overscan: bool) -> (egui::Rect, egui::Vec2) {
+  let hidden = if hide_overscan { OVERSCAN_ROWS } else { 0 };
+  let top = hidden as f32 / SCREEN_HEIGHT as f32;
+  let uv = egui::Rect::from_min_max(egui::pos2(0.0, top), egui::pos2(1.0, 1.0 - top));
+  (uv, egui::vec2(SCREEN_WIDTH as f32, (SCREEN_HEIGHT - 2 * hidden) as f32))
+}
+
+/// Write a 256x240 frame of packed RGB bytes out as a PNG
+pub fn save_png(path: &str, frame: &[u8]) -> Result<(), String> {
+  image::save_buffer(path, frame, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, image::ColorType::Rgb8)
+    .map_err(|error| error.to_string())
+}
+
+/// Average `frame` with the one drawn before it, byte by byte, so sprites a game flickers on
+/// alternate frames show up half transparent rather than strobing
+pub fn blend_frames(frame: &mut [u8], previous: &[u8]) {
+  for (byte, previous) in frame.iter_mut().zip(previous) {
+    *byte = (*byte as u16 + *previous as u16).div_ceil(2) as u8;
+  }
+}
+
+/// A post-processing step run over the RGB framebuffer before it's handed to the frontend
+pub trait VideoFilter {
+  /// Filter a 256x240 frame of packed RGB bytes in place
+  fn apply(&self, frame: &mut [u8]);
+}
+
+/// An ordered list of filters, applied first to last
+#[derive(Default)]
+pub struct VideoFilterChain {
+  filters: Vec<Box<dyn VideoFilter>>,
+}
+
+impl VideoFilterChain {
+  pub fn new() -> Self {
+    Self {
+      filters: Vec::new(),
+    }
+  }
+
+  pub fn push(&mut self, filter: Box<dyn VideoFilter>) {
+    self.filters.push(filter);
+  }
+
+  pub fn clear(&mut self) {
+    self.filters.clear();
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.filters.is_empty()
+  }
+
+  pub fn apply(&self, frame: &mut [u8]) {
+    for filter in &self.filters {
+      filter.apply(frame);
+    }
+  }
+}
+
+/// The picture as an egui texture, updated in place each frame rather than reloaded
+#[derive(Default)]
+pub struct Display {
+  texture: Option<egui::TextureHandle>,
+  /// Copy of the frame for the filters to work on, kept between frames so it isn't reallocated
+  filtered: Vec<u8>,
+}
+
+impl Display {
+  /// Upload a 256x240 frame of packed RGB bytes through `filters`, returning the texture to draw.
+  /// Passing the frame before it blends the two together first.
+  pub fn update(&mut self, ctx: &egui::Context, frame: &[u8], previous: Option<&[u8]>, filters: &VideoFilterChain) -> egui::TextureId {
+    let frame = if filters.is_empty() && previous.is_none() {
+      frame
+    } else {
+      self.filtered.clear();
+      self.filtered.extend_from_slice(frame);
+      if let Some(previous) = previous {
+        blend_frames(&mut self.filtered, previous);
+      }
+      filters.apply(&mut self.filtered);
+      &self.filtered
+    };
+    let image = egui::ColorImage::from_rgb([SCREEN_WIDTH, SCREEN_HEIGHT], frame);
+    match &mut self.texture {
+      Some(texture) => {
+        texture.set(image, egui::TextureOptions::NEAREST);
+        texture.id()
+      },
+      None => {
+        let texture = ctx.load_texture("Display", image, egui::TextureOptions::NEAREST);
+        let id = texture.id();
+        self.texture = Some(texture);
+        id
+      },
+    }
+  }
+}
+
+/// Color vision deficiencies we can compensate for
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorVision {
+  #[default]
+  Normal,
+  /// Red-blind
+  Protanopia,
+  /// Green-blind
+  Deuteranopia,
+  /// Blue-blind
+  Tritanopia,
+}
+
+impl ColorVision {
+  pub const ALL: [ColorVision; 4] = [
+    ColorVision::Normal,
+    ColorVision::Protanopia,
+    ColorVision::Deuteranopia,
+    ColorVision::Tritanopia,
+  ];
+
+  /// Key used both for persisting the setting and for looking up its UI string
+  pub fn key(&self) -> &'static str {
+    match self {
+      ColorVision::Normal => "color_vision.normal",
+      ColorVision::Protanopia => "color_vision.protanopia",
+      ColorVision::Deuteranopia => "color_vision.deuteranopia",
+      ColorVision::Tritanopia => "color_vision.tritanopia",
+    }
+  }
+
+  pub fn from_key(key: &str) -> Option<Self> {
+    ColorVision::ALL.into_iter().find(|mode| mode.key() == key)
+  }
+}
+
+type Matrix = [[f32; 3]; 3];
+
+const RGB_TO_LMS: Matrix = [
+  [17.8824, 43.5161, 4.11935],
+  [3.45565, 27.1554, 3.86714],
+  [0.0299566, 0.184309, 1.46709],
+];
+
+const LMS_TO_RGB: Matrix = [
+  [0.08094445, -0.13050441, 0.116721066],
+  [-0.010248533, 0.05401933, -0.11361471],
+  [-0.00036529693, -0.0041216146, 0.6935114],
+];
+
+/// Moves the error the viewer can't see into the channels they can
+const ERROR_SHIFT: Matrix = [
+  [0.0, 0.0, 0.0],
+  [0.7, 1.0, 0.0],
+  [0.7, 0.0, 1.0],
+];
+
+const IDENTITY: Matrix = [
+  [1.0, 0.0, 0.0],
+  [0.0, 1.0, 0.0],
+  [0.0, 0.0, 1.0],
+];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+  let mut out = [[0.0; 3]; 3];
+  for row in 0..3 {
+    for col in 0..3 {
+      out[row][col] = (0..3).map(|i| a[row][i] * b[i][col]).sum();
+    }
+  }
+  out
+}
+
+fn subtract(a: &Matrix, b: &Matrix) -> Matrix {
+  let mut out = [[0.0; 3]; 3];
+  for row in 0..3 {
+    for col in 0..3 {
+      out[row][col] = a[row][col] - b[row][col];
+    }
+  }
+  out
+}
+
+fn add(a: &Matrix, b: &Matrix) -> Matrix {
+  let mut out = [[0.0; 3]; 3];
+  for row in 0..3 {
+    for col in 0..3 {
+      out[row][col] = a[row][col] + b[row][col];
+    }
+  }
+  out
+}
+
+/// Daltonization filter, which remaps colors so detail that relies on hues the viewer
+/// can't distinguish is still visible.
+///
+/// The whole simulate -> diff -> shift pipeline is linear, so it's collapsed into a single
+/// 3x3 matrix up front and each pixel only costs one multiply.
+pub struct Daltonize {
+  matrix: Matrix,
+}
+
+impl Daltonize {
+  pub fn new(mode: ColorVision) -> Self {
+    let simulate_lms: Matrix = match mode {
+      ColorVision::Normal => IDENTITY,
+      ColorVision::Protanopia => [
+        [0.0, 2.02344, -2.52581],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+      ],
+      ColorVision::Deuteranopia => [
+        [1.0, 0.0, 0.0],
+        [0.494207, 0.0, 1.24827],
+        [0.0, 0.0, 1.0],
+      ],
+      ColorVision::Tritanopia => [
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [-0.395913, 0.801109, 0.0],
+      ],
+    };
+
+    // What the viewer perceives, in RGB
+    let simulate = multiply(&LMS_TO_RGB, &multiply(&simulate_lms, &RGB_TO_LMS));
+    // original + shift * (original - simulated)
+    let error = subtract(&IDENTITY, &simulate);
+    let matrix = add(&IDENTITY, &multiply(&ERROR_SHIFT, &error));
+
+    Self {
+      matrix,
+    }
+  }
+}
+
+impl VideoFilter for Daltonize {
+  fn apply(&self, frame: &mut [u8]) {
+    for pixel in frame.chunks_exact_mut(3) {
+      let rgb = [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32];
+      for (channel, row) in self.matrix.iter().enumerate() {
+        let value = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+        pixel[channel] = value.clamp(0.0, 255.0) as u8;
+      }
+    }
+  }
+}
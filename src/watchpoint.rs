@@ -0,0 +1,120 @@
+use std::cell::Cell;
+use std::fmt;
+
+/// Which bus a watchpoint is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressSpace {
+  Cpu,
+  Ppu,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+  Read,
+  Write,
+  /// The CPU fetching an instruction's opcode to run it
+  Execute,
+}
+
+impl fmt::Display for Access {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Access::Read => write!(f, "read"),
+      Access::Write => write!(f, "write"),
+      Access::Execute => write!(f, "execute"),
+    }
+  }
+}
+
+/// Stop emulation when any of the chosen kinds of access touches an address in `start..=end`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+  pub space: AddressSpace,
+  pub start: u16,
+  pub end: u16,
+  pub read: bool,
+  pub write: bool,
+  pub execute: bool,
+}
+
+impl Watchpoint {
+  pub fn matches(&self, space: AddressSpace, access: Access, address: u16) -> bool {
+    let watched = match access {
+      Access::Read => self.read,
+      Access::Write => self.write,
+      Access::Execute => self.execute,
+    };
+    watched && self.space == space && (self.start..=self.end).contains(&address)
+  }
+}
+
+impl fmt::Display for Watchpoint {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let kinds: String = [(self.read, 'r'), (self.write, 'w'), (self.execute, 'x')].iter()
+      .filter(|(watched, _)| *watched)
+      .map(|(_, kind)| *kind)
+      .collect();
+    write!(f, "{} ${:04X}", kinds, self.start)?;
+    if self.end != self.start {
+      write!(f, "-${:04X}", self.end)?;
+    }
+    if self.space == AddressSpace::Ppu {
+      write!(f, " ppu")?;
+    }
+    Ok(())
+  }
+}
+
+/// An access that set a watchpoint off, as seen from the bus it happened on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchedAccess {
+  pub space: AddressSpace,
+  pub access: Access,
+  pub address: u16,
+  /// The value read or written, or for an execute, the opcode
+  pub value: u8,
+}
+
+/// A watchpoint going off, with where the console was when it did
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+  pub access: WatchedAccess,
+  /// The instruction that made the access, or `None` if the PPU made it while rendering
+  pub pc: Option<u16>,
+  /// Where the PPU was, with -1 the pre-render scanline
+  pub scanline: i16,
+  pub dot: u16,
+}
+
+/// The watchpoints a bus checks its accesses against, and the first one that went off since last asked.
+/// Reads are made through `&self`, so the hit's kept in a `Cell`.
+#[derive(Clone, Debug, Default)]
+pub struct Watchpoints {
+  list: Vec<Watchpoint>,
+  hit: Cell<Option<WatchedAccess>>,
+}
+
+impl Watchpoints {
+  pub fn set(&mut self, list: Vec<Watchpoint>) {
+    self.list = list;
+    self.hit.set(None);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.list.is_empty()
+  }
+
+  /// Record the access if it sets a watchpoint off and nothing else has yet
+  pub fn check(&self, space: AddressSpace, access: Access, address: u16, value: u8) {
+    if self.hit.get().is_some() {
+      return;
+    }
+    if self.list.iter().any(|watchpoint| watchpoint.matches(space, access, address)) {
+      self.hit.set(Some(WatchedAccess { space, access, address, value }));
+    }
+  }
+
+  pub fn take_hit(&self) -> Option<WatchedAccess> {
+    self.hit.take()
+  }
+}
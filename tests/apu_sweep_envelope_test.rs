@@ -0,0 +1,183 @@
+extern crate silknes_web;
+
+use silknes_web::apu::{ApuChannel, APU};
+
+/// An APU with every tone channel enabled
+fn apu() -> APU {
+  let mut apu = APU::new();
+  apu.cpu_write(0x4015, 0x0F);
+  apu
+}
+
+/// Pulse 1 at constant volume 15 with the length counter halted, at a 75% duty cycle so it starts high
+fn play_pulse1(apu: &mut APU, period: u16) {
+  apu.cpu_write(0x4000, 0xFF);
+  apu.cpu_write(0x4002, period as u8);
+  apu.cpu_write(0x4003, 0x08 | (period >> 8) as u8);
+}
+
+fn pulse1_audible(apu: &mut APU) -> bool {
+  apu.output_buffer.clear();
+  apu.update_output(0.0);
+  apu.output_buffer[0] > -1.0
+}
+
+fn period(apu: &APU, channel: ApuChannel) -> u16 {
+  apu.channel_state(channel).period
+}
+
+fn envelope(apu: &APU, channel: ApuChannel) -> u8 {
+  apu.channel_state(channel).envelope.unwrap()
+}
+
+#[test]
+fn pulse_is_muted_below_period_8() {
+  let mut apu = apu();
+  play_pulse1(&mut apu, 7);
+  assert!(!pulse1_audible(&mut apu));
+  play_pulse1(&mut apu, 8);
+  assert!(pulse1_audible(&mut apu));
+}
+
+#[test]
+fn pulse_is_muted_when_the_sweep_would_overflow_even_if_disabled() {
+  let mut apu = apu();
+  play_pulse1(&mut apu, 0x600);
+  // Disabled, shift 1: the target's $900
+  apu.cpu_write(0x4001, 0x01);
+  assert!(!pulse1_audible(&mut apu));
+  // Sweeping down never overflows
+  apu.cpu_write(0x4001, 0x09);
+  assert!(pulse1_audible(&mut apu));
+  // A shift of 0 makes the target double the period, which mutes it too
+  apu.cpu_write(0x4001, 0x00);
+  assert!(!pulse1_audible(&mut apu));
+}
+
+#[test]
+fn pulse_1_negates_with_ones_complement_and_pulse_2_with_twos() {
+  let mut apu = apu();
+  // Enabled, negate, shift 1
+  apu.cpu_write(0x4001, 0x89);
+  apu.cpu_write(0x4002, 0x00);
+  apu.cpu_write(0x4003, 0x01);
+  apu.cpu_write(0x4005, 0x89);
+  apu.cpu_write(0x4006, 0x00);
+  apu.cpu_write(0x4007, 0x01);
+  assert_eq!(apu.channel_state(ApuChannel::Pulse1).sweep_target, Some(0x7F));
+  assert_eq!(apu.channel_state(ApuChannel::Pulse2).sweep_target, Some(0x80));
+}
+
+#[test]
+fn sweep_moves_the_period_each_time_its_divider_runs_out() {
+  let mut apu = apu();
+  play_pulse1(&mut apu, 0x100);
+  // Enabled, divider period 1 (every other half frame), shift 1
+  apu.cpu_write(0x4001, 0x91);
+  // The divider starts at 0, so the first half frame sweeps straight away
+  apu.tick_half_frame();
+  assert_eq!(period(&apu, ApuChannel::Pulse1), 0x180);
+  apu.tick_half_frame();
+  assert_eq!(period(&apu, ApuChannel::Pulse1), 0x180);
+  apu.tick_half_frame();
+  assert_eq!(period(&apu, ApuChannel::Pulse1), 0x240);
+}
+
+#[test]
+fn sweep_reload_restarts_the_divider_without_sweeping() {
+  let mut apu = apu();
+  play_pulse1(&mut apu, 0x100);
+  apu.cpu_write(0x4001, 0x91);
+  apu.tick_half_frame();
+  assert_eq!(period(&apu, ApuChannel::Pulse1), 0x180);
+
+  // Divider period 3 now, reloaded at the next half frame rather than counting down
+  apu.cpu_write(0x4001, 0xB1);
+  for _ in 0..4 {
+    apu.tick_half_frame();
+    assert_eq!(period(&apu, ApuChannel::Pulse1), 0x180);
+  }
+  apu.tick_half_frame();
+  assert_eq!(period(&apu, ApuChannel::Pulse1), 0x240);
+}
+
+#[test]
+fn sweep_leaves_a_muted_channel_alone() {
+  let mut apu = apu();
+  play_pulse1(&mut apu, 0x600);
+  apu.cpu_write(0x4001, 0x81);
+  apu.tick_half_frame();
+  assert_eq!(period(&apu, ApuChannel::Pulse1), 0x600);
+}
+
+#[test]
+fn envelope_decays_once_per_divider_period_and_stops_at_zero() {
+  let mut apu = apu();
+  // Decaying, divider period 2
+  apu.cpu_write(0x4000, 0x02);
+  apu.cpu_write(0x4003, 0x08);
+  apu.tick_quarter_frame();
+  assert_eq!(envelope(&apu, ApuChannel::Pulse1), 15);
+  for _ in 0..3 {
+    apu.tick_quarter_frame();
+  }
+  assert_eq!(envelope(&apu, ApuChannel::Pulse1), 14);
+  for _ in 0..3 * 14 {
+    apu.tick_quarter_frame();
+  }
+  assert_eq!(envelope(&apu, ApuChannel::Pulse1), 0);
+  for _ in 0..10 {
+    apu.tick_quarter_frame();
+  }
+  assert_eq!(envelope(&apu, ApuChannel::Pulse1), 0);
+}
+
+#[test]
+fn looping_envelope_holds_zero_for_a_period_before_wrapping() {
+  let mut apu = apu();
+  // Looping, decaying, divider period 0 so it steps every quarter frame
+  apu.cpu_write(0x400C, 0x20);
+  apu.cpu_write(0x400F, 0x08);
+  for _ in 0..16 {
+    apu.tick_quarter_frame();
+  }
+  assert_eq!(envelope(&apu, ApuChannel::Noise), 0);
+  apu.tick_quarter_frame();
+  assert_eq!(envelope(&apu, ApuChannel::Noise), 15);
+}
+
+#[test]
+fn writing_the_length_restarts_the_envelope() {
+  let mut apu = apu();
+  apu.cpu_write(0x4004, 0x00);
+  apu.cpu_write(0x4007, 0x08);
+  for _ in 0..6 {
+    apu.tick_quarter_frame();
+  }
+  assert_eq!(envelope(&apu, ApuChannel::Pulse2), 10);
+  apu.cpu_write(0x4007, 0x08);
+  // Nothing changes until the next quarter frame
+  assert_eq!(envelope(&apu, ApuChannel::Pulse2), 10);
+  apu.tick_quarter_frame();
+  assert_eq!(envelope(&apu, ApuChannel::Pulse2), 15);
+}
+
+#[test]
+fn half_frames_clock_the_envelope_once() {
+  let mut apu = apu();
+  apu.cpu_write(0x4000, 0x00);
+  apu.cpu_write(0x4003, 0x08);
+  apu.tick_half_frame();
+  apu.tick_half_frame();
+  assert_eq!(envelope(&apu, ApuChannel::Pulse1), 14);
+}
+
+#[test]
+fn timers_with_a_zero_period_keep_running() {
+  let mut apu = apu();
+  apu.cpu_write(0x4008, 0xFF);
+  apu.cpu_write(0x400B, 0x08);
+  for cycle in 0..100 {
+    apu.step(cycle, None);
+  }
+}
@@ -0,0 +1,65 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::apu::{ApuChannel, ChannelState, APU};
+use silknes_web::apu_timeline::ApuTimeline;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+#[test]
+fn channel_state_reflects_registers() {
+  let mut apu = APU::new();
+  apu.cpu_write(0x4015, 0x0F);
+  // Length counter halted, constant volume 10
+  apu.cpu_write(0x4000, 0x3A);
+  // Sweep enabled, adding half the period
+  apu.cpu_write(0x4001, 0x81);
+  apu.cpu_write(0x4002, 0x00);
+  apu.cpu_write(0x4003, 0x0A);
+  assert_eq!(apu.channel_state(ApuChannel::Pulse1), ChannelState {
+    period: 0x200,
+    envelope: Some(10),
+    sweep_target: Some(0x300),
+    length_counter: 254,
+    duty: Some(0),
+    linear_counter: None,
+  });
+  assert_eq!(apu.channel_state(ApuChannel::Pulse2).sweep_target, None);
+
+  apu.cpu_write(0x400A, 0x34);
+  apu.cpu_write(0x400B, 0x01);
+  assert_eq!(apu.channel_state(ApuChannel::Triangle), ChannelState {
+    period: 0x134,
+    envelope: None,
+    sweep_target: None,
+    length_counter: 10,
+    duty: None,
+    linear_counter: Some(0),
+  });
+}
+
+#[test]
+fn timeline_records_once_per_frame() {
+  let mut nes = RomBuilder::new(0).prg(&SPIN).nes();
+  let mut timeline = ApuTimeline::default();
+  timeline.set_channel(ApuChannel::Noise);
+
+  nes.run_frame();
+  timeline.record(&nes);
+  timeline.record(&nes);
+  assert_eq!(timeline.history().count(), 1);
+
+  // Start the noise channel with its length counter halted
+  nes.bus.apu.cpu_write(0x4015, 0x08);
+  nes.bus.apu.cpu_write(0x400C, 0x20);
+  nes.bus.apu.cpu_write(0x400F, 0x08);
+  nes.run_frame();
+  timeline.record(&nes);
+  assert_eq!(timeline.history().count(), 2);
+  assert_eq!(timeline.history().last().unwrap().length_counter, 254);
+
+  // Switching channel starts the graph over
+  timeline.set_channel(ApuChannel::Pulse1);
+  assert_eq!(timeline.history().count(), 0);
+}
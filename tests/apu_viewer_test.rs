@@ -0,0 +1,68 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::apu::{ApuChannel, AudioChannel, DmcState, APU, SCOPE_LENGTH};
+use silknes_web::apu_viewer::{frequency, nearest_note};
+use silknes_web::bus::BusLike;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+#[test]
+fn periods_are_named_as_notes() {
+  // $0FD is the usual pulse period for A4, a few cents flat
+  let a4 = frequency(ApuChannel::Pulse1, 0x0FD).unwrap();
+  assert!((a4 - 440.0).abs() < 2.0, "{}", a4);
+  let (note, cents) = nearest_note(a4);
+  assert_eq!(note, "A4");
+  assert!(cents.abs() < 10);
+
+  // The triangle's an octave lower for the same period
+  let a3 = frequency(ApuChannel::Triangle, 0x0FD).unwrap();
+  assert_eq!(nearest_note(a3).0, "A3");
+
+  assert_eq!(nearest_note(261.63), ("C4".to_string(), 0));
+  assert_eq!(nearest_note(27.5), ("A0".to_string(), 0));
+}
+
+#[test]
+fn inaudible_periods_have_no_frequency() {
+  assert_eq!(frequency(ApuChannel::Pulse2, 7), None);
+  assert_eq!(frequency(ApuChannel::Triangle, 1), None);
+  assert_eq!(frequency(ApuChannel::Noise, 0x0FD), None);
+}
+
+#[test]
+fn channel_state_includes_duty_and_dmc() {
+  let mut apu = APU::new();
+  apu.cpu_write(0x4015, 0x1F);
+  apu.cpu_write(0x4004, 0xB0);
+  assert_eq!(apu.channel_state(ApuChannel::Pulse2).duty, Some(2));
+  assert_eq!(apu.channel_state(ApuChannel::Noise).duty, None);
+
+  apu.cpu_write(0x4010, 0x4F);
+  apu.cpu_write(0x4011, 0x40);
+  // Enabling the DMC started its one byte default sample
+  assert_eq!(apu.dmc_state(), DmcState { rate: 54, output: 0x40, bytes_remaining: 1, looping: true });
+}
+
+#[test]
+fn scope_only_records_while_enabled() {
+  let mut nes = RomBuilder::new(0).prg(&SPIN).nes();
+  nes.run_frame();
+  assert!(nes.scope().is_none());
+
+  nes.set_scope_enabled(true);
+  nes.bus.cpu_write(0x4011, 0x7F);
+  // A frame's about 800 samples, so it takes two to fill the scope
+  nes.run_frame();
+  nes.run_frame();
+  let scope = nes.scope().unwrap();
+  let dmc = scope.levels(AudioChannel::Dmc);
+  assert_eq!(dmc.len(), SCOPE_LENGTH);
+  assert!(dmc.iter().all(|level| *level == 1.0));
+  assert!(scope.levels(AudioChannel::Pulse1).iter().all(|level| *level == 0.0));
+
+  nes.set_scope_enabled(false);
+  assert!(nes.scope().is_none());
+}
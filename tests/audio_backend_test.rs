@@ -0,0 +1,127 @@
+extern crate silknes_web;
+
+use std::sync::mpsc;
+
+use rodio::Source;
+
+use silknes_web::apu::{AudioChannel, StereoMode};
+use silknes_web::apu_output::{AudioBackend, AudioControls, AudioDriver, APUOutput, SyncMode, LATENCY_RANGE_MS};
+use silknes_web::command::{self, Command};
+
+/// Whether or not the machine running the tests has a sound card, starting audio shouldn't panic
+/// and the emulator should always be able to hand it samples
+#[test]
+fn audio_starts_with_or_without_a_device() {
+  let (tx, rx) = mpsc::channel();
+  let audio = AudioBackend::start(rx, AudioDriver::default(), AudioControls::default());
+
+  for _ in 0..10 {
+    tx.send(vec![0.0; 800]).unwrap();
+  }
+  assert!(["audio.device", "audio.null"].contains(&audio.key()));
+}
+
+#[test]
+fn controls_keep_to_their_ranges() {
+  let controls = AudioControls::default();
+  controls.set_volume(1.5);
+  assert_eq!(controls.volume(), 1.0);
+  controls.set_volume(-0.5);
+  assert_eq!(controls.volume(), 0.0);
+  controls.set_latency_ms(5);
+  assert_eq!(controls.latency_ms(), *LATENCY_RANGE_MS.start());
+  controls.set_latency_ms(80);
+  assert_eq!(controls.latency_samples(), 3840);
+}
+
+/// Pull from an output that's been sent `samples` on both sides, returning what came out on the left
+fn play(controls: &AudioControls, samples: Vec<f32>, pulls: usize) -> Vec<f32> {
+  let (tx, rx) = mpsc::channel();
+  let mut output = APUOutput::new(rx, controls.clone());
+  tx.send(samples.iter().flat_map(|&sample| [sample, sample]).collect()).unwrap();
+  (0..pulls)
+    .map(|_| {
+      let left = output.next().unwrap();
+      output.next();
+      left
+    })
+    .collect()
+}
+
+#[test]
+fn output_plays_left_and_right_in_turn() {
+  let controls = AudioControls::default();
+  controls.set_latency_ms(30);
+  let (tx, rx) = mpsc::channel();
+  let mut output = APUOutput::new(rx, controls.clone());
+  assert_eq!(output.channels(), 2);
+  tx.send([0.5, -0.5].repeat(1000)).unwrap();
+
+  let left = output.next().unwrap();
+  assert!(left > 0.0);
+  assert_eq!(output.next(), Some(-left));
+  // Both sides together are one sample played
+  assert_eq!(controls.samples_played(), 1);
+}
+
+#[test]
+fn output_waits_for_half_the_latency_before_playing() {
+  let controls = AudioControls::default();
+  controls.set_latency_ms(40);
+  // 10ms isn't enough to start on
+  assert!(play(&controls, vec![1.0; 480], 10).iter().all(|&sample| sample == 0.0));
+  // 20ms is, and none of it is lost waiting
+  let played = play(&controls, vec![1.0; 960], 960);
+  assert!(played.iter().all(|&sample| sample > 0.0));
+}
+
+#[test]
+fn volume_and_mute_scale_the_output() {
+  let controls = AudioControls::default();
+  controls.set_latency_ms(30);
+  let full = play(&controls, vec![1.0; 1000], 1)[0];
+  controls.set_volume(0.5);
+  assert_eq!(play(&controls, vec![1.0; 1000], 1)[0], full / 2.0);
+  controls.set_muted(true);
+  assert_eq!(play(&controls, vec![1.0; 1000], 1)[0], 0.0);
+}
+
+#[test]
+fn output_skips_ahead_when_it_falls_too_far_behind() {
+  let controls = AudioControls::default();
+  controls.set_latency_ms(30);
+  let latency = controls.latency_samples();
+  // The oldest samples are quiet, so hearing them means nothing was skipped
+  let mut samples = vec![0.5; latency * 2];
+  samples.extend(vec![1.0; latency]);
+  let full = play(&controls, vec![1.0; latency], 1)[0];
+  assert_eq!(play(&controls, samples, 1)[0], full);
+}
+
+#[test]
+fn console_sets_volume_and_latency() {
+  assert_eq!(command::parse("volume 50"), Ok(Command::SetVolume(0.5)));
+  assert!(command::parse("volume 150").is_err());
+  assert_eq!(command::parse("latency 100"), Ok(Command::SetAudioLatency(100)));
+  assert!(command::parse("latency 5").is_err());
+  assert_eq!(command::parse("sync wall_clock"), Ok(Command::SetSyncMode(SyncMode::WallClock)));
+  assert_eq!(command::parse("sync audio"), Ok(Command::SetSyncMode(SyncMode::Audio)));
+  assert!(command::parse("sync video").is_err());
+}
+
+#[test]
+fn console_mutes_and_solos_channels() {
+  assert_eq!(command::parse("mute dmc"), Ok(Command::ToggleChannelMute(AudioChannel::Dmc)));
+  assert_eq!(command::parse("solo Pulse1"), Ok(Command::ToggleChannelSolo(AudioChannel::Pulse1)));
+  assert!(command::parse("mute").is_err());
+  assert!(command::parse("solo vrc6").is_err());
+}
+
+#[test]
+fn console_sets_stereo_and_panning() {
+  assert_eq!(command::parse("stereo split"), Ok(Command::SetStereoMode(StereoMode::Split)));
+  assert!(command::parse("stereo surround").is_err());
+  assert_eq!(command::parse("pan noise -50"), Ok(Command::SetChannelPan(AudioChannel::Noise, -0.5)));
+  assert!(command::parse("pan noise 150").is_err());
+  assert!(command::parse("pan 50").is_err());
+}
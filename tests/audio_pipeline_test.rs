@@ -0,0 +1,62 @@
+extern crate silknes_web;
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use silknes_web::audio_pipeline::{ring, AudioPipeline};
+use silknes_web::nes::{downsample_stereo, AUDIO_CHANNELS, OUTPUT_SAMPLES_PER_FRAME, SAMPLES_PER_OUTPUT_SAMPLE};
+
+#[test]
+fn ring_keeps_order_across_wraparound() {
+  let (producer, consumer) = ring(8);
+  let mut out = Vec::new();
+  assert_eq!(producer.push(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), 6);
+  assert_eq!(consumer.pop_into(&mut out), 6);
+  // Only 8 fit, the rest has to wait
+  assert_eq!(producer.push(&[7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0]), 8);
+  assert_eq!(consumer.pop_into(&mut out), 8);
+  assert_eq!(out, (1..=14).map(|i| i as f32).collect::<Vec<_>>());
+}
+
+/// Everything the worker sends until it goes quiet
+fn collect(rx: &mpsc::Receiver<Vec<f32>>) -> Vec<f32> {
+  let mut samples = Vec::new();
+  while let Ok(chunk) = rx.recv_timeout(Duration::from_millis(200)) {
+    samples.extend(chunk);
+  }
+  samples
+}
+
+#[test]
+fn pipeline_output_matches_downsampling_in_one_go() {
+  let raw: Vec<f32> = (0..SAMPLES_PER_OUTPUT_SAMPLE * AUDIO_CHANNELS * 1000).map(|i| (i as f32 * 0.01).sin()).collect();
+  let (tx, rx) = mpsc::channel();
+  let pipeline = AudioPipeline::start(tx);
+  // Pieces that don't line up with output samples, and more than the ring holds at once
+  for piece in raw.chunks(1234) {
+    pipeline.push(piece);
+  }
+  for _ in 0..4 {
+    pipeline.push(&raw);
+  }
+  let expected: Vec<f32> = [downsample_stereo(&raw), downsample_stereo(&raw).repeat(4)].concat();
+  assert_eq!(collect(&rx), expected);
+}
+
+#[test]
+fn paused_frames_fade_out_in_order() {
+  let (tx, rx) = mpsc::channel();
+  let pipeline = AudioPipeline::start(tx);
+  pipeline.push(&[0.5, -0.5].repeat(SAMPLES_PER_OUTPUT_SAMPLE * 10));
+  pipeline.push_paused_frame();
+  pipeline.push(&[0.25; SAMPLES_PER_OUTPUT_SAMPLE * AUDIO_CHANNELS * 10]);
+
+  // Just the left side, the right being its opposite until it fades out
+  let samples: Vec<f32> = collect(&rx).into_iter().step_by(AUDIO_CHANNELS).collect();
+  assert_eq!(samples.len(), 10 + OUTPUT_SAMPLES_PER_FRAME + 10);
+  assert!(samples[..10].iter().all(|sample| *sample == 0.5));
+  let fade = &samples[10..10 + OUTPUT_SAMPLES_PER_FRAME];
+  assert_eq!(fade[0], 0.5);
+  assert!(fade.windows(2).all(|pair| pair[1] <= pair[0]));
+  assert!(samples[10 + OUTPUT_SAMPLES_PER_FRAME..].iter().all(|sample| *sample == 0.25));
+}
@@ -0,0 +1,78 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::battery::BatterySaves;
+use silknes_web::bus::BusLike;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN};
+use common::MemoryStorage;
+
+/// NROM that spins, with battery backed PRG RAM if `battery`
+fn nes(battery: bool) -> Nes {
+  let rom = RomBuilder::new(0).prg(&SPIN);
+  let rom = if battery { rom.battery() } else { rom };
+  rom.nes()
+}
+
+#[test]
+fn battery_ram_comes_back_in_a_new_console() {
+  let mut nes = nes(true);
+  nes.bus.cpu_write(0x6000, 0x12);
+  nes.bus.cpu_write(0x6100, 0x34);
+  let ram = nes.battery_ram().unwrap();
+
+  let mut restored = self::nes(true);
+  restored.load_battery_ram(&ram[..0x200]);
+  assert_eq!(restored.peek(0x6000), 0x12);
+  assert_eq!(restored.peek(0x6100), 0x34);
+}
+
+#[test]
+fn cartridges_without_a_battery_have_nothing_to_keep() {
+  let mut nes = nes(false);
+  assert_eq!(nes.battery_ram(), None);
+  // Loading is ignored rather than writing over PRG ROM
+  let before = nes.peek(0x6000);
+  nes.load_battery_ram(&[0x12; 0x10]);
+  assert_eq!(nes.peek(0x6000), before);
+  assert_eq!(Nes::new().battery_ram(), None);
+}
+
+#[test]
+fn trainers_alone_give_ram_but_nothing_to_keep() {
+  let mut nes = RomBuilder::new(0).trainer(&[0x42]).prg(&SPIN).nes();
+  assert_eq!(nes.battery_ram(), None);
+  // A save left over from somewhere else doesn't go over the trainer
+  nes.load_battery_ram(&[0x12; 0x2000]);
+  assert_eq!(nes.peek(0x7000), 0x42);
+}
+
+#[test]
+fn saves_survive_storage_without_their_trailing_zeros() {
+  let mut ram = vec![0; 0x8000];
+  ram[0] = 0xAB;
+  ram[5] = 0xCD;
+  let mut saves = BatterySaves::default();
+  saves.set("game", &ram);
+  assert_eq!(saves.get("game"), Some(&[0xAB, 0, 0, 0, 0, 0xCD][..]));
+  saves.set("blank", &[0; 0x2000]);
+
+  let mut storage = MemoryStorage::default();
+  saves.save(&mut storage);
+  let loaded = BatterySaves::load(Some(&storage));
+  assert_eq!(loaded.get("game"), saves.get("game"));
+  assert_eq!(loaded.get("blank"), Some(&[][..]));
+  assert_eq!(loaded.get("other"), None);
+}
+
+#[test]
+fn undecodable_saves_are_skipped() {
+  let mut storage = MemoryStorage::default();
+  eframe::Storage::set_string(&mut storage, "battery_saves", "good\t0aff\nbad\t0g\nno tab\n".to_string());
+  let saves = BatterySaves::load(Some(&storage));
+  assert_eq!(saves.get("good"), Some(&[0x0A, 0xFF][..]));
+  assert_eq!(saves.get("bad"), None);
+  assert_eq!(BatterySaves::load(None).get("good"), None);
+}
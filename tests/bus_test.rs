@@ -0,0 +1,162 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::BusLike;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+/// NROM that spins forever
+fn nes() -> Nes {
+  RomBuilder::new(0).prg(&SPIN).nes()
+}
+
+fn read(nes: &mut Nes, address: u16) -> u8 {
+  nes.bus.cpu_read(address)
+}
+
+fn write(nes: &mut Nes, address: u16, value: u8) {
+  nes.bus.cpu_write(address, value);
+}
+
+#[test]
+fn ram_is_mirrored_every_2kb() {
+  let mut nes = nes();
+  for base in [0x0000, 0x0800, 0x1000, 0x1800] {
+    write(&mut nes, base + 0x0123, base as u8 ^ 0x5A);
+    for mirror in [0x0000, 0x0800, 0x1000, 0x1800] {
+      assert_eq!(read(&mut nes, mirror + 0x0123), base as u8 ^ 0x5A, "${:04X} written, ${:04X} read", base + 0x0123, mirror + 0x0123);
+    }
+  }
+}
+
+#[test]
+fn ppu_registers_are_mirrored_every_8_bytes() {
+  let mut nes = nes();
+  // The PPU ignores PPUADDR until it's warmed up
+  nes.run_frame();
+  // Point PPUADDR at the nametables through one mirror and write PPUDATA through another, all the way up
+  for (i, base) in (0x2000..=0x3FF8u16).step_by(0x0408).enumerate() {
+    write(&mut nes, base + 6, 0x20);
+    write(&mut nes, 0x3FFE, i as u8);
+    write(&mut nes, base + 7, 0xA0 ^ i as u8);
+    write(&mut nes, 0x2006, 0x20);
+    write(&mut nes, 0x2006, i as u8);
+    // The first PPUDATA read only fills the buffer
+    read(&mut nes, 0x3FF7);
+    assert_eq!(read(&mut nes, base + 0x0F), 0xA0 ^ i as u8, "mirror at ${:04X}", base);
+  }
+}
+
+#[test]
+fn write_only_and_unused_io_reads_are_open_bus() {
+  let mut nes = nes();
+  for address in [0x4000, 0x4003, 0x4008, 0x4013, 0x4014, 0x4018, 0x401F, 0x4020, 0x5FFF] {
+    // Reading RAM leaves its value on the data bus
+    write(&mut nes, 0x0000, address as u8);
+    read(&mut nes, 0x0000);
+    assert_eq!(read(&mut nes, address), address as u8, "${:04X}", address);
+  }
+  // Without PRG RAM, $6000-$7FFF is open bus too
+  write(&mut nes, 0x0000, 0x77);
+  read(&mut nes, 0x0000);
+  assert_eq!(read(&mut nes, 0x6000), 0x77);
+}
+
+#[test]
+fn controller_ports_read_their_own_controller() {
+  let mut nes = nes();
+  nes.update_controller(0, 0b1000_0001);
+  nes.update_controller(1, 0b0100_0000);
+  write(&mut nes, 0x4016, 1);
+  write(&mut nes, 0x4016, 0);
+
+  let port_1: Vec<u8> = (0..8).map(|_| read(&mut nes, 0x4016) & 1).collect();
+  let port_2: Vec<u8> = (0..8).map(|_| read(&mut nes, 0x4017) & 1).collect();
+  assert_eq!(port_1, [1, 0, 0, 0, 0, 0, 0, 1]);
+  assert_eq!(port_2, [0, 1, 0, 0, 0, 0, 0, 0]);
+  // A standard controller reads back 1 once every button's been read
+  assert_eq!(read(&mut nes, 0x4016) & 1, 1);
+  assert_eq!(read(&mut nes, 0x4017) & 1, 1);
+}
+
+#[test]
+fn controllers_keep_reloading_while_strobed() {
+  let mut nes = nes();
+  nes.update_controller(0, 0b1000_0000);
+  write(&mut nes, 0x4016, 1);
+  // Only A can be read while the strobe is held, however many times
+  for _ in 0..10 {
+    assert_eq!(read(&mut nes, 0x4016) & 1, 1);
+  }
+  // Straight from the buttons, so letting go of A shows up without another strobe
+  nes.update_controller(0, 0b0000_0000);
+  assert_eq!(read(&mut nes, 0x4016) & 1, 0);
+  // And the buttons latched are the ones held when it's released
+  nes.update_controller(0, 0b0100_0000);
+  write(&mut nes, 0x4016, 0);
+  assert_eq!(read(&mut nes, 0x4016) & 1, 0);
+  assert_eq!(read(&mut nes, 0x4016) & 1, 1);
+}
+
+#[test]
+fn writing_4016_without_the_strobe_bit_latches_nothing() {
+  let mut nes = nes();
+  nes.update_controller(0, 0b1000_0000);
+  write(&mut nes, 0x4016, 1);
+  write(&mut nes, 0x4016, 0);
+  read(&mut nes, 0x4016);
+  nes.update_controller(0, 0xFF);
+  write(&mut nes, 0x4016, 0);
+  // Still partway through the buttons latched before
+  assert_eq!(read(&mut nes, 0x4016) & 1, 0);
+}
+
+#[test]
+fn prg_ram_is_only_there_when_the_cartridge_has_it() {
+  let mut nes = RomBuilder::new(0).battery().prg(&SPIN).nes();
+  write(&mut nes, 0x6000, 0x12);
+  write(&mut nes, 0x7FFF, 0x34);
+  assert_eq!(read(&mut nes, 0x6000), 0x12);
+  assert_eq!(read(&mut nes, 0x7FFF), 0x34);
+}
+
+#[test]
+fn mapper_registers_below_8000_are_reached_without_prg_ram() {
+  // Mapper 140 switches banks from $6000-$7FFF, and has no RAM there
+  let mut nes = RomBuilder::new(140).prg_banks(4).chr_banks(2).nes();
+  assert_eq!(read(&mut nes, 0x8000), 0);
+  // 32 KB bank 1 starts at 8 KB page 4
+  write(&mut nes, 0x6000, 0x10);
+  assert_eq!(read(&mut nes, 0x8000), 4);
+}
+
+#[test]
+fn cartridge_space_reads_come_from_prg_rom() {
+  let mut nes = nes();
+  assert_eq!(read(&mut nes, 0x8000), 0);
+  assert_eq!(read(&mut nes, 0xFFFC), 0x00);
+  assert_eq!(read(&mut nes, 0xFFFD), 0xC0);
+}
+
+#[test]
+fn oam_dma_copies_a_page_and_holds_the_cpu_up() {
+  let mut nes = nes();
+  for i in 0..=255u16 {
+    write(&mut nes, 0x0200 + i, i as u8);
+  }
+  write(&mut nes, 0x4014, 0x02);
+  assert!(nes.dma_active());
+
+  let mut cpu_cycles = 0;
+  while nes.dma_active() {
+    nes.run_cycles(3);
+    cpu_cycles += 1;
+  }
+  // 256 reads and 256 writes, after one or two cycles waiting to line up with a read
+  assert!(cpu_cycles == 513 || cpu_cycles == 514, "took {} cycles", cpu_cycles);
+  let ppu = &nes.bus.ppu;
+  assert_eq!((ppu.oam[1].y, ppu.oam[1].id, ppu.oam[1].x), (4, 5, 7));
+  assert_eq!(ppu.oam[63].x, 255);
+}
@@ -0,0 +1,75 @@
+extern crate silknes_web;
+
+mod common;
+
+use common::rom_builder::{textured_chr, RomBuilder};
+
+/// Reset handler: turn on NMIs and rendering, then spin
+const PROGRAM: [u8; 24] = [
+  0x78,             // SEI
+  // Wait out the PPU's warm up, which takes until the second vblank
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0xA9, 0x80,       // LDA #$80
+  0x8D, 0x00, 0x20, // STA $2000
+  0xA9, 0x1E,       // LDA #$1E
+  0x8D, 0x01, 0x20, // STA $2001
+  // loop:
+  0x4C, 0x15, 0xC0, // JMP loop
+];
+
+/// NMI handler: scroll the background one more pixel every frame, so each frame looks different
+const NMI_HANDLER: [u8; 8] = [
+  0xE6, 0x01,       // INC $01
+  0xA5, 0x01,       // LDA $01
+  0x8D, 0x05, 0x20, // STA $2005
+  0x40,             // RTI
+];
+
+#[test]
+fn captures_the_requested_frame() {
+  let rom = RomBuilder::new(0).prg(&PROGRAM).nmi(&NMI_HANDLER).chr(&textured_chr());
+  let mut nes = rom.nes();
+  let captured = nes.capture_frame(20).unwrap();
+  assert_eq!(nes.frame_count(), 20);
+  assert_eq!(captured, nes.get_screen());
+
+  // Getting there frame by frame gives the same picture
+  let mut stepped = rom.nes();
+  for _ in 0..20 {
+    stepped.run_frame();
+  }
+  assert_eq!(stepped.frame_count(), 20);
+  assert_eq!(stepped.get_screen(), captured);
+}
+
+#[test]
+fn capture_is_independent_of_where_the_console_was_stopped() {
+  let rom = RomBuilder::new(0).prg(&PROGRAM).nmi(&NMI_HANDLER).chr(&textured_chr());
+  let mut nes = rom.nes();
+  nes.run_cycles(12_345);
+  let from_partway = nes.capture_frame(5).unwrap();
+  assert_eq!(from_partway, rom.nes().capture_frame(5).unwrap());
+}
+
+#[test]
+fn frames_already_drawn_cant_be_captured() {
+  let mut nes = RomBuilder::new(0).prg(&PROGRAM).nmi(&NMI_HANDLER).chr(&textured_chr()).nes();
+  nes.capture_frame(3).unwrap();
+  // The current frame is still available, earlier ones aren't
+  assert!(nes.capture_frame(3).is_some());
+  assert!(nes.capture_frame(2).is_none());
+  assert_eq!(nes.frame_count(), 3);
+}
+
+#[test]
+fn frame_count_is_part_of_save_states() {
+  let mut nes = RomBuilder::new(0).prg(&PROGRAM).nmi(&NMI_HANDLER).chr(&textured_chr()).nes();
+  nes.capture_frame(4);
+  let state = nes.save_state();
+  nes.capture_frame(8);
+  nes.load_state(&state);
+  assert_eq!(nes.frame_count(), 4);
+}
@@ -0,0 +1,147 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::apu::{AudioChannel, ChannelMix, ChannelPan, MixerMode, StereoMode};
+use silknes_web::bus::BusLike;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+/// A console with the DMC's output level held high, so there's something to hear
+fn humming_dmc() -> Nes {
+  let mut nes = RomBuilder::new(0).prg(&SPIN).nes();
+  nes.bus.cpu_write(0x4011, 0x7F);
+  nes
+}
+
+/// The audio from the next frame. The first sample's left out since it's partly made of the last frame's.
+fn frame_audio(nes: &mut Nes) -> Vec<f32> {
+  nes.take_audio();
+  nes.run_frame();
+  nes.take_audio().split_off(1)
+}
+
+/// The left and right audio from the next frame, like `frame_audio`
+fn frame_stereo_audio(nes: &mut Nes) -> (Vec<f32>, Vec<f32>) {
+  nes.take_stereo_audio();
+  nes.run_frame();
+  let samples = nes.take_stereo_audio().split_off(2);
+  let left = samples.iter().step_by(2).copied().collect();
+  let right = samples.iter().skip(1).step_by(2).copied().collect();
+  (left, right)
+}
+
+#[test]
+fn every_channel_is_audible_by_default() {
+  let mix = ChannelMix::default();
+  assert!(AudioChannel::ALL.into_iter().all(|channel| mix.audible(channel)));
+}
+
+#[test]
+fn soloing_silences_everything_else() {
+  let mut mix = ChannelMix::default();
+  mix.set_soloed(AudioChannel::Triangle, true);
+  mix.set_soloed(AudioChannel::Noise, true);
+  let audible: Vec<_> = AudioChannel::ALL.into_iter().filter(|channel| mix.audible(*channel)).collect();
+  assert_eq!(audible, [AudioChannel::Triangle, AudioChannel::Noise]);
+
+  // Muting wins over soloing
+  mix.set_muted(AudioChannel::Noise, true);
+  assert!(!mix.audible(AudioChannel::Noise));
+  mix.set_soloed(AudioChannel::Triangle, false);
+  mix.set_soloed(AudioChannel::Noise, false);
+  assert!(mix.audible(AudioChannel::Pulse1));
+}
+
+#[test]
+fn channel_names_round_trip() {
+  for channel in AudioChannel::ALL {
+    assert_eq!(AudioChannel::from_name(channel.name()), Some(channel));
+  }
+}
+
+#[test]
+fn muting_a_channel_takes_it_out_of_the_mix_but_not_the_registers() {
+  let mut nes = humming_dmc();
+  assert!(frame_audio(&mut nes).iter().all(|sample| *sample > -1.0));
+
+  let mut mix = nes.channel_mix();
+  mix.set_muted(AudioChannel::Dmc, true);
+  nes.set_channel_mix(mix);
+  assert!(frame_audio(&mut nes).iter().all(|sample| *sample == -1.0));
+
+  mix.set_muted(AudioChannel::Dmc, false);
+  mix.set_soloed(AudioChannel::Pulse1, true);
+  nes.set_channel_mix(mix);
+  assert!(frame_audio(&mut nes).iter().all(|sample| *sample == -1.0));
+}
+
+#[test]
+fn loading_a_state_keeps_the_mix() {
+  let mut nes = humming_dmc();
+  let state = nes.save_state();
+  let mut mix = ChannelMix::default();
+  mix.set_muted(AudioChannel::Dmc, true);
+  nes.set_channel_mix(mix);
+  nes.load_state(&state);
+  assert_eq!(nes.channel_mix(), mix);
+}
+
+#[test]
+fn centred_channels_sound_the_same_on_both_sides() {
+  let mut nes = humming_dmc();
+  assert!(nes.channel_pan().centred());
+  let (left, right) = frame_stereo_audio(&mut nes);
+  assert_eq!(left, right);
+  assert!(left.iter().all(|sample| *sample > -1.0));
+}
+
+#[test]
+fn panning_moves_a_channel_between_the_speakers() {
+  let mut nes = humming_dmc();
+  let mut pan = ChannelPan::default();
+  pan.set_pan(AudioChannel::Dmc, -1.0);
+  nes.set_channel_pan(pan);
+  let (left, right) = frame_stereo_audio(&mut nes);
+  assert!(left.iter().all(|sample| *sample > -1.0));
+  assert!(right.iter().all(|sample| *sample == -1.0));
+
+  // Halfway across it's still at full volume on its own side
+  pan.set_pan(AudioChannel::Dmc, 0.5);
+  nes.set_channel_pan(pan);
+  let (quieter, louder) = frame_stereo_audio(&mut nes);
+  assert!(louder.iter().zip(&left).all(|(louder, full)| (louder - full).abs() < 1e-6));
+  assert!(quieter.iter().zip(&louder).all(|(quieter, louder)| quieter < louder && *quieter > -1.0));
+}
+
+#[test]
+fn panning_after_playing_centred_doesnt_pop() {
+  // Only the accurate mixer has the console's filters
+  let mut nes = humming_dmc();
+  nes.set_mixer(MixerMode::Accurate);
+  frame_stereo_audio(&mut nes);
+  // Panning a silent channel changes nothing, so each side's filters should carry on just the same
+  let mut pan = ChannelPan::default();
+  pan.set_pan(AudioChannel::Pulse1, -1.0);
+  nes.set_channel_pan(pan);
+  let (left, right) = frame_stereo_audio(&mut nes);
+  assert_eq!(left, right);
+}
+
+#[test]
+fn stereo_modes_place_the_channels() {
+  for mode in StereoMode::ALL {
+    assert_eq!(StereoMode::from_key(mode.key()), Some(mode));
+  }
+  let mut custom = ChannelPan::default();
+  custom.set_pan(AudioChannel::Noise, 2.0);
+  assert_eq!(custom.pan(AudioChannel::Noise), 1.0);
+
+  assert!(StereoMode::Mono.pan(custom).centred());
+  assert_eq!(StereoMode::Custom.pan(custom), custom);
+  let split = StereoMode::Split.pan(custom);
+  assert!(split.pan(AudioChannel::Pulse1) < 0.0 && split.pan(AudioChannel::Pulse2) < 0.0);
+  assert!(split.pan(AudioChannel::Triangle) > 0.0 && split.pan(AudioChannel::Noise) > 0.0);
+  assert_eq!(split.pan(AudioChannel::Dmc), 0.0);
+}
@@ -0,0 +1,58 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::cheat::Cheat;
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: copy a byte from ROM into RAM, then loop forever. $A000 is in the second page of PRG,
+/// which is filled with $01.
+const PROGRAM: [u8; 8] = [
+  0xAD, 0x00, 0xA0, // LDA $A000
+  0x85, 0x00,       // STA $00
+  0x4C, 0x05, 0xC0, // JMP *
+];
+
+#[test]
+fn decodes_game_genie_codes() {
+  let cheat = Cheat::parse("SXIOPO").unwrap();
+  assert_eq!((cheat.address, cheat.value, cheat.compare), (0x91D9, 0xAD, None));
+
+  let cheat = Cheat::parse("gossip").unwrap();
+  assert_eq!((cheat.address, cheat.value, cheat.compare), (0xD1DD, 0x14, None));
+
+  let cheat = Cheat::parse("ZEXPYGLA").unwrap();
+  assert_eq!((cheat.address, cheat.value, cheat.compare), (0x94A7, 0x02, Some(0x03)));
+
+  assert!(Cheat::parse("SXIOP").is_err());
+}
+
+#[test]
+fn decodes_pro_action_replay_and_raw_codes() {
+  let cheat = Cheat::parse("0075-09").unwrap();
+  assert_eq!((cheat.address, cheat.value, cheat.compare), (0x0075, 0x09, None));
+
+  let cheat = Cheat::parse("C0FF?11:99").unwrap();
+  assert_eq!((cheat.address, cheat.value, cheat.compare), (0xC0FF, 0x99, Some(0x11)));
+
+  assert!(Cheat::parse("C0FF:199").is_err());
+  assert!(Cheat::parse("hello").is_err());
+}
+
+#[test]
+fn cheats_patch_cpu_reads() {
+  let run = |code: Option<&str>| {
+    let mut nes = RomBuilder::new(0).prg(&PROGRAM).nes();
+    if let Some(code) = code {
+      nes.set_cheats(vec![Cheat::parse(code).unwrap()]);
+    }
+    nes.run_frame();
+    nes.peek(0x0000)
+  };
+
+  assert_eq!(run(None), 0x01);
+  assert_eq!(run(Some("A000:99")), 0x99);
+  // The compare value doesn't match what's really there, so the read goes through untouched
+  assert_eq!(run(Some("A000?22:99")), 0x01);
+}
@@ -0,0 +1,42 @@
+extern crate silknes_web;
+
+mod common;
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: turn on NMIs, rendering and the 5-step APU sequence, then loop forever
+/// restarting the frame counter and kicking off OAM DMAs
+const PROGRAM: [u8; 39] = [
+  0x78,             // SEI
+  // Wait out the PPU's warm up, which takes until the second vblank
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0xA2, 0xFF,       // LDX #$FF
+  0x9A,             // TXS
+  0xA9, 0x80,       // LDA #$80
+  0x8D, 0x00, 0x20, // STA $2000
+  0xA9, 0x1E,       // LDA #$1E
+  0x8D, 0x01, 0x20, // STA $2001
+  // loop:
+  0xA9, 0xC0,       // LDA #$C0
+  0x8D, 0x17, 0x40, // STA $4017
+  0xA9, 0x02,       // LDA #$02
+  0x8D, 0x14, 0x40, // STA $4014
+  0xE6, 0x00,       // INC $00
+  0x4C, 0x18, 0xC0, // JMP loop
+];
+
+#[test]
+fn clock_domains_stay_in_step() {
+  let mut nes = RomBuilder::new(0).prg(&PROGRAM).nes();
+
+  for _ in 0..10 {
+    nes.run_frame();
+  }
+
+  if let Some(violation) = nes.clock_violation() {
+    panic!("{}", violation);
+  }
+}
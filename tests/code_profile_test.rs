@@ -0,0 +1,94 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::code_profile::{CodeProfile, Grouping, HotSpot};
+use silknes_web::cpu::ExecutionHook;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::RomBuilder;
+
+/// NROM that spins on INX and JMP at $C000 forever, with interrupts off
+fn nes() -> Nes {
+  let rom = RomBuilder::new(0).prg_banks(1).prg(&[
+    0x78,             // SEI
+    0xE8,             // INX
+    0x4C, 0x01, 0xC0, // JMP $C001
+  ]);
+  let mut nes = Nes::new();
+  nes.insert_cartridge(rom.cartridge());
+  nes
+}
+
+/// A profile of the instructions in `runs`, each `(bank, pc, cycles)`
+fn profile(runs: &[(Option<u32>, u16, usize)]) -> CodeProfile {
+  let mut profile = CodeProfile::default();
+  for &(bank, pc, cycles) in runs {
+    profile.bank = bank;
+    profile.executed(pc, 0xEA, cycles);
+  }
+  profile
+}
+
+#[test]
+fn cycles_are_counted_per_instruction_as_the_console_runs() {
+  let mut nes = nes();
+  nes.run_frame();
+  assert!(nes.code_profile().is_none());
+  nes.set_code_profiling(true);
+  nes.run_frames(3);
+
+  let profile = nes.code_profile().unwrap();
+  assert_eq!(profile.frames(), 3);
+  let spots = profile.hot_spots(Grouping::Instruction);
+  assert_eq!(spots.len(), 2);
+  // JMP takes 3 cycles to INX's 2, and they take turns
+  let (jmp, inx) = (spots[0], spots[1]);
+  assert_eq!((jmp.first, inx.first), (0xC002, 0xC001));
+  assert_eq!(jmp.bank, Some(0));
+  assert!(jmp.cycles.abs_diff(inx.cycles * 3 / 2) <= 3, "{} and {}", jmp.cycles, inx.cycles);
+  // There's nothing else running once the loop's started
+  assert_eq!(profile.total_cycles(), jmp.cycles + inx.cycles);
+  assert!(profile.total_cycles() > 3 * 29000);
+
+  nes.set_code_profiling(false);
+  assert!(nes.code_profile().is_none());
+}
+
+#[test]
+fn groupings_lump_instructions_together() {
+  let profile = profile(&[
+    (Some(2), 0x8010, 4),
+    (Some(2), 0x8120, 2),
+    (Some(2), 0x8124, 3),
+    (Some(5), 0x8010, 6),
+    (None, 0x0300, 5),
+    (None, 0x6010, 1),
+  ]);
+  assert_eq!(profile.total_cycles(), 21);
+
+  let by_bank = profile.hot_spots(Grouping::Bank);
+  assert_eq!(by_bank, vec![
+    HotSpot { bank: Some(2), first: 0x8010, last: 0x8124, cycles: 9 },
+    HotSpot { bank: Some(5), first: 0x8010, last: 0x8010, cycles: 6 },
+    HotSpot { bank: None, first: 0x0300, last: 0x0300, cycles: 5 },
+    HotSpot { bank: None, first: 0x6010, last: 0x6010, cycles: 1 },
+  ]);
+
+  // The same address in two banks is two different pieces of code
+  let by_page = profile.hot_spots(Grouping::Page);
+  assert_eq!(by_page.len(), 5);
+  assert_eq!(by_page[0], HotSpot { bank: Some(5), first: 0x8010, last: 0x8010, cycles: 6 });
+  assert!(by_page.contains(&HotSpot { bank: Some(2), first: 0x8120, last: 0x8124, cycles: 5 }));
+
+  assert_eq!(profile.hot_spots(Grouping::Instruction).len(), 6);
+}
+
+#[test]
+fn clearing_starts_the_count_again() {
+  let mut profile = profile(&[(Some(0), 0xC000, 2)]);
+  profile.end_frame();
+  profile.clear();
+  assert_eq!((profile.total_cycles(), profile.frames()), (0, 0));
+  assert!(profile.hot_spots(Grouping::Bank).is_empty());
+}
@@ -0,0 +1,27 @@
+//! Helpers shared by the integration tests. Each test crate only uses some of them.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+pub mod rom_builder;
+
+/// Storage kept in memory, like the browser's local storage, that counts how often it's flushed
+#[derive(Default)]
+pub struct MemoryStorage {
+  pub values: HashMap<String, String>,
+  pub flushes: usize,
+}
+
+impl eframe::Storage for MemoryStorage {
+  fn get_string(&self, key: &str) -> Option<String> {
+    self.values.get(key).cloned()
+  }
+
+  fn set_string(&mut self, key: &str, value: String) {
+    self.values.insert(key.to_string(), value);
+  }
+
+  fn flush(&mut self) {
+    self.flushes += 1;
+  }
+}
@@ -0,0 +1,236 @@
+//! Synthetic iNES images built in code, for testing mappers without needing copyrighted ROMs.
+//!
+//! Each 8 KB page of PRG ROM is filled with its own page number, and each 1 KB page of CHR ROM with its
+//! own, those being the smallest units any of the boards switch. Reading a byte back through the
+//! cartridge then says which page is mapped there, e.g. `cpu_read(0xC000) == Some(5)` means the sixth
+//! 8 KB page of PRG is at $C000.
+//!
+//! Tests that need the console to run something give it code with [`RomBuilder::prg`], which takes
+//! over the last 16 KB of PRG.
+
+use silknes_web::cartridge::Cartridge;
+use silknes_web::nes::Nes;
+
+/// Size of the pages PRG ROM is numbered in
+pub const PRG_PAGE_SIZE: usize = 0x2000;
+/// Size of the pages CHR ROM is numbered in
+pub const CHR_PAGE_SIZE: usize = 0x400;
+
+/// Where the code given to the builder runs from. The last 16 KB of PRG is at $C000 on power on for
+/// NROM, UxROM, MMC1 and MMC3 alike.
+pub const RESET_ADDRESS: u16 = 0xC000;
+pub const NMI_ADDRESS: u16 = 0xD000;
+pub const IRQ_ADDRESS: u16 = 0xE000;
+
+/// Code for [`RomBuilder::prg`] that disables interrupts and spins, for tests that only need the console running
+pub const SPIN: [u8; 4] = [
+  0x78,             // SEI
+  0x4C, 0x01, 0xC0, // JMP $C001
+];
+
+/// Code for [`RomBuilder::prg`] that waits out the PPU's warm up, which takes until the second vblank,
+/// then turns on NMIs and spins, for tests driven by the NMI handler
+pub const SPIN_WITH_NMI: [u8; 19] = [
+  0x78,             // SEI
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0xA9, 0x80,       // LDA #$80
+  0x8D, 0x00, 0x20, // STA $2000
+  // loop:
+  0x4C, 0x10, 0xC0, // JMP loop
+];
+
+/// The start of CHR for [`RomBuilder::chr`] with tile 0 blank and tile 1 solid in colour 1
+pub const SOLID_TILE_CHR: [u8; 0x20] = {
+  let mut chr = [0; 0x20];
+  let mut i = 0x10;
+  while i < 0x18 {
+    chr[i] = 0xFF;
+    i += 1;
+  }
+  chr
+};
+
+/// 8 KB of CHR with something in every tile, for tests that need rendering to draw more than the backdrop
+pub fn textured_chr() -> Vec<u8> {
+  (0..0x2000).map(|i| (i * 7) as u8).collect()
+}
+
+/// Builds an iNES image for a mapper. Sizes are in the header's units, 16 KB of PRG and 8 KB of CHR.
+#[derive(Clone, Debug)]
+pub struct RomBuilder {
+  mapper: u8,
+  prg_banks: u8,
+  chr_banks: u8,
+  vertical_mirroring: bool,
+  battery: bool,
+  /// Flagged in the header as a PAL game, which only iNES headers can say this way
+  pal: bool,
+  /// Loaded into PRG RAM at $7000 when there is one
+  trainer: Option<Vec<u8>>,
+  /// Written into an NES 2.0 header when set, otherwise the header's plain iNES
+  submapper: Option<u8>,
+  /// NES 2.0 miscellaneous ROM, after CHR
+  misc_rom: Option<Vec<u8>>,
+  /// Code run from reset, and the interrupt handlers, which are a lone RTI unless given
+  reset: Option<Vec<u8>>,
+  nmi: Vec<u8>,
+  irq: Vec<u8>,
+  /// What CHR ROM starts with in place of page numbers
+  chr: Vec<u8>,
+}
+
+impl RomBuilder {
+  /// 32 KB of PRG ROM and 8 KB of CHR ROM, with horizontal mirroring
+  pub fn new(mapper: u8) -> Self {
+    Self {
+      mapper,
+      prg_banks: 2,
+      chr_banks: 1,
+      vertical_mirroring: false,
+      battery: false,
+      pal: false,
+      trainer: None,
+      submapper: None,
+      misc_rom: None,
+      reset: None,
+      nmi: vec![0x40],
+      irq: vec![0x40],
+      chr: vec![],
+    }
+  }
+
+  /// How many 16 KB banks of PRG ROM there are
+  pub fn prg_banks(mut self, banks: u8) -> Self {
+    self.prg_banks = banks;
+    self
+  }
+
+  /// How many 8 KB banks of CHR ROM there are, with 0 for CHR RAM instead
+  pub fn chr_banks(mut self, banks: u8) -> Self {
+    self.chr_banks = banks;
+    self
+  }
+
+  pub fn vertical_mirroring(mut self) -> Self {
+    self.vertical_mirroring = true;
+    self
+  }
+
+  /// Battery backed PRG RAM at $6000-$7FFF
+  pub fn battery(mut self) -> Self {
+    self.battery = true;
+    self
+  }
+
+  pub fn pal(mut self) -> Self {
+    self.pal = true;
+    self
+  }
+
+  /// A 512 byte trainer starting with `data`, and zeros after it
+  pub fn trainer(mut self, data: &[u8]) -> Self {
+    let mut trainer = vec![0; 0x200];
+    trainer[..data.len()].copy_from_slice(data);
+    self.trainer = Some(trainer);
+    self
+  }
+
+  pub fn submapper(mut self, submapper: u8) -> Self {
+    self.submapper = Some(submapper);
+    self
+  }
+
+  /// One misc ROM holding `data`, which takes an NES 2.0 header
+  pub fn misc_rom(mut self, data: &[u8]) -> Self {
+    self.misc_rom = Some(data.to_vec());
+    self
+  }
+
+  /// Code to run from reset at [`RESET_ADDRESS`]. The last 16 KB of PRG is NOPs around it, rather
+  /// than page numbers.
+  pub fn prg(mut self, code: &[u8]) -> Self {
+    self.reset = Some(code.to_vec());
+    self
+  }
+
+  /// The NMI handler, at [`NMI_ADDRESS`]
+  pub fn nmi(mut self, code: &[u8]) -> Self {
+    self.nmi = code.to_vec();
+    self
+  }
+
+  /// The IRQ handler, at [`IRQ_ADDRESS`]
+  pub fn irq(mut self, code: &[u8]) -> Self {
+    self.irq = code.to_vec();
+    self
+  }
+
+  /// CHR ROM starting with `data`, with page numbers after it as usual
+  pub fn chr(mut self, data: &[u8]) -> Self {
+    self.chr = data.to_vec();
+    self
+  }
+
+  /// The whole image, header and all
+  pub fn build(&self) -> Vec<u8> {
+    let flags6 = (self.mapper << 4) | ((self.trainer.is_some() as u8) << 2) | ((self.battery as u8) << 1) | self.vertical_mirroring as u8;
+    let mut flags7 = self.mapper & 0xF0;
+    let mut flags8 = 0;
+    if self.submapper.is_some() || self.misc_rom.is_some() {
+      flags7 |= 0b0000_1000;
+      flags8 = self.submapper.unwrap_or(0) << 4;
+    }
+    let flags9 = self.pal as u8;
+    let misc_roms = self.misc_rom.is_some() as u8;
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, self.prg_banks, self.chr_banks, flags6, flags7, flags8, flags9, 0, 0, 0, 0, misc_roms, 0];
+    if let Some(trainer) = &self.trainer {
+      rom.extend(trainer);
+    }
+
+    let prg_pages = self.prg_banks as usize * 0x4000 / PRG_PAGE_SIZE;
+    let mut prg = Vec::with_capacity(prg_pages * PRG_PAGE_SIZE);
+    for page in 0..prg_pages {
+      prg.extend(std::iter::repeat_n(page as u8, PRG_PAGE_SIZE));
+    }
+    if let Some(reset) = &self.reset {
+      let last_bank = prg.len() - 0x4000;
+      let bank = &mut prg[last_bank..];
+      bank.fill(0xEA);
+      for (address, code) in [(RESET_ADDRESS, reset), (NMI_ADDRESS, &self.nmi), (IRQ_ADDRESS, &self.irq)] {
+        let offset = (address - 0xC000) as usize;
+        bank[offset..offset + code.len()].copy_from_slice(code);
+      }
+      for (vector, address) in [(0x3FFA, NMI_ADDRESS), (0x3FFC, RESET_ADDRESS), (0x3FFE, IRQ_ADDRESS)] {
+        bank[vector..vector + 2].copy_from_slice(&address.to_le_bytes());
+      }
+    }
+    rom.extend(prg);
+
+    let chr_pages = self.chr_banks as usize * 0x2000 / CHR_PAGE_SIZE;
+    let mut chr = Vec::with_capacity(chr_pages * CHR_PAGE_SIZE);
+    for page in 0..chr_pages {
+      chr.extend(std::iter::repeat_n(page as u8, CHR_PAGE_SIZE));
+    }
+    chr[..self.chr.len()].copy_from_slice(&self.chr);
+    rom.extend(chr);
+    if let Some(misc_rom) = &self.misc_rom {
+      rom.extend(misc_rom);
+    }
+    rom
+  }
+
+  /// The image loaded as a cartridge, which it always should be for a mapper we have
+  pub fn cartridge(&self) -> Cartridge {
+    Cartridge::from_bytes(self.build()).expect("synthetic ROMs only fail to load for mappers we don't have")
+  }
+
+  /// A console with the cartridge in, switched on and ready to run
+  pub fn nes(&self) -> Nes {
+    let mut nes = Nes::new();
+    nes.insert_cartridge(self.cartridge());
+    nes
+  }
+}
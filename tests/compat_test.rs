@@ -0,0 +1,105 @@
+extern crate silknes_web;
+
+mod common;
+
+use std::path::PathBuf;
+
+use silknes_web::compat::{self, CompatResult};
+
+use common::rom_builder::RomBuilder;
+
+/// NROM that waits out the PPU's warm up, sets two colours, turns rendering on and spins, with CHR
+/// full of a pattern so there's something to see
+fn rendering_rom() -> Vec<u8> {
+  let chr: Vec<u8> = (0..0x2000).map(|i| (i * 7) as u8).collect();
+  RomBuilder::new(0).prg_banks(1).chr(&chr).prg(&[
+    0x78,             // SEI
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C001
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C006
+    0xA9, 0x3F,       // LDA #$3F
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x00,       // LDA #$00
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x0F,       // LDA #$0F
+    0x8D, 0x07, 0x20, // STA $2007
+    0xA9, 0x30,       // LDA #$30
+    0x8D, 0x07, 0x20, // STA $2007
+    0xA9, 0x1E,       // LDA #$1E
+    0x8D, 0x01, 0x20, // STA $2001
+    0x4C, 0x24, 0xC0, // JMP $C024
+  ]).build()
+}
+
+/// A fresh folder holding a game that draws, one that never turns rendering on, one with a mapper we
+/// don't have and a text file
+fn folder(name: &str) -> PathBuf {
+  let folder = std::env::temp_dir().join(format!("silknes-compat-{}-{}", name, std::process::id()));
+  let _ = std::fs::remove_dir_all(&folder);
+  std::fs::create_dir_all(&folder).unwrap();
+  std::fs::write(folder.join("draws.nes"), rendering_rom()).unwrap();
+  std::fs::write(folder.join("blank.NES"), RomBuilder::new(0).prg_banks(1).build()).unwrap();
+  std::fs::write(folder.join("mystery.nes"), RomBuilder::new(254).build()).unwrap();
+  std::fs::write(folder.join("readme.txt"), "not a ROM").unwrap();
+  folder
+}
+
+#[test]
+fn scanning_reports_each_rom_in_the_folder() {
+  let results = compat::scan(&folder("scan"), 60).unwrap();
+  let files: Vec<_> = results.iter().map(|result| result.file.as_str()).collect();
+  assert_eq!(files, ["blank.NES", "draws.nes", "mystery.nes"]);
+
+  assert_eq!(results[0].mapper, "mapper 0, NROM");
+  assert!(results[0].ran_ok);
+  assert!(results[0].blank_screen);
+
+  assert_eq!(results[1], CompatResult {
+    file: "draws.nes".to_string(),
+    mapper: "mapper 0, NROM".to_string(),
+    ran_ok: true,
+    frames: 60,
+    panic: None,
+    blank_screen: false,
+  });
+
+  assert!(!results[2].ran_ok);
+  assert!(results[2].mapper.contains("254"), "{}", results[2].mapper);
+  assert_eq!(results[2].frames, 0);
+}
+
+#[test]
+fn reports_come_as_csv_or_json() {
+  let results = [
+    CompatResult {
+      file: "fine.nes".to_string(),
+      mapper: "mapper 4, MMC3".to_string(),
+      ran_ok: true,
+      frames: 600,
+      panic: None,
+      blank_screen: false,
+    },
+    CompatResult {
+      file: "broken.nes".to_string(),
+      mapper: "mapper 1, MMC1".to_string(),
+      ran_ok: false,
+      frames: 12,
+      panic: Some("index out of bounds: \"len\" is 8".to_string()),
+      blank_screen: true,
+    },
+  ];
+
+  assert_eq!(compat::to_csv(&results), concat!(
+    "file,mapper,ran_ok,frames,panic,blank_screen\n",
+    "fine.nes,\"mapper 4, MMC3\",true,600,,false\n",
+    "broken.nes,\"mapper 1, MMC1\",false,12,\"index out of bounds: \"\"len\"\" is 8\",true\n",
+  ));
+
+  let json: serde_json::Value = serde_json::from_str(&compat::to_json(&results)).unwrap();
+  assert_eq!(json[0]["panic"], serde_json::Value::Null);
+  assert_eq!(json[1]["file"], "broken.nes");
+  assert_eq!(json[1]["frames"], 12);
+  assert_eq!(json[1]["panic"], "index out of bounds: \"len\" is 8");
+  assert_eq!(json[1]["blank_screen"], true);
+}
@@ -0,0 +1,83 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::condition::Comparison;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN_WITH_NMI};
+
+/// NROM whose NMI handler counts frames up in $10
+fn nes() -> Nes {
+  let rom = RomBuilder::new(0)
+    .prg(&SPIN_WITH_NMI)
+    .nmi(&[
+      0xE6, 0x10, // INC $10
+      0x40,       // RTI
+    ]);
+  let mut nes = rom.nes();
+  nes.run_frames(2);
+  nes
+}
+
+#[test]
+fn a_condition_fires_once_when_it_becomes_true() {
+  let mut nes = nes();
+  let target = nes.peek(0x10).wrapping_add(3);
+  let id = nes.watch(0x10, move |_, current| current == target);
+  nes.run_frames(10);
+  let events = nes.take_condition_events();
+  assert_eq!(events.len(), 1);
+  assert_eq!((events[0].id, events[0].address, events[0].current), (id, 0x10, target));
+  assert_eq!(events[0].previous, target.wrapping_sub(1));
+  assert!(nes.take_condition_events().is_empty());
+}
+
+#[test]
+fn a_condition_fires_again_when_the_byte_changes_while_it_holds() {
+  let mut nes = nes();
+  let start = nes.frame_count();
+  nes.watch(0x10, |previous, current| Comparison::Increased.test(0, previous, current));
+  nes.run_frames(5);
+  let frames: Vec<u64> = nes.take_condition_events().iter().map(|event| event.frame).collect();
+  assert_eq!(frames, (start + 1..=start + 5).collect::<Vec<_>>());
+}
+
+#[test]
+fn a_condition_that_already_holds_waits_for_a_change() {
+  let mut nes = nes();
+  nes.poke(0x20, 7);
+  let id = nes.watch(0x20, |_, current| current == 7);
+  nes.run_frames(3);
+  assert!(nes.take_condition_events().is_empty());
+
+  nes.poke(0x20, 8);
+  nes.run_frame();
+  nes.poke(0x20, 7);
+  nes.run_frame();
+  let events = nes.take_condition_events();
+  assert_eq!(events.iter().map(|event| event.id).collect::<Vec<_>>(), [id]);
+}
+
+#[test]
+fn unwatched_conditions_stop_firing() {
+  let mut nes = nes();
+  let id = nes.watch(0x10, |previous, current| current != previous);
+  nes.run_frame();
+  assert_eq!(nes.take_condition_events().len(), 1);
+  nes.unwatch(id);
+  nes.run_frames(3);
+  assert!(nes.take_condition_events().is_empty());
+}
+
+#[test]
+fn comparisons_test_against_the_value_or_the_last_frame() {
+  assert!(Comparison::Equal.test(5, 0, 5));
+  assert!(!Comparison::NotEqual.test(5, 0, 5));
+  assert!(Comparison::Less.test(5, 9, 4));
+  assert!(Comparison::Greater.test(5, 0, 6));
+  assert!(Comparison::Changed.test(0, 1, 2));
+  assert!(!Comparison::Increased.test(0, 2, 2));
+  assert!(Comparison::Decreased.test(0, 2, 1));
+  assert_eq!(Comparison::ALL.iter().filter(|comparison| comparison.uses_value()).count(), 4);
+}
@@ -0,0 +1,75 @@
+extern crate silknes_web;
+
+mod common;
+
+use std::path::PathBuf;
+
+use silknes_web::apu::{AudioChannel, StereoMode};
+use silknes_web::config::{Config, SettingsFile};
+
+use common::MemoryStorage;
+
+#[test]
+fn every_setting_survives_a_round_trip() {
+  let mut config = Config {
+    rom_directory: Some(PathBuf::from("/home/player/roms")),
+    console_open: true,
+    reduce_pulse_popping: true,
+    watches: vec![0x0010, 0x07FF, 0x6000],
+    stereo: StereoMode::Custom,
+    ..Config::default()
+  };
+  config.channel_pan.set_pan(AudioChannel::Triangle, -0.5);
+  let mut storage = MemoryStorage::default();
+  config.save(&mut storage);
+  assert_eq!(Config::load(Some(&storage)), config);
+}
+
+#[test]
+fn no_rom_directory_or_watches_loads_as_none() {
+  let mut storage = MemoryStorage::default();
+  Config::default().save(&mut storage);
+  let config = Config::load(Some(&storage));
+  assert_eq!(config.rom_directory, None);
+  assert!(config.watches.is_empty());
+}
+
+#[test]
+fn saving_only_happens_when_something_changed() {
+  let mut storage = MemoryStorage::default();
+  let mut saved = Config::default();
+  let mut config = saved.clone();
+  config.save_if_changed(&mut saved, &mut storage);
+  assert_eq!(storage.flushes, 0);
+  assert!(storage.values.is_empty());
+
+  config.console_open = true;
+  config.save_if_changed(&mut saved, &mut storage);
+  assert_eq!(storage.flushes, 1);
+  assert_eq!(saved, config);
+  assert!(Config::load(Some(&storage)).console_open);
+
+  config.save_if_changed(&mut saved, &mut storage);
+  assert_eq!(storage.flushes, 1);
+}
+
+#[test]
+fn the_settings_file_keeps_the_config_between_runs() {
+  let folder = std::env::temp_dir().join(format!("silknes-settings-{}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&folder);
+  let path = folder.join("nested/settings.json");
+  let mut file = SettingsFile::at(path.clone());
+  assert!(!file.existed());
+  assert_eq!(Config::load(Some(&file)), Config::default());
+
+  let mut saved = Config::default();
+  let config = Config { hide_overscan: true, watches: vec![0x0300], ..Config::default() };
+  config.save_if_changed(&mut saved, &mut file);
+  let file = SettingsFile::at(path.clone());
+  assert!(file.existed());
+  assert_eq!(Config::load(Some(&file)), config);
+
+  // A mangled file is ignored rather than stopping the emulator from starting
+  std::fs::write(&path, "{ not json").unwrap();
+  assert_eq!(Config::load(Some(&SettingsFile::at(path))), Config::default());
+}
@@ -0,0 +1,96 @@
+extern crate silknes_web;
+
+use silknes_web::bus::{BusLike, IrqSource, MockBus};
+use silknes_web::cpu::NES6502;
+
+const IRQ_HANDLER: u16 = 0x9000;
+
+/// A CPU about to run `program` at $8000, with an IRQ/BRK handler that returns straight away
+fn new_cpu(program: &[u8]) -> (NES6502, MockBus) {
+  let mut mock = MockBus::new();
+  mock.cpu_ram[0x8000..0x8000 + program.len()].copy_from_slice(program);
+  mock.cpu_ram[IRQ_HANDLER as usize] = 0x40; // RTI
+  mock.cpu_ram[0xFFFE..].copy_from_slice(&IRQ_HANDLER.to_le_bytes());
+
+  let mut cpu = NES6502::new();
+  cpu.pc = 0x8000;
+  (cpu, mock)
+}
+
+fn run_instructions(cpu: &mut NES6502, bus: &mut MockBus, count: usize) {
+  for _ in 0..count {
+    cpu.step(bus);
+    while cpu.cycles > 0 {
+      cpu.step(bus);
+    }
+  }
+}
+
+/// The byte on top of the stack
+fn top_of_stack(cpu: &NES6502, bus: &MockBus) -> u8 {
+  bus.peek(0x0100 + cpu.sp.wrapping_add(1) as u16)
+}
+
+#[test]
+fn php_and_brk_push_b_set_and_irqs_push_it_clear() {
+  let (mut cpu, mut bus) = new_cpu(&[0x08]); // PHP
+  run_instructions(&mut cpu, &mut bus, 1);
+  assert_eq!(top_of_stack(&cpu, &bus) & 0x30, 0x30);
+
+  let (mut cpu, mut bus) = new_cpu(&[0x00, 0x00]); // BRK
+  run_instructions(&mut cpu, &mut bus, 1);
+  assert_eq!(cpu.pc, IRQ_HANDLER);
+  assert_eq!(top_of_stack(&cpu, &bus) & 0x30, 0x30);
+
+  let (mut cpu, mut bus) = new_cpu(&[0xEA, 0xEA, 0xEA]); // NOPs
+  bus.set_irq(IrqSource::Mapper, true);
+  run_instructions(&mut cpu, &mut bus, 2);
+  assert_eq!(cpu.pc, IRQ_HANDLER);
+  assert_eq!(top_of_stack(&cpu, &bus) & 0x30, 0x20);
+}
+
+#[test]
+fn plp_and_rti_ignore_bits_4_and_5() {
+  // LDA #$FF, PHA, PLP, PHP
+  let (mut cpu, mut bus) = new_cpu(&[0xA9, 0xFF, 0x48, 0x28, 0x08]);
+  run_instructions(&mut cpu, &mut bus, 3);
+  assert_eq!(cpu.flags.to_u8(), 0xEF);
+  run_instructions(&mut cpu, &mut bus, 1);
+  assert_eq!(top_of_stack(&cpu, &bus), 0xFF);
+
+  // BRK and straight back with RTI, which pulls the B set flags BRK pushed without keeping B
+  let (mut cpu, mut bus) = new_cpu(&[0x00, 0x00, 0xEA]);
+  run_instructions(&mut cpu, &mut bus, 2);
+  assert_eq!(cpu.pc, 0x8002);
+  assert_eq!(cpu.flags.to_u8() & 0x30, 0x20);
+}
+
+#[test]
+fn adc_and_sbc_ignore_decimal_mode() {
+  // SED, CLC, LDA #$09, ADC #$01
+  let (mut cpu, mut bus) = new_cpu(&[0xF8, 0x18, 0xA9, 0x09, 0x69, 0x01]);
+  run_instructions(&mut cpu, &mut bus, 4);
+  assert!(cpu.flags.decimal_mode);
+  assert_eq!(cpu.a, 0x0A);
+
+  // SED, SEC, LDA #$10, SBC #$01
+  let (mut cpu, mut bus) = new_cpu(&[0xF8, 0x38, 0xA9, 0x10, 0xE9, 0x01]);
+  run_instructions(&mut cpu, &mut bus, 4);
+  assert_eq!(cpu.a, 0x0F);
+  assert!(cpu.flags.carry);
+}
+
+#[test]
+fn compares_wrap_below_zero() {
+  // LDA #$00, CMP #$01
+  let (mut cpu, mut bus) = new_cpu(&[0xA9, 0x00, 0xC9, 0x01]);
+  run_instructions(&mut cpu, &mut bus, 2);
+  assert!(!cpu.flags.carry && !cpu.flags.zero && cpu.flags.negative);
+
+  // LDX #$00, CPX #$FF, LDY #$80, CPY #$80
+  let (mut cpu, mut bus) = new_cpu(&[0xA2, 0x00, 0xE0, 0xFF, 0xA0, 0x80, 0xC0, 0x80]);
+  run_instructions(&mut cpu, &mut bus, 2);
+  assert!(!cpu.flags.carry && !cpu.flags.zero && !cpu.flags.negative);
+  run_instructions(&mut cpu, &mut bus, 2);
+  assert!(cpu.flags.carry && cpu.flags.zero && !cpu.flags.negative);
+}
@@ -0,0 +1,80 @@
+extern crate silknes_web;
+
+use std::collections::HashSet;
+
+use silknes_web::demos::{self, starfield, DEMOS};
+use silknes_web::nes::Nes;
+
+const RIGHT: u8 = 0x01;
+const DOWN: u8 = 0x04;
+
+fn nes(id: &str) -> Nes {
+  let mut nes = Nes::new();
+  nes.insert_cartridge(DEMOS[demos::find(id).unwrap()].cartridge().unwrap());
+  nes.run_frames(5);
+  nes
+}
+
+/// Sprite `sprite` as the demo keeps it at $0200, as its X and Y
+fn position(nes: &Nes, sprite: u16) -> (u8, u8) {
+  (nes.peek(0x0203 + sprite * 4), nes.peek(0x0200 + sprite * 4))
+}
+
+#[test]
+fn every_demo_loads_and_draws_something() {
+  for demo in DEMOS {
+    let mut nes = Nes::new();
+    nes.insert_cartridge(demo.cartridge().unwrap());
+    nes.run_frames(30);
+    let colors: HashSet<&[u8]> = nes.screen().chunks(3).collect();
+    assert!(colors.len() > 1, "{} only drew its backdrop", demo.name);
+  }
+}
+
+#[test]
+fn balls_stay_on_screen_and_play_notes_as_they_bounce() {
+  let mut nes = nes("bounce");
+  let start: Vec<_> = (0..8).map(|ball| position(&nes, ball)).collect();
+  nes.take_audio();
+  for _ in 0..300 {
+    nes.run_frame();
+    for ball in 0..8 {
+      let (x, y) = position(&nes, ball);
+      assert!(x <= 0xF8 && (0x08..=0xDE).contains(&y), "ball {ball} at {x}, {y}");
+    }
+  }
+  let end: Vec<_> = (0..8).map(|ball| position(&nes, ball)).collect();
+  assert_ne!(start, end);
+
+  let audio = nes.take_audio();
+  let quietest = audio.iter().cloned().fold(f32::INFINITY, f32::min);
+  let loudest = audio.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+  assert!(loudest - quietest > 0.01);
+}
+
+#[test]
+fn the_ship_flies_with_the_d_pad_while_the_stars_stream_left() {
+  let mut nes = nes("starfield");
+  let (ship_x, ship_y) = position(&nes, 0);
+  let stars: Vec<_> = (1..=starfield::STARS as u16).map(|star| position(&nes, star)).collect();
+
+  nes.update_controller(0, RIGHT | DOWN);
+  nes.run_frames(10);
+  nes.update_controller(0, 0);
+  nes.run_frames(2);
+  let (x, y) = position(&nes, 0);
+  assert!(x > ship_x && y > ship_y);
+
+  for (star, &(x, y)) in stars.iter().enumerate() {
+    let (now_x, now_y) = position(&nes, star as u16 + 1);
+    assert_eq!(now_y, y);
+    // Farther stars move a pixel a frame and the nearest four
+    assert_eq!(x.wrapping_sub(now_x), 12 * (star as u8 % 4 + 1), "star {star}");
+  }
+}
+
+#[test]
+fn demos_are_found_by_their_console_names() {
+  assert_eq!(demos::find("starfield"), Some(1));
+  assert_eq!(demos::find("tetris"), None);
+}
@@ -0,0 +1,119 @@
+extern crate silknes_web;
+
+use silknes_web::apu::APU;
+
+/// An APU and the CPU cycles it's run for, with each of the 256 bytes from $C000 holding the low byte of
+/// its address so a fetched sample byte shows where it came from
+struct Dmc {
+  apu: APU,
+  cycles: u32,
+  fetched: Vec<u16>,
+}
+
+impl Dmc {
+  /// The DMC at its fastest rate with `flags` in $4010's top bits, `length` bytes from $C000, and
+  /// started through $4015
+  fn start(flags: u8, length: u8) -> Self {
+    let mut apu = APU::new();
+    apu.cpu_write(0x4010, flags | 0x0F);
+    apu.cpu_write(0x4012, 0x00);
+    apu.cpu_write(0x4013, length);
+    apu.cpu_write(0x4015, 0x10);
+    Dmc { apu, cycles: 0, fetched: vec![] }
+  }
+
+  /// Run `cycles` CPU cycles, handing over whatever sample bytes the memory reader asks for
+  fn run(&mut self, cycles: u32) {
+    self.run_with(cycles, |address| address as u8);
+  }
+
+  fn run_with(&mut self, cycles: u32, memory: impl Fn(u16) -> u8) {
+    for _ in 0..cycles {
+      let sample = self.apu.dmc_fetch_address().map(|address| {
+        self.fetched.push(address);
+        memory(address)
+      });
+      self.apu.step(self.cycles, sample);
+      self.cycles += 1;
+    }
+  }
+
+  fn status(&mut self) -> u8 {
+    self.apu.cpu_read(0x4015)
+  }
+}
+
+/// Cycles between sample bytes at the fastest rate, 8 bits of 54 cycles each
+const BYTE_CYCLES: u32 = 8 * 54;
+
+#[test]
+fn sample_bytes_are_fetched_in_order_until_the_sample_ends() {
+  // A length of 1 is 17 bytes
+  let mut dmc = Dmc::start(0x00, 1);
+  assert_ne!(dmc.status() & 0x10, 0);
+  dmc.run(BYTE_CYCLES * 20);
+  assert_eq!(dmc.fetched, (0xC000..0xC011).collect::<Vec<u16>>());
+  assert_eq!(dmc.status() & 0x10, 0);
+}
+
+#[test]
+fn turning_the_dmc_on_while_it_plays_carries_on_rather_than_restarting() {
+  let mut dmc = Dmc::start(0x00, 1);
+  dmc.run(BYTE_CYCLES * 3);
+  let fetched = dmc.fetched.len();
+  dmc.apu.cpu_write(0x4015, 0x10);
+  dmc.run(BYTE_CYCLES);
+  assert_eq!(dmc.fetched[fetched], 0xC000 + fetched as u16);
+
+  // Once it's finished, turning it on plays it again from the start
+  dmc.run(BYTE_CYCLES * 20);
+  assert_eq!(dmc.status() & 0x10, 0);
+  dmc.fetched.clear();
+  dmc.apu.cpu_write(0x4015, 0x10);
+  dmc.run(BYTE_CYCLES);
+  assert_eq!(dmc.fetched.first(), Some(&0xC000));
+}
+
+#[test]
+fn turning_the_dmc_off_stops_fetching_straight_away() {
+  let mut dmc = Dmc::start(0x00, 1);
+  dmc.run(BYTE_CYCLES * 3);
+  dmc.apu.cpu_write(0x4015, 0x00);
+  assert_eq!(dmc.status() & 0x10, 0);
+  let fetched = dmc.fetched.len();
+  dmc.run(BYTE_CYCLES * 4);
+  assert_eq!(dmc.fetched.len(), fetched);
+}
+
+#[test]
+fn the_irq_flag_is_acknowledged_by_writing_4015_or_turning_irqs_off_but_not_by_reading() {
+  let mut dmc = Dmc::start(0x80, 0);
+  dmc.run(BYTE_CYCLES * 2);
+  assert_ne!(dmc.status() & 0x80, 0);
+  assert_ne!(dmc.status() & 0x80, 0);
+  dmc.apu.cpu_write(0x4015, 0x00);
+  assert_eq!(dmc.status() & 0x80, 0);
+
+  let mut dmc = Dmc::start(0x80, 0);
+  dmc.run(BYTE_CYCLES * 2);
+  dmc.apu.cpu_write(0x4010, 0x0F);
+  assert_eq!(dmc.status() & 0x80, 0);
+}
+
+#[test]
+fn looping_samples_start_over_without_an_irq() {
+  let mut dmc = Dmc::start(0xC0, 0);
+  dmc.run(BYTE_CYCLES * 4);
+  assert!(dmc.fetched.len() >= 3);
+  assert!(dmc.fetched.iter().all(|&address| address == 0xC000));
+  assert_ne!(dmc.status() & 0x10, 0);
+  assert_eq!(dmc.status() & 0x80, 0);
+}
+
+#[test]
+fn zero_bytes_play_rather_than_counting_as_silence() {
+  let mut dmc = Dmc::start(0x00, 4);
+  dmc.apu.cpu_write(0x4011, 0x40);
+  dmc.run_with(BYTE_CYCLES * 10, |_| 0x00);
+  assert_eq!(dmc.apu.dmc_state().output, 0);
+}
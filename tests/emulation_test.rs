@@ -0,0 +1,252 @@
+extern crate silknes_web;
+
+mod common;
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use silknes_web::apu_output::{APUOutput, AudioClock, AudioControls, SyncMode};
+use silknes_web::audio_pipeline::AudioPipeline;
+use silknes_web::bus::BusLike;
+use silknes_web::emulation::{frame_buffer, Core, Emulation, Event, FrameClock, Input, SLICES_PER_FRAME};
+use silknes_web::frame_advance::BackgroundMode;
+use silknes_web::input::PadState;
+use silknes_web::nes::{Nes, SaveState, AUDIO_CHANNELS, OUTPUT_SAMPLES_PER_FRAME};
+use silknes_web::video::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use common::rom_builder::RomBuilder;
+
+/// 16 KB NROM whose reset handler disables interrupts, runs through a page of NOPs and starts again, with
+/// the receiver its audio goes to
+fn core() -> (Core, mpsc::Receiver<Vec<f32>>) {
+  let mut code = vec![0xEA; 0x100];
+  code[0] = 0x78; // SEI
+  code[0xFD..].copy_from_slice(&[0x4C, 0x00, 0xC0]); // JMP $C000
+  let (tx, rx) = mpsc::channel();
+  let mut core = Core::new(4, AudioPipeline::start(tx));
+  core.nes.insert_cartridge(RomBuilder::new(0).prg_banks(1).prg(&code).cartridge());
+  (core, rx)
+}
+
+#[test]
+fn the_console_can_move_between_threads() {
+  fn assert_send<T: Send>() {}
+  assert_send::<Nes>();
+  assert_send::<SaveState>();
+  assert_send::<Core>();
+}
+
+#[test]
+fn frame_buffer_hands_over_the_newest_frame() {
+  let (mut writer, mut reader) = frame_buffer();
+  assert!(reader.latest().is_empty());
+  writer.publish(&[1, 1]);
+  writer.publish(&[2, 2]);
+  assert_eq!(reader.latest(), &[2, 2]);
+  // Nothing new, so the same frame again
+  assert_eq!(reader.latest(), &[2, 2]);
+  writer.publish(&[3, 3]);
+  writer.publish(&[4, 4]);
+  assert_eq!(reader.latest(), &[4, 4]);
+}
+
+/// How many frames come due one after another, without any time passing to speak of
+fn frames_due(clock: &mut FrameClock) -> usize {
+  let mut due = 0;
+  while clock.frame_due() {
+    due += 1;
+  }
+  due
+}
+
+#[test]
+fn frame_clock_runs_a_frame_for_each_frame_of_audio_played() {
+  let controls = AudioControls::default();
+  let (_tx, rx) = mpsc::channel();
+  let mut output = APUOutput::new(rx, controls.clone());
+  let mut clock = FrameClock::new(controls.clone());
+
+  // Enough up front to fill the latency, and then nothing more until some of it's played
+  let filled = frames_due(&mut clock);
+  assert_eq!(filled, controls.latency_samples() / OUTPUT_SAMPLES_PER_FRAME + 1);
+  assert_eq!(frames_due(&mut clock), 0);
+
+  for _ in 0..3 * OUTPUT_SAMPLES_PER_FRAME * AUDIO_CHANNELS {
+    output.next();
+  }
+  assert_eq!(controls.samples_played(), 3 * OUTPUT_SAMPLES_PER_FRAME as u64);
+  assert_eq!(frames_due(&mut clock), 3);
+}
+
+#[test]
+fn frame_clock_splits_frames_into_slices_with_a_frame_of_audio_between_them() {
+  let controls = AudioControls::default();
+  let (_tx, rx) = mpsc::channel();
+  let mut output = APUOutput::new(rx, controls.clone());
+  let mut clock = FrameClock::new(controls.clone());
+  while clock.slice_due().is_some() {}
+
+  for _ in 0..2 * OUTPUT_SAMPLES_PER_FRAME * AUDIO_CHANNELS {
+    output.next();
+  }
+  let mut slices = vec![];
+  while let Some(slice) = clock.slice_due() {
+    slices.push(slice);
+  }
+  assert_eq!(slices.len(), 2 * SLICES_PER_FRAME as usize);
+  assert!(slices.windows(2).all(|pair| pair[1] == (pair[0] + 1) % SLICES_PER_FRAME));
+}
+
+#[test]
+fn frame_clock_falls_back_to_the_wall_clock_when_audio_stalls() {
+  let mut clock = FrameClock::new(AudioControls::default());
+  frames_due(&mut clock);
+  thread::sleep(Duration::from_millis(300));
+  // At most a frame to catch up on, rather than everything the stall missed
+  let caught_up = frames_due(&mut clock);
+  assert!((1..=2).contains(&caught_up), "{} frames came due", caught_up);
+  thread::sleep(Duration::from_millis(50));
+  let due = frames_due(&mut clock);
+  assert!((2..=5).contains(&due), "{} frames came due in 50ms", due);
+}
+
+#[test]
+fn frame_clock_can_go_by_the_wall_clock_while_audio_plays() {
+  let controls = AudioControls::default();
+  controls.set_sync_mode(SyncMode::WallClock);
+  let (_tx, rx) = mpsc::channel();
+  let mut output = APUOutput::new(rx, controls.clone());
+  let mut clock = FrameClock::new(controls.clone());
+
+  // Only the frame that's due now, however little audio's queued
+  assert_eq!(frames_due(&mut clock), 1);
+  for _ in 0..3 * OUTPUT_SAMPLES_PER_FRAME * AUDIO_CHANNELS {
+    output.next();
+  }
+  assert_eq!(frames_due(&mut clock), 0);
+}
+
+#[test]
+fn audio_clock_asks_for_what_the_output_played() {
+  let controls = AudioControls::default();
+  let (_tx, rx) = mpsc::channel();
+  let mut output = APUOutput::new(rx, controls.clone());
+  let mut clock = AudioClock::new(controls.clone());
+
+  let latency = controls.latency_samples() as u64;
+  assert_eq!(clock.samples_wanted(), Some(latency));
+  clock.queue(latency);
+  assert_eq!(clock.samples_wanted(), Some(0));
+  for _ in 0..100 * AUDIO_CHANNELS {
+    output.next();
+  }
+  assert_eq!(clock.samples_wanted(), Some(100));
+
+  controls.set_sync_mode(SyncMode::WallClock);
+  assert_eq!(clock.samples_wanted(), None);
+}
+
+#[test]
+fn core_runs_frames_at_the_speed_asked() {
+  let (mut core, _audio) = core();
+  let input = Input::default();
+  core.run_due_frame(&input);
+  assert_eq!(core.nes.frame_count(), 1);
+
+  core.speed = 0.5;
+  core.run_due_frame(&input);
+  core.run_due_frame(&input);
+  assert_eq!(core.nes.frame_count(), 2);
+
+  core.speed = 1.0;
+  core.run_due_frame(&Input { fast_forward: true, ..input });
+  assert_eq!(core.nes.frame_count(), 6);
+
+  core.frame_advance.toggle_pause();
+  core.run_due_frame(&input);
+  assert_eq!(core.nes.frame_count(), 6);
+
+  // Netplay runs its own frames
+  core.frame_advance.toggle_pause();
+  core.netplay = true;
+  core.run_due_frame(&input);
+  assert_eq!(core.nes.frame_count(), 6);
+}
+
+#[test]
+fn core_runs_a_frame_in_slices_with_the_input_held_for_each() {
+  let (mut core, _audio) = core();
+  let input = Input::default();
+  let held = Input { pad: PadState { held: 0x80, ..PadState::default() }, ..input };
+  for slice in 0..SLICES_PER_FRAME - 1 {
+    core.run_due_slice(if slice == 0 { &input } else { &held });
+  }
+  // Most of the way through the frame, with the latest input latched, but left for the frame to finish
+  assert_eq!(core.nes.frame_count(), 0);
+  let (scanline, _) = core.nes.bus.ppu.position();
+  assert!(scanline > 150, "only got to scanline {}", scanline);
+  core.nes.bus.cpu_write(0x4016, 1);
+  core.nes.bus.cpu_write(0x4016, 0);
+  assert_eq!(core.nes.bus.cpu_read(0x4016) & 1, 1, "A isn't held");
+  core.run_due_frame(&input);
+  assert_eq!(core.nes.frame_count(), 1);
+  assert!(core.nes.bus.ppu.position().0 < 10);
+
+  // Anything but plain play goes a whole frame at a time
+  core.speed = 0.5;
+  core.run_due_slice(&input);
+  assert!(core.nes.bus.ppu.position().0 < 10);
+  core.speed = 1.0;
+  core.run_due_slice(&Input { fast_forward: true, ..input });
+  assert!(core.nes.bus.ppu.position().0 < 10);
+}
+
+#[test]
+fn core_pauses_or_slows_down_in_the_background() {
+  let (mut core, audio) = core();
+  let input = Input { background: Some(BackgroundMode::Pause), ..Input::default() };
+  core.run_due_frame(&input);
+  core.run_due_frame(&input);
+  assert_eq!(core.nes.frame_count(), 0);
+  // Still fed at the usual rate, so nothing's left waiting once it's focused again
+  let received = audio.recv_timeout(Duration::from_secs(1)).unwrap();
+  assert!(!received.is_empty());
+
+  let input = Input { background: Some(BackgroundMode::Throttle), ..Input::default() };
+  for _ in 0..8 {
+    core.run_due_frame(&input);
+  }
+  assert_eq!(core.nes.frame_count(), 2);
+
+  let input = Input { background: Some(BackgroundMode::Run), ..Input::default() };
+  core.run_due_frame(&input);
+  assert_eq!(core.nes.frame_count(), 3);
+}
+
+#[test]
+fn emulation_thread_runs_frames_and_reports_stops() {
+  let (mut core, _audio) = core();
+  core.nes.add_breakpoint(0xC010);
+  let mut emulation = Emulation::start(core, AudioControls::default());
+  assert_eq!(emulation.screen().len(), SCREEN_WIDTH * SCREEN_HEIGHT * 3);
+
+  let started = Instant::now();
+  let mut events = Vec::new();
+  while events.is_empty() && started.elapsed() < Duration::from_secs(5) {
+    events = emulation.take_events();
+    thread::sleep(Duration::from_millis(10));
+  }
+  assert_eq!(events, vec![Event::Stopped("Hit breakpoint at $C010 (PRG bank 0 + $0010)".to_string())]);
+  assert_eq!(emulation.lock().nes.breakpoint_hit(), Some(0xC010));
+
+  // Carrying on from the UI's side runs frames again
+  let frames = emulation.lock().nes.frame_count();
+  emulation.lock().nes.clear_breakpoints();
+  emulation.lock().nes.resume();
+  let started = Instant::now();
+  while emulation.lock().nes.frame_count() < frames + 3 {
+    assert!(started.elapsed() < Duration::from_secs(5), "the emulation thread stopped running frames");
+    thread::sleep(Duration::from_millis(10));
+  }
+}
@@ -0,0 +1,95 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::{BusLike, Event, EventKind};
+use silknes_web::event_viewer::describe;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: write to a PPU register through a mirror, an APU register, PRG RAM and the mapper,
+/// then spin with the frame IRQ left to fire
+const PROGRAM: [u8; 20] = [
+  0x78,             // SEI
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x09, 0x20, // STA $2009
+  0xA9, 0x0F,       // LDA #$0F
+  0x8D, 0x15, 0x40, // STA $4015
+  0x8D, 0x00, 0x60, // STA $6000
+  0x8D, 0x00, 0x80, // STA $8000
+  // loop:
+  0x4C, 0x11, 0xC0, // JMP loop
+];
+
+/// NROM with battery backed PRG RAM, running the program above
+fn nes() -> Nes {
+  RomBuilder::new(0).battery().prg(&PROGRAM).nes()
+}
+
+fn kinds(events: &[Event]) -> Vec<(EventKind, u16, u8)> {
+  events.iter().map(|event| (event.kind, event.address, event.value)).collect()
+}
+
+#[test]
+fn nothing_is_logged_until_asked() {
+  let mut nes = nes();
+  nes.run_frame();
+  assert_eq!(nes.frame_events(), None);
+  assert!(nes.bus.take_events().is_empty());
+}
+
+#[test]
+fn register_writes_are_logged_with_where_the_ppu_was() {
+  let mut nes = nes();
+  nes.set_event_logging(true);
+  assert_eq!(nes.frame_events(), Some(&[][..]));
+  nes.run_frame();
+
+  let events = nes.frame_events().unwrap();
+  // The mirror's logged as the register it reaches, and PRG RAM isn't a register at all
+  assert_eq!(kinds(events), [
+    (EventKind::PpuWrite, 0x2001, 0x00),
+    (EventKind::ApuWrite, 0x4015, 0x0F),
+    (EventKind::MapperWrite, 0x8000, 0x0F),
+  ]);
+  // A few CPU cycles after power on, which starts on the pre-render line
+  assert_eq!(events[0].scanline, -1);
+  assert!(events.windows(2).all(|pair| pair[0].dot < pair[1].dot));
+  assert_eq!(describe(&events[0]), "$2001 = $00");
+
+  nes.run_frame();
+  assert!(nes.frame_events().unwrap().iter().all(|event| event.kind == EventKind::Irq));
+}
+
+#[test]
+fn irq_is_logged_when_raised_not_while_held() {
+  let mut nes = nes();
+  nes.set_event_logging(true);
+  let mut irqs = vec![];
+  for _ in 0..5 {
+    nes.run_frame();
+    irqs.extend(nes.frame_events().unwrap().iter().filter(|event| event.kind == EventKind::Irq).copied());
+  }
+  // The frame counter's IRQ is never acknowledged, so it only goes up once
+  assert_eq!(irqs.len(), 1);
+  assert_eq!(irqs[0].value, 0);
+}
+
+#[test]
+fn turning_logging_off_drops_the_log() {
+  let mut nes = nes();
+  nes.set_event_logging(true);
+  nes.run_frame();
+  nes.set_event_logging(false);
+  assert_eq!(nes.frame_events(), None);
+  assert!(!nes.bus.event_logging());
+
+  // Loading a state keeps logging as it was, without whatever the state was partway through
+  nes.set_event_logging(true);
+  let state = nes.save_state();
+  nes.run_frame();
+  nes.load_state(&state);
+  assert!(nes.bus.event_logging());
+  assert!(nes.bus.take_events().is_empty());
+}
@@ -0,0 +1,90 @@
+extern crate silknes_web;
+
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use silknes_web::cartridge::MirroringMode;
+use silknes_web::mapper::{Mapper, MapperState};
+use silknes_web::mappers::mapper0::Mapper0;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+/// NROM with a sound chip that holds a constant level, counting the cycles it's clocked for
+#[derive(Clone)]
+struct HummingMapper {
+  inner: Mapper0,
+  level: f32,
+  cycles: Arc<AtomicU32>,
+}
+
+impl Mapper for HummingMapper {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    self.inner.get_mapped_address_cpu(address)
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    self.inner.prg_bank_size()
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    self.inner.get_mapped_address_ppu(address)
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    self.inner.mapped_cpu_write(address, value)
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    self.inner.mirroring_mode()
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn state(&self) -> MapperState {
+    self.inner.state()
+  }
+
+  fn audio_clock(&mut self, cpu_cycles: u32) {
+    self.cycles.fetch_add(cpu_cycles, Ordering::Relaxed);
+  }
+
+  fn audio_output(&self) -> f32 {
+    self.level
+  }
+}
+
+fn run_with_level(level: f32) -> (Vec<f32>, u32) {
+  let cycles = Arc::new(AtomicU32::new(0));
+  let mut cartridge = RomBuilder::new(0).prg(&SPIN).cartridge();
+  cartridge.mapper = Box::new(HummingMapper { inner: Mapper0::new(2, 1), level, cycles: Arc::clone(&cycles) });
+  let mut nes = Nes::new();
+  nes.insert_cartridge(cartridge);
+  nes.run_frame();
+  nes.run_frame();
+  (nes.take_audio(), cycles.load(Ordering::Relaxed))
+}
+
+#[test]
+fn silent_mappers_leave_the_mix_alone() {
+  let (samples, _) = run_with_level(0.0);
+  assert!(!samples.is_empty());
+  assert!(samples.iter().all(|sample| *sample == -1.0));
+}
+
+#[test]
+fn mapper_audio_is_mixed_into_apu_output() {
+  let (samples, _) = run_with_level(0.25);
+  assert!(samples.iter().all(|sample| (*sample + 0.5).abs() < 1e-6));
+}
+
+#[test]
+fn mapper_audio_is_clocked_every_cpu_cycle() {
+  // Two frames is a little under 60,000 CPU cycles
+  let (_, cycles) = run_with_level(0.0);
+  assert!((59_000..60_500).contains(&cycles), "clocked for {} cycles", cycles);
+}
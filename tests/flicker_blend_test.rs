@@ -0,0 +1,86 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::emulation::frame_buffer;
+use silknes_web::nes::Nes;
+use silknes_web::video::blend_frames;
+
+use common::rom_builder::RomBuilder;
+
+/// NROM with rendering off that waits out the PPU's warm up and turns on NMIs, whose handler flips the
+/// backdrop between white and black every frame
+fn nes() -> Nes {
+  let rom = RomBuilder::new(0).prg_banks(1).prg(&[
+    0x78,             // SEI
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C001
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C006
+    0xA9, 0x30,       // LDA #$30
+    0x85, 0x10,       // STA $10
+    0xA9, 0x80,       // LDA #$80
+    0x8D, 0x00, 0x20, // STA $2000
+    0x4C, 0x14, 0xC0, // JMP $C014
+  ]).nmi(&[
+    0xA5, 0x10,       // LDA $10
+    0x49, 0x3F,       // EOR #$3F
+    0x85, 0x10,       // STA $10
+    0xA9, 0x3F,       // LDA #$3F
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x00,       // LDA #$00
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA5, 0x10,       // LDA $10
+    0x8D, 0x07, 0x20, // STA $2007
+    0xA9, 0x00,       // LDA #$00
+    0x8D, 0x06, 0x20, // STA $2006
+    0x8D, 0x06, 0x20, // STA $2006
+    0x40,             // RTI
+  ]);
+  let mut nes = Nes::new();
+  nes.insert_cartridge(rom.cartridge());
+  nes.run_frames(5);
+  nes
+}
+
+#[test]
+fn blending_averages_each_byte_rounding_up() {
+  let mut frame = vec![0, 255, 100, 7];
+  blend_frames(&mut frame, &[255, 255, 50, 8]);
+  assert_eq!(frame, [128, 255, 75, 8]);
+}
+
+#[test]
+fn the_console_keeps_the_frame_before_the_last() {
+  let mut nes = nes();
+  let before = nes.screen().to_vec();
+  nes.run_frame();
+  assert_eq!(nes.previous_screen(), before);
+  assert_ne!(nes.screen(), nes.previous_screen());
+
+  // Blending the flickering backdrop gives the same grey either way round
+  let mut blended = nes.screen().to_vec();
+  blend_frames(&mut blended, nes.previous_screen());
+  nes.run_frame();
+  let mut next = nes.screen().to_vec();
+  blend_frames(&mut next, nes.previous_screen());
+  assert_eq!(blended, next);
+}
+
+#[test]
+fn loading_a_state_has_nothing_to_blend_with() {
+  let mut nes = nes();
+  let state = nes.save_state();
+  nes.run_frame();
+  nes.load_state(&state);
+  assert_eq!(nes.screen(), nes.previous_screen());
+}
+
+#[test]
+fn frame_buffer_hands_over_the_previous_frame_too() {
+  let (mut writer, mut reader) = frame_buffer();
+  writer.publish_with_previous(&[2, 2], &[1, 1]);
+  assert_eq!(reader.latest_with_previous(), (&[2, 2][..], &[1, 1][..]));
+  writer.publish(&[3, 3]);
+  assert_eq!(reader.latest_with_previous(), (&[3, 3][..], &[][..]));
+}
@@ -0,0 +1,101 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::command::{self, Command};
+use silknes_web::frame_advance::{BackgroundMode, FrameAdvance};
+use silknes_web::nes::OUTPUT_SAMPLES_PER_FRAME;
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: turn on the pulse channel at full volume and spin
+const PROGRAM: [u8; 19] = [
+  0x78,             // SEI
+  0xA9, 0x01,       // LDA #$01
+  0x8D, 0x15, 0x40, // STA $4015
+  0xA9, 0xBF,       // LDA #$BF
+  0x8D, 0x00, 0x40, // STA $4000
+  0xA9, 0x40,       // LDA #$40
+  0x8D, 0x02, 0x40, // STA $4002
+  // loop:
+  0x4C, 0x10, 0xC0, // JMP loop
+];
+
+#[test]
+fn running_frames_always_run() {
+  let mut frame_advance = FrameAdvance::default();
+  assert!(frame_advance.take_frame());
+  assert!(frame_advance.take_frame());
+}
+
+#[test]
+fn paused_frames_only_run_when_advanced() {
+  let mut frame_advance = FrameAdvance::default();
+  frame_advance.toggle_pause();
+  assert!(!frame_advance.take_frame());
+
+  frame_advance.advance();
+  frame_advance.advance();
+  assert!(frame_advance.take_frame());
+  assert!(frame_advance.take_frame());
+  assert!(!frame_advance.take_frame());
+}
+
+#[test]
+fn advancing_while_running_pauses() {
+  let mut frame_advance = FrameAdvance::default();
+  frame_advance.advance();
+  assert!(frame_advance.paused);
+  assert!(!frame_advance.take_frame());
+}
+
+#[test]
+fn holding_advance_keeps_advancing_after_a_moment() {
+  let mut frame_advance = FrameAdvance::default();
+  frame_advance.toggle_pause();
+
+  frame_advance.update_hold(true, 10.0);
+  assert!(!frame_advance.take_frame());
+  frame_advance.update_hold(true, 10.1);
+  assert!(!frame_advance.take_frame());
+
+  frame_advance.update_hold(true, 10.5);
+  assert!(frame_advance.take_frame());
+  assert!(frame_advance.take_frame());
+
+  frame_advance.update_hold(false, 10.6);
+  assert!(!frame_advance.take_frame());
+}
+
+#[test]
+fn paused_audio_is_a_frame_long_and_fades_out() {
+  let mut nes = RomBuilder::new(0).prg(&PROGRAM).nes();
+  for _ in 0..3 {
+    nes.run_frame();
+  }
+  let played = nes.take_audio();
+  let last = *played.last().unwrap();
+  assert_ne!(last, 0.0);
+
+  let paused = nes.paused_audio();
+  assert_eq!(paused.len(), OUTPUT_SAMPLES_PER_FRAME);
+  assert_eq!(paused[0], last);
+  assert!(paused.last().unwrap().abs() < last.abs() * 0.01);
+
+  // Once it's faded out it stays silent
+  assert!(nes.paused_audio().iter().all(|sample| *sample == 0.0));
+
+  // A frame run normally makes about as much audio as a paused one stands in for
+  nes.run_frame();
+  let frame = nes.take_audio();
+  assert!(frame.len().abs_diff(OUTPUT_SAMPLES_PER_FRAME) <= 1);
+}
+
+#[test]
+fn background_modes_round_trip_through_their_keys() {
+  for mode in BackgroundMode::ALL {
+    assert_eq!(BackgroundMode::from_key(mode.key()), Some(mode));
+  }
+  assert_eq!(command::parse("background throttle"), Ok(Command::SetBackgroundMode(BackgroundMode::Throttle)));
+  assert!(command::parse("background sleep").is_err());
+}
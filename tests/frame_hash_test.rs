@@ -0,0 +1,116 @@
+//! Runs ROMs for a set number of frames and checks what they drew against the hashes in
+//! tests/frame_hashes.txt, to catch PPU changes that alter the picture. ROMs that aren't under
+//! roms/test are skipped. After a change that's meant to alter the picture, run with
+//! `BLESS_FRAME_HASHES=1` to record the new hashes, and check the frames by eye.
+
+extern crate silknes_web;
+
+mod common;
+
+use std::path::PathBuf;
+
+use silknes_web::cartridge::Cartridge;
+use silknes_web::nes::Nes;
+use silknes_web::palette::Palette;
+
+use common::rom_builder::{textured_chr, RomBuilder};
+
+/// One line of the goldens file: `<ROM under roms/test> <frames to run> <hash in hex>`
+struct Golden {
+  rom: String,
+  frames: u64,
+  hash: u64,
+}
+
+fn goldens_path() -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/frame_hashes.txt")
+}
+
+fn read_goldens() -> Vec<Golden> {
+  let text = std::fs::read_to_string(goldens_path()).unwrap();
+  text.lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    .map(|line| {
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      assert_eq!(fields.len(), 3, "malformed golden {:?}", line);
+      Golden {
+        rom: fields[0].to_string(),
+        frames: fields[1].parse().unwrap(),
+        hash: u64::from_str_radix(fields[2], 16).unwrap(),
+      }
+    })
+    .collect()
+}
+
+/// The hash of the frame `rom` has drawn after `frames` frames, or `None` if it isn't there
+fn hash_after(rom: &str, frames: u64) -> Option<u64> {
+  let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("roms/test").join(rom);
+  let Ok(bytes) = std::fs::read(&path) else {
+    eprintln!("skipping {}, {} isn't there", rom, path.display());
+    return None;
+  };
+  let mut nes = Nes::new();
+  nes.insert_cartridge(Cartridge::from_bytes(bytes).unwrap());
+  nes.run_frames(frames);
+  Some(nes.frame_hash())
+}
+
+#[test]
+fn frames_match_their_goldens() {
+  let mut goldens = read_goldens();
+  let bless = std::env::var_os("BLESS_FRAME_HASHES").is_some();
+  let mut mismatches = Vec::new();
+  for golden in &mut goldens {
+    let Some(hash) = hash_after(&golden.rom, golden.frames) else {
+      continue;
+    };
+    if hash != golden.hash {
+      mismatches.push(format!("{} after {} frames: expected {:016x}, got {:016x}", golden.rom, golden.frames, golden.hash, hash));
+      golden.hash = hash;
+    }
+  }
+
+  if bless {
+    let header: String = std::fs::read_to_string(goldens_path()).unwrap()
+      .lines()
+      .take_while(|line| line.starts_with('#'))
+      .map(|line| format!("{}\n", line))
+      .collect();
+    let lines: String = goldens.iter()
+      .map(|golden| format!("{} {} {:016x}\n", golden.rom, golden.frames, golden.hash))
+      .collect();
+    std::fs::write(goldens_path(), header + &lines).unwrap();
+  } else {
+    assert!(mismatches.is_empty(), "frames no longer match their goldens:\n{}", mismatches.join("\n"));
+  }
+}
+
+/// Waits out the PPU's warm up, then turns rendering on and spins
+const RENDERING: [u8; 19] = [
+  0x78,             // SEI
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0xA9, 0x1E,       // LDA #$1E
+  0x8D, 0x01, 0x20, // STA $2001
+  // loop:
+  0x4C, 0x10, 0xC0, // JMP loop
+];
+
+#[test]
+fn frame_hash_follows_what_was_drawn() {
+  let rom = RomBuilder::new(0).prg(&RENDERING).chr(&textured_chr());
+  let mut first = rom.nes();
+  let mut second = rom.nes();
+  first.run_frames(3);
+  second.run_frames(3);
+  assert_eq!(first.frame_hash(), second.frame_hash());
+
+  // The same picture in different colours hashes differently
+  second.set_palette(Palette::from_pal_bytes(&[0xFF; 192]).unwrap());
+  second.run_frames(1);
+  first.run_frames(1);
+  assert_ne!(first.frame_hash(), second.frame_hash());
+}
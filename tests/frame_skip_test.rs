@@ -0,0 +1,95 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::command::{self, Command};
+use silknes_web::frame_skip::{FrameSkip, FrameSkipper, MAX_FRAME_SKIP};
+use silknes_web::nes::Nes;
+
+use common::rom_builder::RomBuilder;
+
+/// NROM with CHR RAM whose reset handler counts up in $00 forever
+fn nes() -> Nes {
+  RomBuilder::new(0)
+    .chr_banks(0)
+    .prg(&[
+      0x78,             // SEI
+      // loop:
+      0xE6, 0x00,       // INC $00
+      0x4C, 0x01, 0xC0, // JMP loop
+    ])
+    .nes()
+}
+
+/// Which of the next `frames` frames `skipper` draws
+fn drawn(skipper: &mut FrameSkipper, frames: usize) -> Vec<bool> {
+  (0..frames).map(|_| skipper.draw_next()).collect()
+}
+
+#[test]
+fn fixed_skips_draw_one_frame_in_every_few() {
+  let mut skipper = FrameSkipper::default();
+  skipper.mode = FrameSkip::Fixed(2);
+  assert_eq!(drawn(&mut skipper, 6), [false, false, true, false, false, true]);
+  skipper.mode = FrameSkip::Off;
+  assert_eq!(drawn(&mut skipper, 3), [true, true, true]);
+}
+
+#[test]
+fn auto_only_skips_while_behind_and_never_too_many_in_a_row() {
+  let mut skipper = FrameSkipper::default();
+  skipper.mode = FrameSkip::Auto;
+  assert_eq!(drawn(&mut skipper, 2), [true, true]);
+  skipper.behind = true;
+  let frames = drawn(&mut skipper, MAX_FRAME_SKIP as usize + 1);
+  assert_eq!(frames.iter().filter(|drawn| !**drawn).count(), MAX_FRAME_SKIP as usize);
+  assert_eq!(frames.last(), Some(&true));
+}
+
+#[test]
+fn skipped_frames_leave_the_last_one_drawn_on_screen() {
+  let mut skipping = nes();
+  let mut drawing = nes();
+  skipping.set_frame_skip(FrameSkip::Fixed(1));
+  // The first frame's drawn, and the second skipped
+  for nes in [&mut skipping, &mut drawing] {
+    nes.run_frame();
+  }
+  // The backdrop changes, which the frame after the skipped one shows but the skipped one doesn't
+  let before = skipping.screen().to_vec();
+  for nes in [&mut skipping, &mut drawing] {
+    let cartridge = nes.bus.cartridge.as_ref().unwrap();
+    nes.bus.ppu.ppu_write(cartridge, 0x3F00, 0x16);
+    nes.run_frame();
+  }
+  assert_eq!(skipping.screen(), before.as_slice());
+  assert_ne!(drawing.screen(), before.as_slice());
+
+  for nes in [&mut skipping, &mut drawing] {
+    nes.run_frame();
+  }
+  assert_eq!(skipping.screen(), drawing.screen());
+}
+
+#[test]
+fn skipped_frames_still_run_the_game() {
+  let mut skipping = nes();
+  let mut drawing = nes();
+  skipping.set_frame_skip(FrameSkip::Auto);
+  skipping.set_falling_behind(true);
+  for nes in [&mut skipping, &mut drawing] {
+    nes.run_frames(10);
+  }
+  assert_eq!(skipping.frame_count(), drawing.frame_count());
+  assert_eq!(skipping.cpu.total_cycles, drawing.cpu.total_cycles);
+  assert_eq!(skipping.bus.ram_mut(), drawing.bus.ram_mut());
+  assert_eq!(skipping.take_audio(), drawing.take_audio());
+}
+
+#[test]
+fn console_sets_the_frame_skip() {
+  assert_eq!(command::parse("frameskip auto"), Ok(Command::SetFrameSkip(FrameSkip::Auto)));
+  assert_eq!(command::parse("frameskip 2"), Ok(Command::SetFrameSkip(FrameSkip::Fixed(2))));
+  assert_eq!(command::parse("frameskip off"), Ok(Command::SetFrameSkip(FrameSkip::Off)));
+  assert!(command::parse("frameskip 4").is_err());
+}
@@ -0,0 +1,66 @@
+extern crate silknes_web;
+
+mod common;
+
+use eframe::egui::Key;
+use silknes_web::config::Config;
+use silknes_web::game_profile::{GameProfile, GameProfiles};
+use silknes_web::input::{Button, InputBindings};
+use silknes_web::palette::BuiltinPalette;
+use silknes_web::ppu::Region;
+
+use common::MemoryStorage;
+use common::rom_builder::{RomBuilder, SPIN};
+
+fn other_palette() -> BuiltinPalette {
+  *BuiltinPalette::ALL.iter().find(|palette| **palette != BuiltinPalette::default()).unwrap()
+}
+
+#[test]
+fn profiles_only_override_what_they_set() {
+  let config = Config { hide_overscan: true, ..Config::default() };
+  assert_eq!(GameProfile::default().apply(&config), config);
+
+  let mut input = InputBindings::default();
+  input.set(Button::A, Some(Key::Space));
+  let profile = GameProfile { input: Some(input.clone()), palette: Some(other_palette()), hide_overscan: Some(false), ..GameProfile::default() };
+  let applied = profile.apply(&config);
+  assert_eq!(applied.input, input);
+  assert_eq!(applied.palette, other_palette());
+  assert!(!applied.hide_overscan);
+  assert_eq!(applied.volume, config.volume);
+}
+
+#[test]
+fn profiles_come_back_from_storage() {
+  let mut input = InputBindings::default();
+  input.set(Button::Start, None);
+  input.turbo_rate = 5;
+  let mut profiles = GameProfiles::default();
+  *profiles.get_mut("game") = GameProfile {
+    input: Some(input),
+    palette: Some(other_palette()),
+    hide_overscan: Some(true),
+    region: Some(Region::Pal),
+    cheats_enabled: false,
+  };
+  profiles.get_mut("untouched");
+
+  let mut storage = MemoryStorage::default();
+  profiles.save(&mut storage);
+  let loaded = GameProfiles::load(Some(&storage));
+  assert_eq!(loaded.get("game"), profiles.get("game"));
+  assert_eq!(loaded.get("untouched"), GameProfile::default());
+  assert_eq!(GameProfiles::load(None).get("game"), GameProfile::default());
+}
+
+#[test]
+fn region_falls_back_to_the_header() {
+  let mut nes = RomBuilder::new(0).pal().prg(&SPIN).nes();
+  assert_eq!(nes.bus.ppu.region(), Region::Pal);
+
+  nes.set_region(Some(Region::Ntsc));
+  assert_eq!(nes.bus.ppu.region(), Region::Ntsc);
+  nes.set_region(None);
+  assert_eq!(nes.bus.ppu.region(), Region::Pal);
+}
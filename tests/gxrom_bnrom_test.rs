@@ -0,0 +1,60 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::mappers;
+
+use common::rom_builder::RomBuilder;
+
+#[test]
+fn every_registered_mapper_can_be_created() {
+  for info in mappers::MAPPERS {
+    assert!(mappers::create(info.id, 2, 1).is_some(), "mapper {}", info.id);
+  }
+  assert!(mappers::create(5, 2, 1).is_none());
+}
+
+#[test]
+fn gxrom_switches_prg_and_chr_with_one_register() {
+  let mut cartridge = RomBuilder::new(66).prg_banks(8).chr_banks(4).cartridge();
+  assert_eq!(cartridge.cpu_read(0x8000), Some(0));
+  assert_eq!(*cartridge.ppu_read(0x0000), 0);
+
+  // 32 KB PRG bank 2 is 8 KB pages 8-11, 8 KB CHR bank 1 is 1 KB pages 8-15
+  cartridge.cpu_write(0x8000, 0x21);
+  assert_eq!(cartridge.cpu_read(0x8000), Some(8));
+  assert_eq!(cartridge.cpu_read(0xFFFF), Some(11));
+  assert_eq!(*cartridge.ppu_read(0x0000), 8);
+  assert_eq!(*cartridge.ppu_read(0x1FFF), 15);
+}
+
+#[test]
+fn bnrom_switches_32kb_prg_banks() {
+  let mut nes = RomBuilder::new(34).prg_banks(8).chr_banks(0).nes();
+  let cartridge = nes.bus.cartridge.as_mut().unwrap();
+  cartridge.cpu_write(0x8000, 3);
+  assert_eq!(cartridge.cpu_read(0x8000), Some(12));
+  assert_eq!(cartridge.cpu_read(0xFFFF), Some(15));
+  // CHR RAM stays put, in the PPU's pattern tables
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  nes.bus.ppu.ppu_write(cartridge, 0x1234, 0x56);
+  assert_eq!(*nes.bus.ppu.ppu_read(cartridge, 0x1234), 0x56);
+}
+
+#[test]
+fn nina_001_registers_sit_under_prg_ram() {
+  let mut cartridge = RomBuilder::new(34).prg_banks(4).chr_banks(4).battery().cartridge();
+  cartridge.cpu_write(0x7FFD, 1);
+  cartridge.cpu_write(0x7FFE, 5);
+  cartridge.cpu_write(0x7FFF, 2);
+  // 32 KB PRG bank 1 starts at 8 KB page 4, and 4 KB CHR banks 5 and 2 at 1 KB pages 20 and 8
+  assert_eq!(cartridge.cpu_read(0x8000), Some(4));
+  assert_eq!(*cartridge.ppu_read(0x0000), 20);
+  assert_eq!(*cartridge.ppu_read(0x1000), 8);
+  // The writes still land in RAM too
+  assert_eq!(cartridge.cpu_read(0x7FFE), Some(5));
+
+  // Writes to ROM don't touch the registers
+  cartridge.cpu_write(0x8000, 0);
+  assert_eq!(cartridge.cpu_read(0x8000), Some(4));
+}
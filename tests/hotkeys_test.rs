@@ -0,0 +1,91 @@
+extern crate silknes_web;
+
+mod common;
+
+use eframe::egui::{Key, KeyboardShortcut, Modifiers};
+use silknes_web::command::Command;
+use silknes_web::hotkeys::{decode_shortcut, encode_shortcut, HotkeyAction, Hotkeys};
+use silknes_web::rewind::Rewind;
+
+use common::rom_builder::{RomBuilder, SPIN_WITH_NMI};
+
+#[test]
+fn every_action_starts_bound_without_conflicts() {
+  let hotkeys = Hotkeys::default();
+  for action in HotkeyAction::ALL {
+    assert!(hotkeys.active(action).is_some(), "{:?}", action);
+    assert!(hotkeys.conflicts(action).is_empty(), "{:?}", action);
+  }
+  assert_eq!(HotkeyAction::LoadRom.command(), Some(Command::LoadRom));
+  assert_eq!(HotkeyAction::Rewind.command(), None);
+}
+
+#[test]
+fn shortcuts_survive_being_stored() {
+  for shortcut in [
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::O),
+    KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::S),
+    KeyboardShortcut::new(Modifiers::CTRL | Modifiers::ALT, Key::F5),
+    KeyboardShortcut::new(Modifiers::NONE, Key::Backslash),
+    KeyboardShortcut::new(Modifiers::SHIFT, Key::Plus),
+  ] {
+    assert_eq!(decode_shortcut(&encode_shortcut(&shortcut)), Some(shortcut));
+  }
+  assert_eq!(encode_shortcut(&KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::S)), "Cmd+Shift+S");
+  assert_eq!(decode_shortcut("Hyper+S"), None);
+  assert_eq!(decode_shortcut("Cmd+NotAKey"), None);
+}
+
+#[test]
+fn conflicting_bindings_are_reported_and_disabled() {
+  let mut hotkeys = Hotkeys::default();
+  hotkeys.set_shortcut(HotkeyAction::Mute, Some(KeyboardShortcut::new(Modifiers::NONE, Key::P)));
+  assert_eq!(hotkeys.conflicts(HotkeyAction::Mute), vec![HotkeyAction::Pause]);
+  assert_eq!(hotkeys.conflicts(HotkeyAction::Pause), vec![HotkeyAction::Mute]);
+  assert_eq!(hotkeys.active(HotkeyAction::Mute), None);
+  assert_eq!(hotkeys.active(HotkeyAction::Pause), None);
+
+  // Turning one of them off settles it
+  hotkeys.set_enabled(HotkeyAction::Mute, false);
+  assert!(hotkeys.conflicts(HotkeyAction::Pause).is_empty());
+  assert!(hotkeys.active(HotkeyAction::Pause).is_some());
+  assert_eq!(hotkeys.active(HotkeyAction::Mute), None);
+
+  // As does unbinding it
+  hotkeys.set_enabled(HotkeyAction::Mute, true);
+  hotkeys.set_shortcut(HotkeyAction::Mute, None);
+  assert!(hotkeys.active(HotkeyAction::Pause).is_some());
+}
+
+#[test]
+fn rewind_steps_back_through_recorded_frames() {
+  // The NMI handler counts frames in $00
+  let mut nes = RomBuilder::new(0).prg(&SPIN_WITH_NMI).nmi(&[0xE6, 0x00, 0x40]).nes(); // INC $00, RTI
+  let mut rewind = Rewind::default();
+  for _ in 0..20 {
+    nes.run_frame();
+    rewind.record(&nes);
+  }
+  // A snapshot every other frame
+  assert_eq!(rewind.len(), 10);
+
+  let frame = nes.frame_count();
+  let counter = nes.peek(0x0000);
+  assert!(rewind.step_back(&mut nes));
+  assert!(nes.frame_count() < frame);
+  assert!(rewind.step_back(&mut nes));
+  assert!(nes.peek(0x0000) < counter);
+
+  // Stepping back stops at the oldest snapshot
+  while rewind.len() > 1 {
+    rewind.step_back(&mut nes);
+  }
+  assert!(rewind.step_back(&mut nes));
+  assert_eq!(nes.frame_count(), 1);
+
+  // Running on from there carries on recording
+  nes.run_frame();
+  nes.run_frame();
+  rewind.record(&nes);
+  assert_eq!(rewind.len(), 2);
+}
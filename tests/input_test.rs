@@ -0,0 +1,50 @@
+extern crate silknes_web;
+
+use eframe::egui::Key;
+use silknes_web::input::{Button, InputBindings, PadState};
+
+#[test]
+fn default_bindings_press_the_usual_buttons() {
+  let bindings = InputBindings::default();
+  let state = bindings.pad_state(|key| matches!(key, Key::ArrowRight | Key::Enter | Key::X));
+  assert_eq!(state.held, 0x01 | 0x10 | 0x80);
+  assert_eq!(state.turbo, 0);
+  assert_eq!(state.turbo_rate, InputBindings::DEFAULT_TURBO_RATE);
+}
+
+#[test]
+fn turbo_buttons_are_kept_apart_from_held_ones() {
+  let bindings = InputBindings::default();
+  let state = bindings.pad_state(|key| matches!(key, Key::Z | Key::S));
+  assert_eq!(state.held, Button::B.mask());
+  assert_eq!(state.turbo, Button::TurboA.mask());
+  assert_eq!(Button::TurboA.mask(), Button::A.mask());
+}
+
+#[test]
+fn turbo_starts_pressed_and_toggles_every_rate_frames() {
+  let state = PadState { held: 0x01, turbo: 0x80, turbo_rate: 3 };
+  let frames: Vec<u8> = (0..12).map(|frame| state.buttons(frame)).collect();
+  assert_eq!(frames, [0x81, 0x81, 0x81, 0x01, 0x01, 0x01, 0x81, 0x81, 0x81, 0x01, 0x01, 0x01]);
+}
+
+#[test]
+fn zero_turbo_rate_toggles_every_frame() {
+  let state = PadState { held: 0, turbo: 0x40, turbo_rate: 0 };
+  assert_eq!(state.buttons(0), 0x40);
+  assert_eq!(state.buttons(1), 0);
+  assert_eq!(state.buttons(2), 0x40);
+}
+
+#[test]
+fn rebound_and_unbound_buttons() {
+  let mut bindings = InputBindings::default();
+  bindings.set(Button::A, Some(Key::K));
+  bindings.set(Button::TurboB, None);
+  assert_eq!(bindings.get(Button::A), Some(Key::K));
+
+  // The old key does nothing, and nothing presses an unbound button
+  let state = bindings.pad_state(|key| matches!(key, Key::X | Key::A));
+  assert_eq!(state, PadState { turbo_rate: bindings.turbo_rate, ..PadState::default() });
+  assert_eq!(bindings.pad_state(|key| key == Key::K).held, 0x80);
+}
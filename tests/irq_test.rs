@@ -0,0 +1,178 @@
+extern crate silknes_web;
+
+use silknes_web::bus::{BusLike, IrqSource, MockBus};
+use silknes_web::cpu::NES6502;
+
+const IRQ_HANDLER: u16 = 0x9000;
+const NMI_HANDLER: u16 = 0xA000;
+
+/// A CPU about to run `program` at $8000, with handlers full of NOPs
+fn cpu(program: &[u8], interrupt_disable: bool) -> (NES6502, MockBus) {
+  let mut mock = MockBus::new();
+  mock.cpu_ram[0x8000..0x8000 + program.len()].copy_from_slice(program);
+  mock.cpu_ram[IRQ_HANDLER as usize..IRQ_HANDLER as usize + 0x100].fill(0xEA);
+  mock.cpu_ram[NMI_HANDLER as usize..NMI_HANDLER as usize + 0x100].fill(0xEA);
+  mock.cpu_ram[0xFFFA..0xFFFC].copy_from_slice(&NMI_HANDLER.to_le_bytes());
+  mock.cpu_ram[0xFFFE..].copy_from_slice(&IRQ_HANDLER.to_le_bytes());
+
+  let mut cpu = NES6502::new();
+  cpu.pc = 0x8000;
+  cpu.flags.interrupt_disable = interrupt_disable;
+  (cpu, mock)
+}
+
+/// Run until the CPU's between instructions again
+fn run_instruction(cpu: &mut NES6502, bus: &mut MockBus) {
+  cpu.step(bus);
+  while cpu.cycles > 0 {
+    cpu.step(bus);
+  }
+}
+
+/// The return address and flags the last interrupt pushed
+fn pushed(cpu: &NES6502, bus: &MockBus) -> (u16, u8) {
+  let stack = 0x0100 + cpu.sp as u16;
+  let flags = bus.peek(stack + 1);
+  let address = bus.peek(stack + 2) as u16 | (bus.peek(stack + 3) as u16) << 8;
+  (address, flags)
+}
+
+#[test]
+fn held_irq_is_taken_once() {
+  let (mut cpu, mut bus) = cpu(&[0xEA; 4], false);
+  bus.set_irq(IrqSource::Mapper, true);
+  run_instruction(&mut cpu, &mut bus);
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, IRQ_HANDLER);
+  assert!(cpu.flags.interrupt_disable);
+  assert_eq!(pushed(&cpu, &bus).0, 0x8001);
+
+  // The line's still held, but the handler runs with interrupts disabled
+  let sp = cpu.sp;
+  for _ in 0..10 {
+    run_instruction(&mut cpu, &mut bus);
+  }
+  assert_eq!(cpu.sp, sp);
+  assert_eq!(cpu.pc, IRQ_HANDLER + 10);
+}
+
+#[test]
+fn irq_line_stays_low_while_any_source_holds_it() {
+  let (_, mut bus) = cpu(&[], false);
+  bus.set_irq(IrqSource::FrameCounter, true);
+  bus.set_irq(IrqSource::Dmc, true);
+  bus.set_irq(IrqSource::FrameCounter, false);
+  assert!(bus.irq_line());
+  bus.set_irq(IrqSource::Dmc, false);
+  assert!(!bus.irq_line());
+}
+
+#[test]
+fn irq_released_before_the_poll_is_missed() {
+  // LDA $00, NOP
+  let (mut cpu, mut bus) = cpu(&[0xA5, 0x00, 0xEA], false);
+  bus.set_irq(IrqSource::Dmc, true);
+  cpu.step(&mut bus);
+  // Partway through the load, before its poll
+  bus.set_irq(IrqSource::Dmc, false);
+  while cpu.cycles > 0 {
+    cpu.step(&mut bus);
+  }
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, 0x8003);
+}
+
+#[test]
+fn cli_lets_one_more_instruction_run() {
+  // CLI, NOP, NOP
+  let (mut cpu, mut bus) = cpu(&[0x58, 0xEA, 0xEA], true);
+  bus.set_irq(IrqSource::FrameCounter, true);
+  run_instruction(&mut cpu, &mut bus);
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, 0x8002);
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, IRQ_HANDLER);
+  assert_eq!(pushed(&cpu, &bus).0, 0x8002);
+}
+
+#[test]
+fn irq_right_after_sei_is_still_taken() {
+  // SEI, NOP
+  let (mut cpu, mut bus) = cpu(&[0x78, 0xEA], false);
+  bus.set_irq(IrqSource::FrameCounter, true);
+  run_instruction(&mut cpu, &mut bus);
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, IRQ_HANDLER);
+  let (address, flags) = pushed(&cpu, &bus);
+  assert_eq!(address, 0x8001);
+  // SEI had already set the flag by the time it was pushed
+  assert_ne!(flags & 0x04, 0);
+}
+
+#[test]
+fn nmi_is_taken_at_the_next_instruction_boundary() {
+  // LDA $00, NOP
+  let (mut cpu, mut bus) = cpu(&[0xA5, 0x00, 0xEA], true);
+  cpu.step(&mut bus);
+  cpu.nmi();
+  // Still finishes the load it was in the middle of
+  assert_eq!(cpu.pc, 0x8002);
+  while cpu.cycles > 0 {
+    cpu.step(&mut bus);
+  }
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, NMI_HANDLER);
+  assert_eq!(pushed(&cpu, &bus).0, 0x8002);
+}
+
+#[test]
+fn nmi_in_an_instructions_last_cycle_waits_for_the_next_one() {
+  // NOP, NOP
+  let (mut cpu, mut bus) = cpu(&[0xEA, 0xEA], true);
+  // A NOP's second cycle is its last, after the poll
+  cpu.step(&mut bus);
+  cpu.nmi();
+  cpu.step(&mut bus);
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, 0x8002);
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, NMI_HANDLER);
+  assert_eq!(pushed(&cpu, &bus).0, 0x8002);
+}
+
+#[test]
+fn nmi_hijacks_brk() {
+  let (mut cpu, mut bus) = cpu(&[0x00, 0x00], false);
+  cpu.step(&mut bus);
+  cpu.step(&mut bus);
+  cpu.nmi();
+  while cpu.cycles > 0 {
+    cpu.step(&mut bus);
+  }
+  assert_eq!(cpu.pc, NMI_HANDLER);
+  let (address, flags) = pushed(&cpu, &bus);
+  assert_eq!(address, 0x8002);
+  // The pushes were BRK's, so the break flag's still set
+  assert_ne!(flags & 0x10, 0);
+
+  // And the NMI isn't taken a second time
+  run_instruction(&mut cpu, &mut bus);
+  assert_eq!(cpu.pc, NMI_HANDLER + 1);
+}
+
+#[test]
+fn nmi_hijacks_irq() {
+  let (mut cpu, mut bus) = cpu(&[0xEA; 4], false);
+  bus.set_irq(IrqSource::Mapper, true);
+  run_instruction(&mut cpu, &mut bus);
+  // Two cycles into the IRQ's pushes
+  cpu.step(&mut bus);
+  cpu.step(&mut bus);
+  cpu.nmi();
+  while cpu.cycles > 0 {
+    cpu.step(&mut bus);
+  }
+  assert_eq!(cpu.pc, NMI_HANDLER);
+  let (_, flags) = pushed(&cpu, &bus);
+  assert_eq!(flags & 0x10, 0);
+}
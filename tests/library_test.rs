@@ -0,0 +1,78 @@
+extern crate silknes_web;
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use silknes_web::library::{format_date, format_play_time, Library, PlayHistory};
+use silknes_web::rom_database::RomDatabase;
+
+use common::MemoryStorage;
+use common::rom_builder::RomBuilder;
+
+/// A fresh folder holding an MMC1 game, an NROM game, one with a mapper we don't have and a text file
+fn folder(name: &str) -> PathBuf {
+  let folder = std::env::temp_dir().join(format!("silknes-library-{}-{}", name, std::process::id()));
+  let _ = std::fs::remove_dir_all(&folder);
+  std::fs::create_dir_all(&folder).unwrap();
+  std::fs::write(folder.join("Zelda Hack.nes"), RomBuilder::new(1).build()).unwrap();
+  std::fs::write(folder.join("alpha.NES"), RomBuilder::new(0).prg_banks(1).build()).unwrap();
+  std::fs::write(folder.join("Mystery.nes"), RomBuilder::new(254).build()).unwrap();
+  std::fs::write(folder.join("readme.txt"), "not a ROM").unwrap();
+  folder
+}
+
+fn library(folder: &Path) -> Library {
+  let mut library = Library::default();
+  library.scan(folder, &RomDatabase::default()).unwrap();
+  library
+}
+
+#[test]
+fn scanning_lists_roms_by_title_with_their_mappers() {
+  let folder = folder("scan");
+  let library = library(&folder);
+  let listed: Vec<_> = library.entries.iter().map(|entry| (entry.title.as_str(), entry.mapper.as_str())).collect();
+  assert_eq!(listed[0], ("alpha", "mapper 0, NROM"));
+  assert_eq!(listed[1].0, "Mystery");
+  assert!(listed[1].1.contains("254"));
+  assert_eq!(listed[2].0, "Zelda Hack");
+  assert!(listed[2].1.starts_with("mapper 1"));
+  assert_eq!(library.entries.len(), 3);
+  assert_eq!(library.folder.as_deref(), Some(folder.as_path()));
+}
+
+#[test]
+fn history_survives_saving_and_rescanning() {
+  let folder = folder("history");
+  let mut library = library(&folder);
+  let alpha = folder.join("alpha.NES");
+  assert_eq!(library.history(&alpha), None);
+  library.played(&alpha, 1_700_000_000);
+  library.stop();
+
+  let mut storage = MemoryStorage::default();
+  library.save(&mut storage);
+  let mut loaded = Library::load(Some(&storage));
+  assert_eq!(loaded.folder, library.folder);
+  assert_eq!(loaded.entries, library.entries);
+  assert_eq!(loaded.history(&alpha), Some(PlayHistory { last_played: 1_700_000_000, play_time: 0 }));
+
+  // Over to another folder and back
+  let empty = folder.join("empty");
+  std::fs::create_dir(&empty).unwrap();
+  loaded.scan(&empty, &RomDatabase::default()).unwrap();
+  assert!(loaded.entries.is_empty());
+  loaded.scan(&folder, &RomDatabase::default()).unwrap();
+  assert_eq!(loaded.history(&alpha).map(|history| history.last_played), Some(1_700_000_000));
+}
+
+#[test]
+fn dates_and_play_times_read_naturally() {
+  assert_eq!(format_date(0), "1970-01-01");
+  assert_eq!(format_date(951_782_400), "2000-02-29");
+  assert_eq!(format_date(1_717_632_000), "2024-06-06");
+  assert_eq!(format_play_time(59), "0m");
+  assert_eq!(format_play_time(12 * 60 + 30), "12m");
+  assert_eq!(format_play_time(65 * 60), "1h 05m");
+}
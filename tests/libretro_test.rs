@@ -0,0 +1,101 @@
+#![cfg(feature = "libretro")]
+extern crate silknes_web;
+
+mod common;
+
+use std::ffi::{c_uint, c_void};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use silknes_web::libretro::*;
+use silknes_web::nes::NTSC_FRAME_RATE;
+
+use common::rom_builder::RomBuilder;
+
+static FRAMES: AtomicUsize = AtomicUsize::new(0);
+static AUDIO_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn environment(_cmd: c_uint, _data: *mut c_void) -> bool {
+  true
+}
+
+extern "C" fn video_refresh(_data: *const c_void, width: c_uint, height: c_uint, pitch: usize) {
+  assert_eq!((width, height, pitch), (256, 240, 256 * 4));
+  FRAMES.fetch_add(1, Ordering::SeqCst);
+}
+
+extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+  AUDIO_FRAMES.fetch_add(frames, Ordering::SeqCst);
+  frames
+}
+
+extern "C" fn input_poll() {}
+
+/// Start held on the first controller
+extern "C" fn input_state(port: c_uint, _device: c_uint, _index: c_uint, id: c_uint) -> i16 {
+  (port == 0 && id == RETRO_DEVICE_ID_JOYPAD_START) as i16
+}
+
+/// Reset handler: count up in $00 and $6000 forever
+const PROGRAM: [u8; 9] = [
+  0x78,             // SEI
+  // loop:
+  0xE6, 0x00,       // INC $00
+  0xEE, 0x00, 0x60, // INC $6000
+  0x4C, 0x01, 0xC0, // JMP loop
+];
+
+/// The core is one global, so everything's checked in the one test
+#[test]
+fn frontends_can_run_save_and_restore_a_game() {
+  retro_set_environment(environment);
+  retro_set_video_refresh(video_refresh);
+  retro_set_audio_sample_batch(audio_sample_batch);
+  retro_set_input_poll(input_poll);
+  retro_set_input_state(input_state);
+  retro_init();
+  assert_eq!(retro_api_version(), 1);
+
+  // A PAL game, which still runs with NTSC timing
+  let rom = RomBuilder::new(0).battery().pal().prg(&PROGRAM).build();
+  let game = RetroGameInfo {
+    path: std::ptr::null(),
+    data: rom.as_ptr() as *const c_void,
+    size: rom.len(),
+    meta: std::ptr::null(),
+  };
+  assert!(unsafe { retro_load_game(&game) });
+  assert_eq!(retro_get_region(), RETRO_REGION_NTSC);
+  let mut av_info = std::mem::MaybeUninit::<RetroSystemAvInfo>::uninit();
+  let av_info = unsafe {
+    retro_get_system_av_info(av_info.as_mut_ptr());
+    av_info.assume_init()
+  };
+  assert_eq!(av_info.timing.fps, NTSC_FRAME_RATE);
+  assert_eq!(retro_get_memory_size(RETRO_MEMORY_SYSTEM_RAM), 2048);
+  assert_eq!(retro_get_memory_size(RETRO_MEMORY_SAVE_RAM), 0x8000);
+
+  for _ in 0..3 {
+    retro_run();
+  }
+  assert_eq!(FRAMES.load(Ordering::SeqCst), 3);
+  assert!(AUDIO_FRAMES.load(Ordering::SeqCst) > 0);
+
+  // Save, run on, and come back to the saved counters, through the pointers the frontend already has
+  let ram = retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM) as *const u8;
+  let save_ram = retro_get_memory_data(RETRO_MEMORY_SAVE_RAM) as *const u8;
+  let saved_counters = unsafe { (*ram, *save_ram) };
+  let mut state = vec![0; retro_serialize_size()];
+  assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()) });
+  assert_eq!(retro_serialize_size(), state.len());
+  retro_run();
+  assert_ne!(unsafe { (*ram, *save_ram) }, saved_counters);
+  assert!(unsafe { retro_unserialize(state.as_ptr() as *const c_void, state.len()) });
+  assert_eq!(retro_get_memory_data(RETRO_MEMORY_SYSTEM_RAM) as *const u8, ram);
+  assert_eq!(retro_get_memory_data(RETRO_MEMORY_SAVE_RAM) as *const u8, save_ram);
+  assert_eq!(unsafe { (*ram, *save_ram) }, saved_counters);
+
+  retro_unload_game();
+  assert_eq!(retro_get_memory_size(RETRO_MEMORY_SYSTEM_RAM), 0);
+  assert!(!unsafe { retro_load_game(std::ptr::null()) });
+  retro_deinit();
+}
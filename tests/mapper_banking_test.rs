@@ -0,0 +1,317 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::cartridge::{Cartridge, MirroringMode};
+
+use common::rom_builder::{RomBuilder, CHR_PAGE_SIZE, PRG_PAGE_SIZE};
+
+/// Load a register through MMC1's serial port, one bit per write, lowest first
+fn mmc1_write(cartridge: &mut Cartridge, address: u16, value: u8) {
+  for bit in 0..5 {
+    cartridge.cpu_write(address, (value >> bit) & 1);
+  }
+}
+
+/// Set one of MMC3's bank registers through $8000/$8001, keeping the mode bits in `bank_select`
+fn mmc3_bank(cartridge: &mut Cartridge, bank_select: u8, register: u8, value: u8) {
+  cartridge.cpu_write(0x8000, bank_select | register);
+  cartridge.cpu_write(0x8001, value);
+}
+
+/// The 8 KB PRG page at each of $8000, $A000, $C000 and $E000
+fn prg_pages(cartridge: &Cartridge) -> [u8; 4] {
+  [0x8000, 0xA000, 0xC000, 0xE000].map(|address| cartridge.cpu_read(address).unwrap())
+}
+
+/// The 1 KB CHR page at each of the eight 1 KB windows of the pattern tables
+fn chr_pages(cartridge: &Cartridge) -> [u8; 8] {
+  std::array::from_fn(|window| *cartridge.ppu_read(window as u16 * 0x400))
+}
+
+#[test]
+fn synthetic_roms_number_every_page() {
+  let rom = RomBuilder::new(0).prg_banks(2).chr_banks(1).build();
+  assert_eq!(rom.len(), 16 + 0x8000 + 0x2000);
+  assert_eq!(rom[16 + PRG_PAGE_SIZE * 3], 3);
+  assert_eq!(rom[16 + 0x8000 + CHR_PAGE_SIZE * 7 + 0x3FF], 7);
+
+  let cartridge = RomBuilder::new(4).submapper(4).vertical_mirroring().cartridge();
+  assert_eq!(cartridge.header_info.submapper(), 4);
+  assert_eq!(cartridge.mapper_id, 4);
+}
+
+#[test]
+fn nrom_mirrors_16kb_of_prg() {
+  let cartridge = RomBuilder::new(0).prg_banks(1).vertical_mirroring().cartridge();
+  assert_eq!(prg_pages(&cartridge), [0, 1, 0, 1]);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Vertical);
+
+  let cartridge = RomBuilder::new(0).prg_banks(2).cartridge();
+  assert_eq!(prg_pages(&cartridge), [0, 1, 2, 3]);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Horizontal);
+}
+
+#[test]
+fn mmc1_powers_on_with_the_last_bank_fixed() {
+  let cartridge = RomBuilder::new(1).prg_banks(8).chr_banks(4).cartridge();
+  assert_eq!(prg_pages(&cartridge), [0, 1, 14, 15]);
+}
+
+#[test]
+fn mmc1_prg_modes_0_and_1_switch_32kb_ignoring_the_low_bit() {
+  for mode in [0, 1] {
+    let mut cartridge = RomBuilder::new(1).prg_banks(8).chr_banks(4).cartridge();
+    mmc1_write(&mut cartridge, 0x8000, mode << 2);
+    mmc1_write(&mut cartridge, 0xE000, 3);
+    assert_eq!(prg_pages(&cartridge), [4, 5, 6, 7], "mode {}", mode);
+  }
+}
+
+#[test]
+fn mmc1_prg_mode_2_fixes_the_first_bank_at_8000() {
+  let mut cartridge = RomBuilder::new(1).prg_banks(8).chr_banks(4).cartridge();
+  mmc1_write(&mut cartridge, 0x8000, 0b01000);
+  mmc1_write(&mut cartridge, 0xE000, 5);
+  assert_eq!(prg_pages(&cartridge), [0, 1, 10, 11]);
+}
+
+#[test]
+fn mmc1_prg_mode_3_fixes_the_last_bank_at_c000() {
+  let mut cartridge = RomBuilder::new(1).prg_banks(8).chr_banks(4).cartridge();
+  mmc1_write(&mut cartridge, 0x8000, 0b01100);
+  mmc1_write(&mut cartridge, 0xE000, 5);
+  assert_eq!(prg_pages(&cartridge), [10, 11, 14, 15]);
+}
+
+#[test]
+fn mmc1_chr_switches_8kb_or_two_4kb_banks() {
+  let mut cartridge = RomBuilder::new(1).prg_banks(8).chr_banks(4).cartridge();
+  // 8 KB mode counts in 4 KB banks and ignores the low bit, and the second register
+  mmc1_write(&mut cartridge, 0x8000, 0b01100);
+  mmc1_write(&mut cartridge, 0xA000, 3);
+  mmc1_write(&mut cartridge, 0xC000, 7);
+  assert_eq!(chr_pages(&cartridge), [8, 9, 10, 11, 12, 13, 14, 15]);
+
+  mmc1_write(&mut cartridge, 0x8000, 0b11100);
+  mmc1_write(&mut cartridge, 0xA000, 3);
+  mmc1_write(&mut cartridge, 0xC000, 5);
+  assert_eq!(chr_pages(&cartridge), [12, 13, 14, 15, 20, 21, 22, 23]);
+}
+
+#[test]
+fn mmc1_control_picks_the_mirroring() {
+  let mut cartridge = RomBuilder::new(1).prg_banks(8).chr_banks(4).cartridge();
+  let expected = [MirroringMode::SingleScreenLow, MirroringMode::SingleScreenHigh, MirroringMode::Vertical, MirroringMode::Horizontal];
+  for (mirroring, expected) in expected.into_iter().enumerate() {
+    mmc1_write(&mut cartridge, 0x8000, 0b01100 | mirroring as u8);
+    assert_eq!(cartridge.get_nametable_layout(), expected);
+  }
+}
+
+#[test]
+fn mmc1_reset_bit_goes_back_to_prg_mode_3() {
+  let mut cartridge = RomBuilder::new(1).prg_banks(8).chr_banks(4).cartridge();
+  mmc1_write(&mut cartridge, 0x8000, 0b01000);
+  mmc1_write(&mut cartridge, 0xE000, 2);
+  cartridge.cpu_write(0x8000, 0x80);
+  assert_eq!(prg_pages(&cartridge), [4, 5, 14, 15]);
+}
+
+#[test]
+fn uxrom_switches_16kb_at_8000() {
+  let mut cartridge = RomBuilder::new(2).prg_banks(8).chr_banks(0).cartridge();
+  cartridge.cpu_write(0x8000, 3);
+  assert_eq!(prg_pages(&cartridge), [6, 7, 14, 15]);
+}
+
+#[test]
+fn cnrom_switches_8kb_of_chr() {
+  let mut cartridge = RomBuilder::new(3).prg_banks(2).chr_banks(4).cartridge();
+  cartridge.cpu_write(0x8000, 2);
+  assert_eq!(chr_pages(&cartridge), [16, 17, 18, 19, 20, 21, 22, 23]);
+}
+
+#[test]
+fn mmc3_prg_mode_0_fixes_the_second_last_bank_at_c000() {
+  let mut cartridge = RomBuilder::new(4).prg_banks(8).chr_banks(8).cartridge();
+  mmc3_bank(&mut cartridge, 0, 6, 3);
+  mmc3_bank(&mut cartridge, 0, 7, 5);
+  assert_eq!(prg_pages(&cartridge), [3, 5, 14, 15]);
+}
+
+#[test]
+fn mmc3_prg_mode_1_swaps_8000_and_c000() {
+  let mut cartridge = RomBuilder::new(4).prg_banks(8).chr_banks(8).cartridge();
+  mmc3_bank(&mut cartridge, 0x40, 6, 3);
+  mmc3_bank(&mut cartridge, 0x40, 7, 5);
+  assert_eq!(prg_pages(&cartridge), [14, 5, 3, 15]);
+}
+
+#[test]
+fn mmc3_chr_inversion_swaps_the_2kb_and_1kb_halves() {
+  let mut cartridge = RomBuilder::new(4).prg_banks(8).chr_banks(8).cartridge();
+  // The 2 KB banks ignore their low bit
+  for (register, bank) in [9, 10, 20, 21, 22, 23].into_iter().enumerate() {
+    mmc3_bank(&mut cartridge, 0, register as u8, bank);
+  }
+  assert_eq!(chr_pages(&cartridge), [8, 9, 10, 11, 20, 21, 22, 23]);
+
+  cartridge.cpu_write(0x8000, 0x80);
+  assert_eq!(chr_pages(&cartridge), [20, 21, 22, 23, 8, 9, 10, 11]);
+}
+
+#[test]
+fn mmc3_mirroring_register_picks_vertical_or_horizontal() {
+  let mut cartridge = RomBuilder::new(4).prg_banks(8).chr_banks(8).cartridge();
+  cartridge.cpu_write(0xA000, 0);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Vertical);
+  cartridge.cpu_write(0xA000, 1);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Horizontal);
+}
+
+#[test]
+fn axrom_switches_32kb_and_selects_a_single_screen() {
+  let mut cartridge = RomBuilder::new(7).prg_banks(8).chr_banks(0).cartridge();
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::SingleScreenLow);
+
+  cartridge.cpu_write(0x8000, 0x12);
+  assert_eq!(prg_pages(&cartridge), [8, 9, 10, 11]);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::SingleScreenHigh);
+
+  cartridge.cpu_write(0x8000, 0x01);
+  assert_eq!(prg_pages(&cartridge), [4, 5, 6, 7]);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::SingleScreenLow);
+}
+
+/// Set one of the Namco 108's bank registers through $8000/$8001
+fn namco108_bank(cartridge: &mut Cartridge, register: u8, value: u8) {
+  cartridge.cpu_write(0x8000, register);
+  cartridge.cpu_write(0x8001, value);
+}
+
+#[test]
+fn namco108_switches_8kb_at_8000_and_a000_with_the_rest_fixed() {
+  for mapper in [76, 88, 154, 206] {
+    let mut cartridge = RomBuilder::new(mapper).prg_banks(8).chr_banks(8).cartridge();
+    namco108_bank(&mut cartridge, 6, 3);
+    // Mirrors of the registers all through $8000-$9FFF
+    cartridge.cpu_write(0x9FFE, 7);
+    cartridge.cpu_write(0x9FFF, 5);
+    assert_eq!(prg_pages(&cartridge), [3, 5, 14, 15], "mapper {}", mapper);
+  }
+}
+
+#[test]
+fn dxrom_switches_chr_like_mmc3_without_inversion() {
+  let mut cartridge = RomBuilder::new(206).prg_banks(8).chr_banks(8).cartridge();
+  for (register, bank) in [9, 10, 20, 21, 22, 23].into_iter().enumerate() {
+    namco108_bank(&mut cartridge, register as u8, bank);
+  }
+  assert_eq!(chr_pages(&cartridge), [8, 9, 10, 11, 20, 21, 22, 23]);
+  // There's no mode bit to invert them, or mirroring register
+  cartridge.cpu_write(0x8000, 0x80);
+  cartridge.cpu_write(0xA000, 1);
+  assert_eq!(chr_pages(&cartridge), [8, 9, 10, 11, 20, 21, 22, 23]);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Horizontal);
+}
+
+#[test]
+fn namcot3446_switches_2kb_of_chr_with_r2_to_r5() {
+  let mut cartridge = RomBuilder::new(76).prg_banks(8).chr_banks(8).cartridge();
+  for (register, bank) in [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 6)] {
+    namco108_bank(&mut cartridge, register, bank);
+  }
+  assert_eq!(chr_pages(&cartridge), [6, 7, 8, 9, 10, 11, 12, 13]);
+}
+
+#[test]
+fn namcot3433_takes_the_right_pattern_table_from_the_second_64kb() {
+  let mut cartridge = RomBuilder::new(88).prg_banks(8).chr_banks(16).cartridge();
+  for (register, bank) in [0x42, 0x44, 1, 2, 0x43, 0x44].into_iter().enumerate() {
+    namco108_bank(&mut cartridge, register as u8, bank);
+  }
+  assert_eq!(chr_pages(&cartridge), [2, 3, 4, 5, 65, 66, 67, 68]);
+}
+
+#[test]
+fn namcot3453_picks_a_single_screen_with_bit_6_of_any_write() {
+  let mut cartridge = RomBuilder::new(154).prg_banks(8).chr_banks(16).cartridge();
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::SingleScreenLow);
+  cartridge.cpu_write(0xC000, 0x40);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::SingleScreenHigh);
+  // Bank writes carry the bit too
+  namco108_bank(&mut cartridge, 2, 1);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::SingleScreenLow);
+  assert_eq!(chr_pages(&cartridge)[4], 65);
+}
+
+#[test]
+fn vrc1_switches_prg_and_4kb_chr_with_a_fifth_bit_at_9000() {
+  let mut cartridge = RomBuilder::new(75).prg_banks(8).chr_banks(16).cartridge();
+  cartridge.cpu_write(0x8000, 1);
+  cartridge.cpu_write(0xA000, 2);
+  cartridge.cpu_write(0xC000, 3);
+  assert_eq!(prg_pages(&cartridge), [1, 2, 3, 15]);
+
+  cartridge.cpu_write(0xE000, 2);
+  cartridge.cpu_write(0xF000, 5);
+  cartridge.cpu_write(0x9000, 0b101);
+  assert_eq!(chr_pages(&cartridge), [8, 9, 10, 11, 84, 85, 86, 87]);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Horizontal);
+  cartridge.cpu_write(0x9000, 0);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Vertical);
+}
+
+#[test]
+fn x1_005_has_its_registers_and_ram_at_7ef0() {
+  let mut cartridge = RomBuilder::new(80).prg_banks(8).chr_banks(8).battery().cartridge();
+  for (register, bank) in [0x7EFA, 0x7EFC, 0x7EFE].into_iter().zip([4, 5, 6]) {
+    cartridge.cpu_write(register, bank);
+  }
+  assert_eq!(prg_pages(&cartridge), [4, 5, 6, 15]);
+
+  for (register, bank) in [3, 6, 40, 41, 42, 43].into_iter().enumerate() {
+    cartridge.cpu_write(0x7EF0 + register as u16, bank);
+  }
+  assert_eq!(chr_pages(&cartridge), [2, 3, 6, 7, 40, 41, 42, 43]);
+  cartridge.cpu_write(0x7EF6, 1);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Vertical);
+
+  // Its RAM stays locked until $A3 is written, and is mirrored every 128 bytes
+  cartridge.cpu_write(0x7F00, 0x55);
+  assert_eq!(cartridge.cpu_read(0x7F00), None);
+  cartridge.cpu_write(0x7EF8, 0xA3);
+  cartridge.cpu_write(0x7F00, 0x55);
+  assert_eq!(cartridge.cpu_read(0x7F80), Some(0x55));
+  // It's all the RAM there is, so the rest of $6000-$7FFF is open bus
+  cartridge.cpu_write(0x6000, 0x55);
+  assert_eq!(cartridge.cpu_read(0x6000), None);
+  assert_eq!(cartridge.cpu_read(0x7EEF), None);
+}
+
+#[test]
+fn j87_swaps_the_two_chr_bank_bits() {
+  let mut cartridge = RomBuilder::new(87).prg_banks(2).chr_banks(4).cartridge();
+  cartridge.cpu_write(0x6000, 1);
+  assert_eq!(chr_pages(&cartridge)[0], 16);
+  cartridge.cpu_write(0x6000, 2);
+  assert_eq!(chr_pages(&cartridge)[0], 8);
+  assert_eq!(prg_pages(&cartridge), [0, 1, 2, 3]);
+}
+
+#[test]
+fn cnrom_copy_protection_reads_garbage_with_chr_disabled() {
+  let mut cartridge = RomBuilder::new(185).prg_banks(2).chr_banks(1).cartridge();
+  cartridge.cpu_write(0x8000, 0x13);
+  assert_eq!(*cartridge.ppu_read(0x1C42), 0x42);
+  cartridge.cpu_write(0x8000, 0x01);
+  assert_eq!(*cartridge.ppu_read(0x1C42), 7);
+
+  // Submappers say exactly which value turns it on
+  let mut cartridge = RomBuilder::new(185).submapper(6).cartridge();
+  cartridge.cpu_write(0x8000, 0x01);
+  assert_eq!(*cartridge.ppu_read(0x0455), 0x55);
+  cartridge.cpu_write(0x8000, 0x02);
+  assert_eq!(*cartridge.ppu_read(0x0455), 1);
+}
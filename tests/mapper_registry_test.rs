@@ -0,0 +1,71 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::cartridge::{Cartridge, CartridgeError, ConsoleType};
+use silknes_web::mappers;
+
+use common::rom_builder::RomBuilder;
+
+#[test]
+fn registered_mappers_are_unique() {
+  for (i, info) in mappers::MAPPERS.iter().enumerate() {
+    assert!(mappers::MAPPERS[i + 1..].iter().all(|other| other.id != info.id), "mapper {} registered twice", info.id);
+    assert_eq!(mappers::info(info.id).map(|found| found.name), Some(info.name));
+  }
+}
+
+#[test]
+fn capabilities_match_the_mappers() {
+  let mmc3 = mappers::info(4).unwrap();
+  assert!(mmc3.irq && mmc3.prg_ram && !mmc3.audio);
+  let vrc7 = mappers::info(85).unwrap();
+  assert!(vrc7.irq && vrc7.audio);
+  let uxrom = mappers::info(2).unwrap();
+  assert!(!uxrom.irq && !uxrom.prg_ram && !uxrom.audio);
+}
+
+#[test]
+fn unsupported_mappers_are_an_error_rather_than_a_panic() {
+  assert!(matches!(Cartridge::from_bytes(RomBuilder::new(5).build()), Err(CartridgeError::UnsupportedMapper(5))));
+  assert!(Cartridge::from_bytes(RomBuilder::new(4).build()).is_ok());
+}
+
+#[test]
+fn bad_images_are_an_error_rather_than_a_panic() {
+  assert!(matches!(Cartridge::from_bytes(vec![]), Err(CartridgeError::InvalidHeader)));
+  assert!(matches!(Cartridge::from_bytes(vec![0; 0x6010]), Err(CartridgeError::InvalidHeader)));
+  // Cut off partway through CHR
+  let mut truncated = RomBuilder::new(0).build();
+  truncated.truncate(0x9000);
+  assert!(matches!(Cartridge::from_bytes(truncated), Err(CartridgeError::Truncated { expected: 0xA010, actual: 0x9000 })));
+  let mut no_prg = RomBuilder::new(0).build();
+  no_prg[4] = 0;
+  assert!(matches!(Cartridge::from_bytes(no_prg), Err(CartridgeError::NoPrgRom)));
+  assert!(matches!(Cartridge::from_rom("no/such/rom.nes"), Err(CartridgeError::Io(_))));
+}
+
+#[test]
+fn arcade_roms_are_an_error_rather_than_misrunning() {
+  let with_flags7 = |flags7: u8, byte13: u8| {
+    let mut rom = RomBuilder::new(0).build();
+    rom[7] = flags7;
+    rom[13] = byte13;
+    Cartridge::from_bytes(rom)
+  };
+  assert!(matches!(with_flags7(0x01, 0), Err(CartridgeError::UnsupportedConsole(ConsoleType::VsSystem))));
+  assert!(matches!(with_flags7(0x02, 0), Err(CartridgeError::UnsupportedConsole(ConsoleType::PlayChoice10))));
+  // NES 2.0 keeps anything else in byte 13
+  assert!(matches!(with_flags7(0x0B, 0x02), Err(CartridgeError::UnsupportedConsole(ConsoleType::PlayChoice10))));
+  assert!(matches!(with_flags7(0x0B, 0x05), Err(CartridgeError::UnsupportedConsole(ConsoleType::Extended(5)))));
+  assert!(with_flags7(0x0B, 0x00).is_ok());
+  // Famiclones and the EPSM expansion run NES games as they are
+  assert!(with_flags7(0x0B, 0x03).is_ok());
+  assert!(with_flags7(0x0B, 0x04).is_ok());
+  // Junk over the end of an iNES header says nothing about the console
+  let mut dirty = RomBuilder::new(0).build();
+  dirty[7] = 0x01;
+  dirty[12..16].copy_from_slice(b"Dude");
+  assert!(Cartridge::from_bytes(dirty).is_ok());
+  assert_eq!(with_flags7(0x01, 0).err().unwrap().to_string(), "This ROM is for the Vs. System, which isn't supported");
+}
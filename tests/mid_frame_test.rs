@@ -0,0 +1,96 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::BusLike;
+use silknes_web::nes::{Nes, RunTarget};
+use silknes_web::palette::{Palette, PAL_FILE_SIZE};
+
+use common::rom_builder::{RomBuilder, SOLID_TILE_CHR, SPIN};
+
+const BACKDROP: u8 = 0x0F;
+const RED: u8 = 0x16;
+const GREEN: u8 = 0x2A;
+
+/// NROM that spins with NMIs off, showing a screen of solid tiles in background colour 1 of palette 0.
+/// Colour n of the console's palette comes out as [3n, 3n + 1, 3n + 2].
+fn nes() -> Nes {
+  let mut nes = RomBuilder::new(0).prg(&SPIN).chr(&SOLID_TILE_CHR).nes();
+  let colors: Vec<u8> = (0..PAL_FILE_SIZE).map(|i| i as u8).collect();
+  nes.set_palette(Palette::from_pal_bytes(&colors).unwrap());
+  // Past the PPU's warm up, when it starts taking writes
+  nes.run_frames(2);
+
+  nes.run_until(RunTarget::VBlank);
+  write_vram(&mut nes, 0x2000, &[1; 0x3C0]);
+  write_vram(&mut nes, 0x3F00, &[BACKDROP, RED]);
+  nes.bus.cpu_write(0x2000, 0);
+  nes.bus.cpu_write(0x2005, 0);
+  nes.bus.cpu_write(0x2005, 0);
+  nes.bus.cpu_write(0x2001, 0x0A);
+  nes
+}
+
+fn write_vram(nes: &mut Nes, address: u16, bytes: &[u8]) {
+  nes.bus.cpu_write(0x2006, (address >> 8) as u8);
+  nes.bus.cpu_write(0x2006, address as u8);
+  for byte in bytes {
+    nes.bus.cpu_write(0x2007, *byte);
+  }
+}
+
+/// The console palette entry drawn at `x`, `y` this frame
+fn color_at(nes: &Nes, x: usize, y: usize) -> u8 {
+  nes.bus.ppu.screen()[(y * 256 + x) * 3] / 3
+}
+
+#[test]
+fn the_whole_line_is_drawn() {
+  let mut nes = nes();
+  nes.run_until(RunTarget::VBlank);
+  assert_eq!(color_at(&nes, 0, 0), RED);
+  assert_eq!(color_at(&nes, 255, 0), RED);
+  assert_eq!(color_at(&nes, 255, 239), RED);
+}
+
+#[test]
+fn palette_and_mask_changes_show_from_the_next_pixel() {
+  let mut nes = nes();
+  nes.run_until(RunTarget::Position(100, 101));
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  nes.bus.ppu.ppu_write(cartridge, 0x3F01, GREEN);
+  nes.run_until(RunTarget::Position(120, 101));
+  nes.bus.cpu_write(0x2001, 0x0B);
+  nes.run_until(RunTarget::VBlank);
+
+  assert_eq!(color_at(&nes, 99, 100), RED);
+  assert_eq!(color_at(&nes, 100, 100), GREEN);
+  assert_eq!(color_at(&nes, 0, 101), GREEN);
+  // Greyscale keeps only the brightness
+  assert_eq!(color_at(&nes, 99, 120), GREEN);
+  assert_eq!(color_at(&nes, 100, 120), GREEN & 0x30);
+}
+
+#[test]
+fn with_rendering_off_the_palette_entry_v_points_at_is_drawn() {
+  let mut nes = nes();
+  nes.bus.cpu_write(0x2001, 0x00);
+  // Outside palette space the backdrop is drawn as usual
+  nes.bus.cpu_write(0x2006, 0x20);
+  nes.bus.cpu_write(0x2006, 0x00);
+  nes.run_until(RunTarget::VBlank);
+  assert_eq!(color_at(&nes, 100, 100), BACKDROP);
+
+  write_vram(&mut nes, 0x3F05, &[GREEN]);
+  // Writing moved v on to $3F06, so point it back at the entry
+  nes.bus.cpu_write(0x2006, 0x3F);
+  nes.bus.cpu_write(0x2006, 0x05);
+  nes.run_until(RunTarget::VBlank);
+  assert_eq!(color_at(&nes, 100, 100), GREEN);
+
+  // $3F10 mirrors $3F00
+  nes.bus.cpu_write(0x2006, 0x3F);
+  nes.bus.cpu_write(0x2006, 0x10);
+  nes.run_until(RunTarget::VBlank);
+  assert_eq!(color_at(&nes, 100, 100), BACKDROP);
+}
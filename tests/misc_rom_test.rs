@@ -0,0 +1,117 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::cartridge::{Cartridge, Format, MirroringMode};
+use silknes_web::mapper::{Mapper, MapperState};
+use silknes_web::mappers::mapper0::Mapper0;
+
+use common::rom_builder::RomBuilder;
+
+/// A trainer counting up from 0
+fn trainer() -> Vec<u8> {
+  (0..512).map(|i| i as u8).collect()
+}
+
+#[test]
+fn nes2_misc_rom_is_loaded() {
+  let cartridge = RomBuilder::new(0).misc_rom(&[1, 2, 3, 4]).cartridge();
+  assert_eq!(cartridge.header_info.format, Format::NES2_0);
+  assert_eq!(cartridge.header_info.misc_roms, 1);
+  assert_eq!(*cartridge.misc_rom, vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn trailing_data_without_misc_roms_is_ignored() {
+  for rom in [RomBuilder::new(0), RomBuilder::new(0).submapper(0)] {
+    let mut bytes = rom.build();
+    bytes.extend([1, 2, 3, 4]);
+    assert!(Cartridge::from_bytes(bytes).unwrap().misc_rom.is_empty());
+  }
+}
+
+#[test]
+fn trainer_is_skipped() {
+  let without = RomBuilder::new(0).misc_rom(&[5, 6]).cartridge();
+  let cartridge = RomBuilder::new(0).trainer(&trainer()).misc_rom(&[5, 6]).cartridge();
+  assert_eq!(cartridge.prg_rom, without.prg_rom);
+  assert_eq!(cartridge.chr_rom, without.chr_rom);
+  assert_eq!(*cartridge.misc_rom, vec![5, 6]);
+}
+
+#[test]
+fn trainer_is_loaded_at_7000() {
+  let cartridge = RomBuilder::new(0).trainer(&trainer()).cartridge();
+  assert!(cartridge.has_ram);
+  assert_eq!(cartridge.cpu_read(0x6FFF), Some(0));
+  for i in 0..512u16 {
+    assert_eq!(cartridge.cpu_read(0x7000 + i), Some(i as u8));
+  }
+  assert_eq!(cartridge.cpu_read(0x7200), Some(0));
+
+  let without = RomBuilder::new(0).cartridge();
+  assert!(!without.has_ram);
+}
+
+#[test]
+fn trainer_is_loaded_at_7000_whatever_the_mapper() {
+  // UxROM, AxROM and GxROM don't bank PRG RAM, and used to squash all of it into its first byte
+  for mapper in [1u8, 2, 4, 7, 66] {
+    let mut cartridge = RomBuilder::new(mapper).trainer(&trainer()).cartridge();
+    for i in 0..512u16 {
+      assert_eq!(cartridge.cpu_read(0x7000 + i), Some(i as u8), "mapper {} at {:04X}", mapper, 0x7000 + i);
+    }
+    cartridge.cpu_write(0x6000, 0x42);
+    assert_eq!(cartridge.cpu_read(0x6000), Some(0x42), "mapper {}", mapper);
+    assert_eq!(cartridge.cpu_read(0x7001), Some(1), "mapper {}", mapper);
+  }
+}
+
+/// NROM, except $5000-$5FFF reads from the misc ROM
+#[derive(Clone)]
+struct MiscRomMapper(Mapper0);
+
+impl Mapper for MiscRomMapper {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    self.0.get_mapped_address_cpu(address)
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    self.0.prg_bank_size()
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    self.0.get_mapped_address_ppu(address)
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    self.0.mapped_cpu_write(address, value)
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    self.0.mirroring_mode()
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn state(&self) -> MapperState {
+    self.0.state()
+  }
+
+  fn get_mapped_address_misc(&self, address: u16) -> Option<u32> {
+    (0x5000..=0x5FFF).contains(&address).then(|| (address - 0x5000) as u32)
+  }
+}
+
+#[test]
+fn mappers_can_route_reads_to_misc_rom() {
+  let mut cartridge = RomBuilder::new(0).misc_rom(&[1, 2, 3, 4]).cartridge();
+  cartridge.mapper = Box::new(MiscRomMapper(Mapper0::new(2, 1)));
+  assert_eq!(cartridge.cpu_read(0x5000), Some(1));
+  assert_eq!(cartridge.cpu_read(0x5003), Some(4));
+  // Misc ROM smaller than the window mirrors
+  assert_eq!(cartridge.cpu_read(0x5004), Some(1));
+  assert_eq!(cartridge.cpu_read(0x8000), Some(0));
+}
@@ -0,0 +1,86 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::apu::MixerMode;
+use silknes_web::bus::BusLike;
+use silknes_web::command::{self, Command};
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+fn nes(mixer: MixerMode) -> Nes {
+  let mut nes = RomBuilder::new(0).prg(&SPIN).nes();
+  nes.set_mixer(mixer);
+  nes
+}
+
+/// Both pulses at full constant volume playing a square wave, and the DMC held high
+fn play_everything(nes: &mut Nes) {
+  nes.bus.cpu_write(0x4015, 0x03);
+  for base in [0x4000, 0x4004] {
+    nes.bus.cpu_write(base, 0b1011_1111);
+    nes.bus.cpu_write(base + 2, 0xFD);
+    nes.bus.cpu_write(base + 3, 0x00);
+  }
+  nes.bus.cpu_write(0x4011, 0x7F);
+}
+
+/// The audio from the next `frames` frames
+fn audio(nes: &mut Nes, frames: u64) -> Vec<f32> {
+  nes.take_audio();
+  nes.run_frames(frames);
+  nes.take_audio()
+}
+
+#[test]
+fn accurate_silence_is_silent() {
+  let mut nes = nes(MixerMode::Accurate);
+  assert!(audio(&mut nes, 2).iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn the_accurate_mixer_takes_out_the_dc_offset() {
+  let mut fast = nes(MixerMode::Fast);
+  let mut accurate = nes(MixerMode::Accurate);
+  for nes in [&mut fast, &mut accurate] {
+    nes.bus.cpu_write(0x4011, 0x7F);
+  }
+  // Long enough for the 90 Hz high pass to settle
+  let fast = audio(&mut fast, 10);
+  let accurate = audio(&mut accurate, 10);
+  let settled = |samples: &[f32]| samples[samples.len() - 100..].iter().sum::<f32>() / 100.0;
+  assert!(settled(&fast) > -0.8, "the fast mixer holds the DMC's level");
+  assert!(settled(&accurate).abs() < 0.001, "the accurate mixer settles back to silence");
+}
+
+#[test]
+fn the_accurate_mixer_stays_in_range() {
+  let mut nes = nes(MixerMode::Accurate);
+  play_everything(&mut nes);
+  let samples = audio(&mut nes, 10);
+  assert!(samples.iter().all(|sample| sample.is_finite() && (-1.0..=1.0).contains(sample)));
+  // The pulses are still heard once the DMC's offset is gone
+  let tail = &samples[samples.len() / 2..];
+  let (low, high) = tail.iter().fold((f32::MAX, f32::MIN), |(low, high), sample| (low.min(*sample), high.max(*sample)));
+  assert!(high - low > 0.2);
+}
+
+#[test]
+fn loading_a_state_keeps_the_mixer() {
+  let mut nes = nes(MixerMode::Fast);
+  let state = nes.save_state();
+  nes.set_mixer(MixerMode::Accurate);
+  nes.load_state(&state);
+  assert!(audio(&mut nes, 1).iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn mixer_modes_round_trip_and_parse() {
+  for mode in MixerMode::ALL {
+    assert_eq!(MixerMode::from_key(mode.key()), Some(mode));
+  }
+  assert_eq!(command::parse("mixer accurate"), Ok(Command::SetMixer(MixerMode::Accurate)));
+  assert_eq!(command::parse("mixer fast"), Ok(Command::SetMixer(MixerMode::Fast)));
+  assert!(command::parse("mixer loud").is_err());
+}
@@ -0,0 +1,95 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::cartridge::{Cartridge, MirroringMode};
+use silknes_web::mapper::PpuBusSignal;
+
+use common::rom_builder::RomBuilder;
+
+/// Select bank register `bank_select` and write `bank` to it
+fn set_bank(cartridge: &mut Cartridge, bank_select: u8, bank: u8) {
+  cartridge.cpu_write(0x8000, bank_select);
+  cartridge.cpu_write(0x8001, bank);
+}
+
+#[test]
+fn txsrom_picks_nametable_pages_with_chr_banks() {
+  let mut cartridge = RomBuilder::new(118).chr_banks(8).cartridge();
+  set_bank(&mut cartridge, 0, 0x80);
+  set_bank(&mut cartridge, 1, 0x02);
+  // The mirroring register's ignored
+  cartridge.cpu_write(0xA000, 0x01);
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Pages([1, 1, 0, 0]));
+
+  // With the CHR banks swapped around, the 1 KB banks pick instead
+  for (register, bank) in [(2, 0x00), (3, 0x80), (4, 0x80), (5, 0x00)] {
+    set_bank(&mut cartridge, 0x80 | register, bank);
+  }
+  assert_eq!(cartridge.get_nametable_layout(), MirroringMode::Pages([0, 1, 1, 0]));
+}
+
+#[test]
+fn tqrom_has_chr_ram_alongside_chr_rom() {
+  let mut nes = RomBuilder::new(119).chr_banks(8).nes();
+  let cartridge = nes.bus.cartridge.as_mut().unwrap();
+  set_bank(cartridge, 2, 0x05);
+  set_bank(cartridge, 3, 0x41);
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+
+  // Bank 5 of CHR ROM ignores writes, bank 1 of CHR RAM takes them
+  nes.bus.ppu.ppu_write(cartridge, 0x1000, 0xAA);
+  nes.bus.ppu.ppu_write(cartridge, 0x1400, 0xBB);
+  assert_eq!(*nes.bus.ppu.ppu_read(cartridge, 0x1000), 5);
+  assert_eq!(*nes.bus.ppu.ppu_read(cartridge, 0x1400), 0xBB);
+
+  // And it's still there after switching back to CHR ROM and then to the same RAM elsewhere
+  let cartridge = nes.bus.cartridge.as_mut().unwrap();
+  set_bank(cartridge, 3, 0x01);
+  set_bank(cartridge, 2, 0x49);
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  assert_eq!(*nes.bus.ppu.ppu_read(cartridge, 0x1400), 1);
+  assert_eq!(*nes.bus.ppu.ppu_read(cartridge, 0x1000), 0xBB);
+}
+
+/// Whether each of `scanlines` fires an IRQ, with A12 rising once a scanline, acknowledging it each time
+fn irqs(cartridge: &mut Cartridge, scanlines: usize) -> Vec<bool> {
+  (0..scanlines)
+    .map(|_| {
+      cartridge.mapper.ppu_bus(PpuBusSignal { address: 0x1000, a12_rise: true });
+      let fired = cartridge.mapper.irq_state();
+      cartridge.cpu_write(0xE000, 0);
+      cartridge.cpu_write(0xE001, 0);
+      fired
+    })
+    .collect()
+}
+
+fn start_irq(cartridge: &mut Cartridge, latch: u8) {
+  cartridge.cpu_write(0xC000, latch);
+  cartridge.cpu_write(0xC001, 0);
+  cartridge.cpu_write(0xE001, 0);
+}
+
+#[test]
+fn irq_counter_reloads_on_the_clock_after_c001() {
+  let mut cartridge = RomBuilder::new(4).chr_banks(8).cartridge();
+  start_irq(&mut cartridge, 2);
+  assert_eq!(irqs(&mut cartridge, 6), [false, false, true, false, false, true]);
+}
+
+#[test]
+fn alternate_irqs_only_fire_once_with_a_latch_of_0() {
+  let mut cartridge = RomBuilder::new(4).chr_banks(8).cartridge();
+  start_irq(&mut cartridge, 0);
+  assert_eq!(irqs(&mut cartridge, 3), [true, true, true]);
+
+  // MMC3A and MC-ACC are told apart by NES 2.0 submappers 4 and 3
+  for submapper in [3, 4] {
+    let mut cartridge = RomBuilder::new(4).chr_banks(8).submapper(submapper).cartridge();
+    start_irq(&mut cartridge, 0);
+    assert_eq!(irqs(&mut cartridge, 3), [true, false, false]);
+    start_irq(&mut cartridge, 2);
+    assert_eq!(irqs(&mut cartridge, 6), [false, false, true, false, false, true]);
+  }
+}
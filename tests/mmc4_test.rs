@@ -0,0 +1,55 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::mapper::{FetchKind, Mapper, PatternFetch};
+use silknes_web::mappers::mapper10::Mapper10;
+
+use common::rom_builder::RomBuilder;
+
+fn fetch(address: u16) -> PatternFetch {
+  PatternFetch { address, kind: FetchKind::Background, scanline: 0, dot: 0 }
+}
+
+#[test]
+fn prg_switches_in_16kb_banks_with_the_last_fixed() {
+  let mut cartridge = RomBuilder::new(10).prg_banks(8).chr_banks(16).battery().cartridge();
+  assert_eq!(cartridge.cpu_read(0x8000), Some(0));
+  assert_eq!(cartridge.cpu_read(0xC000), Some(14));
+  // 16 KB bank 5 is 8 KB pages 10 and 11
+  cartridge.cpu_write(0xA000, 5);
+  assert_eq!(cartridge.cpu_read(0x8000), Some(10));
+  assert_eq!(cartridge.cpu_read(0xBFFF), Some(11));
+  assert_eq!(cartridge.cpu_read(0xFFFF), Some(15));
+}
+
+#[test]
+fn prg_ram_is_mapped_at_6000() {
+  let mut cartridge = RomBuilder::new(10).prg_banks(8).chr_banks(16).battery().cartridge();
+  cartridge.cpu_write(0x6000, 0x12);
+  cartridge.cpu_write(0x7FFF, 0x34);
+  assert_eq!(cartridge.cpu_read(0x6000), Some(0x12));
+  assert_eq!(cartridge.cpu_read(0x7FFF), Some(0x34));
+}
+
+#[test]
+fn both_latches_flip_on_any_row_of_the_trigger_tiles() {
+  let mut mapper = Mapper10::new(8, 16);
+  for (register, bank) in [(0xB000, 1), (0xC000, 2), (0xD000, 3), (0xE000, 4)] {
+    mapper.mapped_cpu_write(register, bank);
+  }
+  assert_eq!(mapper.get_mapped_address_ppu(0x0010), 0x1010);
+  assert_eq!(mapper.get_mapped_address_ppu(0x1010), 0x3010);
+
+  // Unlike MMC2, the lower latch isn't limited to the first row
+  mapper.pattern_fetch(fetch(0x0FEC));
+  assert_eq!(mapper.get_mapped_address_ppu(0x0010), 0x2010);
+  mapper.pattern_fetch(fetch(0x0FDF));
+  assert_eq!(mapper.get_mapped_address_ppu(0x0010), 0x1010);
+
+  mapper.pattern_fetch(fetch(0x1FE9));
+  assert_eq!(mapper.get_mapped_address_ppu(0x1010), 0x4010);
+  // First plane fetches leave it alone
+  mapper.pattern_fetch(fetch(0x1FD0));
+  assert_eq!(mapper.get_mapped_address_ppu(0x1010), 0x4010);
+}
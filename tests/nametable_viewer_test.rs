@@ -0,0 +1,113 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::nes::Nes;
+use silknes_web::ppu::{ScrollSample, NAMETABLES_WIDTH};
+
+use common::rom_builder::RomBuilder;
+
+/// NROM with vertical mirroring that puts a solid white tile at the top left of $2400, then scrolls to
+/// X 35, Y 17 in that nametable with only the background on
+fn nes() -> Nes {
+  // Tile 1 is colour 1 all over
+  let mut chr = [0; 0x18];
+  chr[0x10..].fill(0xFF);
+  let rom = RomBuilder::new(0).prg_banks(1).vertical_mirroring().chr(&chr).prg(&[
+    0x78,             // SEI
+    0xA9, 0x40,       // LDA #$40
+    0x8D, 0x17, 0x40, // STA $4017
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C006
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C00B
+    0xA9, 0x24,       // LDA #$24
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x00,       // LDA #$00
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x01,       // LDA #$01
+    0x8D, 0x07, 0x20, // STA $2007
+    0xA9, 0x3F,       // LDA #$3F
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x00,       // LDA #$00
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x0F,       // LDA #$0F
+    0x8D, 0x07, 0x20, // STA $2007
+    0xA9, 0x30,       // LDA #$30
+    0x8D, 0x07, 0x20, // STA $2007
+    0xA9, 0x23,       // LDA #35
+    0x8D, 0x05, 0x20, // STA $2005
+    0xA9, 0x11,       // LDA #17
+    0x8D, 0x05, 0x20, // STA $2005
+    0xA9, 0x01,       // LDA #$01
+    0x8D, 0x00, 0x20, // STA $2000
+    0xA9, 0x08,       // LDA #$08
+    0x8D, 0x01, 0x20, // STA $2001
+    0x4C, 0x47, 0xC0, // JMP $C047
+  ]);
+  let mut nes = Nes::new();
+  nes.insert_cartridge(rom.cartridge());
+  nes
+}
+
+/// The RGB bytes of one pixel of the nametables picture
+fn pixel(image: &[u8], x: usize, y: usize) -> &[u8] {
+  let offset = (y * NAMETABLES_WIDTH + x) * 3;
+  &image[offset..offset + 3]
+}
+
+#[test]
+fn scroll_origin_allows_for_the_two_tiles_fetched_ahead() {
+  // X 35 in the right nametable is coarse X 4 and fine X 3, and two tiles on that's coarse X 6
+  let sample = ScrollSample { scanline: 0, v: 0x0400 | 6, t: 0x0400 | 4, fine_x: 3 };
+  assert_eq!(sample.origin(), (256 + 35, 0));
+
+  // Near the right edge of the right nametable the prefetch wraps around into the left one
+  let sample = ScrollSample { scanline: 0, v: 0x2000 | (3 << 5) | 1, t: 0x0400 | 31, fine_x: 0 };
+  assert_eq!(sample.origin(), (256 + 31 * 8, 3 * 8 + 2));
+}
+
+#[test]
+fn every_rendered_line_is_sampled_while_logging() {
+  let mut nes = nes();
+  assert!(nes.frame_scroll().is_none());
+  nes.set_scroll_logging(true);
+  nes.run_frames(4);
+
+  let samples = nes.frame_scroll().unwrap();
+  assert_eq!(samples.len(), 240);
+  for (line, sample) in samples.iter().enumerate() {
+    assert_eq!(sample.scanline as usize, line);
+    // Carrying on past the bottom of the nametable goes into the one below, as the picture's laid out
+    assert_eq!(sample.origin(), (256 + 35, 17 + line), "line {line}");
+  }
+
+  nes.set_scroll_logging(false);
+  assert!(nes.frame_scroll().is_none());
+}
+
+#[test]
+fn state_loads_keep_sampling() {
+  let mut nes = nes();
+  nes.set_scroll_logging(true);
+  nes.run_frames(4);
+  let state = nes.save_state();
+  nes.load_state(&state);
+  nes.run_frame();
+  assert_eq!(nes.frame_scroll().map(<[_]>::len), Some(240));
+}
+
+#[test]
+fn nametables_are_drawn_as_the_mirroring_lays_them_out() {
+  let mut nes = nes();
+  nes.run_frames(4);
+  let image = nes.nametables_image();
+  let backdrop = pixel(&image, 0, 0).to_vec();
+  let white = pixel(&image, 256, 0).to_vec();
+  assert_ne!(backdrop, white);
+  assert_eq!(pixel(&image, 263, 7), white);
+  assert_eq!(pixel(&image, 264, 0), backdrop);
+  // With vertical mirroring $2C00 is $2400 again, and $2800 is $2000
+  assert_eq!(pixel(&image, 256, 240), white);
+  assert_eq!(pixel(&image, 0, 240), backdrop);
+}
@@ -0,0 +1,75 @@
+extern crate silknes_web;
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use silknes_web::netplay::{Session, Status, DEFAULT_INPUT_DELAY};
+
+fn connect() -> (Session, Session) {
+  let mut host = Session::host(0, "rom").unwrap();
+  let mut guest = Session::join(&format!("127.0.0.1:{}", host.local_port()), "rom").unwrap();
+  for _ in 0..100 {
+    if *host.status() == Status::Connected && *guest.status() == Status::Connected {
+      break;
+    }
+    guest.update(0);
+    host.update(0);
+    sleep(Duration::from_millis(5));
+  }
+  (host, guest)
+}
+
+#[test]
+fn players_connect_and_start_together() {
+  let (mut host, mut guest) = connect();
+  assert_eq!(*host.status(), Status::Connected);
+  assert_eq!(*guest.status(), Status::Connected);
+  assert!(host.take_started());
+  assert!(guest.take_started());
+  assert!(!host.take_started());
+}
+
+#[test]
+fn both_sides_run_every_frame_with_the_same_inputs() {
+  let mut host = Session::host(0, "rom").unwrap();
+  let mut guest = Session::join(&format!("127.0.0.1:{}", host.local_port()), "rom").unwrap();
+
+  let mut host_frames = vec![];
+  let mut guest_frames = vec![];
+  let mut presses = 0u8;
+  while host_frames.len() < 30 || guest_frames.len() < 30 {
+    presses = presses.wrapping_add(1);
+    if let Some(inputs) = host.update(presses) {
+      host_frames.push(inputs);
+    }
+    if let Some(inputs) = guest.update(presses.wrapping_mul(3)) {
+      guest_frames.push(inputs);
+    }
+    sleep(Duration::from_millis(1));
+  }
+
+  host_frames.truncate(30);
+  guest_frames.truncate(30);
+  assert_eq!(host_frames, guest_frames);
+  // Nobody could have pressed anything in time for the delayed frames
+  assert!(host_frames[..DEFAULT_INPUT_DELAY as usize].iter().all(|inputs| *inputs == [0, 0]));
+  // The host is player 1 and the guest is player 2
+  assert!(host_frames.iter().any(|[player_1, _]| *player_1 != 0));
+  assert!(host_frames.iter().any(|[_, player_2]| *player_2 != 0));
+}
+
+#[test]
+fn a_different_rom_is_turned_away() {
+  let mut host = Session::host(0, "rom").unwrap();
+  let mut guest = Session::join(&format!("127.0.0.1:{}", host.local_port()), "other rom").unwrap();
+  for _ in 0..100 {
+    guest.update(0);
+    host.update(0);
+    if matches!(guest.status(), Status::Disconnected(_)) {
+      break;
+    }
+    sleep(Duration::from_millis(5));
+  }
+  assert!(matches!(guest.status(), Status::Disconnected(_)));
+  assert_eq!(*host.status(), Status::Waiting);
+}
@@ -0,0 +1,117 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::BusLike;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: wait for $10 to be set, then enable NMIs and count through X
+const PROGRAM: [u8; 19] = [
+  0x78,             // SEI
+  0xA5, 0x10,       // LDA $10
+  0xF0, 0xFC,       // BEQ -4
+  0xA9, 0x80,       // LDA #$80
+  0x8D, 0x00, 0x20, // STA $2000
+  0xA2, 0x01,       // LDX #$01
+  0xA2, 0x02,       // LDX #$02
+  0xA2, 0x03,       // LDX #$03
+  0x4C, 0x10, 0xC0, // JMP $C010
+];
+
+/// NMI handler: bump the count at $11 and keep the X it interrupted in $12
+const NMI_HANDLER: [u8; 5] = [
+  0xE6, 0x11, // INC $11
+  0x86, 0x12, // STX $12
+  0x40,       // RTI
+];
+
+/// Past the PPU's warm up, with NMIs still off
+fn started() -> Nes {
+  let mut nes = RomBuilder::new(0).chr_banks(0).prg(&PROGRAM).nmi(&NMI_HANDLER).nes();
+  nes.run_frame();
+  nes.run_frame();
+  nes
+}
+
+fn run_to(nes: &mut Nes, scanline: i16, cycle: u16) {
+  while nes.bus.ppu.position() != (scanline, cycle) {
+    nes.clock();
+  }
+}
+
+fn nmi_count(nes: &Nes) -> u8 {
+  nes.peek(0x11)
+}
+
+#[test]
+fn enabling_nmis_in_vblank_takes_effect_after_the_next_instruction() {
+  let mut nes = started();
+  run_to(&mut nes, 245, 0);
+  nes.bus.cpu_write(0x10, 1);
+  run_to(&mut nes, 250, 0);
+  assert_eq!(nmi_count(&nes), 1);
+  // The write to $2000 set the NMI off in its last cycle, so LDX #$01 still ran
+  assert_eq!(nes.peek(0x12), 1);
+}
+
+#[test]
+fn nmi_fires_once_per_frame() {
+  let mut nes = started();
+  nes.bus.cpu_write(0x10, 1);
+  for _ in 0..5 {
+    nes.run_frame();
+  }
+  let count = nmi_count(&nes);
+  nes.run_frame();
+  assert_eq!(nmi_count(&nes), count + 1);
+}
+
+/// Turn NMIs off for a CPU cycle, long enough for the CPU to see the line released
+fn toggle_nmi_enable(nes: &mut Nes) {
+  nes.bus.cpu_write(0x2000, 0x00);
+  for _ in 0..3 {
+    nes.clock();
+  }
+  nes.bus.cpu_write(0x2000, 0x80);
+}
+
+#[test]
+fn toggling_nmi_enable_in_vblank_retriggers_it() {
+  let mut nes = started();
+  nes.bus.cpu_write(0x10, 1);
+  nes.run_frame();
+  run_to(&mut nes, 245, 0);
+  let count = nmi_count(&nes);
+
+  toggle_nmi_enable(&mut nes);
+  run_to(&mut nes, 246, 0);
+  assert_eq!(nmi_count(&nes), count + 1);
+
+  // Without the vblank flag there's nothing to retrigger
+  nes.bus.cpu_read(0x2002);
+  toggle_nmi_enable(&mut nes);
+  run_to(&mut nes, 247, 0);
+  assert_eq!(nmi_count(&nes), count + 1);
+}
+
+#[test]
+fn reading_status_just_before_vblank_suppresses_it() {
+  let mut nes = started();
+  nes.bus.cpu_write(0x10, 1);
+  nes.run_frame();
+  let count = nmi_count(&nes);
+
+  run_to(&mut nes, 241, 1);
+  assert_eq!(nes.bus.cpu_read(0x2002) & 0x80, 0);
+  run_to(&mut nes, 250, 0);
+  // The flag never gets set this frame, so there's no NMI either
+  assert_eq!(nes.bus.cpu_read(0x2002) & 0x80, 0);
+  assert_eq!(nmi_count(&nes), count);
+
+  // Next frame's back to normal
+  nes.run_frame();
+  run_to(&mut nes, 250, 0);
+  assert_eq!(nmi_count(&nes), count + 1);
+}
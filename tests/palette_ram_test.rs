@@ -0,0 +1,89 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::nes::Nes;
+use silknes_web::ppu::PaletteRam;
+
+use common::rom_builder::RomBuilder;
+
+#[test]
+fn sprite_backdrop_entries_mirror_the_background_ones() {
+  for (mirror, entry) in [(0x3F10, 0x00), (0x3F14, 0x04), (0x3F18, 0x08), (0x3F1C, 0x0C)] {
+    assert_eq!(PaletteRam::index(mirror), entry);
+    // Written through either address, read back through both
+    let mut palette = PaletteRam::default();
+    palette.write(mirror, 0x21);
+    assert_eq!(palette.read(entry as u16 + 0x3F00), 0x21);
+    palette.write(entry as u16 + 0x3F00, 0x12);
+    assert_eq!(palette.read(mirror), 0x12);
+  }
+}
+
+#[test]
+fn every_other_entry_is_its_own() {
+  let mut palette = PaletteRam::default();
+  for entry in 0..32u16 {
+    palette.write(0x3F00 + entry, entry as u8 + 0x20);
+  }
+  // $3F04/$3F08/$3F0C are separate from $3F00, each other and the sprite palettes' entries
+  let entries = palette.entries();
+  assert_eq!(entries[0x00], 0x30);
+  assert_eq!(entries[0x04], 0x34);
+  assert_eq!(entries[0x08], 0x38);
+  assert_eq!(entries[0x0C], 0x3C);
+  for entry in (0..32).filter(|entry| entry % 4 != 0) {
+    assert_eq!(entries[entry], entry as u8 + 0x20, "entry {:02X}", entry);
+  }
+  for entry in [0x10, 0x14, 0x18, 0x1C] {
+    assert_eq!(entries[entry], entries[entry - 0x10]);
+  }
+}
+
+#[test]
+fn the_32_entries_repeat_up_to_3fff_and_are_6_bits_wide() {
+  for address in 0x3F00..=0x3FFFu16 {
+    assert_eq!(PaletteRam::index(address), PaletteRam::index(0x3F00 + address % 0x20), "{:04X}", address);
+  }
+  let mut palette = PaletteRam::default();
+  palette.write(0x3FE5, 0xFF);
+  assert_eq!(palette.read(0x3F05), 0x3F);
+}
+
+/// NROM that waits out the PPU's warm up, writes $16 to $3F04 through its mirror at $3F14, then
+/// leaves v pointing at $3F14 with rendering off, so the background palette hack draws entry $04
+/// everywhere
+fn backdrop_rom() -> RomBuilder {
+  RomBuilder::new(0).prg_banks(1).prg(&[
+    0x78,             // SEI
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C001
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C006
+    0xA9, 0x3F,       // LDA #$3F
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x14,       // LDA #$14
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x16,       // LDA #$16
+    0x8D, 0x07, 0x20, // STA $2007
+    0xA9, 0x3F,       // LDA #$3F
+    0x8D, 0x06, 0x20, // STA $2006
+    0xA9, 0x14,       // LDA #$14
+    0x8D, 0x06, 0x20, // STA $2006
+    0x4C, 0x24, 0xC0, // JMP $C024
+  ])
+}
+
+#[test]
+fn the_background_palette_hack_draws_a_mirrored_entry_as_the_one_it_mirrors() {
+  let mut nes = Nes::new();
+  nes.insert_cartridge(backdrop_rom().cartridge());
+  nes.run_frames(3);
+  assert_eq!(nes.bus.ppu_peek(0x3F04), 0x16);
+  assert_eq!(nes.bus.ppu_peek(0x3F14), 0x16);
+  assert_eq!(nes.bus.ppu_peek(0x3F00), 0x00);
+  assert_eq!(nes.bus.ppu.get_palettes()[0x14], 0x16);
+
+  let red = nes.bus.ppu.colors().colors[0x16];
+  assert!(nes.screen().chunks_exact(3).all(|pixel| pixel == red));
+}
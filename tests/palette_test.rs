@@ -0,0 +1,27 @@
+extern crate silknes_web;
+
+use silknes_web::palette::{BuiltinPalette, Palette, PAL_FILE_SIZE};
+
+#[test]
+fn pal_file_round_trips() {
+  let bytes: Vec<u8> = (0..PAL_FILE_SIZE).map(|i| i as u8).collect();
+  let palette = Palette::from_pal_bytes(&bytes).unwrap();
+
+  assert_eq!(palette.colors[0], [0, 1, 2]);
+  assert_eq!(palette.colors[63], [189, 190, 191]);
+  assert_eq!(palette.to_pal_bytes(), bytes);
+}
+
+#[test]
+fn pal_file_with_emphasis_uses_first_64_colors() {
+  let mut bytes = BuiltinPalette::Fceux.palette().to_pal_bytes();
+  bytes.resize(PAL_FILE_SIZE * 8, 0xFF);
+
+  assert_eq!(Palette::from_pal_bytes(&bytes).unwrap(), BuiltinPalette::Fceux.palette());
+}
+
+#[test]
+fn wrong_sized_pal_file_is_rejected() {
+  assert!(Palette::from_pal_bytes(&[0; PAL_FILE_SIZE - 1]).is_err());
+  assert!(Palette::from_pal_bytes(&[]).is_err());
+}
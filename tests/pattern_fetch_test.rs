@@ -0,0 +1,127 @@
+extern crate silknes_web;
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use silknes_web::cartridge::MirroringMode;
+use silknes_web::mapper::{FetchKind, Mapper, MapperState, PatternFetch};
+use silknes_web::mappers::mapper0::Mapper0;
+use silknes_web::mappers::mapper9::Mapper9;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: turn on background and sprite rendering, then spin
+const PROGRAM: [u8; 19] = [
+  0x78,             // SEI
+  // Wait out the PPU's warm up, which takes until the second vblank
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0xA9, 0x18,       // LDA #$18
+  0x8D, 0x01, 0x20, // STA $2001
+  // loop:
+  0x4C, 0x10, 0xC0, // JMP loop
+];
+
+/// NROM that writes down every pattern fetch it's told about
+#[derive(Clone)]
+struct RecordingMapper {
+  inner: Mapper0,
+  fetches: Arc<Mutex<Vec<PatternFetch>>>,
+}
+
+impl Mapper for RecordingMapper {
+  fn get_mapped_address_cpu(&self, address: u16) -> u32 {
+    self.inner.get_mapped_address_cpu(address)
+  }
+
+  fn prg_bank_size(&self) -> u32 {
+    self.inner.prg_bank_size()
+  }
+
+  fn get_mapped_address_ppu(&self, address: u16) -> u32 {
+    self.inner.get_mapped_address_ppu(address)
+  }
+
+  fn mapped_cpu_write(&mut self, address: u16, value: u8) {
+    self.inner.mapped_cpu_write(address, value)
+  }
+
+  fn mirroring_mode(&self) -> MirroringMode {
+    self.inner.mirroring_mode()
+  }
+
+  fn irq_state(&self) -> bool {
+    false
+  }
+
+  fn state(&self) -> MapperState {
+    self.inner.state()
+  }
+
+  fn pattern_fetch(&mut self, fetch: PatternFetch) {
+    self.fetches.lock().unwrap().push(fetch);
+  }
+}
+
+#[test]
+fn every_rendering_fetch_is_reported_in_order() {
+  let fetches = Arc::new(Mutex::new(Vec::new()));
+  let mut cartridge = RomBuilder::new(0).prg(&PROGRAM).cartridge();
+  cartridge.mapper = Box::new(RecordingMapper { inner: Mapper0::new(2, 1), fetches: Arc::clone(&fetches) });
+  let mut nes = Nes::new();
+  nes.insert_cartridge(cartridge);
+  // Rendering's turned on once the PPU's warmed up, two frames in
+  nes.run_frame();
+  nes.run_frame();
+  fetches.lock().unwrap().clear();
+  nes.run_frame();
+
+  let fetches = fetches.lock().unwrap();
+  let line: Vec<&PatternFetch> = fetches.iter().filter(|fetch| fetch.scanline == 100).collect();
+  // 34 tiles of background and 8 sprite slots, two planes each
+  let background = line.iter().filter(|fetch| fetch.kind == FetchKind::Background).count();
+  let sprites = line.iter().filter(|fetch| fetch.kind == FetchKind::Sprite).count();
+  assert_eq!(background, 68);
+  assert_eq!(sprites, 16);
+  assert!(line.windows(2).all(|pair| pair[0].dot < pair[1].dot));
+
+  // Each tile's second plane comes right after its first
+  let first = line.iter().position(|fetch| fetch.kind == FetchKind::Background).unwrap();
+  assert_eq!(line[first + 1].address, line[first].address + 8);
+  // Empty sprite slots fetch tile $FF
+  let sprite = line.iter().find(|fetch| fetch.kind == FetchKind::Sprite).unwrap();
+  assert_eq!(sprite.address & 0x0FF0, 0x0FF0);
+}
+
+fn fetch(address: u16) -> PatternFetch {
+  PatternFetch { address, kind: FetchKind::Background, scanline: 0, dot: 0 }
+}
+
+#[test]
+fn mmc2_latches_follow_fd_and_fe_fetches() {
+  let mut mapper = Mapper9::new(8, 16);
+  for (register, bank) in [(0xB000, 1), (0xC000, 2), (0xD000, 3), (0xE000, 4)] {
+    mapper.mapped_cpu_write(register, bank);
+  }
+  assert_eq!(mapper.get_mapped_address_ppu(0x0010), 0x1010);
+  assert_eq!(mapper.get_mapped_address_ppu(0x1010), 0x3010);
+
+  mapper.pattern_fetch(fetch(0x0FE8));
+  assert_eq!(mapper.get_mapped_address_ppu(0x0010), 0x2010);
+  // The lower latch only flips on the exact address
+  mapper.pattern_fetch(fetch(0x0FD9));
+  assert_eq!(mapper.get_mapped_address_ppu(0x0010), 0x2010);
+  mapper.pattern_fetch(fetch(0x0FD8));
+  assert_eq!(mapper.get_mapped_address_ppu(0x0010), 0x1010);
+
+  // The upper one flips on any row of the tile's second plane
+  mapper.pattern_fetch(fetch(0x1FEB));
+  assert_eq!(mapper.get_mapped_address_ppu(0x1010), 0x4010);
+  // First plane fetches leave it alone
+  mapper.pattern_fetch(fetch(0x1FD3));
+  assert_eq!(mapper.get_mapped_address_ppu(0x1010), 0x4010);
+}
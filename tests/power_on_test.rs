@@ -0,0 +1,75 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::{Bus, BusLike, PowerOnState, RamFill};
+use silknes_web::config::Config;
+use silknes_web::nes::Nes;
+
+use common::MemoryStorage;
+use common::rom_builder::{RomBuilder, SPIN};
+
+/// NROM that spins with interrupts disabled
+fn nes(state: PowerOnState) -> Nes {
+  let mut nes = Nes::new();
+  nes.set_power_on_state(state);
+  nes.insert_cartridge(RomBuilder::new(0).prg(&SPIN).cartridge());
+  nes
+}
+
+fn ram(nes: &Nes) -> Vec<u8> {
+  (0..0x800).map(|address| nes.peek(address)).collect()
+}
+
+fn with_ram(ram: RamFill) -> PowerOnState {
+  PowerOnState { ram, ..PowerOnState::default() }
+}
+
+#[test]
+fn ram_starts_filled_as_chosen() {
+  let pattern = ram(&nes(PowerOnState::default()));
+  assert_eq!(pattern[..8], [0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]);
+  assert!(ram(&nes(with_ram(RamFill::Zeros))).iter().all(|byte| *byte == 0x00));
+  assert!(ram(&nes(with_ram(RamFill::Ones))).iter().all(|byte| *byte == 0xFF));
+}
+
+#[test]
+fn random_ram_is_the_same_for_the_same_seed() {
+  let first = ram(&nes(with_ram(RamFill::Random(1))));
+  assert_eq!(ram(&nes(with_ram(RamFill::Random(1)))), first);
+  assert_ne!(ram(&nes(with_ram(RamFill::Random(2)))), first);
+
+  // Power cycling fills it the same way again
+  let mut nes = nes(with_ram(RamFill::Random(1)));
+  nes.bus.cpu_write(0x0000, !first[0]);
+  nes.power_on();
+  assert_eq!(ram(&nes), first);
+}
+
+#[test]
+fn open_bus_and_the_frame_counter_start_as_chosen() {
+  // Before the CPU's read anything, as it reads the reset vector straight away
+  let mut bus = Bus::new();
+  bus.power_on_state.open_bus = 0x5A;
+  bus.power_on();
+  assert_eq!(bus.peek(0x5000), 0x5A);
+
+  // Frame IRQs flag themselves in $4015 unless they start inhibited
+  let mut normal = nes(PowerOnState::default());
+  normal.run_frames(2);
+  assert_ne!(normal.bus.cpu_read(0x4015) & 0x40, 0);
+  let mut inhibited = nes(PowerOnState { frame_counter: 0x40, ..PowerOnState::default() });
+  inhibited.run_frames(2);
+  assert_eq!(inhibited.bus.cpu_read(0x4015) & 0x40, 0);
+}
+
+#[test]
+fn power_on_settings_come_back_from_storage() {
+  let config = Config {
+    power_on: PowerOnState { ram: RamFill::Random(12345), open_bus: 0x40, frame_counter: 0xC0 },
+    ..Config::default()
+  };
+  let mut storage = MemoryStorage::default();
+  config.save(&mut storage);
+  assert_eq!(Config::load(Some(&storage)).power_on, config.power_on);
+}
@@ -0,0 +1,60 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+/// NROM past the PPU's warm up, with CHR RAM
+fn nes() -> Nes {
+  let mut nes = RomBuilder::new(0).chr_banks(0).prg(&SPIN).nes();
+  nes.run_frames(2);
+  nes
+}
+
+#[test]
+fn registers_repeat_every_8_bytes() {
+  let mut nes = nes();
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  let ppu = &mut nes.bus.ppu;
+  // $200E and $200F are $2006 and $2007 again, and so is anything past the 8 registers
+  ppu.cpu_write(cartridge, 0x000E, 0x21);
+  ppu.cpu_write(cartridge, 0x1FFE, 0x00);
+  ppu.cpu_write(cartridge, 0x000F, 0x55);
+  assert_eq!(ppu.peek(cartridge, 0x2100), 0x55);
+
+  ppu.cpu_write(cartridge, 0x0006, 0x21);
+  ppu.cpu_write(cartridge, 0x0006, 0x00);
+  ppu.cpu_read(cartridge, 0x000F);
+  assert_eq!(ppu.cpu_read(cartridge, 0xFFFF), 0x55);
+}
+
+#[test]
+fn status_writes_only_fill_the_open_bus() {
+  let mut nes = nes();
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  let ppu = &mut nes.bus.ppu;
+  // Read once first to clear vblank, which reading does
+  ppu.cpu_read(cartridge, 0x0002);
+  let status = ppu.cpu_read(cartridge, 0x0002) & 0xE0;
+  ppu.cpu_write(cartridge, 0x0002, 0xFF);
+  assert_eq!(ppu.cpu_read(cartridge, 0x0002), status | 0x1F);
+}
+
+#[test]
+fn every_ppu_address_can_be_read_and_written() {
+  let mut nes = nes();
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  let ppu = &mut nes.bus.ppu;
+  // Above $3FFF the bus wraps around
+  for address in 0..=0xFFFF {
+    ppu.ppu_write(cartridge, address, address as u8);
+    ppu.ppu_read(cartridge, address);
+  }
+  assert_eq!(ppu.peek(cartridge, 0x0000), 0x00);
+  assert_eq!(ppu.peek(cartridge, 0x2001), 0x01);
+  // $3F10 mirrors $3F00, so $FFF0 was the last write to it, and only the bottom 6 bits are kept
+  assert_eq!(ppu.peek(cartridge, 0x3F00), 0x30);
+  assert_eq!(ppu.peek(cartridge, 0x3F1F), 0x3F);
+}
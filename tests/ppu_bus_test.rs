@@ -0,0 +1,79 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::mapper::{A12Filter, A12_LOW_CYCLES};
+use silknes_web::nes::Nes;
+
+use common::rom_builder::RomBuilder;
+
+/// MMC3 whose IRQ handler counts IRQs in $10, with an IRQ every 11 scanlines. `mask` is written to
+/// $2001 once the PPU's warmed up, with sprites coming from the right pattern table. The APU's frame
+/// IRQ is turned off so the only IRQs are the mapper's.
+fn nes(mask: u8) -> Nes {
+  let rom = RomBuilder::new(4).prg(&[
+    0x78,             // SEI
+    0xA9, 0x40,       // LDA #$40
+    0x8D, 0x17, 0x40, // STA $4017
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C006
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL $C00B
+    0xA9, 0x08,       // LDA #$08
+    0x8D, 0x00, 0x20, // STA $2000
+    0xA9, 0x0A,       // LDA #$0A
+    0x8D, 0x00, 0xC0, // STA $C000
+    0x8D, 0x01, 0xC0, // STA $C001
+    0x8D, 0x01, 0xE0, // STA $E001
+    0xA9, mask,       // LDA #mask
+    0x8D, 0x01, 0x20, // STA $2001
+    0x58,             // CLI
+    0x4C, 0x26, 0xC0, // JMP $C026
+  ]).irq(&[
+    0xE6, 0x10,       // INC $10
+    0x8D, 0x00, 0xE0, // STA $E000
+    0x8D, 0x01, 0xE0, // STA $E001
+    0x40,             // RTI
+  ]);
+  let mut nes = Nes::new();
+  nes.insert_cartridge(rom.cartridge());
+  nes.run_frames(3);
+  nes
+}
+
+#[test]
+fn a12_only_rises_after_staying_low_long_enough() {
+  let mut filter = A12Filter::default();
+  for _ in 0..A12_LOW_CYCLES {
+    assert!(!filter.clock(0x0000).a12_rise);
+  }
+  let signal = filter.clock(0x1234);
+  assert!(signal.a12_rise);
+  assert_eq!(signal.address, 0x1234);
+  // Staying high isn't another edge
+  assert!(!filter.clock(0x1000).a12_rise);
+
+  // A short dip, like between the background's pattern fetches, doesn't count
+  for _ in 0..A12_LOW_CYCLES - 1 {
+    filter.clock(0x2000);
+  }
+  assert!(!filter.clock(0x1000).a12_rise);
+}
+
+#[test]
+fn mmc3_counts_one_scanline_per_a12_rise_while_rendering() {
+  let mut nes = nes(0x18);
+  let before = nes.peek(0x10);
+  nes.run_frame();
+  // 241 rendered lines counting the pre-render one, with an IRQ every 11th
+  let irqs = nes.peek(0x10).wrapping_sub(before);
+  assert!((21..=22).contains(&irqs), "{irqs} IRQs in a frame");
+}
+
+#[test]
+fn mmc3_counter_stops_with_rendering_off() {
+  let mut nes = nes(0x00);
+  nes.run_frames(2);
+  assert_eq!(nes.peek(0x10), 0);
+}
+
@@ -0,0 +1,60 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+/// NROM past the PPU's warm up, with CHR RAM
+fn nes() -> Nes {
+  let mut nes = RomBuilder::new(0).chr_banks(0).prg(&SPIN).nes();
+  nes.run_frames(2);
+  nes
+}
+
+#[test]
+fn write_only_registers_read_back_the_last_write() {
+  let mut nes = nes();
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  let ppu = &mut nes.bus.ppu;
+  ppu.cpu_write(cartridge, 0x0003, 0xA5);
+  for register in [0x0000, 0x0001, 0x0003, 0x0005, 0x0006] {
+    assert_eq!(ppu.cpu_read(cartridge, register), 0xA5, "${:04X}", 0x2000 + register);
+  }
+}
+
+#[test]
+fn palette_reads_fill_the_buffer_from_the_nametable_underneath() {
+  let mut nes = nes();
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  let ppu = &mut nes.bus.ppu;
+  ppu.ppu_write(cartridge, 0x3F01, 0x21);
+  ppu.ppu_write(cartridge, 0x2FC1, 0x77);
+
+  // $3FC1 mirrors $3F01, and writing its low byte leaves $C1 on the open bus
+  ppu.cpu_write(cartridge, 0x0006, 0x3F);
+  ppu.cpu_write(cartridge, 0x0006, 0xC1);
+  // Palette reads aren't buffered, and only drive the bottom 6 bits
+  assert_eq!(ppu.cpu_read(cartridge, 0x0007), 0xC0 | 0x21);
+
+  // The next buffered read gets the nametable byte the palette read put in the buffer
+  ppu.cpu_write(cartridge, 0x0006, 0x20);
+  ppu.cpu_write(cartridge, 0x0006, 0x00);
+  assert_eq!(ppu.cpu_read(cartridge, 0x0007), 0x77);
+}
+
+#[test]
+fn open_bus_decays_once_it_is_left_alone() {
+  let mut nes = nes();
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  nes.bus.ppu.cpu_write(cartridge, 0x0002, 0xFF);
+  nes.run_frames(10);
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  assert_eq!(nes.bus.ppu.cpu_read(cartridge, 0x0000), 0xFF);
+
+  // Well over half a second later, every bit has gone
+  nes.run_frames(40);
+  let cartridge = nes.bus.cartridge.as_ref().unwrap();
+  assert_eq!(nes.bus.ppu.cpu_read(cartridge, 0x0000), 0x00);
+}
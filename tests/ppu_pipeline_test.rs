@@ -0,0 +1,102 @@
+//! The PPU draws pixels a run of dots at a time, catching up whenever the CPU touches a register.
+//! Watching the PPU bus makes it draw every dot as it happens instead, so these check both ways
+//! draw the same picture, with games poking the registers at every point of the frame.
+
+extern crate silknes_web;
+
+mod common;
+
+use proptest::prelude::*;
+
+use silknes_web::watchpoint::{AddressSpace, Watchpoint};
+
+use common::rom_builder::RomBuilder;
+
+/// One thing the game does each time round its loop
+#[derive(Clone, Debug)]
+enum Step {
+  /// Write a PPU register, $2000-$2007
+  Write(u8, u8),
+  /// Read $2002, storing it in zero page so when sprite 0 hits shows up in RAM
+  ReadStatus(u8),
+  /// Burn a few cycles, so the writes land all over the line
+  Wait(u8),
+}
+
+fn step() -> impl Strategy<Value = Step> {
+  prop_oneof![
+    (0..8u8, any::<u8>()).prop_map(|(register, value)| {
+      // Keeping NMIs off keeps the game in its loop
+      Step::Write(register, if register == 0 { value & 0x7F } else { value })
+    }),
+    any::<u8>().prop_map(Step::ReadStatus),
+    (0..40u8).prop_map(Step::Wait),
+  ]
+}
+
+/// NROM that waits out the PPU's warm up, fills OAM, the first nametable and the palette, switches
+/// rendering on and then loops through `steps` forever, with random CHR
+fn rom(oam: &[u8], nametable: &[u8], palette: &[u8], steps: &[Step], chr: &[u8]) -> RomBuilder {
+  let mut code = vec![
+    0x78,             // SEI
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL -5
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL -5
+  ];
+  let write = |code: &mut Vec<u8>, register: u8, value: u8| code.extend([0xA9, value, 0x8D, register, 0x20]);
+  for &byte in oam {
+    write(&mut code, 0x04, byte);
+  }
+  for (address, bytes) in [(0x2000u16, nametable), (0x3F00, palette)] {
+    write(&mut code, 0x06, (address >> 8) as u8);
+    write(&mut code, 0x06, address as u8);
+    for &byte in bytes {
+      write(&mut code, 0x07, byte);
+    }
+  }
+  let loop_start = 0xC000 + code.len() as u16;
+  write(&mut code, 0x01, 0x1E);
+  for step in steps {
+    match *step {
+      Step::Write(register, value) => write(&mut code, register, value),
+      Step::ReadStatus(address) => code.extend([0xAD, 0x02, 0x20, 0x85, address]),
+      Step::Wait(nops) => code.extend(std::iter::repeat_n(0xEA, nops as usize)),
+    }
+  }
+  code.extend([0x4C, loop_start as u8, (loop_start >> 8) as u8]);
+  // The code runs on past where the interrupt handlers would go, and with NMIs kept off and IRQs
+  // disabled it doesn't need them
+  RomBuilder::new(0).prg(&code).nmi(&[]).irq(&[]).chr(chr)
+}
+
+/// A watchpoint that never goes off, but still makes the PPU draw every dot as it happens
+fn watch_nothing() -> Watchpoint {
+  Watchpoint { space: AddressSpace::Ppu, start: 0, end: 0, read: false, write: false, execute: false }
+}
+
+proptest! {
+  // Each case runs whole frames twice over, so there are fewer of them
+  #![proptest_config(ProptestConfig::with_cases(8))]
+
+  #[test]
+  fn drawing_in_runs_matches_drawing_every_dot(
+    oam in proptest::collection::vec(any::<u8>(), 256),
+    nametable in proptest::collection::vec(any::<u8>(), 0x400),
+    palette in proptest::collection::vec(any::<u8>(), 32),
+    steps in proptest::collection::vec(step(), 0..200),
+    chr in proptest::collection::vec(any::<u8>(), 0x2000),
+  ) {
+    let rom = rom(&oam, &nametable, &palette, &steps, &chr);
+    let mut in_runs = rom.nes();
+    let mut every_dot = rom.nes();
+    every_dot.bus.ppu.set_watchpoints(vec![watch_nothing()]);
+
+    for frame in 1..=3 {
+      in_runs.run_frame();
+      every_dot.run_frame();
+      prop_assert!(in_runs.screen() == every_dot.screen(), "frame {} was drawn differently", frame);
+      prop_assert_eq!(in_runs.bus.ram_mut(), every_dot.bus.ram_mut());
+    }
+  }
+}
@@ -0,0 +1,82 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::condition::Comparison;
+use silknes_web::nes::Nes;
+use silknes_web::practice::{thumbnail, DeathCondition, Practice, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH};
+use silknes_web::video::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use common::rom_builder::{RomBuilder, SPIN_WITH_NMI};
+
+/// NROM whose NMI handler counts frames down in $10, like a timer running out
+fn nes() -> Nes {
+  let rom = RomBuilder::new(0)
+    .prg(&SPIN_WITH_NMI)
+    .nmi(&[
+      0xC6, 0x10, // DEC $10
+      0x40,       // RTI
+    ]);
+  let mut nes = rom.nes();
+  nes.run_frames(2);
+  nes
+}
+
+#[test]
+fn thumbnails_average_each_block_of_four_pixels() {
+  let mut screen = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 3];
+  // Top left block is half white, the block to its right is solid red
+  screen[..3].copy_from_slice(&[255, 255, 255]);
+  screen[SCREEN_WIDTH * 3..SCREEN_WIDTH * 3 + 3].copy_from_slice(&[255, 255, 255]);
+  for pixel in [2, 3, SCREEN_WIDTH + 2, SCREEN_WIDTH + 3] {
+    screen[pixel * 3] = 200;
+  }
+
+  let thumbnail = thumbnail(&screen);
+  assert_eq!(thumbnail.len(), THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT * 3);
+  assert_eq!(thumbnail[..6], [127, 127, 127, 200, 0, 0]);
+}
+
+#[test]
+fn saved_slots_keep_their_frame_until_cleared() {
+  let nes = nes();
+  let mut practice = Practice::default();
+  assert!(practice.slot(3).is_none());
+
+  practice.saved(3, nes.frame_count(), nes.screen());
+  let (frame, thumbnail) = practice.slot(3).unwrap();
+  assert_eq!(frame, nes.frame_count());
+  assert_eq!(thumbnail.len(), THUMBNAIL_WIDTH * THUMBNAIL_HEIGHT * 3);
+
+  practice.clear();
+  assert!(practice.slot(3).is_none());
+}
+
+#[test]
+fn deaths_only_count_in_practice_mode() {
+  let mut nes = nes();
+  let mut practice = Practice::default();
+  let condition = DeathCondition { address: 0x10, comparison: Comparison::Decreased, value: 0 };
+  practice.set_death_condition(&mut nes, Some(condition));
+  assert_eq!(practice.death_condition(), Some(condition));
+
+  nes.run_frame();
+  assert!(!practice.died(&nes.take_condition_events()));
+
+  practice.enabled = true;
+  nes.run_frame();
+  assert!(practice.died(&nes.take_condition_events()));
+}
+
+#[test]
+fn clearing_the_death_condition_stops_watching_memory() {
+  let mut nes = nes();
+  let mut practice = Practice::default();
+  practice.enabled = true;
+  practice.set_death_condition(&mut nes, Some(DeathCondition { address: 0x10, comparison: Comparison::Changed, value: 0 }));
+  practice.set_death_condition(&mut nes, None);
+
+  nes.run_frames(3);
+  assert!(nes.take_condition_events().is_empty());
+  assert_eq!(practice.death_condition(), None);
+}
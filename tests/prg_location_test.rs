@@ -0,0 +1,39 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::mapper::{Mapper, PrgLocation};
+use silknes_web::mappers::mapper9::Mapper9;
+
+use common::rom_builder::RomBuilder;
+
+#[test]
+fn reports_switched_and_fixed_banks() {
+  let mut cartridge = RomBuilder::new(2).prg_banks(4).chr_banks(0).cartridge();
+  cartridge.cpu_write(0x8000, 2);
+
+  assert_eq!(cartridge.mapper.prg_location(0x9ABC), Some(PrgLocation { bank: 2, offset: 0x1ABC }));
+  assert_eq!(cartridge.mapper.prg_location(0xC123), Some(PrgLocation { bank: 3, offset: 0x0123 }));
+  assert_eq!(cartridge.mapper.prg_location(0x9ABC).unwrap().to_string(), "PRG bank 2 + $1ABC");
+}
+
+#[test]
+fn ram_and_registers_have_no_prg_location() {
+  let cartridge = RomBuilder::new(2).prg_banks(4).chr_banks(0).cartridge();
+
+  assert_eq!(cartridge.mapper.prg_location(0x0000), None);
+  assert_eq!(cartridge.mapper.prg_location(0x6000), None);
+}
+
+#[test]
+fn mmc2_fixes_the_last_three_8kb_banks() {
+  // 8 16 KB banks, as Punch-Out!! has, are 16 8 KB ones
+  let mapper = Mapper9::new(8, 16);
+  assert_eq!(mapper.get_mapped_address_cpu(0xA000), 13 * 0x2000);
+  assert_eq!(mapper.get_mapped_address_cpu(0xC000), 14 * 0x2000);
+  assert_eq!(mapper.get_mapped_address_cpu(0xFFFF), 16 * 0x2000 - 1);
+  // With only 2 8 KB banks they count round from the start again, rather than before it
+  let mapper = Mapper9::new(1, 1);
+  assert_eq!(mapper.get_mapped_address_cpu(0xA000), 0x2000);
+  assert_eq!(mapper.get_mapped_address_cpu(0xC000), 0x0000);
+}
@@ -0,0 +1,79 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::BusLike;
+use silknes_web::cartridge::Cartridge;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::RomBuilder;
+
+/// Load an MMC1 register a bit at a time
+fn write_mmc1(cartridge: &mut Cartridge, address: u16, value: u8) {
+  for bit in 0..5 {
+    cartridge.cpu_write(address, (value >> bit) & 1);
+  }
+}
+
+#[test]
+fn mmc1_can_switch_prg_ram_off() {
+  let mut cartridge = RomBuilder::new(1).prg_banks(8).battery().cartridge();
+  cartridge.cpu_write(0x6000, 0x42);
+  assert_eq!(cartridge.cpu_read(0x6000), Some(0x42));
+
+  write_mmc1(&mut cartridge, 0xE000, 0x10);
+  assert_eq!(cartridge.cpu_read(0x6000), None);
+  cartridge.cpu_write(0x6000, 0x24);
+
+  write_mmc1(&mut cartridge, 0xE000, 0x00);
+  assert_eq!(cartridge.cpu_read(0x6000), Some(0x42));
+}
+
+#[test]
+fn mmc3_can_disable_and_write_protect_prg_ram() {
+  let mut cartridge = RomBuilder::new(4).battery().submapper(0).cartridge();
+  cartridge.cpu_write(0x6000, 0x42);
+  assert_eq!(cartridge.cpu_read(0x6000), Some(0x42));
+
+  // Protected, so it reads but ignores writes
+  cartridge.cpu_write(0xA001, 0xC0);
+  cartridge.cpu_write(0x6000, 0x24);
+  assert_eq!(cartridge.cpu_read(0x6000), Some(0x42));
+
+  cartridge.cpu_write(0xA001, 0x00);
+  assert_eq!(cartridge.cpu_read(0x6000), None);
+
+  cartridge.cpu_write(0xA001, 0x80);
+  cartridge.cpu_write(0x6000, 0x24);
+  assert_eq!(cartridge.cpu_read(0x6000), Some(0x24));
+}
+
+#[test]
+fn mmc6_and_ines_mmc3_boards_ignore_the_mmc3_protect_bits() {
+  // StarTropics' MMC6 turns its RAM on through $A001 with bit 7 clear, which on an MMC3 would disconnect it.
+  // iNES headers can't tell the two apart, so neither can switch RAM off that way.
+  for mut cartridge in [RomBuilder::new(4).battery().submapper(1).cartridge(), RomBuilder::new(4).battery().cartridge()] {
+    cartridge.cpu_write(0xA001, 0x30);
+    cartridge.cpu_write(0x6000, 0x42);
+    assert_eq!(cartridge.cpu_read(0x6000), Some(0x42));
+    cartridge.cpu_write(0xA001, 0xC0);
+    cartridge.cpu_write(0x6000, 0x24);
+    assert_eq!(cartridge.cpu_read(0x6000), Some(0x24));
+  }
+}
+
+#[test]
+fn missing_prg_ram_reads_open_bus() {
+  let cartridge = RomBuilder::new(0).cartridge();
+  assert_eq!(cartridge.cpu_read(0x6000), None);
+  assert_eq!(cartridge.cpu_read(0x7FFF), None);
+
+  let mut nes = Nes::new();
+  nes.insert_cartridge(cartridge);
+  // Nothing answers, so the read sees whatever was last on the bus
+  nes.bus.cpu_write(0x0000, 0x5A);
+  assert_eq!(nes.bus.cpu_read(0x0000), 0x5A);
+  assert_eq!(nes.bus.cpu_read(0x6000), 0x5A);
+  nes.bus.cpu_write(0x6000, 0x55);
+  assert_eq!(nes.bus.peek(0x6000), nes.bus.cpu_read(0x6000));
+}
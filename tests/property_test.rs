@@ -0,0 +1,78 @@
+//! Random input for the ROM parser and the CPU, which shouldn't panic or hang whatever they're given.
+//! ROMs come from anywhere, and in the browser a panic takes the whole page down. fuzz/ has the same
+//! checks as cargo-fuzz targets, for running far longer than the tests do.
+
+extern crate silknes_web;
+
+use proptest::prelude::*;
+
+use silknes_web::bus::MockBus;
+use silknes_web::cartridge::Cartridge;
+use silknes_web::cpu::NES6502;
+use silknes_web::nes::{Nes, CYCLES_PER_FRAME};
+
+/// The most cycles an instruction or interrupt takes: 7, plus one for crossing a page
+const MAX_INSTRUCTION_CYCLES: u32 = 8;
+
+/// Bytes with a valid iNES header in front, claiming 1 or 2 PRG banks and up to 1 CHR bank, with
+/// random flags and exactly as much random ROM as it claims
+fn rom() -> impl Strategy<Value = Vec<u8>> {
+  (1..=2u8, 0..=1u8, any::<[u8; 10]>()).prop_flat_map(|(prg_banks, chr_banks, flags)| {
+    let mut header = vec![0x4E, 0x45, 0x53, 0x1A, prg_banks, chr_banks];
+    header.extend(flags);
+    // No trainer, so the ROM's the size the banks say
+    header[6] &= !0x04;
+    let size = prg_banks as usize * 0x4000 + chr_banks as usize * 0x2000;
+    proptest::collection::vec(any::<u8>(), size).prop_map(move |body| [header.clone(), body].concat())
+  })
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(256))]
+
+  #[test]
+  fn any_bytes_load_or_fail_cleanly(bytes in proptest::collection::vec(any::<u8>(), 0..0x100)) {
+    let _ = Cartridge::from_bytes(bytes);
+  }
+
+  #[test]
+  fn any_header_loads_or_fails_cleanly(header in any::<[u8; 12]>(), body in proptest::collection::vec(any::<u8>(), 0..0x8000)) {
+    let bytes = [&[0x4E, 0x45, 0x53, 0x1A][..], &header, &body].concat();
+    let _ = Cartridge::from_bytes(bytes);
+  }
+
+  #[test]
+  fn instructions_take_a_bounded_number_of_cycles(memory in proptest::collection::vec(any::<u8>(), 0x10000)) {
+    let mut bus = MockBus::new();
+    bus.cpu_ram.copy_from_slice(&memory);
+    let mut cpu = NES6502::new();
+    cpu.pc = u16::from_le_bytes([memory[0xFFFC], memory[0xFFFD]]);
+    for _ in 0..1000 {
+      cpu.step(&mut bus);
+      let mut cycles = 1;
+      while cpu.cycles > 0 {
+        cpu.step(&mut bus);
+        cycles += 1;
+        prop_assert!(cycles <= MAX_INSTRUCTION_CYCLES, "an instruction at ${:04X} took over {} cycles", cpu.pc, MAX_INSTRUCTION_CYCLES);
+      }
+    }
+  }
+}
+
+proptest! {
+  // Each case runs whole frames, so there are fewer of them
+  #![proptest_config(ProptestConfig::with_cases(32))]
+
+  #[test]
+  fn random_games_run_without_panicking(rom in rom()) {
+    let Ok(cartridge) = Cartridge::from_bytes(rom) else {
+      return Ok(());
+    };
+    let mut nes = Nes::new();
+    nes.insert_cartridge(cartridge);
+    nes.run_frames(2);
+    // Frames always end, whatever the CPU's up to
+    prop_assert_eq!(nes.frame_count(), 2);
+    nes.run_cycles(CYCLES_PER_FRAME);
+  }
+}
@@ -0,0 +1,85 @@
+extern crate silknes_web;
+
+use silknes_web::apu::{AudioChannel, APU};
+
+const PERIOD: u16 = 8;
+
+/// Pulse 1 playing `duty` at constant volume 15 with the length counter halted, and how many CPU cycles
+/// it's been run for
+fn pulse1(duty: u8) -> (APU, u32) {
+  let mut apu = APU::new();
+  apu.cpu_write(0x4015, 0x01);
+  apu.cpu_write(0x4000, duty << 6 | 0x3F);
+  apu.cpu_write(0x4002, PERIOD as u8);
+  apu.cpu_write(0x4003, 0x08);
+  (apu, 0)
+}
+
+/// Pulse 1's level going into the mixer, from 0 to 15
+fn level(apu: &mut APU) -> u8 {
+  apu.update_output(0.0);
+  (apu.channel_levels()[AudioChannel::Pulse1 as usize] * 15.0).round() as u8
+}
+
+/// Run until the sequencer's next step, which comes every `PERIOD + 1` APU cycles
+fn next_step(apu: &mut APU, cycles: &mut u32) {
+  let first = *cycles == 0;
+  let until = if first { 1 } else { *cycles + 2 * (PERIOD as u32 + 1) };
+  while *cycles < until {
+    apu.step(*cycles, None);
+    *cycles += 1;
+  }
+}
+
+/// The level at each of the next 8 steps, starting with the one it's on
+fn waveform(apu: &mut APU, cycles: &mut u32) -> Vec<u8> {
+  let mut levels = vec![level(apu)];
+  for _ in 0..7 {
+    next_step(apu, cycles);
+    levels.push(level(apu));
+  }
+  levels
+}
+
+#[test]
+fn duty_cycles_play_out_as_on_hardware_after_a_restart() {
+  let expected = [
+    [0, 0, 0, 0, 0, 0, 0, 15],
+    [0, 0, 0, 0, 0, 0, 15, 15],
+    [0, 0, 0, 0, 15, 15, 15, 15],
+    [15, 15, 15, 15, 15, 15, 0, 0],
+  ];
+  for (duty, expected) in expected.iter().enumerate() {
+    let (mut apu, mut cycles) = pulse1(duty as u8);
+    assert_eq!(waveform(&mut apu, &mut cycles), expected, "duty {duty}");
+  }
+}
+
+#[test]
+fn rewriting_the_period_mid_note_restarts_the_duty_cycle_unless_reducing_popping() {
+  for reduce_popping in [false, true] {
+    let (mut apu, mut cycles) = pulse1(2);
+    apu.reduce_pulse_popping = reduce_popping;
+    for _ in 0..5 {
+      next_step(&mut apu, &mut cycles);
+    }
+    // Halfway through the high half, where a restart drops it back to the low half
+    assert_eq!(level(&mut apu), 15);
+    apu.cpu_write(0x4003, 0x08);
+    assert_eq!(level(&mut apu), if reduce_popping { 15 } else { 0 }, "reduce popping {reduce_popping}");
+  }
+}
+
+#[test]
+fn a_new_note_always_starts_the_duty_cycle_from_the_beginning() {
+  let (mut apu, mut cycles) = pulse1(2);
+  apu.reduce_pulse_popping = true;
+  for _ in 0..5 {
+    next_step(&mut apu, &mut cycles);
+  }
+  // Silenced by switching the channel off, then started again
+  apu.cpu_write(0x4015, 0x00);
+  apu.cpu_write(0x4015, 0x01);
+  apu.cpu_write(0x4003, 0x08);
+  assert_eq!(waveform(&mut apu, &mut cycles), [0, 0, 0, 0, 15, 15, 15, 15]);
+}
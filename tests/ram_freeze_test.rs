@@ -0,0 +1,50 @@
+extern crate silknes_web;
+
+mod common;
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: bump a counter forever
+const PROGRAM: [u8; 6] = [
+  0x78,             // SEI
+  // loop:
+  0xE6, 0x00,       // INC $00
+  0x4C, 0x01, 0xC0, // JMP loop
+];
+
+#[test]
+fn poke_writes_ram() {
+  let mut nes = RomBuilder::new(0).prg(&PROGRAM).nes();
+  nes.poke(0x0010, 0x42);
+  assert_eq!(nes.peek(0x0010), 0x42);
+  // RAM is mirrored every 2 KB
+  assert_eq!(nes.peek(0x0810), 0x42);
+}
+
+#[test]
+fn frozen_addresses_are_written_back_every_frame() {
+  let mut nes = RomBuilder::new(0).prg(&PROGRAM).nes();
+  nes.freeze(0x0000, 0x05);
+
+  for _ in 0..3 {
+    nes.run_frame();
+    assert_eq!(nes.peek(0x0000), 0x05);
+  }
+
+  // Partway into the next frame the counter has moved on, and nothing puts it back
+  nes.unfreeze(0x0000);
+  nes.run_cycles(3000);
+  assert_ne!(nes.peek(0x0000), 0x05);
+}
+
+#[test]
+fn loading_a_state_keeps_frozen_addresses() {
+  let mut nes = RomBuilder::new(0).prg(&PROGRAM).nes();
+  let state = nes.save_state();
+  nes.freeze(0x0000, 0x05);
+  nes.load_state(&state);
+
+  assert!(nes.is_frozen(0x0000));
+  nes.run_frame();
+  assert_eq!(nes.peek(0x0000), 0x05);
+}
@@ -0,0 +1,132 @@
+extern crate silknes_web;
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use silknes_web::movie::{Movie, MovieFrame};
+use silknes_web::regression::{self, Check, Verdict};
+
+use common::rom_builder::RomBuilder;
+
+/// NROM that reads controller 1 into $10 every frame, and ORs everything it's read since power on or
+/// reset into $11
+fn pad_rom() -> Vec<u8> {
+  RomBuilder::new(0).prg_banks(1).prg(&[
+    0x78,             // SEI
+    0xD8,             // CLD
+    0xA9, 0x00,       // LDA #$00
+    0x85, 0x11,       // STA $11
+    0x85, 0x12,       // STA $12
+    // wait:
+    0x2C, 0x02, 0x20, // BIT $2002
+    0x10, 0xFB,       // BPL wait
+    0xA9, 0x01,       // LDA #$01
+    0x8D, 0x16, 0x40, // STA $4016
+    0xA9, 0x00,       // LDA #$00
+    0x8D, 0x16, 0x40, // STA $4016
+    0xA2, 0x08,       // LDX #$08
+    // read:
+    0xAD, 0x16, 0x40, // LDA $4016
+    0x4A,             // LSR A
+    0x26, 0x10,       // ROL $10
+    0xCA,             // DEX
+    0xD0, 0xF7,       // BNE read
+    0xA5, 0x10,       // LDA $10
+    0x05, 0x11,       // ORA $11
+    0x85, 0x11,       // STA $11
+    0xE6, 0x12,       // INC $12
+    0x4C, 0x08, 0xC0, // JMP wait
+  ]).build()
+}
+
+/// An FM2 movie holding `held` on controller 1 for `frames` frames each, with `commands` on the first
+/// frame of each run
+fn fm2(runs: &[(u32, &str, usize)]) -> String {
+  let mut movie = "version 3\nemuVersion 22020\nport0 1\nport1 1\nport2 0\n".to_string();
+  for (commands, held, frames) in runs {
+    for frame in 0..*frames {
+      movie += &format!("|{}|{}|........||\n", if frame == 0 { *commands } else { 0 }, held);
+    }
+  }
+  movie
+}
+
+/// A fresh folder holding the pad ROM and a couple of movies for it
+fn pack(name: &str) -> PathBuf {
+  let folder = std::env::temp_dir().join(format!("silknes-regression-{}-{}", name, std::process::id()));
+  let _ = std::fs::remove_dir_all(&folder);
+  std::fs::create_dir_all(folder.join("movies")).unwrap();
+  std::fs::write(folder.join("pad.nes"), pad_rom()).unwrap();
+  std::fs::write(folder.join("movies/a-then-right.fm2"), fm2(&[(0, "........", 5), (0, ".......A", 10), (0, "R.......", 10)])).unwrap();
+  // Holding A, then reset, which clears what the ROM's seen so far, then Right
+  std::fs::write(folder.join("movies/reset.fm2"), fm2(&[(0, ".......A", 10), (1, "R.......", 10)])).unwrap();
+  folder
+}
+
+#[test]
+fn fm2_frames_give_each_controllers_buttons_and_resets() {
+  let movie = Movie::from_fm2("version 3\nport0 1\n|0|R..U...A|.L....B.||\n|1|........|........||\n|2|||\n").unwrap();
+  assert_eq!(movie.frames, [
+    MovieFrame { controllers: [0x89, 0x42], reset: false, power: false },
+    MovieFrame { controllers: [0, 0], reset: true, power: false },
+    MovieFrame { controllers: [0, 0], reset: false, power: true },
+  ]);
+
+  assert!(Movie::from_fm2("|0|RLDU|........||").unwrap_err().starts_with("Line 1"));
+  assert!(Movie::from_fm2("version 3\nbinary 1\n").is_err());
+}
+
+#[test]
+fn manifests_list_cases_relative_to_themselves() {
+  let base = Path::new("/packs");
+  let manifest = "# ROM, movie, checks\n\nsmb.nes runs/warp.fm2 $075F=03 hash=00000000000000FF\nnestest.nes - frames=30 $0002=00\n";
+  let cases = regression::parse_manifest(manifest, base).unwrap();
+  assert_eq!(cases.len(), 2);
+  assert_eq!(cases[0].name, "smb.nes runs/warp.fm2");
+  assert_eq!(cases[0].rom, base.join("smb.nes"));
+  assert_eq!(cases[0].movie, Some(base.join("runs/warp.fm2")));
+  assert_eq!(cases[0].frames, None);
+  assert_eq!(cases[0].checks, [Check::Ram(0x075F, 0x03), Check::FrameHash(0xFF)]);
+  assert_eq!(cases[1].movie, None);
+  assert_eq!(cases[1].frames, Some(30));
+
+  for (manifest, error) in [
+    ("smb.nes", "Line 1: expected"),
+    ("\nsmb.nes warp.fm2", "Line 2: smb.nes warp.fm2 doesn't check"),
+    ("smb.nes - $0000=00", "Line 1: smb.nes - needs frames="),
+    ("smb.nes warp.fm2 $75F=300", "Line 1: can't make sense of \"$75F=300\""),
+  ] {
+    let result = regression::parse_manifest(manifest, base);
+    assert!(result.as_ref().is_err_and(|message| message.starts_with(error)), "{:?} gave {:?}", manifest, result);
+  }
+}
+
+#[test]
+fn a_pack_runs_every_case_and_reports_them_in_order() {
+  let folder = pack("run");
+  let manifest = folder.join("pack.txt");
+  std::fs::write(&manifest, [
+    "pad.nes movies/a-then-right.fm2 $0010=01 $0011=81",
+    "pad.nes movies/reset.fm2 $0011=01",
+    "pad.nes movies/a-then-right.fm2 frames=5 $0011=00 hash=0123456789abcdef",
+    "missing.nes - frames=10 $0000=00",
+  ].join("\n")).unwrap();
+
+  let reports = regression::run_all(&regression::load_manifest(&manifest).unwrap());
+  let verdicts: Vec<_> = reports.iter().map(|report| (report.name.as_str(), &report.verdict, report.frames)).collect();
+  assert_eq!(verdicts[0], ("pad.nes movies/a-then-right.fm2", &Verdict::Passed, 25));
+  assert_eq!(verdicts[1], ("pad.nes movies/reset.fm2", &Verdict::Passed, 20));
+
+  // Stopped before A was pressed, so only the hash is wrong
+  let Verdict::Failed(mismatches) = verdicts[2].1 else {
+    panic!("expected a failure, got {:?}", verdicts[2]);
+  };
+  assert_eq!(mismatches.len(), 1);
+  assert!(mismatches[0].starts_with("frame hash is "), "{}", mismatches[0]);
+  assert!(mismatches[0].ends_with(", expected 0123456789abcdef"), "{}", mismatches[0]);
+  assert!(reports[2].to_string().starts_with("FAIL  pad.nes movies/a-then-right.fm2 after 5 frames: frame hash is"));
+
+  assert!(matches!(verdicts[3].1, Verdict::Error(error) if error.contains("missing.nes")));
+  assert_eq!(reports.iter().filter(|report| report.passed()).count(), 2);
+}
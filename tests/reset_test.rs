@@ -0,0 +1,130 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::BusLike;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{RomBuilder, SPIN};
+
+/// Switches in 16 KB bank 1, which is 8 KB pages 2 and 3, starts the noise channel and spins
+const PROGRAM: [u8; 20] = [
+  0x78,             // SEI
+  0xA2, 0xFF,       // LDX #$FF
+  0x9A,             // TXS
+  0xA9, 0x01,       // LDA #$01
+  0x8D, 0x00, 0x80, // STA $8000
+  0xA9, 0x08,       // LDA #$08
+  0x8D, 0x15, 0x40, // STA $4015
+  0x8D, 0x0F, 0x40, // STA $400F
+  // loop:
+  0x4C, 0x11, 0xC0, // JMP loop
+];
+
+/// UxROM with four 16 KB banks and CHR RAM, running the program above
+fn test_rom() -> RomBuilder {
+  RomBuilder::new(2).prg_banks(4).chr_banks(0).prg(&PROGRAM)
+}
+
+fn started() -> Nes {
+  let mut nes = test_rom().nes();
+  nes.run_frame();
+  nes
+}
+
+#[test]
+fn reset_keeps_ram_and_the_mapper() {
+  let mut nes = started();
+  nes.bus.cpu_write(0x0300, 0x42);
+  nes.run_frame();
+  nes.reset();
+  assert_eq!(nes.peek(0x0300), 0x42);
+  assert_eq!(nes.peek(0x8000), 2);
+  assert_eq!(nes.cpu.pc, 0xC000);
+  // The stack pointer moves down as if an interrupt had been taken, and interrupts are disabled
+  assert_eq!(nes.cpu.sp, 0xFC);
+  assert!(nes.cpu.flags.interrupt_disable);
+}
+
+#[test]
+fn reset_silences_the_apu() {
+  let mut nes = started();
+  assert_eq!(nes.bus.cpu_read(0x4015) & 0x08, 0x08);
+  nes.reset();
+  assert_eq!(nes.bus.cpu_read(0x4015) & 0x08, 0);
+}
+
+#[test]
+fn power_cycling_starts_everything_from_scratch() {
+  let mut nes = started();
+  nes.bus.cpu_write(0x0300, 0x42);
+  nes.run_frame();
+  nes.power_on();
+  assert_eq!(nes.frame_count(), 0);
+  assert_eq!(nes.peek(0x8000), 0);
+  assert_eq!(nes.cpu.sp, 0xFD);
+  assert_eq!(nes.bus.cpu_read(0x4015) & 0x08, 0);
+  // RAM comes up in the usual power on pattern rather than keeping what the game wrote
+  let ram: Vec<u8> = (0x0300..0x0308).map(|address| nes.peek(address)).collect();
+  assert_eq!(ram, [0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn power_cycling_clears_prg_ram_without_a_battery() {
+  // A trainer's the one thing that gives a cartridge PRG RAM without a battery
+  let mut nes = Nes::new();
+  nes.insert_cartridge(RomBuilder::new(0).trainer(&[0x42]).prg(&SPIN).cartridge());
+  nes.bus.cpu_write(0x6000, 0x24);
+  assert_eq!(nes.peek(0x6000), 0x24);
+  nes.power_on();
+  assert_eq!(nes.peek(0x6000), 0x00);
+  // The trainer's loaded again, as the copier would
+  assert_eq!(nes.peek(0x7000), 0x42);
+}
+
+#[test]
+fn power_cycling_keeps_battery_backed_prg_ram() {
+  let mut nes = Nes::new();
+  nes.insert_cartridge(RomBuilder::new(0).battery().prg(&SPIN).cartridge());
+  nes.bus.cpu_write(0x6000, 0x24);
+  nes.power_on();
+  assert_eq!(nes.peek(0x6000), 0x24);
+}
+
+#[test]
+fn loading_a_rom_powers_the_console_on() {
+  let mut nes = started();
+  nes.bus.cpu_write(0x0300, 0x42);
+  nes.insert_cartridge(test_rom().cartridge());
+  assert_eq!(nes.peek(0x0300), 0x00);
+  assert_eq!(nes.bus.cpu_read(0x4015) & 0x08, 0);
+}
+
+/// Point PPUADDR at the first palette entry
+fn select_palette(nes: &mut Nes) {
+  nes.bus.cpu_write(0x2006, 0x3F);
+  nes.bus.cpu_write(0x2006, 0x00);
+}
+
+/// Write the first palette entry through PPUADDR and PPUDATA, then read it back the same way
+fn write_and_read_palette(nes: &mut Nes, value: u8) -> u8 {
+  select_palette(nes);
+  nes.bus.cpu_write(0x2007, value);
+  select_palette(nes);
+  nes.bus.cpu_read(0x2007) & 0x3F
+}
+
+#[test]
+fn the_ppu_ignores_its_address_registers_while_warming_up() {
+  let mut nes = test_rom().nes();
+  assert_ne!(write_and_read_palette(&mut nes, 0x21), 0x21);
+  nes.run_frame();
+  assert_eq!(write_and_read_palette(&mut nes, 0x21), 0x21);
+
+  // Resetting warms it up again, but palette RAM keeps its contents
+  nes.reset();
+  assert_ne!(write_and_read_palette(&mut nes, 0x12), 0x12);
+  nes.run_frame();
+  select_palette(&mut nes);
+  assert_eq!(nes.bus.cpu_read(0x2007) & 0x3F, 0x21);
+}
@@ -0,0 +1,96 @@
+extern crate silknes_web;
+
+use silknes_web::cartridge::Cartridge;
+use silknes_web::rom_database::{crc32, HeaderFix, RomDatabase};
+
+/// The header the database says the game should have: MMC1, vertical mirroring and a battery, in NES 2.0
+/// with 8 KB of PRG NVRAM
+const GOOD_HEADER: [u8; 16] = [0x4E, 0x45, 0x53, 0x1A, 2, 1, 0x13, 0x08, 0, 0, 0x70, 0, 0, 0, 0, 1];
+
+/// The same game as a bad dump had it, claiming NROM with horizontal mirroring and no battery
+const BAD_HEADER: [u8; 16] = [0x4E, 0x45, 0x53, 0x1A, 2, 1, 0x00, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+
+fn rom(header: [u8; 16]) -> Vec<u8> {
+  let mut rom = header.to_vec();
+  rom.extend((0..0x8000 + 0x2000).map(|i| (i * 31 % 251) as u8));
+  rom
+}
+
+fn hex(header: &[u8]) -> String {
+  header.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+/// A dat with the game in it, alongside one the same size that it isn't
+fn database() -> RomDatabase {
+  let good = rom(GOOD_HEADER);
+  let mut other = rom(GOOD_HEADER);
+  other[100] ^= 0xFF;
+  let xml = format!(r#"<?xml version="1.0"?>
+<datafile>
+  <game name="Other Game (USA)"><rom name="Other Game (USA).nes" size="{}" crc="{:08x}" header="{}"/></game>
+  <game name="Test Game (USA)"><rom name="Test Game (USA).nes" size="{}" crc="{:08x}" sha256="{}" header="{}"/></game>
+  <game name="No Header (USA)"><rom name="No Header (USA).nes" size="{}" crc="{:08x}"/></game>
+</datafile>"#,
+    other.len(), crc32(&other), hex(&[0x4E, 0x45, 0x53, 0x1A, 2, 1, 0x40, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+    good.len(), crc32(&good), sha256::digest(good.as_slice()), hex(&GOOD_HEADER),
+    good.len(), crc32(&rom(BAD_HEADER)) ^ 1);
+  RomDatabase::parse(&xml).unwrap()
+}
+
+#[test]
+fn crc32_matches_the_standard_check_value() {
+  assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+  assert_eq!(crc32(&[]), 0);
+}
+
+#[test]
+fn bad_header_is_replaced_with_the_known_one() {
+  let mut bytes = rom(BAD_HEADER);
+  let fix = database().fix_header(&mut bytes);
+  assert_eq!(fix, Some(HeaderFix {
+    name: "Test Game (USA)".to_string(),
+    changes: vec![
+      "mapper 0 → 1".to_string(),
+      "horizontal → vertical mirroring".to_string(),
+      "battery added".to_string(),
+      "8 KB PRG RAM".to_string(),
+    ],
+  }));
+  assert_eq!(bytes, rom(GOOD_HEADER));
+
+  // The cartridge sees the fixed header
+  let cartridge = Cartridge::from_bytes(bytes).unwrap();
+  assert_eq!(cartridge.mapper_id, 1);
+  assert!(cartridge.has_ram);
+}
+
+#[test]
+fn good_and_unknown_dumps_are_left_alone() {
+  let database = database();
+  let mut good = rom(GOOD_HEADER);
+  assert_eq!(database.fix_header(&mut good), None);
+  assert_eq!(good, rom(GOOD_HEADER));
+
+  let mut unknown = rom(BAD_HEADER);
+  unknown[200] ^= 0xFF;
+  assert_eq!(database.fix_header(&mut unknown), None);
+  let mut too_short = vec![0x4E, 0x45];
+  assert_eq!(database.fix_header(&mut too_short), None);
+}
+
+#[test]
+fn dumps_are_named_by_their_hash() {
+  let database = database();
+  assert_eq!(database.len(), 3);
+  let hash = sha256::digest(rom(GOOD_HEADER).as_slice());
+  assert_eq!(database.find_sha256(&hash).map(|entry| entry.name.as_str()), Some("Test Game (USA)"));
+  assert!(database.find_sha256("00").is_none());
+}
+
+#[test]
+fn bundled_dat_loads() {
+  let path = concat!(env!("CARGO_MANIFEST_DIR"), "/res/Nintendo - Nintendo Entertainment System (Headered) (20240606-224704).dat");
+  let database = RomDatabase::load(path).unwrap();
+  assert_eq!(database.len(), 3368);
+  assert!(RomDatabase::parse("<datafile>").is_err());
+}
@@ -0,0 +1,112 @@
+//! Runs the blargg test suites and checks they pass. The ROMs aren't distributed with the emulator, so
+//! put them in roms/test/blargg to run these; any that are missing are skipped.
+
+extern crate silknes_web;
+
+mod common;
+
+use std::path::PathBuf;
+
+use silknes_web::cartridge::Cartridge;
+use silknes_web::nes::Nes;
+use silknes_web::test_rom::{self, TestStatus};
+
+use common::rom_builder::RomBuilder;
+
+fn rom_path(name: &str) -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("roms/test/blargg").join(name)
+}
+
+/// Run a suite's ROM for up to `max_frames`, failing with whatever it printed if it doesn't pass
+fn run_suite(name: &str, max_frames: u64) {
+  let path = rom_path(name);
+  let Ok(rom) = std::fs::read(&path) else {
+    eprintln!("skipping {}, {} isn't there", name, path.display());
+    return;
+  };
+  let mut nes = Nes::new();
+  nes.insert_cartridge(Cartridge::from_bytes(rom).unwrap());
+  let outcome = test_rom::run(&mut nes, max_frames);
+  assert!(outcome.passed(), "{} gave {:?} after {} frames:\n{}", name, outcome.result, outcome.frames, outcome.text);
+}
+
+#[test]
+fn cpu_instrs() {
+  run_suite("cpu_instrs.nes", 4000);
+}
+
+#[test]
+fn instr_timing() {
+  run_suite("instr_timing.nes", 2000);
+}
+
+#[test]
+fn ppu_vbl_nmi() {
+  run_suite("ppu_vbl_nmi.nes", 2000);
+}
+
+#[test]
+fn ppu_sprite_hit() {
+  run_suite("ppu_sprite_hit.nes", 1000);
+}
+
+#[test]
+fn apu_test() {
+  run_suite("apu_test.nes", 1000);
+}
+
+/// Reports through $6000 like blargg's tests. It asks to be reset, then passes printing "OK" once it
+/// has been.
+const REPORTING: [u8; 52] = [
+  0x78,             // SEI
+  0xAD, 0x00, 0x60, // LDA $6000
+  0xC9, 0x81,       // CMP #$81
+  0xF0, 0x17,       // BEQ done
+  0xA9, 0xDE,       // LDA #$DE
+  0x8D, 0x01, 0x60, // STA $6001
+  0xA9, 0xB0,       // LDA #$B0
+  0x8D, 0x02, 0x60, // STA $6002
+  0xA9, 0x61,       // LDA #$61
+  0x8D, 0x03, 0x60, // STA $6003
+  0xA9, 0x81,       // LDA #$81
+  0x8D, 0x00, 0x60, // STA $6000
+  0x4C, 0x1C, 0xC0, // JMP $C01C
+  // done:
+  0xA9, b'O',       // LDA #'O'
+  0x8D, 0x04, 0x60, // STA $6004
+  0xA9, b'K',       // LDA #'K'
+  0x8D, 0x05, 0x60, // STA $6005
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x06, 0x60, // STA $6006
+  0x8D, 0x00, 0x60, // STA $6000
+  0x4C, 0x31, 0xC0, // JMP $C031
+];
+
+/// NROM with battery backed PRG RAM for the results, running `code`
+fn reporting_rom(code: &[u8]) -> RomBuilder {
+  RomBuilder::new(0).battery().chr_banks(0).prg(code)
+}
+
+#[test]
+fn harness_presses_reset_when_asked_and_reads_the_result() {
+  let mut nes = reporting_rom(&REPORTING).nes();
+  nes.run_frames(1);
+  assert_eq!(test_rom::status(&nes), Some(TestStatus::ResetRequested));
+
+  let outcome = test_rom::run(&mut nes, 100);
+  assert!(outcome.passed());
+  assert_eq!(outcome.text, "OK");
+  assert!(outcome.frames < 100);
+}
+
+#[test]
+fn harness_gives_up_on_a_rom_that_never_reports() {
+  let mut code = REPORTING;
+  // Spin before writing anything
+  code[..3].copy_from_slice(&[0x4C, 0x00, 0xC0]);
+  let mut nes = reporting_rom(&code).nes();
+  let outcome = test_rom::run(&mut nes, 20);
+  assert_eq!(outcome.result, None);
+  assert_eq!(outcome.frames, 20);
+  assert_eq!(test_rom::status(&nes), None);
+}
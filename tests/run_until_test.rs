@@ -0,0 +1,90 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::BusLike;
+use silknes_web::command::{self, Command};
+use silknes_web::nes::{Nes, RunTarget};
+
+use common::rom_builder::RomBuilder;
+
+/// 16 KB NROM whose reset handler runs SEI and LDA #$00, then a page of NOPs before starting again
+fn nes() -> Nes {
+  let mut code = vec![0xEA; 0x100];
+  code[..3].copy_from_slice(&[
+    0x78,       // SEI
+    0xA9, 0x00, // LDA #$00
+  ]);
+  code[0xFD..].copy_from_slice(&[0x4C, 0x00, 0xC0]); // JMP $C000
+  RomBuilder::new(0).prg_banks(1).prg(&code).nes()
+}
+
+fn position(nes: &Nes) -> (i16, u16) {
+  nes.bus.ppu.position()
+}
+
+#[test]
+fn steps_one_instruction_at_a_time() {
+  let mut nes = nes();
+  // Past the reset sequence first, to the start of the first instruction
+  assert!(nes.run_until(RunTarget::Instruction));
+  assert_eq!(nes.cpu.pc, 0xC000);
+  assert!(nes.run_until(RunTarget::Instruction));
+  assert_eq!(nes.cpu.pc, 0xC001);
+  assert!(nes.run_until(RunTarget::Instruction));
+  assert_eq!(nes.cpu.pc, 0xC003);
+}
+
+#[test]
+fn runs_to_the_next_scanline_and_frame() {
+  let mut nes = nes();
+  assert!(nes.run_until(RunTarget::Scanline));
+  assert_eq!(position(&nes), (0, 0));
+  assert!(nes.run_until(RunTarget::Scanline));
+  assert_eq!(position(&nes), (1, 0));
+
+  assert!(nes.run_until(RunTarget::Frame));
+  assert_eq!(nes.frame_count(), 1);
+  assert!(nes.run_until(RunTarget::Frame));
+  assert_eq!(nes.frame_count(), 2);
+}
+
+#[test]
+fn runs_to_the_start_of_vblank() {
+  let mut nes = nes();
+  assert!(nes.run_until(RunTarget::VBlank));
+  assert_eq!(position(&nes), (241, 2));
+  assert_ne!(nes.bus.cpu_read(0x2002) & 0x80, 0);
+}
+
+#[test]
+fn runs_to_a_position_or_gives_up() {
+  let mut nes = nes();
+  assert!(nes.run_until(RunTarget::Position(100, 200)));
+  assert_eq!(position(&nes), (100, 200));
+
+  // There's no dot 341, so two frames go by without getting there
+  let frames = nes.frame_count();
+  assert!(!nes.run_until(RunTarget::Position(100, 341)));
+  assert_eq!(nes.frame_count(), frames + 2);
+}
+
+#[test]
+fn breakpoints_stop_it_early() {
+  let mut nes = nes();
+  nes.add_breakpoint(0xC010);
+  assert!(!nes.run_until(RunTarget::Frame));
+  assert_eq!(nes.breakpoint_hit(), Some(0xC010));
+  assert_eq!(nes.describe_stop(), Some("Hit breakpoint at $C010 (PRG bank 0 + $0010)".to_string()));
+}
+
+#[test]
+fn console_parses_run_targets() {
+  assert_eq!(command::parse("step"), Ok(Command::RunTo(RunTarget::Instruction)));
+  assert_eq!(command::parse("next vblank"), Ok(Command::RunTo(RunTarget::VBlank)));
+  assert_eq!(command::parse("n scanline"), Ok(Command::RunTo(RunTarget::Scanline)));
+  assert_eq!(command::parse("runto -1 340"), Ok(Command::RunTo(RunTarget::Position(-1, 340))));
+  assert!(command::parse("next").is_err());
+  assert!(command::parse("runto 312 0").is_err());
+  assert!(command::parse("runto 0 341").is_err());
+}
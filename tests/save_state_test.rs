@@ -0,0 +1,102 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::BusLike;
+use silknes_web::nes::Nes;
+
+use common::rom_builder::{textured_chr, RomBuilder};
+
+/// Reset handler: turn on NMIs and rendering, then loop forever bumping a counter,
+/// writing it into the sprite page and kicking off an OAM DMA from it
+const PROGRAM: [u8; 41] = [
+  0x78,             // SEI
+  // Wait out the PPU's warm up, which takes until the second vblank
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0xD8,             // CLD
+  0xA2, 0xFF,       // LDX #$FF
+  0x9A,             // TXS
+  0xA9, 0x80,       // LDA #$80
+  0x8D, 0x00, 0x20, // STA $2000
+  0xA9, 0x1E,       // LDA #$1E
+  0x8D, 0x01, 0x20, // STA $2001
+  // loop:
+  0xE6, 0x00,       // INC $00
+  0xA5, 0x00,       // LDA $00
+  0x9D, 0x00, 0x02, // STA $0200,X
+  0xE8,             // INX
+  0xA9, 0x02,       // LDA #$02
+  0x8D, 0x14, 0x40, // STA $4014
+  0x4C, 0x19, 0xC0, // JMP loop
+];
+
+/// NMI handler: bump a second counter and scroll the background by it
+const NMI_HANDLER: [u8; 8] = [
+  0xE6, 0x01,       // INC $01
+  0xA5, 0x01,       // LDA $01
+  0x8D, 0x05, 0x20, // STA $2005
+  0x40,             // RTI
+];
+
+/// Everything observable that should continue identically after loading a state
+fn fingerprint(nes: &Nes) -> Vec<u8> {
+  let mut fingerprint = vec![];
+  let cpu = &nes.cpu;
+  fingerprint.extend([cpu.a, cpu.x, cpu.y, cpu.sp, cpu.flags.to_u8(), cpu.cycles as u8]);
+  fingerprint.extend(cpu.pc.to_le_bytes());
+  fingerprint.extend(cpu.total_cycles.to_le_bytes());
+  let bus = &nes.bus;
+  fingerprint.extend(bus.get_global_cycles().to_le_bytes());
+  fingerprint.extend([bus.dma_page(), bus.dma_address(), bus.dma_data(), bus.dma_queued() as u8, bus.dma_running() as u8]);
+  fingerprint.extend((0x0000..0x0800).map(|address| bus.peek(address)));
+  fingerprint.extend(nes.bus.ppu.oam.iter().flat_map(|sprite| [sprite.y, sprite.id, sprite.attributes.to_u8(), sprite.x]));
+  fingerprint.extend(nes.get_screen());
+  fingerprint
+}
+
+#[test]
+fn save_state_continuation_is_identical() {
+  let mut nes = RomBuilder::new(0).prg(&PROGRAM).nmi(&NMI_HANDLER).chr(&textured_chr()).nes();
+
+  // Small LCG so the test picks the same "random" cycles every run
+  let mut seed: u32 = 0x1234_5678;
+  let mut next_random = |max: u32| {
+    seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+    (seed >> 8) % max
+  };
+
+  let mut saved_mid_dma = 0;
+  let mut saved_mid_instruction = 0;
+  for _ in 0..2000 {
+    for _ in 0..next_random(1000) {
+      nes.clock();
+    }
+
+    if nes.bus.dma_running() || nes.bus.dma_queued() {
+      saved_mid_dma += 1;
+    }
+    if nes.cpu.cycles > 0 {
+      saved_mid_instruction += 1;
+    }
+    let state = nes.save_state();
+
+    let continuation = next_random(500);
+    for _ in 0..continuation {
+      nes.clock();
+    }
+    let expected = fingerprint(&nes);
+
+    nes.load_state(&state);
+    for _ in 0..continuation {
+      nes.clock();
+    }
+    assert_eq!(fingerprint(&nes), expected);
+  }
+
+  // Make sure we actually exercised the awkward cases
+  assert!(saved_mid_dma > 0);
+  assert!(saved_mid_instruction > 0);
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "serde")]
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::nes::SaveState;
+
+use common::rom_builder::{textured_chr, RomBuilder};
+
+/// Reset handler: turn on NMIs and rendering, then loop forever bumping a counter
+const PROGRAM: [u8; 26] = [
+  0x78,             // SEI
+  // Wait out the PPU's warm up, which takes until the second vblank
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0xA9, 0x80,       // LDA #$80
+  0x8D, 0x00, 0x20, // STA $2000
+  0xA9, 0x1E,       // LDA #$1E
+  0x8D, 0x01, 0x20, // STA $2001
+  // loop:
+  0xE6, 0x00,       // INC $00
+  0x4C, 0x15, 0xC0, // JMP loop
+];
+
+/// NMI handler: bump a second counter and scroll the background by it
+const NMI_HANDLER: [u8; 8] = [
+  0xE6, 0x01,       // INC $01
+  0xA5, 0x01,       // LDA $01
+  0x8D, 0x05, 0x20, // STA $2005
+  0x40,             // RTI
+];
+
+#[test]
+fn serialized_state_loads_into_another_console() {
+  let rom = RomBuilder::new(0).prg(&PROGRAM).nmi(&NMI_HANDLER).chr(&textured_chr());
+  let mut original = rom.nes();
+  for _ in 0..10 {
+    original.run_frame();
+  }
+  original.run_cycles(1234);
+
+  let json = serde_json::to_string(&original.save_state()).unwrap();
+  let state: SaveState = serde_json::from_str(&json).unwrap();
+
+  // A fresh console with the same game continues exactly where the original left off
+  let mut restored = rom.nes();
+  restored.load_state(&state);
+  for _ in 0..5 {
+    original.run_frame();
+    restored.run_frame();
+  }
+
+  assert_eq!(restored.cpu.pc, original.cpu.pc);
+  assert_eq!(restored.cpu.total_cycles, original.cpu.total_cycles);
+  for address in 0x0000..0x0800 {
+    assert_eq!(restored.peek(address), original.peek(address));
+  }
+  assert_eq!(restored.get_screen(), original.get_screen());
+}
+
+#[test]
+fn state_bytes_round_trip() {
+  let rom = RomBuilder::new(0).prg(&PROGRAM).nmi(&NMI_HANDLER).chr(&textured_chr());
+  let mut original = rom.nes();
+  original.run_frames(3);
+
+  let bytes = original.save_state().to_bytes().unwrap();
+  let mut restored = rom.nes();
+  restored.load_state(&SaveState::from_bytes(&bytes).unwrap());
+  assert_eq!(restored.frame_count(), original.frame_count());
+  assert_eq!(restored.cpu.pc, original.cpu.pc);
+
+  assert!(SaveState::from_bytes(b"not a state").is_err());
+}
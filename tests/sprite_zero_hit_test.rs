@@ -0,0 +1,63 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::bus::BusLike;
+use silknes_web::nes::{Nes, RunTarget};
+
+use common::rom_builder::{RomBuilder, SOLID_TILE_CHR, SPIN};
+
+/// NROM that spins with NMIs off, with tile 0 blank and tile 1 solid
+fn nes() -> Nes {
+  let mut nes = RomBuilder::new(0).prg(&SPIN).chr(&SOLID_TILE_CHR).nes();
+  // Past the PPU's warm up, when it starts taking writes
+  nes.run_frames(2);
+  nes
+}
+
+/// Draw sprite 0 at `x` over a background of `tile`, and whether it hit after a frame of `mask`
+fn hits(x: u8, tile: u8, mask: u8) -> bool {
+  let mut nes = nes();
+  nes.run_until(RunTarget::VBlank);
+  let bus = &mut nes.bus;
+  bus.cpu_write(0x2006, 0x20);
+  bus.cpu_write(0x2006, 0x00);
+  for _ in 0..0x3C0 {
+    bus.cpu_write(0x2007, tile);
+  }
+  for (address, byte) in [100, 1, 0, x].into_iter().enumerate() {
+    bus.cpu_write(0x2003, address as u8);
+    bus.cpu_write(0x2004, byte);
+  }
+  bus.cpu_write(0x2005, 0);
+  bus.cpu_write(0x2005, 0);
+  bus.cpu_write(0x2001, mask);
+  nes.run_until(RunTarget::VBlank);
+  nes.bus.cpu_read(0x2002) & 0x40 != 0
+}
+
+#[test]
+fn hits_where_both_layers_are_opaque() {
+  assert!(hits(100, 1, 0x18));
+  assert!(!hits(100, 0, 0x18));
+  // Both layers have to be switched on
+  assert!(!hits(100, 1, 0x10));
+  assert!(!hits(100, 1, 0x08));
+}
+
+#[test]
+fn left_column_masks_each_stop_hits_there() {
+  assert!(hits(0, 1, 0x1E));
+  assert!(!hits(0, 1, 0x1C));
+  assert!(!hits(0, 1, 0x1A));
+  assert!(!hits(0, 1, 0x18));
+  // Partly out of the left column, it hits past it
+  assert!(hits(4, 1, 0x18));
+}
+
+#[test]
+fn never_hits_at_x_255() {
+  assert!(!hits(255, 1, 0x1E));
+  assert!(hits(254, 1, 0x1E));
+}
+
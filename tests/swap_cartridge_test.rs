@@ -0,0 +1,71 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::nes::Nes;
+use silknes_web::ppu::Region;
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: write a marker to PRG RAM, then spin
+const PROGRAM: [u8; 9] = [
+  0x78,             // SEI
+  0xA9, 0x5A,       // LDA #$5A
+  0x8D, 0x00, 0x60, // STA $6000
+  // loop:
+  0x4C, 0x06, 0xC0, // JMP loop
+];
+
+/// Where the tag after the program is
+const TAG: u16 = 0xC000 + PROGRAM.len() as u16;
+
+/// NROM with battery backed PRG RAM, running the program above. `tag` goes after the program, to tell
+/// cartridges apart.
+fn rom(tag: u8) -> RomBuilder {
+  RomBuilder::new(0).battery().prg(&[&PROGRAM[..], &[tag]].concat())
+}
+
+fn running_nes() -> Nes {
+  let mut nes = rom(1).nes();
+  nes.run_frame();
+  nes.run_frame();
+  assert_eq!(nes.peek(0x6000), 0x5A);
+  nes
+}
+
+#[test]
+fn swapping_keeps_the_console_running() {
+  let mut nes = running_nes();
+  nes.poke(0x0010, 0x42);
+  let frames = nes.frame_count();
+  let cycles = nes.cpu.total_cycles;
+
+  nes.swap_cartridge(rom(2).cartridge(), false);
+  assert_eq!(nes.peek(TAG), 2);
+  // Nothing was reset
+  assert_eq!(nes.peek(0x0010), 0x42);
+  assert_eq!(nes.frame_count(), frames);
+  assert_eq!(nes.cpu.total_cycles, cycles);
+
+  nes.run_frame();
+  assert_eq!(nes.frame_count(), frames + 1);
+}
+
+#[test]
+fn prg_ram_is_kept_only_when_asked() {
+  let mut kept = running_nes();
+  kept.swap_cartridge(rom(2).cartridge(), true);
+  assert_eq!(kept.peek(0x6000), 0x5A);
+
+  let mut blank = running_nes();
+  blank.swap_cartridge(rom(2).cartridge(), false);
+  assert_eq!(blank.peek(0x6000), 0x00);
+}
+
+#[test]
+fn region_stays_with_the_console() {
+  let mut nes = running_nes();
+  assert_eq!(nes.bus.ppu.region(), Region::Ntsc);
+  nes.swap_cartridge(rom(2).pal().cartridge(), false);
+  assert_eq!(nes.bus.ppu.region(), Region::Ntsc);
+}
@@ -0,0 +1,75 @@
+extern crate silknes_web;
+
+use std::collections::HashSet;
+
+use silknes_web::cartridge::Cartridge;
+use silknes_web::nes::Nes;
+use silknes_web::test_pattern;
+
+const A: u8 = 0x80;
+const B: u8 = 0x40;
+const START: u8 = 0x10;
+const RIGHT: u8 = 0x01;
+
+fn nes() -> Nes {
+  let mut nes = Nes::new();
+  nes.insert_cartridge(Cartridge::from_bytes(test_pattern::rom()).unwrap());
+  nes.run_frames(5);
+  nes
+}
+
+/// Hold `buttons` for a frame, then let go for another
+fn press(nes: &mut Nes, buttons: u8) {
+  nes.update_controller(0, buttons);
+  nes.run_frame();
+  nes.update_controller(0, 0);
+  nes.run_frames(2);
+}
+
+fn colors(nes: &Nes) -> HashSet<[u8; 3]> {
+  nes.screen().chunks(3).map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect()
+}
+
+#[test]
+fn bars_show_twelve_hues_on_grey() {
+  let nes = nes();
+  assert_eq!(colors(&nes).len(), 13);
+}
+
+#[test]
+fn greyscale_leaves_only_grey() {
+  let mut nes = nes();
+  press(&mut nes, B);
+  assert_eq!(colors(&nes).len(), 1);
+  press(&mut nes, B);
+  assert_eq!(colors(&nes).len(), 13);
+}
+
+#[test]
+fn emphasis_steps_through_every_combination_and_back() {
+  let mut nes = nes();
+  let plain = colors(&nes);
+  let mut seen = vec![plain.clone()];
+  for _ in 0..7 {
+    press(&mut nes, A);
+    let emphasised = colors(&nes);
+    assert!(!seen.contains(&emphasised));
+    seen.push(emphasised);
+  }
+  press(&mut nes, A);
+  assert_eq!(colors(&nes), plain);
+}
+
+#[test]
+fn right_moves_to_the_next_brightness_row_and_start_resets() {
+  let mut nes = nes();
+  let first_row = colors(&nes);
+  press(&mut nes, RIGHT);
+  let second_row = colors(&nes);
+  assert_eq!(second_row.len(), 13);
+  assert!(first_row.is_disjoint(&second_row));
+
+  press(&mut nes, A);
+  press(&mut nes, START);
+  assert_eq!(colors(&nes), first_row);
+}
@@ -0,0 +1,81 @@
+extern crate silknes_web;
+
+use eframe::egui;
+use silknes_web::toast::{ToastKind, Toasts};
+
+/// Run one UI frame at `time` seconds, drawing the toasts
+fn show_at(ctx: &egui::Context, toasts: &mut Toasts, time: f64) {
+  let input = egui::RawInput { time: Some(time), ..Default::default() };
+  let _ = ctx.run(input, |ctx| toasts.show(ctx));
+}
+
+#[test]
+fn toasts_expire_a_few_seconds_after_they_first_show() {
+  let ctx = egui::Context::default();
+  let mut toasts = Toasts::default();
+  toasts.info("Saved state to slot 0");
+
+  // Time only starts counting once it's actually drawn
+  show_at(&ctx, &mut toasts, 100.0);
+  show_at(&ctx, &mut toasts, 102.0);
+  assert_eq!(toasts.messages().collect::<Vec<_>>(), vec![("Saved state to slot 0", ToastKind::Info)]);
+
+  toasts.error("Slot 1 is empty");
+  show_at(&ctx, &mut toasts, 103.5);
+  assert_eq!(toasts.messages().collect::<Vec<_>>(), vec![("Slot 1 is empty", ToastKind::Error)]);
+
+  show_at(&ctx, &mut toasts, 107.0);
+  assert_eq!(toasts.messages().count(), 0);
+}
+
+#[test]
+fn oldest_toasts_make_room_for_new_ones() {
+  let mut toasts = Toasts::default();
+  for i in 0..8 {
+    toasts.info(format!("Toast {}", i));
+  }
+  let messages: Vec<_> = toasts.messages().map(|(text, _)| text.to_string()).collect();
+  assert_eq!(messages.first().map(String::as_str), Some("Toast 3"));
+  assert_eq!(messages.last().map(String::as_str), Some("Toast 7"));
+}
+
+#[test]
+fn toasts_fade_out_over_their_last_half_second() {
+  let ctx = egui::Context::default();
+  let mut toasts = Toasts::default();
+  toasts.info("Saved state to slot 3");
+  assert_eq!(toasts.opacities(0.0).collect::<Vec<_>>(), vec![1.0]);
+
+  show_at(&ctx, &mut toasts, 10.0);
+  assert_eq!(toasts.opacities(12.0).collect::<Vec<_>>(), vec![1.0]);
+  assert_eq!(toasts.opacities(12.75).collect::<Vec<_>>(), vec![0.5]);
+  assert_eq!(toasts.opacities(13.0).collect::<Vec<_>>(), vec![0.0]);
+}
+
+#[test]
+fn held_toasts_stay_up_while_held_without_stacking() {
+  let ctx = egui::Context::default();
+  let mut toasts = Toasts::default();
+  for frame in 0..600 {
+    toasts.hold("rewind", "Rewinding…");
+    show_at(&ctx, &mut toasts, frame as f64 / 60.0);
+  }
+  assert_eq!(toasts.messages().collect::<Vec<_>>(), vec![("Rewinding…", ToastKind::Info)]);
+
+  // Let go and it fades straight away rather than hanging around for the usual few seconds
+  show_at(&ctx, &mut toasts, 10.25);
+  assert_eq!(toasts.messages().count(), 1);
+  show_at(&ctx, &mut toasts, 10.6);
+  assert_eq!(toasts.messages().count(), 0);
+}
+
+#[test]
+fn toasts_sit_over_the_game_once_its_been_placed() {
+  let ctx = egui::Context::default();
+  let mut toasts = Toasts::default();
+  toasts.info("ROM loaded");
+  toasts.place_over(egui::Rect::from_min_size(egui::pos2(100.0, 50.0), egui::vec2(512.0, 480.0)));
+  show_at(&ctx, &mut toasts, 0.0);
+  let area = ctx.memory(|memory| memory.area_rect(egui::Id::new("toasts"))).unwrap();
+  assert_eq!(area.right_bottom(), egui::pos2(604.0, 522.0));
+}
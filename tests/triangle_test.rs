@@ -0,0 +1,96 @@
+extern crate silknes_web;
+
+use silknes_web::apu::{ApuChannel, AudioChannel, APU};
+
+/// An APU playing the triangle with `period`, its linear counter loaded from `linear` (control flag
+/// included) and a long length
+fn triangle(linear: u8, period: u16) -> APU {
+  let mut apu = APU::new();
+  apu.cpu_write(0x4015, 0x04);
+  apu.cpu_write(0x4008, linear);
+  apu.cpu_write(0x400A, period as u8);
+  apu.cpu_write(0x400B, 0x08 | (period >> 8) as u8);
+  apu
+}
+
+fn linear_counter(apu: &APU) -> u8 {
+  apu.channel_state(ApuChannel::Triangle).linear_counter.unwrap()
+}
+
+/// The triangle's level going into the mixer, from 0 to 15
+fn level(apu: &mut APU) -> u8 {
+  apu.update_output(0.0);
+  (apu.channel_levels()[AudioChannel::Triangle as usize] * 15.0).round() as u8
+}
+
+#[test]
+fn linear_counter_reloads_once_then_counts_down() {
+  let mut apu = triangle(0x05, 0x100);
+  // Writing $400B only sets the reload flag
+  assert_eq!(linear_counter(&apu), 0);
+  apu.tick_quarter_frame();
+  assert_eq!(linear_counter(&apu), 5);
+  apu.tick_quarter_frame();
+  apu.tick_quarter_frame();
+  assert_eq!(linear_counter(&apu), 3);
+  for _ in 0..10 {
+    apu.tick_quarter_frame();
+  }
+  assert_eq!(linear_counter(&apu), 0);
+}
+
+#[test]
+fn control_flag_keeps_reloading_the_linear_counter() {
+  let mut apu = triangle(0x85, 0x100);
+  for _ in 0..10 {
+    apu.tick_quarter_frame();
+    assert_eq!(linear_counter(&apu), 5);
+  }
+
+  // Clearing the control flag only lets it count down after the next reload clears the flag
+  apu.cpu_write(0x4008, 0x05);
+  apu.tick_quarter_frame();
+  assert_eq!(linear_counter(&apu), 5);
+  apu.tick_quarter_frame();
+  assert_eq!(linear_counter(&apu), 4);
+}
+
+#[test]
+fn writing_the_linear_counter_register_doesnt_reload_it() {
+  let mut apu = triangle(0x05, 0x100);
+  apu.tick_quarter_frame();
+  apu.tick_quarter_frame();
+  apu.cpu_write(0x4008, 0x20);
+  apu.tick_quarter_frame();
+  assert_eq!(linear_counter(&apu), 3);
+}
+
+#[test]
+fn sequencer_steps_every_period_plus_one_cpu_cycles() {
+  let mut apu = triangle(0x85, 3);
+  apu.tick_quarter_frame();
+  let mut levels = vec![];
+  for cycle in 0..12 {
+    apu.step(cycle, None);
+    levels.push(level(&mut apu));
+  }
+  assert_eq!(levels, [14, 14, 14, 14, 13, 13, 13, 13, 12, 12, 12, 12]);
+}
+
+#[test]
+fn ultrasonic_periods_hold_the_triangle_still_unless_asked_not_to() {
+  let mut apu = triangle(0x85, 0);
+  apu.tick_quarter_frame();
+  for cycle in 0..20 {
+    apu.step(cycle, None);
+    assert_eq!(level(&mut apu), 15);
+  }
+
+  apu.ultrasonic_triangle = true;
+  let mut levels = vec![];
+  for cycle in 0..4 {
+    apu.step(cycle, None);
+    levels.push(level(&mut apu));
+  }
+  assert_eq!(levels, [14, 13, 12, 11]);
+}
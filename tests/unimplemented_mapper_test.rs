@@ -0,0 +1,44 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::cartridge::Cartridge;
+
+use common::rom_builder::RomBuilder;
+
+/// Load an MMC1 register a bit at a time
+fn write_mmc1(cartridge: &mut Cartridge, address: u16, value: u8) {
+  for bit in 0..5 {
+    cartridge.cpu_write(address, (value >> bit) & 1);
+  }
+}
+
+#[test]
+fn supported_writes_are_not_flagged() {
+  let mut cartridge = RomBuilder::new(4).prg_banks(2).battery().cartridge();
+  cartridge.cpu_write(0xA001, 0xC0);
+  cartridge.cpu_write(0x8000, 0x06);
+  cartridge.cpu_write(0x8001, 0x01);
+  assert!(cartridge.unimplemented.is_empty());
+}
+
+#[test]
+fn ignored_features_are_recorded_once() {
+  let mut cartridge = RomBuilder::new(1).prg_banks(32).battery().cartridge();
+  write_mmc1(&mut cartridge, 0xA000, 0x10);
+  write_mmc1(&mut cartridge, 0xC000, 0x10);
+  assert_eq!(cartridge.unimplemented, ["MMC1 512 KB PRG banking (SUROM/SXROM)"]);
+}
+
+#[test]
+fn mmc1_features_are_checked_on_the_write_that_completes_a_register() {
+  // CHR registers only switch PRG on boards with more than 256 KB of it
+  let mut cartridge = RomBuilder::new(1).prg_banks(8).battery().cartridge();
+  write_mmc1(&mut cartridge, 0xA000, 0x10);
+  assert!(cartridge.unimplemented.is_empty());
+  let mut surom = RomBuilder::new(1).prg_banks(32).battery().cartridge();
+  surom.cpu_write(0xA000, 0x01);
+  assert!(surom.unimplemented.is_empty());
+  write_mmc1(&mut surom, 0xA000, 0x10);
+  assert_eq!(surom.unimplemented, ["MMC1 512 KB PRG banking (SUROM/SXROM)"]);
+}
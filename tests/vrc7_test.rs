@@ -0,0 +1,120 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::cartridge::MirroringMode;
+use silknes_web::mapper::Mapper;
+use silknes_web::mappers::mapper85::Mapper85;
+
+use common::rom_builder::RomBuilder;
+
+#[test]
+fn prg_banks_switch_in_8kb_pieces() {
+  let mut cartridge = RomBuilder::new(85).prg_banks(8).chr_banks(16).cartridge();
+  cartridge.cpu_write(0x8000, 3);
+  cartridge.cpu_write(0x8010, 5);
+  cartridge.cpu_write(0x9000, 7);
+  assert_eq!(cartridge.cpu_read(0x8000), Some(3));
+  assert_eq!(cartridge.cpu_read(0xA000), Some(5));
+  assert_eq!(cartridge.cpu_read(0xC000), Some(7));
+  assert_eq!(cartridge.cpu_read(0xE000), Some(15));
+
+  // Some boards use A3 rather than A4 for the second register
+  cartridge.cpu_write(0x8008, 9);
+  assert_eq!(cartridge.cpu_read(0xA123), Some(9));
+}
+
+#[test]
+fn chr_banks_switch_in_1kb_pieces() {
+  let mut mapper = Mapper85::new(8, 16);
+  for (i, register) in [0xA000, 0xA010, 0xB000, 0xB010, 0xC000, 0xC010, 0xD000, 0xD008].into_iter().enumerate() {
+    mapper.mapped_cpu_write(register, 100 + i as u8);
+  }
+  for i in 0..8 {
+    assert_eq!(mapper.get_mapped_address_ppu(i * 0x400 + 0x12), (100 + i as u32) * 0x400 + 0x12);
+  }
+}
+
+#[test]
+fn mirroring_is_set_by_the_control_register() {
+  let mut mapper = Mapper85::new(8, 16);
+  for (value, mode) in [(0, MirroringMode::Vertical), (1, MirroringMode::Horizontal), (2, MirroringMode::SingleScreenLow), (3, MirroringMode::SingleScreenHigh)] {
+    mapper.mapped_cpu_write(0xE000, value);
+    assert_eq!(mapper.mirroring_mode(), mode);
+  }
+}
+
+#[test]
+fn irq_fires_when_the_counter_overflows() {
+  let mut mapper = Mapper85::new(8, 16);
+  mapper.mapped_cpu_write(0xE010, 0xFD);
+  // Enabled, in CPU cycle mode
+  mapper.mapped_cpu_write(0xF000, 0b110);
+  mapper.cpu_clock();
+  mapper.cpu_clock();
+  assert!(!mapper.irq_state());
+  mapper.cpu_clock();
+  assert!(mapper.irq_state());
+
+  mapper.mapped_cpu_write(0xF010, 0);
+  assert!(!mapper.irq_state());
+}
+
+#[test]
+fn irq_counts_scanlines_from_the_cpu_clock() {
+  let mut mapper = Mapper85::new(8, 16);
+  mapper.mapped_cpu_write(0xE010, 0xFF);
+  mapper.mapped_cpu_write(0xF000, 0b010);
+  // A scanline is 113 2/3 CPU cycles
+  for _ in 0..113 {
+    mapper.cpu_clock();
+  }
+  assert!(!mapper.irq_state());
+  mapper.cpu_clock();
+  assert!(mapper.irq_state());
+}
+
+/// Output over `cycles` CPU cycles, one sample per 36 cycles
+fn run_audio(mapper: &mut Mapper85, cycles: usize) -> Vec<f32> {
+  (0..cycles).map(|_| {
+    mapper.audio_clock(1);
+    mapper.audio_output()
+  }).step_by(36).collect()
+}
+
+fn write_audio(mapper: &mut Mapper85, register: u8, value: u8) {
+  mapper.mapped_cpu_write(0x9010, register);
+  mapper.mapped_cpu_write(0x9030, value);
+}
+
+#[test]
+fn fm_channel_plays_after_key_on() {
+  let mut mapper = Mapper85::new(8, 16);
+  assert!(run_audio(&mut mapper, 36 * 100).iter().all(|sample| *sample == 0.0));
+
+  // Flute at full volume, key on A4 in octave 4
+  write_audio(&mut mapper, 0x30, 0x40);
+  write_audio(&mut mapper, 0x10, 0x20);
+  write_audio(&mut mapper, 0x20, 0x19);
+  let samples = run_audio(&mut mapper, 36 * 2000);
+  let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+  assert!(peak > 0.01, "peak was {}", peak);
+  assert!(peak <= 0.1);
+
+  // Holding the chip in reset silences it
+  mapper.mapped_cpu_write(0xE000, 0x40);
+  assert!(run_audio(&mut mapper, 36 * 100).iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn key_off_fades_out() {
+  let mut mapper = Mapper85::new(8, 16);
+  write_audio(&mut mapper, 0x30, 0x40);
+  write_audio(&mut mapper, 0x10, 0x20);
+  write_audio(&mut mapper, 0x20, 0x19);
+  run_audio(&mut mapper, 36 * 2000);
+  write_audio(&mut mapper, 0x20, 0x09);
+  // Two seconds is plenty for the flute's release
+  let samples = run_audio(&mut mapper, 36 * 100_000);
+  assert!(samples[samples.len() - 100..].iter().all(|sample| *sample == 0.0));
+}
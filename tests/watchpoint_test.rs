@@ -0,0 +1,146 @@
+extern crate silknes_web;
+
+mod common;
+
+use silknes_web::command::{self, Command};
+use silknes_web::nes::Nes;
+use silknes_web::watchpoint::{Access, AddressSpace, WatchedAccess, Watchpoint};
+
+use common::rom_builder::RomBuilder;
+
+/// Reset handler: wait out the PPU's warm up, write $24 to the first nametable, then store it in RAM,
+/// load it back and spin
+const PROGRAM: [u8; 35] = [
+  0x78,             // SEI
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0x2C, 0x02, 0x20, // BIT $2002
+  0x10, 0xFB,       // BPL -5
+  0xA9, 0x20,       // LDA #$20
+  0x8D, 0x06, 0x20, // STA $2006
+  0xA9, 0x00,       // LDA #$00
+  0x8D, 0x06, 0x20, // STA $2006
+  0xA9, 0x24,       // LDA #$24
+  0x8D, 0x07, 0x20, // STA $2007 ($C017)
+  0x8D, 0x00, 0x03, // STA $0300 ($C01A)
+  0xAD, 0x00, 0x03, // LDA $0300 ($C01D)
+  // loop:
+  0x4C, 0x20, 0xC0, // JMP loop ($C020)
+];
+
+fn nes() -> Nes {
+  RomBuilder::new(0).prg(&PROGRAM).nes()
+}
+
+fn watchpoint(space: AddressSpace, start: u16, end: u16, kinds: &str) -> Watchpoint {
+  Watchpoint {
+    space,
+    start,
+    end,
+    read: kinds.contains('r'),
+    write: kinds.contains('w'),
+    execute: kinds.contains('x'),
+  }
+}
+
+#[test]
+fn cpu_write_stops_at_the_instruction_that_made_it() {
+  let mut nes = nes();
+  nes.add_watchpoint(watchpoint(AddressSpace::Cpu, 0x0300, 0x03FF, "w"));
+  nes.run_frames(10);
+
+  let hit = nes.watch_hit().expect("the watchpoint should have gone off");
+  assert_eq!(hit.access, WatchedAccess { space: AddressSpace::Cpu, access: Access::Write, address: 0x0300, value: 0x24 });
+  assert_eq!(hit.pc, Some(0xC01A));
+  assert_eq!(nes.breakpoint_hit(), Some(0xC01A));
+  assert!(nes.describe_watch_hit(&hit).starts_with("Watchpoint: CPU write $0300 = $24 by $C01A"));
+}
+
+#[test]
+fn reads_and_executes_are_told_apart() {
+  let mut nes = nes();
+  nes.add_watchpoint(watchpoint(AddressSpace::Cpu, 0x0300, 0x0300, "r"));
+  nes.add_watchpoint(watchpoint(AddressSpace::Cpu, 0xC020, 0xC020, "x"));
+  nes.run_frames(10);
+  let hit = nes.watch_hit().unwrap();
+  assert_eq!((hit.access.access, hit.access.value, hit.pc), (Access::Read, 0x24, Some(0xC01D)));
+
+  nes.resume();
+  nes.run_frames(1);
+  let hit = nes.watch_hit().unwrap();
+  assert_eq!((hit.access.access, hit.access.address, hit.access.value), (Access::Execute, 0xC020, 0x4C));
+  assert_eq!(hit.pc, Some(0xC020));
+}
+
+#[test]
+fn ppu_bus_accesses_are_caught_too() {
+  let mut nes = nes();
+  nes.add_watchpoint(watchpoint(AddressSpace::Ppu, 0x2000, 0x23FF, "w"));
+  nes.run_frames(10);
+  let hit = nes.watch_hit().unwrap();
+  assert_eq!(hit.access, WatchedAccess { space: AddressSpace::Ppu, access: Access::Write, address: 0x2000, value: 0x24 });
+  assert_eq!(hit.pc, Some(0xC017));
+}
+
+#[test]
+fn rendering_reads_are_not_blamed_on_an_instruction() {
+  let mut nes = nes();
+  // The backdrop color's read for every pixel, even with rendering off
+  nes.add_watchpoint(watchpoint(AddressSpace::Ppu, 0x3F00, 0x3F00, "r"));
+  nes.run_frames(1);
+  let hit = nes.watch_hit().unwrap();
+  assert_eq!(hit.pc, None);
+  assert!(nes.describe_watch_hit(&hit).contains("by rendering"));
+}
+
+#[test]
+fn untouched_ranges_never_stop_and_clearing_lets_emulation_go() {
+  let mut nes = nes();
+  nes.add_watchpoint(watchpoint(AddressSpace::Cpu, 0x0400, 0x04FF, "rw"));
+  nes.run_frames(10);
+  assert_eq!(nes.watch_hit(), None);
+  assert_eq!(nes.breakpoint_hit(), None);
+
+  nes.add_watchpoint(watchpoint(AddressSpace::Cpu, 0xC020, 0xC020, "x"));
+  nes.run_frames(1);
+  assert!(nes.watch_hit().is_some());
+  nes.clear_watchpoints();
+  assert!(nes.watchpoints().is_empty());
+  assert_eq!((nes.watch_hit(), nes.breakpoint_hit()), (None, None));
+  let frames = nes.frame_count();
+  nes.run_frames(2);
+  assert_eq!(nes.frame_count(), frames + 2);
+}
+
+#[test]
+fn watchpoints_survive_loading_a_state() {
+  let mut nes = nes();
+  let state = nes.save_state();
+  nes.add_watchpoint(watchpoint(AddressSpace::Cpu, 0x0300, 0x0300, "w"));
+  nes.load_state(&state);
+  nes.run_frames(10);
+  assert_eq!(nes.watch_hit().map(|hit| hit.pc), Some(Some(0xC01A)));
+}
+
+#[test]
+fn console_parses_watchpoints() {
+  assert_eq!(
+    command::parse("wp rw $0300-$03FF"),
+    Ok(Command::AddWatchpoint(watchpoint(AddressSpace::Cpu, 0x0300, 0x03FF, "rw"))),
+  );
+  assert_eq!(
+    command::parse("watchpoint w 2000 PPU"),
+    Ok(Command::AddWatchpoint(watchpoint(AddressSpace::Ppu, 0x2000, 0x2000, "w"))),
+  );
+  assert_eq!(command::parse("clearwp"), Ok(Command::ClearWatchpoints));
+  assert!(command::parse("wp $0300").is_err());
+  assert!(command::parse("wp w $0400-$0300").is_err());
+  assert!(command::parse("wp x $2000 ppu").is_err());
+  assert!(command::parse("wp r $4000 ppu").is_err());
+}
+
+#[test]
+fn watchpoints_display_as_typed() {
+  assert_eq!(watchpoint(AddressSpace::Cpu, 0x0300, 0x03FF, "rw").to_string(), "rw $0300-$03FF");
+  assert_eq!(watchpoint(AddressSpace::Ppu, 0x2000, 0x2000, "w").to_string(), "w $2000 ppu");
+}